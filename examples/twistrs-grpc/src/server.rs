@@ -31,7 +31,7 @@ impl DomainEnumeration for DomainEnumerationService {
             // Spawn DNS Resolution check
             tokio::spawn(async move {
                 if let Ok(metadata) = domain_metadata.dns_resolvable().await {
-                    if let Some(ips) = metadata.ips {
+                    if let Some(ips) = metadata.dns.ips() {
                         if tx
                             .send(Ok(DomainEnumerationResponse {
                                 fqdn: permutation.domain.fqdn.to_string(),
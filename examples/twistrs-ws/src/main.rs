@@ -101,7 +101,7 @@ async fn user_message(my_id: usize, msg: Message, users: &Users) {
 
                 tokio::spawn(async move {
                     if let Ok(metadata) = domain_metadata.dns_resolvable().await {
-                        if let Some(ips) = metadata.ips {
+                        if let Some(ips) = metadata.dns.ips() {
                             if tx.send(Ok(Message::text(format!("{:?}", ips)))).is_err() {
                                 println!("received dropped");
                                 return;
@@ -0,0 +1,614 @@
+//! Official command-line interface to twistrs.
+//!
+//! Output is written to stdout in the selected format, whereas errors
+//! and summaries are written to stderr, so that the output can be piped
+//! straight into other tools.
+use std::collections::HashMap;
+use std::io::{self, BufWriter, Write};
+use std::net::IpAddr;
+use std::path::PathBuf;
+use std::process::ExitCode;
+
+use anyhow::{bail, Context, Result};
+use clap::{Parser, ValueEnum};
+use futures::stream::StreamExt;
+use serde::Serialize;
+
+use twistrs::config::ScanConfig;
+use twistrs::enrich::DomainMetadata;
+use twistrs::permutate::{Domain, ParseOptions, Permutation, PermutationKind};
+use twistrs::pipeline::ScanResult;
+use twistrs::summary::ScanSummary;
+
+const EXIT_CODES: &str = "\
+Exit codes:
+  0  Completed, no resolving permutation found (always the case in permutate mode)
+  1  Completed, at least one resolving permutation found
+  2  Invalid arguments or runtime error";
+
+/// Exit code reported when resolving permutations are found.
+const EXIT_FOUND: u8 = 1;
+
+/// Exit code reported on errors, matching the one used by clap.
+const EXIT_ERROR: u8 = 2;
+
+#[derive(Debug, Parser)]
+#[command(name = "twistrs", version, about, after_help = EXIT_CODES)]
+struct Args {
+    /// Domains to generate permutations of (e.g. example.com), in addition
+    /// to the ones listed in the config file.
+    domains: Vec<String>,
+
+    /// TOML or JSON scan config, command-line flags take precedence.
+    #[arg(long, value_name = "FILE")]
+    config: Option<PathBuf>,
+
+    /// Whether to only generate permutations or to also enrich them.
+    #[arg(short, long, value_enum, default_value_t = Mode::Enrich)]
+    mode: Mode,
+
+    /// Only generate permutations of the given kind, may be repeated.
+    #[arg(short, long = "kind", value_name = "KIND", value_parser = parse_kind)]
+    kinds: Vec<PermutationKind>,
+
+    /// Only generate the N most probable typos, weighted by the typo
+    /// model of the config file, and output their likelihood.
+    #[arg(long, value_name = "N", conflicts_with = "kinds")]
+    typos: Option<usize>,
+
+    /// Only output permutations that resolve (enrich mode only).
+    #[arg(short, long)]
+    resolving_only: bool,
+
+    /// Treat private suffixes (e.g. github.io) as TLDs, so that
+    /// example.github.io is permuted as example under github.io.
+    #[arg(long)]
+    private_suffixes: bool,
+
+    /// Maximum number of permutations enriched concurrently.
+    #[arg(short, long)]
+    concurrency: Option<usize>,
+
+    /// Comma-separated enrichments run on resolving permutations (e.g.
+    /// dns,mail_capable), or all. Only the DNS and HTTP banner lookups
+    /// are run by default.
+    #[arg(short, long = "enrich", value_name = "KINDS", value_delimiter = ',')]
+    enrichments: Vec<String>,
+
+    /// Output format.
+    #[arg(short, long, value_enum, default_value_t = Format::Text)]
+    format: Format,
+
+    /// Write a JSON summary of each scan to stderr once it completes.
+    #[arg(long)]
+    summary: bool,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, ValueEnum)]
+enum Mode {
+    /// Generate permutations only, without any network activity.
+    Permutate,
+
+    /// Generate permutations and enrich them, riskiest first.
+    Enrich,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, ValueEnum)]
+enum Format {
+    /// Human-readable, aligned columns.
+    Text,
+
+    /// A single JSON array, written once all results are in.
+    Json,
+
+    /// One JSON object per line, written as results come in.
+    Jsonl,
+
+    /// Comma-separated values with a header row.
+    Csv,
+}
+
+/// Parse a kind by name, listing the known ones if it is unknown.
+fn parse_kind(name: &str) -> Result<PermutationKind, String> {
+    name.parse().map_err(|_| {
+        let names: Vec<String> = PermutationKind::ALL
+            .iter()
+            .map(|kind| format!("{kind:?}"))
+            .collect();
+        format!("expected one of {}", names.join(", "))
+    })
+}
+
+/// Single line of output, shared by every format.
+#[derive(Debug, Clone, Serialize)]
+struct Record {
+    fqdn: String,
+    kind: PermutationKind,
+
+    /// `None` in permutate mode, since nothing has been resolved.
+    ips: Option<Vec<IpAddr>>,
+    http_banner: Option<String>,
+
+    /// Every field enriched, only set (and output by the JSON formats)
+    /// when enrichments are selected.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    metadata: Option<DomainMetadata>,
+
+    /// Fingerprint of the engine configuration the permutation was
+    /// generated with, output by the JSON formats.
+    fingerprint: Option<String>,
+
+    /// Likelihood of the typo, only set when generating typos.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    likelihood: Option<f64>,
+}
+
+impl From<Permutation> for Record {
+    fn from(permutation: Permutation) -> Record {
+        Record {
+            fqdn: permutation.domain.fqdn,
+            kind: permutation.kind,
+            ips: None,
+            http_banner: None,
+            metadata: None,
+            fingerprint: None,
+            likelihood: None,
+        }
+    }
+}
+
+impl From<ScanResult> for Record {
+    fn from(result: ScanResult) -> Record {
+        Record {
+            ips: Some(result.metadata.dns.ips().unwrap_or_default()),
+            http_banner: result.metadata.http_banner,
+            fingerprint: result.fingerprint,
+            ..Record::from(result.permutation)
+        }
+    }
+}
+
+impl Record {
+    fn ips(&self) -> String {
+        self.ips
+            .iter()
+            .flatten()
+            .map(ToString::to_string)
+            .collect::<Vec<_>>()
+            .join(" ")
+    }
+
+    fn likelihood(&self) -> String {
+        self.likelihood
+            .map(|likelihood| format!("{likelihood:.6}"))
+            .unwrap_or_default()
+    }
+}
+
+/// Writes records in the selected format. The text and CSV formats only
+/// have a likelihood column when generating typos.
+enum Output<W: Write> {
+    Text(W),
+    Json(W, Vec<Record>),
+    Jsonl(W),
+    Csv(Box<csv::Writer<W>>, bool),
+}
+
+impl<W: Write> Output<W> {
+    fn new(format: Format, writer: W, likelihoods: bool) -> Result<Self> {
+        Ok(match format {
+            Format::Text => Output::Text(writer),
+            Format::Json => Output::Json(writer, vec![]),
+            Format::Jsonl => Output::Jsonl(writer),
+            Format::Csv => {
+                let mut csv = csv::Writer::from_writer(writer);
+                let header = ["fqdn", "kind", "ips", "http_banner", "likelihood"];
+                csv.write_record(&header[..header.len() - usize::from(!likelihoods)])?;
+                Output::Csv(Box::new(csv), likelihoods)
+            }
+        })
+    }
+
+    fn write(&mut self, record: Record) -> Result<()> {
+        match self {
+            Output::Text(writer) => {
+                let kind = format!("{:?}", record.kind);
+                let fqdn = &record.fqdn;
+
+                match record.likelihood {
+                    Some(_) => writeln!(
+                        writer,
+                        "{kind:<22} {fqdn:<40} {} {}",
+                        record.likelihood(),
+                        record.ips()
+                    )?,
+                    None => writeln!(writer, "{kind:<22} {fqdn:<40} {}", record.ips())?,
+                }
+            }
+            Output::Json(_, buffered) => buffered.push(record),
+            Output::Jsonl(writer) => {
+                serde_json::to_writer(&mut *writer, &record)?;
+                writeln!(writer)?;
+            }
+            Output::Csv(csv, likelihoods) => {
+                let fields = [
+                    record.fqdn.clone(),
+                    format!("{:?}", record.kind),
+                    record.ips(),
+                    record.http_banner.clone().unwrap_or_default(),
+                    record.likelihood(),
+                ];
+                csv.write_record(&fields[..fields.len() - usize::from(!*likelihoods)])?;
+            }
+        }
+
+        Ok(())
+    }
+
+    fn finish(self) -> Result<()> {
+        match self {
+            Output::Text(mut writer) | Output::Jsonl(mut writer) => writer.flush()?,
+            Output::Json(mut writer, buffered) => {
+                serde_json::to_writer_pretty(&mut writer, &buffered)?;
+                writeln!(writer)?;
+                writer.flush()?;
+            }
+            Output::Csv(mut csv, _) => csv.flush()?,
+        }
+
+        Ok(())
+    }
+}
+
+/// Merge the config file (if any) with the command-line flags.
+fn scan_config(args: &Args) -> Result<ScanConfig> {
+    let mut config = match &args.config {
+        Some(path) => ScanConfig::from_file(path)?,
+        None => ScanConfig::default(),
+    };
+
+    config.domains.extend(args.domains.iter().cloned());
+
+    if !args.kinds.is_empty() {
+        config.kinds = args.kinds.clone();
+    }
+
+    if let Some(concurrency) = args.concurrency {
+        config.enrichment.concurrency = concurrency;
+    }
+
+    if !args.enrichments.is_empty() {
+        config.enrichment.enrichments = args.enrichments.clone();
+    }
+
+    config.enrichment.resolving_only |= args.resolving_only;
+
+    if config.domains.is_empty() {
+        bail!("no domain given, either as an argument or in the config file");
+    }
+
+    Ok(config)
+}
+
+/// Run the command, returning whether any resolving permutation was found.
+/// Summaries, if requested, are written to `report`.
+async fn run<W: Write, R: Write>(args: Args, writer: W, mut report: R) -> Result<bool> {
+    let config = scan_config(&args)?;
+    let engine = config.engine();
+    let fingerprint = engine.fingerprint();
+    let mut pipeline = config.enrichment.pipeline()?;
+    pipeline.set_fingerprint(fingerprint.clone());
+    let detailed = !config.enrichment.enrichments.is_empty();
+
+    let mut output = Output::new(args.format, writer, args.typos.is_some())?;
+    let mut found = false;
+
+    for fqdn in &config.domains {
+        let options = ParseOptions {
+            private_suffixes: args.private_suffixes,
+        };
+        let domain =
+            Domain::new_with(fqdn, &options).with_context(|| format!("invalid domain: {fqdn}"))?;
+
+        // Typos are kept most probable first, and looked up by FQDN once
+        // enriched.
+        let mut likelihoods = HashMap::new();
+        let permutations: Vec<Permutation> = match args.typos {
+            Some(n) => config
+                .typo
+                .top(&domain, n)
+                .into_iter()
+                .map(|typo| {
+                    likelihoods.insert(typo.permutation.domain.fqdn.clone(), typo.likelihood);
+                    typo.permutation
+                })
+                .collect(),
+            None => {
+                let mut generated: Vec<Permutation> = engine.generate(&domain)?.collect();
+                generated.sort();
+                generated.dedup();
+                generated
+            }
+        };
+
+        let scorer = config.scoring.scorer(domain);
+
+        if args.mode == Mode::Permutate {
+            if args.summary {
+                let mut summary = ScanSummary::new(&scorer, &[]);
+                summary.set_generated(&permutations);
+                write_summary(&mut report, &summary)?;
+            }
+
+            for permutation in permutations {
+                output.write(Record {
+                    fingerprint: Some(fingerprint.clone()),
+                    likelihood: likelihoods.get(&permutation.domain.fqdn).copied(),
+                    ..permutation.into()
+                })?;
+            }
+
+            continue;
+        }
+
+        let mut results = if config.enrichment.prioritize {
+            pipeline.run_prioritized(permutations, &scorer).boxed()
+        } else {
+            pipeline.run(permutations).boxed()
+        };
+        let mut enriched = vec![];
+
+        while let Some(result) = results.next().await {
+            let resolving = result.metadata.dns.resolves();
+            found |= resolving;
+
+            if args.summary {
+                enriched.push(result.clone());
+            }
+
+            if resolving || !config.enrichment.resolving_only {
+                let metadata = detailed.then(|| result.metadata.clone());
+                let likelihood = likelihoods.get(&result.permutation.domain.fqdn).copied();
+                output.write(Record {
+                    metadata,
+                    likelihood,
+                    ..result.into()
+                })?;
+            }
+        }
+
+        if args.summary {
+            write_summary(&mut report, &ScanSummary::new(&scorer, &enriched))?;
+        }
+    }
+
+    output.finish()?;
+    Ok(found)
+}
+
+/// Write `summary` as a single line of JSON.
+fn write_summary<R: Write>(report: &mut R, summary: &ScanSummary) -> Result<()> {
+    serde_json::to_writer(&mut *report, summary)?;
+    writeln!(report)?;
+    Ok(())
+}
+
+#[tokio::main]
+async fn main() -> ExitCode {
+    let args = Args::parse();
+
+    match run(args, BufWriter::new(io::stdout()), io::stderr()).await {
+        Ok(false) => ExitCode::SUCCESS,
+        Ok(true) => ExitCode::from(EXIT_FOUND),
+        // The consumer (e.g. `head`) stopped reading, which is not an error.
+        Err(e) if is_broken_pipe(&e) => ExitCode::SUCCESS,
+        Err(e) => {
+            eprintln!("error: {e:#}");
+            ExitCode::from(EXIT_ERROR)
+        }
+    }
+}
+
+fn is_broken_pipe(error: &anyhow::Error) -> bool {
+    error.chain().any(|cause| {
+        let kind = if let Some(e) = cause.downcast_ref::<io::Error>() {
+            Some(e.kind())
+        } else if let Some(e) = cause.downcast_ref::<serde_json::Error>() {
+            e.io_error_kind()
+        } else if let Some(csv::ErrorKind::Io(e)) =
+            cause.downcast_ref::<csv::Error>().map(csv::Error::kind)
+        {
+            Some(e.kind())
+        } else {
+            None
+        };
+
+        kind == Some(io::ErrorKind::BrokenPipe)
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use twistrs::enrich::EnrichmentKinds;
+
+    async fn permutate(format: &str) -> String {
+        let args = Args::parse_from([
+            "twistrs",
+            "example.com",
+            "--mode",
+            "permutate",
+            "--kind",
+            "tld",
+            "--format",
+            format,
+        ]);
+        let mut buffer = vec![];
+
+        assert!(!run(args, &mut buffer, io::sink()).await.unwrap());
+        String::from_utf8(buffer).unwrap()
+    }
+
+    #[tokio::test]
+    async fn test_permutate_output_formats() {
+        let csv = permutate("csv").await;
+        assert!(csv.starts_with("fqdn,kind,ips,http_banner\n"));
+        assert!(csv.lines().skip(1).all(|line| line.contains(",Tld,")));
+
+        let jsonl = permutate("jsonl").await;
+        for line in jsonl.lines() {
+            let record: serde_json::Value = serde_json::from_str(line).unwrap();
+            assert_eq!(record["kind"], "Tld");
+            assert!(record["ips"].is_null());
+            assert!(record["fingerprint"].is_string());
+        }
+
+        let json: Vec<serde_json::Value> = serde_json::from_str(&permutate("json").await).unwrap();
+        assert_eq!(json.len(), jsonl.lines().count());
+        assert_eq!(csv.lines().count(), json.len() + 1);
+    }
+
+    #[tokio::test]
+    async fn test_permutate_summary() {
+        let args = Args::parse_from([
+            "twistrs",
+            "example.com",
+            "--mode",
+            "permutate",
+            "--kind",
+            "tld",
+            "--summary",
+        ]);
+        let mut buffer = vec![];
+        let mut report = vec![];
+
+        assert!(!run(args, &mut buffer, &mut report).await.unwrap());
+
+        let summary: serde_json::Value = serde_json::from_slice(&report).unwrap();
+        assert_eq!(summary["domain"]["fqdn"], "example.com");
+        assert_eq!(
+            summary["generated"],
+            buffer.split(|b| *b == b'\n').count() - 1
+        );
+        assert_eq!(summary["resolvable"], 0);
+    }
+
+    #[test]
+    fn test_scan_config_flags_take_precedence() {
+        let file = tempfile::Builder::new().suffix(".toml").tempfile().unwrap();
+        std::fs::write(
+            file.path(),
+            "domains = [\"example.com\"]\nkinds = [\"Tld\"]\n[enrichment]\nconcurrency = 5\n",
+        )
+        .unwrap();
+
+        let args = Args::parse_from([
+            "twistrs",
+            "example.org",
+            "--config",
+            file.path().to_str().unwrap(),
+            "--concurrency",
+            "7",
+            "--enrich",
+            "dns,mail_capable",
+        ]);
+        let config = scan_config(&args).unwrap();
+
+        assert_eq!(config.domains, vec!["example.com", "example.org"]);
+        assert_eq!(config.kinds, vec![PermutationKind::Tld]);
+        assert_eq!(config.enrichment.concurrency, 7);
+        assert_eq!(config.enrichment.enrichments, vec!["dns", "mail_capable"]);
+
+        // The enrichments advertised by the help text are all supported.
+        let pipeline = config.enrichment.pipeline().unwrap();
+        assert!(pipeline
+            .enrichments()
+            .contains(EnrichmentKinds::DNS | EnrichmentKinds::MAIL_CAPABLE));
+
+        let all = scan_config(&Args::parse_from([
+            "twistrs",
+            "example.com",
+            "--enrich",
+            "all",
+        ]))
+        .unwrap()
+        .enrichment
+        .pipeline()
+        .unwrap();
+        assert_eq!(all.enrichments(), EnrichmentKinds::all());
+
+        assert!(scan_config(&Args::parse_from(["twistrs"])).is_err());
+    }
+
+    #[tokio::test]
+    async fn test_permutate_typos() {
+        let file = tempfile::Builder::new().suffix(".toml").tempfile().unwrap();
+        std::fs::write(file.path(), "[typo.kind_weights]\nOmission = 1.0\n").unwrap();
+
+        let args = Args::parse_from([
+            "twistrs",
+            "example.com",
+            "--mode",
+            "permutate",
+            "--config",
+            file.path().to_str().unwrap(),
+            "--typos",
+            "3",
+            "--format",
+            "jsonl",
+        ]);
+        let mut buffer = vec![];
+
+        assert!(!run(args, &mut buffer, io::sink()).await.unwrap());
+
+        let records: Vec<serde_json::Value> = buffer
+            .split(|b| *b == b'\n')
+            .filter(|line| !line.is_empty())
+            .map(|line| serde_json::from_slice(line).unwrap())
+            .collect();
+        assert_eq!(records.len(), 3);
+        assert!(records.iter().all(|record| record["kind"] == "Omission"));
+
+        // Most probable first, e.g. omitting either `e` of `example`.
+        let likelihoods: Vec<f64> = records
+            .iter()
+            .map(|record| record["likelihood"].as_f64().unwrap())
+            .collect();
+        assert!(likelihoods.windows(2).all(|pair| pair[0] >= pair[1]));
+
+        let csv = {
+            let args = Args::parse_from([
+                "twistrs",
+                "example.com",
+                "--mode",
+                "permutate",
+                "--typos",
+                "2",
+                "--format",
+                "csv",
+            ]);
+            let mut buffer = vec![];
+            run(args, &mut buffer, io::sink()).await.unwrap();
+            String::from_utf8(buffer).unwrap()
+        };
+        assert!(csv.starts_with("fqdn,kind,ips,http_banner,likelihood\n"));
+        assert_eq!(csv.lines().count(), 3);
+
+        assert!(
+            Args::try_parse_from(["twistrs", "example.com", "--typos", "3", "--kind", "tld"])
+                .is_err()
+        );
+    }
+
+    #[test]
+    fn test_rejects_unknown_kind() {
+        assert!(Args::try_parse_from(["twistrs", "example.com", "--kind", "nope"]).is_err());
+    }
+
+    #[tokio::test]
+    async fn test_rejects_unknown_enrichment() {
+        let args = Args::parse_from(["twistrs", "example.com", "--enrich", "nope"]);
+
+        assert!(run(args, io::sink(), io::sink()).await.is_err());
+    }
+}
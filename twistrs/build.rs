@@ -12,37 +12,105 @@ fn main() {
     // https://dev.to/rustyoctopus/generating-static-arrays-during-compile-time-in-rust-10d8
     let mut dicionary_output = String::from("");
 
-    let mut keywords_array_string = String::from(
+    let mut whois_servers_string = String::from(
         "#[allow(dead_code)]
-                                                 static KEYWORDS: [&str; ",
+                                                  pub(crate) static WHOIS_RAW_JSON: &str = r#",
     );
-    let mut whois_servers_string = String::from(
+
+    let mut keywords_array_string = keyword_array("KEYWORDS", "./data/keywords.txt");
+
+    // Industry-specific keyword packs, see `KeywordSet`.
+    for (name, path) in [
+        ("KEYWORDS_FINANCE", "./data/keywords/finance.txt"),
+        ("KEYWORDS_CRYPTO", "./data/keywords/crypto.txt"),
+        ("KEYWORDS_LOGISTICS", "./data/keywords/logistics.txt"),
+        ("KEYWORDS_HEALTHCARE", "./data/keywords/healthcare.txt"),
+    ] {
+        keywords_array_string.push('\n');
+        keywords_array_string.push_str(&keyword_array(name, path));
+    }
+
+    // Phishing lexicons of other languages, see `Locale`.
+    for (name, path) in [
+        ("KEYWORDS_ES", "./data/keywords/locales/es.txt"),
+        ("KEYWORDS_PT", "./data/keywords/locales/pt.txt"),
+        ("KEYWORDS_DE", "./data/keywords/locales/de.txt"),
+        ("KEYWORDS_FR", "./data/keywords/locales/fr.txt"),
+    ] {
+        keywords_array_string.push('\n');
+        keywords_array_string.push_str(&keyword_array(name, path));
+    }
+
+    // Compile the WhoIs server config to later perform WhoIs lookups against
+    match read_lines("./data/whois-servers.json") {
+        Ok(lines) => {
+            // Construct the in-memory JSON
+            whois_servers_string.push('"');
+            lines.for_each(|l| whois_servers_string.push_str(&l.unwrap()));
+            whois_servers_string.push_str("\"#;");
+        }
+        Err(e) => panic!(
+            "{}",
+            format!(
+                "unable to build library due to missing dictionary file(s): {}",
+                e
+            )
+        ),
+    }
+
+    // Build the final output
+    dicionary_output.push_str(&keywords_array_string);
+    dicionary_output.push('\n');
+    dicionary_output.push_str(&whois_servers_string);
+
+    // Write out contents to the final Rust file artifact
+    let out_dir = env::var("OUT_DIR").unwrap();
+    let dest_path = Path::new(&out_dir).join("data.rs");
+    fs::write(dest_path, dicionary_output).unwrap();
+
+    compile_tld_categories(Path::new(&out_dir));
+    compile_abused_tlds(Path::new(&out_dir));
+    compile_cctld_neighbors(Path::new(&out_dir));
+    compile_words(Path::new(&out_dir));
+
+    // Declared explicitly, as the protobuf compiler declares its own
+    // inputs, which would otherwise be the only ones tracked.
+    println!("cargo:rerun-if-changed=build.rs");
+    println!("cargo:rerun-if-changed=data");
+
+    #[cfg(feature = "grpc")]
+    compile_protos();
+}
+
+// Compile a keyword dictionary into a static array named `name`.
+fn keyword_array(name: &str, path: &str) -> String {
+    let mut keywords_array_string = format!(
         "#[allow(dead_code)]
-                                                  static WHOIS_RAW_JSON: &str = r#",
+                                                 static {name}: [&str; "
     );
 
-    match read_lines("./data/keywords.txt") {
+    match read_lines(path) {
         Ok(lines) => {
-            // We want to unwrap to make sure that we are able to fetch all TLDs
-            let tlds = lines.map(|l| l.unwrap()).collect::<Vec<String>>();
+            // We want to unwrap to make sure that we are able to fetch all keywords
+            let keywords = lines.map(|l| l.unwrap()).collect::<Vec<String>>();
 
             // Finalize the variable signature and break into newline to
-            // start populating the TLDs
-            keywords_array_string.push_str(&tlds.len().to_string());
+            // start populating the keywords
+            keywords_array_string.push_str(&keywords.len().to_string());
             keywords_array_string.push_str("] = [\r\n");
 
-            // Start populating TLD contents
-            for line in tlds.into_iter() {
+            // Start populating keyword contents
+            for line in keywords.into_iter() {
                 // Formatting some tabs (ASCII-20)
                 keywords_array_string.push_str("\u{20}\u{20}\u{20}\u{20}\"");
 
-                let tld = if line.chars().all(char::is_alphanumeric) {
+                let keyword = if line.chars().all(char::is_alphanumeric) {
                     line.to_string()
                 } else {
                     punycode::encode(line.to_string().as_str()).unwrap()
                 };
 
-                keywords_array_string.push_str(&tld[..]);
+                keywords_array_string.push_str(&keyword[..]);
                 keywords_array_string.push_str("\",\r\n");
             }
 
@@ -58,32 +126,157 @@ fn main() {
         ),
     }
 
-    // Compile the WhoIs server config to later perform WhoIs lookups against
-    match read_lines("./data/whois-servers.json") {
-        Ok(lines) => {
-            // Construct the in-memory JSON
-            whois_servers_string.push('"');
-            lines.for_each(|l| whois_servers_string.push_str(&l.unwrap()));
-            whois_servers_string.push_str("\"#;");
+    keywords_array_string
+}
+
+// Compile the TLD categories into a static map, keyed by both the Unicode
+// and the punycode form of each TLD.
+fn compile_tld_categories(out_dir: &Path) {
+    let mut entries = String::new();
+
+    let lines = read_lines("./data/tld-categories.txt").unwrap_or_else(|e| {
+        panic!("unable to build library due to missing dictionary file(s): {e}")
+    });
+
+    for line in lines.map(|l| l.unwrap()) {
+        let line = line.trim();
+
+        if line.is_empty() || line.starts_with('#') {
+            continue;
+        }
+
+        let fields: Vec<&str> = line.split_whitespace().collect();
+        let (tld, category, risk) = match fields[..] {
+            [tld, category, risk] => (tld, category, risk),
+            _ => panic!("invalid tld category entry: {line}"),
+        };
+
+        let category = match category {
+            "generic" => "Generic",
+            "country-code" => "CountryCode",
+            "new-generic" => "NewGeneric",
+            "sponsored" => "Sponsored",
+            "infrastructure" => "Infrastructure",
+            _ => panic!("invalid tld category: {line}"),
+        };
+
+        let risk = match risk {
+            "low" => "Low",
+            "medium" => "Medium",
+            "high" => "High",
+            _ => panic!("invalid tld risk tier: {line}"),
+        };
+
+        let value = format!("(TldCategory::{category}, RiskTier::{risk})");
+        entries.push_str(&format!("    \"{tld}\" => {value},\n"));
+
+        if !tld.is_ascii() {
+            let encoded = punycode::encode(tld).unwrap();
+            entries.push_str(&format!("    \"xn--{encoded}\" => {value},\n"));
         }
-        Err(e) => panic!(
-            "{}",
-            format!(
-                "unable to build library due to missing dictionary file(s): {}",
-                e
-            )
-        ),
     }
 
-    // Build the final output
-    dicionary_output.push_str(&keywords_array_string);
-    dicionary_output.push('\n');
-    dicionary_output.push_str(&whois_servers_string);
+    let output = format!(
+        "static TLD_CATEGORIES: phf::Map<&'static str, (TldCategory, RiskTier)> = phf::phf_map! {{\n{entries}}};\n"
+    );
 
-    // Write out contents to the final Rust file artifact
-    let out_dir = env::var("OUT_DIR").unwrap();
-    let dest_path = Path::new(&out_dir).join("data.rs");
-    fs::write(dest_path, dicionary_output).unwrap();
+    fs::write(out_dir.join("tld_categories.rs"), output).unwrap();
+}
+
+// Compile the commonly abused TLDs into a static set, keyed by both the
+// Unicode and the punycode form of each TLD.
+fn compile_abused_tlds(out_dir: &Path) {
+    let mut entries = String::new();
+
+    let lines = read_lines("./data/abused-tlds.txt").unwrap_or_else(|e| {
+        panic!("unable to build library due to missing dictionary file(s): {e}")
+    });
+
+    for line in lines.map(|l| l.unwrap()) {
+        let tld = line.trim();
+
+        if tld.is_empty() || tld.starts_with('#') {
+            continue;
+        }
+
+        entries.push_str(&format!("    \"{tld}\",\n"));
+
+        if !tld.is_ascii() {
+            let encoded = punycode::encode(tld).unwrap();
+            entries.push_str(&format!("    \"xn--{encoded}\",\n"));
+        }
+    }
+
+    let output =
+        format!("static ABUSED_TLDS: phf::Set<&'static str> = phf::phf_set! {{\n{entries}}};\n");
+
+    fs::write(out_dir.join("abused_tlds.rs"), output).unwrap();
+}
+
+// Compile the ccTLD neighbors into a static map of each ccTLD to its
+// neighboring suffixes, in order.
+fn compile_cctld_neighbors(out_dir: &Path) {
+    let mut entries = String::new();
+
+    let lines = read_lines("./data/cctld-neighbors.txt").unwrap_or_else(|e| {
+        panic!("unable to build library due to missing dictionary file(s): {e}")
+    });
+
+    for line in lines.map(|l| l.unwrap()) {
+        let line = line.trim();
+
+        if line.is_empty() || line.starts_with('#') {
+            continue;
+        }
+
+        let mut fields = line.split_whitespace();
+        let cctld = fields.next().unwrap();
+        let neighbors: Vec<String> = fields.map(|neighbor| format!("\"{neighbor}\"")).collect();
+
+        entries.push_str(&format!(
+            "    \"{cctld}\" => &[{}],\n",
+            neighbors.join(", ")
+        ));
+    }
+
+    let output = format!(
+        "static CCTLD_NEIGHBORS: phf::Map<&'static str, &'static [&'static str]> = phf::phf_map! {{\n{entries}}};\n"
+    );
+
+    fs::write(out_dir.join("cctld_neighbors.rs"), output).unwrap();
+}
+
+// Compile the word list used to segment domain labels into a static set.
+fn compile_words(out_dir: &Path) {
+    let mut entries = String::new();
+
+    let lines = read_lines("./data/words.txt").unwrap_or_else(|e| {
+        panic!("unable to build library due to missing dictionary file(s): {e}")
+    });
+
+    for line in lines.map(|l| l.unwrap()) {
+        let word = line.trim();
+
+        if word.is_empty() || word.starts_with('#') {
+            continue;
+        }
+
+        entries.push_str(&format!("    \"{word}\",\n"));
+    }
+
+    let output = format!("static WORDS: phf::Set<&'static str> = phf::phf_set! {{\n{entries}}};\n");
+
+    fs::write(out_dir.join("words.rs"), output).unwrap();
+}
+
+// Compile the versioned gRPC service definition. A vendored `protoc` is
+// used so that clients do not need to have one installed.
+#[cfg(feature = "grpc")]
+fn compile_protos() {
+    env::set_var("PROTOC", protoc_bin_vendored::protoc_bin_path().unwrap());
+
+    tonic_build::compile_protos("proto/twistrs/v1/twistrs.proto")
+        .expect("unable to build library due to invalid protobuf definition(s)");
 }
 
 // The output is wrapped in a Result to allow matching on errors
@@ -69,7 +69,7 @@ def generate_rust_array(suffixes, output_path):
     
     rust_lines = []
     rust_lines.append("// This file is auto-generated. Do not edit manually.")
-    rust_lines.append(f"pub const TLDS: [&str; {array_len}] = [")
+    rust_lines.append(f"pub static TLDS: [&str; {array_len}] = [")
     for s in suffixes:
         rust_lines.append(f'    "{s}",')
     rust_lines.append("];\n")
@@ -0,0 +1,8 @@
+#![no_main]
+
+use libfuzzer_sys::fuzz_target;
+use twistrs::permutate::Domain;
+
+fuzz_target!(|fqdn: &str| {
+    let _ = Domain::new(fqdn);
+});
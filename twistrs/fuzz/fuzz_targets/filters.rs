@@ -0,0 +1,13 @@
+#![no_main]
+
+use libfuzzer_sys::fuzz_target;
+use twistrs::filter::FilterDescriptor;
+use twistrs::permutate::Permutation;
+
+fuzz_target!(|input: (FilterDescriptor, Permutation)| {
+    let (descriptor, permutation) = input;
+
+    if let Some(filter) = descriptor.build() {
+        filter.matches(&permutation);
+    }
+});
@@ -0,0 +1,13 @@
+#![no_main]
+
+use libfuzzer_sys::fuzz_target;
+use twistrs::permutate::{Domain, PermutationKind};
+
+// Covers every generator, the kind being picked by the fuzzer.
+fuzz_target!(|input: (Domain, PermutationKind)| {
+    let (domain, kind) = input;
+
+    if let Ok(permutations) = domain.permutations(kind) {
+        permutations.take(1000).for_each(drop);
+    };
+});
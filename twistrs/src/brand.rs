@@ -0,0 +1,263 @@
+//! The brand module exposes [`Brand`], the portfolio of domains owned by
+//! a brand along with the suffixes it operates under and the tokens
+//! identifying it.
+//!
+//! Brand protection works on brands rather than single domains:
+//! permutations are generated across the whole portfolio, the brand's
+//! own assets are suppressed, and detections are attributed back to the
+//! brand and domain they most likely target (see [`attribute`]).
+//!
+//! Example:
+//!
+//! ```
+//! use twistrs::brand::Brand;
+//! use twistrs::permutate::{Domain, PermutationEngine, PermutationKind};
+//!
+//! let mut brand = Brand::new(
+//!     "Example",
+//!     vec![
+//!         Domain::new("example.com").unwrap(),
+//!         Domain::new("examplepay.com").unwrap(),
+//!     ],
+//! );
+//! brand.known_suffixes.insert("de".to_string());
+//!
+//! let mut engine = PermutationEngine::new();
+//! engine.kinds = vec![PermutationKind::Tld];
+//!
+//! let permutations: Vec<_> = brand.permutations(&engine).unwrap().collect();
+//! assert!(permutations.iter().any(|(_, p)| p.domain.fqdn == "examplepay.net"));
+//! assert!(!permutations.iter().any(|(_, p)| p.domain.fqdn == "example.de"));
+//! ```
+use std::collections::{BTreeSet, HashSet};
+
+use serde::{Deserialize, Serialize};
+
+use crate::error::Error;
+use crate::permutate::{Domain, DomainRef, Permutation, PermutationEngine};
+use crate::scoring::similarity;
+
+/// Domains, suffixes and tokens of a brand.
+#[derive(Clone, Debug, Default, Serialize, Deserialize, Eq, PartialEq)]
+#[serde(default)]
+pub struct Brand {
+    /// Name of the brand, e.g. for reporting.
+    pub name: String,
+
+    /// Domains owned by the brand, which permutations are generated from.
+    pub domains: Vec<Domain>,
+
+    /// Suffixes the brand operates under (e.g. `de`), under which the
+    /// root labels of its domains are owned as well (e.g. `example.de`).
+    pub known_suffixes: BTreeSet<String>,
+
+    /// Lowercase tokens identifying the brand (e.g. `example`), used to
+    /// attribute detections. Defaults to the root labels of its domains.
+    pub tokens: BTreeSet<String>,
+
+    /// Lowercase languages of the customers of the brand (e.g. `de`), used
+    /// to prioritize lookalikes serving content in one of them.
+    pub locales: BTreeSet<String>,
+}
+
+impl Brand {
+    /// Create a brand owning `domains`, identified by their root labels.
+    pub fn new(name: &str, domains: Vec<Domain>) -> Brand {
+        let tokens = domains
+            .iter()
+            .map(|domain| domain.domain.to_lowercase())
+            .collect();
+
+        Brand {
+            name: name.to_string(),
+            domains,
+            known_suffixes: BTreeSet::new(),
+            tokens,
+            locales: BTreeSet::new(),
+        }
+    }
+
+    /// Whether `fqdn` belongs to the brand, i.e. is (a subdomain of) one
+    /// of its domains, or one of their root labels under a known suffix.
+    pub fn is_owned(&self, fqdn: &str) -> bool {
+        DomainRef::new(fqdn).is_ok_and(|domain| self.owns(domain))
+    }
+
+    fn owns(&self, candidate: DomainRef<'_>) -> bool {
+        let label = candidate.domain.to_lowercase();
+        let tld = candidate.tld.to_lowercase();
+
+        self.domains.iter().any(|domain| {
+            domain.domain.eq_ignore_ascii_case(&label)
+                && (domain.tld.eq_ignore_ascii_case(&tld) || self.known_suffixes.contains(&tld))
+        })
+    }
+
+    /// Generate the permutations of every domain of the brand with
+    /// `engine`, along with the domain each was generated from.
+    ///
+    /// Permutations owned by the brand are suppressed, and permutations
+    /// generated from several domains are only yielded once.
+    pub fn permutations<'a>(
+        &'a self,
+        engine: &'a PermutationEngine,
+    ) -> Result<impl Iterator<Item = (&'a Domain, Permutation)> + 'a, Error> {
+        let generators = self
+            .domains
+            .iter()
+            .map(|domain| {
+                engine
+                    .generate(domain)
+                    .map(|permutations| permutations.map(move |permutation| (domain, permutation)))
+            })
+            .collect::<Result<Vec<_>, Error>>()?;
+        let mut seen = HashSet::new();
+
+        Ok(generators
+            .into_iter()
+            .flatten()
+            .filter(move |(_, permutation)| {
+                !self.owns(permutation.domain.as_domain_ref())
+                    && seen.insert(permutation.domain.canonical().to_string())
+            }))
+    }
+
+    /// The domain of the brand that `fqdn` most closely resembles, or
+    /// `None` if `fqdn` is owned by the brand.
+    pub fn target(&self, fqdn: &str) -> Option<&Domain> {
+        if self.is_owned(fqdn) {
+            return None;
+        }
+
+        let lowercase = fqdn.to_lowercase();
+
+        self.domains.iter().max_by(|left, right| {
+            similarity(&left.fqdn, &lowercase).total_cmp(&similarity(&right.fqdn, &lowercase))
+        })
+    }
+
+    /// Whether `fqdn` contains one of the tokens of the brand.
+    pub fn mentions(&self, fqdn: &str) -> bool {
+        let lowercase = fqdn.to_lowercase();
+        self.tokens
+            .iter()
+            .any(|token| !token.is_empty() && lowercase.contains(token.as_str()))
+    }
+
+    /// Whether content in `language` (e.g. `de-CH`, as detected by an HTTP
+    /// probe) targets the customers of the brand. Only the primary subtags
+    /// are compared.
+    pub fn targets_language(&self, language: &str) -> bool {
+        let primary = |tag: &str| {
+            tag.split(['-', '_'])
+                .next()
+                .unwrap_or_default()
+                .trim()
+                .to_lowercase()
+        };
+
+        let wanted = primary(language);
+        !wanted.is_empty() && self.locales.iter().any(|locale| primary(locale) == wanted)
+    }
+}
+
+/// Attribute the detection `fqdn` to the brand, and domain of that brand,
+/// it most likely targets.
+///
+/// Brands whose tokens appear in `fqdn` are preferred, falling back on
+/// the brand with the most similar domain otherwise. Detections owned by
+/// any of the `brands` are not attributed.
+pub fn attribute<'a>(brands: &'a [Brand], fqdn: &str) -> Option<(&'a Brand, &'a Domain)> {
+    if brands.iter().any(|brand| brand.is_owned(fqdn)) {
+        return None;
+    }
+
+    let lowercase = fqdn.to_lowercase();
+    let mentioned = brands.iter().any(|brand| brand.mentions(fqdn));
+
+    brands
+        .iter()
+        .filter(|brand| !mentioned || brand.mentions(fqdn))
+        .filter_map(|brand| Some((brand, brand.target(fqdn)?)))
+        .max_by(|(_, left), (_, right)| {
+            similarity(&left.fqdn, &lowercase).total_cmp(&similarity(&right.fqdn, &lowercase))
+        })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    use crate::permutate::PermutationKind;
+
+    fn brand(name: &str, fqdns: &[&str]) -> Brand {
+        Brand::new(
+            name,
+            fqdns
+                .iter()
+                .map(|fqdn| Domain::new(fqdn).unwrap())
+                .collect(),
+        )
+    }
+
+    #[test]
+    fn test_brand_ownership() {
+        let mut acme = brand("Acme", &["acme.com", "acmebank.com"]);
+        acme.known_suffixes.insert("de".to_string());
+
+        assert!(acme.is_owned("acme.com"));
+        assert!(acme.is_owned("login.ACMEBANK.com"));
+        assert!(acme.is_owned("acmebank.de"));
+        assert!(!acme.is_owned("acmebank.fr"));
+        assert!(!acme.is_owned("acme-bank.com"));
+
+        let mut engine = PermutationEngine::new();
+        engine.kinds = vec![PermutationKind::Tld, PermutationKind::Addition];
+
+        let permutations: Vec<(&Domain, Permutation)> =
+            acme.permutations(&engine).unwrap().collect();
+        let fqdns: HashSet<&str> = permutations
+            .iter()
+            .map(|(_, p)| p.domain.fqdn.as_str())
+            .collect();
+
+        assert_eq!(fqdns.len(), permutations.len());
+        assert!(fqdns.contains("acmebank.fr"));
+        assert!(!fqdns.contains("acme.de"));
+        assert!(!fqdns.contains("acme.com"));
+        assert!(permutations
+            .iter()
+            .any(|(origin, p)| origin.fqdn == "acmebank.com" && p.domain.fqdn == "acmebanka.com"));
+    }
+
+    #[test]
+    fn test_attribute() {
+        let brands = vec![
+            brand("Acme", &["acme.com", "acmebank.com"]),
+            brand("Globex", &["globex.com"]),
+        ];
+
+        let (attributed, target) = attribute(&brands, "acmebenk.com").unwrap();
+        assert_eq!(attributed.name, "Acme");
+        assert_eq!(target.fqdn, "acmebank.com");
+
+        // Tokens take precedence over similarity.
+        let (mentioned, _) = attribute(&brands, "glabex-acme.com").unwrap();
+        assert_eq!(mentioned.name, "Acme");
+        assert_eq!(attribute(&brands, "glabex.com").unwrap().0.name, "Globex");
+
+        assert!(attribute(&brands, "www.globex.com").is_none());
+    }
+
+    #[test]
+    fn test_targets_language() {
+        let mut acme = brand("Acme", &["acme.com"]);
+        assert!(!acme.targets_language("de"));
+
+        acme.locales.insert("de-AT".to_string());
+        assert!(acme.targets_language("de"));
+        assert!(acme.targets_language("DE-ch"));
+        assert!(!acme.targets_language("fr"));
+        assert!(!acme.targets_language(""));
+    }
+}
@@ -0,0 +1,366 @@
+//! The breaker module exposes [`CircuitBreaker`], short-circuiting the
+//! lookups of an enrichment towards a destination that keeps timing out,
+//! e.g. a dead WHOIS server or a network blocking port 25.
+//!
+//! Each enrichment kind and destination pair has its own circuit. After
+//! `failure_threshold` consecutive timeouts the circuit opens and lookups
+//! fail straight away. Once `cooldown` has elapsed, a single probe is let
+//! through (the circuit is half-open): the circuit closes again if the
+//! probe completes in time, and re-opens otherwise. Probes that are
+//! cancelled, or never recorded for a whole `cooldown`, re-open it too.
+//!
+//! The HTTP, HTTPS, JARM, SMTP, mail capability and `WhoIs` lookups of an
+//! [`EnrichmentContext`] go through its breaker, and a [`Pipeline`] can
+//! short-circuit the banner lookups towards each resolved IP, see
+//! [`Pipeline::set_circuit_breaker`]. `GeoIP` lookups read a local
+//! database and have no destination to short-circuit.
+//!
+//! Example:
+//!
+//! ```
+//! use std::time::Duration;
+//!
+//! use twistrs::breaker::CircuitBreaker;
+//! use twistrs::enrich::EnrichmentContext;
+//!
+//! let context = EnrichmentContext {
+//!     breaker: Some(CircuitBreaker::new(3, Duration::from_secs(60))),
+//!     ..EnrichmentContext::default()
+//! };
+//! ```
+//!
+//! ### Features
+//!
+//! This module requires the `enrich` feature toggled.
+//!
+//! [`EnrichmentContext`]: crate::enrich::EnrichmentContext
+//! [`Pipeline`]: crate::pipeline::Pipeline
+//! [`Pipeline::set_circuit_breaker`]: crate::pipeline::Pipeline::set_circuit_breaker
+
+use std::collections::HashMap;
+use std::future::Future;
+use std::sync::{Arc, Mutex, MutexGuard, PoisonError};
+use std::time::Duration;
+
+use serde::{Deserialize, Serialize};
+use tokio::time::Instant;
+
+use crate::enrich::EnrichmentKind;
+use crate::error::Error;
+
+// Clashes with the crate `Error` it converts into without the prefix.
+#[allow(clippy::module_name_repetitions)]
+#[derive(thiserror::Error, Debug)]
+pub enum BreakerError {
+    #[error("circuit open, skipping lookup (kind: {kind:?}, destination: {destination})")]
+    Open {
+        kind: EnrichmentKind,
+        destination: String,
+    },
+
+    #[error("lookup timed out (kind: {kind:?}, destination: {destination})")]
+    Timeout {
+        kind: EnrichmentKind,
+        destination: String,
+    },
+}
+
+/// State of the circuit of an enrichment kind and destination.
+// Reads ambiguously without the prefix once imported.
+#[allow(clippy::module_name_repetitions)]
+#[derive(Clone, Copy, Debug, Default, Serialize, Deserialize, Hash, Eq, PartialEq)]
+pub enum BreakerState {
+    /// Lookups go through.
+    #[default]
+    Closed,
+
+    /// Lookups fail straight away until the cooldown has elapsed.
+    Open,
+
+    /// A single probe is in flight, deciding whether the circuit closes.
+    HalfOpen,
+}
+
+/// Consecutive timeouts and state of a single circuit.
+#[derive(Clone, Copy, Debug, Default)]
+struct Circuit {
+    state: BreakerState,
+    timeouts: usize,
+
+    /// When the circuit last opened, or let its probe through.
+    since: Option<Instant>,
+}
+
+/// Circuit breakers keyed by enrichment kind and destination.
+///
+/// Clones share the same circuits, so a single breaker can be handed to
+/// every task of a scan.
+// The usual name of the pattern.
+#[allow(clippy::module_name_repetitions)]
+#[derive(Clone, Debug)]
+pub struct CircuitBreaker {
+    /// Number of consecutive timeouts opening a circuit.
+    pub failure_threshold: usize,
+
+    /// How long a circuit stays open before letting a probe through.
+    pub cooldown: Duration,
+
+    circuits: Arc<Mutex<HashMap<(EnrichmentKind, String), Circuit>>>,
+}
+
+impl Default for CircuitBreaker {
+    fn default() -> CircuitBreaker {
+        CircuitBreaker::new(5, Duration::from_secs(60))
+    }
+}
+
+impl CircuitBreaker {
+    /// Create a breaker opening circuits after `failure_threshold`
+    /// consecutive timeouts, for `cooldown`.
+    pub fn new(failure_threshold: usize, cooldown: Duration) -> CircuitBreaker {
+        CircuitBreaker {
+            failure_threshold,
+            cooldown,
+            circuits: Arc::default(),
+        }
+    }
+
+    /// Whether a lookup of `kind` towards `destination` may go through.
+    /// Letting a lookup through an open circuit past its cooldown makes
+    /// it the probe of the now half-open circuit. A probe that is still
+    /// not recorded after another cooldown is considered lost, and the
+    /// lookup becomes the new probe.
+    pub fn allow(&self, kind: EnrichmentKind, destination: &str) -> bool {
+        self.with_circuit(kind, destination, |circuit| {
+            if circuit.state == BreakerState::Closed {
+                return true;
+            }
+
+            let cooled_down = circuit
+                .since
+                .is_some_and(|since| since.elapsed() >= self.cooldown);
+
+            if cooled_down {
+                circuit.state = BreakerState::HalfOpen;
+                circuit.since = Some(Instant::now());
+            }
+
+            cooled_down
+        })
+    }
+
+    /// Record the outcome of a lookup of `kind` towards `destination`
+    /// that was let through, see [`CircuitBreaker::allow`].
+    pub fn record(&self, kind: EnrichmentKind, destination: &str, timed_out: bool) {
+        self.with_circuit(kind, destination, |circuit| {
+            if !timed_out {
+                *circuit = Circuit::default();
+                return;
+            }
+
+            circuit.timeouts += 1;
+
+            if circuit.state == BreakerState::HalfOpen
+                || circuit.timeouts >= self.failure_threshold.max(1)
+            {
+                circuit.state = BreakerState::Open;
+                circuit.since = Some(Instant::now());
+            }
+        });
+    }
+
+    /// Re-open the circuit of `kind` towards `destination` if its probe
+    /// was abandoned before completing, e.g. because it was cancelled.
+    fn abandon(&self, kind: EnrichmentKind, destination: &str) {
+        self.with_circuit(kind, destination, |circuit| {
+            if circuit.state == BreakerState::HalfOpen {
+                circuit.state = BreakerState::Open;
+                circuit.since = Some(Instant::now());
+            }
+        });
+    }
+
+    /// State of the circuit of `kind` towards `destination`.
+    pub fn state(&self, kind: EnrichmentKind, destination: &str) -> BreakerState {
+        self.lock()
+            .get(&(kind, destination.to_lowercase()))
+            .map_or(BreakerState::Closed, |circuit| circuit.state)
+    }
+
+    /// Run `lookup` of `kind` towards `destination` through its circuit,
+    /// timing it out after `timeout`. Fails straight away if the circuit
+    /// is open. Errors of the lookup itself do not count as timeouts,
+    /// since the destination did answer, whereas dropping the returned
+    /// future before it completes counts as a failed probe.
+    pub async fn guard<T, F>(
+        &self,
+        kind: EnrichmentKind,
+        destination: &str,
+        timeout: Duration,
+        lookup: F,
+    ) -> Result<T, Error>
+    where
+        F: Future<Output = Result<T, Error>>,
+    {
+        if !self.allow(kind, destination) {
+            return Err(BreakerError::Open {
+                kind,
+                destination: destination.to_string(),
+            }
+            .into());
+        }
+
+        let mut pending = Pending {
+            breaker: self,
+            kind,
+            destination,
+            recorded: false,
+        };
+        let outcome = tokio::time::timeout(timeout, lookup).await;
+        pending.recorded = true;
+
+        if let Ok(result) = outcome {
+            self.record(kind, destination, false);
+            result
+        } else {
+            self.record(kind, destination, true);
+            Err(BreakerError::Timeout {
+                kind,
+                destination: destination.to_string(),
+            }
+            .into())
+        }
+    }
+
+    /// Run `f` over the circuit of `kind` towards `destination`. Only
+    /// circuits that are not closed, or have timeouts, are tracked, so that
+    /// scans spanning many destinations do not accumulate healthy ones.
+    fn with_circuit<T, F>(&self, kind: EnrichmentKind, destination: &str, f: F) -> T
+    where
+        F: FnOnce(&mut Circuit) -> T,
+    {
+        let mut circuits = self.lock();
+        let key = (kind, destination.to_lowercase());
+        let mut circuit = circuits.get(&key).copied().unwrap_or_default();
+        let result = f(&mut circuit);
+
+        if circuit.state == BreakerState::Closed && circuit.timeouts == 0 {
+            circuits.remove(&key);
+        } else {
+            circuits.insert(key, circuit);
+        }
+
+        result
+    }
+
+    fn lock(&self) -> MutexGuard<'_, HashMap<(EnrichmentKind, String), Circuit>> {
+        self.circuits.lock().unwrap_or_else(PoisonError::into_inner)
+    }
+}
+
+/// Lookup let through by [`CircuitBreaker::guard`], abandoning its probe
+/// if dropped before being recorded.
+struct Pending<'a> {
+    breaker: &'a CircuitBreaker,
+    kind: EnrichmentKind,
+    destination: &'a str,
+    recorded: bool,
+}
+
+impl Drop for Pending<'_> {
+    fn drop(&mut self) {
+        if !self.recorded {
+            self.breaker.abandon(self.kind, self.destination);
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[tokio::test]
+    async fn test_circuit_breaker() {
+        let cooldown = Duration::from_millis(50);
+        let breaker = CircuitBreaker::new(2, cooldown);
+        let timeout = Duration::from_millis(10);
+        let kind = EnrichmentKind::WhoIs;
+        let hang = || std::future::pending::<Result<(), Error>>();
+
+        for _ in 0..2 {
+            assert!(matches!(
+                breaker.guard(kind, "whois.example", timeout, hang()).await,
+                Err(Error::BreakerError(BreakerError::Timeout { .. }))
+            ));
+        }
+        assert_eq!(breaker.state(kind, "whois.example"), BreakerState::Open);
+
+        // Open circuits fail straight away, other circuits are unaffected.
+        assert!(matches!(
+            breaker
+                .guard(kind, "WHOIS.example", timeout, async { Ok(()) })
+                .await,
+            Err(Error::BreakerError(BreakerError::Open { .. }))
+        ));
+        assert!(breaker.allow(EnrichmentKind::MailCapable, "whois.example"));
+        assert!(breaker.allow(kind, "whois.other"));
+
+        // A probe timing out re-opens the circuit.
+        tokio::time::sleep(cooldown).await;
+        assert!(breaker.allow(kind, "whois.example"));
+        assert_eq!(breaker.state(kind, "whois.example"), BreakerState::HalfOpen);
+        assert!(!breaker.allow(kind, "whois.example"));
+        breaker.record(kind, "whois.example", true);
+        assert_eq!(breaker.state(kind, "whois.example"), BreakerState::Open);
+
+        // A probe completing in time closes it.
+        tokio::time::sleep(cooldown).await;
+        assert!(breaker
+            .guard(kind, "whois.example", timeout, async { Ok(()) })
+            .await
+            .is_ok());
+        assert_eq!(breaker.state(kind, "whois.example"), BreakerState::Closed);
+
+        // Closed circuits without timeouts are not tracked.
+        assert!(breaker.lock().is_empty());
+    }
+
+    #[tokio::test]
+    async fn test_circuit_breaker_lost_probe() {
+        let cooldown = Duration::from_millis(50);
+        let breaker = CircuitBreaker::new(1, cooldown);
+        let timeout = Duration::from_secs(60);
+        let kind = EnrichmentKind::HttpBanner;
+
+        breaker.record(kind, "203.0.113.1", true);
+        tokio::time::sleep(cooldown).await;
+
+        // A cancelled probe re-opens the circuit rather than leaving it
+        // half-open for good.
+        let probe = breaker.guard(
+            kind,
+            "203.0.113.1",
+            timeout,
+            std::future::pending::<Result<(), Error>>(),
+        );
+        assert!(tokio::time::timeout(Duration::from_millis(10), probe)
+            .await
+            .is_err());
+        assert_eq!(breaker.state(kind, "203.0.113.1"), BreakerState::Open);
+
+        tokio::time::sleep(cooldown).await;
+        assert!(breaker
+            .guard(kind, "203.0.113.1", timeout, async { Ok(()) })
+            .await
+            .is_ok());
+
+        // A probe let through without ever being recorded expires after
+        // another cooldown.
+        breaker.record(kind, "203.0.113.2", true);
+        tokio::time::sleep(cooldown).await;
+        assert!(breaker.allow(kind, "203.0.113.2"));
+        assert!(!breaker.allow(kind, "203.0.113.2"));
+        tokio::time::sleep(cooldown).await;
+        assert!(breaker.allow(kind, "203.0.113.2"));
+    }
+}
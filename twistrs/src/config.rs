@@ -0,0 +1,617 @@
+//! The config module exposes a declarative description of a scan, shared
+//! by the CLI, the servers and library clients alike.
+//!
+//! Configs can be constructed programmatically or, with the `config`
+//! feature toggled, loaded from TOML or JSON files. Every field has a
+//! sensible default, so files only need to contain what they override.
+//!
+//! Example:
+//!
+//! ```
+//! use twistrs::config::{EnrichmentConfig, ScanConfig};
+//! use twistrs::permutate::PermutationKind;
+//!
+//! let config = ScanConfig {
+//!     domains: vec![String::from("example.com")],
+//!     kinds: vec![PermutationKind::Homoglyph],
+//!     enrichment: EnrichmentConfig {
+//!         concurrency: 50,
+//!         ..EnrichmentConfig::default()
+//!     },
+//!     ..ScanConfig::default()
+//! };
+//!
+//! let engine = config.engine();
+//! ```
+//!
+//! The equivalent TOML file:
+//!
+//! ```toml
+//! domains = ["example.com"]
+//! kinds = ["Homoglyph"]
+//!
+//! [enrichment]
+//! concurrency = 50
+//! ```
+
+// The names of the config types are part of the public API and read
+// better with the suffix, even within this module.
+#![allow(clippy::module_name_repetitions)]
+
+use std::collections::BTreeMap;
+#[cfg(feature = "enrich")]
+use std::time::Duration;
+
+#[cfg(feature = "config")]
+use std::path::Path;
+
+use serde::{Deserialize, Serialize};
+
+#[cfg(feature = "enrich")]
+use crate::breaker::CircuitBreaker;
+use crate::constants::DEFAULT_CONCURRENCY;
+#[cfg(feature = "enrich")]
+use crate::enrich::{EnrichmentError, EnrichmentKinds};
+#[cfg(any(feature = "config", feature = "enrich"))]
+use crate::error::Error;
+use crate::permutate::{
+    Charset, Domain, HomoglyphOptions, HomoglyphTable, IdnaMode, KeywordForms, KeywordSet, Locale,
+    MappedMode, PermutationEngine, PermutationKind, Script, TldExclusions,
+};
+#[cfg(feature = "enrich")]
+use crate::pipeline::Pipeline;
+use crate::scoring::{CompositeScorer, PositionCurve};
+use crate::tld::TldSet;
+use crate::typo::TypoModel;
+
+#[derive(thiserror::Error, Debug)]
+pub enum ConfigError {
+    #[error("error reading config file (path: {path}, error: {error})")]
+    IoError { path: String, error: anyhow::Error },
+
+    #[error("error parsing config (error: {error})")]
+    ParseError { error: anyhow::Error },
+
+    #[error("unsupported config file format, expected .toml or .json (path: {path})")]
+    UnsupportedFormat { path: String },
+}
+
+/// Description of a scan: what to permutate, how to enrich it and how to
+/// score it.
+#[derive(Debug, Clone, Default, Serialize, Deserialize, PartialEq)]
+#[serde(default, deny_unknown_fields)]
+pub struct ScanConfig {
+    /// Base domains to permutate.
+    pub domains: Vec<String>,
+
+    /// Permutation kinds to generate, all kinds are generated if empty.
+    pub kinds: Vec<PermutationKind>,
+
+    /// Suffixes swapped in by the TLD permutation.
+    pub tlds: TldSet,
+
+    /// Candidates skipped by the TLD permutation.
+    pub tld_exclusions: TldExclusions,
+
+    /// Keyword sets used by the keyword permutation, only the generic
+    /// keywords are used if empty.
+    pub keywords: Vec<KeywordSet>,
+
+    /// Languages of the generic keywords, only English is used if empty.
+    pub locales: Vec<Locale>,
+
+    /// Forms generated by the keyword and dictionary permutations.
+    pub keyword_forms: KeywordForms,
+
+    /// Words used by the dictionary permutation, e.g. an industry
+    /// wordlist.
+    pub dictionary: Vec<String>,
+
+    /// Characters used by the addition and repetition permutations.
+    pub charset: Charset,
+
+    /// How permutations are checked against IDNA.
+    pub idna: IdnaMode,
+
+    /// Glyphs each character can be confused with, merged into or
+    /// replacing the built-in homoglyph table depending on
+    /// `homoglyphs_mode`.
+    pub homoglyphs: BTreeMap<char, String>,
+
+    /// Whether `homoglyphs` is merged into the built-in homoglyph table
+    /// or replaces it.
+    pub homoglyphs_mode: HomoglyphsMode,
+
+    /// Scripts the homoglyph permutation is restricted to, and whether
+    /// it may mix scripts. Listing a script other than Latin merges the
+    /// Cyrillic and Greek lookalikes into the homoglyph table, see
+    /// [`HomoglyphTable::cross_script`].
+    pub homoglyph_options: HomoglyphOptions,
+
+    /// Additional substitutions of each substring, merged into the
+    /// built-in mapped values table.
+    pub mapped: BTreeMap<String, Vec<String>>,
+
+    /// Which occurrences the mapped permutation substitutes.
+    pub mapped_mode: MappedMode,
+
+    pub enrichment: EnrichmentConfig,
+    pub scoring: ScoringConfig,
+
+    /// Weights of the probabilistic typo model, see [`TypoModel`].
+    pub typo: TypoModel,
+}
+
+/// How the homoglyphs of a [`ScanConfig`] are combined with the built-in
+/// homoglyph table.
+#[derive(Debug, Clone, Copy, Default, Serialize, Deserialize, PartialEq, Eq)]
+pub enum HomoglyphsMode {
+    /// Add the glyphs to those of the built-in table.
+    #[default]
+    #[serde(alias = "merge")]
+    Merge,
+
+    /// Only use the glyphs listed, e.g. to restrict the homoglyph
+    /// permutation to a vetted table. The Cyrillic and Greek lookalikes
+    /// of non-Latin scripts are not merged in either.
+    #[serde(alias = "replace")]
+    Replace,
+}
+
+/// How permutations are enriched.
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq, Eq)]
+#[serde(default, deny_unknown_fields)]
+pub struct EnrichmentConfig {
+    /// Maximum number of permutations enriched concurrently.
+    pub concurrency: usize,
+
+    /// Only keep results of permutations that resolve.
+    pub resolving_only: bool,
+
+    /// Enrich the riskiest permutations first.
+    pub prioritize: bool,
+
+    /// Names of the enrichments run on resolving permutations (e.g.
+    /// `"mail_capable"`), or `"all"` for every enrichment supported by
+    /// the features toggled. Only the DNS and HTTP banner lookups are run
+    /// if left empty, see `twistrs::enrich::EnrichmentKinds::parse`.
+    pub enrichments: Vec<String>,
+
+    /// Short-circuit the HTTP banner lookups towards resolved IPs that
+    /// keep timing out, see [`BreakerConfig`].
+    pub breaker: Option<BreakerConfig>,
+}
+
+impl Default for EnrichmentConfig {
+    fn default() -> EnrichmentConfig {
+        EnrichmentConfig {
+            concurrency: DEFAULT_CONCURRENCY,
+            resolving_only: false,
+            prioritize: true,
+            enrichments: vec![],
+            breaker: None,
+        }
+    }
+}
+
+/// Circuit breaker of the HTTP banner lookups, see
+/// `twistrs::breaker::CircuitBreaker`.
+#[derive(Debug, Clone, Copy, Serialize, Deserialize, PartialEq, Eq)]
+#[serde(default, deny_unknown_fields)]
+pub struct BreakerConfig {
+    /// Number of consecutive timeouts opening a circuit.
+    pub failure_threshold: usize,
+
+    /// Seconds a circuit stays open before letting a probe through.
+    pub cooldown_secs: u64,
+
+    /// Seconds after which a lookup times out.
+    pub timeout_secs: u64,
+}
+
+impl Default for BreakerConfig {
+    fn default() -> BreakerConfig {
+        BreakerConfig {
+            failure_threshold: 5,
+            cooldown_secs: 60,
+            timeout_secs: 10,
+        }
+    }
+}
+
+/// How permutations are scored, see [`CompositeScorer`].
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq)]
+#[serde(default, deny_unknown_fields)]
+pub struct ScoringConfig {
+    /// Relative risk of each permutation kind, between `0.0` and `1.0`.
+    /// Kinds that are not listed keep their default weight.
+    pub kind_weights: BTreeMap<PermutationKind, f64>,
+
+    /// Factor applied to the score of likely defensive registrations.
+    pub defensive_weight: f64,
+
+    /// How much edits weigh depending on where they occur in the root
+    /// label.
+    pub position_curve: PositionCurve,
+}
+
+impl Default for ScoringConfig {
+    fn default() -> ScoringConfig {
+        let scorer = CompositeScorer::new(Domain::default());
+
+        ScoringConfig {
+            kind_weights: scorer.kind_weights,
+            defensive_weight: scorer.defensive_weight,
+            position_curve: scorer.position_curve,
+        }
+    }
+}
+
+impl ScanConfig {
+    /// Parse a config from a TOML document.
+    ///
+    /// ### Features
+    ///
+    /// This function requires the `config` feature toggled.
+    #[cfg(feature = "config")]
+    pub fn from_toml(document: &str) -> Result<ScanConfig, Error> {
+        Ok(
+            toml::from_str(document).map_err(|e| ConfigError::ParseError {
+                error: anyhow::Error::msg(e),
+            })?,
+        )
+    }
+
+    /// Parse a config from a JSON document.
+    ///
+    /// ### Features
+    ///
+    /// This function requires the `config` feature toggled.
+    #[cfg(feature = "config")]
+    pub fn from_json(document: &str) -> Result<ScanConfig, Error> {
+        Ok(
+            serde_json::from_str(document).map_err(|e| ConfigError::ParseError {
+                error: anyhow::Error::msg(e),
+            })?,
+        )
+    }
+
+    /// Load a config from a `.toml` or `.json` file.
+    ///
+    /// ### Features
+    ///
+    /// This function requires the `config` feature toggled.
+    #[cfg(feature = "config")]
+    pub fn from_file<P: AsRef<Path>>(path: P) -> Result<ScanConfig, Error> {
+        let file = path.as_ref();
+        let display = file.display().to_string();
+
+        let document = std::fs::read_to_string(file).map_err(|e| ConfigError::IoError {
+            path: display.clone(),
+            error: anyhow::Error::msg(e),
+        })?;
+
+        match file.extension().and_then(|extension| extension.to_str()) {
+            Some("toml") => ScanConfig::from_toml(&document),
+            Some("json") => ScanConfig::from_json(&document),
+            _ => Err(ConfigError::UnsupportedFormat { path: display }.into()),
+        }
+    }
+
+    /// Build the permutation engine described by the config.
+    pub fn engine(&self) -> PermutationEngine {
+        let mut engine = PermutationEngine::new();
+
+        if !self.kinds.is_empty() {
+            engine.kinds = self.kinds.clone();
+        }
+
+        engine.tlds = self.tlds.clone();
+        engine.tld_exclusions = self.tld_exclusions.clone();
+        engine.idna = self.idna;
+
+        if self.homoglyphs_mode == HomoglyphsMode::Replace {
+            engine.homoglyphs = HomoglyphTable::empty();
+        }
+
+        for (c, glyphs) in &self.homoglyphs {
+            engine.homoglyphs.insert(*c, glyphs);
+        }
+
+        if self.homoglyphs_mode == HomoglyphsMode::Merge
+            && self
+                .homoglyph_options
+                .scripts
+                .iter()
+                .any(|script| *script != Script::Latin)
+        {
+            engine.homoglyphs.merge(&HomoglyphTable::cross_script());
+        }
+
+        engine.homoglyph_options = self.homoglyph_options.clone();
+
+        for (key, values) in &self.mapped {
+            engine.mapped.insert(key, values);
+        }
+
+        engine.mapped_mode = self.mapped_mode;
+
+        if !self.keywords.is_empty() {
+            engine.keywords = self.keywords.clone();
+        }
+
+        if !self.locales.is_empty() {
+            engine.locales = self.locales.clone();
+        }
+
+        engine.keyword_forms = self.keyword_forms;
+        engine.dictionary = self.dictionary.clone();
+        engine.charset = self.charset;
+
+        engine
+    }
+}
+
+impl EnrichmentConfig {
+    /// Build the pipeline described by the config, failing if an
+    /// enrichment is unknown or requires a feature that is not toggled.
+    ///
+    /// ### Features
+    ///
+    /// This function requires the `enrich` feature toggled.
+    #[cfg(feature = "enrich")]
+    pub fn pipeline(&self) -> Result<Pipeline, Error> {
+        let mut pipeline = Pipeline::new();
+        pipeline.concurrency = self.concurrency.max(1);
+
+        if !self.enrichments.is_empty() {
+            let kinds = EnrichmentKinds::parse(&self.enrichments.join(","))?;
+
+            if let Some(kind) = (kinds - EnrichmentKinds::supported()).kinds().next() {
+                return Err(EnrichmentError::UnsupportedKind { kind }.into());
+            }
+
+            pipeline.set_enrichments(kinds);
+        }
+
+        if let Some(breaker) = self.breaker {
+            pipeline.set_circuit_breaker(
+                CircuitBreaker::new(
+                    breaker.failure_threshold,
+                    Duration::from_secs(breaker.cooldown_secs),
+                ),
+                Duration::from_secs(breaker.timeout_secs),
+            );
+        }
+
+        Ok(pipeline)
+    }
+}
+
+impl ScoringConfig {
+    /// Build a scorer for permutations of `base`.
+    pub fn scorer(&self, base: Domain) -> CompositeScorer {
+        let mut scorer = CompositeScorer::new(base);
+        scorer.kind_weights.extend(self.kind_weights.clone());
+        scorer.defensive_weight = self.defensive_weight;
+        scorer.position_curve = self.position_curve;
+        scorer
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_config_defaults() {
+        let config = ScanConfig::default();
+
+        assert_eq!(config.engine().kinds, PermutationKind::ALL.to_vec());
+
+        #[cfg(feature = "enrich")]
+        assert_eq!(
+            config.enrichment.pipeline().unwrap().concurrency,
+            Pipeline::new().concurrency
+        );
+        #[cfg(feature = "enrich")]
+        assert!(config
+            .enrichment
+            .pipeline()
+            .unwrap()
+            .circuit_breaker()
+            .is_none());
+        #[cfg(feature = "enrich")]
+        assert_eq!(
+            config.enrichment.pipeline().unwrap().enrichments(),
+            Pipeline::new().enrichments()
+        );
+    }
+
+    #[test]
+    #[cfg(feature = "config")]
+    fn test_config_toml_and_json_are_equivalent() {
+        let from_toml = ScanConfig::from_toml(
+            r#"
+            domains = ["example.com"]
+            kinds = ["Homoglyph", "Tld"]
+
+            [homoglyphs]
+            a = "а"
+
+            [homoglyph_options]
+            scripts = ["Latin"]
+            single_script = true
+
+            [mapped]
+            vv = ["w"]
+
+            [enrichment]
+            concurrency = 10
+            enrichments = ["dns", "http_banner"]
+
+            [enrichment.breaker]
+            failure_threshold = 3
+
+            [scoring.kind_weights]
+            Tld = 0.1
+
+            [scoring.position_curve.Exponential]
+            decay = 0.9
+
+            [typo.char_weights]
+            x = 2.0
+            "#,
+        )
+        .unwrap();
+
+        let from_json = ScanConfig::from_json(
+            r#"{
+                "domains": ["example.com"],
+                "kinds": ["Homoglyph", "Tld"],
+                "homoglyphs": { "a": "а" },
+                "homoglyph_options": { "scripts": ["Latin"], "single_script": true },
+                "mapped": { "vv": ["w"] },
+                "enrichment": {
+                    "concurrency": 10,
+                    "enrichments": ["dns", "http_banner"],
+                    "breaker": { "failure_threshold": 3 }
+                },
+                "scoring": {
+                    "kind_weights": { "Tld": 0.1 },
+                    "position_curve": { "Exponential": { "decay": 0.9 } }
+                },
+                "typo": { "char_weights": { "x": 2.0 } }
+            }"#,
+        )
+        .unwrap();
+
+        assert_eq!(from_toml.domains, from_json.domains);
+        assert_eq!(from_toml.kinds, from_json.kinds);
+        assert_eq!(from_toml.homoglyphs, from_json.homoglyphs);
+        assert_eq!(from_toml.homoglyph_options, from_json.homoglyph_options);
+        assert_eq!(
+            from_toml.engine().homoglyph_options,
+            HomoglyphOptions::script(Script::Latin)
+        );
+
+        let cyrillic = ScanConfig {
+            homoglyph_options: HomoglyphOptions::script(Script::Cyrillic),
+            ..ScanConfig::default()
+        };
+        assert!(cyrillic
+            .engine()
+            .homoglyphs
+            .get('p')
+            .is_some_and(|glyphs| glyphs.contains('р')));
+        assert_eq!(from_toml.mapped, from_json.mapped);
+        assert!(from_toml
+            .engine()
+            .homoglyphs
+            .get('a')
+            .is_some_and(|glyphs| glyphs.starts_with('à') && glyphs.ends_with('а')));
+        assert_eq!(from_toml.enrichment, from_json.enrichment);
+        assert_eq!(from_toml.typo, from_json.typo);
+        assert_eq!(
+            from_toml.typo.kind_weights,
+            TypoModel::default().kind_weights
+        );
+        assert!(from_toml.enrichment.prioritize);
+        assert_eq!(
+            from_toml.enrichment.breaker,
+            Some(BreakerConfig {
+                failure_threshold: 3,
+                ..BreakerConfig::default()
+            })
+        );
+
+        #[cfg(feature = "enrich")]
+        assert_eq!(
+            from_toml
+                .enrichment
+                .pipeline()
+                .unwrap()
+                .circuit_breaker()
+                .map(|breaker| (breaker.failure_threshold, breaker.cooldown)),
+            Some((3, Duration::from_secs(60)))
+        );
+
+        let scorer = from_toml
+            .scoring
+            .scorer(Domain::new("example.com").unwrap());
+        assert_eq!(scorer.kind_weights.get(&PermutationKind::Tld), Some(&0.1));
+        assert_eq!(
+            scorer.kind_weights.get(&PermutationKind::Homoglyph),
+            Some(&1.0)
+        );
+        assert_eq!(from_toml.scoring, from_json.scoring);
+        assert_eq!(
+            scorer.position_curve,
+            PositionCurve::Exponential { decay: 0.9 }
+        );
+    }
+
+    #[test]
+    #[cfg(feature = "config")]
+    fn test_config_homoglyphs_mode() {
+        let merged = ScanConfig::from_toml("[homoglyphs]\na = \"@\"").unwrap();
+        assert_eq!(merged.homoglyphs_mode, HomoglyphsMode::Merge);
+        assert!(merged
+            .engine()
+            .homoglyphs
+            .get('a')
+            .is_some_and(|glyphs| glyphs.starts_with('à') && glyphs.ends_with('@')));
+        assert!(merged.engine().homoglyphs.get('o').is_some());
+
+        let replaced = ScanConfig::from_toml(
+            r#"
+            homoglyphs_mode = "replace"
+            homoglyph_options = { scripts = ["Cyrillic"] }
+
+            [homoglyphs]
+            a = "@"
+            "#,
+        )
+        .unwrap();
+        assert_eq!(replaced.homoglyphs_mode, HomoglyphsMode::Replace);
+        assert_eq!(
+            replaced.engine().homoglyphs.glyphs,
+            BTreeMap::from([('a', String::from("@"))])
+        );
+
+        assert_eq!(
+            ScanConfig::from_json(r#"{"homoglyphs_mode": "Replace"}"#)
+                .unwrap()
+                .homoglyphs_mode,
+            HomoglyphsMode::Replace
+        );
+    }
+
+    #[test]
+    #[cfg(feature = "config")]
+    fn test_config_rejects_unknown_fields() {
+        assert!(ScanConfig::from_toml("concurency = 10").is_err());
+    }
+
+    #[test]
+    #[cfg(feature = "enrich")]
+    fn test_config_enrichments() {
+        let config = |names: &[&str]| EnrichmentConfig {
+            enrichments: names.iter().map(ToString::to_string).collect(),
+            ..EnrichmentConfig::default()
+        };
+
+        assert_eq!(
+            config(&["dns"]).pipeline().unwrap().enrichments(),
+            EnrichmentKinds::DNS
+        );
+        assert_eq!(
+            config(&["all"]).pipeline().unwrap().enrichments(),
+            EnrichmentKinds::supported()
+        );
+        assert!(config(&["nope"]).pipeline().is_err());
+
+        #[cfg(not(feature = "whois"))]
+        assert!(config(&["whois"]).pipeline().is_err());
+    }
+}
@@ -1,11 +1,5 @@
 use phf::phf_map;
 
-#[cfg(feature = "whois_lookup")]
-use whois_rust::WhoIs;
-
-use hyper::client::Client;
-use hyper::client::HttpConnector;
-
 // Include further constants such as dictionaries that are
 // generated during compile time.
 include!(concat!(env!("OUT_DIR"), "/data.rs"));
@@ -16,42 +10,54 @@ lazy_static! {
         &QWERTZ_KEYBOARD_LAYOUT,
         &AZERTY_KEYBOARD_LAYOUT
     ];
+}
 
+// Kept for backwards compatibility, the clients of the enrichments now
+// live in an `EnrichmentContext`.
+#[cfg(feature = "enrich")]
+#[allow(deprecated)]
+mod legacy {
+    use hyper::client::{Client, HttpConnector};
 
-    /// Global HTTP client we use throughout the library
-    pub static ref HTTP_CLIENT: Client<HttpConnector> = Client::builder()
-        .pool_idle_timeout(std::time::Duration::from_secs(30))
-        .http2_only(false)
-        .http1_read_buf_exact_size(1024)
-        .retry_canceled_requests(false)
-        .build(http_connector());
-}
+    #[cfg(feature = "whois")]
+    use whois_rust::WhoIs;
 
-// This is currently a bit annoying, however since the WHOIS lookup table
-// is build at runtime, and is feature-gated, we cannot have this activated
-// within the original lazy_static! macro. We would need to block the
-// entire macro behind the feature gate instead.
-#[cfg(feature = "whois_lookup")]
-lazy_static! {
-    pub static ref WHOIS: WhoIs = WhoIs::from_string(WHOIS_RAW_JSON).unwrap();
-}
+    use crate::enrich::EnrichmentContext;
+
+    lazy_static! {
+        /// Global HTTP client we use throughout the library
+        #[deprecated(note = "use `EnrichmentContext::http_client` instead")]
+        pub static ref HTTP_CLIENT: Client<HttpConnector> = EnrichmentContext::default().http_client;
+    }
 
-/// Internal helper to create an HTTP Connector
-fn http_connector() -> HttpConnector {
-    let mut c = HttpConnector::new();
-    c.set_recv_buffer_size(Some(1024));
-    c.set_connect_timeout(Some(std::time::Duration::new(5, 0)));
-    c.enforce_http(true);
-    c
+    #[cfg(feature = "whois")]
+    lazy_static! {
+        #[deprecated(note = "use `EnrichmentContext::whois` instead")]
+        pub static ref WHOIS: WhoIs = WhoIs::from_string(super::WHOIS_RAW_JSON).unwrap();
+    }
 }
 
+#[cfg(feature = "enrich")]
+#[allow(deprecated)]
+pub use legacy::HTTP_CLIENT;
+
+#[cfg(feature = "whois")]
+#[allow(deprecated)]
+pub use legacy::WHOIS;
+
+/// Default number of in-flight enrichments.
+pub(crate) const DEFAULT_CONCURRENCY: usize = 100;
+
+/// Most tokens of a label that are reordered, see `Domain::token_reorder`.
+pub(crate) const MAX_REORDER_TOKENS: usize = 5;
+
 /// Static list of lowercase ASCII characters.
 pub static ASCII_LOWER: [char; 26] = [
     'a', 'b', 'c', 'd', 'e', 'f', 'g', 'h', 'i', 'j', 'k', 'l', 'm', 'n', 'o', 'p', 'q', 'r', 's',
     't', 'u', 'v', 'w', 'x', 'y', 'z',
 ];
 
-static QWERTY_KEYBOARD_LAYOUT: phf::Map<char, &'static str> = phf_map! {
+pub(crate) static QWERTY_KEYBOARD_LAYOUT: phf::Map<char, &'static str> = phf_map! {
     '1' => "2q",
     '2' => "3wq1",
     '3' => "4ew2",
@@ -168,6 +174,116 @@ static AZERTY_KEYBOARD_LAYOUT: phf::Map<char, &'static str> = phf_map! {
     'n'=> "bhj"
 };
 
+/// Letter rows of the Latin keyboard layouts (QWERTY, QWERTZ and AZERTY),
+/// aligned by physical key, see `Domain::wrong_layout`. Keys that are not
+/// letters on a layout hold punctuation, which is never typed into a
+/// label.
+pub(crate) static KEYBOARD_ROWS: [[&str; 3]; 3] = [
+    ["qwertyuiop", "asdfghjkl;", "zxcvbnm"],
+    ["qwertzuiop", "asdfghjklö", "yxcvbnm"],
+    ["azertyuiop", "qsdfghjklm", "wxcvbn,"],
+];
+
+// Layouts of non-Latin scripts, only applied to labels written in them,
+// see `Domain::keyboard_layouts`.
+pub(crate) static RUSSIAN_KEYBOARD_LAYOUT: phf::Map<char, &'static str> = phf_map! {
+    'й' => "цф",
+    'ц' => "уыфй",
+    'у' => "квыц",
+    'к' => "еаву",
+    'е' => "нпак",
+    'н' => "грпе",
+    'г' => "шорн",
+    'ш' => "щлог",
+    'щ' => "здлш",
+    'з' => "хждщ",
+    'х' => "ъэжз",
+    'ъ' => "эх",
+    'ф' => "цыяй",
+    'ы' => "увчяфц",
+    'в' => "касчыу",
+    'а' => "епмсвк",
+    'п' => "нримае",
+    'р' => "готипн",
+    'о' => "шльтрг",
+    'л' => "щдбьош",
+    'д' => "зжюблщ",
+    'ж' => "хэюдз",
+    'э' => "ъжх",
+    'я' => "ычф",
+    'ч' => "всяы",
+    'с' => "амчв",
+    'м' => "писа",
+    'и' => "ртмп",
+    'т' => "оьир",
+    'ь' => "лбто",
+    'б' => "дюьл",
+    'ю' => "жбд"
+};
+
+pub(crate) static GREEK_KEYBOARD_LAYOUT: phf::Map<char, &'static str> = phf_map! {
+    'ς' => "εσα",
+    'ε' => "ρδσς",
+    'ρ' => "τφδε",
+    'τ' => "υγφρ",
+    'υ' => "θηγτ",
+    'θ' => "ιξηυ",
+    'ι' => "οκξθ",
+    'ο' => "πλκι",
+    'π' => "λο",
+    'α' => "ςσζ",
+    'σ' => "εδχζας",
+    'δ' => "ρφψχσε",
+    'φ' => "τγωψδρ",
+    'γ' => "υηβωφτ",
+    'η' => "θξνβγυ",
+    'ξ' => "ικμνηθ",
+    'κ' => "ολμξι",
+    'λ' => "πκο",
+    'ζ' => "σχα",
+    'χ' => "δψζσ",
+    'ψ' => "φωχδ",
+    'ω' => "γβψφ",
+    'β' => "ηνωγ",
+    'ν' => "ξμβη",
+    'μ' => "κνξ"
+};
+
+pub(crate) static ARABIC_KEYBOARD_LAYOUT: phf::Map<char, &'static str> = phf_map! {
+    'ض' => "صش",
+    'ص' => "ثسشض",
+    'ث' => "قيسص",
+    'ق' => "فبيث",
+    'ف' => "غلبق",
+    'غ' => "عالف",
+    'ع' => "هتاغ",
+    'ه' => "خنتع",
+    'خ' => "حمنه",
+    'ح' => "جكمخ",
+    'ج' => "دطكح",
+    'د' => "طج",
+    'ش' => "صسئض",
+    'س' => "ثيءئشص",
+    'ي' => "قبؤءسث",
+    'ب' => "فلرؤيق",
+    'ل' => "غاربف",
+    'ا' => "عتىلغ",
+    'ت' => "هنةىاع",
+    'ن' => "خموةته",
+    'م' => "حكزونخ",
+    'ك' => "جطظزمح",
+    'ط' => "دظكج",
+    'ئ' => "سءش",
+    'ء' => "يؤئس",
+    'ؤ' => "برءي",
+    'ر' => "لؤب",
+    'ى' => "تةا",
+    'ة' => "نوىت",
+    'و' => "مزةن",
+    'ز' => "كظوم",
+    'ظ' => "طزك"
+};
+
 pub static HOMOGLYPHS: phf::Map<char, &'static str> = phf_map! {
     'a' => "àáâãäåɑạǎăȧą",
     'b' => "dʙɓḃḅḇƅ",
@@ -196,6 +312,59 @@ pub static HOMOGLYPHS: phf::Map<char, &'static str> = phf_map! {
     'z' => "ʐżźᴢƶẓẕⱬ"
 };
 
+/// Cyrillic and Greek lookalikes of Latin letters, kept apart from
+/// `HOMOGLYPHS` as most registries do not allow mixing them with Latin.
+pub static CROSS_SCRIPT_HOMOGLYPHS: phf::Map<char, &'static str> = phf_map! {
+    'a' => "аα",
+    'c' => "сϲ",
+    'd' => "ԁ",
+    'e' => "е",
+    'h' => "һ",
+    'i' => "іι",
+    'j' => "ј",
+    'k' => "кκ",
+    'l' => "ӏ",
+    'o' => "оο",
+    'p' => "рρ",
+    'q' => "ԛ",
+    'r' => "г",
+    's' => "ѕ",
+    't' => "τ",
+    'u' => "υ",
+    'v' => "ѵν",
+    'w' => "ѡω",
+    'x' => "хχ",
+    'y' => "уγ"
+};
+
+/// Cyrillic, Greek and Armenian lookalikes of Latin letters that IDNA
+/// (UTS #46) accepts as-is, i.e. lowercase and not mapped onto other
+/// characters, used by the IDN homograph permutation.
+pub static IDN_CONFUSABLES: phf::Map<char, &'static str> = phf_map! {
+    'a' => "аα",
+    'c' => "с",
+    'd' => "ԁ",
+    'e' => "е",
+    'g' => "ց",
+    'h' => "һհ",
+    'i' => "іι",
+    'j' => "јյ",
+    'k' => "кκ",
+    'l' => "ӏ",
+    'n' => "ոη",
+    'o' => "оοօ",
+    'p' => "рρ",
+    'q' => "ԛզ",
+    'r' => "г",
+    's' => "ѕ",
+    't' => "τ",
+    'u' => "υս",
+    'v' => "ѵν",
+    'w' => "ѡωա",
+    'x' => "хχ",
+    'y' => "уγ"
+};
+
 pub static MAPPED_VALUES: phf::Map<&'static str, &'static [&'static str]> = phf_map! {
     "a" => &["4"],
     "b" => &["8", "6"],
@@ -237,4 +406,330 @@ pub static MAPPED_VALUES: phf::Map<&'static str, &'static [&'static str]> = phf_
     "oo" => &["00"],
 };
 
+/// Character sequences that OCR engines (and readers skimming a low
+/// resolution rendering) commonly confuse, substituted in both directions.
+pub static OCR_CONFUSIONS: [(&str, &str); 7] = [
+    ("rn", "m"),
+    ("cl", "d"),
+    ("vv", "w"),
+    ("l", "i"),
+    ("l", "1"),
+    ("o", "0"),
+    ("li", "h"),
+];
+
+/// Numbers and their English spelling, swapped for one another by the
+/// spelling permutation.
+pub static NUMBER_WORDS: [(&str, &str); 14] = [
+    ("0", "zero"),
+    ("1", "one"),
+    ("2", "two"),
+    ("3", "three"),
+    ("4", "four"),
+    ("5", "five"),
+    ("6", "six"),
+    ("7", "seven"),
+    ("8", "eight"),
+    ("9", "nine"),
+    ("10", "ten"),
+    ("11", "eleven"),
+    ("12", "twelve"),
+    ("100", "hundred"),
+];
+
+/// Groups of English words that sound alike, swapped for one another by
+/// the homophone permutation.
+#[cfg(feature = "phonetic")]
+pub static HOMOPHONES: [&[&str]; 65] = [
+    &["air", "heir"],
+    &["ate", "eight"],
+    &["band", "banned"],
+    &["bare", "bear"],
+    &["be", "bee"],
+    &["blew", "blue"],
+    &["board", "bored"],
+    &["brake", "break"],
+    &["buy", "by", "bye"],
+    &["cache", "cash"],
+    &["cell", "sell"],
+    &["cent", "scent", "sent"],
+    &["cite", "sight", "site"],
+    &["dear", "deer"],
+    &["dew", "due"],
+    &["fair", "fare"],
+    &["find", "fined"],
+    &["flour", "flower"],
+    &["for", "fore", "four"],
+    &["grate", "great"],
+    &["guessed", "guest"],
+    &["hair", "hare"],
+    &["hole", "whole"],
+    &["hour", "our"],
+    &["knew", "new"],
+    &["knight", "night"],
+    &["knot", "not"],
+    &["know", "no"],
+    &["made", "maid"],
+    &["mail", "male"],
+    &["meat", "meet"],
+    &["mind", "mined"],
+    &["one", "won"],
+    &["pail", "pale"],
+    &["pair", "pare", "pear"],
+    &["peace", "piece"],
+    &["plain", "plane"],
+    &["pole", "poll"],
+    &["principal", "principle"],
+    &["rain", "reign", "rein"],
+    &["read", "red"],
+    &["right", "rite", "write"],
+    &["road", "rode"],
+    &["role", "roll"],
+    &["root", "route"],
+    &["sail", "sale"],
+    &["scene", "seen"],
+    &["sea", "see"],
+    &["so", "sew", "sow"],
+    &["some", "sum"],
+    &["son", "sun"],
+    &["stair", "stare"],
+    &["steal", "steel"],
+    &["suite", "sweet"],
+    &["tail", "tale"],
+    &["tea", "tee"],
+    &["their", "there"],
+    &["to", "too", "two"],
+    &["wait", "weight"],
+    &["ware", "wear", "where"],
+    &["way", "weigh"],
+    &["weak", "week"],
+    &["weather", "whether"],
+    &["wood", "would"],
+    &["yore", "your"],
+];
+
+/// Most registered TLDs, appended by the TLD-append permutation on top of
+/// the TLD of the domain.
+pub static POPULAR_TLDS: [&str; 8] = ["com", "net", "org", "info", "co", "io", "xyz", "online"];
+
+/// Frequent English misspellings, keyed by the word they misspell, swapped
+/// in by the misspelling permutation. Drawn from Wikipedia's list of
+/// common misspellings.
+pub static MISSPELLINGS: [(&str, &[&str]); 62] = [
+    ("absence", &["absense", "abscence"]),
+    ("accommodate", &["accomodate", "acommodate"]),
+    ("achieve", &["acheive"]),
+    ("acquire", &["aquire", "adquire"]),
+    ("address", &["adress", "addres"]),
+    ("advertise", &["advertize"]),
+    ("apparent", &["apparant", "aparent"]),
+    ("argument", &["arguement"]),
+    ("basically", &["basicly"]),
+    ("beginning", &["begining"]),
+    ("believe", &["beleive", "belive"]),
+    ("business", &["buisness", "busness", "bussiness"]),
+    ("calendar", &["calender"]),
+    ("category", &["catagory"]),
+    ("cemetery", &["cemetary"]),
+    ("colleague", &["collegue"]),
+    ("committee", &["commitee", "comittee"]),
+    ("conscious", &["concious"]),
+    ("definitely", &["definately", "definitly"]),
+    ("dependent", &["dependant"]),
+    ("embarrass", &["embarass"]),
+    ("environment", &["enviroment"]),
+    ("existence", &["existance"]),
+    ("experience", &["experiance"]),
+    ("foreign", &["foriegn"]),
+    ("forward", &["foward"]),
+    ("friend", &["freind"]),
+    ("government", &["goverment"]),
+    ("grammar", &["grammer"]),
+    ("guarantee", &["garantee", "gaurantee"]),
+    ("harass", &["harrass"]),
+    ("immediately", &["immediatly"]),
+    ("independent", &["independant"]),
+    ("knowledge", &["knowlege"]),
+    ("license", &["lisence", "lisense"]),
+    ("maintenance", &["maintainance", "maintenence"]),
+    ("millennium", &["millenium"]),
+    ("necessary", &["neccessary", "necessery"]),
+    ("neighbor", &["nieghbor"]),
+    ("occasion", &["occassion"]),
+    ("occurred", &["occured"]),
+    ("official", &["offical"]),
+    ("possession", &["posession"]),
+    ("privilege", &["privelege", "priviledge"]),
+    ("professional", &["proffesional", "profesional"]),
+    ("publicly", &["publically"]),
+    ("receipt", &["reciept"]),
+    ("receive", &["recieve"]),
+    ("recommend", &["recomend", "reccommend"]),
+    ("reference", &["refrence"]),
+    ("relevant", &["relevent"]),
+    ("restaurant", &["restaraunt"]),
+    ("schedule", &["shedule"]),
+    ("secretary", &["secratary"]),
+    ("separate", &["seperate"]),
+    ("success", &["sucess"]),
+    ("successful", &["succesful", "successfull"]),
+    ("surprise", &["suprise"]),
+    ("tomorrow", &["tommorow", "tomorow"]),
+    ("truly", &["truely"]),
+    ("until", &["untill"]),
+    ("weird", &["wierd"]),
+];
+
+/// Romanizations of Cyrillic letters (Russian and Ukrainian), the first
+/// being the most common one.
+pub static CYRILLIC_TO_LATIN: phf::Map<char, &'static [&'static str]> = phf_map! {
+    'а' => &["a"],
+    'б' => &["b"],
+    'в' => &["v"],
+    'г' => &["g", "h"],
+    'ґ' => &["g"],
+    'д' => &["d"],
+    'е' => &["e"],
+    'є' => &["ye", "e"],
+    'ё' => &["yo", "e"],
+    'ж' => &["zh", "j"],
+    'з' => &["z"],
+    'и' => &["i"],
+    'і' => &["i"],
+    'ї' => &["yi", "i"],
+    'й' => &["y", "i", "j"],
+    'к' => &["k"],
+    'л' => &["l"],
+    'м' => &["m"],
+    'н' => &["n"],
+    'о' => &["o"],
+    'п' => &["p"],
+    'р' => &["r"],
+    'с' => &["s"],
+    'т' => &["t"],
+    'у' => &["u"],
+    'ф' => &["f"],
+    'х' => &["kh", "h", "x"],
+    'ц' => &["ts", "c"],
+    'ч' => &["ch"],
+    'ш' => &["sh"],
+    'щ' => &["shch", "sch"],
+    'ъ' => &[""],
+    'ы' => &["y"],
+    'ь' => &[""],
+    'э' => &["e"],
+    'ю' => &["yu", "iu"],
+    'я' => &["ya", "ia"],
+};
+
+/// Romanizations of Greek letters, the first being the most common one.
+pub static GREEK_TO_LATIN: phf::Map<char, &'static [&'static str]> = phf_map! {
+    'α' => &["a"],
+    'ά' => &["a"],
+    'β' => &["v", "b"],
+    'γ' => &["g"],
+    'δ' => &["d"],
+    'ε' => &["e"],
+    'έ' => &["e"],
+    'ζ' => &["z"],
+    'η' => &["i", "e"],
+    'ή' => &["i", "e"],
+    'θ' => &["th"],
+    'ι' => &["i"],
+    'ί' => &["i"],
+    'κ' => &["k"],
+    'λ' => &["l"],
+    'μ' => &["m"],
+    'ν' => &["n"],
+    'ξ' => &["x", "ks"],
+    'ο' => &["o"],
+    'ό' => &["o"],
+    'π' => &["p"],
+    'ρ' => &["r"],
+    'σ' => &["s"],
+    'ς' => &["s"],
+    'τ' => &["t"],
+    'υ' => &["y", "u"],
+    'ύ' => &["y", "u"],
+    'φ' => &["f", "ph"],
+    'χ' => &["ch", "kh", "h"],
+    'ψ' => &["ps"],
+    'ω' => &["o"],
+    'ώ' => &["o"],
+};
+
+/// Cyrillic spelling of Latin letters and digraphs, matched longest first.
+pub static LATIN_TO_CYRILLIC: phf::Map<&'static str, &'static str> = phf_map! {
+    "shch" => "щ",
+    "sh" => "ш",
+    "ch" => "ч",
+    "zh" => "ж",
+    "kh" => "х",
+    "ts" => "ц",
+    "ya" => "я",
+    "yu" => "ю",
+    "yo" => "ё",
+    "a" => "а",
+    "b" => "б",
+    "c" => "к",
+    "d" => "д",
+    "e" => "е",
+    "f" => "ф",
+    "g" => "г",
+    "h" => "х",
+    "i" => "и",
+    "j" => "й",
+    "k" => "к",
+    "l" => "л",
+    "m" => "м",
+    "n" => "н",
+    "o" => "о",
+    "p" => "п",
+    "q" => "к",
+    "r" => "р",
+    "s" => "с",
+    "t" => "т",
+    "u" => "у",
+    "v" => "в",
+    "w" => "в",
+    "x" => "кс",
+    "y" => "ы",
+    "z" => "з",
+};
+
+/// Greek spelling of Latin letters and digraphs, matched longest first.
+pub static LATIN_TO_GREEK: phf::Map<&'static str, &'static str> = phf_map! {
+    "th" => "θ",
+    "ph" => "φ",
+    "ps" => "ψ",
+    "ch" => "χ",
+    "ks" => "ξ",
+    "a" => "α",
+    "b" => "β",
+    "c" => "κ",
+    "d" => "δ",
+    "e" => "ε",
+    "f" => "φ",
+    "g" => "γ",
+    "h" => "χ",
+    "i" => "ι",
+    "j" => "ι",
+    "k" => "κ",
+    "l" => "λ",
+    "m" => "μ",
+    "n" => "ν",
+    "o" => "ο",
+    "p" => "π",
+    "q" => "κ",
+    "r" => "ρ",
+    "s" => "σ",
+    "t" => "τ",
+    "u" => "υ",
+    "v" => "β",
+    "w" => "ω",
+    "x" => "ξ",
+    "y" => "υ",
+    "z" => "ζ",
+};
+
 pub static VOWELS: [char; 5] = ['a', 'e', 'i', 'o', 'u'];
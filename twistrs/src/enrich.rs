@@ -3,7 +3,12 @@
 //! including:
 //!
 //! * DNS resolution (through HTTP/80 lookup).
+//! * DNS records along with their TTLs (A, AAAA, CNAME, MX, NS, TXT and SOA).
+//! * HTTP(S) server banners, optionally following redirects.
+//! * Language and charset of the content served over HTTP.
 //! * Open SMTP server (for email misdirects).
+//! * Mail exchangers reachable on the SMTP, submission and implicit TLS
+//!   ports.
 //!
 //! Example:
 //!
@@ -18,46 +23,123 @@
 //! ```
 //!
 //! Note that the enrichment module is independent from the
-//! permutation module and can be used with any given FQDN.
-use serde::Serialize;
+//! permutation module and can be used with any given FQDN. Lists of
+//! FQDNs (e.g. from certificate transparency or newly registered domain
+//! feeds) can be enriched in bulk through [`from_reader`].
+//!
+//! The clients used by the lookups (e.g. HTTP client or DNS resolver)
+//! are held by an [`EnrichmentContext`]. Lookups use a default context
+//! unless one is supplied through their `_with_context` counterpart.
+//!
+//! ### Features
+//!
+//! This module requires the `enrich` feature toggled. DNS record, HTTPS,
+//! SMTP, `GeoIP` and WHOIS lookups additionally require the `dns`, `tls`,
+//! `smtp`, `geoip` and `whois` features respectively.
+use serde::de::value::MapAccessDeserializer;
+use serde::de::{self, MapAccess, SeqAccess, Visitor};
+use serde::{Deserialize, Deserializer, Serialize};
+use std::collections::{BTreeMap, BTreeSet};
+use std::fmt;
+use std::future::ready;
+use std::future::Future;
 use std::net::IpAddr;
+use std::str::FromStr;
+use std::time::Duration;
+#[cfg(feature = "whois")]
+use std::time::Instant;
+
+use bitflags::bitflags;
+use futures::stream::{self, Stream, StreamExt};
+use tokio::io::{AsyncBufRead, AsyncBufReadExt};
+#[cfg(all(feature = "dns", feature = "tls"))]
+use tokio::io::{AsyncWrite, AsyncWriteExt};
 
-#[cfg(feature = "geoip_lookup")]
+#[cfg(feature = "geoip")]
 use maxminddb;
-#[cfg(feature = "geoip_lookup")]
+#[cfg(feature = "geoip")]
 use maxminddb::geoip2;
 
-#[cfg(feature = "whois_lookup")]
-use whois_rust::WhoIsLookupOptions;
+#[cfg(feature = "whois")]
+use whois_rust::{WhoIs, WhoIsLookupOptions, WhoIsServerValue};
 
-#[cfg(feature = "smtp_lookup")]
+#[cfg(feature = "smtp")]
 use async_smtp::{Envelope, SendableEmail, SmtpClient, SmtpTransport};
 
-#[cfg(feature = "smtp_lookup")]
-use tokio::{io::BufStream, net::TcpStream};
+#[cfg(feature = "smtp")]
+use tokio::io::BufStream;
+#[cfg(any(feature = "dns", feature = "tls", feature = "smtp"))]
+use tokio::net::TcpStream;
 
-use hyper::{Body, Request};
+#[cfg(any(feature = "tls", feature = "whois"))]
+use std::sync::Arc;
+#[cfg(feature = "tls")]
+use tokio_rustls::TlsConnector;
+
+#[cfg(feature = "tls")]
+use crate::tls::{self, Sni, TlsOptions};
+
+#[cfg(feature = "dns")]
+use trust_dns_resolver::{error::ResolveErrorKind, proto::rr::RecordType, TokioAsyncResolver};
+
+use hyper::body::HttpBody;
+use hyper::client::{Client, HttpConnector};
+use hyper::header::{CONTENT_LANGUAGE, CONTENT_TYPE, LOCATION, SERVER};
+use hyper::{Body, Request, Uri};
 use tokio::net;
 
-use crate::constants::HTTP_CLIENT;
+use crate::breaker::{BreakerError, CircuitBreaker};
 use crate::error::Error;
+use crate::permutate::{Domain, DomainRef};
+use crate::pipeline::Pipeline;
+use crate::resolver::ReverseIp;
+use crate::scoring;
+
+#[cfg(feature = "whois")]
+use crate::constants::WHOIS_RAW_JSON;
 
-#[cfg(feature = "whois_lookup")]
-use crate::constants::WHOIS;
+/// Timeout of a single `WhoIs` lookup.
+#[cfg(feature = "whois")]
+const WHOIS_TIMEOUT: Duration = Duration::from_secs(5);
+
+/// Timeout of a single HTTP banner lookup.
+const HTTP_BANNER_TIMEOUT: Duration = Duration::from_secs(10);
+
+/// Timeout of a single JARM fingerprinting, covering all of its probes.
+#[cfg(feature = "jarm")]
+const JARM_TIMEOUT: Duration = Duration::from_secs(30);
+
+/// Timeout of a single SMTP check.
+#[cfg(feature = "smtp")]
+const SMTP_TIMEOUT: Duration = Duration::from_secs(30);
 
 #[derive(thiserror::Error, Debug)]
 pub enum EnrichmentError {
     #[error("error resolving domain name (domain: {domain})")]
     DnsResolutionError { domain: String },
 
-    #[cfg(feature = "whois_lookup")]
+    #[cfg(feature = "dns")]
+    #[error("error looking up dns records (domain: {domain}, error: {error})")]
+    DnsRecordError {
+        domain: String,
+        error: anyhow::Error,
+    },
+
+    #[cfg(feature = "whois")]
     #[error("error resolving domain name (domain: {domain}, error: {error})")]
     WhoIsLookupError {
         domain: String,
         error: whois_rust::WhoIsError,
     },
 
-    #[cfg(feature = "smtp_lookup")]
+    #[cfg(feature = "whois")]
+    #[error("whois lookup task failed (domain: {domain}, error: {error})")]
+    WhoIsTaskError {
+        domain: String,
+        error: anyhow::Error,
+    },
+
+    #[cfg(feature = "smtp")]
     #[error("error performing smtp lookup (domain: {domain}, error: {error})")]
     SmtpLookupError {
         domain: String,
@@ -70,11 +152,318 @@ pub enum EnrichmentError {
         error: anyhow::Error,
     },
 
+    #[error("http probe timed out (domain: {domain}, timeout: {timeout:?})")]
+    HttpProbeTimeout { domain: String, timeout: Duration },
+
+    #[cfg(feature = "tls")]
+    #[error("error performing https banner lookup (domain: {domain}, error: {error})")]
+    HttpsBannerError {
+        domain: String,
+        error: anyhow::Error,
+    },
+
     #[error("error performing geoip lookup (domain: {domain}, error: {error})")]
     GeoIpLookupError {
         domain: String,
         error: anyhow::Error,
     },
+
+    #[error("error reading fqdns (error: {error})")]
+    ReadError { error: anyhow::Error },
+
+    #[error("unknown enrichment kind (name: {name})")]
+    UnknownKind { name: String },
+
+    #[error("enrichment kind requires a feature that is not toggled (kind: {kind:?})")]
+    UnsupportedKind { kind: EnrichmentKind },
+
+    /// Some of the enrichments of [`DomainMetadata::run`] failed, where
+    /// `metadata` holds the results of those that succeeded.
+    #[error("enrichments failed (domain: {}, kinds: {:?})", .metadata.fqdn, .errors.iter().map(|(kind, _)| kind).collect::<Vec<_>>())]
+    PartialFailure {
+        metadata: Box<DomainMetadata>,
+        errors: Vec<(EnrichmentKind, Error)>,
+    },
+}
+
+/// Enrichment check that can be run on a domain, see
+/// [`DomainMetadata::run`].
+#[derive(Clone, Copy, Debug, Serialize, Deserialize, Hash, Eq, PartialEq, Ord, PartialOrd)]
+pub enum EnrichmentKind {
+    Dns,
+    HttpBanner,
+
+    /// Requires the `dns` feature toggled.
+    DnsRecords,
+
+    /// Requires the `dns` feature toggled.
+    MailCapable,
+
+    /// Requires the `jarm` feature toggled.
+    Jarm,
+
+    /// Requires the `smtp` feature toggled.
+    Mx,
+
+    /// Requires the `whois` feature toggled.
+    WhoIs,
+}
+
+impl EnrichmentKind {
+    /// Every enrichment kind, in the order they are run.
+    pub const ALL: [EnrichmentKind; 7] = [
+        EnrichmentKind::Dns,
+        EnrichmentKind::HttpBanner,
+        EnrichmentKind::DnsRecords,
+        EnrichmentKind::MailCapable,
+        EnrichmentKind::Jarm,
+        EnrichmentKind::Mx,
+        EnrichmentKind::WhoIs,
+    ];
+}
+
+impl FromStr for EnrichmentKind {
+    type Err = Error;
+
+    /// Parse a kind by name, ignoring case and separators (e.g. `dns`,
+    /// `http_banner` or `HttpBanner`).
+    fn from_str(name: &str) -> Result<EnrichmentKind, Error> {
+        let normalized: String = name
+            .trim()
+            .chars()
+            .filter(|c| *c != '_' && *c != '-')
+            .collect();
+
+        EnrichmentKind::ALL
+            .iter()
+            .copied()
+            .find(|kind| format!("{kind:?}").eq_ignore_ascii_case(&normalized))
+            .ok_or_else(|| {
+                EnrichmentError::UnknownKind {
+                    name: name.to_string(),
+                }
+                .into()
+            })
+    }
+}
+
+bitflags! {
+    /// Set of enrichment checks, e.g. `EnrichmentKinds::DNS |
+    /// EnrichmentKinds::HTTP_BANNER`.
+    #[derive(Clone, Copy, Debug, Default, Hash, Eq, PartialEq)]
+    pub struct EnrichmentKinds: u8 {
+        const DNS = 1;
+        const HTTP_BANNER = 1 << 1;
+        const MX = 1 << 2;
+        const WHOIS = 1 << 3;
+        const DNS_RECORDS = 1 << 4;
+        const MAIL_CAPABLE = 1 << 5;
+        const JARM = 1 << 6;
+    }
+}
+
+impl From<EnrichmentKind> for EnrichmentKinds {
+    fn from(kind: EnrichmentKind) -> EnrichmentKinds {
+        match kind {
+            EnrichmentKind::Dns => EnrichmentKinds::DNS,
+            EnrichmentKind::HttpBanner => EnrichmentKinds::HTTP_BANNER,
+            EnrichmentKind::DnsRecords => EnrichmentKinds::DNS_RECORDS,
+            EnrichmentKind::MailCapable => EnrichmentKinds::MAIL_CAPABLE,
+            EnrichmentKind::Jarm => EnrichmentKinds::JARM,
+            EnrichmentKind::Mx => EnrichmentKinds::MX,
+            EnrichmentKind::WhoIs => EnrichmentKinds::WHOIS,
+        }
+    }
+}
+
+impl FromIterator<EnrichmentKind> for EnrichmentKinds {
+    fn from_iter<I: IntoIterator<Item = EnrichmentKind>>(kinds: I) -> EnrichmentKinds {
+        kinds
+            .into_iter()
+            .fold(EnrichmentKinds::empty(), |set, kind| set | kind.into())
+    }
+}
+
+impl EnrichmentKinds {
+    /// Parse a comma-separated list of kinds (e.g. `dns,http_banner`), or
+    /// `all` for every kind supported by the features toggled.
+    pub fn parse(names: &str) -> Result<EnrichmentKinds, Error> {
+        if names.trim().eq_ignore_ascii_case("all") {
+            return Ok(EnrichmentKinds::supported());
+        }
+
+        names
+            .split(',')
+            .filter(|name| !name.trim().is_empty())
+            .map(EnrichmentKind::from_str)
+            .collect()
+    }
+
+    /// The kinds that can be run with the features toggled, the others
+    /// failing with `UnsupportedKind`.
+    pub fn supported() -> EnrichmentKinds {
+        let mut supported = EnrichmentKinds::DNS | EnrichmentKinds::HTTP_BANNER;
+
+        if cfg!(feature = "dns") {
+            supported |= EnrichmentKinds::DNS_RECORDS | EnrichmentKinds::MAIL_CAPABLE;
+        }
+        if cfg!(feature = "jarm") {
+            supported |= EnrichmentKinds::JARM;
+        }
+        if cfg!(feature = "smtp") {
+            supported |= EnrichmentKinds::MX;
+        }
+        if cfg!(feature = "whois") {
+            supported |= EnrichmentKinds::WHOIS;
+        }
+
+        supported
+    }
+
+    /// The kinds in the set, in the order they are run.
+    pub fn kinds(self) -> impl Iterator<Item = EnrichmentKind> {
+        EnrichmentKind::ALL
+            .into_iter()
+            .filter(move |kind| self.contains((*kind).into()))
+    }
+}
+
+/// Clients used by the enrichments, so that embedders can supply their
+/// own configured ones (e.g. behind a proxy, or with a custom resolver)
+/// through the `_with_context` enrichments, such as
+/// [`DomainMetadata::run_with_context`].
+///
+/// A context is cheap to clone, its clients being shared by the clones.
+#[derive(Clone)]
+pub struct EnrichmentContext {
+    /// Client of the HTTP banner lookups.
+    pub http_client: Client<HttpConnector>,
+
+    /// Resolver of the DNS record lookups, or `None` to create one from
+    /// the system configuration on each lookup.
+    #[cfg(feature = "dns")]
+    pub resolver: Option<TokioAsyncResolver>,
+
+    /// Client of the `WhoIs` lookups, holding the servers of each TLD.
+    #[cfg(feature = "whois")]
+    pub whois: Arc<WhoIs>,
+
+    /// Breaker short-circuiting the lookups towards destinations that keep
+    /// timing out, see [`CircuitBreaker`].
+    pub breaker: Option<CircuitBreaker>,
+
+    /// Ports the mail capability lookups probe the mail exchangers on.
+    #[cfg(feature = "dns")]
+    pub mail: MailOptions,
+}
+
+impl Default for EnrichmentContext {
+    fn default() -> EnrichmentContext {
+        let mut connector = HttpConnector::new();
+        connector.set_recv_buffer_size(Some(1024));
+        connector.set_connect_timeout(Some(Duration::from_secs(5)));
+        connector.enforce_http(true);
+
+        EnrichmentContext {
+            http_client: Client::builder()
+                .pool_idle_timeout(Duration::from_secs(30))
+                .http2_only(false)
+                .http1_read_buf_exact_size(1024)
+                .retry_canceled_requests(false)
+                .build(connector),
+            #[cfg(feature = "dns")]
+            resolver: None,
+            #[cfg(feature = "whois")]
+            whois: Arc::new(WhoIs::from_string(WHOIS_RAW_JSON).unwrap()),
+            breaker: None,
+            #[cfg(feature = "dns")]
+            mail: MailOptions::default(),
+        }
+    }
+}
+
+impl fmt::Debug for EnrichmentContext {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("EnrichmentContext").finish_non_exhaustive()
+    }
+}
+
+impl EnrichmentContext {
+    /// The configured resolver, or one created from the system
+    /// configuration.
+    #[cfg(feature = "dns")]
+    fn resolver(&self) -> Result<TokioAsyncResolver, trust_dns_resolver::error::ResolveError> {
+        match &self.resolver {
+            Some(resolver) => Ok(resolver.clone()),
+            None => TokioAsyncResolver::tokio_from_system_conf(),
+        }
+    }
+
+    /// Run `lookup` of `kind` towards `destination` through the circuit
+    /// breaker, if any, timing it out after `timeout` either way.
+    async fn guarded<T, F>(
+        &self,
+        kind: EnrichmentKind,
+        destination: &str,
+        timeout: Duration,
+        lookup: F,
+    ) -> Result<T, Error>
+    where
+        F: Future<Output = Result<T, Error>>,
+    {
+        match &self.breaker {
+            Some(breaker) => breaker.guard(kind, destination, timeout, lookup).await,
+            None => tokio::time::timeout(timeout, lookup)
+                .await
+                .unwrap_or_else(|_| {
+                    Err(BreakerError::Timeout {
+                        kind,
+                        destination: destination.to_string(),
+                    }
+                    .into())
+                }),
+        }
+    }
+}
+
+lazy_static! {
+    /// Context of the enrichments performed without one.
+    static ref DEFAULT_CONTEXT: EnrichmentContext = EnrichmentContext::default();
+}
+
+#[cfg(feature = "whois")]
+lazy_static! {
+    /// Host of the compiled-in `WhoIs` server of each TLD listing one.
+    static ref WHOIS_SERVERS: BTreeMap<String, String> = whois_servers(WHOIS_RAW_JSON);
+}
+
+/// Compiled-in `WhoIs` server of a TLD, either its host or its host and
+/// query format. TLDs without a server are listed as `null`.
+#[cfg(feature = "whois")]
+#[derive(Deserialize)]
+#[serde(untagged)]
+enum WhoIsServer {
+    Host(String),
+    Server { host: String },
+    Unknown(de::IgnoredAny),
+}
+
+/// Hosts of the `WhoIs` servers listed in `raw`, keyed by TLD.
+#[cfg(feature = "whois")]
+fn whois_servers(raw: &str) -> BTreeMap<String, String> {
+    let servers: BTreeMap<String, WhoIsServer> = serde_json::from_str(raw).unwrap_or_default();
+
+    servers
+        .into_iter()
+        .filter_map(|(tld, server)| {
+            let host = match server {
+                WhoIsServer::Host(host) | WhoIsServer::Server { host } => host,
+                WhoIsServer::Unknown(_) => return None,
+            };
+
+            Some((tld.to_lowercase(), host.to_lowercase()))
+        })
+        .collect()
 }
 
 /// Container to store interesting FQDN metadata
@@ -87,14 +476,18 @@ pub enum EnrichmentError {
 /// **N.B**—there will be cases where a single
 /// domain can have multiple `DomainMetadata`
 /// instancees associated with it.
-#[derive(Debug, Clone, Serialize, Default)]
+#[derive(Debug, Clone, Serialize, Deserialize, Default, Hash, Eq, PartialEq)]
 pub struct DomainMetadata {
     /// The domain that is being enriched.
     pub fqdn: String,
 
-    /// Any IPv4 and IPv6 ips that were discovered during
-    /// domain resolution.
-    pub ips: Option<Vec<IpAddr>>,
+    /// DNS records of the domain along with their TTLs, including the
+    /// addresses discovered during domain resolution.
+    ///
+    /// Records persisted before the records were structured carry a flat
+    /// `ips` list instead, which is read into the A and AAAA records.
+    #[serde(default, alias = "ips", deserialize_with = "dns_or_legacy_ips")]
+    pub dns: DnsRecords,
 
     /// Any SMTP message data (if any) that was returned by
     /// an SMTP server.
@@ -103,16 +496,62 @@ pub struct DomainMetadata {
     /// HTTP server banner data extracted.
     pub http_banner: Option<String>,
 
+    /// HTTP response data gathered while following redirects, see
+    /// [`DomainMetadata::http_banner_following`].
+    pub http: Option<HttpMetadata>,
+
+    /// TLS handshake data gathered by [`DomainMetadata::https_banner`].
+    pub tls: Option<TlsMetadata>,
+
+    /// JARM fingerprint of the TLS stack served on port 443, see
+    /// [`DomainMetadata::jarm_fingerprint`].
+    pub jarm: Option<String>,
+
+    /// Other domains found while enriching, such as the names listed on
+    /// the certificate served by [`DomainMetadata::https_banner`].
+    pub related_domains: Option<Vec<RelatedDomain>>,
+
+    /// Domains co-hosted on each of the IPs, see
+    /// [`DomainMetadata::reverse_ip_lookup`].
+    pub neighbors: Option<BTreeMap<IpAddr, Vec<String>>>,
+
+    /// Whether the domain shares a nameserver with the domain it was
+    /// compared against, a near-certain sign of a defensive registration.
+    /// See [`DomainMetadata::compare_nameservers`].
+    pub shared_nameservers: Option<bool>,
+
+    /// Whether at least one of the mail exchangers of the domain accepts
+    /// connections on one of the probed ports, see
+    /// [`DomainMetadata::mail_capability`].
+    pub mail_capable: Option<bool>,
+
+    /// Whether at least one of the mail exchangers of the domain accepts
+    /// connections, keyed by each probed port, see [`MailOptions`].
+    pub mail_ports: Option<BTreeMap<u16, bool>>,
+
     /// IP addresses resolved through `GeoIP` lookup to `City`, `Country`, `Continent`.
     pub geo_ip_lookups: Option<Vec<(IpAddr, String)>>,
 
+    /// IP addresses resolved through the `GeoIP` Anonymous-IP database.
+    pub anonymous_ip_lookups: Option<Vec<(IpAddr, AnonymousIpMetadata)>>,
+
+    /// IP addresses resolved through the `GeoIP` ISP database.
+    pub isp_lookups: Option<Vec<(IpAddr, IspMetadata)>>,
+
     /// Block of text returned by the `WhoIs` registrar.
     pub who_is_lookup: Option<String>,
+
+    /// Raw and parsed `WhoIs` response, see [`DomainMetadata::whois_lookup_with`].
+    pub who_is: Option<WhoIsMetadata>,
+
+    /// Unix timestamp (in seconds) until which the record is served from
+    /// an [`EnrichmentCache`](crate::storage::EnrichmentCache).
+    pub cached_until: Option<u64>,
 }
 
 /// SMTP specific metadata generated by a partic
 /// ular domain.
-#[derive(Debug, Clone, Serialize)]
+#[derive(Debug, Clone, Serialize, Deserialize, Hash, Eq, PartialEq)]
 pub struct SmtpMetadata {
     /// Whether the email was dispatched successfully
     pub is_positive: bool,
@@ -121,101 +560,1046 @@ pub struct SmtpMetadata {
     pub message: String,
 }
 
-impl DomainMetadata {
-    /// Create a new empty state for a particular FQDN.
-    pub fn new(fqdn: String) -> DomainMetadata {
-        DomainMetadata {
-            fqdn,
-            ..Default::default()
+/// A single HTTP response of a redirect chain.
+#[derive(Debug, Clone, Serialize, Deserialize, Hash, Eq, PartialEq)]
+pub struct RedirectHop {
+    /// Host the request was sent to.
+    pub host: String,
+
+    /// Status code of the response.
+    pub status: u16,
+
+    /// Redirect target, if the response was a redirect.
+    pub location: Option<String>,
+}
+
+/// HTTP specific metadata gathered by following redirects.
+#[derive(Debug, Clone, Default, Serialize, Deserialize, Hash, Eq, PartialEq)]
+pub struct HttpMetadata {
+    /// Responses in the order they were received, starting with the
+    /// response of the domain itself.
+    pub hops: Vec<RedirectHop>,
+
+    /// Server banner of the last response.
+    pub banner: Option<String>,
+
+    /// Every header of the last response (e.g. `x-powered-by`, `via` or
+    /// `cf-ray`), keyed by lowercase name. Only captured if requested
+    /// through [`HttpProbe::capture_headers`].
+    pub headers: Option<BTreeMap<String, Vec<String>>>,
+
+    /// `Content-Language` header of the last response. This and the
+    /// following fields are only captured if requested through
+    /// [`HttpProbe::detect_language`].
+    pub content_language: Option<String>,
+
+    /// `lang` attribute of the `html` element served by the last response.
+    pub html_lang: Option<String>,
+
+    /// Lowercase charset of the last response, as declared by its
+    /// `Content-Type` header or, failing that, by a `meta` element.
+    pub charset: Option<String>,
+
+    /// Lowercase primary language subtag of the content (e.g. `de`), taken
+    /// from `html_lang` or, failing that, from `content_language`. Used to
+    /// prioritize lookalikes targeting the locales of a brand.
+    pub language: Option<String>,
+}
+
+/// Options of the HTTP lookup performed by [`DomainMetadata::http_probe`].
+#[derive(Debug, Clone, Copy, Serialize, Deserialize, Hash, Eq, PartialEq)]
+#[serde(default, deny_unknown_fields)]
+pub struct HttpProbe {
+    /// Maximum number of redirects followed.
+    pub max_redirects: usize,
+
+    /// Whether to capture every response header rather than only the
+    /// `server` banner.
+    pub capture_headers: bool,
+
+    /// Whether to detect the language and charset of the content served,
+    /// see [`HttpMetadata::language`]. Since the markup is needed, the
+    /// lookup uses `GET` rather than `HEAD` requests, reading at most the
+    /// first `max_body_size` bytes of the last response.
+    pub detect_language: bool,
+
+    /// Bytes of the last response read by the content-based enrichments,
+    /// so that huge responses cannot exhaust memory. Defaults to 64 KiB.
+    pub max_body_size: usize,
+
+    /// Longest the whole probe may take, redirects included, so that
+    /// slow responses cannot stall a scan. Bodies still being read by
+    /// then are cut short, and the probe fails with
+    /// [`EnrichmentError::HttpProbeTimeout`] otherwise. Defaults to 30s,
+    /// unbounded if `None`.
+    pub timeout: Option<Duration>,
+}
+
+impl Default for HttpProbe {
+    fn default() -> HttpProbe {
+        HttpProbe {
+            max_redirects: 0,
+            capture_headers: false,
+            detect_language: false,
+            max_body_size: MARKUP_LIMIT,
+            timeout: Some(PROBE_TIMEOUT),
         }
     }
+}
 
-    /// Asynchronous DNS resolution on a `DomainMetadata` instance.
-    ///
-    /// Returns `Ok(DomainMetadata)` is the domain was resolved,
-    /// otherwise returns `Err(EnrichmentError)`.
-    ///
-    /// **N.B**—also host lookups are done over port 80.
-    pub async fn dns_resolvable(&self) -> Result<DomainMetadata, Error> {
-        Ok(net::lookup_host(&format!("{}:80", self.fqdn)[..])
-            .await
-            .map(|addrs| DomainMetadata {
-                fqdn: self.fqdn.clone(),
-                ips: Some(addrs.map(|addr| addr.ip()).collect()),
-                smtp: None,
-                http_banner: None,
-                geo_ip_lookups: None,
-                who_is_lookup: None,
+/// TLS specific metadata gathered during the handshake.
+#[derive(Debug, Clone, Default, Serialize, Deserialize, Hash, Eq, PartialEq)]
+pub struct TlsMetadata {
+    /// Server name sent through SNI, `None` if SNI was disabled.
+    pub sni: Option<String>,
+
+    /// Why the certificate failed validation. Only recorded when invalid
+    /// certificates are accepted, as the handshake fails otherwise.
+    pub validation_error: Option<String>,
+
+    /// DNS names the certificate is valid for, wildcards included (e.g.
+    /// `*.example.com`).
+    pub subject_alt_names: Vec<String>,
+}
+
+/// Domain discovered through another one, e.g. listed on the same
+/// certificate, see [`DomainMetadata::related_domains`].
+#[derive(Debug, Clone, Serialize, Deserialize, Hash, Eq, PartialEq)]
+pub struct RelatedDomain {
+    pub fqdn: String,
+
+    /// How the domain relates to the base domain, only set once
+    /// classified through [`DomainMetadata::classify_related`].
+    pub relation: Option<Relation>,
+}
+
+/// Relation of a discovered domain to the base domain.
+#[derive(Debug, Clone, Copy, Serialize, Deserialize, Hash, Eq, PartialEq)]
+pub enum Relation {
+    /// The base domain itself or one of its subdomains, e.g. a kit
+    /// certificate also covering the impersonated domain.
+    Base,
+
+    /// A domain resembling the base domain, likely another squat.
+    Lookalike,
+
+    Unrelated,
+}
+
+/// Anonymizer flags of an IP address, as found in the `GeoIP`
+/// Anonymous-IP database. Ranges missing from the database have every
+/// flag unset.
+#[derive(Debug, Clone, Copy, Default, Serialize, Deserialize, Hash, Eq, PartialEq)]
+pub struct AnonymousIpMetadata {
+    pub anonymous: bool,
+    pub anonymous_vpn: bool,
+    pub hosting_provider: bool,
+    pub public_proxy: bool,
+    pub residential_proxy: bool,
+    pub tor_exit_node: bool,
+}
+
+impl AnonymousIpMetadata {
+    /// Whether the IP address is behind a VPN, proxy, Tor exit node or
+    /// hosting range.
+    pub fn is_anonymized(&self) -> bool {
+        self.anonymous
+            || self.anonymous_vpn
+            || self.hosting_provider
+            || self.public_proxy
+            || self.residential_proxy
+            || self.tor_exit_node
+    }
+}
+
+#[cfg(feature = "geoip")]
+impl From<geoip2::AnonymousIp> for AnonymousIpMetadata {
+    fn from(record: geoip2::AnonymousIp) -> AnonymousIpMetadata {
+        AnonymousIpMetadata {
+            anonymous: record.is_anonymous.unwrap_or_default(),
+            anonymous_vpn: record.is_anonymous_vpn.unwrap_or_default(),
+            hosting_provider: record.is_hosting_provider.unwrap_or_default(),
+            public_proxy: record.is_public_proxy.unwrap_or_default(),
+            residential_proxy: record.is_residential_proxy.unwrap_or_default(),
+            tor_exit_node: record.is_tor_exit_node.unwrap_or_default(),
+        }
+    }
+}
+
+/// Network operator of an IP address, as found in the `GeoIP` ISP
+/// database.
+#[derive(Debug, Clone, Default, Serialize, Deserialize, Hash, Eq, PartialEq)]
+pub struct IspMetadata {
+    pub autonomous_system_number: Option<u32>,
+    pub autonomous_system_organization: Option<String>,
+    pub isp: Option<String>,
+    pub organization: Option<String>,
+}
+
+#[cfg(feature = "geoip")]
+impl From<geoip2::Isp<'_>> for IspMetadata {
+    fn from(record: geoip2::Isp<'_>) -> IspMetadata {
+        IspMetadata {
+            autonomous_system_number: record.autonomous_system_number,
+            autonomous_system_organization: record
+                .autonomous_system_organization
+                .map(str::to_string),
+            isp: record.isp.map(str::to_string),
+            organization: record.organization.map(str::to_string),
+        }
+    }
+}
+
+/// Options of the `WhoIs` lookup performed by
+/// [`DomainMetadata::whois_lookup_with`].
+///
+/// The compiled-in server list lags behind newer TLDs, so servers can be
+/// overridden either for a single query or per TLD.
+#[derive(Debug, Clone, Default, Serialize, Deserialize, Hash, Eq, PartialEq)]
+#[serde(default, deny_unknown_fields)]
+pub struct WhoIsOptions {
+    /// Server queried regardless of the TLD, e.g. `whois.nic.xyz`.
+    pub server: Option<String>,
+
+    /// Servers queried per TLD, keyed without the leading dot (e.g. `xyz`
+    /// or `co.uk`). The longest matching suffix wins.
+    pub tld_servers: BTreeMap<String, String>,
+}
+
+impl WhoIsOptions {
+    /// Server overriding the compiled-in one for `fqdn`, if any.
+    pub fn server_for(&self, fqdn: &str) -> Option<&str> {
+        if let Some(server) = &self.server {
+            return Some(server);
+        }
+
+        let name = fqdn.trim_end_matches('.').to_lowercase();
+
+        self.tld_servers
+            .iter()
+            .filter(|(tld, _)| {
+                let suffix = tld.trim_start_matches('.').to_lowercase();
+                name.strip_suffix(&suffix)
+                    .is_some_and(|rest| rest.is_empty() || rest.ends_with('.'))
             })
-            .map_err(|_| EnrichmentError::DnsResolutionError {
-                domain: self.fqdn.clone(),
-            })?)
+            .max_by_key(|(tld, _)| tld.trim_start_matches('.').len())
+            .map(|(_, server)| server.as_str())
     }
 
-    /// Asynchronous SMTP check. Attempts to establish an SMTP
-    /// connection to the FQDN on port 25 and send a pre-defi
-    /// ned email.
+    /// Destination the lookup of `fqdn` is sent to: its overriding server
+    /// if any, or else the host of its compiled-in server, so that TLDs
+    /// served by the same registry share the same destination. Falls back
+    /// to the TLD of `fqdn` if no server is known.
     ///
-    /// Currently returns `Ok(DomainMetadata)` always, which
-    /// internally contains `Option<SmtpMetadata>`. To check
-    /// if the SMTP relay worked, check that
-    /// `DomainMetadata.smtp` is `Some(v)`.
-    #[cfg(feature = "smtp_lookup")]
-    pub async fn mx_check(&self) -> Result<DomainMetadata, Error> {
-        let email = SendableEmail::new(
-            Envelope::new(
-                Some("twistrs@example.com".parse().unwrap()),
-                vec!["twistrs@example.com".parse().unwrap()],
-            )
-            .map_err(|e| EnrichmentError::SmtpLookupError {
-                domain: self.fqdn.clone(),
-                error: anyhow::Error::msg(e),
-            })?,
-            "And that's how the cookie crumbles\n",
-        );
+    /// Compiled-in servers are only known with the `whois` feature
+    /// toggled.
+    pub fn destination_for(&self, fqdn: &str) -> String {
+        let name = fqdn.trim_end_matches('.').to_lowercase();
 
-        let stream = BufStream::new(
-            TcpStream::connect(&format!("{}:25", self.fqdn))
-                .await
-                .map_err(|e| EnrichmentError::SmtpLookupError {
-                    domain: self.fqdn.clone(),
-                    error: anyhow::Error::msg(e),
-                })?,
-        );
-        let client = SmtpClient::new();
-        let mut transport = SmtpTransport::new(client, stream).await.map_err(|e| {
-            EnrichmentError::SmtpLookupError {
-                domain: self.fqdn.clone(),
-                error: anyhow::Error::msg(e),
-            }
-        })?;
+        if let Some(server) = self.server_for(&name) {
+            return server.to_lowercase();
+        }
 
-        let result = transport.send(email).await.map(|response| DomainMetadata {
-            fqdn: self.fqdn.clone(),
-            ips: None,
-            smtp: Some(SmtpMetadata {
-                is_positive: response.is_positive(),
-                message: response.message.into_iter().collect::<String>(),
-            }),
-            http_banner: None,
-            geo_ip_lookups: None,
-            who_is_lookup: None,
-        });
+        #[cfg(feature = "whois")]
+        {
+            let mut suffixes = std::iter::successors(Some(name.as_str()), |suffix| {
+                suffix.split_once('.').map(|(_, rest)| rest)
+            });
 
-        Ok(match result {
-            Ok(domain_metadata) => Ok(domain_metadata),
-            Err(async_smtp::error::Error::Timeout(_)) => Ok(DomainMetadata::new(self.fqdn.clone())),
-            Err(e) => Err(EnrichmentError::SmtpLookupError {
-                domain: self.fqdn.clone(),
-                error: anyhow::Error::msg(e),
-            }),
-        }?)
+            if let Some(host) = suffixes.find_map(|suffix| WHOIS_SERVERS.get(suffix)) {
+                return host.clone();
+            }
+        }
+
+        name.rsplit('.').next().unwrap_or_default().to_string()
     }
+}
 
-    /// Asynchronous HTTP Banner fetch. Searches and parses `server` header
-    /// from an HTTP request to gather the HTTP banner.
-    ///
-    /// Note that a `HEAD` request is issued to minimise bandwidth. Also note
+/// Port the mail exchangers of a domain are probed on by
+/// [`DomainMetadata::mail_capability`].
+#[derive(Clone, Copy, Debug, Serialize, Deserialize, Hash, Eq, PartialEq, Ord, PartialOrd)]
+pub enum MailPort {
+    /// Plain SMTP on port 25, which many networks block outbound.
+    Smtp,
+
+    /// SMTP over implicit TLS on port 465.
+    Submissions,
+
+    /// Message submission on port 587, upgraded to TLS through
+    /// `STARTTLS`.
+    Submission,
+}
+
+impl MailPort {
+    /// Every mail port, in the order they are probed.
+    pub const ALL: [MailPort; 3] = [MailPort::Smtp, MailPort::Submissions, MailPort::Submission];
+
+    /// The TCP port number.
+    pub fn number(self) -> u16 {
+        match self {
+            MailPort::Smtp => 25,
+            MailPort::Submissions => 465,
+            MailPort::Submission => 587,
+        }
+    }
+}
+
+/// Options of the mail capability lookup, see
+/// [`DomainMetadata::mail_capability`]. Only port 25 is probed by
+/// default.
+///
+/// With the `tls` feature toggled, exchangers only count as reachable on
+/// ports 465 and 587 once the TLS handshake completes. Certificates are
+/// not validated, since reachability is all that is checked.
+#[derive(Debug, Clone, Serialize, Deserialize, Hash, Eq, PartialEq)]
+#[serde(default, deny_unknown_fields)]
+pub struct MailOptions {
+    pub ports: Vec<MailPort>,
+}
+
+impl Default for MailOptions {
+    fn default() -> MailOptions {
+        MailOptions {
+            ports: vec![MailPort::Smtp],
+        }
+    }
+}
+
+impl MailOptions {
+    /// Probe every mail port, e.g. from networks blocking outbound port
+    /// 25.
+    pub fn all() -> MailOptions {
+        MailOptions {
+            ports: MailPort::ALL.to_vec(),
+        }
+    }
+}
+
+/// `WhoIs` response, kept raw for evidence preservation along with its
+/// parsed fields.
+#[derive(Debug, Clone, Default, Serialize, Deserialize, Hash, Eq, PartialEq)]
+pub struct WhoIsMetadata {
+    /// Server the lookup was sent to, `None` if the compiled-in server
+    /// list was used.
+    pub server: Option<String>,
+
+    /// Response as returned by the server.
+    pub raw: String,
+
+    /// `key: value` fields of the response (e.g. `Registrar` or
+    /// `Creation Date`), keyed as they appear. Repeated keys such as
+    /// `Name Server` keep every value in order.
+    pub fields: BTreeMap<String, Vec<String>>,
+}
+
+/// Type of a DNS record captured in [`DnsRecords`].
+#[derive(Clone, Copy, Debug, Serialize, Deserialize, Hash, Eq, PartialEq, Ord, PartialOrd)]
+pub enum DnsRecordType {
+    A,
+    Aaaa,
+    Cname,
+    Mx,
+    Ns,
+    Txt,
+    Soa,
+}
+
+impl DnsRecordType {
+    /// Every record type captured, in the order they are looked up.
+    pub const ALL: [DnsRecordType; 7] = [
+        DnsRecordType::A,
+        DnsRecordType::Aaaa,
+        DnsRecordType::Cname,
+        DnsRecordType::Mx,
+        DnsRecordType::Ns,
+        DnsRecordType::Txt,
+        DnsRecordType::Soa,
+    ];
+
+    #[cfg(feature = "dns")]
+    fn record_type(self) -> RecordType {
+        match self {
+            DnsRecordType::A => RecordType::A,
+            DnsRecordType::Aaaa => RecordType::AAAA,
+            DnsRecordType::Cname => RecordType::CNAME,
+            DnsRecordType::Mx => RecordType::MX,
+            DnsRecordType::Ns => RecordType::NS,
+            DnsRecordType::Txt => RecordType::TXT,
+            DnsRecordType::Soa => RecordType::SOA,
+        }
+    }
+}
+
+/// DNS answer along with the TTL it was served with. Very low TTLs on
+/// freshly registered lookalikes are a common sign of fast-flux hosting.
+#[derive(Clone, Debug, Serialize, Deserialize, Hash, Eq, PartialEq)]
+pub struct DnsRecord {
+    pub record_type: DnsRecordType,
+
+    /// Record data in presentation format (e.g. `10 mail.example.com.`).
+    pub value: String,
+
+    /// Time-to-live in seconds, `None` for addresses resolved through the
+    /// system resolver, which does not expose it. Answers served from a
+    /// resolver cache carry the remaining TTL rather than the original one.
+    pub ttl: Option<u32>,
+}
+
+impl DnsRecord {
+    /// Record of `record_type` without a TTL.
+    pub fn new(record_type: DnsRecordType, value: impl Into<String>) -> DnsRecord {
+        DnsRecord {
+            record_type,
+            value: value.into(),
+            ttl: None,
+        }
+    }
+}
+
+/// DNS records of a domain by type. Each type is `None` if it was not
+/// looked up, and empty if the domain has no records of that type.
+#[derive(Clone, Debug, Default, Serialize, Deserialize, Hash, Eq, PartialEq)]
+#[serde(default)]
+pub struct DnsRecords {
+    pub a: Option<Vec<DnsRecord>>,
+    pub aaaa: Option<Vec<DnsRecord>>,
+    pub cname: Option<Vec<DnsRecord>>,
+    pub mx: Option<Vec<DnsRecord>>,
+    pub ns: Option<Vec<DnsRecord>>,
+    pub txt: Option<Vec<DnsRecord>>,
+    pub soa: Option<Vec<DnsRecord>>,
+}
+
+/// Deserialize the DNS records of a `DomainMetadata`, or the flat list of
+/// IPs they replaced, so that records persisted by earlier versions (e.g.
+/// in a [`DomainStore`](crate::storage::DomainStore) or a `ScanState`)
+/// keep their addresses.
+fn dns_or_legacy_ips<'de, D>(deserializer: D) -> Result<DnsRecords, D::Error>
+where
+    D: Deserializer<'de>,
+{
+    struct DnsVisitor;
+
+    impl<'de> Visitor<'de> for DnsVisitor {
+        type Value = DnsRecords;
+
+        fn expecting(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+            f.write_str("DNS records or a list of IPs")
+        }
+
+        fn visit_map<A: MapAccess<'de>>(self, map: A) -> Result<DnsRecords, A::Error> {
+            DnsRecords::deserialize(MapAccessDeserializer::new(map))
+        }
+
+        fn visit_seq<A: SeqAccess<'de>>(self, mut seq: A) -> Result<DnsRecords, A::Error> {
+            let mut ips = vec![];
+            while let Some(ip) = seq.next_element::<IpAddr>()? {
+                ips.push(ip);
+            }
+
+            Ok(DnsRecords::from_ips(ips))
+        }
+
+        // Legacy records of domains that did not resolve.
+        fn visit_none<E: de::Error>(self) -> Result<DnsRecords, E> {
+            Ok(DnsRecords::default())
+        }
+
+        fn visit_unit<E: de::Error>(self) -> Result<DnsRecords, E> {
+            Ok(DnsRecords::default())
+        }
+
+        fn visit_some<S: Deserializer<'de>>(self, inner: S) -> Result<DnsRecords, S::Error> {
+            inner.deserialize_any(DnsVisitor)
+        }
+    }
+
+    deserializer.deserialize_any(DnsVisitor)
+}
+
+impl DnsRecords {
+    /// Records of `record_type`, `None` if they were not looked up.
+    pub fn get(&self, record_type: DnsRecordType) -> Option<&Vec<DnsRecord>> {
+        match record_type {
+            DnsRecordType::A => self.a.as_ref(),
+            DnsRecordType::Aaaa => self.aaaa.as_ref(),
+            DnsRecordType::Cname => self.cname.as_ref(),
+            DnsRecordType::Mx => self.mx.as_ref(),
+            DnsRecordType::Ns => self.ns.as_ref(),
+            DnsRecordType::Txt => self.txt.as_ref(),
+            DnsRecordType::Soa => self.soa.as_ref(),
+        }
+    }
+
+    /// Set the records of `record_type`, marking it as looked up.
+    pub fn set(&mut self, record_type: DnsRecordType, records: Vec<DnsRecord>) {
+        let slot = match record_type {
+            DnsRecordType::A => &mut self.a,
+            DnsRecordType::Aaaa => &mut self.aaaa,
+            DnsRecordType::Cname => &mut self.cname,
+            DnsRecordType::Mx => &mut self.mx,
+            DnsRecordType::Ns => &mut self.ns,
+            DnsRecordType::Txt => &mut self.txt,
+            DnsRecordType::Soa => &mut self.soa,
+        };
+
+        *slot = Some(records);
+    }
+
+    /// Every record captured, in the order of [`DnsRecordType::ALL`].
+    pub fn iter(&self) -> impl Iterator<Item = &DnsRecord> {
+        DnsRecordType::ALL
+            .into_iter()
+            .filter_map(|record_type| self.get(record_type))
+            .flatten()
+    }
+
+    /// Addresses of the A and AAAA records, `None` if neither was looked
+    /// up.
+    pub fn ips(&self) -> Option<Vec<IpAddr>> {
+        if self.a.is_none() && self.aaaa.is_none() {
+            return None;
+        }
+
+        Some(
+            self.a
+                .iter()
+                .chain(&self.aaaa)
+                .flatten()
+                .filter_map(|record| record.value.parse().ok())
+                .collect(),
+        )
+    }
+
+    /// Whether the domain has at least one A or AAAA record.
+    pub fn resolves(&self) -> bool {
+        self.ips().is_some_and(|ips| !ips.is_empty())
+    }
+
+    /// Records of resolved `ips`, split into A and AAAA records, without
+    /// TTLs.
+    pub fn from_ips(ips: impl IntoIterator<Item = IpAddr>) -> DnsRecords {
+        let (v4, v6): (Vec<IpAddr>, Vec<IpAddr>) = ips.into_iter().partition(IpAddr::is_ipv4);
+
+        DnsRecords {
+            a: Some(
+                v4.into_iter()
+                    .map(|ip| DnsRecord::new(DnsRecordType::A, ip.to_string()))
+                    .collect(),
+            ),
+            aaaa: Some(
+                v6.into_iter()
+                    .map(|ip| DnsRecord::new(DnsRecordType::Aaaa, ip.to_string()))
+                    .collect(),
+            ),
+            ..DnsRecords::default()
+        }
+    }
+
+    /// Fill in the record types that `other` has and `self` lacks.
+    fn merge(self, other: DnsRecords) -> DnsRecords {
+        DnsRecords {
+            a: self.a.or(other.a),
+            aaaa: self.aaaa.or(other.aaaa),
+            cname: self.cname.or(other.cname),
+            mx: self.mx.or(other.mx),
+            ns: self.ns.or(other.ns),
+            txt: self.txt.or(other.txt),
+            soa: self.soa.or(other.soa),
+        }
+    }
+}
+
+impl DomainMetadata {
+    /// Create a new empty state for a particular FQDN.
+    pub fn new(fqdn: String) -> DomainMetadata {
+        DomainMetadata {
+            fqdn,
+            ..Default::default()
+        }
+    }
+
+    /// Any IPv4 and IPv6 ips that were discovered during domain
+    /// resolution, see [`DnsRecords::ips`].
+    #[deprecated(note = "use `dns.ips()` instead")]
+    pub fn ips(&self) -> Option<Vec<IpAddr>> {
+        self.dns.ips()
+    }
+
+    /// Nameservers of the domain, lowercase and without the trailing dot.
+    /// Returns `None` if the NS records were not looked up.
+    pub fn nameservers(&self) -> Option<BTreeSet<String>> {
+        self.dns.ns.as_ref().map(|records| {
+            records
+                .iter()
+                .map(|record| record.value.trim_end_matches('.').to_lowercase())
+                .collect()
+        })
+    }
+
+    /// Compare the nameservers of the domain against those of `base`
+    /// (e.g. the domain the permutations were generated from), setting
+    /// `shared_nameservers`. Left unset if either lacks DNS records.
+    ///
+    /// ```
+    /// use twistrs::enrich::{DnsRecord, DnsRecordType, DomainMetadata};
+    ///
+    /// let ns = |value: &str| DnsRecord::new(DnsRecordType::Ns, value);
+    ///
+    /// let mut base = DomainMetadata::new(String::from("example.com"));
+    /// base.dns.ns = Some(vec![ns("a.iana-servers.net."), ns("b.iana-servers.net.")]);
+    ///
+    /// let mut candidate = DomainMetadata::new(String::from("examp1e.com"));
+    /// candidate.dns.ns = Some(vec![ns("B.IANA-SERVERS.NET.")]);
+    /// candidate.compare_nameservers(&base);
+    ///
+    /// assert_eq!(candidate.shared_nameservers, Some(true));
+    /// ```
+    pub fn compare_nameservers(&mut self, base: &DomainMetadata) {
+        if let (Some(ours), Some(theirs)) = (self.nameservers(), base.nameservers()) {
+            self.shared_nameservers = Some(!ours.is_disjoint(&theirs));
+        }
+    }
+
+    /// Asynchronous lookup of the domains co-hosted on each of the IPs
+    /// through `provider`, excluding the domain itself. Domains without
+    /// IPs have no neighbors to look up.
+    pub async fn reverse_ip_lookup(
+        &self,
+        provider: &dyn ReverseIp,
+    ) -> Result<DomainMetadata, Error> {
+        let Some(ips) = self.dns.ips() else {
+            return Ok(DomainMetadata::new(self.fqdn.clone()));
+        };
+
+        let own = self.fqdn.trim_end_matches('.').to_lowercase();
+        let mut neighbors = BTreeMap::new();
+
+        for ip in ips {
+            let hosted = provider
+                .neighbors(ip)
+                .await?
+                .into_iter()
+                .filter(|fqdn| fqdn.trim_end_matches('.').to_lowercase() != own)
+                .collect();
+
+            neighbors.insert(ip, hosted);
+        }
+
+        Ok(DomainMetadata {
+            neighbors: Some(neighbors),
+            ..DomainMetadata::new(self.fqdn.clone())
+        })
+    }
+
+    /// Classify the related domains against the `base` domain being
+    /// monitored, see [`Relation`]. Related domains sharing the
+    /// registrable domain of `base` relate to it as [`Relation::Base`],
+    /// while ones whose registrable label contains or closely resembles
+    /// that of `base` are lookalikes.
+    ///
+    /// ```
+    /// use twistrs::enrich::{DomainMetadata, RelatedDomain, Relation};
+    /// use twistrs::permutate::Domain;
+    ///
+    /// let related = |fqdn: &str| RelatedDomain {
+    ///     fqdn: fqdn.to_string(),
+    ///     relation: None,
+    /// };
+    ///
+    /// let mut candidate = DomainMetadata::new(String::from("examplle.com"));
+    /// candidate.related_domains = Some(vec![related("www.example.com"), related("exarnple.net")]);
+    /// candidate.classify_related(&Domain::new("example.com").unwrap());
+    ///
+    /// let relations: Vec<_> = candidate.related_domains.unwrap().into_iter().map(|r| r.relation).collect();
+    /// assert_eq!(relations, vec![Some(Relation::Base), Some(Relation::Lookalike)]);
+    /// ```
+    pub fn classify_related(&mut self, base: &Domain) {
+        for related in self.related_domains.iter_mut().flatten() {
+            let relation = match DomainRef::new(&related.fqdn) {
+                Ok(domain) if domain.domain == base.domain && domain.tld == base.tld => {
+                    Relation::Base
+                }
+                Ok(domain)
+                    if domain.domain.contains(base.domain.as_str())
+                        || scoring::similarity(domain.domain, &base.domain)
+                            >= LOOKALIKE_SIMILARITY =>
+                {
+                    Relation::Lookalike
+                }
+                _ => Relation::Unrelated,
+            };
+
+            related.relation = Some(relation);
+        }
+    }
+
+    /// Related domains out of the `names` listed alongside this domain,
+    /// excluding the domain itself.
+    #[cfg_attr(not(feature = "tls"), allow(dead_code))]
+    fn related_to(&self, names: &[String]) -> Vec<RelatedDomain> {
+        let own = self.fqdn.trim_end_matches('.').to_lowercase();
+
+        let fqdns: BTreeSet<String> = names
+            .iter()
+            .map(|name| {
+                name.trim_start_matches("*.")
+                    .trim_end_matches('.')
+                    .to_lowercase()
+            })
+            .filter(|fqdn| !fqdn.is_empty() && *fqdn != own)
+            .collect();
+
+        fqdns
+            .into_iter()
+            .map(|fqdn| RelatedDomain {
+                fqdn,
+                relation: None,
+            })
+            .collect()
+    }
+
+    /// Lowest TTL across the DNS records, in seconds, after which the
+    /// metadata should be considered stale. Returns `None` if no records
+    /// with a TTL were captured.
+    pub fn min_ttl(&self) -> Option<u32> {
+        self.dns.iter().filter_map(|record| record.ttl).min()
+    }
+
+    /// Asynchronous DNS resolution on a `DomainMetadata` instance.
+    ///
+    /// Returns `Ok(DomainMetadata)` is the domain was resolved,
+    /// otherwise returns `Err(EnrichmentError)`.
+    ///
+    /// **N.B**—also host lookups are done over port 80.
+    pub async fn dns_resolvable(&self) -> Result<DomainMetadata, Error> {
+        Ok(net::lookup_host(&format!("{}:80", self.fqdn)[..])
+            .await
+            .map(|addrs| DomainMetadata {
+                fqdn: self.fqdn.clone(),
+                dns: DnsRecords::from_ips(addrs.map(|addr| addr.ip())),
+                smtp: None,
+                http_banner: None,
+                geo_ip_lookups: None,
+                who_is_lookup: None,
+                http: None,
+                tls: None,
+                shared_nameservers: None,
+                mail_capable: None,
+                mail_ports: None,
+                who_is: None,
+                anonymous_ip_lookups: None,
+                isp_lookups: None,
+                jarm: None,
+                related_domains: None,
+                neighbors: None,
+                cached_until: None,
+            })
+            .map_err(|_| EnrichmentError::DnsResolutionError {
+                domain: self.fqdn.clone(),
+            })?)
+    }
+
+    /// Asynchronous DNS resolution through the resolver of `context`, if
+    /// one is configured, see [`DomainMetadata::dns_resolvable`].
+    /// Otherwise, the domain is resolved by the system.
+    #[cfg_attr(not(feature = "dns"), allow(unused_variables))]
+    pub async fn dns_resolvable_with_context(
+        &self,
+        context: &EnrichmentContext,
+    ) -> Result<DomainMetadata, Error> {
+        #[cfg(feature = "dns")]
+        if let Some(resolver) = &context.resolver {
+            // Fully qualified, so that the search domains are not appended.
+            let name = format!("{}.", self.fqdn.trim_end_matches('.'));
+
+            return match resolver.lookup_ip(name).await {
+                Ok(lookup) => Ok(DomainMetadata {
+                    dns: DnsRecords::from_ips(lookup.iter()),
+                    ..DomainMetadata::new(self.fqdn.clone())
+                }),
+                Err(_) => Err(EnrichmentError::DnsResolutionError {
+                    domain: self.fqdn.clone(),
+                }
+                .into()),
+            };
+        }
+
+        self.dns_resolvable().await
+    }
+
+    /// Asynchronous lookup of the A, AAAA, CNAME, MX, NS, TXT and SOA
+    /// records of the domain along with their TTLs, using the system
+    /// resolver configuration.
+    ///
+    /// Record types the domain has no records for are left empty.
+    ///
+    /// ### Features
+    ///
+    /// This function requires the `dns` feature toggled.
+    #[cfg(feature = "dns")]
+    pub async fn dns_records(&self) -> Result<DomainMetadata, Error> {
+        self.dns_records_with_context(&DEFAULT_CONTEXT).await
+    }
+
+    /// Asynchronous DNS records lookup through the resolver of `context`,
+    /// see [`DomainMetadata::dns_records`].
+    ///
+    /// ### Features
+    ///
+    /// This function requires the `dns` feature toggled.
+    #[cfg(feature = "dns")]
+    pub async fn dns_records_with_context(
+        &self,
+        context: &EnrichmentContext,
+    ) -> Result<DomainMetadata, Error> {
+        let error = |e: trust_dns_resolver::error::ResolveError| EnrichmentError::DnsRecordError {
+            domain: self.fqdn.clone(),
+            error: anyhow::Error::msg(e),
+        };
+
+        let resolver = &context.resolver().map_err(error)?;
+
+        let lookups = DnsRecordType::ALL
+            .into_iter()
+            .map(|record_type| async move {
+                lookup_records(resolver, &self.fqdn, record_type)
+                    .await
+                    .map(|records| (record_type, records))
+                    .map_err(error)
+            });
+
+        let mut dns = DnsRecords::default();
+        for (record_type, records) in futures::future::try_join_all(lookups).await? {
+            dns.set(record_type, records);
+        }
+
+        Ok(DomainMetadata {
+            dns,
+            ..DomainMetadata::new(self.fqdn.clone())
+        })
+    }
+
+    /// Asynchronous lookup of the NS records of the domain only, through
+    /// the resolver of `context`, see [`DomainMetadata::nameservers`].
+    ///
+    /// ### Features
+    ///
+    /// This function requires the `dns` feature toggled.
+    #[cfg(feature = "dns")]
+    pub async fn nameserver_lookup_with_context(
+        &self,
+        context: &EnrichmentContext,
+    ) -> Result<DomainMetadata, Error> {
+        let error = |e: trust_dns_resolver::error::ResolveError| EnrichmentError::DnsRecordError {
+            domain: self.fqdn.clone(),
+            error: anyhow::Error::msg(e),
+        };
+
+        let resolver = context.resolver().map_err(error)?;
+        let records = lookup_records(&resolver, &self.fqdn, DnsRecordType::Ns)
+            .await
+            .map_err(error)?;
+
+        Ok(DomainMetadata {
+            dns: DnsRecords {
+                ns: Some(records),
+                ..DnsRecords::default()
+            },
+            ..DomainMetadata::new(self.fqdn.clone())
+        })
+    }
+
+    /// Asynchronous mail capability check. Resolves the MX records of the
+    /// domain and attempts to connect to its mail exchangers on port 25,
+    /// in order of preference, setting `mail_capable` if any of them
+    /// accepts the connection. The MX records are kept as `dns.mx`, and
+    /// the reachability of each port as `mail_ports`.
+    ///
+    /// Ports 465 and 587 are also probed if enabled in the
+    /// [`MailOptions`] of the context, see
+    /// [`DomainMetadata::mail_capability_with_context`].
+    ///
+    /// Domains without MX records, or with a null MX record (RFC 7505),
+    /// are not mail capable. Each connection attempt times out after 5s.
+    ///
+    /// ### Features
+    ///
+    /// This function requires the `dns` feature toggled.
+    #[cfg(feature = "dns")]
+    pub async fn mail_capability(&self) -> Result<DomainMetadata, Error> {
+        self.mail_capability_with_context(&DEFAULT_CONTEXT).await
+    }
+
+    /// Asynchronous mail capability check, resolving the MX records
+    /// through the resolver of `context` and probing the ports of its
+    /// [`MailOptions`], see [`DomainMetadata::mail_capability`].
+    ///
+    /// Each exchanger and port pair has its own circuit, so that a
+    /// blocked port 25 does not short-circuit the other ports.
+    ///
+    /// ### Features
+    ///
+    /// This function requires the `dns` feature toggled.
+    #[cfg(feature = "dns")]
+    pub async fn mail_capability_with_context(
+        &self,
+        context: &EnrichmentContext,
+    ) -> Result<DomainMetadata, Error> {
+        let error = |e: trust_dns_resolver::error::ResolveError| EnrichmentError::DnsRecordError {
+            domain: self.fqdn.clone(),
+            error: anyhow::Error::msg(e),
+        };
+
+        let resolver = context.resolver().map_err(error)?;
+        let records = lookup_records(&resolver, &self.fqdn, DnsRecordType::Mx)
+            .await
+            .map_err(error)?;
+
+        let exchanges = mail_exchanges(&records);
+        let mut mail_ports = BTreeMap::new();
+
+        for port in &context.mail.ports {
+            let mut reachable = false;
+
+            for exchange in &exchanges {
+                let probe = async { Ok(probe_mail_port(exchange, *port).await) };
+
+                let connected = context
+                    .guarded(
+                        EnrichmentKind::MailCapable,
+                        &format!("{exchange}:{}", port.number()),
+                        Duration::from_secs(5),
+                        probe,
+                    )
+                    .await;
+
+                if let Ok(true) = connected {
+                    reachable = true;
+                    break;
+                }
+            }
+
+            mail_ports.insert(port.number(), reachable);
+        }
+
+        Ok(DomainMetadata {
+            dns: DnsRecords {
+                mx: Some(records),
+                ..DnsRecords::default()
+            },
+            mail_capable: Some(mail_ports.values().any(|reachable| *reachable)),
+            mail_ports: Some(mail_ports),
+            ..DomainMetadata::new(self.fqdn.clone())
+        })
+    }
+
+    /// Asynchronous SMTP check. Attempts to establish an SMTP
+    /// connection to the FQDN on port 25 and send a pre-defi
+    /// ned email.
+    ///
+    /// Currently returns `Ok(DomainMetadata)` always, which
+    /// internally contains `Option<SmtpMetadata>`. To check
+    /// if the SMTP relay worked, check that
+    /// `DomainMetadata.smtp` is `Some(v)`.
+    ///
+    /// ### Features
+    ///
+    /// This function requires the `smtp` feature toggled.
+    #[cfg(feature = "smtp")]
+    pub async fn mx_check(&self) -> Result<DomainMetadata, Error> {
+        self.mx_check_with_context(&DEFAULT_CONTEXT).await
+    }
+
+    /// Asynchronous SMTP check through the circuit breaker of `context`,
+    /// see [`DomainMetadata::mx_check`].
+    ///
+    /// ### Features
+    ///
+    /// This function requires the `smtp` feature toggled.
+    #[cfg(feature = "smtp")]
+    pub async fn mx_check_with_context(
+        &self,
+        context: &EnrichmentContext,
+    ) -> Result<DomainMetadata, Error> {
+        context
+            .guarded(
+                EnrichmentKind::Mx,
+                &format!("{}:25", self.fqdn),
+                SMTP_TIMEOUT,
+                self.smtp_exchange(),
+            )
+            .await
+    }
+
+    #[cfg(feature = "smtp")]
+    async fn smtp_exchange(&self) -> Result<DomainMetadata, Error> {
+        let email = SendableEmail::new(
+            Envelope::new(
+                Some("twistrs@example.com".parse().unwrap()),
+                vec!["twistrs@example.com".parse().unwrap()],
+            )
+            .map_err(|e| EnrichmentError::SmtpLookupError {
+                domain: self.fqdn.clone(),
+                error: anyhow::Error::msg(e),
+            })?,
+            "And that's how the cookie crumbles\n",
+        );
+
+        let stream = BufStream::new(
+            TcpStream::connect(&format!("{}:25", self.fqdn))
+                .await
+                .map_err(|e| EnrichmentError::SmtpLookupError {
+                    domain: self.fqdn.clone(),
+                    error: anyhow::Error::msg(e),
+                })?,
+        );
+        let client = SmtpClient::new();
+        let mut transport = SmtpTransport::new(client, stream).await.map_err(|e| {
+            EnrichmentError::SmtpLookupError {
+                domain: self.fqdn.clone(),
+                error: anyhow::Error::msg(e),
+            }
+        })?;
+
+        let result = transport.send(email).await.map(|response| DomainMetadata {
+            fqdn: self.fqdn.clone(),
+            dns: DnsRecords::default(),
+            smtp: Some(SmtpMetadata {
+                is_positive: response.is_positive(),
+                message: response.message.into_iter().collect::<String>(),
+            }),
+            http_banner: None,
+            geo_ip_lookups: None,
+            who_is_lookup: None,
+            http: None,
+            tls: None,
+            shared_nameservers: None,
+            mail_capable: None,
+            mail_ports: None,
+            who_is: None,
+            anonymous_ip_lookups: None,
+            isp_lookups: None,
+            jarm: None,
+            related_domains: None,
+            neighbors: None,
+            cached_until: None,
+        });
+
+        Ok(match result {
+            Ok(domain_metadata) => Ok(domain_metadata),
+            Err(async_smtp::error::Error::Timeout(_)) => Ok(DomainMetadata::new(self.fqdn.clone())),
+            Err(e) => Err(EnrichmentError::SmtpLookupError {
+                domain: self.fqdn.clone(),
+                error: anyhow::Error::msg(e),
+            }),
+        }?)
+    }
+
+    /// Asynchronous HTTP Banner fetch. Searches and parses `server` header
+    /// from an HTTP request to gather the HTTP banner.
+    ///
+    /// Note that a `HEAD` request is issued to minimise bandwidth. Also note
     /// that the internal [`HttpConnector`](https://docs.rs/hyper/0.13.8/hyper/client/struct.HttpConnector.html)
     /// sets the response buffer window to 1024 bytes, the CONNECT timeout to
     /// 5s and enforces HTTP scheme.
@@ -230,6 +1614,26 @@ impl DomainMetadata {
     /// }
     /// ```
     pub async fn http_banner(&self) -> Result<DomainMetadata, Error> {
+        self.http_banner_with_context(&DEFAULT_CONTEXT).await
+    }
+
+    /// Asynchronous HTTP banner lookup through the HTTP client and circuit
+    /// breaker of `context`, see [`DomainMetadata::http_banner`].
+    pub async fn http_banner_with_context(
+        &self,
+        context: &EnrichmentContext,
+    ) -> Result<DomainMetadata, Error> {
+        context
+            .guarded(
+                EnrichmentKind::HttpBanner,
+                &format!("{}:80", self.fqdn),
+                HTTP_BANNER_TIMEOUT,
+                self.http_exchange(context),
+            )
+            .await
+    }
+
+    async fn http_exchange(&self, context: &EnrichmentContext) -> Result<DomainMetadata, Error> {
         // Construst the basic request to be sent out
         let request = Request::builder()
             .method("HEAD")
@@ -241,7 +1645,7 @@ impl DomainMetadata {
                 error: anyhow::Error::msg(e),
             })?;
 
-        if let Ok(response) = HTTP_CLIENT.request(request).await {
+        if let Ok(response) = context.http_client.request(request).await {
             if let Some(server_header) = response.headers().get("server") {
                 let server =
                     server_header
@@ -251,22 +1655,379 @@ impl DomainMetadata {
                             error: anyhow::Error::msg(e),
                         })?;
 
-                return Ok(DomainMetadata {
-                    fqdn: self.fqdn.clone(),
-                    ips: None,
-                    smtp: None,
-                    http_banner: Some(String::from(server)),
-                    geo_ip_lookups: None,
-                    who_is_lookup: None,
-                });
-            }
-        }
+                return Ok(DomainMetadata {
+                    fqdn: self.fqdn.clone(),
+                    dns: DnsRecords::default(),
+                    smtp: None,
+                    http_banner: Some(String::from(server)),
+                    geo_ip_lookups: None,
+                    who_is_lookup: None,
+                    http: None,
+                    tls: None,
+                    shared_nameservers: None,
+                    mail_capable: None,
+                    mail_ports: None,
+                    who_is: None,
+                    anonymous_ip_lookups: None,
+                    isp_lookups: None,
+                    jarm: None,
+                    related_domains: None,
+                    neighbors: None,
+                    cached_until: None,
+                });
+            }
+        }
+
+        Err(EnrichmentError::HttpBannerError {
+            domain: self.fqdn.clone(),
+            error: anyhow::Error::msg("unable to extract or parse server header from response"),
+        }
+        .into())
+    }
+
+    /// Asynchronous HTTP banner lookup following up to `max_redirects`
+    /// redirects, recording the host and status of each response along the
+    /// way. The banner is taken from the last response, since parked and
+    /// phishing domains commonly redirect once or twice before serving
+    /// any content.
+    ///
+    /// Only plain HTTP redirects are followed. Redirects to any other
+    /// scheme (e.g. HTTPS) are recorded as the last hop.
+    ///
+    /// Unlike [`DomainMetadata::http_banner`], a missing `server` header is
+    /// not an error, in which case the banner is `None`.
+    ///
+    /// ```
+    /// use twistrs::enrich::DomainMetadata;
+    ///
+    /// #[tokio::main]
+    /// async fn main() {
+    ///     let domain_metadata = DomainMetadata::new(String::from("www.phishdeck.com"));
+    ///     println!("{:?}", domain_metadata.http_banner_following(3).await);
+    /// }
+    /// ```
+    pub async fn http_banner_following(
+        &self,
+        max_redirects: usize,
+    ) -> Result<DomainMetadata, Error> {
+        self.http_probe(&HttpProbe {
+            max_redirects,
+            ..HttpProbe::default()
+        })
+        .await
+    }
+
+    /// Asynchronous HTTP lookup as configured by `probe`, see
+    /// [`DomainMetadata::http_banner_following`]. Headers are captured
+    /// from the last response, as they are used to cluster the
+    /// infrastructure serving the content.
+    ///
+    /// ```
+    /// use twistrs::enrich::{DomainMetadata, HttpProbe};
+    ///
+    /// #[tokio::main]
+    /// async fn main() {
+    ///     let probe = HttpProbe {
+    ///         max_redirects: 3,
+    ///         capture_headers: true,
+    ///         detect_language: true,
+    ///         ..HttpProbe::default()
+    ///     };
+    ///
+    ///     let domain_metadata = DomainMetadata::new(String::from("www.phishdeck.com"));
+    ///     println!("{:?}", domain_metadata.http_probe(&probe).await);
+    /// }
+    /// ```
+    pub async fn http_probe(&self, probe: &HttpProbe) -> Result<DomainMetadata, Error> {
+        self.http_probe_with_context(probe, &DEFAULT_CONTEXT).await
+    }
+
+    /// Asynchronous HTTP lookup as configured by `probe`, through the
+    /// HTTP client of `context`, see [`DomainMetadata::http_probe`].
+    pub async fn http_probe_with_context(
+        &self,
+        probe: &HttpProbe,
+        context: &EnrichmentContext,
+    ) -> Result<DomainMetadata, Error> {
+        let error = |e: anyhow::Error| EnrichmentError::HttpBannerError {
+            domain: self.fqdn.clone(),
+            error: e,
+        };
+
+        let mut uri: Uri = format!("http://{}/", self.fqdn)
+            .parse()
+            .map_err(|e| error(anyhow::Error::msg(e)))?;
+        let mut http = HttpMetadata::default();
+
+        let method = if probe.detect_language { "GET" } else { "HEAD" };
+        let deadline = probe
+            .timeout
+            .map(|timeout| tokio::time::Instant::now() + timeout);
+
+        loop {
+            let request = Request::builder()
+                .method(method)
+                .uri(uri.clone())
+                .header("User-Agent", "github-juxhindb-twistrs-http-banner/1.0")
+                .body(Body::empty())
+                .map_err(|e| error(anyhow::Error::msg(e)))?;
+
+            let response = within(deadline, context.http_client.request(request))
+                .await
+                .ok_or_else(|| EnrichmentError::HttpProbeTimeout {
+                    domain: self.fqdn.clone(),
+                    timeout: probe.timeout.unwrap_or_default(),
+                })?
+                .map_err(|e| error(anyhow::Error::msg(e)))?;
+
+            let header = |name| {
+                response
+                    .headers()
+                    .get(name)
+                    .and_then(|value| value.to_str().ok())
+                    .map(String::from)
+            };
+
+            let location = response
+                .status()
+                .is_redirection()
+                .then(|| header(LOCATION))
+                .flatten();
+
+            http.banner = header(SERVER);
+            http.headers = probe.capture_headers.then(|| {
+                let mut headers: BTreeMap<String, Vec<String>> = BTreeMap::new();
+
+                for (name, value) in response.headers() {
+                    headers
+                        .entry(name.as_str().to_string())
+                        .or_default()
+                        .push(String::from_utf8_lossy(value.as_bytes()).into_owned());
+                }
+
+                headers
+            });
+            http.hops.push(RedirectHop {
+                host: uri.host().unwrap_or_default().to_string(),
+                status: response.status().as_u16(),
+                location: location.clone(),
+            });
+
+            match location.and_then(|target| redirect_target(&uri, &target)) {
+                Some(next)
+                    if http.hops.len() <= probe.max_redirects && next.scheme() == uri.scheme() =>
+                {
+                    uri = next;
+                }
+                _ => {
+                    if probe.detect_language {
+                        let content_type = header(CONTENT_TYPE);
+                        http.content_language = header(CONTENT_LANGUAGE);
+
+                        let markup =
+                            body_prefix(response.into_body(), probe.max_body_size, deadline).await;
+                        let html = String::from_utf8_lossy(&markup);
+
+                        http.html_lang = html_lang(&html);
+                        http.charset = content_type
+                            .as_deref()
+                            .and_then(|value| attribute(value, "charset"))
+                            .or_else(|| html_charset(&html))
+                            .map(|charset| charset.to_lowercase());
+                        http.language = http
+                            .html_lang
+                            .as_deref()
+                            .and_then(primary_language)
+                            .or_else(|| {
+                                http.content_language.as_deref().and_then(primary_language)
+                            });
+                    }
+
+                    break;
+                }
+            }
+        }
+
+        Ok(DomainMetadata {
+            http_banner: http.banner.clone(),
+            http: Some(http),
+            ..DomainMetadata::new(self.fqdn.clone())
+        })
+    }
+
+    /// Asynchronous HTTPS banner lookup on port 443, using the TLS client
+    /// settings of `options`. Alongside the banner, the handshake is
+    /// recorded, including why the certificate failed validation if
+    /// invalid certificates are accepted.
+    ///
+    /// The CONNECT timeout is 5s, in line with [`DomainMetadata::http_banner`],
+    /// and the whole exchange times out after [`TlsOptions::timeout`].
+    ///
+    /// ### Features
+    ///
+    /// This function requires the `tls` feature toggled.
+    #[cfg(feature = "tls")]
+    pub async fn https_banner(&self, options: &TlsOptions) -> Result<DomainMetadata, Error> {
+        self.https_banner_with_context(options, &DEFAULT_CONTEXT)
+            .await
+    }
+
+    /// Asynchronous HTTPS banner lookup through the circuit breaker of
+    /// `context`, see [`DomainMetadata::https_banner`].
+    ///
+    /// ### Features
+    ///
+    /// This function requires the `tls` feature toggled.
+    #[cfg(feature = "tls")]
+    pub async fn https_banner_with_context(
+        &self,
+        options: &TlsOptions,
+        context: &EnrichmentContext,
+    ) -> Result<DomainMetadata, Error> {
+        self.https_banner_on(options, 443, context).await
+    }
+
+    #[cfg(feature = "tls")]
+    async fn https_banner_on(
+        &self,
+        options: &TlsOptions,
+        port: u16,
+        context: &EnrichmentContext,
+    ) -> Result<DomainMetadata, Error> {
+        context
+            .guarded(
+                EnrichmentKind::HttpBanner,
+                &format!("{}:{port}", self.fqdn),
+                options.timeout,
+                self.https_exchange(options, port),
+            )
+            .await
+    }
+
+    #[cfg(feature = "tls")]
+    async fn https_exchange(
+        &self,
+        options: &TlsOptions,
+        port: u16,
+    ) -> Result<DomainMetadata, Error> {
+        let error = |e: anyhow::Error| EnrichmentError::HttpsBannerError {
+            domain: self.fqdn.clone(),
+            error: e,
+        };
+
+        let (config, validation) = options.client_config()?;
+        let server_name = options.server_name(&self.fqdn)?;
+
+        let tcp = tokio::time::timeout(
+            Duration::from_secs(5),
+            TcpStream::connect((self.fqdn.as_str(), port)),
+        )
+        .await
+        .map_err(|e| error(anyhow::Error::msg(e)))?
+        .map_err(|e| error(anyhow::Error::msg(e)))?;
+
+        let stream = TlsConnector::from(Arc::new(config))
+            .connect(server_name, tcp)
+            .await
+            .map_err(|e| error(anyhow::Error::msg(e)))?;
+
+        let subject_alt_names = stream
+            .get_ref()
+            .1
+            .peer_certificates()
+            .and_then(|certificates| certificates.first())
+            .map(tls::subject_alt_names)
+            .unwrap_or_default();
+
+        let (mut sender, connection) = hyper::client::conn::handshake(stream)
+            .await
+            .map_err(|e| error(anyhow::Error::msg(e)))?;
+        tokio::spawn(connection);
+
+        let request = Request::builder()
+            .method("HEAD")
+            .uri("/")
+            .header("Host", self.fqdn.as_str())
+            .header("User-Agent", "github-juxhindb-twistrs-http-banner/1.0")
+            .body(Body::empty())
+            .map_err(|e| error(anyhow::Error::msg(e)))?;
+
+        let response = sender
+            .send_request(request)
+            .await
+            .map_err(|e| error(anyhow::Error::msg(e)))?;
 
-        Err(EnrichmentError::HttpBannerError {
-            domain: self.fqdn.clone(),
-            error: anyhow::Error::msg("unable to extract or parse server header from response"),
-        }
-        .into())
+        Ok(DomainMetadata {
+            http_banner: response
+                .headers()
+                .get(SERVER)
+                .and_then(|value| value.to_str().ok())
+                .map(String::from),
+            related_domains: Some(self.related_to(&subject_alt_names)),
+            tls: Some(TlsMetadata {
+                sni: match &options.sni {
+                    Sni::Domain => Some(self.fqdn.clone()),
+                    Sni::Name(name) => Some(name.clone()),
+                    Sni::Disabled => None,
+                },
+                validation_error: validation.take(),
+                subject_alt_names,
+            }),
+            ..DomainMetadata::new(self.fqdn.clone())
+        })
+    }
+
+    /// Asynchronous JARM fingerprinting of the TLS stack served on port
+    /// 443, see [`crate::jarm`]. Domains sharing a fingerprint are likely
+    /// served by the same kit or hosting setup.
+    ///
+    /// Unreachable domains, or domains not serving TLS, are fingerprinted
+    /// as [`crate::jarm::EMPTY_FINGERPRINT`].
+    ///
+    /// ```no_run
+    /// use twistrs::enrich::DomainMetadata;
+    ///
+    /// #[tokio::main]
+    /// async fn main() {
+    ///     let domain_metadata = DomainMetadata::new(String::from("www.phishdeck.com"));
+    ///     println!("{:?}", domain_metadata.jarm_fingerprint().await);
+    /// }
+    /// ```
+    ///
+    /// ### Features
+    ///
+    /// This function requires the `jarm` feature toggled.
+    #[cfg(feature = "jarm")]
+    pub async fn jarm_fingerprint(&self) -> Result<DomainMetadata, Error> {
+        self.jarm_fingerprint_with_context(&DEFAULT_CONTEXT).await
+    }
+
+    /// Asynchronous JARM fingerprinting through the circuit breaker of
+    /// `context`, see [`DomainMetadata::jarm_fingerprint`].
+    ///
+    /// ### Features
+    ///
+    /// This function requires the `jarm` feature toggled.
+    #[cfg(feature = "jarm")]
+    pub async fn jarm_fingerprint_with_context(
+        &self,
+        context: &EnrichmentContext,
+    ) -> Result<DomainMetadata, Error> {
+        let fingerprint = async { Ok(crate::jarm::fingerprint(&self.fqdn, 443).await) };
+
+        Ok(DomainMetadata {
+            jarm: Some(
+                context
+                    .guarded(
+                        EnrichmentKind::Jarm,
+                        &format!("{}:443", self.fqdn),
+                        JARM_TIMEOUT,
+                        fingerprint,
+                    )
+                    .await?,
+            ),
+            ..DomainMetadata::new(self.fqdn.clone())
+        })
     }
 
     /// Asynchronous cached `GeoIP` lookup. Interface deviates from the usual enrichment
@@ -293,18 +2054,18 @@ impl DomainMetadata {
     ///
     /// ### Features
     ///
-    /// This function requires the `geoip_lookup` feature toggled.
-    #[cfg(feature = "geoip_lookup")]
-    pub async fn geoip_lookup(
+    /// This function requires the `geoip` feature toggled.
+    #[cfg(feature = "geoip")]
+    pub async fn geoip_lookup<S: AsRef<[u8]>>(
         &self,
-        geoip: &maxminddb::Reader<Vec<u8>>,
+        geoip: &maxminddb::Reader<S>,
     ) -> Result<DomainMetadata, Error> {
         let mut result: Vec<(IpAddr, String)> = Vec::new();
 
-        match &self.ips {
+        match self.dns.ips() {
             Some(ips) => {
                 for ip in ips {
-                    if let Ok(lookup_result) = geoip.lookup::<geoip2::City>(*ip) {
+                    if let Ok(lookup_result) = geoip.lookup::<geoip2::City>(ip) {
                         let mut geoip_string = String::new();
 
                         if lookup_result.city.is_some() {
@@ -363,101 +2124,753 @@ impl DomainMetadata {
                             );
                         }
 
-                        result.push((*ip, geoip_string));
-                    }
-                }
+                        result.push((ip, geoip_string));
+                    }
+                }
+
+                Ok(DomainMetadata {
+                    fqdn: self.fqdn.clone(),
+                    dns: DnsRecords::default(),
+                    smtp: None,
+                    http_banner: None,
+                    geo_ip_lookups: Some(result),
+                    who_is_lookup: None,
+                    http: None,
+                    tls: None,
+                    shared_nameservers: None,
+                    mail_capable: None,
+                    mail_ports: None,
+                    who_is: None,
+                    anonymous_ip_lookups: None,
+                    isp_lookups: None,
+                    jarm: None,
+                    related_domains: None,
+                    neighbors: None,
+                    cached_until: None,
+                })
+            }
+            None => Ok(DomainMetadata::new(self.fqdn.clone())),
+        }
+    }
+
+    /// Asynchronous cached `GeoIP` Anonymous-IP lookup, flagging IP addresses
+    /// behind VPNs, proxies, Tor exit nodes or hosting ranges. Any reader
+    /// works, whether memory-mapped or buffer-backed.
+    ///
+    /// ```no_run
+    /// use twistrs::enrich::{DnsRecords, DomainMetadata};
+    ///
+    /// #[tokio::main]
+    /// async fn main() {
+    ///     let reader = maxminddb::Reader::open_readfile("./GeoIP2-Anonymous-IP.mmdb").unwrap();
+    ///     let mut domain_metadata = DomainMetadata::new(String::from("www.phishdeck.com"));
+    ///     domain_metadata.dns = DnsRecords::from_ips(["1.2.0.1".parse().unwrap()]);
+    ///
+    ///     let result = domain_metadata.anonymous_ip_lookup(&reader).await.unwrap();
+    ///     println!("{:?}", result.anonymized());
+    /// }
+    /// ```
+    ///
+    /// ### Features
+    ///
+    /// This function requires the `geoip` feature toggled.
+    #[cfg(feature = "geoip")]
+    pub async fn anonymous_ip_lookup<S: AsRef<[u8]>>(
+        &self,
+        database: &maxminddb::Reader<S>,
+    ) -> Result<DomainMetadata, Error> {
+        let lookups = self
+            .lookup_each(database, |record: geoip2::AnonymousIp| record.into())
+            .map(|lookups| DomainMetadata {
+                anonymous_ip_lookups: Some(lookups),
+                ..DomainMetadata::new(self.fqdn.clone())
+            });
+
+        Ok(lookups.unwrap_or_else(|| DomainMetadata::new(self.fqdn.clone())))
+    }
+
+    /// Asynchronous cached `GeoIP` ISP lookup, resolving the network operator
+    /// of each IP address. Any reader works, whether memory-mapped or
+    /// buffer-backed.
+    ///
+    /// ### Features
+    ///
+    /// This function requires the `geoip` feature toggled.
+    #[cfg(feature = "geoip")]
+    pub async fn isp_lookup<S: AsRef<[u8]>>(
+        &self,
+        database: &maxminddb::Reader<S>,
+    ) -> Result<DomainMetadata, Error> {
+        let lookups = self
+            .lookup_each(database, |record: geoip2::Isp<'_>| record.into())
+            .map(|lookups| DomainMetadata {
+                isp_lookups: Some(lookups),
+                ..DomainMetadata::new(self.fqdn.clone())
+            });
+
+        Ok(lookups.unwrap_or_else(|| DomainMetadata::new(self.fqdn.clone())))
+    }
+
+    /// Lookup each of the IPs in `database`, skipping the ones missing
+    /// from it. `None` if the domain has no IPs.
+    #[cfg(feature = "geoip")]
+    fn lookup_each<'de, S, R, T>(
+        &self,
+        database: &'de maxminddb::Reader<S>,
+        convert: impl Fn(R) -> T,
+    ) -> Option<Vec<(IpAddr, T)>>
+    where
+        S: AsRef<[u8]>,
+        R: Deserialize<'de>,
+    {
+        self.dns.ips().map(|ips| {
+            ips.into_iter()
+                .filter_map(|ip| {
+                    database
+                        .lookup::<R>(ip)
+                        .ok()
+                        .map(|record| (ip, convert(record)))
+                })
+                .collect()
+        })
+    }
+
+    /// Whether any of the IPs of the domain was flagged through
+    /// [`DomainMetadata::anonymous_ip_lookup`], `None` if it was not
+    /// performed.
+    pub fn anonymized(&self) -> Option<bool> {
+        self.anonymous_ip_lookups.as_ref().map(|lookups| {
+            lookups
+                .iter()
+                .any(|(_, anonymous_ip)| anonymous_ip.is_anonymized())
+        })
+    }
+
+    /// Asyncrhonous `WhoIs` lookup using cached `WhoIs` server config. Note that
+    /// the internal lookups are not async and so this should be considered
+    /// a heavy/slow call.
+    ///
+    /// ```
+    /// use twistrs::enrich::DomainMetadata;
+    ///
+    /// #[tokio::main]
+    /// async fn main() {
+    ///     let domain_metadata = DomainMetadata::new(String::from("www.phishdeck.com"));
+    ///     println!("{:?}", domain_metadata.whois_lookup().await);
+    /// }
+    /// ```
+    ///
+    /// ### Features
+    ///
+    /// This function requires the `whois` feature toggled.
+    #[cfg(feature = "whois")]
+    pub async fn whois_lookup(&self) -> Result<DomainMetadata, Error> {
+        self.whois_lookup_with(&WhoIsOptions::default()).await
+    }
+
+    /// Asyncrhonous `WhoIs` lookup, querying the server selected through
+    /// `options` rather than the compiled-in one where set. On top of
+    /// `who_is_lookup`, the raw response and its parsed fields are kept
+    /// in `who_is`.
+    ///
+    /// ```
+    /// use twistrs::enrich::{DomainMetadata, WhoIsOptions};
+    ///
+    /// #[tokio::main]
+    /// async fn main() {
+    ///     let mut options = WhoIsOptions::default();
+    ///     options.tld_servers.insert(String::from("com"), String::from("whois.verisign-grs.com"));
+    ///
+    ///     let domain_metadata = DomainMetadata::new(String::from("www.phishdeck.com"));
+    ///     println!("{:?}", domain_metadata.whois_lookup_with(&options).await);
+    /// }
+    /// ```
+    ///
+    /// ### Features
+    ///
+    /// This function requires the `whois` feature toggled.
+    #[cfg(feature = "whois")]
+    pub async fn whois_lookup_with(&self, options: &WhoIsOptions) -> Result<DomainMetadata, Error> {
+        self.whois_lookup_with_context(options, &DEFAULT_CONTEXT)
+            .await
+    }
+
+    /// Asyncrhonous `WhoIs` lookup as configured by `options`, through the
+    /// `WhoIs` client of `context`, see [`DomainMetadata::whois_lookup_with`].
+    ///
+    /// ### Features
+    ///
+    /// This function requires the `whois` feature toggled.
+    #[cfg(feature = "whois")]
+    pub async fn whois_lookup_with_context(
+        &self,
+        options: &WhoIsOptions,
+        context: &EnrichmentContext,
+    ) -> Result<DomainMetadata, Error> {
+        let error = |e| EnrichmentError::WhoIsLookupError {
+            domain: self.fqdn.to_string(),
+            error: e,
+        };
+
+        let mut result = DomainMetadata::new(self.fqdn.clone());
+
+        let mut whois_lookup_options =
+            WhoIsLookupOptions::from_string(&self.fqdn).map_err(error)?;
+
+        let server = options.server_for(&self.fqdn);
+        if let Some(host) = server {
+            whois_lookup_options.server = Some(WhoIsServerValue::from_string(host).map_err(error)?);
+        }
+
+        whois_lookup_options.timeout = Some(WHOIS_TIMEOUT);
+        whois_lookup_options.follow = 1; // Only allow at most one redirect
+
+        // Lookups through the same server share its circuit.
+        let destination = options.destination_for(&self.fqdn);
+
+        if let Some(breaker) = &context.breaker {
+            if !breaker.allow(EnrichmentKind::WhoIs, &destination) {
+                return Err(BreakerError::Open {
+                    kind: EnrichmentKind::WhoIs,
+                    destination,
+                }
+                .into());
+            }
+        }
+
+        // The client performs blocking I/O, off the async workers.
+        let started = Instant::now();
+        let whois = Arc::clone(&context.whois);
+        let lookup = tokio::task::spawn_blocking(move || whois.lookup(whois_lookup_options))
+            .await
+            .map_err(|e| EnrichmentError::WhoIsTaskError {
+                domain: self.fqdn.clone(),
+                error: anyhow::Error::msg(e),
+            })?;
+
+        if let Some(breaker) = &context.breaker {
+            // The client does not tell timeouts apart from other I/O
+            // errors, so failures taking the whole timeout count as such.
+            let timed_out = lookup.is_err() && started.elapsed() >= WHOIS_TIMEOUT;
+            breaker.record(EnrichmentKind::WhoIs, &destination, timed_out);
+        }
+
+        let raw = lookup.map_err(error)?;
+
+        result.who_is = Some(WhoIsMetadata {
+            server: server.map(str::to_string),
+            fields: whois_fields(&raw),
+            raw: raw.clone(),
+        });
+
+        result.who_is_lookup = Some(
+            raw.split("\r\n")
+                // The only entries we care about are the ones that start with 3 spaces.
+                // Ideally the whois_rust library would have parsed this nicely for us.
+                .filter(|s| s.starts_with("   "))
+                .collect::<Vec<&str>>()
+                .join("\n"),
+        );
+
+        Ok(result)
+    }
+
+    /// Performs the selected enrichment methods concurrently, merging
+    /// their results into a single `DomainMetadata`.
+    ///
+    /// Every enrichment runs to completion, even if others fail. Should
+    /// any fail, including the DNS resolution of domains that do not
+    /// resolve or kinds requiring a feature that is not toggled
+    /// (`UnsupportedKind`), a `PartialFailure` is returned holding the
+    /// error of each failed kind along with the results of the others.
+    ///
+    /// ```
+    /// use twistrs::enrich::{DomainMetadata, EnrichmentKinds};
+    ///
+    /// #[tokio::main]
+    /// async fn main() {
+    ///     let kinds = EnrichmentKinds::parse("dns,http_banner").unwrap();
+    ///     let domain_metadata = DomainMetadata::new(String::from("www.phishdeck.com"));
+    ///     println!("{:?}", domain_metadata.run(kinds).await);
+    /// }
+    /// ```
+    pub async fn run(&self, kinds: EnrichmentKinds) -> Result<DomainMetadata, Error> {
+        self.run_with_context(kinds, &DEFAULT_CONTEXT).await
+    }
+
+    /// Run the enrichments in `kinds` through the clients of `context`,
+    /// see [`DomainMetadata::run`].
+    ///
+    /// ```
+    /// use twistrs::enrich::{DomainMetadata, EnrichmentContext, EnrichmentKinds};
+    ///
+    /// #[tokio::main]
+    /// async fn main() {
+    ///     let context = EnrichmentContext::default();
+    ///     let domain_metadata = DomainMetadata::new(String::from("www.phishdeck.com"));
+    ///     println!("{:?}", domain_metadata.run_with_context(EnrichmentKinds::HTTP_BANNER, &context).await);
+    /// }
+    /// ```
+    pub async fn run_with_context(
+        &self,
+        kinds: EnrichmentKinds,
+        context: &EnrichmentContext,
+    ) -> Result<DomainMetadata, Error> {
+        let enrichments = kinds
+            .kinds()
+            .map(|kind| async move { (kind, self.enrichment(kind, context).await) });
+
+        let mut metadata = DomainMetadata::new(self.fqdn.clone());
+        let mut errors = vec![];
+
+        for (kind, result) in futures::future::join_all(enrichments).await {
+            match result {
+                Ok(enriched) => metadata = metadata.merge(enriched),
+                Err(e) => errors.push((kind, e)),
+            }
+        }
+
+        if errors.is_empty() {
+            Ok(metadata)
+        } else {
+            Err(EnrichmentError::PartialFailure {
+                metadata: Box::new(metadata),
+                errors,
+            }
+            .into())
+        }
+    }
+
+    async fn enrichment(
+        &self,
+        kind: EnrichmentKind,
+        context: &EnrichmentContext,
+    ) -> Result<DomainMetadata, Error> {
+        match kind {
+            EnrichmentKind::Dns => self.dns_resolvable_with_context(context).await,
+            EnrichmentKind::HttpBanner => self.http_banner_with_context(context).await,
+            #[cfg(feature = "dns")]
+            EnrichmentKind::DnsRecords => self.dns_records_with_context(context).await,
+            #[cfg(feature = "dns")]
+            EnrichmentKind::MailCapable => self.mail_capability_with_context(context).await,
+            #[cfg(feature = "jarm")]
+            EnrichmentKind::Jarm => self.jarm_fingerprint_with_context(context).await,
+            #[cfg(feature = "smtp")]
+            EnrichmentKind::Mx => self.mx_check_with_context(context).await,
+            #[cfg(feature = "whois")]
+            EnrichmentKind::WhoIs => {
+                self.whois_lookup_with_context(&WhoIsOptions::default(), context)
+                    .await
+            }
+            #[allow(unreachable_patterns)]
+            unsupported => Err(EnrichmentError::UnsupportedKind { kind: unsupported }.into()),
+        }
+    }
+
+    /// Fill in the fields of `self` that `other` has and `self` lacks.
+    pub(crate) fn merge(self, other: DomainMetadata) -> DomainMetadata {
+        DomainMetadata {
+            fqdn: self.fqdn,
+            dns: self.dns.merge(other.dns),
+            smtp: self.smtp.or(other.smtp),
+            http_banner: self.http_banner.or(other.http_banner),
+            geo_ip_lookups: self.geo_ip_lookups.or(other.geo_ip_lookups),
+            who_is_lookup: self.who_is_lookup.or(other.who_is_lookup),
+            who_is: self.who_is.or(other.who_is),
+            anonymous_ip_lookups: self.anonymous_ip_lookups.or(other.anonymous_ip_lookups),
+            isp_lookups: self.isp_lookups.or(other.isp_lookups),
+            jarm: self.jarm.or(other.jarm),
+            related_domains: self.related_domains.or(other.related_domains),
+            neighbors: self.neighbors.or(other.neighbors),
+            http: self.http.or(other.http),
+            tls: self.tls.or(other.tls),
+            shared_nameservers: self.shared_nameservers.or(other.shared_nameservers),
+            mail_capable: self.mail_capable.or(other.mail_capable),
+            mail_ports: self.mail_ports.or(other.mail_ports),
+            cached_until: self.cached_until.or(other.cached_until),
+        }
+    }
+}
+
+/// Lookup the `record_type` records of `fqdn`, treating a lack of records
+/// as an empty answer.
+#[cfg(feature = "dns")]
+async fn lookup_records(
+    resolver: &TokioAsyncResolver,
+    fqdn: &str,
+    record_type: DnsRecordType,
+) -> Result<Vec<DnsRecord>, trust_dns_resolver::error::ResolveError> {
+    // Fully qualified, so that the search domains are not appended.
+    let name = format!("{}.", fqdn.trim_end_matches('.'));
+
+    match resolver.lookup(name, record_type.record_type()).await {
+        Ok(lookup) => Ok(lookup
+            .records()
+            .iter()
+            // Answers may include the CNAME chain leading up to them.
+            .filter(|record| record.record_type() == record_type.record_type())
+            .filter_map(|record| {
+                record.data().map(|data| DnsRecord {
+                    record_type,
+                    value: data.to_string(),
+                    ttl: Some(record.ttl()),
+                })
+            })
+            .collect()),
+        Err(e) if matches!(e.kind(), ResolveErrorKind::NoRecordsFound { .. }) => Ok(vec![]),
+        Err(e) => Err(e),
+    }
+}
+
+/// Whether `exchange` accepts connections on `port`. With the `tls`
+/// feature toggled, the TLS handshake has to complete on ports 465 and
+/// 587 as well.
+#[cfg(feature = "dns")]
+async fn probe_mail_port(exchange: &str, port: MailPort) -> bool {
+    let Ok(stream) = TcpStream::connect((exchange, port.number())).await else {
+        return false;
+    };
+
+    #[cfg(feature = "tls")]
+    match port {
+        MailPort::Smtp => true,
+        MailPort::Submissions => tls_handshake(exchange, stream).await.is_some(),
+        MailPort::Submission => {
+            let mut buffered = tokio::io::BufReader::new(stream);
+
+            starttls(&mut buffered).await.is_some()
+                && tls_handshake(exchange, buffered.into_inner())
+                    .await
+                    .is_some()
+        }
+    }
+
+    #[cfg(not(feature = "tls"))]
+    {
+        drop((stream, port));
+        true
+    }
+}
+
+/// Complete a TLS handshake with `exchange` over `stream`, accepting any
+/// certificate.
+#[cfg(all(feature = "dns", feature = "tls"))]
+async fn tls_handshake(exchange: &str, stream: TcpStream) -> Option<()> {
+    let options = TlsOptions {
+        accept_invalid: true,
+        ..TlsOptions::default()
+    };
+    let (config, _) = options.client_config().ok()?;
+    let server_name = options.server_name(exchange).ok()?;
+
+    TlsConnector::from(Arc::new(config))
+        .connect(server_name, stream)
+        .await
+        .ok()?;
+
+    Some(())
+}
+
+/// Negotiate `STARTTLS` over the SMTP session `stream`, up to the point
+/// where the TLS handshake can start.
+#[cfg(all(feature = "dns", feature = "tls"))]
+async fn starttls<S>(stream: &mut S) -> Option<()>
+where
+    S: AsyncBufRead + AsyncWrite + Unpin,
+{
+    smtp_reply(stream, 220).await?;
+    stream
+        .write_all(b"EHLO twistrs.example.com\r\n")
+        .await
+        .ok()?;
+
+    let extensions = smtp_reply(stream, 250).await?;
+    if !extensions.iter().any(|line| {
+        line.get(4..)
+            .is_some_and(|extension| extension.trim().eq_ignore_ascii_case("STARTTLS"))
+    }) {
+        return None;
+    }
+
+    stream.write_all(b"STARTTLS\r\n").await.ok()?;
+    smtp_reply(stream, 220).await?;
+
+    Some(())
+}
+
+/// Lines of the next (possibly multiline) SMTP reply read from `stream`,
+/// if its code is `expected`.
+#[cfg(all(feature = "dns", feature = "tls"))]
+async fn smtp_reply<S>(stream: &mut S, expected: u16) -> Option<Vec<String>>
+where
+    S: AsyncBufRead + Unpin,
+{
+    let mut lines = vec![];
+
+    loop {
+        let mut raw = String::new();
+        if stream.read_line(&mut raw).await.ok()? == 0 {
+            return None;
+        }
+
+        let line = raw.trim_end().to_string();
+        if line.get(..3)?.parse::<u16>().ok()? != expected {
+            return None;
+        }
+
+        // Lines of a multiline reply but the last have a `-` after the
+        // code.
+        let last = line.as_bytes().get(3) != Some(&b'-');
+        lines.push(line);
+
+        if last {
+            return Some(lines);
+        }
+    }
+}
+
+/// Hosts of the MX `records` in order of preference, without the trailing
+/// dot. Null MX records (i.e. `0 .`) are skipped.
+pub(crate) fn mail_exchanges(records: &[DnsRecord]) -> Vec<String> {
+    let mut exchanges: Vec<(u16, String)> = records
+        .iter()
+        .filter(|record| record.record_type == DnsRecordType::Mx)
+        .filter_map(|record| {
+            let (preference, exchange) = record.value.split_once(' ')?;
+            let host = exchange.trim().trim_end_matches('.');
+
+            if host.is_empty() {
+                return None;
+            }
+
+            Some((preference.parse().ok()?, host.to_lowercase()))
+        })
+        .collect();
+
+    exchanges.sort();
+    exchanges.into_iter().map(|(_, host)| host).collect()
+}
+
+/// Minimum similarity between registrable labels for a related domain to
+/// be considered a lookalike of the base domain.
+const LOOKALIKE_SIMILARITY: f64 = 0.75;
+
+/// Parse the `key: value` fields of a raw `WhoIs` response, skipping
+/// comments and notices (e.g. `% ...` or `>>> Last update ... <<<`).
+#[cfg_attr(not(feature = "whois"), allow(dead_code))]
+fn whois_fields(raw: &str) -> BTreeMap<String, Vec<String>> {
+    let mut fields: BTreeMap<String, Vec<String>> = BTreeMap::new();
+
+    for line in raw.lines().map(str::trim) {
+        if line.starts_with('%') || line.starts_with('#') || line.starts_with(">>>") {
+            continue;
+        }
+
+        let Some((key, value)) = line.split_once(':') else {
+            continue;
+        };
+
+        let (name, field) = (key.trim(), value.trim());
+        if !name.is_empty() && !field.is_empty() {
+            fields
+                .entry(name.to_string())
+                .or_default()
+                .push(field.to_string());
+        }
+    }
+
+    fields
+}
+
+/// Bytes of markup read by [`HttpProbe::detect_language`] by default, in
+/// which the `html` and `meta` elements are expected to appear.
+const MARKUP_LIMIT: usize = 64 * 1024;
+
+/// Longest an [`HttpProbe`] takes by default.
+const PROBE_TIMEOUT: Duration = Duration::from_secs(30);
+
+/// First `limit` bytes of `body`, or fewer if it is shorter, fails midway
+/// or is still being read by `deadline`.
+async fn body_prefix(
+    mut body: Body,
+    limit: usize,
+    deadline: Option<tokio::time::Instant>,
+) -> Vec<u8> {
+    let mut prefix = vec![];
+
+    while prefix.len() < limit {
+        match within(deadline, body.data()).await.flatten() {
+            Some(Ok(chunk)) => prefix.extend_from_slice(&chunk),
+            _ => break,
+        }
+    }
+
+    prefix.truncate(limit);
+    prefix
+}
+
+/// Output of `future`, or `None` if it did not complete by `deadline`.
+async fn within<F: Future>(deadline: Option<tokio::time::Instant>, future: F) -> Option<F::Output> {
+    match deadline {
+        Some(instant) => tokio::time::timeout_at(instant, future).await.ok(),
+        None => Some(future.await),
+    }
+}
 
-                Ok(DomainMetadata {
-                    fqdn: self.fqdn.clone(),
-                    ips: None,
-                    smtp: None,
-                    http_banner: None,
-                    geo_ip_lookups: Some(result),
-                    who_is_lookup: None,
-                })
-            }
-            None => Ok(DomainMetadata::new(self.fqdn.clone())),
+/// Value of the `name` attribute (or parameter, e.g. `charset` of a
+/// `Content-Type` header) within `tag`, case-insensitively.
+fn attribute(tag: &str, name: &str) -> Option<String> {
+    // ASCII lowercasing keeps the byte offsets of the original.
+    let lowercase = tag.to_ascii_lowercase();
+    let mut offset = 0;
+
+    while let Some(found) = lowercase[offset..].find(name) {
+        let start = offset + found;
+        offset = start + name.len();
+
+        let delimited = lowercase[..start]
+            .chars()
+            .next_back()
+            .is_some_and(|c| c.is_ascii_whitespace() || matches!(c, '"' | '\'' | ';'));
+        let rest = lowercase[offset..].trim_start();
+
+        if !delimited || !rest.starts_with('=') {
+            continue;
         }
+
+        let raw = tag[lowercase.len() - rest.len() + 1..].trim_start();
+        let value = match raw.chars().next() {
+            Some(quote @ ('"' | '\'')) => raw[1..].split(quote).next(),
+            _ => raw
+                .split(|c: char| c.is_ascii_whitespace() || matches!(c, '>' | ';' | '"' | '\''))
+                .next(),
+        };
+
+        return value
+            .map(str::trim)
+            .filter(|trimmed| !trimmed.is_empty())
+            .map(String::from);
     }
 
-    /// Asyncrhonous `WhoIs` lookup using cached `WhoIs` server config. Note that
-    /// the internal lookups are not async and so this should be considered
-    /// a heavy/slow call.
-    ///
-    /// ```
-    /// use twistrs::enrich::DomainMetadata;
-    ///
-    /// #[tokio::main]
-    /// async fn main() {
-    ///     let domain_metadata = DomainMetadata::new(String::from("www.phishdeck.com"));
-    ///     println!("{:?}", domain_metadata.whois_lookup().await);
-    /// }
-    /// ```
-    ///
-    /// ### Features
-    ///
-    /// This function requires the `whois_lookup` feature toggled.
-    #[cfg(feature = "whois_lookup")]
-    pub async fn whois_lookup(&self) -> Result<DomainMetadata, Error> {
-        let mut result = DomainMetadata::new(self.fqdn.clone());
+    None
+}
 
-        let mut whois_lookup_options =
-            WhoIsLookupOptions::from_string(&self.fqdn).map_err(|e| {
-                EnrichmentError::WhoIsLookupError {
-                    domain: self.fqdn.to_string(),
-                    error: e,
-                }
-            })?;
+/// Opening tags of the `name` elements within `html`, e.g. `<meta ...>`.
+fn tags<'a>(html: &'a str, name: &str) -> impl Iterator<Item = &'a str> + 'a {
+    let lowercase = html.to_ascii_lowercase();
+    let opening = format!("<{name}");
 
-        whois_lookup_options.timeout = Some(std::time::Duration::from_secs(5));
-        whois_lookup_options.follow = 1; // Only allow at most one redirect
+    let starts: Vec<usize> = lowercase
+        .match_indices(&opening)
+        .map(|(start, _)| start)
+        .filter(|start| {
+            lowercase[start + opening.len()..]
+                .chars()
+                .next()
+                .is_some_and(|c| c.is_ascii_whitespace() || c == '>' || c == '/')
+        })
+        .collect();
 
-        result.who_is_lookup = Some(
-            WHOIS
-                .lookup(whois_lookup_options)
-                .map_err(|e| EnrichmentError::WhoIsLookupError {
-                    domain: self.fqdn.to_string(),
-                    error: e,
-                })?
-                .split("\r\n")
-                // The only entries we care about are the ones that start with 3 spaces.
-                // Ideally the whois_rust library would have parsed this nicely for us.
-                .filter(|s| s.starts_with("   "))
-                .collect::<Vec<&str>>()
-                .join("\n"),
-        );
+    starts.into_iter().map(move |start| {
+        let end = html[start..]
+            .find('>')
+            .map_or(html.len(), |end| start + end);
+        &html[start..end]
+    })
+}
 
-        Ok(result)
-    }
+/// `lang` attribute of the `html` element of `html`.
+fn html_lang(html: &str) -> Option<String> {
+    tags(html, "html").find_map(|tag| attribute(tag, "lang"))
+}
 
-    /// Performs all FQDN enrichment methods on a given FQDN.
-    /// This is the only function that returns a `Vec<DomainMetadata>`.
-    ///
-    /// # Panics
-    ///
-    /// Currently panics if any of the internal enrichment methods returns
-    /// an Err.
-    pub async fn all(&self) -> Result<Vec<DomainMetadata>, Error> {
-        // @CLEANUP(JDB): This should use try_join! in the future instead
-        #[cfg(feature = "smtp_lookup")]
-        let mx_check = self.mx_check();
+/// Charset declared by the `meta` elements of `html`, either through their
+/// `charset` attribute or a `Content-Type` in their `content` attribute.
+fn html_charset(html: &str) -> Option<String> {
+    tags(html, "meta").find_map(|tag| attribute(tag, "charset"))
+}
 
-        let result = futures::join!(self.dns_resolvable(), self.http_banner());
+/// Lowercase primary subtag of the first language of `tags`, e.g. `de` out
+/// of `de-CH, en;q=0.5`.
+fn primary_language(tags: &str) -> Option<String> {
+    let primary = tags.split(',').next()?.split(';').next()?.trim();
+    let subtag = primary.split(['-', '_']).next()?;
 
-        Ok(vec![
-            result.0.unwrap(),
-            #[cfg(feature = "smtp_lookup")]
-            mx_check.await.unwrap(),
-            result.1.unwrap(),
-        ])
+    (matches!(subtag.len(), 2 | 3) && subtag.chars().all(|c| c.is_ascii_alphabetic()))
+        .then(|| subtag.to_ascii_lowercase())
+}
+
+/// Resolve the `location` of a redirect received from `current`, which
+/// may be relative to it.
+fn redirect_target(current: &Uri, location: &str) -> Option<Uri> {
+    let target: Uri = location.parse().ok()?;
+
+    if target.scheme().is_some() {
+        return Some(target);
     }
+
+    let mut parts = target.into_parts();
+    parts.scheme = current.scheme().cloned();
+    parts.authority = current.authority().cloned();
+
+    Uri::from_parts(parts).ok()
+}
+
+/// Enrich the newline-delimited FQDNs read from `reader` (e.g. a file or
+/// stdin) through `pipeline`, without generating any permutations.
+///
+/// Blank lines and lines starting with `#` are skipped. Results are
+/// yielded in completion order, and the stream ends after yielding an
+/// error should reading fail. The result filter of `pipeline` does not
+/// apply, see [`Pipeline::run_fqdns`].
+pub fn from_reader<'a, R>(
+    reader: R,
+    pipeline: &'a Pipeline,
+) -> impl Stream<Item = Result<DomainMetadata, Error>> + 'a
+where
+    R: AsyncBufRead + Unpin + 'a,
+{
+    let lines = stream::unfold(Some(reader.lines()), |state| async move {
+        let mut lines = state?;
+
+        match lines.next_line().await {
+            Ok(Some(line)) => Some((Ok(line), Some(lines))),
+            Ok(None) => None,
+            Err(e) => Some((
+                Err(EnrichmentError::ReadError {
+                    error: anyhow::Error::msg(e),
+                }
+                .into()),
+                None,
+            )),
+        }
+    });
+
+    let fqdns = lines.filter_map(|line| {
+        ready(match line {
+            Ok(fqdn) => {
+                let trimmed = fqdn.trim();
+
+                if trimmed.is_empty() || trimmed.starts_with('#') {
+                    None
+                } else {
+                    Some(Ok(trimmed.to_string()))
+                }
+            }
+            Err(e) => Some(Err(e)),
+        })
+    });
+
+    pipeline.run_fqdns(fqdns)
 }
 
 #[cfg(test)]
 mod tests {
     use super::*;
 
-    #[cfg(feature = "geoip_lookup")]
+    use tokio::io::{AsyncReadExt, AsyncWriteExt};
+
+    #[cfg(feature = "geoip")]
     use maxminddb;
 
     use futures::executor::block_on;
@@ -469,7 +2882,7 @@ mod tests {
     }
 
     #[tokio::test]
-    #[cfg(feature = "geoip_lookup")]
+    #[cfg(feature = "geoip")]
     async fn test_geoip_lookup() {
         let domain_metadata = DomainMetadata::new(String::from("example.com"))
             .dns_resolvable()
@@ -485,9 +2898,689 @@ mod tests {
     }
 
     #[tokio::test]
-    #[cfg(feature = "whois_lookup")]
+    #[cfg(feature = "whois")]
     async fn test_whois_lookup() {
         let domain_metadata = DomainMetadata::new(String::from("example.com"));
         assert!(domain_metadata.whois_lookup().await.is_ok());
     }
+
+    #[tokio::test]
+    async fn test_enrichment_kinds() {
+        assert_eq!(
+            EnrichmentKinds::parse("dns, http_banner").unwrap(),
+            EnrichmentKinds::DNS | EnrichmentKinds::HTTP_BANNER
+        );
+        assert_eq!(
+            EnrichmentKinds::parse("ALL").unwrap(),
+            EnrichmentKinds::supported()
+        );
+        #[cfg(not(feature = "whois"))]
+        assert!(!EnrichmentKinds::parse("all")
+            .unwrap()
+            .contains(EnrichmentKinds::WHOIS));
+        assert_eq!(
+            "WhoIs".parse::<EnrichmentKind>().unwrap(),
+            EnrichmentKind::WhoIs
+        );
+        assert!(EnrichmentKinds::parse("dns,nope").is_err());
+
+        let domain_metadata = DomainMetadata::new(String::from("example.invalid"));
+        assert_eq!(
+            domain_metadata.run(EnrichmentKinds::empty()).await.unwrap(),
+            domain_metadata
+        );
+
+        #[cfg(not(feature = "whois"))]
+        assert!(domain_metadata.run(EnrichmentKinds::WHOIS).await.is_err());
+    }
+
+    #[tokio::test]
+    #[cfg(not(feature = "whois"))]
+    async fn test_run_collects_errors() {
+        let listener = net::TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let fqdn = listener.local_addr().unwrap().to_string();
+
+        tokio::spawn(async move {
+            let (mut stream, _) = listener.accept().await.unwrap();
+            let mut request = [0; 1024];
+            let _ = stream.read(&mut request).await.unwrap();
+
+            stream
+                .write_all(
+                    b"HTTP/1.1 200 OK\r\nServer: twistrs\r\n\
+                      Content-Length: 0\r\nConnection: close\r\n\r\n",
+                )
+                .await
+                .unwrap();
+        });
+
+        let failed = DomainMetadata::new(fqdn)
+            .run(EnrichmentKinds::HTTP_BANNER | EnrichmentKinds::WHOIS)
+            .await;
+
+        let Err(Error::EnrichmentError(EnrichmentError::PartialFailure { metadata, errors })) =
+            failed
+        else {
+            panic!("expected a partial failure, got {failed:?}");
+        };
+
+        assert_eq!(metadata.http_banner.as_deref(), Some("twistrs"));
+        assert_eq!(errors.len(), 1);
+        assert_eq!(errors[0].0, EnrichmentKind::WhoIs);
+    }
+
+    #[test]
+    fn test_min_ttl() {
+        let record = |record_type, value: &str, ttl| DnsRecord {
+            ttl: Some(ttl),
+            ..DnsRecord::new(record_type, value)
+        };
+
+        let mut domain_metadata = DomainMetadata::new(String::from("example.com"));
+        assert_eq!(domain_metadata.min_ttl(), None);
+
+        domain_metadata.dns.a = Some(vec![record(DnsRecordType::A, "93.184.216.34", 3600)]);
+        domain_metadata.dns.mx = Some(vec![record(DnsRecordType::Mx, "10 mail.example.com.", 60)]);
+        domain_metadata.dns.ns = Some(vec![record(
+            DnsRecordType::Ns,
+            "a.iana-servers.net.",
+            86400,
+        )]);
+        domain_metadata.dns.aaaa = Some(vec![DnsRecord::new(DnsRecordType::Aaaa, "::1")]);
+        assert_eq!(domain_metadata.min_ttl(), Some(60));
+        assert_eq!(
+            "dns-records".parse::<EnrichmentKind>().unwrap(),
+            EnrichmentKind::DnsRecords
+        );
+    }
+
+    #[test]
+    #[allow(deprecated)]
+    fn test_dns_records() {
+        let mut dns = DnsRecords::default();
+        assert_eq!(dns.ips(), None);
+        assert!(!dns.resolves());
+
+        dns.set(DnsRecordType::A, vec![]);
+        assert_eq!(dns.ips(), Some(vec![]));
+        assert!(!dns.resolves());
+
+        let ips: Vec<IpAddr> = vec!["93.184.216.34".parse().unwrap(), "::1".parse().unwrap()];
+        let domain_metadata = DomainMetadata {
+            dns: DnsRecords::from_ips(ips.clone()),
+            ..DomainMetadata::new(String::from("example.com"))
+        };
+        assert_eq!(domain_metadata.dns.a.as_ref().map(Vec::len), Some(1));
+        assert_eq!(domain_metadata.dns.ips(), Some(ips.clone()));
+        assert_eq!(domain_metadata.ips(), Some(ips));
+
+        // Record types already looked up are kept when merging.
+        let mut other = DnsRecords::default();
+        other.set(DnsRecordType::A, vec![]);
+        other.set(
+            DnsRecordType::Txt,
+            vec![DnsRecord::new(DnsRecordType::Txt, "v=spf1 -all")],
+        );
+        let merged = domain_metadata.dns.clone().merge(other);
+        assert_eq!(merged.a, domain_metadata.dns.a);
+        assert_eq!(merged.iter().count(), 3);
+
+        let json = serde_json::to_string(&domain_metadata).unwrap();
+        assert_eq!(
+            serde_json::from_str::<DomainMetadata>(&json).unwrap(),
+            domain_metadata
+        );
+
+        // Records persisted with the flat `ips` list keep their addresses.
+        let legacy: DomainMetadata =
+            serde_json::from_str(r#"{"fqdn":"example.com","ips":["93.184.216.34","::1"]}"#)
+                .unwrap();
+        assert_eq!(legacy.dns, domain_metadata.dns);
+        let unresolved: DomainMetadata =
+            serde_json::from_str(r#"{"fqdn":"example.com","ips":null}"#).unwrap();
+        assert_eq!(unresolved.dns, DnsRecords::default());
+    }
+
+    #[test]
+    fn test_mail_exchanges() {
+        let record = |record_type, value: &str| DnsRecord {
+            ttl: Some(300),
+            ..DnsRecord::new(record_type, value)
+        };
+
+        let records = vec![
+            record(DnsRecordType::Mx, "20 MX2.example.com."),
+            record(DnsRecordType::A, "93.184.216.34"),
+            record(DnsRecordType::Mx, "10 mx1.example.com."),
+        ];
+        assert_eq!(
+            mail_exchanges(&records),
+            vec![
+                String::from("mx1.example.com"),
+                String::from("mx2.example.com")
+            ]
+        );
+
+        // Null MX, the domain explicitly does not accept mail.
+        assert!(mail_exchanges(&[record(DnsRecordType::Mx, "0 .")]).is_empty());
+        assert_eq!(
+            MailOptions::all()
+                .ports
+                .iter()
+                .map(|port| port.number())
+                .collect::<Vec<u16>>(),
+            vec![25, 465, 587]
+        );
+        assert_eq!(
+            "mail-capable".parse::<EnrichmentKind>().unwrap(),
+            EnrichmentKind::MailCapable
+        );
+    }
+
+    #[test]
+    fn test_anonymized() {
+        let mut domain_metadata = DomainMetadata::new(String::from("example.com"));
+        assert_eq!(domain_metadata.anonymized(), None);
+
+        let ip: IpAddr = "203.0.113.1".parse().unwrap();
+        domain_metadata.anonymous_ip_lookups = Some(vec![(ip, AnonymousIpMetadata::default())]);
+        assert_eq!(domain_metadata.anonymized(), Some(false));
+
+        domain_metadata.anonymous_ip_lookups = Some(vec![
+            (ip, AnonymousIpMetadata::default()),
+            (
+                ip,
+                AnonymousIpMetadata {
+                    hosting_provider: true,
+                    ..AnonymousIpMetadata::default()
+                },
+            ),
+        ]);
+        assert_eq!(domain_metadata.anonymized(), Some(true));
+    }
+
+    #[test]
+    fn test_related_domains() {
+        let names = [
+            String::from("*.examplle.com"),
+            String::from("EXAMPLLE.com"),
+            String::from("www.example.com"),
+            String::from("login-example.org"),
+            String::from("cdn.unrelated.net"),
+        ];
+
+        let mut domain_metadata = DomainMetadata::new(String::from("examplle.com"));
+        domain_metadata.related_domains = Some(domain_metadata.related_to(&names));
+        domain_metadata.classify_related(&Domain::new("example.com").unwrap());
+
+        let relations: BTreeMap<String, Option<Relation>> = domain_metadata
+            .related_domains
+            .unwrap()
+            .into_iter()
+            .map(|related| (related.fqdn, related.relation))
+            .collect();
+
+        assert_eq!(relations.len(), 3);
+        assert_eq!(relations["www.example.com"], Some(Relation::Base));
+        assert_eq!(relations["login-example.org"], Some(Relation::Lookalike));
+        assert_eq!(relations["cdn.unrelated.net"], Some(Relation::Unrelated));
+    }
+
+    #[test]
+    fn test_whois_options() {
+        let mut options = WhoIsOptions::default();
+        assert_eq!(options.server_for("example.com"), None);
+
+        options
+            .tld_servers
+            .insert(String::from("uk"), String::from("whois.nic.uk"));
+        options
+            .tld_servers
+            .insert(String::from(".co.uk"), String::from("whois.co.uk"));
+
+        assert_eq!(options.server_for("Example.CO.UK."), Some("whois.co.uk"));
+        assert_eq!(options.server_for("example.uk"), Some("whois.nic.uk"));
+        assert_eq!(options.server_for("example.fuk"), None);
+        assert_eq!(options.destination_for("example.co.uk"), "whois.co.uk");
+        assert_eq!(options.destination_for("Example.FUK."), "fuk");
+
+        options.server = Some(String::from("whois.example.net"));
+        assert_eq!(
+            options.server_for("example.co.uk"),
+            Some("whois.example.net")
+        );
+    }
+
+    #[test]
+    fn test_whois_fields() {
+        let raw = "% comment: ignored\r\n   Domain Name: EXAMPLE.COM\r\n   \
+                   Name Server: A.IANA-SERVERS.NET\r\n   Name Server: B.IANA-SERVERS.NET\r\n\
+                   Registrant Email:\r\n>>> Last update of whois database: 2024-01-01 <<<\r\n";
+
+        let fields = whois_fields(raw);
+        assert_eq!(fields.len(), 2);
+        assert_eq!(fields["Domain Name"], vec![String::from("EXAMPLE.COM")]);
+        assert_eq!(
+            fields["Name Server"],
+            vec![
+                String::from("A.IANA-SERVERS.NET"),
+                String::from("B.IANA-SERVERS.NET")
+            ]
+        );
+    }
+
+    #[test]
+    fn test_redirect_target() {
+        let current: Uri = "http://example.com/a/b".parse().unwrap();
+
+        assert_eq!(
+            redirect_target(&current, "/landing").unwrap(),
+            "http://example.com/landing"
+        );
+        assert_eq!(
+            redirect_target(&current, "https://example.net/").unwrap(),
+            "https://example.net/"
+        );
+        assert!(redirect_target(&current, "not a uri").is_none());
+    }
+
+    #[tokio::test]
+    async fn test_http_banner_following() {
+        let listener = net::TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let fqdn = listener.local_addr().unwrap().to_string();
+
+        tokio::spawn(async move {
+            let responses = [
+                "HTTP/1.1 301 Moved Permanently\r\nLocation: /landing\r\n",
+                "HTTP/1.1 302 Found\r\nLocation: https://example.com/\r\n",
+            ];
+
+            for head in responses {
+                let (mut stream, _) = listener.accept().await.unwrap();
+                let mut request = [0; 1024];
+                let _ = stream.read(&mut request).await.unwrap();
+
+                let response = format!(
+                    "{head}Server: twistrs\r\nContent-Length: 0\r\nConnection: close\r\n\r\n"
+                );
+                stream.write_all(response.as_bytes()).await.unwrap();
+            }
+        });
+
+        let domain_metadata = DomainMetadata::new(fqdn);
+        let followed = domain_metadata.http_banner_following(5).await.unwrap();
+        let http = followed.http.unwrap();
+
+        assert_eq!(followed.http_banner.as_deref(), Some("twistrs"));
+        assert_eq!(
+            http.hops
+                .iter()
+                .map(|hop| (hop.host.as_str(), hop.status))
+                .collect::<Vec<_>>(),
+            vec![("127.0.0.1", 301), ("127.0.0.1", 302)]
+        );
+        assert_eq!(
+            http.hops[1].location.as_deref(),
+            Some("https://example.com/")
+        );
+        assert!(http.headers.is_none());
+    }
+
+    #[tokio::test]
+    async fn test_http_probe_headers() {
+        let listener = net::TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let fqdn = listener.local_addr().unwrap().to_string();
+
+        tokio::spawn(async move {
+            let (mut stream, _) = listener.accept().await.unwrap();
+            let mut request = [0; 1024];
+            let _ = stream.read(&mut request).await.unwrap();
+
+            stream
+                .write_all(
+                    b"HTTP/1.1 200 OK\r\nX-Powered-By: PHP\r\nVia: a\r\nVia: b\r\n\
+                      Content-Length: 0\r\nConnection: close\r\n\r\n",
+                )
+                .await
+                .unwrap();
+        });
+
+        let probe = HttpProbe {
+            max_redirects: 0,
+            capture_headers: true,
+            ..HttpProbe::default()
+        };
+        let probed = DomainMetadata::new(fqdn).http_probe(&probe).await.unwrap();
+        let headers = probed.http.unwrap().headers.unwrap();
+
+        assert_eq!(probed.http_banner, None);
+        assert_eq!(headers["x-powered-by"], vec!["PHP"]);
+        assert_eq!(headers["via"], vec!["a", "b"]);
+    }
+
+    #[tokio::test]
+    async fn test_http_probe_language() {
+        let listener = net::TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let fqdn = listener.local_addr().unwrap().to_string();
+
+        tokio::spawn(async move {
+            let (mut stream, _) = listener.accept().await.unwrap();
+            let mut request = [0; 1024];
+            let read = stream.read(&mut request).await.unwrap();
+            assert!(request[..read].starts_with(b"GET "));
+
+            let body = "<!DOCTYPE html><HTML class=\"no-js\" Lang=\"de-CH\"><head>\
+                        <meta http-equiv=\"Content-Type\" content=\"text/html; charset=ISO-8859-1\">\
+                        </head></html>";
+            let response = format!(
+                "HTTP/1.1 200 OK\r\nContent-Language: fr\r\nContent-Length: {}\r\n\
+                 Connection: close\r\n\r\n{body}",
+                body.len()
+            );
+            stream.write_all(response.as_bytes()).await.unwrap();
+        });
+
+        let probe = HttpProbe {
+            detect_language: true,
+            ..HttpProbe::default()
+        };
+        let http = DomainMetadata::new(fqdn)
+            .http_probe(&probe)
+            .await
+            .unwrap()
+            .http
+            .unwrap();
+
+        assert_eq!(http.content_language.as_deref(), Some("fr"));
+        assert_eq!(http.html_lang.as_deref(), Some("de-CH"));
+        assert_eq!(http.charset.as_deref(), Some("iso-8859-1"));
+        assert_eq!(http.language.as_deref(), Some("de"));
+    }
+
+    #[tokio::test]
+    async fn test_http_probe_limits() {
+        // Serves the start of a body that never completes, or nothing at
+        // all past the request.
+        async fn serve(respond: bool) -> String {
+            let listener = net::TcpListener::bind("127.0.0.1:0").await.unwrap();
+            let fqdn = listener.local_addr().unwrap().to_string();
+
+            tokio::spawn(async move {
+                let (mut stream, _) = listener.accept().await.unwrap();
+                let mut request = [0; 1024];
+                let _ = stream.read(&mut request).await.unwrap();
+
+                if respond {
+                    stream
+                        .write_all(
+                            b"HTTP/1.1 200 OK\r\nContent-Length: 1048576\r\n\r\n\
+                              <html lang=\"en\"><head>",
+                        )
+                        .await
+                        .unwrap();
+                }
+
+                tokio::time::sleep(Duration::from_secs(5)).await;
+            });
+
+            fqdn
+        }
+
+        let probe = HttpProbe {
+            detect_language: true,
+            timeout: Some(Duration::from_millis(200)),
+            ..HttpProbe::default()
+        };
+
+        let partial = DomainMetadata::new(serve(true).await)
+            .http_probe(&probe)
+            .await
+            .unwrap();
+        assert_eq!(partial.http.unwrap().html_lang.as_deref(), Some("en"));
+
+        let capped = DomainMetadata::new(serve(true).await)
+            .http_probe(&HttpProbe {
+                max_body_size: 8,
+                ..probe
+            })
+            .await
+            .unwrap();
+        assert_eq!(capped.http.unwrap().html_lang, None);
+
+        assert!(matches!(
+            DomainMetadata::new(serve(false).await)
+                .http_probe(&probe)
+                .await,
+            Err(Error::EnrichmentError(
+                EnrichmentError::HttpProbeTimeout { .. }
+            ))
+        ));
+    }
+
+    #[test]
+    fn test_markup_detection() {
+        assert_eq!(
+            html_charset("<head><meta charset=utf-8 /><meta charset='latin1'>").as_deref(),
+            Some("utf-8")
+        );
+        assert_eq!(html_lang("<htmlx lang=\"en\"><html xml:lang='pt'>"), None);
+        assert_eq!(
+            attribute("text/html;charset=\"Shift_JIS\"", "charset").as_deref(),
+            Some("Shift_JIS")
+        );
+        assert_eq!(primary_language("en_GB, fr;q=0.5").as_deref(), Some("en"));
+        assert_eq!(primary_language("*"), None);
+    }
+
+    #[tokio::test]
+    // The fields of the context depend on the features toggled.
+    #[allow(clippy::field_reassign_with_default)]
+    async fn test_run_with_context() {
+        let listener = net::TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let fqdn = listener.local_addr().unwrap().to_string();
+
+        tokio::spawn(async move {
+            let (mut stream, _) = listener.accept().await.unwrap();
+            let mut request = [0; 1024];
+            let _ = stream.read(&mut request).await.unwrap();
+
+            stream
+                .write_all(
+                    b"HTTP/1.1 200 OK\r\nServer: twistrs\r\n\
+                      Content-Length: 0\r\nConnection: close\r\n\r\n",
+                )
+                .await
+                .unwrap();
+        });
+
+        let mut context = EnrichmentContext::default();
+        context.http_client = Client::builder().build_http();
+
+        let enriched = DomainMetadata::new(fqdn)
+            .run_with_context(EnrichmentKinds::HTTP_BANNER, &context)
+            .await
+            .unwrap();
+
+        assert_eq!(enriched.http_banner.as_deref(), Some("twistrs"));
+    }
+
+    #[tokio::test]
+    async fn test_from_reader() {
+        let input: &[u8] = b"# candidates\nexample.invalid\n\n  twistrs.invalid  \n";
+        let pipeline = Pipeline::new();
+
+        let mut fqdns: Vec<String> = from_reader(tokio::io::BufReader::new(input), &pipeline)
+            .map(|result| result.unwrap().fqdn)
+            .collect()
+            .await;
+        fqdns.sort();
+
+        assert_eq!(fqdns, vec!["example.invalid", "twistrs.invalid"]);
+    }
+
+    #[test]
+    fn test_metadata_round_trip() {
+        let mut domain_metadata = DomainMetadata::new(String::from("example.com"));
+        domain_metadata.dns = DnsRecords::from_ips(["93.184.216.34".parse().unwrap()]);
+        domain_metadata.smtp = Some(SmtpMetadata {
+            is_positive: true,
+            message: String::from("250 OK"),
+        });
+
+        let json = serde_json::to_string(&domain_metadata).unwrap();
+        let read: DomainMetadata = serde_json::from_str(&json).unwrap();
+        assert_eq!(read, domain_metadata);
+
+        let unique: std::collections::HashSet<DomainMetadata> =
+            vec![domain_metadata, read].into_iter().collect();
+        assert_eq!(unique.len(), 1);
+    }
+
+    #[cfg(feature = "tls")]
+    #[tokio::test]
+    async fn test_https_banner_timeout() {
+        // Accepts connections without ever answering the TLS handshake.
+        let listener = net::TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let port = listener.local_addr().unwrap().port();
+        let server = tokio::spawn(async move {
+            let (_stream, _) = listener.accept().await.unwrap();
+            std::future::pending::<()>().await;
+        });
+
+        let options = TlsOptions {
+            sni: Sni::Name(String::from("localhost")),
+            timeout: Duration::from_millis(50),
+            ..TlsOptions::default()
+        };
+        let context = EnrichmentContext {
+            breaker: Some(CircuitBreaker::new(1, Duration::from_secs(60))),
+            ..EnrichmentContext::default()
+        };
+        let domain_metadata = DomainMetadata::new(String::from("127.0.0.1"));
+
+        assert!(matches!(
+            domain_metadata
+                .https_banner_on(&options, port, &context)
+                .await,
+            Err(Error::BreakerError(BreakerError::Timeout { .. }))
+        ));
+
+        // The timeout opened the circuit towards the stalled server.
+        assert!(matches!(
+            domain_metadata
+                .https_banner_on(&options, port, &context)
+                .await,
+            Err(Error::BreakerError(BreakerError::Open { .. }))
+        ));
+        server.abort();
+    }
+
+    #[cfg(feature = "dns")]
+    #[tokio::test]
+    async fn test_dns_resolvable_with_context() {
+        use trust_dns_resolver::config::{NameServerConfigGroup, ResolverConfig, ResolverOpts};
+        use trust_dns_resolver::proto::op::{Message, MessageType};
+        use trust_dns_resolver::proto::rr::{rdata, RData, Record};
+
+        // Answers every query with 192.0.2.1, which the system resolver
+        // would never return for a domain under `.example` (RFC 2606).
+        let socket = net::UdpSocket::bind("127.0.0.1:0").await.unwrap();
+        let address = socket.local_addr().unwrap();
+        let server = tokio::spawn(async move {
+            let mut buffer = [0; 512];
+
+            loop {
+                let (len, peer) = socket.recv_from(&mut buffer).await.unwrap();
+                let query = Message::from_vec(&buffer[..len]).unwrap();
+
+                let mut response = Message::new();
+                response.set_id(query.id());
+                response.set_message_type(MessageType::Response);
+                response.set_recursion_desired(query.recursion_desired());
+                response.set_recursion_available(true);
+
+                for question in query.queries() {
+                    response.add_query(question.clone());
+
+                    if question.query_type() == RecordType::A {
+                        response.add_answer(Record::from_rdata(
+                            question.name().clone(),
+                            60,
+                            RData::A(rdata::A::new(192, 0, 2, 1)),
+                        ));
+                    }
+                }
+
+                let bytes = response.to_vec().unwrap();
+                socket.send_to(&bytes, peer).await.unwrap();
+            }
+        });
+
+        let context = EnrichmentContext {
+            resolver: Some(TokioAsyncResolver::tokio(
+                ResolverConfig::from_parts(
+                    None,
+                    vec![],
+                    NameServerConfigGroup::from_ips_clear(&[address.ip()], address.port(), true),
+                ),
+                ResolverOpts::default(),
+            )),
+            ..EnrichmentContext::default()
+        };
+
+        let resolved = DomainMetadata::new(String::from("twistrs.example"))
+            .dns_resolvable_with_context(&context)
+            .await
+            .unwrap();
+        assert_eq!(resolved.dns.ips(), Some(vec!["192.0.2.1".parse().unwrap()]));
+
+        server.abort();
+    }
+
+    #[cfg(all(feature = "dns", feature = "tls"))]
+    #[tokio::test]
+    async fn test_starttls() {
+        use tokio::io::AsyncReadExt;
+
+        async fn negotiate(replies: &'static [u8]) -> (Option<()>, String) {
+            let (client, mut server) = tokio::io::duplex(1024);
+
+            let session = tokio::spawn(async move {
+                server.write_all(replies).await.unwrap();
+                server.shutdown().await.unwrap();
+
+                let mut commands = String::new();
+                server.read_to_string(&mut commands).await.unwrap();
+                commands
+            });
+
+            let mut stream = tokio::io::BufReader::new(client);
+            let negotiated = starttls(&mut stream).await;
+            drop(stream);
+
+            (negotiated, session.await.unwrap())
+        }
+
+        let upgraded = negotiate(
+            b"220 mx.example.com ESMTP\r\n\
+              250-mx.example.com\r\n\
+              250-SIZE 35882577\r\n\
+              250 STARTTLS\r\n\
+              220 2.0.0 Ready to start TLS\r\n",
+        )
+        .await;
+        assert_eq!(
+            upgraded,
+            (
+                Some(()),
+                String::from("EHLO twistrs.example.com\r\nSTARTTLS\r\n")
+            )
+        );
+
+        // Servers not advertising STARTTLS are not asked to upgrade.
+        let plain = negotiate(b"220 mx.example.com ESMTP\r\n250 mx.example.com\r\n").await;
+        assert_eq!(plain, (None, String::from("EHLO twistrs.example.com\r\n")));
+
+        assert_eq!(negotiate(b"554 No SMTP service\r\n").await.0, None);
+    }
 }
@@ -1,5 +1,27 @@
+#[cfg(feature = "enrich")]
+use crate::breaker::BreakerError;
+use crate::config::ConfigError;
+#[cfg(feature = "enrich")]
 use crate::enrich::EnrichmentError;
 use crate::permutate::PermutationError;
+#[cfg(feature = "enrich")]
+use crate::pipeline::ScanError;
+use crate::psl::SuffixListError;
+#[cfg(feature = "enrich")]
+use crate::resolver::ResolverError;
+#[cfg(any(feature = "grpc", feature = "http"))]
+use crate::server::ServeError;
+#[cfg(feature = "spill")]
+use crate::spill::BufferError;
+#[cfg(feature = "enrich")]
+use crate::storage::StoreError;
+#[cfg(feature = "tls")]
+use crate::tls::TlsError;
+#[cfg(feature = "enrich")]
+use crate::watchdog::WatchdogError;
+#[cfg(feature = "whois")]
+use crate::whois::WhoIsError;
+use crate::zone::ZoneError;
 use std::convert::Infallible;
 
 #[derive(thiserror::Error, Debug)]
@@ -7,9 +29,55 @@ pub enum Error {
     #[error(transparent)]
     PermutationError(#[from] PermutationError),
 
+    #[cfg(feature = "enrich")]
     #[error(transparent)]
     EnrichmentError(#[from] EnrichmentError),
 
+    #[cfg(feature = "enrich")]
+    #[error(transparent)]
+    StoreError(#[from] StoreError),
+
+    #[cfg(feature = "enrich")]
+    #[error(transparent)]
+    ResolverError(#[from] ResolverError),
+
+    #[cfg(feature = "enrich")]
+    #[error(transparent)]
+    ScanError(#[from] ScanError),
+
+    #[error(transparent)]
+    ConfigError(#[from] ConfigError),
+
+    #[cfg(any(feature = "grpc", feature = "http"))]
+    #[error(transparent)]
+    ServeError(#[from] ServeError),
+
+    #[error(transparent)]
+    SuffixListError(#[from] SuffixListError),
+
+    #[cfg(feature = "spill")]
+    #[error(transparent)]
+    BufferError(#[from] BufferError),
+
+    #[cfg(feature = "tls")]
+    #[error(transparent)]
+    TlsError(#[from] TlsError),
+
+    #[error(transparent)]
+    ZoneError(#[from] ZoneError),
+
+    #[cfg(feature = "enrich")]
+    #[error(transparent)]
+    WatchdogError(#[from] WatchdogError),
+
+    #[cfg(feature = "enrich")]
+    #[error(transparent)]
+    BreakerError(#[from] BreakerError),
+
+    #[cfg(feature = "whois")]
+    #[error(transparent)]
+    WhoIsError(#[from] WhoIsError),
+
     #[error(transparent)]
     Infallible(#[from] Infallible),
 }
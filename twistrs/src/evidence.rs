@@ -0,0 +1,204 @@
+//! The evidence module exposes [`EvidenceBundle`], packaging everything
+//! known about a flagged candidate into a single serializable record, so
+//! that it can be attached as is to a takedown request sent to the
+//! registrar or host of the candidate.
+//!
+//! A bundle records where the candidate came from (the domain it squats
+//! and the permutation kind generating it) alongside the evidence the
+//! enrichment gathered: DNS records, the raw and parsed `WhoIs` response,
+//! the certificate served, the HTTP responses and mail capability, and
+//! when all of it was collected. Only `WhoIs` is supported, not RDAP.
+//!
+//! Example:
+//!
+//! ```no_run
+//! use futures::stream::StreamExt;
+//! use twistrs::evidence::EvidenceBundle;
+//! use twistrs::permutate::Domain;
+//! use twistrs::pipeline::{Pipeline, ScanResult};
+//!
+//! #[tokio::main]
+//! async fn main() {
+//!     let domain = Domain::new("example.com").unwrap();
+//!     let results: Vec<ScanResult> = Pipeline::new().run(domain.addition()).collect().await;
+//!
+//!     for result in results.iter().filter(|result| result.metadata.dns.resolves()) {
+//!         let bundle = EvidenceBundle::new(&domain, result);
+//!         println!("{}", serde_json::to_string_pretty(&bundle).unwrap());
+//!     }
+//! }
+//! ```
+//!
+//! ### Features
+//!
+//! This module requires the `enrich` feature toggled.
+
+use std::collections::BTreeMap;
+
+use serde::{Deserialize, Serialize};
+
+use crate::enrich::{DnsRecords, HttpMetadata, TlsMetadata, WhoIsMetadata};
+use crate::permutate::{Domain, PermutationKind};
+use crate::pipeline::ScanResult;
+use crate::scoring::Scorer;
+use crate::storage::unix_now;
+
+/// Where a flagged candidate comes from.
+#[derive(Clone, Debug, Serialize, Deserialize, Hash, Eq, PartialEq)]
+pub struct Provenance {
+    /// Domain the candidate squats.
+    pub origin: Domain,
+
+    /// Permutation kind generating the candidate from `origin`.
+    pub kind: PermutationKind,
+
+    /// Stable identifier of the candidate, see
+    /// [`Permutation::id`](crate::permutate::Permutation::id).
+    pub id: String,
+}
+
+/// Everything known about a flagged candidate, see the
+/// [module](self) documentation.
+// Reads better than `evidence::Bundle` once imported.
+#[allow(clippy::module_name_repetitions)]
+#[derive(Clone, Debug, Serialize, Deserialize, PartialEq)]
+pub struct EvidenceBundle {
+    /// The flagged candidate, in its display form.
+    pub fqdn: String,
+
+    /// The flagged candidate in its ASCII-compatible (punycode) form, as
+    /// registrars know it.
+    pub canonical: String,
+
+    pub provenance: Provenance,
+
+    /// Risk score of the candidate, see [`EvidenceBundle::with_score`].
+    pub score: Option<f64>,
+
+    /// DNS records of the candidate, along with their TTLs.
+    pub dns: DnsRecords,
+
+    /// Raw and parsed `WhoIs` response.
+    pub who_is: Option<WhoIsMetadata>,
+
+    /// Raw `WhoIs` response, if it was looked up without being parsed.
+    pub who_is_raw: Option<String>,
+
+    /// Details of the certificate served on port 443.
+    pub certificate: Option<TlsMetadata>,
+
+    /// JARM fingerprint of the TLS stack served on port 443.
+    pub jarm: Option<String>,
+
+    /// Server banner of the HTTP response.
+    pub http_banner: Option<String>,
+
+    /// Snapshot of the HTTP responses, redirects included.
+    pub http: Option<HttpMetadata>,
+
+    /// Whether the candidate can receive mail, keyed by each probed port.
+    pub mail_ports: Option<BTreeMap<u16, bool>>,
+
+    /// Unix timestamp (in seconds) at which the bundle was collected.
+    pub collected_at: u64,
+
+    /// Unix timestamp (in seconds) until which the enrichment was served
+    /// from a cache, i.e. the evidence may be older than `collected_at`.
+    pub cached_until: Option<u64>,
+}
+
+impl EvidenceBundle {
+    /// Bundle the enriched `result` of a scan of `origin`, timestamped now.
+    pub fn new(origin: &Domain, result: &ScanResult) -> EvidenceBundle {
+        let permutation = &result.permutation;
+        let metadata = &result.metadata;
+
+        EvidenceBundle {
+            fqdn: permutation.domain.fqdn.clone(),
+            canonical: permutation.domain.canonical().to_string(),
+            provenance: Provenance {
+                origin: origin.clone(),
+                kind: permutation.kind,
+                id: permutation.id(origin),
+            },
+            score: None,
+            dns: metadata.dns.clone(),
+            who_is: metadata.who_is.clone(),
+            who_is_raw: metadata.who_is_lookup.clone(),
+            certificate: metadata.tls.clone(),
+            jarm: metadata.jarm.clone(),
+            http_banner: metadata.http_banner.clone(),
+            http: metadata.http.clone(),
+            mail_ports: metadata.mail_ports.clone(),
+            collected_at: unix_now(),
+            cached_until: metadata.cached_until,
+        }
+    }
+
+    /// Record the score `scorer` gives to `result`, the enriched result
+    /// the bundle was built from.
+    #[must_use]
+    pub fn with_score(mut self, scorer: &dyn Scorer, result: &ScanResult) -> EvidenceBundle {
+        self.score = Some(scorer.score(&result.permutation, Some(&result.metadata)));
+        self
+    }
+
+    /// Whether the bundle holds any evidence of the candidate being live,
+    /// i.e. it resolves, serves content or has a `WhoIs` record.
+    pub fn has_evidence(&self) -> bool {
+        self.dns.resolves()
+            || self.http_banner.is_some()
+            || self.http.is_some()
+            || self.certificate.is_some()
+            || self.who_is.is_some()
+            || self.who_is_raw.is_some()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::enrich::DomainMetadata;
+    use crate::permutate::Permutation;
+    use crate::scoring::CompositeScorer;
+
+    #[test]
+    fn test_evidence_bundle() {
+        let origin = Domain::new("example.com").unwrap();
+        let permutation = Permutation {
+            domain: Domain::new("exampel.com").unwrap(),
+            kind: PermutationKind::Transposition,
+            substitutions: vec![],
+        };
+        let mut metadata = DomainMetadata::new(permutation.domain.fqdn.clone());
+        let mut result = ScanResult {
+            permutation: permutation.clone(),
+            metadata: metadata.clone(),
+            fingerprint: None,
+        };
+
+        let empty = EvidenceBundle::new(&origin, &result);
+        assert!(!empty.has_evidence());
+        assert_eq!(empty.provenance.id, permutation.id(&origin));
+        assert!(empty.collected_at > 0);
+
+        metadata.dns = DnsRecords::from_ips(["127.0.0.1".parse().unwrap()]);
+        metadata.http_banner = Some(String::from("nginx"));
+        metadata.who_is_lookup = Some(String::from("Domain Name: EXAMPEL.COM"));
+        result.metadata = metadata;
+
+        let scorer = CompositeScorer::new(origin.clone());
+        let bundle = EvidenceBundle::new(&origin, &result).with_score(&scorer, &result);
+        assert!(bundle.has_evidence());
+        assert_eq!(bundle.provenance.kind, PermutationKind::Transposition);
+        assert_eq!(bundle.http_banner.as_deref(), Some("nginx"));
+        assert_eq!(bundle.who_is_raw, result.metadata.who_is_lookup);
+        assert!(bundle.score.is_some_and(|score| score > 0.0));
+
+        let json = serde_json::to_string(&bundle).unwrap();
+        assert_eq!(
+            serde_json::from_str::<EvidenceBundle>(&json).unwrap(),
+            bundle
+        );
+    }
+}
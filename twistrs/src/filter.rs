@@ -0,0 +1,413 @@
+//! The filter module exposes predicates that select which permutations
+//! are kept by the [`PermutationEngine`], allowing scans to be scoped
+//! before any enrichment takes place.
+//!
+//! Any `Fn(&Permutation) -> bool` closure is a filter, alongside the
+//! ready-made filters in this module.
+//!
+//! Example:
+//!
+//! ```
+//! use twistrs::filter::TldCategories;
+//! use twistrs::permutate::{Domain, PermutationEngine};
+//! use twistrs::tld::TldCategory;
+//!
+//! let domain = Domain::new("example.com").unwrap();
+//! let mut engine = PermutationEngine::new();
+//! engine.set_filter(TldCategories {
+//!     categories: vec![TldCategory::Generic, TldCategory::CountryCode],
+//! });
+//!
+//! let permutations: Vec<_> = engine.generate(&domain).unwrap().collect();
+//! ```
+//!
+//! Wrapping a filter in [`Instrumented`] tallies the candidates it
+//! accepts and rejects, telling apart whether the filter or the generator
+//! is responsible for a small result set.
+//!
+//! With the `enrich` feature toggled, [`ResultFilter`]s select which
+//! enriched permutations are yielded by the [`Pipeline`], so that
+//! consumers only receive actionable records:
+//!
+//! ```
+//! # #[cfg(feature = "enrich")]
+//! # {
+//! use twistrs::filter::{HasMx, Resolvable, ResultFilter};
+//! use twistrs::pipeline::Pipeline;
+//!
+//! let mut pipeline = Pipeline::new();
+//! pipeline.set_result_filter(|permutation: &_, metadata: &_| {
+//!     Resolvable.matches(permutation, metadata) && HasMx.matches(permutation, metadata)
+//! });
+//! # }
+//! ```
+//!
+//! [`PermutationEngine`]: crate::permutate::PermutationEngine
+//! [`Pipeline`]: crate::pipeline::Pipeline
+use std::collections::BTreeMap;
+use std::sync::{Arc, Mutex, PoisonError};
+
+use serde::{Deserialize, Serialize};
+
+#[cfg(feature = "enrich")]
+use crate::enrich::{mail_exchanges, DomainMetadata};
+use crate::permutate::{Permutation, PermutationKind};
+use crate::tld::{is_abused, tld_info, TldCategory};
+
+/// Predicate deciding whether a permutation is kept.
+pub trait Filter: Send + Sync {
+    /// Returns `true` if `permutation` should be kept.
+    fn matches(&self, permutation: &Permutation) -> bool;
+
+    /// Serializable description of the filter, recorded in the engine
+    /// configuration. Filters that cannot be described, such as
+    /// closures, are only identified by their type name.
+    fn descriptor(&self) -> FilterDescriptor {
+        FilterDescriptor::Custom(std::any::type_name::<Self>().to_string())
+    }
+}
+
+impl<F> Filter for F
+where
+    F: Fn(&Permutation) -> bool + Send + Sync,
+{
+    fn matches(&self, permutation: &Permutation) -> bool {
+        self(permutation)
+    }
+}
+
+/// Description of a filter, see [`Filter::descriptor`].
+// Named after the trait it describes.
+#[allow(clippy::module_name_repetitions)]
+#[derive(Clone, Debug, Serialize, Deserialize, Hash, Eq, PartialEq)]
+#[cfg_attr(feature = "arbitrary", derive(arbitrary::Arbitrary))]
+pub enum FilterDescriptor {
+    TldCategories(TldCategories),
+    AbusedTlds,
+
+    /// Filter that cannot be rebuilt from its description, identified by
+    /// its type name.
+    Custom(String),
+}
+
+impl FilterDescriptor {
+    /// Rebuild the filter described, `None` for custom filters.
+    pub fn build(&self) -> Option<Arc<dyn Filter>> {
+        match self {
+            FilterDescriptor::TldCategories(filter) => Some(Arc::new(filter.clone())),
+            FilterDescriptor::AbusedTlds => Some(Arc::new(AbusedTlds)),
+            FilterDescriptor::Custom(_) => None,
+        }
+    }
+}
+
+/// Keeps permutations whose TLD falls under one of `categories`.
+#[derive(Clone, Debug, Default, Serialize, Deserialize, Hash, Eq, PartialEq)]
+#[cfg_attr(feature = "arbitrary", derive(arbitrary::Arbitrary))]
+pub struct TldCategories {
+    pub categories: Vec<TldCategory>,
+}
+
+impl Filter for TldCategories {
+    fn matches(&self, permutation: &Permutation) -> bool {
+        tld_info(&permutation.domain.tld)
+            .is_some_and(|info| self.categories.contains(&info.category))
+    }
+
+    fn descriptor(&self) -> FilterDescriptor {
+        FilterDescriptor::TldCategories(self.clone())
+    }
+}
+
+/// Keeps permutations under a commonly abused TLD, see
+/// [`crate::tld::is_abused`].
+#[derive(Clone, Copy, Debug, Default, Serialize, Deserialize, Hash, Eq, PartialEq)]
+#[cfg_attr(feature = "arbitrary", derive(arbitrary::Arbitrary))]
+pub struct AbusedTlds;
+
+impl Filter for AbusedTlds {
+    fn matches(&self, permutation: &Permutation) -> bool {
+        is_abused(&permutation.domain.tld)
+    }
+
+    fn descriptor(&self) -> FilterDescriptor {
+        FilterDescriptor::AbusedTlds
+    }
+}
+
+/// Predicate deciding whether an enriched permutation is yielded by the
+/// [`Pipeline`](crate::pipeline::Pipeline), see
+/// [`Pipeline::set_result_filter`](crate::pipeline::Pipeline::set_result_filter).
+///
+/// ### Features
+///
+/// This trait requires the `enrich` feature toggled.
+// Named after the pipeline results it filters.
+#[allow(clippy::module_name_repetitions)]
+#[cfg(feature = "enrich")]
+pub trait ResultFilter: Send + Sync {
+    /// Returns `true` if `permutation`, enriched with `metadata`, should
+    /// be kept.
+    fn matches(&self, permutation: &Permutation, metadata: &DomainMetadata) -> bool;
+}
+
+#[cfg(feature = "enrich")]
+impl<F> ResultFilter for F
+where
+    F: Fn(&Permutation, &DomainMetadata) -> bool + Send + Sync,
+{
+    fn matches(&self, permutation: &Permutation, metadata: &DomainMetadata) -> bool {
+        self(permutation, metadata)
+    }
+}
+
+/// Keeps enriched permutations that resolve.
+///
+/// ### Features
+///
+/// This filter requires the `enrich` feature toggled.
+#[cfg(feature = "enrich")]
+#[derive(Clone, Copy, Debug, Default, Serialize, Deserialize, Hash, Eq, PartialEq)]
+pub struct Resolvable;
+
+#[cfg(feature = "enrich")]
+impl ResultFilter for Resolvable {
+    fn matches(&self, _permutation: &Permutation, metadata: &DomainMetadata) -> bool {
+        metadata.dns.resolves()
+    }
+}
+
+/// Keeps enriched permutations with at least one MX record, i.e. that
+/// are set up to receive mail. Null MX records (RFC 7505) do not count.
+///
+/// ### Features
+///
+/// This filter requires the `enrich` feature toggled.
+#[cfg(feature = "enrich")]
+#[derive(Clone, Copy, Debug, Default, Serialize, Deserialize, Hash, Eq, PartialEq)]
+pub struct HasMx;
+
+#[cfg(feature = "enrich")]
+impl ResultFilter for HasMx {
+    fn matches(&self, _permutation: &Permutation, metadata: &DomainMetadata) -> bool {
+        metadata
+            .dns
+            .mx
+            .as_deref()
+            .is_some_and(|records| !mail_exchanges(records).is_empty())
+    }
+}
+
+/// Number of candidates accepted and rejected by a filter.
+#[derive(Clone, Copy, Debug, Default, Serialize, Deserialize, Hash, Eq, PartialEq)]
+pub struct Counts {
+    pub accepted: usize,
+    pub rejected: usize,
+}
+
+impl Counts {
+    fn record(&mut self, accepted: bool) {
+        if accepted {
+            self.accepted += 1;
+        } else {
+            self.rejected += 1;
+        }
+    }
+}
+
+/// Tallies of an [`Instrumented`] filter.
+#[derive(Clone, Debug, Default, Serialize, Deserialize, Eq, PartialEq)]
+pub struct Tallies {
+    pub total: Counts,
+
+    /// Tallies per permutation kind, only kept if requested through
+    /// [`Instrumented::per_kind`].
+    pub per_kind: BTreeMap<PermutationKind, Counts>,
+}
+
+/// Filter wrapper counting the candidates accepted and rejected by
+/// `filter`.
+///
+/// Clones share the same tallies, so a clone can be kept to read them
+/// once the filter has been handed over to the engine.
+#[derive(Clone, Debug)]
+pub struct Instrumented<F> {
+    pub filter: F,
+
+    per_kind: bool,
+    stats: Arc<Mutex<Tallies>>,
+}
+
+impl<F: Filter> Instrumented<F> {
+    /// Wrap `filter`, only keeping the total tallies.
+    pub fn new(filter: F) -> Instrumented<F> {
+        Instrumented {
+            filter,
+            per_kind: false,
+            stats: Arc::default(),
+        }
+    }
+
+    /// Wrap `filter`, keeping tallies per permutation kind as well.
+    pub fn per_kind(filter: F) -> Instrumented<F> {
+        Instrumented {
+            per_kind: true,
+            ..Instrumented::new(filter)
+        }
+    }
+
+    /// Snapshot of the tallies so far.
+    pub fn stats(&self) -> Tallies {
+        // Tallies are never left half-updated, recover the guard regardless.
+        self.stats
+            .lock()
+            .unwrap_or_else(PoisonError::into_inner)
+            .clone()
+    }
+
+    /// Reset the tallies, e.g. between scans.
+    pub fn reset(&self) {
+        *self.stats.lock().unwrap_or_else(PoisonError::into_inner) = Tallies::default();
+    }
+}
+
+impl<F: Filter> Filter for Instrumented<F> {
+    fn matches(&self, permutation: &Permutation) -> bool {
+        let accepted = self.filter.matches(permutation);
+        let mut stats = self.stats.lock().unwrap_or_else(PoisonError::into_inner);
+
+        stats.total.record(accepted);
+        if self.per_kind {
+            stats
+                .per_kind
+                .entry(permutation.kind)
+                .or_default()
+                .record(accepted);
+        }
+
+        accepted
+    }
+
+    fn descriptor(&self) -> FilterDescriptor {
+        self.filter.descriptor()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::permutate::{Domain, PermutationEngine};
+
+    #[test]
+    fn test_tld_categories_filter() {
+        let filter = TldCategories {
+            categories: vec![TldCategory::CountryCode],
+        };
+
+        let permutations: Vec<Permutation> = Domain::new("example.com")
+            .unwrap()
+            .tld()
+            .filter(|permutation| filter.matches(permutation))
+            .collect();
+
+        assert!(!permutations.is_empty());
+        assert!(permutations
+            .iter()
+            .all(|permutation| permutation.domain.tld != "com"));
+    }
+
+    #[test]
+    fn test_abused_tlds_filter() {
+        let domain = Domain::new("example.com").unwrap();
+
+        assert!(AbusedTlds.matches(&Permutation {
+            domain: Domain::new("example.xyz").unwrap(),
+            kind: PermutationKind::Tld,
+            substitutions: vec![],
+        }));
+
+        // Besides TLD swaps, only the permutations that rewrite the TLD of
+        // `example.com` can be kept.
+        let kept: Vec<Permutation> = domain
+            .all()
+            .unwrap()
+            .filter(|p| AbusedTlds.matches(p))
+            .collect();
+        let (swaps, others): (Vec<Permutation>, Vec<Permutation>) = kept
+            .into_iter()
+            .partition(|p| p.kind == PermutationKind::Tld);
+
+        assert!(swaps.iter().any(|p| p.domain.fqdn == "example.xyz"));
+        assert!(swaps.iter().all(|p| is_abused(&p.domain.tld)));
+        assert_eq!(
+            others
+                .iter()
+                .map(|p| (p.kind, p.domain.fqdn.as_str()))
+                .collect::<Vec<_>>(),
+            vec![
+                (PermutationKind::VowelSwap, "example.cam"),
+                (PermutationKind::TldAppend, "examplecom.info"),
+                (PermutationKind::TldAppend, "examplecom.xyz"),
+                (PermutationKind::TldAppend, "examplecom.online"),
+                (PermutationKind::TldAppend, "example-com.info"),
+                (PermutationKind::TldAppend, "example-com.xyz"),
+                (PermutationKind::TldAppend, "example-com.online"),
+            ]
+        );
+    }
+
+    #[test]
+    fn test_instrumented_filter() {
+        let domain = Domain::new("example.com").unwrap();
+        let filter = Instrumented::per_kind(AbusedTlds);
+
+        let mut engine = PermutationEngine::new();
+        engine.kinds = vec![PermutationKind::Addition, PermutationKind::Tld];
+        engine.set_filter(filter.clone());
+
+        let kept = engine.generate(&domain).unwrap().count();
+        let stats = filter.stats();
+
+        assert_eq!(stats.total.accepted, kept);
+        assert_eq!(
+            stats.total.accepted + stats.total.rejected,
+            domain.addition().count() + domain.tld().count()
+        );
+        assert_eq!(
+            stats.per_kind[&PermutationKind::Addition],
+            Counts {
+                accepted: 0,
+                rejected: domain.addition().count(),
+            }
+        );
+
+        filter.reset();
+        assert_eq!(filter.stats(), Tallies::default());
+    }
+
+    #[cfg(feature = "enrich")]
+    #[test]
+    fn test_result_filters() {
+        use crate::enrich::{DnsRecord, DnsRecordType, DnsRecords};
+
+        let permutation = Permutation {
+            domain: Domain::new("examp1e.com").unwrap(),
+            kind: PermutationKind::Homoglyph,
+            substitutions: vec![],
+        };
+        let mut metadata = DomainMetadata::new(permutation.domain.fqdn.clone());
+
+        assert!(!Resolvable.matches(&permutation, &metadata));
+        metadata.dns = DnsRecords::from_ips(["127.0.0.1".parse().unwrap()]);
+        assert!(Resolvable.matches(&permutation, &metadata));
+
+        assert!(!HasMx.matches(&permutation, &metadata));
+        metadata.dns.mx = Some(vec![DnsRecord::new(DnsRecordType::Mx, "0 .")]);
+        assert!(!HasMx.matches(&permutation, &metadata));
+        metadata.dns.mx = Some(vec![DnsRecord::new(
+            DnsRecordType::Mx,
+            "10 mx.examp1e.com.",
+        )]);
+        assert!(HasMx.matches(&permutation, &metadata));
+    }
+}
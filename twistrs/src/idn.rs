@@ -0,0 +1,245 @@
+//! The idn module exposes [`analyze`], inspecting an arbitrary candidate
+//! FQDN (e.g. taken from a feed or an inbound email) for the internationalized
+//! domain name tricks used by lookalikes, independently of how the
+//! candidate was found.
+//!
+//! Findings cover labels mixing scripts, invisible characters, and root
+//! labels whose skeleton, i.e. their form with every lookalike folded
+//! onto the Latin letter it imitates, collides with the one of the base
+//! domain.
+//!
+//! Example:
+//!
+//! ```
+//! use twistrs::idn::{analyze, Finding};
+//! use twistrs::permutate::Domain;
+//!
+//! let base = Domain::new("apple.com").unwrap();
+//! let findings = analyze(&base, "xn--pple-43d.com");
+//!
+//! assert!(findings.iter().any(|finding| matches!(finding, Finding::MixedScript { .. })));
+//! assert!(findings.iter().any(|finding| matches!(finding, Finding::SkeletonCollision { .. })));
+//! ```
+
+use std::collections::{BTreeSet, HashMap};
+
+use serde::{Deserialize, Serialize};
+
+use crate::constants::{CROSS_SCRIPT_HOMOGLYPHS, HOMOGLYPHS, IDN_CONFUSABLES};
+use crate::permutate::{Domain, DomainRef, Script};
+
+lazy_static! {
+    /// Non-ASCII lookalikes mapped to the Latin letter they imitate. Glyphs
+    /// imitating several letters are mapped to the first one.
+    static ref SKELETONS: HashMap<char, char> = {
+        let mut skeletons = HashMap::new();
+
+        for (letter, glyphs) in HOMOGLYPHS
+            .entries()
+            .chain(CROSS_SCRIPT_HOMOGLYPHS.entries())
+            .chain(IDN_CONFUSABLES.entries())
+        {
+            for glyph in glyphs.chars().filter(|glyph| !glyph.is_ascii()) {
+                let entry = skeletons.entry(glyph).or_insert(*letter);
+                *entry = (*entry).min(*letter);
+            }
+        }
+
+        skeletons
+    };
+}
+
+/// Anomaly found in a candidate FQDN.
+#[derive(Clone, Debug, Serialize, Deserialize, Hash, Eq, PartialEq)]
+pub enum Finding {
+    /// A label mixes characters of several scripts, e.g. Cyrillic and
+    /// Latin ones. Digits and hyphens mix with any script.
+    MixedScript {
+        label: String,
+        scripts: BTreeSet<Script>,
+    },
+
+    /// A label holds a character that is not rendered, e.g. a zero-width
+    /// joiner, at the given position in characters.
+    InvisibleChar {
+        label: String,
+        position: usize,
+        character: char,
+    },
+
+    /// The root label differs from the one of the base domain, but both
+    /// share the same skeleton, see [`skeleton`].
+    SkeletonCollision { label: String, skeleton: String },
+
+    /// A label starts with `xn--` but is not valid punycode.
+    InvalidPunycode { label: String },
+}
+
+/// Inspect `fqdn`, in its Unicode or punycode form, for mixed-script
+/// labels, invisible characters and skeleton collisions with `base`.
+///
+/// Unlike [`Domain::new`], the FQDN is not required to be valid, so that
+/// candidates that fail to parse because of the very characters looked
+/// for can still be inspected.
+pub fn analyze(base: &Domain, fqdn: &str) -> Vec<Finding> {
+    let mut findings = vec![];
+    let mut labels = vec![];
+
+    for raw in fqdn.trim_end_matches('.').split('.') {
+        let decoded = match raw.get(..4) {
+            Some(prefix) if prefix.eq_ignore_ascii_case("xn--") => {
+                idna::punycode::decode_to_string(&raw[4..])
+            }
+            _ => Some(raw.to_string()),
+        };
+
+        // Undecodable labels are reported as such, rather than as
+        // characters of their encoded form.
+        let Some(label) = decoded.map(|unicode| unicode.to_lowercase()) else {
+            findings.push(Finding::InvalidPunycode {
+                label: raw.to_string(),
+            });
+            labels.push(raw.to_lowercase());
+            continue;
+        };
+
+        for (position, character) in label.chars().enumerate() {
+            if is_invisible(character) {
+                findings.push(Finding::InvisibleChar {
+                    label: label.clone(),
+                    position,
+                    character,
+                });
+            }
+        }
+
+        let scripts: BTreeSet<Script> = label
+            .chars()
+            .filter(|c| !is_invisible(*c))
+            .map(Script::of)
+            .filter(|script| *script != Script::Common)
+            .collect();
+
+        if scripts.len() > 1 {
+            findings.push(Finding::MixedScript {
+                label: label.clone(),
+                scripts,
+            });
+        }
+
+        labels.push(label);
+    }
+
+    if let Some(label) = root_label(&labels) {
+        let visible: String = label.chars().filter(|c| !is_invisible(*c)).collect();
+        let base_label = base.domain.to_lowercase();
+
+        if *label != base_label && skeleton(&visible) == skeleton(&base_label) {
+            findings.push(Finding::SkeletonCollision {
+                label: label.clone(),
+                skeleton: skeleton(&visible),
+            });
+        }
+    }
+
+    findings
+}
+
+/// Skeleton of `label`, lowercase and with every non-ASCII lookalike
+/// folded onto the Latin letter it imitates (e.g. both `аpple` in
+/// Cyrillic and `äpple` map to `apple`).
+pub fn skeleton(label: &str) -> String {
+    label
+        .to_lowercase()
+        .chars()
+        .map(|c| SKELETONS.get(&c).copied().unwrap_or(c))
+        .collect()
+}
+
+/// Whether `c` is not rendered, such as zero-width characters, joiners,
+/// variation selectors and bidirectional controls.
+fn is_invisible(c: char) -> bool {
+    matches!(
+        c,
+        '\u{00AD}'
+            | '\u{034F}'
+            | '\u{115F}'..='\u{1160}'
+            | '\u{180B}'..='\u{180E}'
+            | '\u{200B}'..='\u{200F}'
+            | '\u{202A}'..='\u{202E}'
+            | '\u{2060}'..='\u{2064}'
+            | '\u{2066}'..='\u{2069}'
+            | '\u{3164}'
+            | '\u{FE00}'..='\u{FE0F}'
+            | '\u{FEFF}'
+    )
+}
+
+/// Root label among the Unicode `labels` of a FQDN, falling back on the
+/// label right before the last one if the FQDN does not parse.
+fn root_label(labels: &[String]) -> Option<&String> {
+    let fqdn = labels.join(".");
+    let parsed = DomainRef::new(&fqdn).ok();
+
+    parsed
+        .and_then(|domain| labels.iter().find(|label| *label == domain.domain))
+        .or_else(|| labels.iter().rev().nth(1))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_analyze() {
+        let base = Domain::new("apple.com").unwrap();
+
+        // Cyrillic `а` followed by Latin letters.
+        let findings = analyze(&base, "аpple.com");
+        assert_eq!(
+            findings,
+            vec![
+                Finding::MixedScript {
+                    label: "аpple".to_string(),
+                    scripts: BTreeSet::from([Script::Latin, Script::Cyrillic]),
+                },
+                Finding::SkeletonCollision {
+                    label: "аpple".to_string(),
+                    skeleton: "apple".to_string(),
+                },
+            ]
+        );
+        assert_eq!(analyze(&base, "xn--pple-43d.com"), findings);
+
+        // Entirely Cyrillic, so only the skeleton gives it away.
+        assert_eq!(
+            analyze(&base, "арр\u{04CF}е.com"),
+            vec![Finding::SkeletonCollision {
+                label: "арр\u{04CF}е".to_string(),
+                skeleton: "apple".to_string(),
+            }]
+        );
+
+        let joined = analyze(&base, "app\u{200D}le.com");
+        assert!(joined.contains(&Finding::InvisibleChar {
+            label: "app\u{200D}le".to_string(),
+            position: 3,
+            character: '\u{200D}',
+        }));
+        assert!(joined
+            .iter()
+            .any(|finding| matches!(finding, Finding::SkeletonCollision { .. })));
+
+        assert_eq!(
+            analyze(&base, "xn--abc#.com"),
+            vec![Finding::InvalidPunycode {
+                label: "xn--abc#".to_string(),
+            }]
+        );
+
+        assert!(analyze(&base, "apple.com").is_empty());
+        assert!(analyze(&base, "bücher.de").is_empty());
+        assert!(analyze(&base, "APPLE.com").is_empty());
+        assert_eq!(skeleton("ÄPPLE"), "apple");
+    }
+}
@@ -0,0 +1,560 @@
+//! The jarm module exposes [JARM](https://github.com/salesforce/jarm)
+//! fingerprinting of TLS servers, see [`DomainMetadata::jarm_fingerprint`].
+//!
+//! A server is sent ten crafted TLS client hellos, and the way it answers
+//! each of them is hashed into a 62 character fingerprint of its TLS stack.
+//! Servers with the same configuration share the same fingerprint, so
+//! lookalike domains hosted by the same phishing kit can be clustered
+//! together regardless of their certificates.
+//!
+//! Example:
+//!
+//! ```no_run
+//! #[tokio::main]
+//! async fn main() {
+//!     let fingerprint = twistrs::jarm::fingerprint("examplle.com", 443).await;
+//!     println!("{fingerprint}");
+//! }
+//! ```
+//!
+//! ### Features
+//!
+//! This module requires the `jarm` feature toggled.
+//!
+//! [`DomainMetadata::jarm_fingerprint`]: crate::enrich::DomainMetadata::jarm_fingerprint
+
+use std::time::Duration;
+
+use rand::Rng;
+use sha2::{Digest, Sha256};
+use tokio::io::{AsyncReadExt, AsyncWriteExt};
+use tokio::net::TcpStream;
+
+/// Fingerprint of a server that did not answer any of the probes.
+pub const EMPTY_FINGERPRINT: &str =
+    "00000000000000000000000000000000000000000000000000000000000000";
+
+/// Maximum number of bytes of the server hello read per probe.
+const MAX_RESPONSE: usize = 1484;
+
+#[derive(Clone, Copy, PartialEq)]
+enum Version {
+    Tls11,
+    Tls12,
+    Tls13,
+}
+
+#[derive(Clone, Copy, PartialEq)]
+enum Order {
+    Forward,
+    Reverse,
+    TopHalf,
+    BottomHalf,
+    MiddleOut,
+}
+
+#[derive(Clone, Copy)]
+struct Probe {
+    version: Version,
+    /// Whether TLS 1.3 ciphers are offered.
+    tls13_ciphers: bool,
+    cipher_order: Order,
+    grease: bool,
+    rare_alpn: bool,
+    /// Whether the supported versions extension stops at TLS 1.2, only
+    /// relevant to TLS 1.2 probes.
+    tls12_support: bool,
+    extension_order: Order,
+}
+
+impl Probe {
+    const fn new(
+        version: Version,
+        cipher_order: Order,
+        grease: bool,
+        rare_alpn: bool,
+        tls12_support: bool,
+        extension_order: Order,
+    ) -> Probe {
+        Probe {
+            version,
+            tls13_ciphers: true,
+            cipher_order,
+            grease,
+            rare_alpn,
+            tls12_support,
+            extension_order,
+        }
+    }
+}
+
+/// The probes of the reference implementation, in order.
+#[rustfmt::skip]
+const PROBES: [Probe; 10] = [
+    //         version          ciphers            grease rare_alpn 1.2_support extensions
+    Probe::new(Version::Tls12, Order::Forward,    false, false,    true,       Order::Reverse),
+    Probe::new(Version::Tls12, Order::Reverse,    false, false,    true,       Order::Forward),
+    Probe::new(Version::Tls12, Order::TopHalf,    false, false,    false,      Order::Forward),
+    Probe::new(Version::Tls12, Order::BottomHalf, false, true,     false,      Order::Forward),
+    Probe::new(Version::Tls12, Order::MiddleOut,  true,  true,     false,      Order::Reverse),
+    Probe::new(Version::Tls11, Order::Forward,    false, false,    false,      Order::Forward),
+    Probe::new(Version::Tls13, Order::Forward,    false, false,    false,      Order::Reverse),
+    Probe::new(Version::Tls13, Order::Reverse,    false, false,    false,      Order::Forward),
+    Probe {
+        tls13_ciphers: false,
+        ..Probe::new(Version::Tls13, Order::Forward, false, false, false, Order::Forward)
+    },
+    Probe::new(Version::Tls13, Order::MiddleOut,  true,  false,    false,      Order::Reverse),
+];
+
+/// Ciphers offered by the probes, TLS 1.3 ones included.
+const CIPHERS: [u16; 69] = [
+    0x0016, 0x0033, 0x0067, 0xc09e, 0xc0a2, 0x009e, 0x0039, 0x006b, 0xc09f, 0xc0a3, 0x009f, 0x0045,
+    0x00be, 0x0088, 0x00c4, 0x009a, 0xc008, 0xc009, 0xc023, 0xc0ac, 0xc0ae, 0xc02b, 0xc00a, 0xc024,
+    0xc0ad, 0xc0af, 0xc02c, 0xc072, 0xc073, 0xcca9, 0x1302, 0x1301, 0xcc14, 0xc007, 0xc012, 0xc013,
+    0xc027, 0xc02f, 0xc014, 0xc028, 0xc030, 0xc060, 0xc061, 0xc076, 0xc077, 0xcca8, 0x1305, 0x1304,
+    0x1303, 0xcc13, 0xc011, 0x000a, 0x002f, 0x003c, 0xc09c, 0xc0a0, 0x009c, 0x0035, 0x003d, 0xc09d,
+    0xc0a1, 0x009d, 0x0041, 0x00ba, 0x0084, 0x00c0, 0x0007, 0x0004, 0x0005,
+];
+
+/// Ciphers a server may pick, in the order they are encoded in the
+/// fingerprint.
+const CIPHER_INDEX: [u16; 69] = [
+    0x0004, 0x0005, 0x0007, 0x000a, 0x0016, 0x002f, 0x0033, 0x0035, 0x0039, 0x003c, 0x003d, 0x0041,
+    0x0045, 0x0067, 0x006b, 0x0084, 0x0088, 0x009a, 0x009c, 0x009d, 0x009e, 0x009f, 0x00ba, 0x00be,
+    0x00c0, 0x00c4, 0xc007, 0xc008, 0xc009, 0xc00a, 0xc011, 0xc012, 0xc013, 0xc014, 0xc023, 0xc024,
+    0xc027, 0xc028, 0xc02b, 0xc02c, 0xc02f, 0xc030, 0xc060, 0xc061, 0xc072, 0xc073, 0xc076, 0xc077,
+    0xc09c, 0xc09d, 0xc09e, 0xc09f, 0xc0a0, 0xc0a1, 0xc0a2, 0xc0a3, 0xc0ac, 0xc0ad, 0xc0ae, 0xc0af,
+    0xcc13, 0xcc14, 0xcca8, 0xcca9, 0x1301, 0x1302, 0x1303, 0x1304, 0x1305,
+];
+
+const ALPNS: [&[u8]; 9] = [
+    b"http/0.9",
+    b"http/1.0",
+    b"http/1.1",
+    b"spdy/1",
+    b"spdy/2",
+    b"spdy/3",
+    b"h2",
+    b"h2c",
+    b"hq",
+];
+
+const RARE_ALPNS: [&[u8]; 7] = [
+    b"http/0.9",
+    b"http/1.0",
+    b"spdy/1",
+    b"spdy/2",
+    b"spdy/3",
+    b"h2c",
+    b"hq",
+];
+
+/// Compute the JARM fingerprint of the TLS server listening on `host`
+/// and `port`. Probes that fail, e.g. because the server is unreachable
+/// or times out after 5s, count as unanswered, so a server without TLS
+/// yields [`EMPTY_FINGERPRINT`].
+pub async fn fingerprint(host: &str, port: u16) -> String {
+    let probes = PROBES.iter().map(|probe| async move {
+        let hello = client_hello(probe, host);
+
+        match tokio::time::timeout(Duration::from_secs(5), exchange(host, port, &hello)).await {
+            Ok(Ok(response)) => server_hello(&response),
+            _ => String::from("|||"),
+        }
+    });
+
+    hash(&futures::future::join_all(probes).await.join(","))
+}
+
+/// Hash the raw, comma separated, answers to the ten probes into a
+/// fingerprint, as done by the reference implementation.
+pub fn hash(raw: &str) -> String {
+    let answers: Vec<&str> = raw.split(',').collect();
+
+    if answers.iter().all(|answer| *answer == "|||") {
+        return EMPTY_FINGERPRINT.to_string();
+    }
+
+    let mut fingerprint = String::new();
+    let mut alpns_and_extensions = String::new();
+
+    for answer in answers {
+        let mut components = answer.split('|');
+        let cipher = components.next().unwrap_or_default();
+        let version = components.next().unwrap_or_default();
+
+        fingerprint.push_str(&cipher_byte(cipher));
+        fingerprint.push(version_byte(version));
+        alpns_and_extensions.extend(components.take(2));
+    }
+
+    let digest = Sha256::digest(alpns_and_extensions.as_bytes());
+    fingerprint.push_str(&hex(&digest[..16]));
+    fingerprint
+}
+
+async fn exchange(host: &str, port: u16, hello: &[u8]) -> std::io::Result<Vec<u8>> {
+    let mut stream = TcpStream::connect((host, port)).await?;
+    stream.write_all(hello).await?;
+
+    let mut response = vec![0; MAX_RESPONSE];
+    let mut read = 0;
+
+    // Keep reading until the first record is complete, the reference
+    // implementation only parses the server hello out of it.
+    while read < MAX_RESPONSE {
+        let n = stream.read(&mut response[read..]).await?;
+        read += n;
+
+        let record_length = response
+            .get(3..5)
+            .map(|length| usize::from(u16::from_be_bytes([length[0], length[1]])));
+
+        if n == 0 || (read >= 5 && record_length.is_some_and(|length| read >= length + 5)) {
+            break;
+        }
+    }
+
+    response.truncate(read);
+    Ok(response)
+}
+
+fn client_hello(probe: &Probe, host: &str) -> Vec<u8> {
+    let mut rng = rand::thread_rng();
+
+    let (record_version, hello_version): (&[u8], &[u8]) = match probe.version {
+        Version::Tls11 => (b"\x03\x02", b"\x03\x02"),
+        Version::Tls12 => (b"\x03\x03", b"\x03\x03"),
+        Version::Tls13 => (b"\x03\x01", b"\x03\x03"),
+    };
+
+    let mut ciphers: Vec<u16> = CIPHERS
+        .into_iter()
+        .filter(|cipher| probe.tls13_ciphers || cipher >> 8 != 0x13)
+        .collect();
+    ciphers = mung(&ciphers, probe.cipher_order);
+    if probe.grease {
+        ciphers.insert(0, grease(&mut rng));
+    }
+
+    let mut hello = hello_version.to_vec();
+    hello.extend(rng.gen::<[u8; 32]>());
+    hello.push(32);
+    hello.extend(rng.gen::<[u8; 32]>());
+    push_length(&mut hello, ciphers.len() * 2);
+    hello.extend(ciphers.iter().flat_map(|cipher| cipher.to_be_bytes()));
+    // A single, null, compression method.
+    hello.extend(b"\x01\x00");
+    hello.extend(extensions(probe, host, &mut rng));
+
+    let mut handshake = vec![0x01, 0x00];
+    push_length(&mut handshake, hello.len());
+    handshake.extend(hello);
+
+    let mut record = vec![0x16];
+    record.extend(record_version);
+    push_length(&mut record, handshake.len());
+    record.extend(handshake);
+    record
+}
+
+fn extensions(probe: &Probe, host: &str, rng: &mut impl Rng) -> Vec<u8> {
+    let mut extensions = vec![];
+
+    if probe.grease {
+        extensions.extend(grease(rng).to_be_bytes());
+        extensions.extend(b"\x00\x00");
+    }
+
+    // Server name
+    extensions.extend(b"\x00\x00");
+    push_length(&mut extensions, host.len() + 5);
+    push_length(&mut extensions, host.len() + 3);
+    extensions.push(0);
+    push_length(&mut extensions, host.len());
+    extensions.extend(host.as_bytes());
+
+    // Extended master secret, max fragment length, renegotiation info,
+    // supported groups, EC point formats and session ticket.
+    extensions.extend(b"\x00\x17\x00\x00");
+    extensions.extend(b"\x00\x01\x00\x01\x01");
+    extensions.extend(b"\xff\x01\x00\x01\x00");
+    extensions.extend(b"\x00\x0a\x00\x0a\x00\x08\x00\x1d\x00\x17\x00\x18\x00\x19");
+    extensions.extend(b"\x00\x0b\x00\x02\x01\x00");
+    extensions.extend(b"\x00\x23\x00\x00");
+
+    // Application layer protocol negotiation
+    let alpns: &[&[u8]] = if probe.rare_alpn { &RARE_ALPNS } else { &ALPNS };
+    let protocols: Vec<u8> = mung(alpns, probe.extension_order)
+        .into_iter()
+        .flat_map(|alpn| [&[u8::try_from(alpn.len()).unwrap_or_default()], alpn].concat())
+        .collect();
+    extensions.extend(b"\x00\x10");
+    push_length(&mut extensions, protocols.len() + 2);
+    push_length(&mut extensions, protocols.len());
+    extensions.extend(protocols);
+
+    // Signature algorithms
+    extensions.extend(
+        b"\x00\x0d\x00\x14\x00\x12\x04\x03\x08\x04\x04\x01\x05\x03\x08\x05\x05\x01\x08\x06\x06\x01\x02\x01",
+    );
+
+    // Key share
+    let mut share = vec![];
+    if probe.grease {
+        share.extend(grease(rng).to_be_bytes());
+        share.extend(b"\x00\x01\x00");
+    }
+    share.extend(b"\x00\x1d\x00\x20");
+    share.extend(rng.gen::<[u8; 32]>());
+    extensions.extend(b"\x00\x33");
+    push_length(&mut extensions, share.len() + 2);
+    push_length(&mut extensions, share.len());
+    extensions.extend(share);
+
+    // PSK key exchange modes
+    extensions.extend(b"\x00\x2d\x00\x02\x01\x01");
+
+    // Supported versions
+    if probe.version == Version::Tls13 || probe.tls12_support {
+        let supported: &[&[u8]] = if probe.tls12_support {
+            &[b"\x03\x01", b"\x03\x02", b"\x03\x03"]
+        } else {
+            &[b"\x03\x01", b"\x03\x02", b"\x03\x03", b"\x03\x04"]
+        };
+
+        let mut versions = vec![];
+        if probe.grease {
+            versions.extend(grease(rng).to_be_bytes());
+        }
+        versions.extend(mung(supported, probe.extension_order).concat());
+
+        extensions.extend(b"\x00\x2b");
+        push_length(&mut extensions, versions.len() + 1);
+        extensions.push(u8::try_from(versions.len()).unwrap_or_default());
+        extensions.extend(versions);
+    }
+
+    let mut block = vec![];
+    push_length(&mut block, extensions.len());
+    block.extend(extensions);
+    block
+}
+
+/// Reorder `items` as done by the reference implementation.
+fn mung<T: Copy>(items: &[T], order: Order) -> Vec<T> {
+    let middle = items.len() / 2;
+    let odd = items.len() % 2 == 1;
+
+    match order {
+        Order::Forward => items.to_vec(),
+        Order::Reverse => items.iter().rev().copied().collect(),
+        Order::BottomHalf => items[middle + usize::from(odd)..].to_vec(),
+        Order::TopHalf => {
+            let mut top = if odd { vec![items[middle]] } else { vec![] };
+            top.extend(mung(&mung(items, Order::Reverse), Order::BottomHalf));
+            top
+        }
+        Order::MiddleOut => {
+            let mut out = if odd { vec![items[middle]] } else { vec![] };
+            let center = if odd { middle } else { middle - 1 };
+
+            for i in 1..=middle {
+                out.push(items[center + i]);
+                out.push(items[middle - i]);
+            }
+
+            out
+        }
+    }
+}
+
+/// Parse the `cipher|version|alpn|extensions` answer out of the server
+/// hello, `|||` if the server did not answer with one.
+fn server_hello(data: &[u8]) -> String {
+    let parse = || -> Option<String> {
+        if *data.first()? != 0x16 || *data.get(5)? != 0x02 {
+            return None;
+        }
+
+        let length = usize::from(u16::from_be_bytes([*data.get(3)?, *data.get(4)?]));
+        let session_id = usize::from(*data.get(43)?);
+        let cipher = data.get(session_id + 44..session_id + 46)?;
+        let version = data.get(9..11)?;
+
+        Some(format!(
+            "{}|{}|{}",
+            hex(cipher),
+            hex(version),
+            server_extensions(data, session_id, length)?
+        ))
+    };
+
+    parse().unwrap_or_else(|| String::from("|||"))
+}
+
+fn server_extensions(data: &[u8], session_id: usize, length: usize) -> Option<String> {
+    if *data.get(session_id + 47)? == 0x0b
+        || data.get(session_id + 50..session_id + 53) == Some(b"\x0e\xac\x0b")
+        || data.get(82..85) == Some(b"\x0f\xf0\x0b")
+        || session_id + 42 >= length
+    {
+        return Some(String::from("|"));
+    }
+
+    let block = data.get(session_id + 47..session_id + 49)?;
+    let mut offset = session_id + 49;
+    let end = usize::from(u16::from_be_bytes([block[0], block[1]])) + offset - 1;
+
+    let mut extensions: Vec<(&[u8], &[u8])> = vec![];
+    while offset < end {
+        let kind = data.get(offset..offset + 2)?;
+        let prefix = data.get(offset + 2..offset + 4)?;
+        let size = usize::from(u16::from_be_bytes([prefix[0], prefix[1]]));
+        let value = data.get(offset + 4..(offset + 4 + size).min(data.len()))?;
+
+        extensions.push((kind, value));
+        offset += size + 4;
+    }
+
+    let alpn = extensions
+        .iter()
+        .find(|(kind, _)| *kind == b"\x00\x10")
+        .map(|(_, value)| String::from_utf8_lossy(value.get(3..).unwrap_or_default()))
+        .unwrap_or_default();
+
+    let kinds: Vec<String> = extensions.iter().map(|(kind, _)| hex(kind)).collect();
+
+    Some(format!("{alpn}|{}", kinds.join("-")))
+}
+
+fn cipher_byte(cipher: &str) -> String {
+    if cipher.is_empty() {
+        return String::from("00");
+    }
+
+    let position = CIPHER_INDEX
+        .iter()
+        .position(|known| format!("{known:04x}") == cipher)
+        .unwrap_or(CIPHER_INDEX.len());
+
+    format!("{:02x}", position + 1)
+}
+
+fn version_byte(version: &str) -> char {
+    version
+        .get(3..4)
+        .and_then(|minor| minor.parse::<usize>().ok())
+        .and_then(|minor| "abcdef".chars().nth(minor))
+        .unwrap_or('0')
+}
+
+fn grease(rng: &mut impl Rng) -> u16 {
+    let nibble: u16 = rng.gen_range(0..16);
+    (nibble << 12) | (nibble << 4) | 0x0a0a
+}
+
+fn push_length(buffer: &mut Vec<u8>, length: usize) {
+    buffer.extend(u16::try_from(length).unwrap_or(u16::MAX).to_be_bytes());
+}
+
+fn hex(bytes: &[u8]) -> String {
+    bytes.iter().map(|byte| format!("{byte:02x}")).collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    use tokio::net::TcpListener;
+
+    /// TLS 1.2 server hello selecting `c02f`, with the renegotiation info
+    /// and ALPN (`h2`) extensions.
+    fn sample_server_hello() -> Vec<u8> {
+        let mut hello = vec![0x03, 0x03];
+        hello.extend([0; 32]);
+        hello.push(0);
+        hello.extend(b"\xc0\x2f\x00");
+
+        let extensions = b"\xff\x01\x00\x01\x00\x00\x10\x00\x05\x00\x03\x02h2";
+        push_length(&mut hello, extensions.len());
+        hello.extend(extensions);
+
+        let mut handshake = vec![0x02, 0x00];
+        push_length(&mut handshake, hello.len());
+        handshake.extend(hello);
+
+        let mut record = vec![0x16, 0x03, 0x03];
+        push_length(&mut record, handshake.len());
+        record.extend(handshake);
+        record
+    }
+
+    #[test]
+    fn test_mung() {
+        let items = [1, 2, 3, 4, 5];
+
+        assert_eq!(mung(&items, Order::Reverse), vec![5, 4, 3, 2, 1]);
+        assert_eq!(mung(&items, Order::BottomHalf), vec![4, 5]);
+        assert_eq!(mung(&items, Order::TopHalf), vec![3, 2, 1]);
+        assert_eq!(mung(&items, Order::MiddleOut), vec![3, 4, 2, 5, 1]);
+        assert_eq!(mung(&[1, 2, 3, 4], Order::MiddleOut), vec![3, 2, 4, 1]);
+    }
+
+    #[test]
+    fn test_client_hello() {
+        for probe in &PROBES {
+            let hello = client_hello(probe, "example.com");
+
+            // Record and handshake lengths cover the rest of the hello.
+            assert_eq!(hello[0], 0x16);
+            assert_eq!(
+                usize::from(u16::from_be_bytes([hello[3], hello[4]])),
+                hello.len() - 5
+            );
+            assert_eq!(
+                usize::from(u16::from_be_bytes([hello[7], hello[8]])),
+                hello.len() - 9
+            );
+            assert!(hello.windows(11).any(|window| window == b"example.com"));
+        }
+    }
+
+    #[test]
+    fn test_server_hello() {
+        assert_eq!(
+            server_hello(&sample_server_hello()),
+            "c02f|0303|h2|ff01-0010"
+        );
+        assert_eq!(server_hello(b"\x15\x03\x03\x00\x02\x02\x28"), "|||");
+        assert_eq!(server_hello(&[]), "|||");
+    }
+
+    #[test]
+    fn test_hash() {
+        assert_eq!(hash(&["|||"; 10].join(",")), EMPTY_FINGERPRINT);
+
+        let fingerprint = hash(&["c02f|0303|h2|ff01-0010"; 10].join(","));
+        assert_eq!(fingerprint.len(), 62);
+        assert!(fingerprint.starts_with("29d29d29d"));
+    }
+
+    #[tokio::test]
+    async fn test_fingerprint() {
+        let listener = TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let port = listener.local_addr().unwrap().port();
+
+        tokio::spawn(async move {
+            while let Ok((mut stream, _)) = listener.accept().await {
+                let mut request = vec![0; 1024];
+                let _ = stream.read(&mut request).await;
+                let _ = stream.write_all(&sample_server_hello()).await;
+            }
+        });
+
+        let fingerprint = fingerprint("127.0.0.1", port).await;
+        assert_eq!(fingerprint, hash(&["c02f|0303|h2|ff01-0010"; 10].join(",")));
+        assert_ne!(fingerprint, EMPTY_FINGERPRINT);
+    }
+}
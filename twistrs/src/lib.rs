@@ -18,12 +18,17 @@
 //! The following is a trivial example using [Tokio mpsc](https://docs.rs/tokio/0.2.22/tokio/sync/mpsc/index.html).
 //!
 //! ```
+//! # #[cfg(feature = "enrich")]
 //! use twistrs::enrich::DomainMetadata;
 //! use twistrs::permutate::Domain;
 //!
+//! # #[cfg(feature = "enrich")]
 //! use tokio::sync::mpsc;
 //!
 //!
+//! # #[cfg(not(feature = "enrich"))]
+//! # fn main() {}
+//! # #[cfg(feature = "enrich")]
 //! #[tokio::main]
 //! async fn main() {
 //!     let domain = Domain::new("google.com").unwrap();
@@ -51,9 +56,25 @@
 //!         println!("{:?}", i);
 //!     }
 //! }
-//!
 //! ```
 //!
+//! ### Features
+//!
+//! The default build only includes the permutation core, without any
+//! networking dependencies. Everything else is opt-in:
+//!
+//! * `enrich`—DNS and HTTP enrichment, pipelines, monitoring and stores.
+//! * `dns`, `tls`, `jarm`, `geoip`, `whois` and `smtp`—additional
+//!   enrichment lookups.
+//! * `phonetic`—homophone permutations and their word list.
+//! * `full`—all of the above.
+//!
+//! The stores (`sled_store`, `sqlite_store`), servers (`http`, `grpc`),
+//! `config`, `psl_refresh` and `spill` features are toggled separately.
+//! The `arbitrary` feature implements `Arbitrary` for domains,
+//! permutations and filters, for use by the fuzz targets in `fuzz/`.
+//! The `perf` feature exposes runtime counters of the permutation core.
+//!
 
 #![deny(
     // TODO(jdb): Uncomment missing docs later on
@@ -75,7 +96,7 @@
     clippy::checked_conversions,
     clippy::decimal_literal_representation,
     clippy::doc_markdown,
-    clippy::empty_enum,
+    clippy::empty_enums,
     clippy::explicit_into_iter_loop,
     clippy::explicit_iter_loop,
     clippy::expl_impl_clone_on_copy,
@@ -122,8 +143,52 @@
 #[macro_use]
 extern crate lazy_static;
 
+pub mod brand;
+#[cfg(feature = "enrich")]
+pub mod breaker;
+pub mod config;
 pub mod constants;
+#[cfg(feature = "enrich")]
 pub mod enrich;
 pub mod error;
+#[cfg(feature = "enrich")]
+pub mod evidence;
+pub mod filter;
+pub mod idn;
+#[cfg(feature = "jarm")]
+pub mod jarm;
+pub mod memory;
+#[cfg(feature = "enrich")]
+pub mod monitor;
+#[cfg(feature = "perf")]
+pub mod perf;
 pub mod permutate;
+#[cfg(feature = "enrich")]
+pub mod pipeline;
+pub mod progress;
+pub mod psl;
+#[cfg(feature = "enrich")]
+pub mod resolver;
+pub mod scoring;
+#[cfg(any(feature = "grpc", feature = "http"))]
+pub mod server;
+#[cfg(feature = "spill")]
+pub mod spill;
+#[cfg(feature = "enrich")]
+pub mod storage;
+#[cfg(feature = "enrich")]
+pub mod summary;
+#[cfg(feature = "enrich")]
+pub mod throttle;
+pub mod tld;
 mod tlds;
+#[cfg(feature = "tls")]
+pub mod tls;
+pub mod token;
+pub mod typo;
+pub mod verdict;
+#[cfg(feature = "enrich")]
+pub mod watchdog;
+#[cfg(feature = "whois")]
+pub mod whois;
+pub mod zone;
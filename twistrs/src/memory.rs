@@ -0,0 +1,204 @@
+//! The memory module exposes [`MemoryEstimate`], estimating the peak
+//! memory of a planned scan before running it, so that deployments with a
+//! hard memory limit (e.g. containers) can pick a scan mode that fits
+//! rather than finding out through the OOM killer.
+//!
+//! Estimates are derived from the number of permutations the engine
+//! expects to generate, see [`PermutationEngine::estimate`], and the
+//! average size of a candidate held in memory. They are meant for
+//! capacity planning, not accounting, and err on the high side.
+//!
+//! Example:
+//!
+//! ```
+//! use twistrs::memory::{MemoryEstimate, ScanMode};
+//! use twistrs::permutate::{Domain, PermutationEngine};
+//!
+//! let engine = PermutationEngine::new();
+//! let domains = vec![Domain::new("example.com").unwrap()];
+//!
+//! let estimate = MemoryEstimate::of(&engine, &domains);
+//! assert!(estimate.peak() > 0);
+//!
+//! match estimate.suggest(64 * 1024 * 1024) {
+//!     ScanMode::InMemory => println!("collect the permutations"),
+//!     ScanMode::Spill { threshold } => println!("buffer {threshold} permutations, spill the rest"),
+//!     ScanMode::Streaming => println!("consume the permutations as they are generated"),
+//! }
+//! ```
+
+use std::mem::size_of;
+
+use serde::{Deserialize, Serialize};
+
+use crate::permutate::{Domain, Permutation, PermutationEngine};
+
+/// Least number of items worth buffering in memory before spilling the
+/// rest, below which streaming is suggested instead.
+const MIN_SPILL_THRESHOLD: usize = 1_000;
+
+/// Rough average heap size of the metadata of an enriched permutation,
+/// i.e. its DNS records, banners and the like.
+#[cfg(feature = "enrich")]
+const ENRICHED_HEAP_SIZE: usize = 2 * 1024;
+
+/// How a scan should hold its permutations, given a memory budget.
+#[derive(Clone, Copy, Debug, Serialize, Deserialize, Hash, Eq, PartialEq)]
+pub enum ScanMode {
+    /// Every permutation fits in memory at once.
+    InMemory,
+
+    /// Hold up to `threshold` items in memory and spill the rest to disk,
+    /// e.g. through a `spill::ResultBuffer`.
+    Spill { threshold: usize },
+
+    /// Consume permutations as they are generated without gathering them,
+    /// e.g. through [`PermutationEngine::generate`] or
+    /// `Pipeline::run`, as the budget does not even hold a useful buffer.
+    Streaming,
+}
+
+/// Estimated memory footprint of a planned scan.
+// Reads ambiguously without the prefix once imported.
+#[allow(clippy::module_name_repetitions)]
+#[derive(Clone, Copy, Debug, Default, Serialize, Deserialize, Hash, Eq, PartialEq)]
+pub struct MemoryEstimate {
+    /// Estimated number of permutations generated.
+    pub permutations: usize,
+
+    /// Estimated average size of a permutation held in memory, in bytes,
+    /// including the strings it owns.
+    pub candidate_size: usize,
+}
+
+impl MemoryEstimate {
+    /// Estimate the footprint of gathering the permutations `engine`
+    /// generates for each of `domains`.
+    pub fn of(engine: &PermutationEngine, domains: &[Domain]) -> MemoryEstimate {
+        let (permutations, bytes) =
+            domains
+                .iter()
+                .fold((0_usize, 0_usize), |(count, bytes), domain| {
+                    let estimate = engine.estimate(domain);
+
+                    (
+                        count.saturating_add(estimate),
+                        bytes.saturating_add(estimate.saturating_mul(candidate_size(domain))),
+                    )
+                });
+
+        MemoryEstimate {
+            permutations,
+            candidate_size: bytes.checked_div(permutations).unwrap_or_default(),
+        }
+    }
+
+    /// Estimate the footprint of gathering the enriched results of the
+    /// scan rather than its bare permutations, e.g. to summarize them.
+    ///
+    /// ### Features
+    ///
+    /// This function requires the `enrich` feature toggled.
+    #[cfg(feature = "enrich")]
+    pub fn enriched(self) -> MemoryEstimate {
+        let metadata = size_of::<crate::pipeline::ScanResult>() - size_of::<Permutation>();
+
+        MemoryEstimate {
+            permutations: self.permutations,
+            candidate_size: self
+                .candidate_size
+                .saturating_add(metadata + ENRICHED_HEAP_SIZE),
+        }
+    }
+
+    /// Estimated peak memory of the scan in bytes, if every permutation
+    /// is held in memory at once.
+    pub fn peak(&self) -> usize {
+        self.permutations.saturating_mul(self.candidate_size)
+    }
+
+    /// Whether the scan fits in `budget` bytes when held in memory.
+    pub fn fits(&self, budget: usize) -> bool {
+        self.peak() <= budget
+    }
+
+    /// Suggest how the scan should hold its permutations to stay within
+    /// `budget` bytes.
+    pub fn suggest(&self, budget: usize) -> ScanMode {
+        if self.fits(budget) {
+            return ScanMode::InMemory;
+        }
+
+        match budget.checked_div(self.candidate_size) {
+            Some(threshold) if threshold >= MIN_SPILL_THRESHOLD => ScanMode::Spill { threshold },
+            _ => ScanMode::Streaming,
+        }
+    }
+}
+
+/// Estimated size of a permutation of `domain` held in memory. Each
+/// permutation owns its FQDN, canonical form, TLD and remainder, which
+/// together take about three times the FQDN (one character longer to
+/// account for additions and insertions).
+fn candidate_size(domain: &Domain) -> usize {
+    size_of::<Permutation>() + 3 * (domain.fqdn.len() + 1)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_memory_estimate() {
+        let engine = PermutationEngine::new();
+        let short = Domain::new("example.com").unwrap();
+        let long = Domain::new("a-much-longer-example.com").unwrap();
+
+        let estimate = MemoryEstimate::of(&engine, std::slice::from_ref(&short));
+        assert_eq!(estimate.permutations, engine.estimate(&short));
+        assert_eq!(estimate.candidate_size, candidate_size(&short));
+        assert!(
+            estimate.peak() >= engine.generate(&short).unwrap().count() * size_of::<Permutation>()
+        );
+
+        let both = MemoryEstimate::of(&engine, &[short.clone(), long.clone()]);
+        assert_eq!(
+            both.permutations,
+            engine.estimate(&short) + engine.estimate(&long)
+        );
+        assert!(both.candidate_size > candidate_size(&short));
+        assert!(both.candidate_size < candidate_size(&long));
+
+        assert_eq!(MemoryEstimate::of(&engine, &[]).peak(), 0);
+    }
+
+    #[test]
+    fn test_suggest() {
+        let estimate = MemoryEstimate {
+            permutations: 1_000_000,
+            candidate_size: 100,
+        };
+
+        assert!(estimate.fits(100_000_000));
+        assert_eq!(estimate.suggest(100_000_000), ScanMode::InMemory);
+        assert_eq!(
+            estimate.suggest(10_000_000),
+            ScanMode::Spill { threshold: 100_000 }
+        );
+        assert_eq!(estimate.suggest(50_000), ScanMode::Streaming);
+        assert_eq!(MemoryEstimate::default().suggest(0), ScanMode::InMemory);
+    }
+
+    #[cfg(feature = "enrich")]
+    #[test]
+    fn test_enriched_estimate() {
+        let estimate = MemoryEstimate::of(
+            &PermutationEngine::new(),
+            &[Domain::new("example.com").unwrap()],
+        );
+        let enriched = estimate.enriched();
+
+        assert_eq!(enriched.permutations, estimate.permutations);
+        assert!(enriched.candidate_size > estimate.candidate_size + ENRICHED_HEAP_SIZE);
+    }
+}
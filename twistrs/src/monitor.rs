@@ -0,0 +1,311 @@
+//! The monitor module exposes a scheduled loop that keeps track of
+//! a set of base domains over time. On every tick the permutations
+//! of each base domain are regenerated and re-enriched, and the
+//! results are compared against the previous snapshot.
+//!
+//! Any differences are emitted as [`Event`]s over a channel.
+//!
+//! Example:
+//!
+//! ```no_run
+//! use std::time::Duration;
+//!
+//! use tokio::sync::mpsc;
+//! use twistrs::monitor::Monitor;
+//! use twistrs::permutate::Domain;
+//!
+//! #[tokio::main]
+//! async fn main() {
+//!     let domains = vec![Domain::new("example.com").unwrap()];
+//!     let monitor = Monitor::new(domains, Duration::from_secs(60 * 60 * 24));
+//!
+//!     let (tx, mut rx) = mpsc::channel(1000);
+//!     tokio::spawn(monitor.run(tx));
+//!
+//!     while let Some(event) = rx.recv().await {
+//!         println!("{:?}", event);
+//!     }
+//! }
+//! ```
+use std::collections::HashSet;
+use std::time::Duration;
+
+use futures::stream::StreamExt;
+use serde::{Deserialize, Serialize};
+use tokio::sync::mpsc;
+
+use crate::enrich::DomainMetadata;
+use crate::error::Error;
+use crate::permutate::{Domain, Permutation};
+use crate::pipeline::{Pipeline, ScanResult};
+use crate::storage::{Comparison, DomainStore, MemoryStore};
+
+/// Change detected between two consecutive monitoring snapshots.
+// Events are yielded once per change, boxing the metadata is not worth
+// the API churn.
+#[allow(clippy::large_enum_variant)]
+#[derive(Debug, Clone, Serialize, Deserialize, Hash, Eq, PartialEq)]
+pub enum Event {
+    /// The permutation resolves for the first time since monitoring started.
+    NewlyRegistered {
+        permutation: Permutation,
+        metadata: DomainMetadata,
+    },
+
+    /// The permutation resolved at some point in the past, stopped
+    /// resolving, and now resolves again.
+    NewlyResolving {
+        permutation: Permutation,
+        metadata: DomainMetadata,
+    },
+
+    /// The permutation still resolves, however the content served
+    /// (currently the HTTP banner) has changed.
+    ContentChanged {
+        permutation: Permutation,
+        previous: DomainMetadata,
+        current: DomainMetadata,
+    },
+
+    /// The permutation used to resolve and no longer does.
+    Dropped {
+        permutation: Permutation,
+        previous: DomainMetadata,
+    },
+}
+
+/// Scheduled monitor over one or more base domains.
+///
+/// Snapshots are kept in a [`DomainStore`], meaning that a monitor backed
+/// by a persistent store picks up where the previous process left off.
+/// Only permutations that resolved at least once are stored.
+///
+/// The first tick against an empty store only seeds the snapshot and
+/// does not emit any events, since every resolving permutation would
+/// otherwise be reported as newly registered.
+pub struct Monitor<S: DomainStore = MemoryStore> {
+    /// Base domains that are permutated and monitored.
+    pub domains: Vec<Domain>,
+
+    /// Time to wait between two consecutive ticks.
+    pub interval: Duration,
+
+    /// Pipeline used to enrich the permutations on every tick.
+    pub pipeline: Pipeline,
+
+    store: S,
+    seeded: bool,
+}
+
+impl Monitor<MemoryStore> {
+    /// Create a new in-memory monitor over `domains`, ticking every `interval`.
+    pub fn new(domains: Vec<Domain>, interval: Duration) -> Monitor<MemoryStore> {
+        Monitor::with_store(domains, interval, MemoryStore::new())
+    }
+}
+
+impl<S: DomainStore> Monitor<S> {
+    /// Create a new monitor over `domains` that keeps its snapshots in
+    /// `store`. If the store already contains records, the first tick
+    /// emits events against them.
+    pub fn with_store(domains: Vec<Domain>, interval: Duration, store: S) -> Monitor<S> {
+        let seeded = store
+            .iter()
+            .is_ok_and(|mut records| records.next().is_some());
+
+        Monitor {
+            domains,
+            interval,
+            pipeline: Pipeline::new(),
+            store,
+            seeded,
+        }
+    }
+
+    /// Store holding the latest snapshot of each resolving permutation.
+    pub fn store(&self) -> &S {
+        &self.store
+    }
+
+    /// Run the monitor until the receiving half of `tx` is dropped.
+    pub async fn run(mut self, tx: mpsc::Sender<Event>) -> Result<(), Error> {
+        let mut interval = tokio::time::interval(self.interval);
+
+        while !tx.is_closed() {
+            interval.tick().await;
+            self.tick(&tx).await?;
+        }
+
+        Ok(())
+    }
+
+    /// Perform a single monitoring pass, regenerating and enriching all
+    /// permutations and emitting any detected changes over `tx`.
+    pub async fn tick(&mut self, tx: &mpsc::Sender<Event>) -> Result<(), Error> {
+        let mut permutations = HashSet::new();
+
+        for domain in &self.domains {
+            permutations.extend(domain.all()?);
+        }
+
+        let results: Vec<ScanResult> = self.pipeline.run(permutations).collect().await;
+
+        let seeded = self.seeded;
+        self.seeded = true;
+
+        for result in results {
+            if let Some(event) = self.compare(result.permutation, result.metadata)? {
+                if seeded && tx.send(event).await.is_err() {
+                    // Receiver has been dropped, nothing left to report to.
+                    break;
+                }
+            }
+        }
+
+        Ok(())
+    }
+
+    /// Compare the latest metadata of a permutation against the store,
+    /// updating the store and returning the change (if any).
+    fn compare(
+        &mut self,
+        permutation: Permutation,
+        current: DomainMetadata,
+    ) -> Result<Option<Event>, Error> {
+        let now_resolving = is_resolving(&current);
+
+        let event = match self.store.compare(&current)? {
+            Comparison::New if now_resolving => Some(Event::NewlyRegistered {
+                permutation,
+                metadata: current.clone(),
+            }),
+            Comparison::Changed(previous) => match (is_resolving(&previous), now_resolving) {
+                (false, true) => Some(Event::NewlyResolving {
+                    permutation,
+                    metadata: current.clone(),
+                }),
+                (true, false) => Some(Event::Dropped {
+                    permutation,
+                    previous,
+                }),
+                (true, true) if previous.http_banner != current.http_banner => {
+                    Some(Event::ContentChanged {
+                        permutation,
+                        previous,
+                        current: current.clone(),
+                    })
+                }
+                _ => None,
+            },
+            _ => None,
+        };
+
+        // Permutations that never resolved are not worth keeping track of.
+        if event.is_some() || now_resolving {
+            self.store.put(current)?;
+        }
+
+        Ok(event)
+    }
+}
+
+fn is_resolving(metadata: &DomainMetadata) -> bool {
+    metadata.dns.resolves()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    use crate::enrich::DnsRecords;
+    use crate::permutate::PermutationKind;
+
+    fn permutation(fqdn: &str) -> Permutation {
+        Permutation {
+            domain: Domain::new(fqdn).unwrap(),
+            kind: PermutationKind::Addition,
+            substitutions: vec![],
+        }
+    }
+
+    fn metadata(fqdn: &str, resolving: bool, banner: Option<&str>) -> DomainMetadata {
+        let mut metadata = DomainMetadata::new(fqdn.to_string());
+
+        if resolving {
+            metadata.dns = DnsRecords::from_ips(["127.0.0.1".parse().unwrap()]);
+        }
+
+        metadata.http_banner = banner.map(String::from);
+        metadata
+    }
+
+    #[test]
+    fn test_monitor_compare_lifecycle() {
+        let mut monitor = Monitor::new(vec![], Duration::from_secs(1));
+        let fqdn = "examplea.com";
+
+        assert!(monitor
+            .compare(permutation(fqdn), metadata(fqdn, false, None))
+            .unwrap()
+            .is_none());
+
+        assert!(matches!(
+            monitor
+                .compare(permutation(fqdn), metadata(fqdn, true, Some("nginx")))
+                .unwrap(),
+            Some(Event::NewlyRegistered { .. })
+        ));
+
+        assert!(monitor
+            .compare(permutation(fqdn), metadata(fqdn, true, Some("nginx")))
+            .unwrap()
+            .is_none());
+
+        assert!(matches!(
+            monitor
+                .compare(permutation(fqdn), metadata(fqdn, true, Some("apache")))
+                .unwrap(),
+            Some(Event::ContentChanged { .. })
+        ));
+
+        assert!(matches!(
+            monitor
+                .compare(permutation(fqdn), metadata(fqdn, false, None))
+                .unwrap(),
+            Some(Event::Dropped { .. })
+        ));
+
+        assert!(matches!(
+            monitor
+                .compare(permutation(fqdn), metadata(fqdn, true, None))
+                .unwrap(),
+            Some(Event::NewlyResolving { .. })
+        ));
+    }
+
+    #[test]
+    fn test_monitor_legacy_snapshot() {
+        // Snapshot persisted before `ips` was replaced by `dns`.
+        let legacy: DomainMetadata = serde_json::from_str(
+            r#"{"fqdn":"examplea.com","ips":["127.0.0.1"],"smtp":null,"http_banner":"nginx","geo_ip_lookups":null,"who_is_lookup":null}"#,
+        )
+        .unwrap();
+        assert!(legacy.dns.resolves());
+
+        let mut store = MemoryStore::new();
+        store.put(legacy).unwrap();
+
+        let fqdn = "examplea.com";
+        let mut monitor = Monitor::with_store(vec![], Duration::from_secs(1), store);
+        assert!(monitor
+            .compare(permutation(fqdn), metadata(fqdn, true, Some("nginx")))
+            .unwrap()
+            .is_none());
+        assert!(matches!(
+            monitor
+                .compare(permutation(fqdn), metadata(fqdn, false, None))
+                .unwrap(),
+            Some(Event::Dropped { .. })
+        ));
+    }
+}
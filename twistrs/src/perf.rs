@@ -0,0 +1,102 @@
+//! The perf module exposes lightweight counters of the work performed by
+//! the permutation core, so that slow scans can be diagnosed (e.g. most
+//! candidates being rejected, or parsed over and over) without attaching
+//! a profiler.
+//!
+//! Counters are process-wide and only ever increase, until [`reset`].
+//!
+//! Example:
+//!
+//! ```
+//! use twistrs::perf;
+//! use twistrs::permutate::Domain;
+//!
+//! let domain = Domain::new("example.com").unwrap();
+//! let permutations = domain.addition().count();
+//!
+//! let counters = perf::snapshot();
+//! assert!(counters.candidates >= permutations as u64);
+//! assert!(counters.psl_parses >= counters.candidates);
+//! ```
+//!
+//! ### Features
+//!
+//! This module requires the `perf` feature toggled. Without it, the
+//! counters are compiled out entirely.
+use std::sync::atomic::{AtomicU64, Ordering};
+
+use serde::{Deserialize, Serialize};
+
+static CANDIDATES: AtomicU64 = AtomicU64::new(0);
+static REJECTED: AtomicU64 = AtomicU64::new(0);
+static PSL_PARSES: AtomicU64 = AtomicU64::new(0);
+
+/// Snapshot of the counters, see [`snapshot`].
+#[derive(Clone, Copy, Debug, Default, Serialize, Deserialize, Hash, Eq, PartialEq)]
+pub struct Counters {
+    /// Candidate domains generated by the permutation methods.
+    pub candidates: u64,
+
+    /// Candidates rejected, either because they are not valid domains or
+    /// by the IDNA mode or filter of a
+    /// [`ValidationCtx`](crate::permutate::ValidationCtx).
+    pub rejected: u64,
+
+    /// Domain names parsed against the public suffix list, including the
+    /// ones parsed by [`Domain::new`](crate::permutate::Domain::new).
+    pub psl_parses: u64,
+}
+
+/// Current value of the counters.
+pub fn snapshot() -> Counters {
+    Counters {
+        candidates: CANDIDATES.load(Ordering::Relaxed),
+        rejected: REJECTED.load(Ordering::Relaxed),
+        psl_parses: PSL_PARSES.load(Ordering::Relaxed),
+    }
+}
+
+/// Reset the counters to zero, e.g. between two scans.
+pub fn reset() {
+    for counter in [&CANDIDATES, &REJECTED, &PSL_PARSES] {
+        counter.store(0, Ordering::Relaxed);
+    }
+}
+
+pub(crate) fn count_candidate() {
+    CANDIDATES.fetch_add(1, Ordering::Relaxed);
+}
+
+pub(crate) fn count_rejected() {
+    REJECTED.fetch_add(1, Ordering::Relaxed);
+}
+
+pub(crate) fn count_psl_parse() {
+    PSL_PARSES.fetch_add(1, Ordering::Relaxed);
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    use crate::permutate::{Domain, IdnaMode, ValidationCtx};
+
+    #[test]
+    fn test_counters() {
+        // Other tests run concurrently, so only lower bounds hold.
+        let before = snapshot();
+
+        let domain = Domain::new("example.com").unwrap();
+        let mut ctx = ValidationCtx::new();
+        ctx.idna = IdnaMode::Validate;
+
+        let generated = domain.homoglyph().unwrap().count() as u64;
+        let validated = domain.all_in(&ctx).unwrap().count() as u64;
+        let after = snapshot();
+
+        assert!(after.candidates - before.candidates >= generated);
+        assert!(after.psl_parses - before.psl_parses > generated);
+        assert!(after.rejected > before.rejected);
+        assert!(validated > 0);
+    }
+}
@@ -17,15 +17,36 @@
 //!
 //! Additionally the permutation module can be used independently
 //! from the enrichment module.
-use crate::constants::{ASCII_LOWER, HOMOGLYPHS, KEYBOARD_LAYOUTS, MAPPED_VALUES, VOWELS};
+#[cfg(feature = "phonetic")]
+use crate::constants::HOMOPHONES;
+use crate::constants::{
+    ARABIC_KEYBOARD_LAYOUT, ASCII_LOWER, CROSS_SCRIPT_HOMOGLYPHS, CYRILLIC_TO_LATIN,
+    GREEK_KEYBOARD_LAYOUT, GREEK_TO_LATIN, HOMOGLYPHS, IDN_CONFUSABLES, KEYBOARD_LAYOUTS,
+    KEYBOARD_ROWS, LATIN_TO_CYRILLIC, LATIN_TO_GREEK, MAPPED_VALUES, MAX_REORDER_TOKENS,
+    MISSPELLINGS, NUMBER_WORDS, OCR_CONFUSIONS, POPULAR_TLDS, RUSSIAN_KEYBOARD_LAYOUT, VOWELS,
+};
 use crate::error::Error;
+use crate::filter::{Filter, FilterDescriptor};
+#[cfg(feature = "perf")]
+use crate::perf;
+use crate::progress::{Callback, Phase, ProgressIter};
+use crate::psl::{self, SuffixList, SuffixSection};
+use crate::tld::{CcTldNeighbors, TldSet};
+use crate::token::{self, Token};
 
-use std::collections::HashSet;
+use std::collections::btree_map::{self, Entry};
+use std::collections::{BTreeMap, BTreeSet, HashMap, HashSet};
+use std::fmt;
+use std::hash::{Hash, Hasher};
+use std::net::IpAddr;
+use std::str::FromStr;
+use std::sync::Arc;
 
 use addr::parser::DomainName;
 use addr::psl::List;
 use itertools::Itertools;
 use serde::{Deserialize, Serialize};
+use sha2::{Digest, Sha256};
 
 // Include further constants such as dictionaries that are
 // generated during compile time.
@@ -34,9 +55,15 @@ include!(concat!(env!("OUT_DIR"), "/data.rs"));
 use crate::tlds::TLDS;
 
 /// Wrapper around an FQDN to perform permutations against.
-#[derive(Clone, Hash, Default, Debug, Serialize, Deserialize, Eq, PartialEq, Ord, PartialOrd)]
+///
+/// Domains are compared, hashed and ordered by their canonical form, so
+/// that `Example.com` and `example.com`, or `bücher.de` and
+/// `xn--bcher-kva.de`, are the same domain.
+#[derive(Clone, Default, Debug, Serialize, Deserialize)]
+#[serde(from = "DomainParts")]
 pub struct Domain {
-    /// The domain FQDN to generate permutations from.
+    /// The domain FQDN to generate permutations from, as it was given,
+    /// i.e. its display form.
     pub fqdn: String,
 
     /// The top-level domain of the FQDN (e.g. `.com`).
@@ -44,31 +71,1714 @@ pub struct Domain {
 
     /// The remainder of the domain (e.g. `google`).
     pub domain: String,
+
+    /// The FQDN lowercased, in its ASCII-compatible (punycode) form and
+    /// without a trailing dot, see [`Domain::canonical`].
+    canonical: String,
+}
+
+/// Serialized form of a [`Domain`], whose canonical form is computed
+/// again rather than trusted.
+#[derive(Deserialize)]
+struct DomainParts {
+    fqdn: String,
+    tld: String,
+    domain: String,
+}
+
+impl From<DomainParts> for Domain {
+    fn from(parts: DomainParts) -> Domain {
+        Domain::from_parts(parts.fqdn, parts.tld, parts.domain)
+    }
+}
+
+impl PartialEq for Domain {
+    fn eq(&self, other: &Domain) -> bool {
+        self.canonical == other.canonical
+    }
+}
+
+impl Eq for Domain {}
+
+impl Hash for Domain {
+    fn hash<H: Hasher>(&self, state: &mut H) {
+        self.canonical.hash(state);
+    }
+}
+
+impl PartialOrd for Domain {
+    fn partial_cmp(&self, other: &Domain) -> Option<std::cmp::Ordering> {
+        Some(self.cmp(other))
+    }
+}
+
+impl Ord for Domain {
+    fn cmp(&self, other: &Domain) -> std::cmp::Ordering {
+        self.canonical.cmp(&other.canonical)
+    }
+}
+
+/// Canonical form of `fqdn`: lowercased, in its ASCII-compatible
+/// (punycode) form and without a trailing dot. FQDNs that IDNA rejects
+/// are only lowercased.
+pub fn canonicalize(fqdn: &str) -> String {
+    let trimmed = fqdn.trim_end_matches('.');
+
+    if trimmed.is_ascii() {
+        return trimmed.to_ascii_lowercase();
+    }
+
+    idna::domain_to_ascii(trimmed).unwrap_or_else(|_| trimmed.to_lowercase())
+}
+
+/// Borrowed counterpart of [`Domain`], whose parts are slices of the FQDN
+/// it was parsed from. Parsing a `DomainRef` does not allocate, which
+/// suits read-only workflows that discard most of the candidates they
+/// look at.
+///
+/// ```
+/// use twistrs::permutate::DomainRef;
+///
+/// let domain = DomainRef::new("mail.example.co.uk").unwrap();
+/// assert_eq!((domain.domain, domain.tld), ("example", "co.uk"));
+/// assert_eq!(domain.to_domain().fqdn, "mail.example.co.uk");
+/// ```
+#[derive(Clone, Copy, Hash, Debug, Serialize, Eq, PartialEq, Ord, PartialOrd)]
+pub struct DomainRef<'a> {
+    pub fqdn: &'a str,
+    pub tld: &'a str,
+    pub domain: &'a str,
+}
+
+/// Options of [`Domain::new_with`].
+#[derive(Clone, Copy, Debug, Default, Serialize, Deserialize, Hash, Eq, PartialEq)]
+#[serde(default)]
+pub struct ParseOptions {
+    /// Whether suffixes of the private section of the public suffix list
+    /// (e.g. `github.io`) are TLDs, so that `example.github.io` has the
+    /// root label `example`. Otherwise they are regular labels under their
+    /// ICANN suffix, and the root label is `github`.
+    pub private_suffixes: bool,
+}
+
+#[cfg(feature = "arbitrary")]
+impl<'a> arbitrary::Arbitrary<'a> for Domain {
+    fn arbitrary(u: &mut arbitrary::Unstructured<'a>) -> arbitrary::Result<Domain> {
+        // Arbitrary labels under a known suffix, so that most inputs make
+        // it past validation and on to the generators.
+        let label: String = u.arbitrary()?;
+        let tld = u.choose(&TLDS)?;
+
+        Domain::new(&format!("{label}.{tld}")).map_err(|_| arbitrary::Error::IncorrectFormat)
+    }
+}
+
+#[derive(Clone, Debug, Serialize, Deserialize, Hash, Eq, PartialEq, Ord, PartialOrd)]
+#[cfg_attr(feature = "arbitrary", derive(arbitrary::Arbitrary))]
+pub struct Permutation {
+    pub domain: Domain,
+    pub kind: PermutationKind,
+
+    /// Characters substituted to produce the permutation, recorded by the
+    /// generators that replace characters one-for-one (i.e. homoglyphs
+    /// and IDN homographs), so that lookalikes can be rendered with the
+    /// substituted glyphs highlighted. Empty for every other kind.
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub substitutions: Vec<Substitution>,
+}
+
+#[derive(Clone, Copy, Serialize, Deserialize, Hash, Debug, Eq, PartialEq, Ord, PartialOrd)]
+#[cfg_attr(feature = "arbitrary", derive(arbitrary::Arbitrary))]
+pub enum PermutationKind {
+    Addition,
+    Bitsquatting,
+    Hyphenation,
+    Insertion,
+    Omission,
+    Repetition,
+    Replacement,
+    Subdomain,
+    Transposition,
+    VowelSwap,
+    DoubleVowelInsertion,
+    Keyword,
+    Tld,
+    Homoglyph,
+    Mapped,
+    Transliteration,
+    OcrConfusion,
+    TokenReorder,
+    SeparatorVariation,
+    Dictionary,
+
+    /// Only generated with the `phonetic` feature toggled.
+    Homophone,
+    NumberStep,
+    Spelling,
+    IdnHomograph,
+    HyphenRemoval,
+    DotOmission,
+    DotHyphenSwap,
+    FatFinger,
+    WrongLayout,
+    VowelRemoval,
+    Misspelling,
+    TldAppend,
+}
+
+impl Permutation {
+    /// Stable identifier of the permutation of `origin`, suitable as a
+    /// primary key across runs, machines and crate versions.
+    ///
+    /// The identifier is the hex-encoded first 128 bits of the SHA-256
+    /// digest of the canonical origin FQDN, canonical permutation FQDN
+    /// (see [`Domain::canonical`]) and permutation kind name, so that the
+    /// Unicode and punycode forms of a domain share their identifiers.
+    pub fn id(&self, origin: &Domain) -> String {
+        let mut hasher = Sha256::new();
+
+        for field in [
+            origin.canonical(),
+            self.domain.canonical(),
+            &format!("{:?}", self.kind),
+        ] {
+            // Length-prefix each field so that their boundaries are
+            // unambiguous.
+            hasher.update((field.len() as u64).to_be_bytes());
+            hasher.update(field.as_bytes());
+        }
+
+        hasher
+            .finalize()
+            .iter()
+            .take(16)
+            .map(|byte| format!("{byte:02x}"))
+            .collect()
+    }
+}
+
+/// Set of permutations keyed by their canonical FQDN (see
+/// [`Domain::canonical`]), or by their stable identifier (see
+/// [`PermutationSet::by_id`]), supporting set operations to compare the
+/// outputs of different configurations, crate versions or tools.
+///
+/// Only sets keyed the same way can be meaningfully combined. Keyed by
+/// FQDN, a set holds the first permutation inserted for each FQDN.
+///
+/// ```
+/// use twistrs::permutate::{Domain, PermutationSet};
+///
+/// let domain = Domain::new("example.com").unwrap();
+/// let additions: PermutationSet = domain.addition().collect();
+/// let repetitions: PermutationSet = domain.repetition().collect();
+///
+/// let both = additions.intersection(&repetitions);
+/// assert!(both.contains("examplee.com"));
+/// assert!(!additions.difference(&repetitions).contains("examplee.com"));
+/// ```
+#[derive(Clone, Debug, Default, Serialize, Deserialize, Eq, PartialEq)]
+#[serde(transparent)]
+pub struct PermutationSet {
+    permutations: BTreeMap<String, Permutation>,
+}
+
+impl PermutationSet {
+    pub fn new() -> PermutationSet {
+        PermutationSet::default()
+    }
+
+    /// Set of `permutations` of `origin` keyed by their stable identifier
+    /// (see [`Permutation::id`]), i.e. telling the same FQDN generated by
+    /// different kinds apart.
+    pub fn by_id<I>(origin: &Domain, permutations: I) -> PermutationSet
+    where
+        I: IntoIterator<Item = Permutation>,
+    {
+        let mut set = PermutationSet::new();
+
+        for permutation in permutations {
+            set.permutations
+                .entry(permutation.id(origin))
+                .or_insert(permutation);
+        }
+
+        set
+    }
+
+    /// Insert `permutation` keyed by its canonical FQDN, returning
+    /// whether the set did not hold the FQDN yet.
+    pub fn insert(&mut self, permutation: Permutation) -> bool {
+        let key = permutation.domain.canonical().to_string();
+
+        match self.permutations.entry(key) {
+            Entry::Vacant(entry) => {
+                entry.insert(permutation);
+                true
+            }
+            Entry::Occupied(_) => false,
+        }
+    }
+
+    pub fn len(&self) -> usize {
+        self.permutations.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.permutations.is_empty()
+    }
+
+    /// Whether the set holds `key`, an FQDN or an identifier depending on
+    /// how the set is keyed.
+    pub fn contains(&self, key: &str) -> bool {
+        self.get(key).is_some()
+    }
+
+    /// The permutation stored under `key`, see [`PermutationSet::contains`].
+    pub fn get(&self, key: &str) -> Option<&Permutation> {
+        self.permutations
+            .get(key)
+            .or_else(|| self.permutations.get(&canonicalize(key)))
+    }
+
+    /// The keys of the set, in order.
+    pub fn keys(&self) -> impl Iterator<Item = &str> {
+        self.permutations.keys().map(String::as_str)
+    }
+
+    /// The permutations of the set, ordered by key.
+    pub fn iter(&self) -> impl Iterator<Item = &Permutation> {
+        self.permutations.values()
+    }
+
+    /// Permutations in either set, taken from `self` when in both.
+    pub fn union(&self, other: &PermutationSet) -> PermutationSet {
+        let mut permutations = other.permutations.clone();
+        permutations.extend(self.permutations.clone());
+
+        PermutationSet { permutations }
+    }
+
+    /// Permutations of `self` also in `other`.
+    pub fn intersection(&self, other: &PermutationSet) -> PermutationSet {
+        self.filter(|key| other.permutations.contains_key(key))
+    }
+
+    /// Permutations of `self` not in `other`.
+    pub fn difference(&self, other: &PermutationSet) -> PermutationSet {
+        self.filter(|key| !other.permutations.contains_key(key))
+    }
+
+    /// Permutations in exactly one of the sets.
+    pub fn symmetric_difference(&self, other: &PermutationSet) -> PermutationSet {
+        self.difference(other).union(&other.difference(self))
+    }
+
+    fn filter<F>(&self, keep: F) -> PermutationSet
+    where
+        F: Fn(&str) -> bool,
+    {
+        PermutationSet {
+            permutations: self
+                .permutations
+                .iter()
+                .filter(|(key, _)| keep(key))
+                .map(|(key, permutation)| (key.clone(), permutation.clone()))
+                .collect(),
+        }
+    }
+}
+
+impl FromIterator<Permutation> for PermutationSet {
+    fn from_iter<I: IntoIterator<Item = Permutation>>(permutations: I) -> PermutationSet {
+        let mut set = PermutationSet::new();
+        set.extend(permutations);
+        set
+    }
+}
+
+impl Extend<Permutation> for PermutationSet {
+    fn extend<I: IntoIterator<Item = Permutation>>(&mut self, permutations: I) {
+        for permutation in permutations {
+            self.insert(permutation);
+        }
+    }
+}
+
+impl IntoIterator for PermutationSet {
+    type Item = Permutation;
+    type IntoIter = btree_map::IntoValues<String, Permutation>;
+
+    fn into_iter(self) -> Self::IntoIter {
+        self.permutations.into_values()
+    }
+}
+
+/// Unicode script a character belongs to, as far as lookalike domains are
+/// concerned.
+#[derive(Clone, Copy, Debug, Serialize, Deserialize, Hash, Eq, PartialEq, Ord, PartialOrd)]
+#[cfg_attr(feature = "arbitrary", derive(arbitrary::Arbitrary))]
+pub enum Script {
+    Latin,
+    Greek,
+    Cyrillic,
+    Armenian,
+    Arabic,
+
+    /// Characters shared across scripts, i.e. digits and hyphens.
+    Common,
+    Other,
+}
+
+impl Script {
+    /// The script of `c`.
+    pub fn of(c: char) -> Script {
+        match c {
+            '0'..='9' | '-' => Script::Common,
+            'a'..='z'
+            | 'A'..='Z'
+            | '\u{00C0}'..='\u{02AF}'
+            | '\u{1D00}'..='\u{1DBF}'
+            | '\u{1E00}'..='\u{1EFF}'
+            | '\u{2C60}'..='\u{2C7F}'
+            | '\u{A720}'..='\u{A7FF}' => Script::Latin,
+            '\u{0370}'..='\u{03FF}' | '\u{1F00}'..='\u{1FFF}' => Script::Greek,
+            '\u{0400}'..='\u{052F}' | '\u{2DE0}'..='\u{2DFF}' | '\u{A640}'..='\u{A69F}' => {
+                Script::Cyrillic
+            }
+            '\u{0530}'..='\u{058F}' => Script::Armenian,
+            '\u{0600}'..='\u{06FF}' => Script::Arabic,
+            _ => Script::Other,
+        }
+    }
+}
+
+/// Character substituted by a permutation, e.g. `a` by the Cyrillic `а`.
+#[derive(Clone, Copy, Debug, Serialize, Deserialize, Hash, Eq, PartialEq, Ord, PartialOrd)]
+#[cfg_attr(feature = "arbitrary", derive(arbitrary::Arbitrary))]
+pub struct Substitution {
+    /// Index of the character within the FQDN, in characters. IDN
+    /// homographs are yielded in punycode, so their index is within the
+    /// Unicode form of the FQDN instead.
+    pub position: usize,
+    pub original: char,
+    pub replacement: char,
+    pub script: Script,
+}
+
+impl Substitution {
+    fn new(position: usize, original: char, replacement: char) -> Substitution {
+        Substitution {
+            position,
+            original,
+            replacement,
+            script: Script::of(replacement),
+        }
+    }
+
+    /// Characters of `replacement` that differ from those of `original`
+    /// at the same position, for the generators that substitute
+    /// characters one-for-one.
+    fn between(original: &str, replacement: &str) -> Vec<Substitution> {
+        if original.chars().count() != replacement.chars().count() {
+            return vec![];
+        }
+
+        original
+            .chars()
+            .zip(replacement.chars())
+            .enumerate()
+            .filter(|(_, (before, after))| before != after)
+            .map(|(position, (before, after))| Substitution::new(position, before, after))
+            .collect()
+    }
+}
+
+impl PermutationKind {
+    /// Every permutation kind, in the order they are generated by
+    /// [`Domain::all`].
+    pub const ALL: [PermutationKind; 32] = [
+        PermutationKind::Addition,
+        PermutationKind::Bitsquatting,
+        PermutationKind::Hyphenation,
+        PermutationKind::Insertion,
+        PermutationKind::Omission,
+        PermutationKind::Repetition,
+        PermutationKind::Replacement,
+        PermutationKind::Subdomain,
+        PermutationKind::Transposition,
+        PermutationKind::VowelSwap,
+        PermutationKind::DoubleVowelInsertion,
+        PermutationKind::Keyword,
+        PermutationKind::Tld,
+        PermutationKind::Mapped,
+        PermutationKind::Transliteration,
+        PermutationKind::OcrConfusion,
+        PermutationKind::TokenReorder,
+        PermutationKind::SeparatorVariation,
+        PermutationKind::Dictionary,
+        PermutationKind::Homophone,
+        PermutationKind::NumberStep,
+        PermutationKind::Spelling,
+        PermutationKind::IdnHomograph,
+        PermutationKind::HyphenRemoval,
+        PermutationKind::DotOmission,
+        PermutationKind::DotHyphenSwap,
+        PermutationKind::FatFinger,
+        PermutationKind::WrongLayout,
+        PermutationKind::VowelRemoval,
+        PermutationKind::Misspelling,
+        PermutationKind::TldAppend,
+        PermutationKind::Homoglyph,
+    ];
+}
+
+impl FromStr for PermutationKind {
+    type Err = Error;
+
+    /// Parse a kind by name, ignoring case and separators (e.g. `tld`,
+    /// `vowel_swap` or `VowelSwap`).
+    fn from_str(name: &str) -> Result<PermutationKind, Error> {
+        let normalized: String = name
+            .trim()
+            .chars()
+            .filter(|c| *c != '_' && *c != '-')
+            .collect();
+
+        PermutationKind::ALL
+            .iter()
+            .copied()
+            .find(|kind| format!("{kind:?}").eq_ignore_ascii_case(&normalized))
+            .ok_or_else(|| {
+                PermutationError::UnknownKind {
+                    name: name.to_string(),
+                }
+                .into()
+            })
+    }
+}
+
+/// Keyword dictionary used by the keyword permutation, allowing scans to
+/// target the vocabulary of a given industry.
+#[derive(
+    Clone, Copy, Default, Serialize, Deserialize, Hash, Debug, Eq, PartialEq, Ord, PartialOrd,
+)]
+pub enum KeywordSet {
+    /// General-purpose keywords (e.g. `login`, `secure`).
+    #[default]
+    Generic,
+    Finance,
+    Crypto,
+    Logistics,
+    Healthcare,
+}
+
+impl KeywordSet {
+    /// Every keyword set.
+    pub const ALL: [KeywordSet; 5] = [
+        KeywordSet::Generic,
+        KeywordSet::Finance,
+        KeywordSet::Crypto,
+        KeywordSet::Logistics,
+        KeywordSet::Healthcare,
+    ];
+
+    /// The keywords in the set.
+    pub fn keywords(self) -> &'static [&'static str] {
+        match self {
+            KeywordSet::Generic => &KEYWORDS,
+            KeywordSet::Finance => &KEYWORDS_FINANCE,
+            KeywordSet::Crypto => &KEYWORDS_CRYPTO,
+            KeywordSet::Logistics => &KEYWORDS_LOGISTICS,
+            KeywordSet::Healthcare => &KEYWORDS_HEALTHCARE,
+        }
+    }
+}
+
+/// Language of the generic keywords used by the keyword permutation, as
+/// lookalikes targeting non-English speakers use their own vocabulary
+/// (e.g. `cuenta` rather than `account`).
+#[derive(
+    Clone, Copy, Default, Serialize, Deserialize, Hash, Debug, Eq, PartialEq, Ord, PartialOrd,
+)]
+pub enum Locale {
+    /// English (`en`).
+    #[default]
+    En,
+
+    /// Spanish (`es`).
+    Es,
+
+    /// Portuguese (`pt`).
+    Pt,
+
+    /// German (`de`).
+    De,
+
+    /// French (`fr`).
+    Fr,
+}
+
+impl Locale {
+    /// Every locale.
+    pub const ALL: [Locale; 5] = [Locale::En, Locale::Es, Locale::Pt, Locale::De, Locale::Fr];
+
+    /// The generic keywords of the locale, spelled without diacritics
+    /// (e.g. `seguranca` rather than `segurança`).
+    pub fn keywords(self) -> &'static [&'static str] {
+        match self {
+            Locale::En => &KEYWORDS,
+            Locale::Es => &KEYWORDS_ES,
+            Locale::Pt => &KEYWORDS_PT,
+            Locale::De => &KEYWORDS_DE,
+            Locale::Fr => &KEYWORDS_FR,
+        }
+    }
+}
+
+/// Forms generated by the keyword and dictionary permutations. A form is
+/// generated when both its position and its joiner are enabled, e.g.
+/// `prefix` and `hyphenated` for `word-foo.com`.
+#[derive(Clone, Copy, Serialize, Deserialize, Hash, Debug, Eq, PartialEq)]
+#[serde(default)]
+pub struct KeywordForms {
+    /// Keyword before the domain (e.g. `wordfoo.com`).
+    pub prefix: bool,
+
+    /// Keyword after the domain (e.g. `fooword.com`).
+    pub suffix: bool,
+
+    /// Keyword between two tokens of the domain, see
+    /// [`Domain::tokenize`] (e.g. `foo-word-bar.com` for `foobar.com`).
+    /// Off by default, since compound domains have several such forms.
+    pub infix: bool,
+
+    /// Keyword joined with a hyphen (e.g. `foo-word.com`).
+    pub hyphenated: bool,
+
+    /// Keyword joined without a separator (e.g. `fooword.com`).
+    pub concatenated: bool,
+}
+
+impl Default for KeywordForms {
+    fn default() -> KeywordForms {
+        KeywordForms {
+            prefix: true,
+            suffix: true,
+            infix: false,
+            hyphenated: true,
+            concatenated: true,
+        }
+    }
+}
+
+impl KeywordForms {
+    /// Number of forms generated per keyword before and after the
+    /// domain, i.e. leaving infixes out.
+    pub fn count(self) -> usize {
+        let positions = usize::from(self.prefix) + usize::from(self.suffix);
+
+        positions * self.joiners()
+    }
+
+    /// Number of forms generated per keyword for `domain`, including
+    /// infixes between each of its tokens.
+    pub fn count_in(self, domain: &Domain) -> usize {
+        let infixes = if self.infix {
+            domain.tokenize().len().saturating_sub(1)
+        } else {
+            0
+        };
+
+        self.count() + infixes * self.joiners()
+    }
+
+    fn joiners(self) -> usize {
+        usize::from(self.hyphenated) + usize::from(self.concatenated)
+    }
+}
+
+/// Characters used by the addition and repetition permutations.
+#[derive(Clone, Copy, Serialize, Deserialize, Hash, Debug, Eq, PartialEq)]
+#[serde(default)]
+pub struct Charset {
+    /// Letters, i.e. `a` to `z` for the addition permutation.
+    pub letters: bool,
+
+    /// Digits (e.g. `google1.com`, `no44.com`).
+    pub digits: bool,
+
+    /// Hyphens, only repetitions of which are ever valid (e.g.
+    /// `my--domain.com`).
+    pub hyphen: bool,
+}
+
+impl Default for Charset {
+    fn default() -> Charset {
+        Charset {
+            letters: true,
+            digits: false,
+            hyphen: false,
+        }
+    }
+}
+
+impl Charset {
+    /// Whether `c` belongs to the charset.
+    pub fn contains(self, c: char) -> bool {
+        (self.letters && c.is_alphabetic())
+            || (self.digits && c.is_ascii_digit())
+            || (self.hyphen && c == '-')
+    }
+
+    /// The ASCII characters appended by the addition permutation.
+    pub fn chars(self) -> impl Iterator<Item = char> {
+        let letters = ASCII_LOWER.iter().copied().filter(move |_| self.letters);
+        let digits = ('0'..='9').filter(move |_| self.digits);
+        let hyphen = std::iter::once('-').filter(move |_| self.hyphen);
+
+        letters.chain(digits).chain(hyphen)
+    }
+}
+
+/// Candidates skipped by the TLD permutation, since they are guaranteed
+/// to be noise.
+#[derive(Clone, Default, Serialize, Deserialize, Hash, Debug, Eq, PartialEq)]
+#[serde(default)]
+pub struct TldExclusions {
+    /// Skip the base domain's own TLD, i.e. regenerating the input.
+    pub original: bool,
+
+    /// Registrable domains owned by the caller (e.g. `example.de`), which
+    /// are skipped when generated.
+    pub owned: BTreeSet<String>,
+}
+
+impl TldExclusions {
+    /// Whether `fqdn`, generated from `base` under `tld`, is skipped.
+    fn excludes(&self, base: &Domain, tld: &str, fqdn: &str) -> bool {
+        (self.original && tld.eq_ignore_ascii_case(&base.tld))
+            || self
+                .owned
+                .iter()
+                .any(|owned| owned.trim_end_matches('.').eq_ignore_ascii_case(fqdn))
+    }
+}
+
+/// TLD permutations of a label grouped into a single record, e.g.
+/// `example` under `net`, `org` and `de`. Far smaller than the
+/// permutations it stands for, for consumers presenting a label along with
+/// the TLDs it exists under. See [`Domain::tld_grouped`].
+#[derive(Clone, Default, Serialize, Deserialize, Hash, Debug, Eq, PartialEq)]
+pub struct TldGroup {
+    /// Label the TLDs are appended to (e.g. `example`).
+    pub label: String,
+
+    /// TLD variants of the label, in the order they were generated.
+    pub tlds: Vec<String>,
+}
+
+impl TldGroup {
+    /// Group the TLD `permutations` by label, in the order each label is
+    /// first seen. Permutations of other kinds are skipped, and TLDs
+    /// repeated under a label are kept once.
+    pub fn group(permutations: impl IntoIterator<Item = Permutation>) -> Vec<TldGroup> {
+        let mut groups: Vec<TldGroup> = vec![];
+        let mut indices: HashMap<String, usize> = HashMap::new();
+        let mut seen = HashSet::new();
+
+        for permutation in permutations {
+            if permutation.kind != PermutationKind::Tld {
+                continue;
+            }
+
+            let Domain { domain, tld, .. } = permutation.domain;
+
+            let index = *indices.entry(domain.to_lowercase()).or_insert_with(|| {
+                groups.push(TldGroup {
+                    label: domain,
+                    tlds: vec![],
+                });
+                groups.len() - 1
+            });
+
+            let group = &mut groups[index];
+            if seen.insert(group.key(&tld)) {
+                group.tlds.push(tld);
+            }
+        }
+
+        groups
+    }
+
+    /// Dedup key of the variant of the label under `tld`, i.e. its
+    /// lowercase FQDN, so that grouped and ungrouped records of the same
+    /// variant deduplicate against each other.
+    pub fn key(&self, tld: &str) -> String {
+        format!("{}.{}", self.label, tld).to_lowercase()
+    }
+
+    /// Dedup keys of every variant, see [`TldGroup::key`].
+    pub fn keys(&self) -> impl Iterator<Item = String> + '_ {
+        self.tlds.iter().map(|tld| self.key(tld))
+    }
+
+    /// Expand the group back into the permutations it stands for.
+    pub fn permutations(&self) -> impl Iterator<Item = Permutation> + '_ {
+        self.tlds.iter().filter_map(|tld| {
+            Domain::new(&format!("{}.{}", self.label, tld))
+                .ok()
+                .map(|domain| Permutation {
+                    domain,
+                    kind: PermutationKind::Tld,
+                    substitutions: vec![],
+                })
+        })
+    }
+}
+
+/// Characters mapped to the glyphs they can be confused with, used by the
+/// homoglyph permutation. Defaults to the built-in table.
+#[derive(Clone, Debug, Serialize, Deserialize, Hash, Eq, PartialEq)]
+#[serde(transparent)]
+pub struct HomoglyphTable {
+    pub glyphs: BTreeMap<char, String>,
+}
+
+impl Default for HomoglyphTable {
+    fn default() -> HomoglyphTable {
+        HomoglyphTable::builtin()
+    }
+}
+
+impl HomoglyphTable {
+    /// The table shipped with the library.
+    pub fn builtin() -> HomoglyphTable {
+        HomoglyphTable {
+            glyphs: HOMOGLYPHS
+                .entries()
+                .map(|(c, glyphs)| (*c, (*glyphs).to_string()))
+                .collect(),
+        }
+    }
+
+    /// Cyrillic and Greek lookalikes of Latin letters, which the built-in
+    /// table leaves out since most registries refuse labels mixing them
+    /// with Latin ones. Meant to be merged into a table restricted to a
+    /// single script, see [`HomoglyphOptions`].
+    pub fn cross_script() -> HomoglyphTable {
+        HomoglyphTable {
+            glyphs: CROSS_SCRIPT_HOMOGLYPHS
+                .entries()
+                .map(|(c, glyphs)| (*c, (*glyphs).to_string()))
+                .collect(),
+        }
+    }
+
+    /// A table without any entries, e.g. to replace the built-in table.
+    pub fn empty() -> HomoglyphTable {
+        HomoglyphTable {
+            glyphs: BTreeMap::new(),
+        }
+    }
+
+    /// Add `glyphs` as confusions of `c`, keeping the existing ones.
+    pub fn insert(&mut self, c: char, glyphs: &str) {
+        let entry = self.glyphs.entry(c).or_default();
+
+        for glyph in glyphs.chars() {
+            if glyph != c && !entry.contains(glyph) {
+                entry.push(glyph);
+            }
+        }
+    }
+
+    /// Add every entry of `other` to the table, keeping the existing ones.
+    pub fn merge(&mut self, other: &HomoglyphTable) {
+        for (c, glyphs) in &other.glyphs {
+            self.insert(*c, glyphs);
+        }
+    }
+
+    /// The glyphs `c` can be confused with, if any.
+    pub fn get(&self, c: char) -> Option<&str> {
+        self.glyphs.get(&c).map(String::as_str)
+    }
+
+    /// Characters of `candidate` confused with those of `base` according
+    /// to the table, see [`confusable_chars`].
+    pub fn confusable_chars(&self, base: &Domain, candidate: &Domain) -> Vec<Confusable> {
+        confusables(base, candidate, |c| self.get(c))
+    }
+
+    /// The table without the glyphs `options` do not allow, see
+    /// [`HomoglyphOptions`].
+    pub fn restrict(&self, options: &HomoglyphOptions) -> HomoglyphTable {
+        HomoglyphTable {
+            glyphs: self
+                .glyphs
+                .iter()
+                .map(|(c, glyphs)| (*c, glyphs.chars().filter(|g| options.allows(*g)).collect()))
+                .filter(|(_, glyphs): &(char, String)| !glyphs.is_empty())
+                .collect(),
+        }
+    }
+}
+
+/// Restrictions of the homoglyph permutation, e.g. to match the IDN
+/// policy of a registry. Nothing is restricted by default.
+///
+/// Options only ever remove glyphs from the table in use, and the
+/// built-in table only holds Latin and common lookalikes. Merge
+/// [`HomoglyphTable::cross_script`] into it to restrict permutations to
+/// Cyrillic or Greek ones.
+#[derive(Clone, Default, Serialize, Deserialize, Hash, Debug, Eq, PartialEq)]
+#[serde(default)]
+pub struct HomoglyphOptions {
+    /// Scripts of the glyphs substituted in, any script if empty.
+    pub scripts: BTreeSet<Script>,
+
+    /// Only substitute in non-ASCII glyphs, e.g. `à` but not `1` for `a`.
+    pub non_ascii: bool,
+
+    /// Skip permutations with a label mixing scripts (e.g. Cyrillic and
+    /// Latin characters), which most registries refuse to register.
+    /// Digits and hyphens mix with any script.
+    ///
+    /// Since substituting a few characters of a label always mixes
+    /// scripts, the root label is also substituted as a whole in each
+    /// script it can be entirely spelled in (e.g. `аррӏе.com`).
+    pub single_script: bool,
+}
+
+impl HomoglyphOptions {
+    /// Only substitute in glyphs of `script`, without mixing scripts.
+    pub fn script(script: Script) -> HomoglyphOptions {
+        HomoglyphOptions {
+            scripts: BTreeSet::from([script]),
+            non_ascii: false,
+            single_script: true,
+        }
+    }
+
+    /// Only substitute in Latin letters with diacritics, e.g. `à` for `a`.
+    pub fn latin_diacritics() -> HomoglyphOptions {
+        HomoglyphOptions {
+            non_ascii: true,
+            ..HomoglyphOptions::script(Script::Latin)
+        }
+    }
+
+    /// Whether `glyph` may be substituted in.
+    fn allows(&self, glyph: char) -> bool {
+        (!self.non_ascii || !glyph.is_ascii())
+            && (self.scripts.is_empty() || self.scripts.contains(&Script::of(glyph)))
+    }
+}
+
+/// Whether none of the labels of `fqdn` mixes scripts, see
+/// [`HomoglyphOptions::single_script`].
+fn is_single_script(fqdn: &str) -> bool {
+    fqdn.split('.').all(|label| {
+        label
+            .chars()
+            .map(Script::of)
+            .filter(|script| *script != Script::Common)
+            .all_equal()
+    })
+}
+
+/// Character of a candidate domain confused with the character of the
+/// base domain at the same position, see [`confusable_chars`].
+#[derive(Clone, Copy, Debug, Serialize, Deserialize, Hash, Eq, PartialEq)]
+pub struct Confusable {
+    /// Position of the character in the Unicode form of the FQDN.
+    pub position: usize,
+    pub base: char,
+    pub glyph: char,
+}
+
+/// Substrings mapped to the alternatives they can be confused with, used by
+/// the mapped permutation. Defaults to the built-in table.
+#[derive(Clone, Debug, Serialize, Deserialize, Hash, Eq, PartialEq)]
+#[serde(transparent)]
+pub struct MappedTable {
+    pub values: BTreeMap<String, Vec<String>>,
+}
+
+impl Default for MappedTable {
+    fn default() -> MappedTable {
+        MappedTable::builtin()
+    }
+}
+
+impl MappedTable {
+    /// The table shipped with the library.
+    pub fn builtin() -> MappedTable {
+        MappedTable {
+            values: MAPPED_VALUES
+                .entries()
+                .map(|(key, values)| {
+                    (
+                        (*key).to_string(),
+                        values.iter().map(|value| (*value).to_string()).collect(),
+                    )
+                })
+                .collect(),
+        }
+    }
+
+    /// A table without any entries, e.g. to replace the built-in table.
+    pub fn empty() -> MappedTable {
+        MappedTable {
+            values: BTreeMap::new(),
+        }
+    }
+
+    /// Add `values` as alternatives of `key`, keeping the existing ones.
+    pub fn insert<S: AsRef<str>>(&mut self, key: &str, values: &[S]) {
+        let entry = self.values.entry(key.to_string()).or_default();
+
+        for value in values.iter().map(AsRef::as_ref) {
+            if value != key && !entry.iter().any(|existing| existing == value) {
+                entry.push(value.to_string());
+            }
+        }
+    }
+
+    /// Add every entry of `other` to the table, keeping the existing ones.
+    pub fn merge(&mut self, other: &MappedTable) {
+        for (key, values) in &other.values {
+            self.insert(key, values);
+        }
+    }
+
+    /// The alternatives of `key`, if any.
+    pub fn get(&self, key: &str) -> Option<&[String]> {
+        self.values.get(key).map(Vec::as_slice)
+    }
+}
+
+/// Which occurrences of a substring the mapped permutation substitutes.
+#[derive(
+    Clone, Copy, Default, Serialize, Deserialize, Hash, Debug, Eq, PartialEq, Ord, PartialOrd,
+)]
+pub enum MappedMode {
+    /// Substitute every occurrence at once (e.g. `wvvwvv` for `wwww` and
+    /// `w` -> `vv`).
+    #[default]
+    Every,
+
+    /// Substitute one occurrence at a time (e.g. `vvwww`, `wvvww`, ...).
+    Single,
+
+    /// Both of the above.
+    Both,
+}
+
+impl MappedMode {
+    /// Number of candidates yielded per alternative for a substring
+    /// occurring `occurrences` times.
+    fn candidates(self, occurrences: usize) -> usize {
+        match (self, occurrences) {
+            (_, 0) => 0,
+            (MappedMode::Every, _) | (MappedMode::Both, 1) => 1,
+            (MappedMode::Single, _) => occurrences,
+            (MappedMode::Both, _) => occurrences + 1,
+        }
+    }
+}
+
+/// How generated permutations are checked against IDNA (UTS #46) before
+/// being yielded by the [`PermutationEngine`].
+#[derive(
+    Clone, Copy, Default, Serialize, Deserialize, Hash, Debug, Eq, PartialEq, Ord, PartialOrd,
+)]
+pub enum IdnaMode {
+    /// Yield permutations as generated.
+    #[default]
+    Off,
+
+    /// Drop permutations that are not valid IDNA, i.e. that no registry
+    /// would accept.
+    Validate,
+
+    /// Drop invalid permutations and convert the remaining ones to their
+    /// ASCII-compatible (punycode) form.
+    Ascii,
+}
+
+impl IdnaMode {
+    /// Apply the mode to `permutation`, returning `None` if it is dropped.
+    pub fn apply(self, permutation: Permutation) -> Option<Permutation> {
+        if self == IdnaMode::Off {
+            return Some(permutation);
+        }
+
+        let fqdn = idna::domain_to_ascii_strict(&permutation.domain.fqdn).ok()?;
+
+        // Characters that UTS #46 maps onto others (e.g. full-width
+        // letters) are not registrable as-is, so the round trip has to be
+        // lossless, up to case and to labels already in punycode.
+        let (unicode, result) = idna::domain_to_unicode(&fqdn);
+        if result.is_err() || Some(unicode) != unicode_form(&permutation.domain.fqdn) {
+            return None;
+        }
+
+        if self == IdnaMode::Validate {
+            return Some(permutation);
+        }
+
+        let domain = Domain::from_parts(
+            fqdn,
+            idna::domain_to_ascii_strict(&permutation.domain.tld).ok()?,
+            idna::domain_to_ascii_strict(&permutation.domain.domain).ok()?,
+        );
+
+        Some(Permutation {
+            domain,
+            ..permutation
+        })
+    }
+}
+
+/// `fqdn` lowercased and without a trailing dot, with its punycode labels
+/// decoded, or `None` if one of them is not valid punycode.
+fn unicode_form(fqdn: &str) -> Option<String> {
+    fqdn.trim_end_matches('.')
+        .split('.')
+        .map(|label| {
+            let lowercase = label.to_lowercase();
+
+            match lowercase.strip_prefix("xn--") {
+                Some(encoded) => idna::punycode::decode_to_string(encoded),
+                None => Some(lowercase),
+            }
+        })
+        .collect::<Option<Vec<String>>>()
+        .map(|labels| labels.join("."))
+}
+
+/// Validation state shared by the generators of a scan: the suffix list
+/// candidates are parsed against, along with the IDNA mode and filter
+/// applied to the permutations generated.
+///
+/// A context is created once per [`Domain::all`] call or engine run, so
+/// that the installed suffix list (see [`crate::psl`]) is looked up once
+/// rather than for every candidate. Contexts can also be built upfront
+/// and reused, e.g. for repeated scans of the same domain:
+///
+/// ```
+/// use twistrs::permutate::{Domain, IdnaMode, ValidationCtx};
+///
+/// let mut ctx = ValidationCtx::new();
+/// ctx.idna = IdnaMode::Validate;
+///
+/// let domain = Domain::new("example.com").unwrap();
+/// let first = domain.all_in(&ctx).unwrap().count();
+/// let second = domain.all_in(&ctx).unwrap().count();
+///
+/// assert_eq!(first, second);
+/// ```
+///
+/// The suffix list is captured when the context is created, so lists
+/// installed afterwards are only used by contexts created afterwards.
+#[derive(Clone)]
+pub struct ValidationCtx {
+    suffixes: Option<Arc<SuffixList>>,
+
+    /// How permutations are checked against IDNA, before being filtered.
+    pub idna: IdnaMode,
+
+    /// Whether permutations are parsed with private suffixes as TLDs, see
+    /// [`ParseOptions::private_suffixes`]. The TLD permutation then also
+    /// swaps in the private suffixes of the installed list, if any.
+    pub private_suffixes: bool,
+
+    filter: Option<Arc<dyn Filter>>,
+}
+
+impl Default for ValidationCtx {
+    fn default() -> ValidationCtx {
+        ValidationCtx::new()
+    }
+}
+
+impl fmt::Debug for ValidationCtx {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("ValidationCtx")
+            .field("suffixes", &self.suffixes.is_some())
+            .field("idna", &self.idna)
+            .field("private_suffixes", &self.private_suffixes)
+            .field("filter", &self.filter.is_some())
+            .finish()
+    }
+}
+
+impl ValidationCtx {
+    /// Create a context using the installed suffix list, if any, without
+    /// IDNA checks nor filter.
+    pub fn new() -> ValidationCtx {
+        ValidationCtx {
+            suffixes: psl::active(),
+            idna: IdnaMode::Off,
+            private_suffixes: false,
+            filter: None,
+        }
+    }
+
+    /// Create a context for the permutations of `domain`, parsing them
+    /// with private suffixes as TLDs if its own TLD is one.
+    pub fn of(domain: &Domain) -> ValidationCtx {
+        ValidationCtx {
+            private_suffixes: domain.section() == SuffixSection::Private,
+            ..ValidationCtx::new()
+        }
+    }
+
+    /// Parse candidates against `list` rather than the installed one.
+    pub fn set_suffix_list(&mut self, list: SuffixList) {
+        self.suffixes = Some(Arc::new(list));
+    }
+
+    /// Only keep the permutations matching `filter` (see [`crate::filter`]).
+    pub fn set_filter<F>(&mut self, filter: F)
+    where
+        F: Filter + 'static,
+    {
+        self.filter = Some(Arc::new(filter));
+    }
+
+    /// Parse `fqdn` as [`Domain::new`] does, against the suffix list of
+    /// the context.
+    pub fn parse(&self, fqdn: &str) -> Result<Domain, Error> {
+        self.parse_ref(fqdn).map(Domain::from)
+    }
+
+    /// Parse `fqdn` as [`ValidationCtx::parse`] does, borrowing its parts
+    /// rather than copying them.
+    pub fn parse_ref<'a>(&self, fqdn: &'a str) -> Result<DomainRef<'a>, Error> {
+        #[cfg(feature = "perf")]
+        perf::count_candidate();
+
+        let options = ParseOptions {
+            private_suffixes: self.private_suffixes,
+        };
+        let parsed = DomainRef::parse(fqdn, self.suffixes.as_deref(), &options);
+
+        #[cfg(feature = "perf")]
+        if parsed.is_err() {
+            perf::count_rejected();
+        }
+
+        parsed
+    }
+
+    /// Apply the IDNA mode and the filter of the context to
+    /// `permutation`, returning `None` if it is dropped.
+    pub fn validate(&self, permutation: Permutation) -> Option<Permutation> {
+        let validated = self
+            .idna
+            .apply(permutation)
+            .filter(|applied| match &self.filter {
+                Some(filter) => filter.matches(applied),
+                None => true,
+            });
+
+        #[cfg(feature = "perf")]
+        if validated.is_none() {
+            perf::count_rejected();
+        }
+
+        validated
+    }
+
+    /// Parse each of `fqdns`, yielding the valid ones as permutations of
+    /// the given `kind`. Candidates are parsed borrowed, so that rejected
+    /// ones are not copied and accepted ones keep their FQDN allocation.
+    fn parse_each<'a, I>(
+        self,
+        kind: PermutationKind,
+        fqdns: I,
+    ) -> impl Iterator<Item = Permutation> + 'a
+    where
+        I: Iterator<Item = String> + 'a,
+    {
+        fqdns.filter_map(move |fqdn| {
+            let parsed = self.parse_ref(&fqdn).ok()?;
+            let (tld, domain) = (parsed.tld.to_string(), parsed.domain.to_string());
+
+            Some(Permutation {
+                domain: Domain::from_parts(fqdn, tld, domain),
+                kind,
+                substitutions: vec![],
+            })
+        })
+    }
+
+    /// Number of suffixes swapped in by the TLD permutation, i.e. those
+    /// of the suffix list of the context along with its private ones if
+    /// enabled, or the embedded TLDs.
+    fn tld_count(&self) -> usize {
+        self.suffixes.as_ref().map_or(TLDS.len(), |list| {
+            list.suffixes().len() + self.private_tlds(list).len()
+        })
+    }
+
+    /// Suffix swapped in by the TLD permutation at `index`.
+    fn tld_at(&self, index: usize) -> Option<&str> {
+        match &self.suffixes {
+            Some(list) => {
+                let icann = list.suffixes();
+
+                icann
+                    .get(index)
+                    .or_else(|| self.private_tlds(list).get(index - icann.len()))
+                    .map(String::as_str)
+            }
+            None => TLDS.get(index).copied(),
+        }
+    }
+
+    /// Private suffixes of `list` swapped in by the TLD permutation.
+    fn private_tlds<'a>(&self, list: &'a SuffixList) -> &'a [String] {
+        if self.private_suffixes {
+            list.private_suffixes()
+        } else {
+            &[]
+        }
+    }
+}
+
+/// Serializable configuration of a [`PermutationEngine`], see
+/// [`PermutationEngine::config`].
+///
+/// Progress hooks are not part of the configuration, as they do not
+/// affect the permutations generated.
+#[derive(Clone, Debug, Serialize, Deserialize, Hash, Eq, PartialEq)]
+#[serde(default)]
+pub struct EngineConfig {
+    pub kinds: Vec<PermutationKind>,
+    pub tlds: TldSet,
+    pub tld_exclusions: TldExclusions,
+    pub charset: Charset,
+    pub keywords: Vec<KeywordSet>,
+    pub locales: Vec<Locale>,
+    pub keyword_forms: KeywordForms,
+    pub dictionary: Vec<String>,
+    pub idna: IdnaMode,
+    pub homoglyphs: HomoglyphTable,
+    pub homoglyph_options: HomoglyphOptions,
+    pub mapped: MappedTable,
+    pub mapped_mode: MappedMode,
+    pub filter: Option<FilterDescriptor>,
+}
+
+impl Default for EngineConfig {
+    fn default() -> EngineConfig {
+        PermutationEngine::default().config()
+    }
 }
 
-#[derive(Clone, Debug, Serialize, Deserialize, Hash, Eq, PartialEq, Ord, PartialOrd)]
-pub struct Permutation {
-    pub domain: Domain,
-    pub kind: PermutationKind,
+impl EngineConfig {
+    /// Stable fingerprint of the configuration, as a hex string.
+    ///
+    /// The fingerprint covers the crate version as well, since the
+    /// builtin dictionaries referenced by the configuration (e.g.
+    /// [`KeywordSet`]) ship with the crate.
+    pub fn fingerprint(&self) -> String {
+        let mut hasher = FingerprintHasher(Sha256::new());
+
+        env!("CARGO_PKG_VERSION").hash(&mut hasher);
+        self.hash(&mut hasher);
+
+        hasher
+            .0
+            .finalize()
+            .iter()
+            .take(16)
+            .map(|byte| format!("{byte:02x}"))
+            .collect()
+    }
 }
 
-#[derive(Clone, Copy, Serialize, Deserialize, Hash, Debug, Eq, PartialEq, Ord, PartialOrd)]
-pub enum PermutationKind {
-    Addition,
-    Bitsquatting,
-    Hyphenation,
-    Insertion,
-    Omission,
-    Repetition,
-    Replacement,
-    Subdomain,
-    Transposition,
-    VowelSwap,
-    DoubleVowelInsertion,
-    Keyword,
-    Tld,
-    Homoglyph,
-    Mapped,
+// Feeds `Hash` implementations into SHA-256. Integers are hashed in a
+// fixed width and byte order, so that the fingerprint does not depend
+// on the platform.
+struct FingerprintHasher(Sha256);
+
+impl Hasher for FingerprintHasher {
+    fn finish(&self) -> u64 {
+        // Only the digest is used, see `EngineConfig::fingerprint`.
+        0
+    }
+
+    fn write(&mut self, bytes: &[u8]) {
+        self.0.update(bytes);
+    }
+
+    fn write_u16(&mut self, i: u16) {
+        self.write(&i.to_le_bytes());
+    }
+
+    fn write_u32(&mut self, i: u32) {
+        self.write(&i.to_le_bytes());
+    }
+
+    fn write_u64(&mut self, i: u64) {
+        self.write(&i.to_le_bytes());
+    }
+
+    fn write_usize(&mut self, i: usize) {
+        self.write_u64(i as u64);
+    }
+
+    fn write_i64(&mut self, i: i64) {
+        self.write(&i.to_le_bytes());
+    }
+
+    fn write_isize(&mut self, i: isize) {
+        self.write_i64(i as i64);
+    }
+}
+
+/// Configurable permutation generator.
+///
+/// Unlike [`Domain::all`], the engine allows clients to select which
+/// permutation kinds are generated, to filter the permutations and to
+/// hook into the generation progress.
+///
+/// The engine (de)serializes as its [`EngineConfig`], so that scans can
+/// be reproduced from a stored configuration.
+#[derive(Clone, Serialize, Deserialize)]
+#[serde(into = "EngineConfig", try_from = "EngineConfig")]
+pub struct PermutationEngine {
+    /// Permutation kinds to generate, in order.
+    pub kinds: Vec<PermutationKind>,
+
+    /// Suffixes swapped in by the TLD permutation.
+    pub tlds: TldSet,
+
+    /// Candidates skipped by the TLD permutation.
+    pub tld_exclusions: TldExclusions,
+
+    /// Characters used by the addition and repetition permutations.
+    pub charset: Charset,
+
+    /// Keyword sets used by the keyword permutation.
+    pub keywords: Vec<KeywordSet>,
+
+    /// Languages of the generic keyword set, see [`Locale`].
+    pub locales: Vec<Locale>,
+
+    /// Forms generated by the keyword and dictionary permutations.
+    pub keyword_forms: KeywordForms,
+
+    /// Words used by the dictionary permutation, e.g. an industry
+    /// wordlist loaded at runtime. See [`Domain::dictionary`].
+    pub dictionary: Vec<String>,
+
+    /// How permutations are checked against IDNA, before being filtered.
+    pub idna: IdnaMode,
+
+    /// Confusions used by the homoglyph permutation.
+    pub homoglyphs: HomoglyphTable,
+
+    /// Restrictions of the homoglyph permutation, see [`HomoglyphOptions`].
+    pub homoglyph_options: HomoglyphOptions,
+
+    /// Substitutions used by the mapped permutation.
+    pub mapped: MappedTable,
+
+    /// Which occurrences the mapped permutation substitutes.
+    pub mapped_mode: MappedMode,
+
+    filter: Option<Arc<dyn Filter>>,
+    progress: Option<Callback>,
+}
+
+impl Default for PermutationEngine {
+    fn default() -> PermutationEngine {
+        PermutationEngine {
+            kinds: PermutationKind::ALL.to_vec(),
+            tlds: TldSet::All,
+            tld_exclusions: TldExclusions::default(),
+            charset: Charset::default(),
+            keywords: vec![KeywordSet::Generic],
+            locales: vec![Locale::En],
+            keyword_forms: KeywordForms::default(),
+            dictionary: vec![],
+            idna: IdnaMode::Off,
+            homoglyphs: HomoglyphTable::builtin(),
+            homoglyph_options: HomoglyphOptions::default(),
+            mapped: MappedTable::builtin(),
+            mapped_mode: MappedMode::Every,
+            filter: None,
+            progress: None,
+        }
+    }
+}
+
+impl fmt::Debug for PermutationEngine {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("PermutationEngine")
+            .field("kinds", &self.kinds)
+            .field("tlds", &self.tlds)
+            .field("tld_exclusions", &self.tld_exclusions)
+            .field("charset", &self.charset)
+            .field("keywords", &self.keywords)
+            .field("locales", &self.locales)
+            .field("keyword_forms", &self.keyword_forms)
+            .field("dictionary", &self.dictionary.len())
+            .field("idna", &self.idna)
+            .field("homoglyphs", &self.homoglyphs)
+            .field("homoglyph_options", &self.homoglyph_options)
+            .field("mapped", &self.mapped)
+            .field("mapped_mode", &self.mapped_mode)
+            .field("filter", &self.filter.is_some())
+            .field("progress", &self.progress)
+            .finish()
+    }
+}
+
+impl Hash for PermutationEngine {
+    fn hash<H: Hasher>(&self, state: &mut H) {
+        self.config().hash(state);
+    }
+}
+
+impl From<PermutationEngine> for EngineConfig {
+    fn from(engine: PermutationEngine) -> EngineConfig {
+        engine.config()
+    }
+}
+
+impl TryFrom<EngineConfig> for PermutationEngine {
+    type Error = Error;
+
+    fn try_from(config: EngineConfig) -> Result<PermutationEngine, Error> {
+        PermutationEngine::from_config(config)
+    }
+}
+
+impl PermutationEngine {
+    /// Create a new engine generating every permutation kind.
+    pub fn new() -> PermutationEngine {
+        PermutationEngine::default()
+    }
+
+    /// Create an engine from `config`. Custom filters cannot be rebuilt
+    /// from their description, and have to be set again instead.
+    pub fn from_config(config: EngineConfig) -> Result<PermutationEngine, Error> {
+        let filter = match config.filter {
+            Some(descriptor) => {
+                Some(
+                    descriptor
+                        .build()
+                        .ok_or_else(|| PermutationError::CustomFilter {
+                            name: match descriptor {
+                                FilterDescriptor::Custom(name) => name,
+                                _ => String::new(),
+                            },
+                        })?,
+                )
+            }
+            None => None,
+        };
+
+        Ok(PermutationEngine {
+            kinds: config.kinds,
+            tlds: config.tlds,
+            tld_exclusions: config.tld_exclusions,
+            charset: config.charset,
+            keywords: config.keywords,
+            locales: config.locales,
+            keyword_forms: config.keyword_forms,
+            dictionary: config.dictionary,
+            idna: config.idna,
+            homoglyphs: config.homoglyphs,
+            homoglyph_options: config.homoglyph_options,
+            mapped: config.mapped,
+            mapped_mode: config.mapped_mode,
+            filter,
+            progress: None,
+        })
+    }
+
+    /// Snapshot of the engine configuration.
+    pub fn config(&self) -> EngineConfig {
+        EngineConfig {
+            kinds: self.kinds.clone(),
+            tlds: self.tlds.clone(),
+            tld_exclusions: self.tld_exclusions.clone(),
+            charset: self.charset,
+            keywords: self.keywords.clone(),
+            locales: self.locales.clone(),
+            keyword_forms: self.keyword_forms,
+            dictionary: self.dictionary.clone(),
+            idna: self.idna,
+            homoglyphs: self.homoglyphs.clone(),
+            homoglyph_options: self.homoglyph_options.clone(),
+            mapped: self.mapped.clone(),
+            mapped_mode: self.mapped_mode,
+            filter: self.filter.as_ref().map(|filter| filter.descriptor()),
+        }
+    }
+
+    /// Fingerprint of the engine configuration, see
+    /// [`EngineConfig::fingerprint`].
+    pub fn fingerprint(&self) -> String {
+        self.config().fingerprint()
+    }
+
+    /// Only keep the permutations matching `filter` (see [`crate::filter`]).
+    pub fn set_filter<F>(&mut self, filter: F)
+    where
+        F: Filter + 'static,
+    {
+        self.filter = Some(Arc::new(filter));
+    }
+
+    /// Validation context applying the IDNA mode and filter of the engine,
+    /// e.g. to be reused across runs with [`PermutationEngine::generate_in`].
+    pub fn validation_ctx(&self) -> ValidationCtx {
+        ValidationCtx {
+            suffixes: psl::active(),
+            idna: self.idna,
+            private_suffixes: false,
+            filter: self.filter.clone(),
+        }
+    }
+
+    /// Register a hook that is invoked for every permutation generated,
+    /// along with the estimated total (see [`PermutationEngine::estimate`]).
+    pub fn on_progress<F>(&mut self, f: F)
+    where
+        F: Fn(usize, usize, Phase) + Send + Sync + 'static,
+    {
+        self.progress = Some(Callback::new(f));
+    }
+
+    /// The distinct keywords used by the keyword permutation, i.e. those
+    /// of each of the `keywords` sets, the generic one being taken in
+    /// every locale. Keywords shared by several lists (e.g. `bank` in
+    /// English and German) are only kept once.
+    fn merged_keywords(&self) -> Vec<&'static str> {
+        let mut seen = HashSet::new();
+
+        self.keywords
+            .iter()
+            .flat_map(|set| match set {
+                KeywordSet::Generic => self
+                    .locales
+                    .iter()
+                    .map(|locale| locale.keywords())
+                    .collect(),
+                _ => vec![set.keywords()],
+            })
+            .flatten()
+            .copied()
+            .filter(|keyword| seen.insert(*keyword))
+            .collect()
+    }
+
+    /// Estimate how many permutations the engine generates for `domain`.
+    pub fn estimate(&self, domain: &Domain) -> usize {
+        self.kinds
+            .iter()
+            .map(|kind| match kind {
+                PermutationKind::Keyword => {
+                    self.merged_keywords().len() * self.keyword_forms.count_in(domain)
+                }
+                PermutationKind::Dictionary => {
+                    self.dictionary.len() * self.keyword_forms.count_in(domain)
+                }
+                PermutationKind::Addition => self.charset.chars().count(),
+                PermutationKind::Repetition => domain
+                    .fqdn
+                    .chars()
+                    .filter(|c| self.charset.contains(*c))
+                    .count(),
+                PermutationKind::Homoglyph => {
+                    domain.homoglyph_estimate(&self.homoglyphs.restrict(&self.homoglyph_options))
+                }
+                PermutationKind::Mapped => domain.mapped_estimate(&self.mapped, self.mapped_mode),
+                _ => domain.estimate(*kind),
+            })
+            .sum()
+    }
+
+    /// Generate the permutations of `domain` for each configured kind.
+    pub fn generate<'a>(
+        &'a self,
+        domain: &'a Domain,
+    ) -> Result<impl Iterator<Item = Permutation> + 'a, Error> {
+        let mut ctx = self.validation_ctx();
+        ctx.private_suffixes = domain.section() == SuffixSection::Private;
+
+        self.generate_in(domain, &ctx)
+    }
+
+    /// Generate the permutations of `domain` for each configured kind,
+    /// validating them with `ctx` rather than with the IDNA mode and
+    /// filter of the engine.
+    pub fn generate_in<'a>(
+        &'a self,
+        domain: &'a Domain,
+        ctx: &ValidationCtx,
+    ) -> Result<impl Iterator<Item = Permutation> + 'a, Error> {
+        let mut generators: Vec<Box<dyn Iterator<Item = Permutation> + 'a>> =
+            Vec::with_capacity(self.kinds.len());
+
+        for kind in &self.kinds {
+            generators.push(match kind {
+                PermutationKind::Tld => {
+                    Box::new(domain.tld_in(&self.tlds, &self.tld_exclusions, ctx))
+                }
+                PermutationKind::Keyword => Box::new(domain.affix_in(
+                    self.merged_keywords(),
+                    self.keyword_forms,
+                    PermutationKind::Keyword,
+                    ctx,
+                )),
+                PermutationKind::Dictionary => Box::new(domain.affix_in(
+                    &self.dictionary,
+                    self.keyword_forms,
+                    PermutationKind::Dictionary,
+                    ctx,
+                )),
+                PermutationKind::Addition => Box::new(domain.addition_in(self.charset, ctx)),
+                PermutationKind::Repetition => Box::new(domain.repetition_in(self.charset, ctx)),
+                PermutationKind::Homoglyph => {
+                    Box::new(domain.homoglyph_in(&self.homoglyphs, &self.homoglyph_options, ctx)?)
+                }
+                PermutationKind::Mapped => Box::new(
+                    domain
+                        .substitute(&self.mapped, self.mapped_mode, PermutationKind::Mapped, ctx)
+                        .into_iter(),
+                ),
+                _ => domain.generate_in(*kind, ctx)?,
+            });
+        }
+
+        let total_estimate = match self.progress {
+            Some(_) => self.estimate(domain),
+            None => 0,
+        };
+        let validation = ctx.clone();
+
+        Ok(generators
+            .into_iter()
+            .flatten()
+            .enumerate()
+            .map(move |(i, permutation)| {
+                if let Some(progress) = &self.progress {
+                    progress.call(i + 1, total_estimate, Phase::Generation);
+                }
+
+                permutation
+            })
+            .filter_map(move |permutation| validation.validate(permutation)))
+    }
 }
 
 #[derive(Clone, thiserror::Error, Debug)]
@@ -78,19 +1788,109 @@ pub enum PermutationError {
 
     #[error("error generating homoglyph permutation (domain {domain:?}, homoglyph {homoglyph:?})")]
     InvalidHomoglyph { domain: String, homoglyph: String },
+
+    #[error("expected a domain name, found an ip address (found {found:?})")]
+    IpAddressInput { found: String },
+
+    #[error("domain name has no tld, expected e.g. example.com (found {found:?})")]
+    MissingTld { found: String },
+
+    #[error("domain suffix is not in the list of accepted tlds globally (fqdn {fqdn:?}, suffix {suffix:?})")]
+    UnsupportedSuffix { fqdn: String, suffix: String },
+
+    #[error("custom filter cannot be rebuilt from its description (filter {name:?})")]
+    CustomFilter { name: String },
+
+    #[error("unknown permutation kind (name {name:?})")]
+    UnknownKind { name: String },
 }
 
-impl Domain {
-    /// Wrap a desired FQDN into a `Domain` container. Internally
-    /// will perform additional operations to break the domain into
-    /// one or more chunks to be used during domain permutations.
-    pub fn new(fqdn: &str) -> Result<Domain, Error> {
+impl<'a> DomainRef<'a> {
+    /// Parse `fqdn` as [`Domain::new`] does, borrowing its parts rather
+    /// than copying them.
+    pub fn new(fqdn: &'a str) -> Result<DomainRef<'a>, Error> {
+        DomainRef::new_with(fqdn, &ParseOptions::default())
+    }
+
+    /// Parse `fqdn` as [`Domain::new_with`] does, borrowing its parts
+    /// rather than copying them.
+    pub fn new_with(fqdn: &'a str, options: &ParseOptions) -> Result<DomainRef<'a>, Error> {
+        DomainRef::parse(fqdn, psl::active().as_deref(), options)
+    }
+
+    /// Copy the parts of the domain into an owned [`Domain`].
+    pub fn to_domain(&self) -> Domain {
+        Domain::from(*self)
+    }
+
+    /// Parse `fqdn` against the runtime suffix `list` if there is one,
+    /// falling back on the lists embedded at compile time otherwise.
+    fn parse(
+        fqdn: &'a str,
+        list: Option<&SuffixList>,
+        options: &ParseOptions,
+    ) -> Result<DomainRef<'a>, Error> {
+        if fqdn
+            .trim_start_matches('[')
+            .trim_end_matches(']')
+            .parse::<IpAddr>()
+            .is_ok()
+        {
+            return Err(PermutationError::IpAddressInput {
+                found: fqdn.to_string(),
+            }
+            .into());
+        }
+
+        #[cfg(feature = "perf")]
+        perf::count_psl_parse();
+
         let parsed_domain =
             List.parse_domain_name(fqdn)
                 .map_err(|_| PermutationError::InvalidDomain {
                     expected: "valid domain name that can be parsed".to_string(),
                     found: fqdn.to_string(),
                 })?;
+
+        if !fqdn.trim_end_matches('.').contains('.') {
+            return Err(PermutationError::MissingTld {
+                found: fqdn.to_string(),
+            }
+            .into());
+        }
+
+        let unsupported = || PermutationError::UnsupportedSuffix {
+            fqdn: fqdn.to_string(),
+            suffix: parsed_domain.suffix().to_string(),
+        };
+
+        if let Some(runtime) = list {
+            let tld = options
+                .private_suffixes
+                .then(|| runtime.private_suffix_of(fqdn))
+                .flatten()
+                .or_else(|| runtime.suffix_of(fqdn))
+                .ok_or_else(unsupported)?;
+
+            return DomainRef::under(fqdn, tld);
+        }
+
+        // The embedded list matches private suffixes as well, which are
+        // either kept as the TLD or swapped for their ICANN parent.
+        if parsed_domain.is_private() {
+            let suffix = parsed_domain.suffix();
+            let tld = if options.private_suffixes {
+                Some(suffix)
+            } else {
+                suffix
+                    .match_indices('.')
+                    .filter_map(|(offset, _)| suffix.get(offset + 1..))
+                    .find(|parent| TLDS.binary_search(&parent.to_lowercase().as_str()).is_ok())
+            };
+
+            return DomainRef::under(fqdn, tld.ok_or_else(unsupported)?);
+        }
+
         let root_domain = parsed_domain
             .root()
             .ok_or(PermutationError::InvalidDomain {
@@ -98,15 +1898,15 @@ impl Domain {
                 found: fqdn.to_string(),
             })?;
 
-        let tld = parsed_domain.suffix().to_string();
+        let tld = parsed_domain.suffix();
 
         // Verify that the TLD is in the list of known TLDs, this requires that
         // the TLD data list is already ordered, otherwise the result of the
         // binary search is meaningless. We also assume that all TLDs generated
         // are lowercase already.
-        if TLDS.binary_search(&tld.as_str()).is_ok() {
-            let domain = Domain {
-                fqdn: fqdn.to_string(),
+        if TLDS.binary_search(&tld).is_ok() {
+            let domain = DomainRef {
+                fqdn,
                 tld,
                 domain: root_domain
                     .find('.')
@@ -115,21 +1915,132 @@ impl Domain {
                     .ok_or(PermutationError::InvalidDomain {
                         expected: "valid domain name with a root domain".to_string(),
                         found: fqdn.to_string(),
-                    })?
-                    .to_string(),
+                    })?,
             };
 
             Ok(domain)
         } else {
-            let err = PermutationError::InvalidDomain {
-                expected: "valid domain tld in the list of accepted tlds globally".to_string(),
-                found: tld,
+            let err = PermutationError::UnsupportedSuffix {
+                fqdn: fqdn.to_string(),
+                suffix: tld.to_string(),
             };
 
             Err(err.into())
         }
     }
 
+    /// Split `fqdn` into the root label right before `tld` and `tld`.
+    fn under(fqdn: &'a str, tld: &'a str) -> Result<DomainRef<'a>, Error> {
+        let domain = fqdn
+            .strip_suffix(tld)
+            .and_then(|remainder| remainder.strip_suffix('.'))
+            .and_then(|remainder| remainder.rsplit('.').next())
+            .filter(|label| !label.is_empty())
+            .ok_or(PermutationError::InvalidDomain {
+                expected: "valid domain name with a root domain".to_string(),
+                found: fqdn.to_string(),
+            })?;
+
+        Ok(DomainRef { fqdn, tld, domain })
+    }
+}
+
+impl From<DomainRef<'_>> for Domain {
+    fn from(domain: DomainRef<'_>) -> Domain {
+        Domain::from_parts(
+            domain.fqdn.to_string(),
+            domain.tld.to_string(),
+            domain.domain.to_string(),
+        )
+    }
+}
+
+impl Domain {
+    /// Wrap a desired FQDN into a `Domain` container. Internally
+    /// will perform additional operations to break the domain into
+    /// one or more chunks to be used during domain permutations.
+    ///
+    /// Private suffixes (e.g. `github.io`) are not TLDs, see
+    /// [`Domain::new_with`].
+    pub fn new(fqdn: &str) -> Result<Domain, Error> {
+        Domain::new_with(fqdn, &ParseOptions::default())
+    }
+
+    /// Wrap a desired FQDN into a `Domain` container as [`Domain::new`]
+    /// does, parsing it according to `options`.
+    ///
+    /// ```
+    /// use twistrs::permutate::{Domain, ParseOptions};
+    /// use twistrs::psl::SuffixSection;
+    ///
+    /// let options = ParseOptions { private_suffixes: true };
+    /// let domain = Domain::new_with("example.github.io", &options).unwrap();
+    /// assert_eq!((domain.domain.as_str(), domain.tld.as_str()), ("example", "github.io"));
+    /// assert_eq!(domain.section(), SuffixSection::Private);
+    ///
+    /// let domain = Domain::new("example.github.io").unwrap();
+    /// assert_eq!((domain.domain.as_str(), domain.tld.as_str()), ("github", "io"));
+    /// ```
+    pub fn new_with(fqdn: &str, options: &ParseOptions) -> Result<Domain, Error> {
+        Domain::parse(fqdn, psl::active().as_deref(), options)
+    }
+
+    /// Section of the public suffix list the TLD of the domain comes from.
+    /// Only domains parsed with [`ParseOptions::private_suffixes`] can
+    /// have a private TLD.
+    pub fn section(&self) -> SuffixSection {
+        if let Some(list) = psl::active() {
+            return list.section_of(&self.tld).unwrap_or(SuffixSection::Icann);
+        }
+
+        let private = List
+            .parse_domain_name(&format!("twistrs.{}", self.tld))
+            .is_ok_and(|name| name.is_private() && name.suffix().eq_ignore_ascii_case(&self.tld));
+
+        if private {
+            SuffixSection::Private
+        } else {
+            SuffixSection::Icann
+        }
+    }
+
+    /// Parse `fqdn` against the runtime suffix `list` if there is one,
+    /// falling back on the lists embedded at compile time otherwise.
+    fn parse(
+        fqdn: &str,
+        list: Option<&SuffixList>,
+        options: &ParseOptions,
+    ) -> Result<Domain, Error> {
+        DomainRef::parse(fqdn, list, options).map(Domain::from)
+    }
+
+    /// Assemble a domain from an FQDN already split into its TLD and root
+    /// label, computing its canonical form. The parts are not validated,
+    /// see [`Domain::new`] otherwise.
+    pub fn from_parts(fqdn: String, tld: String, domain: String) -> Domain {
+        Domain {
+            canonical: canonicalize(&fqdn),
+            fqdn,
+            tld,
+            domain,
+        }
+    }
+
+    /// The canonical form of the FQDN the domain is compared, hashed and
+    /// ordered by, see [`canonicalize`].
+    pub fn canonical(&self) -> &str {
+        &self.canonical
+    }
+
+    /// Borrow the parts of the domain, see [`DomainRef`].
+    pub fn as_domain_ref(&self) -> DomainRef<'_> {
+        DomainRef {
+            fqdn: &self.fqdn,
+            tld: &self.tld,
+            domain: &self.domain,
+        }
+    }
+
     /// Generate any and all possible domain permutations for a given `Domain`.
     ///
     /// Returns `Iterator<String>` with an iterator of domain permutations
@@ -137,40 +2048,285 @@ impl Domain {
     ///
     /// Any future permutations will also be included into this function call
     /// without any changes required from any client implementations.
+    ///
+    /// Permutations are yielded one kind after the other, see
+    /// [`Domain::all_interleaved`] to mix kinds instead.
     pub fn all(&self) -> Result<impl Iterator<Item = Permutation> + '_, Error> {
-        Ok(self
-            .addition()
-            .chain(self.bitsquatting())
-            .chain(self.hyphentation())
-            .chain(self.insertion())
-            .chain(self.omission())
-            .chain(self.repetition())
-            .chain(self.replacement())
-            .chain(self.subdomain())
-            .chain(self.transposition())
-            .chain(self.vowel_swap())
-            .chain(self.double_vowel_insertion())
-            .chain(self.keyword())
-            .chain(self.tld())
-            .chain(self.mapped())
-            .chain(self.homoglyph()?))
+        self.all_in(&ValidationCtx::of(self))
+    }
+
+    /// Generate all possible domain permutations as [`Domain::all`] does,
+    /// validating them with `ctx`.
+    pub fn all_in(
+        &self,
+        ctx: &ValidationCtx,
+    ) -> Result<impl Iterator<Item = Permutation> + '_, Error> {
+        let generators = PermutationKind::ALL
+            .iter()
+            .map(|kind| self.permutations_in(*kind, ctx))
+            .collect::<Result<Vec<_>, Error>>()?;
+
+        Ok(generators.into_iter().flatten())
+    }
+
+    /// Generate all possible domain permutations, taking one permutation
+    /// of each kind in turn rather than every permutation of a kind
+    /// before moving on to the next one. Stopping early (e.g. with
+    /// `take(n)`) thus still yields a representative mix of kinds.
+    ///
+    /// At most `cap` permutations of each kind are yielded, if given.
+    ///
+    /// ```
+    /// use std::collections::HashSet;
+    ///
+    /// use twistrs::permutate::Domain;
+    ///
+    /// let domain = Domain::new("example.com").unwrap();
+    /// let kinds: HashSet<_> = domain
+    ///     .all_interleaved(Some(10))
+    ///     .unwrap()
+    ///     .take(50)
+    ///     .map(|permutation| permutation.kind)
+    ///     .collect();
+    ///
+    /// assert!(kinds.len() > 10);
+    /// ```
+    pub fn all_interleaved(
+        &self,
+        cap: Option<usize>,
+    ) -> Result<impl Iterator<Item = Permutation> + '_, Error> {
+        self.all_interleaved_in(&ValidationCtx::of(self), cap)
+    }
+
+    /// Generate all possible domain permutations as
+    /// [`Domain::all_interleaved`] does, validating them with `ctx`.
+    pub fn all_interleaved_in(
+        &self,
+        ctx: &ValidationCtx,
+        cap: Option<usize>,
+    ) -> Result<impl Iterator<Item = Permutation> + '_, Error> {
+        let mut generators = PermutationKind::ALL
+            .iter()
+            .map(|kind| {
+                self.permutations_in(*kind, ctx)
+                    .map(|permutations| permutations.take(cap.unwrap_or(usize::MAX)))
+            })
+            .collect::<Result<Vec<_>, Error>>()?;
+        let mut next = 0;
+
+        Ok(std::iter::from_fn(move || {
+            // Exhausted generators are dropped, so that the remaining ones
+            // keep taking turns.
+            while !generators.is_empty() {
+                let index = next % generators.len();
+
+                if let Some(permutation) = generators[index].next() {
+                    next = index + 1;
+                    return Some(permutation);
+                }
+
+                drop(generators.remove(index));
+                next = index;
+            }
+
+            None
+        }))
+    }
+
+    /// Generate the permutations of a single `kind`.
+    pub fn permutations(
+        &self,
+        kind: PermutationKind,
+    ) -> Result<Box<dyn Iterator<Item = Permutation> + '_>, Error> {
+        self.permutations_in(kind, &ValidationCtx::of(self))
+    }
+
+    /// Generate the permutations of a single `kind`, validating them with
+    /// `ctx`.
+    pub fn permutations_in(
+        &self,
+        kind: PermutationKind,
+        ctx: &ValidationCtx,
+    ) -> Result<Box<dyn Iterator<Item = Permutation> + '_>, Error> {
+        let validation = ctx.clone();
+
+        Ok(Box::new(self.generate_in(kind, ctx)?.filter_map(
+            move |permutation| validation.validate(permutation),
+        )))
+    }
+
+    /// Generate the permutations of a single `kind`, parsing candidates
+    /// against the suffix list of `ctx` without validating them further.
+    fn generate_in(
+        &self,
+        kind: PermutationKind,
+        ctx: &ValidationCtx,
+    ) -> Result<Box<dyn Iterator<Item = Permutation> + '_>, Error> {
+        Ok(match kind {
+            PermutationKind::Addition => Box::new(self.addition_in(Charset::default(), ctx)),
+            PermutationKind::Bitsquatting => Box::new(self.bitsquatting_in(ctx)),
+            PermutationKind::Hyphenation => Box::new(self.hyphentation_in(ctx)),
+            PermutationKind::Insertion => Box::new(self.insertion_in(ctx)),
+            PermutationKind::Omission => Box::new(self.omission_in(ctx)),
+            PermutationKind::Repetition => Box::new(self.repetition_in(Charset::default(), ctx)),
+            PermutationKind::Replacement => Box::new(self.replacement_in(ctx)),
+            PermutationKind::Subdomain => Box::new(self.subdomain_in(ctx)),
+            PermutationKind::Transposition => Box::new(self.transposition_in(ctx)),
+            PermutationKind::VowelSwap => Box::new(self.vowel_swap_in(ctx)),
+            PermutationKind::DoubleVowelInsertion => Box::new(self.double_vowel_insertion_in(ctx)),
+            PermutationKind::Keyword => Box::new(self.keyword_list(
+                KeywordSet::Generic.keywords(),
+                KeywordForms::default(),
+                ctx,
+            )),
+            PermutationKind::Tld => {
+                Box::new(self.tld_in(&TldSet::All, &TldExclusions::default(), ctx))
+            }
+            PermutationKind::Homoglyph => Box::new(self.homoglyph_in(
+                &HomoglyphTable::builtin(),
+                &HomoglyphOptions::default(),
+                ctx,
+            )?),
+            PermutationKind::Mapped => Box::new(
+                self.substitute(
+                    &MappedTable::builtin(),
+                    MappedMode::Every,
+                    PermutationKind::Mapped,
+                    ctx,
+                )
+                .into_iter(),
+            ),
+            PermutationKind::Transliteration => Box::new(self.transliteration_in(ctx)),
+            PermutationKind::OcrConfusion => Box::new(
+                self.substitute(
+                    &ocr_confusions(),
+                    MappedMode::Single,
+                    PermutationKind::OcrConfusion,
+                    ctx,
+                )
+                .into_iter(),
+            ),
+            PermutationKind::TokenReorder => Box::new(self.token_reorder_in(ctx)),
+            PermutationKind::SeparatorVariation => Box::new(self.separator_variation_in(ctx)),
+            // Dictionaries are supplied at runtime, see
+            // [`PermutationEngine::dictionary`].
+            PermutationKind::Dictionary => Box::new(std::iter::empty()),
+            #[cfg(feature = "phonetic")]
+            PermutationKind::Homophone => Box::new(self.homophone_in(ctx)),
+            #[cfg(not(feature = "phonetic"))]
+            PermutationKind::Homophone => Box::new(std::iter::empty()),
+            PermutationKind::NumberStep => Box::new(self.number_step_in(ctx)),
+            PermutationKind::Spelling => Box::new(self.spelling_in(ctx)),
+            PermutationKind::IdnHomograph => Box::new(self.idn_homograph_in(ctx)),
+            PermutationKind::HyphenRemoval => Box::new(self.hyphen_removal_in(ctx)),
+            PermutationKind::DotOmission => Box::new(self.dot_omission_in(ctx)),
+            PermutationKind::DotHyphenSwap => Box::new(self.dot_hyphen_swap_in(ctx)),
+            PermutationKind::FatFinger => Box::new(self.fat_finger_in(ctx)),
+            PermutationKind::WrongLayout => Box::new(self.wrong_layout_in(ctx)),
+            PermutationKind::VowelRemoval => Box::new(self.vowel_removal_in(ctx)),
+            PermutationKind::Misspelling => Box::new(self.misspelling_in(ctx)),
+            PermutationKind::TldAppend => Box::new(self.tld_append_in(ctx)),
+        })
+    }
+
+    /// Cheaply estimate the number of permutations of a given `kind`
+    /// without generating them. The estimate is derived from the length
+    /// of the domain and the size of the dictionaries involved, and is
+    /// meant for progress reporting and capacity planning.
+    pub fn estimate(&self, kind: PermutationKind) -> usize {
+        let chars: Vec<char> = self.fqdn.chars().collect();
+        let len = chars.len();
+        let layouts = self.keyboard_layouts();
+        let adjacent_keys = |c: &char| -> usize {
+            layouts
+                .iter()
+                .filter_map(|layout| layout.get(c))
+                .map(|keys| keys.chars().count())
+                .sum()
+        };
+
+        match kind {
+            PermutationKind::Addition => ASCII_LOWER.len(),
+            PermutationKind::Bitsquatting => len * 8 * len.saturating_sub(1),
+            PermutationKind::Hyphenation | PermutationKind::Transposition => len.saturating_sub(1),
+            PermutationKind::Insertion | PermutationKind::Replacement => chars
+                .iter()
+                .skip(1)
+                .take(len.saturating_sub(2))
+                .map(adjacent_keys)
+                .sum(),
+            PermutationKind::Omission | PermutationKind::Repetition => len,
+            PermutationKind::Subdomain => len.saturating_sub(4),
+            PermutationKind::VowelSwap => {
+                chars.iter().filter(|c| VOWELS.contains(c)).count() * (VOWELS.len() - 1)
+            }
+            PermutationKind::DoubleVowelInsertion => {
+                chars
+                    .windows(2)
+                    .filter(|w| VOWELS.contains(&w[0]) && VOWELS.contains(&w[1]))
+                    .count()
+                    * ASCII_LOWER.len()
+            }
+            PermutationKind::Keyword => KEYWORDS.len() * KeywordForms::default().count_in(self),
+            PermutationKind::Tld => psl::active().map_or(TLDS.len(), |list| list.suffixes().len()),
+            PermutationKind::Homoglyph => self.homoglyph_estimate(&HomoglyphTable::builtin()),
+            PermutationKind::Mapped => {
+                self.mapped_estimate(&MappedTable::builtin(), MappedMode::Every)
+            }
+            PermutationKind::Transliteration => {
+                self.transliterations().len() + self.transliteration_typos().len()
+            }
+            PermutationKind::OcrConfusion => {
+                self.mapped_estimate(&ocr_confusions(), MappedMode::Single)
+            }
+            PermutationKind::TokenReorder => self.token_reorders().len(),
+            PermutationKind::SeparatorVariation => self.separator_labels().len(),
+            PermutationKind::Dictionary => 0,
+            #[cfg(feature = "phonetic")]
+            PermutationKind::Homophone => self.homophones().len(),
+            #[cfg(not(feature = "phonetic"))]
+            PermutationKind::Homophone => 0,
+            PermutationKind::NumberStep => self.number_steps().len(),
+            PermutationKind::Spelling => self.spellings().len(),
+            PermutationKind::IdnHomograph => self.idn_homographs().len(),
+            PermutationKind::HyphenRemoval => self.hyphen_removals().len(),
+            PermutationKind::DotOmission => self.fqdn.matches('.').count(),
+            PermutationKind::DotHyphenSwap => self.fqdn.matches(['.', '-']).count(),
+            PermutationKind::FatFinger => chars
+                .iter()
+                .take(len.saturating_sub(1))
+                .map(adjacent_keys)
+                .sum(),
+            PermutationKind::WrongLayout => self.wrong_layouts().len(),
+            PermutationKind::VowelRemoval => self.vowel_removals().len(),
+            PermutationKind::Misspelling => self.misspellings().len(),
+            PermutationKind::TldAppend => self.tld_appends().len(),
+        }
     }
 
     /// Add every ASCII lowercase character between the Domain
     /// (e.g. `google`) and top-level domain (e.g. `.com`).
     pub fn addition(&self) -> impl Iterator<Item = Permutation> + '_ {
-        ASCII_LOWER.iter().filter_map(move |c| {
-            let fqdn = format!("{}{}.{}", self.domain, c, self.tld);
+        self.addition_charset(Charset::default())
+    }
 
-            if let Ok(domain) = Domain::new(fqdn.as_str()) {
-                Some(Permutation {
-                    domain,
-                    kind: PermutationKind::Addition,
-                })
-            } else {
-                None
-            }
-        })
+    /// Add every character of `charset` between the Domain and top-level
+    /// domain, e.g. to also add digits (`google1.com`).
+    pub fn addition_charset(&self, charset: Charset) -> impl Iterator<Item = Permutation> + '_ {
+        self.addition_in(charset, &ValidationCtx::of(self))
+    }
+
+    fn addition_in(
+        &self,
+        charset: Charset,
+        ctx: &ValidationCtx,
+    ) -> impl Iterator<Item = Permutation> + '_ {
+        ctx.clone().parse_each(
+            PermutationKind::Addition,
+            charset
+                .chars()
+                .map(move |c| format!("{}{}.{}", self.domain, c, self.tld)),
+        )
     }
 
     /// Following implementation takes inspiration from the following content:
@@ -191,7 +2347,11 @@ impl Domain {
     ///
     /// Then check if the resulting bit operation falls within ASCII range.
     pub fn bitsquatting(&self) -> impl Iterator<Item = Permutation> + '_ {
-        let permutations = self
+        self.bitsquatting_in(&ValidationCtx::of(self))
+    }
+
+    fn bitsquatting_in(&self, ctx: &ValidationCtx) -> impl Iterator<Item = Permutation> + '_ {
+        let candidates = self
             .fqdn
             .chars()
             .flat_map(move |c| {
@@ -206,40 +2366,149 @@ impl Domain {
                         || ((97..=122).contains(&squatted_char))
                         || squatted_char == 45
                     {
-                        Some((1..self.fqdn.len()).map(move |idx| {
-                            let mut permutation = self.fqdn.to_string();
-                            permutation.insert(idx, squatted_char as char);
-                            permutation
-                        }))
+                        Some(
+                            (1..self.fqdn.len())
+                                .filter(|idx| self.fqdn.is_char_boundary(*idx))
+                                .map(move |idx| {
+                                    let mut permutation = self.fqdn.to_string();
+                                    permutation.insert(idx, squatted_char as char);
+                                    permutation
+                                }),
+                        )
                     } else {
                         None
                     }
                 })
             })
-            .flatten()
-            .filter_map(move |fqdn| {
-                if let Ok(domain) = Domain::new(fqdn.as_str()) {
-                    Some(Permutation {
-                        domain,
-                        kind: PermutationKind::Bitsquatting,
-                    })
-                } else {
-                    None
-                }
-            });
+            .flatten();
+
+        ctx.clone()
+            .parse_each(PermutationKind::Bitsquatting, candidates)
+    }
+
+    /// Estimate how many mapped permutations `table` yields.
+    fn mapped_estimate(&self, table: &MappedTable, mode: MappedMode) -> usize {
+        table
+            .values
+            .iter()
+            .filter(|(key, _)| !key.is_empty())
+            .map(|(key, values)| {
+                values.len() * mode.candidates(self.domain.matches(key.as_str()).count())
+            })
+            .sum()
+    }
 
-        permutations
+    /// Estimate how many homoglyph permutations `table` yields.
+    fn homoglyph_estimate(&self, table: &HomoglyphTable) -> usize {
+        self.fqdn
+            .chars()
+            .filter_map(|c| table.get(c))
+            .map(|glyphs| glyphs.chars().count())
+            .sum::<usize>()
+            * self.fqdn.chars().count()
     }
 
     /// Permutation method that replaces ASCII characters with multiple homoglyphs
     /// similar to the respective ASCII character.
     pub fn homoglyph(&self) -> Result<impl Iterator<Item = Permutation> + '_, Error> {
+        self.homoglyph_table(&HomoglyphTable::builtin())
+    }
+
+    /// Permutation method that replaces characters with the homoglyphs
+    /// listed in `table`, e.g. to only use Cyrillic lookalikes.
+    pub fn homoglyph_table(
+        &self,
+        table: &HomoglyphTable,
+    ) -> Result<impl Iterator<Item = Permutation> + '_, Error> {
+        self.homoglyph_with(table, &HomoglyphOptions::default())
+    }
+
+    /// Permutation method that replaces characters with the homoglyphs
+    /// listed in `table` that `options` allow, e.g. to only generate
+    /// Cyrillic lookalikes that do not mix scripts.
+    pub fn homoglyph_with(
+        &self,
+        table: &HomoglyphTable,
+        options: &HomoglyphOptions,
+    ) -> Result<impl Iterator<Item = Permutation> + '_, Error> {
+        self.homoglyph_in(table, options, &ValidationCtx::of(self))
+    }
+
+    fn homoglyph_in(
+        &self,
+        table: &HomoglyphTable,
+        options: &HomoglyphOptions,
+        ctx: &ValidationCtx,
+    ) -> Result<impl Iterator<Item = Permutation> + '_, Error> {
+        let restricted = table.restrict(options);
+        let mut permutations = self.homoglyph_passes(&restricted, ctx)?;
+
+        if options.single_script {
+            permutations.retain(|permutation| is_single_script(&permutation.domain.fqdn));
+            permutations.extend(self.homoglyph_labels(&restricted, ctx));
+        }
+
+        Ok(permutations.into_iter())
+    }
+
+    /// Root labels spelled entirely in glyphs of `table` of a single
+    /// script other than the label's own, e.g. `аррӏе.com` in Cyrillic.
+    fn homoglyph_labels<'a>(
+        &'a self,
+        table: &'a HomoglyphTable,
+        ctx: &ValidationCtx,
+    ) -> impl Iterator<Item = Permutation> + 'a {
+        let scripts: BTreeSet<Script> = table
+            .glyphs
+            .values()
+            .flat_map(|glyphs| glyphs.chars().map(Script::of))
+            .filter(|script| *script != Script::Common && *script != Script::Other)
+            .collect();
+
+        ctx.clone()
+            .parse_each(
+                PermutationKind::Homoglyph,
+                scripts.into_iter().filter_map(move |script| {
+                    let label = self
+                        .domain
+                        .chars()
+                        .map(|c| match Script::of(c) {
+                            Script::Common => Some(c),
+                            _ => table.get(c).and_then(|glyphs| {
+                                glyphs.chars().find(|g| Script::of(*g) == script)
+                            }),
+                        })
+                        .collect::<Option<String>>()?;
+
+                    (is_single_script(&label) && label != self.domain)
+                        .then(|| self.with_label(&label))
+                        .flatten()
+                }),
+            )
+            .map(move |mut permutation| {
+                permutation.substitutions =
+                    Substitution::between(&self.fqdn, &permutation.domain.fqdn);
+                permutation
+            })
+    }
+
+    /// Substitute one and then two characters of the FQDN with their
+    /// glyphs in `table`.
+    fn homoglyph_passes(
+        &self,
+        table: &HomoglyphTable,
+        ctx: &ValidationCtx,
+    ) -> Result<HashSet<Permutation>, Error> {
         // @CLEANUP(jdb): Tidy this entire mess up
         let mut result_first_pass: HashSet<Permutation> = HashSet::new();
         let mut result_second_pass: HashSet<Permutation> = HashSet::new();
 
         for ws in 1..self.fqdn.len() {
             for i in 0..(self.fqdn.len() - ws) + 1 {
+                if !self.fqdn.is_char_boundary(i) || !self.fqdn.is_char_boundary(i + ws) {
+                    continue;
+                }
+
                 let win: String = self.fqdn[i..i + ws].to_string().chars().collect();
                 let mut j = 0;
 
@@ -252,15 +2521,16 @@ impl Domain {
                             homoglyph: win.to_string(),
                         })?;
 
-                    if let Some(glyph) = HOMOGLYPHS.get(&c) {
+                    if let Some(glyph) = table.get(c) {
                         for g in glyph.chars().collect::<Vec<char>>() {
                             let new_win = win.replace(c, &g.to_string());
 
                             let fqdn =
                                 format!("{}{}{}", &self.fqdn[..i], &new_win, &self.fqdn[i + ws..]);
 
-                            if let Ok(domain) = Domain::new(fqdn.as_str()) {
+                            if let Ok(domain) = ctx.parse(fqdn.as_str()) {
                                 result_first_pass.insert(Permutation {
+                                    substitutions: Substitution::between(&self.fqdn, &fqdn),
                                     domain,
                                     kind: PermutationKind::Homoglyph,
                                 });
@@ -276,6 +2546,13 @@ impl Domain {
         for permutation in &result_first_pass {
             for ws in 1..self.fqdn.len() {
                 for i in 0..(self.fqdn.len() - ws) + 1 {
+                    if i + ws > permutation.domain.fqdn.chars().count()
+                        || !self.fqdn.is_char_boundary(i)
+                        || !self.fqdn.is_char_boundary(i + ws)
+                    {
+                        continue;
+                    }
+
                     // We need to do this as we are dealing with UTF8 characters
                     // meaning that we cannot simple iterate over single byte
                     // values (as certain characters are composed of two or more)
@@ -294,7 +2571,7 @@ impl Domain {
                                     homoglyph: win.to_string(),
                                 })?;
 
-                        if let Some(glyph) = HOMOGLYPHS.get(&c) {
+                        if let Some(glyph) = table.get(c) {
                             for g in glyph.chars().collect::<Vec<char>>() {
                                 let new_win = win.replace(c, &g.to_string());
                                 let fqdn = format!(
@@ -304,8 +2581,9 @@ impl Domain {
                                     &self.fqdn[i + ws..]
                                 );
 
-                                if let Ok(domain) = Domain::new(fqdn.as_str()) {
+                                if let Ok(domain) = ctx.parse(fqdn.as_str()) {
                                     result_second_pass.insert(Permutation {
+                                        substitutions: Substitution::between(&self.fqdn, &fqdn),
                                         domain,
                                         kind: PermutationKind::Homoglyph,
                                     });
@@ -319,238 +2597,987 @@ impl Domain {
             }
         }
 
-        Ok((&result_first_pass | &result_second_pass).into_iter())
+        Ok(&result_first_pass | &result_second_pass)
+    }
+
+    /// Keyboard layouts of the keyboard-based permutations, i.e. the Latin
+    /// ones along with the layout of any other script the FQDN is written
+    /// in (e.g. the Russian one for Cyrillic labels).
+    fn keyboard_layouts(&self) -> Vec<&'static phf::Map<char, &'static str>> {
+        let mut layouts = KEYBOARD_LAYOUTS.clone();
+        layouts.extend(script_layouts(&self.fqdn));
+        layouts
+    }
+
+    /// Byte offset of the `i`-th character of the FQDN, or its length if
+    /// there are fewer characters.
+    fn char_offset(&self, i: usize) -> usize {
+        self.fqdn
+            .char_indices()
+            .nth(i)
+            .map_or(self.fqdn.len(), |(offset, _)| offset)
     }
 
     /// Permutation method that inserts hyphens (i.e. `-`) between each
     /// character in the domain where valid.
     pub fn hyphentation(&self) -> impl Iterator<Item = Permutation> + '_ {
-        self.fqdn
-            .chars()
-            .skip(1)
-            .enumerate()
-            .filter_map(move |(i, _)| {
-                let mut permutation = self.fqdn.to_string();
-                permutation.insert(i, '-');
+        self.hyphentation_in(&ValidationCtx::of(self))
+    }
+
+    fn hyphentation_in(&self, ctx: &ValidationCtx) -> impl Iterator<Item = Permutation> + '_ {
+        ctx.clone().parse_each(
+            PermutationKind::Hyphenation,
+            self.fqdn
+                .char_indices()
+                .take(self.fqdn.chars().count() - 1)
+                .map(move |(i, _)| {
+                    let mut permutation = self.fqdn.to_string();
+                    permutation.insert(i, '-');
+                    permutation
+                }),
+        )
+    }
+
+    /// Split the root label of the domain into tokens, e.g.
+    /// `unitedhealthgroup` into `united`, `health` and `group`, see
+    /// [`crate::token`].
+    ///
+    /// Punycode root labels (e.g. `xn--mnchen-3ya`) yield no tokens, as
+    /// their encoded form has no words to split on, and token offsets
+    /// into their Unicode form would not apply to the label itself.
+    pub fn tokenize(&self) -> Vec<Token> {
+        if self.domain.starts_with("xn--") {
+            return vec![];
+        }
+
+        token::tokenize(&self.domain)
+    }
+
+    /// FQDN of the domain with its root label replaced by `label`,
+    /// keeping any subdomains and the TLD.
+    fn with_label(&self, label: &str) -> Option<String> {
+        let subdomains = self
+            .fqdn
+            .strip_suffix(&format!("{}.{}", self.domain, self.tld))?;
+
+        Some(format!("{subdomains}{label}.{}", self.tld))
+    }
+
+    /// Permutation method that inserts hyphens between the tokens of the
+    /// root label, one boundary at a time and then all of them at once
+    /// (e.g. `unitedhealthgroup.com` -> `united-healthgroup.com`,
+    /// `unitedhealth-group.com` and `united-health-group.com`).
+    pub fn token_hyphenation(&self) -> impl Iterator<Item = Permutation> + '_ {
+        ValidationCtx::of(self).parse_each(
+            PermutationKind::Hyphenation,
+            self.separated_labels('-')
+                .into_iter()
+                .filter_map(move |label| self.with_label(&label)),
+        )
+    }
+
+    /// Permutation method that inserts hyphens or dots between the tokens
+    /// of the root label, one separator at a time and then all of them at
+    /// once (e.g. `bankofamerica.com` -> `bank-of-america.com` and
+    /// `bank.of.america.com`). Existing hyphens are removed by
+    /// [`Domain::hyphen_removal`] instead.
+    pub fn separator_variation(&self) -> impl Iterator<Item = Permutation> + '_ {
+        self.separator_variation_in(&ValidationCtx::of(self))
+    }
+
+    fn separator_variation_in(
+        &self,
+        ctx: &ValidationCtx,
+    ) -> impl Iterator<Item = Permutation> + '_ {
+        ctx.clone().parse_each(
+            PermutationKind::SeparatorVariation,
+            self.separator_labels()
+                .into_iter()
+                .filter_map(move |label| self.with_label(&label)),
+        )
+    }
+
+    /// Labels of [`Domain::separator_variation`].
+    fn separator_labels(&self) -> Vec<String> {
+        self.separated_labels('-')
+            .into_iter()
+            .chain(self.separated_labels('.'))
+            .collect()
+    }
+
+    /// Permutation method that strips the hyphens of the root label, one
+    /// hyphen at a time and then all of them at once (e.g.
+    /// `my-brand-name.com` -> `mybrand-name.com`, `my-brandname.com` and
+    /// `mybrandname.com`), the reverse of [`Domain::token_hyphenation`].
+    pub fn hyphen_removal(&self) -> impl Iterator<Item = Permutation> + '_ {
+        self.hyphen_removal_in(&ValidationCtx::of(self))
+    }
+
+    fn hyphen_removal_in(&self, ctx: &ValidationCtx) -> impl Iterator<Item = Permutation> + '_ {
+        ctx.clone().parse_each(
+            PermutationKind::HyphenRemoval,
+            self.hyphen_removals()
+                .into_iter()
+                .filter_map(move |label| self.with_label(&label)),
+        )
+    }
+
+    /// Permutation method that drops the vowels of the root label, one
+    /// vowel at a time and then all of them at once (e.g. `facebook.com`
+    /// -> `facbook.com`, `facebok.com` and `fcbk.com`), catching
+    /// abbreviation squats.
+    pub fn vowel_removal(&self) -> impl Iterator<Item = Permutation> + '_ {
+        self.vowel_removal_in(&ValidationCtx::of(self))
+    }
+
+    fn vowel_removal_in(&self, ctx: &ValidationCtx) -> impl Iterator<Item = Permutation> + '_ {
+        ctx.clone().parse_each(
+            PermutationKind::VowelRemoval,
+            self.vowel_removals()
+                .into_iter()
+                .filter_map(move |label| self.with_label(&label)),
+        )
+    }
+
+    /// Distinct non-empty root labels with each vowel removed, and then
+    /// all of them.
+    fn vowel_removals(&self) -> Vec<String> {
+        let vowels: Vec<usize> = self
+            .domain
+            .char_indices()
+            .filter(|(_, c)| VOWELS.contains(&c.to_ascii_lowercase()))
+            .map(|(i, _)| i)
+            .collect();
+        let mut seen = HashSet::from([self.domain.clone()]);
+
+        Self::splits(vowels)
+            .into_iter()
+            .map(|split| {
+                let mut label = self.domain.clone();
+                for vowel in split.into_iter().rev() {
+                    label.remove(vowel);
+                }
+                label
+            })
+            .filter(|label| !label.is_empty() && seen.insert(label.clone()))
+            .collect()
+    }
+
+    /// Distinct root labels with each hyphen removed, and then all of
+    /// them. Removing either of two adjacent hyphens (e.g. those of an
+    /// A-label) yields the same label, which is only kept once.
+    fn hyphen_removals(&self) -> Vec<String> {
+        let hyphens: Vec<usize> = self.domain.match_indices('-').map(|(i, _)| i).collect();
+        let mut seen = HashSet::new();
+
+        Self::splits(hyphens)
+            .into_iter()
+            .map(|split| {
+                let mut label = self.domain.clone();
+                for hyphen in split.into_iter().rev() {
+                    label.remove(hyphen);
+                }
+                label
+            })
+            .filter(|label| seen.insert(label.clone()))
+            .collect()
+    }
+
+    /// Root labels with `separator` inserted between adjacent tokens, at
+    /// each boundary and then at all of them.
+    fn separated_labels(&self, separator: char) -> Vec<String> {
+        let boundaries: Vec<usize> = self
+            .tokenize()
+            .iter()
+            .tuple_windows()
+            .filter(|(left, right)| left.end() == right.start)
+            .map(|(_, right)| right.start)
+            .collect();
+
+        Self::splits(boundaries)
+            .into_iter()
+            .map(|split| {
+                let mut label = self.domain.clone();
+                for boundary in split.into_iter().rev() {
+                    label.insert(boundary, separator);
+                }
+                label
+            })
+            .collect()
+    }
+
+    /// Each of `positions` on its own, then all of them if there are more
+    /// than one.
+    fn splits(positions: Vec<usize>) -> Vec<Vec<usize>> {
+        let mut splits: Vec<Vec<usize>> = positions.iter().map(|&p| vec![p]).collect();
+        if positions.len() > 1 {
+            splits.push(positions);
+        }
 
-                if let Ok(domain) = Domain::new(permutation.as_str()) {
-                    Some(Permutation {
-                        domain,
-                        kind: PermutationKind::Hyphenation,
+        splits
+    }
+
+    /// Generate the permutations of a single `kind` within `token` only,
+    /// leaving the other tokens of the root label untouched (e.g.
+    /// `unitedhealthgroup.com` -> `unitedhealtgroup.com`). Permutations
+    /// that change the TLD are skipped.
+    pub fn token_permutations(
+        &self,
+        token: &Token,
+        kind: PermutationKind,
+    ) -> Result<impl Iterator<Item = Permutation> + '_, Error> {
+        let (Some(head), Some(tail), true) = (
+            self.domain.get(..token.start),
+            self.domain.get(token.end()..),
+            self.domain.get(token.start..token.end()) == Some(token.text.as_str()),
+        ) else {
+            return Err(PermutationError::InvalidDomain {
+                expected: format!("token of {:?}", self.domain),
+                found: token.text.clone(),
+            }
+            .into());
+        };
+
+        let standalone = Domain::new(&format!("{}.{}", token.text, self.tld))?;
+        let permutations: Vec<Permutation> = standalone.permutations(kind)?.collect();
+        let (prefix, suffix) = (head.to_string(), tail.to_string());
+
+        let candidates = permutations.into_iter().filter_map(move |permutation| {
+            if permutation.domain.tld != self.tld {
+                return None;
+            }
+
+            let variant = permutation
+                .domain
+                .fqdn
+                .strip_suffix(&format!(".{}", self.tld))?;
+
+            self.with_label(&format!("{prefix}{variant}{suffix}"))
+        });
+
+        Ok(ValidationCtx::of(self).parse_each(kind, candidates))
+    }
+
+    /// Permutation method that reorders the tokens of the root label and
+    /// drops some of them (e.g. `bankofamerica.com` -> `americabank.com`
+    /// and `ofamericabank.com`), keeping at least two tokens.
+    ///
+    /// Labels with more than five tokens are skipped, as the number of
+    /// orderings grows factorially.
+    pub fn token_reorder(&self) -> impl Iterator<Item = Permutation> + '_ {
+        self.token_reorder_in(&ValidationCtx::of(self))
+    }
+
+    fn token_reorder_in(&self, ctx: &ValidationCtx) -> impl Iterator<Item = Permutation> + '_ {
+        ctx.clone().parse_each(
+            PermutationKind::TokenReorder,
+            self.token_reorders()
+                .into_iter()
+                .filter_map(move |label| self.with_label(&label)),
+        )
+    }
+
+    /// Distinct labels made of at least two of the tokens of the root
+    /// label in any order, other than the root label itself.
+    fn token_reorders(&self) -> Vec<String> {
+        let tokens: Vec<String> = self
+            .tokenize()
+            .into_iter()
+            .map(|token| token.text)
+            .collect();
+
+        if tokens.len() < 2 || tokens.len() > MAX_REORDER_TOKENS {
+            return vec![];
+        }
+
+        let mut seen = HashSet::from([self.domain.clone()]);
+
+        (2..=tokens.len())
+            .flat_map(|k| tokens.iter().permutations(k))
+            .map(|ordering| ordering.into_iter().join(""))
+            .filter(|label| seen.insert(label.clone()))
+            .collect()
+    }
+
+    /// Permutation method that types the whole root label as if on
+    /// another keyboard layout, i.e. pressing the keys of its letters on
+    /// one of QWERTY, QWERTZ or AZERTY while another one is active (e.g.
+    /// `amazon.com` typed on AZERTY -> `qmqwon.com`). Letters whose key
+    /// types punctuation on the active layout are left as they are.
+    pub fn wrong_layout(&self) -> impl Iterator<Item = Permutation> + '_ {
+        self.wrong_layout_in(&ValidationCtx::of(self))
+    }
+
+    fn wrong_layout_in(&self, ctx: &ValidationCtx) -> impl Iterator<Item = Permutation> + '_ {
+        ctx.clone().parse_each(
+            PermutationKind::WrongLayout,
+            self.wrong_layouts()
+                .into_iter()
+                .filter_map(move |label| self.with_label(&label)),
+        )
+    }
+
+    /// Distinct root labels typed on each layout in place of each other
+    /// one, see [`Domain::wrong_layout`].
+    fn wrong_layouts(&self) -> Vec<String> {
+        let label = self.domain.to_lowercase();
+        let mut seen = HashSet::from([label.clone()]);
+        let mut labels = vec![];
+
+        for intended in &KEYBOARD_ROWS {
+            for active in KEYBOARD_ROWS.iter().filter(|active| *active != intended) {
+                let typed: String = label
+                    .chars()
+                    .map(|c| {
+                        intended
+                            .iter()
+                            .zip(active)
+                            .find_map(|(from, to)| {
+                                let position = from.chars().position(|key| key == c)?;
+                                to.chars().nth(position)
+                            })
+                            .filter(char::is_ascii_alphanumeric)
+                            .unwrap_or(c)
                     })
-                } else {
-                    None
+                    .collect();
+
+                if seen.insert(typed.clone()) {
+                    labels.push(typed);
                 }
+            }
+        }
+
+        labels
+    }
+
+    /// Permutation method that swaps the English words of the root label
+    /// for words that sound alike, one word at a time (e.g.
+    /// `forsale.com` -> `foursale.com`, `righthome.com` ->
+    /// `writehome.com`), see [`Domain::tokenize`].
+    ///
+    /// This method requires the `phonetic` feature toggled.
+    #[cfg(feature = "phonetic")]
+    pub fn homophone(&self) -> impl Iterator<Item = Permutation> + '_ {
+        self.homophone_in(&ValidationCtx::of(self))
+    }
+
+    #[cfg(feature = "phonetic")]
+    fn homophone_in(&self, ctx: &ValidationCtx) -> impl Iterator<Item = Permutation> + '_ {
+        ctx.clone().parse_each(
+            PermutationKind::Homophone,
+            self.homophones()
+                .into_iter()
+                .filter_map(move |label| self.with_label(&label)),
+        )
+    }
+
+    /// Distinct labels with one of the tokens of the root label swapped
+    /// for one of its homophones.
+    #[cfg(feature = "phonetic")]
+    fn homophones(&self) -> Vec<String> {
+        self.token_substitutions(|word| {
+            HOMOPHONES
+                .iter()
+                .filter(|group| group.contains(&word))
+                .flat_map(|group| group.iter().copied())
+                .filter(|homophone| *homophone != word)
+                .collect()
+        })
+    }
+
+    /// Permutation method that spells out the numbers of the root label
+    /// and turns spelled-out numbers into digits, one token at a time
+    /// (e.g. `4chan.com` -> `fourchan.com`, `onepassword.com` ->
+    /// `1password.com`), see [`Domain::tokenize`].
+    pub fn spelling(&self) -> impl Iterator<Item = Permutation> + '_ {
+        self.spelling_in(&ValidationCtx::of(self))
+    }
+
+    fn spelling_in(&self, ctx: &ValidationCtx) -> impl Iterator<Item = Permutation> + '_ {
+        ctx.clone().parse_each(
+            PermutationKind::Spelling,
+            self.spellings()
+                .into_iter()
+                .filter_map(move |label| self.with_label(&label)),
+        )
+    }
+
+    /// Distinct labels with one of the tokens of the root label spelled
+    /// out, or turned into digits.
+    fn spellings(&self) -> Vec<String> {
+        self.token_substitutions(|token| {
+            NUMBER_WORDS
+                .iter()
+                .filter_map(|(number, word)| {
+                    if token == *number {
+                        Some(*word)
+                    } else {
+                        (token == *word).then_some(*number)
+                    }
+                })
+                .collect()
+        })
+    }
+
+    /// Permutation method that substitutes the letters of the root label
+    /// with their Cyrillic, Greek or Armenian lookalikes, one letter at a
+    /// time and then every letter at once, script by script (e.g.
+    /// `apple.com` -> `аpple.com` and `аррӏе.com`).
+    ///
+    /// Root labels that are already punycode (e.g. `xn--mnchen-3ya`) are
+    /// decoded first, so that the letters of their Unicode form are
+    /// substituted rather than those of the encoding.
+    ///
+    /// Only candidates that are valid IDNA, hyphen placement included, are
+    /// yielded, in their ASCII-compatible (punycode) form, e.g.
+    /// `xn--pple-43d.com`.
+    pub fn idn_homograph(&self) -> impl Iterator<Item = Permutation> + '_ {
+        self.idn_homograph_in(&ValidationCtx::of(self))
+    }
+
+    fn idn_homograph_in(&self, ctx: &ValidationCtx) -> impl Iterator<Item = Permutation> + '_ {
+        let validation = ctx.clone();
+        // Characters of the subdomains preceding the root label.
+        let offset = self
+            .fqdn
+            .strip_suffix(&format!("{}.{}", self.domain, self.tld))
+            .map_or(0, |subdomains| subdomains.chars().count());
+
+        self.idn_homographs()
+            .into_iter()
+            .filter_map(move |(label, substitutions)| {
+                let mut permutation = validation
+                    .clone()
+                    .parse_each(
+                        PermutationKind::IdnHomograph,
+                        self.with_label(&label).into_iter(),
+                    )
+                    .next()?;
+
+                permutation.substitutions = substitutions
+                    .into_iter()
+                    .map(|substitution| Substitution {
+                        position: offset + substitution.position,
+                        ..substitution
+                    })
+                    .collect();
+                Some(permutation)
+            })
+            .filter_map(|permutation| IdnaMode::Ascii.apply(permutation))
+            .filter(|permutation| {
+                idna::Config::default()
+                    .use_std3_ascii_rules(true)
+                    .verify_dns_length(true)
+                    .check_hyphens(true)
+                    .to_ascii(&permutation.domain.fqdn)
+                    .is_ok()
             })
     }
 
+    /// Distinct Unicode labels with one, and then every letter of the
+    /// (decoded) root label substituted with a lookalike of a single
+    /// script, along with the substitutions made within the label.
+    fn idn_homographs(&self) -> Vec<(String, Vec<Substitution>)> {
+        let root = if self.domain.starts_with("xn--") {
+            match idna::domain_to_unicode(&self.domain) {
+                (unicode, Ok(())) => unicode,
+                _ => return vec![],
+            }
+        } else {
+            self.domain.clone()
+        };
+
+        let mut seen = HashSet::from([root.clone()]);
+        let mut labels = vec![];
+        let chars: Vec<char> = root.chars().collect();
+
+        for script in [Script::Cyrillic, Script::Greek, Script::Armenian] {
+            let glyphs = |c: char| {
+                IDN_CONFUSABLES
+                    .get(&c)
+                    .into_iter()
+                    .flat_map(|glyphs| glyphs.chars())
+                    .filter(move |glyph| Script::of(*glyph) == script)
+            };
+
+            for (i, c) in chars.iter().enumerate() {
+                for glyph in glyphs(*c) {
+                    let mut substituted = chars.clone();
+                    substituted[i] = glyph;
+                    labels.push((
+                        substituted.into_iter().collect::<String>(),
+                        vec![Substitution::new(i, *c, glyph)],
+                    ));
+                }
+            }
+
+            let substitutions: Vec<Substitution> = chars
+                .iter()
+                .enumerate()
+                .filter_map(|(i, c)| Some(Substitution::new(i, *c, glyphs(*c).next()?)))
+                .collect();
+            let mut substituted = chars.clone();
+            for substitution in &substitutions {
+                substituted[substitution.position] = substitution.replacement;
+            }
+            labels.push((substituted.into_iter().collect(), substitutions));
+        }
+
+        // UTS #46 reserves hyphens in the third and fourth positions (as
+        // in `xn--`), which `idna` leaves unchecked.
+        labels.retain(|(label, _)| {
+            let reserved = label.chars().skip(2).take(2).eq(['-', '-']);
+
+            !reserved && seen.insert(label.clone())
+        });
+        labels
+    }
+
+    /// Distinct labels with one of the lowercase tokens of the root label
+    /// swapped for one of its `substitutes`, other than the root label
+    /// itself.
+    fn token_substitutions<'a, F>(&self, substitutes: F) -> Vec<String>
+    where
+        F: Fn(&str) -> Vec<&'a str>,
+    {
+        let mut seen = HashSet::from([self.domain.clone()]);
+        let mut labels = vec![];
+
+        for token in self.tokenize() {
+            for substitute in substitutes(&token.text.to_lowercase()) {
+                let label = format!(
+                    "{}{substitute}{}",
+                    &self.domain[..token.start],
+                    &self.domain[token.end()..]
+                );
+
+                if seen.insert(label.clone()) {
+                    labels.push(label);
+                }
+            }
+        }
+
+        labels
+    }
+
+    /// Permutation method that swaps the English words found in the root
+    /// label for their frequent misspellings, one occurrence at a time
+    /// (e.g. `address.com` -> `adress.com`, `receivepay.com` ->
+    /// `recievepay.com`). Unlike homophones, words are looked
+    /// up anywhere in the label rather than among its tokens, as few of
+    /// them are part of the segmentation word list.
+    pub fn misspelling(&self) -> impl Iterator<Item = Permutation> + '_ {
+        self.misspelling_in(&ValidationCtx::of(self))
+    }
+
+    fn misspelling_in(&self, ctx: &ValidationCtx) -> impl Iterator<Item = Permutation> + '_ {
+        ctx.clone().parse_each(
+            PermutationKind::Misspelling,
+            self.misspellings()
+                .into_iter()
+                .filter_map(move |label| self.with_label(&label)),
+        )
+    }
+
+    /// Distinct labels with one occurrence of a word of [`MISSPELLINGS`]
+    /// in the root label misspelled, regardless of its case.
+    fn misspellings(&self) -> Vec<String> {
+        let mut seen = HashSet::from([self.domain.clone()]);
+        let mut labels = vec![];
+        // The words are ASCII, so the offsets of the lowercased label are
+        // those of the label itself.
+        let lowercase = self.domain.to_ascii_lowercase();
+
+        for (word, misspellings) in &MISSPELLINGS {
+            for (start, _) in lowercase.match_indices(word) {
+                for misspelling in *misspellings {
+                    let label = format!(
+                        "{}{misspelling}{}",
+                        &self.domain[..start],
+                        &self.domain[start + word.len()..]
+                    );
+
+                    if seen.insert(label.clone()) {
+                        labels.push(label);
+                    }
+                }
+            }
+        }
+
+        labels
+    }
+
+    /// Permutation method that increments and decrements each run of
+    /// digits of the FQDN, one run at a time (e.g. `office365.com` ->
+    /// `office364.com` and `office366.com`). Zero-padded runs keep their
+    /// width (e.g. `agent007.com` -> `agent006.com`).
+    pub fn number_step(&self) -> impl Iterator<Item = Permutation> + '_ {
+        self.number_step_in(&ValidationCtx::of(self))
+    }
+
+    fn number_step_in(&self, ctx: &ValidationCtx) -> impl Iterator<Item = Permutation> + '_ {
+        ctx.clone()
+            .parse_each(PermutationKind::NumberStep, self.number_steps().into_iter())
+    }
+
+    /// FQDNs with one run of digits incremented or decremented. Runs too
+    /// long to fit a `u64` are left untouched.
+    fn number_steps(&self) -> Vec<String> {
+        let mut fqdns = vec![];
+        let mut offset = 0;
+
+        while let Some(start) = self.fqdn[offset..]
+            .find(|c: char| c.is_ascii_digit())
+            .map(|found| offset + found)
+        {
+            let end = self.fqdn[start..]
+                .find(|c: char| !c.is_ascii_digit())
+                .map_or(self.fqdn.len(), |found| start + found);
+            let run = &self.fqdn[start..end];
+            let width = if run.starts_with('0') { run.len() } else { 0 };
+
+            if let Ok(number) = run.parse::<u64>() {
+                for stepped in [number.checked_sub(1), number.checked_add(1)]
+                    .into_iter()
+                    .flatten()
+                {
+                    fqdns.push(format!(
+                        "{}{stepped:0width$}{}",
+                        &self.fqdn[..start],
+                        &self.fqdn[end..]
+                    ));
+                }
+            }
+
+            offset = end;
+        }
+
+        fqdns
+    }
+
     /// Permutation method that inserts specific characters that are close to
     /// any character in the domain depending on the keyboard (e.g. `Q` next
     /// to `W` in qwerty keyboard layout.
     pub fn insertion(&self) -> impl Iterator<Item = Permutation> + '_ {
-        self.fqdn
+        self.insertion_in(&ValidationCtx::of(self))
+    }
+
+    fn insertion_in(&self, ctx: &ValidationCtx) -> impl Iterator<Item = Permutation> + '_ {
+        let layouts = self.keyboard_layouts();
+        let candidates = self
+            .fqdn
             .chars()
             .skip(1) // We don't want to insert at the beginning of the domain...
             .take(self.fqdn.len() - 2) // ...or at the end of the domain.
             .enumerate()
             .flat_map(move |(i, c)| {
-                KEYBOARD_LAYOUTS.iter().filter_map(move |layout| {
+                layouts.clone().into_iter().filter_map(move |layout| {
                     layout
                         .get(&c) // Option<&[char]>
                         .map(move |keyboard_chars| {
-                            keyboard_chars.chars().filter_map(move |keyboard_char| {
+                            keyboard_chars.chars().map(move |keyboard_char| {
                                 let mut permutation = self.fqdn.to_string();
-                                permutation.insert(i, keyboard_char);
-
-                                if let Ok(domain) = Domain::new(permutation.as_str()) {
-                                    Some(Permutation {
-                                        domain,
-                                        kind: PermutationKind::Insertion,
-                                    })
-                                } else {
-                                    None
-                                }
+                                permutation.insert(self.char_offset(i), keyboard_char);
+                                permutation
                             })
                         })
                 })
             })
-            .flatten()
+            .flatten();
+
+        ctx.clone()
+            .parse_each(PermutationKind::Insertion, candidates)
+    }
+
+    /// Permutation method that follows each character with a key adjacent
+    /// to it on the keyboard, as when two keys are struck at once (e.g.
+    /// `google.com` -> `googvle.com`). Unlike [`Domain::insertion`], the
+    /// adjacent key always comes right after the character it neighbours.
+    pub fn fat_finger(&self) -> impl Iterator<Item = Permutation> + '_ {
+        self.fat_finger_in(&ValidationCtx::of(self))
+    }
+
+    fn fat_finger_in(&self, ctx: &ValidationCtx) -> impl Iterator<Item = Permutation> + '_ {
+        let layouts = self.keyboard_layouts();
+        let candidates = self
+            .fqdn
+            .char_indices()
+            .take(self.fqdn.chars().count().saturating_sub(1)) // Nothing is struck past the end.
+            .flat_map(move |(i, c)| {
+                layouts.clone().into_iter().filter_map(move |layout| {
+                    layout.get(&c).map(move |keyboard_chars| {
+                        keyboard_chars.chars().map(move |keyboard_char| {
+                            let mut permutation = self.fqdn.to_string();
+                            permutation.insert(i + c.len_utf8(), keyboard_char);
+                            permutation
+                        })
+                    })
+                })
+            })
+            .flatten();
+
+        ctx.clone()
+            .parse_each(PermutationKind::FatFinger, candidates)
+    }
+
+    /// Permutation method that selectively removes a character from the domain.
+    pub fn omission(&self) -> impl Iterator<Item = Permutation> + '_ {
+        self.omission_in(&ValidationCtx::of(self))
+    }
+
+    fn omission_in(&self, ctx: &ValidationCtx) -> impl Iterator<Item = Permutation> + '_ {
+        ctx.clone().parse_each(
+            PermutationKind::Omission,
+            self.fqdn.char_indices().map(move |(i, _)| {
+                let mut permutation = self.fqdn.to_string();
+                permutation.remove(i);
+                permutation
+            }),
+        )
+    }
+
+    /// Permutation method that removes the dots of the FQDN, one at a
+    /// time (e.g. `mail.example.com` -> `mailexample.com`). Permutations
+    /// left without a known suffix (e.g. `mail.examplecom`) are skipped.
+    pub fn dot_omission(&self) -> impl Iterator<Item = Permutation> + '_ {
+        self.dot_omission_in(&ValidationCtx::of(self))
+    }
+
+    fn dot_omission_in(&self, ctx: &ValidationCtx) -> impl Iterator<Item = Permutation> + '_ {
+        ctx.clone().parse_each(
+            PermutationKind::DotOmission,
+            self.fqdn.match_indices('.').map(move |(i, _)| {
+                let mut permutation = self.fqdn.to_string();
+                permutation.remove(i);
+                permutation
+            }),
+        )
+    }
+
+    /// Permutation method that glues the TLD onto the root label, as is or
+    /// behind a hyphen, and appends the TLD again or a popular one (e.g.
+    /// `example.com` -> `examplecom.com` and `example-com.net`), a
+    /// phishing pattern neither [`Domain::addition`] nor [`Domain::tld`]
+    /// produces. Dots of multi-label suffixes are dropped or hyphenated
+    /// alike (e.g. `example.co.uk` -> `example-co-uk.com`).
+    pub fn tld_append(&self) -> impl Iterator<Item = Permutation> + '_ {
+        self.tld_append_in(&ValidationCtx::of(self))
+    }
+
+    fn tld_append_in(&self, ctx: &ValidationCtx) -> impl Iterator<Item = Permutation> + '_ {
+        ctx.clone()
+            .parse_each(PermutationKind::TldAppend, self.tld_appends().into_iter())
+    }
+
+    /// FQDNs with the TLD glued onto the root label, under the TLD of the
+    /// domain and then each popular TLD.
+    fn tld_appends(&self) -> Vec<String> {
+        let Some(subdomains) = self
+            .fqdn
+            .strip_suffix(&format!("{}.{}", self.domain, self.tld))
+        else {
+            return vec![];
+        };
+
+        let labels = [
+            format!("{}{}", self.domain, self.tld.replace('.', "")),
+            format!("{}-{}", self.domain, self.tld.replace('.', "-")),
+        ];
+        let tlds: Vec<&str> = std::iter::once(self.tld.as_str())
+            .chain(POPULAR_TLDS.into_iter().filter(|tld| *tld != self.tld))
+            .collect();
+
+        labels
+            .iter()
+            .flat_map(|label| {
+                tlds.iter()
+                    .map(move |tld| format!("{subdomains}{label}.{tld}"))
+            })
+            .collect()
     }
 
-    /// Permutation method that selectively removes a character from the domain.
-    pub fn omission(&self) -> impl Iterator<Item = Permutation> + '_ {
-        self.fqdn.chars().enumerate().filter_map(move |(i, _)| {
-            let mut permutation = self.fqdn.to_string();
-            permutation.remove(i);
+    /// Permutation method that swaps the dots of the FQDN for hyphens and
+    /// its hyphens for dots, one at a time (e.g. `shop.example.com` ->
+    /// `shop-example.com`), catching registrations impersonating a
+    /// subdomain. Permutations left without a known suffix are skipped.
+    pub fn dot_hyphen_swap(&self) -> impl Iterator<Item = Permutation> + '_ {
+        self.dot_hyphen_swap_in(&ValidationCtx::of(self))
+    }
 
-            if let Ok(domain) = Domain::new(permutation.as_str()) {
-                Some(Permutation {
-                    domain,
-                    kind: PermutationKind::Omission,
-                })
-            } else {
-                None
-            }
-        })
+    fn dot_hyphen_swap_in(&self, ctx: &ValidationCtx) -> impl Iterator<Item = Permutation> + '_ {
+        ctx.clone().parse_each(
+            PermutationKind::DotHyphenSwap,
+            self.fqdn
+                .match_indices(['.', '-'])
+                .map(move |(i, separator)| {
+                    let swapped = if separator == "." { "-" } else { "." };
+                    let mut permutation = self.fqdn.to_string();
+                    permutation.replace_range(i..=i, swapped);
+                    permutation
+                }),
+        )
     }
 
     /// Permutation method that repeats characters twice provided they are
     /// alphabetic characters (e.g. `google.com` -> `gooogle.com`).
     pub fn repetition(&self) -> impl Iterator<Item = Permutation> + '_ {
-        self.fqdn.chars().enumerate().filter_map(move |(i, c)| {
-            if c.is_alphabetic() {
-                let permutation = format!("{}{}{}", &self.fqdn[..=i], c, &self.fqdn[i + 1..]);
-
-                if let Ok(domain) = Domain::new(permutation.as_str()) {
-                    return Some(Permutation {
-                        domain,
-                        kind: PermutationKind::Repetition,
-                    });
-                }
-            }
+        self.repetition_charset(Charset::default())
+    }
 
-            None
-        })
+    /// Permutation method that repeats the characters of `charset` twice,
+    /// e.g. to also repeat digits (`no4.com` -> `no44.com`).
+    pub fn repetition_charset(&self, charset: Charset) -> impl Iterator<Item = Permutation> + '_ {
+        self.repetition_in(charset, &ValidationCtx::of(self))
+    }
+
+    fn repetition_in(
+        &self,
+        charset: Charset,
+        ctx: &ValidationCtx,
+    ) -> impl Iterator<Item = Permutation> + '_ {
+        ctx.clone().parse_each(
+            PermutationKind::Repetition,
+            self.fqdn
+                .char_indices()
+                .filter(move |(_, c)| charset.contains(*c))
+                .map(move |(i, c)| {
+                    let end = i + c.len_utf8();
+                    format!("{}{}{}", &self.fqdn[..end], c, &self.fqdn[end..])
+                }),
+        )
     }
 
     /// Permutation method similar to insertion, except that it replaces a given
     /// character with another character in proximity depending on keyboard layout.
     pub fn replacement(&self) -> impl Iterator<Item = Permutation> + '_ {
-        self.fqdn
+        self.replacement_in(&ValidationCtx::of(self))
+    }
+
+    fn replacement_in(&self, ctx: &ValidationCtx) -> impl Iterator<Item = Permutation> + '_ {
+        let layouts = self.keyboard_layouts();
+        let candidates = self
+            .fqdn
             .chars()
             .skip(1) // We don't want to insert at the beginning of the domain...
             .take(self.fqdn.len() - 2) // ...or at the end of the domain.
             .enumerate()
             .flat_map(move |(i, c)| {
-                KEYBOARD_LAYOUTS.iter().filter_map(move |layout| {
+                layouts.clone().into_iter().filter_map(move |layout| {
                     layout.get(&c).map(move |keyboard_chars| {
-                        keyboard_chars.chars().filter_map(move |keyboard_char| {
-                            let permutation = format!(
+                        keyboard_chars.chars().map(move |keyboard_char| {
+                            format!(
                                 "{}{}{}",
-                                &self.fqdn[..i],
+                                &self.fqdn[..self.char_offset(i)],
                                 keyboard_char,
-                                &self.fqdn[i + 1..]
-                            );
-
-                            if let Ok(domain) = Domain::new(permutation.as_str()) {
-                                Some(Permutation {
-                                    domain,
-                                    kind: PermutationKind::Replacement,
-                                })
-                            } else {
-                                None
-                            }
+                                &self.fqdn[self.char_offset(i + 1)..]
+                            )
                         })
                     })
                 })
             })
-            .flatten()
+            .flatten();
+
+        ctx.clone()
+            .parse_each(PermutationKind::Replacement, candidates)
     }
 
     pub fn subdomain(&self) -> impl Iterator<Item = Permutation> + '_ {
-        self.fqdn
-            .chars()
-            .take(self.fqdn.len() - 3)
-            .enumerate()
-            .tuple_windows()
-            .filter_map(move |((_, c1), (i2, c2))| {
-                if !['-', '.'].iter().all(|x| [c1, c2].contains(x)) {
-                    let permutation = format!("{}.{}", &self.fqdn[..i2], &self.fqdn[i2..]);
-
-                    if let Ok(domain) = Domain::new(permutation.as_str()) {
-                        return Some(Permutation {
-                            domain,
-                            kind: PermutationKind::Subdomain,
-                        });
-                    }
-                }
+        self.subdomain_in(&ValidationCtx::of(self))
+    }
 
-                None
-            })
+    fn subdomain_in(&self, ctx: &ValidationCtx) -> impl Iterator<Item = Permutation> + '_ {
+        ctx.clone().parse_each(
+            PermutationKind::Subdomain,
+            self.fqdn
+                .char_indices()
+                .take(self.fqdn.len() - 3)
+                .tuple_windows()
+                .filter(|((_, c1), (_, c2))| !['-', '.'].iter().all(|x| [*c1, *c2].contains(x)))
+                .map(move |(_, (i2, _))| format!("{}.{}", &self.fqdn[..i2], &self.fqdn[i2..])),
+        )
     }
 
     /// Permutation method that swaps out characters in the domain (e.g.
     /// `google.com` -> `goolge.com`).
     pub fn transposition(&self) -> impl Iterator<Item = Permutation> + '_ {
-        self.fqdn
-            .chars()
-            .enumerate()
-            .tuple_windows()
-            .filter_map(move |((i1, c1), (i2, c2))| {
-                if c1 != c2 {
-                    let permutation =
-                        format!("{}{}{}{}", &self.fqdn[..i1], c2, c1, &self.fqdn[i2 + 1..]);
-                    if let Ok(domain) = Domain::new(permutation.as_str()) {
-                        return Some(Permutation {
-                            domain,
-                            kind: PermutationKind::Transposition,
-                        });
-                    }
-                }
+        self.transposition_in(&ValidationCtx::of(self))
+    }
 
-                None
-            })
+    fn transposition_in(&self, ctx: &ValidationCtx) -> impl Iterator<Item = Permutation> + '_ {
+        ctx.clone().parse_each(
+            PermutationKind::Transposition,
+            self.fqdn
+                .char_indices()
+                .tuple_windows()
+                .filter(|((_, c1), (_, c2))| c1 != c2)
+                .map(move |((i1, c1), (i2, c2))| {
+                    format!(
+                        "{}{}{}{}",
+                        &self.fqdn[..i1],
+                        c2,
+                        c1,
+                        &self.fqdn[i2 + c2.len_utf8()..]
+                    )
+                }),
+        )
     }
 
     /// Permutation method that swaps vowels for other vowels (e.g.
     /// `google.com` -> `gougle.com`).
     pub fn vowel_swap(&self) -> impl Iterator<Item = Permutation> + '_ {
-        self.fqdn
-            .chars()
-            .enumerate()
-            .filter_map(move |(i, c)| {
-                if VOWELS.contains(&c.to_ascii_lowercase()) {
-                    Some(VOWELS.iter().filter_map(move |vowel| {
-                        let permutation =
-                            format!("{}{}{}", &self.fqdn[..i], vowel, &self.fqdn[i + 1..]);
-
-                        if *vowel == c {
-                            None
-                        } else if let Ok(domain) = Domain::new(permutation.as_str()) {
-                            Some(Permutation {
-                                domain,
-                                kind: PermutationKind::VowelSwap,
-                            })
-                        } else {
-                            None
-                        }
-                    }))
-                } else {
-                    None
-                }
-            })
-            .flatten()
+        self.vowel_swap_in(&ValidationCtx::of(self))
+    }
+
+    fn vowel_swap_in(&self, ctx: &ValidationCtx) -> impl Iterator<Item = Permutation> + '_ {
+        let candidates = self
+            .fqdn
+            .char_indices()
+            .filter(|(_, c)| VOWELS.contains(&c.to_ascii_lowercase()))
+            .flat_map(move |(i, c)| {
+                VOWELS
+                    .iter()
+                    .filter(move |vowel| **vowel != c)
+                    .map(move |vowel| {
+                        format!(
+                            "{}{}{}",
+                            &self.fqdn[..i],
+                            vowel,
+                            &self.fqdn[i + c.len_utf8()..]
+                        )
+                    })
+            });
+
+        ctx.clone()
+            .parse_each(PermutationKind::VowelSwap, candidates)
     }
 
     /// Permutation method that inserts every lowercase ascii character between
     /// two vowels.
     pub fn double_vowel_insertion(&self) -> impl Iterator<Item = Permutation> + '_ {
-        self.fqdn
+        self.double_vowel_insertion_in(&ValidationCtx::of(self))
+    }
+
+    fn double_vowel_insertion_in(
+        &self,
+        ctx: &ValidationCtx,
+    ) -> impl Iterator<Item = Permutation> + '_ {
+        let candidates = self
+            .fqdn
             .chars()
             .enumerate()
             .tuple_windows()
-            .filter_map(move |((i1, c1), (i2, c2))| {
-                if VOWELS.contains(&c1.to_ascii_lowercase())
+            .filter(|((_, c1), (_, c2))| {
+                VOWELS.contains(&c1.to_ascii_lowercase())
                     && VOWELS.contains(&c2.to_ascii_lowercase())
-                {
-                    Some(ASCII_LOWER.iter().filter_map(move |inserted| {
-                        let permutation =
-                            format!("{}{inserted}{}", &self.fqdn[..=i1], &self.fqdn[i2..]);
-
-                        if let Ok(domain) = Domain::new(permutation.as_str()) {
-                            Some(Permutation {
-                                domain,
-                                kind: PermutationKind::DoubleVowelInsertion,
-                            })
-                        } else {
-                            None
-                        }
-                    }))
-                } else {
-                    None
-                }
             })
-            .flatten()
+            .flat_map(move |((i1, _), (i2, _))| {
+                ASCII_LOWER.iter().map(move |inserted| {
+                    format!("{}{inserted}{}", &self.fqdn[..=i1], &self.fqdn[i2..])
+                })
+            });
+
+        ctx.clone()
+            .parse_each(PermutationKind::DoubleVowelInsertion, candidates)
     }
 
     /// Permutation mode that appends and prepends common keywords to the
@@ -561,73 +3588,545 @@ impl Domain {
     /// 3. Append keyword and dash (e.g. `foo.com` -> `foo-word.com`)
     /// 4. Append keyword and dash (e.g. `foo.com` -> `fooword.com`)
     pub fn keyword(&self) -> impl Iterator<Item = Permutation> + '_ {
-        KEYWORDS.iter().flat_map(move |keyword| {
-            vec![
-                format!("{}-{}.{}", &self.domain, keyword, &self.tld),
-                format!("{}{}.{}", &self.domain, keyword, &self.tld),
-                format!("{}-{}.{}", keyword, &self.domain, &self.tld),
-                format!("{}{}.{}", keyword, &self.domain, &self.tld),
-            ]
-            .into_iter()
-            .filter_map(move |fqdn| {
-                if let Ok(domain) = Domain::new(fqdn.as_str()) {
-                    return Some(Permutation {
-                        domain,
-                        kind: PermutationKind::Keyword,
-                    });
+        self.keyword_set(KeywordSet::Generic)
+    }
+
+    /// Permutation method that prepends and appends the keywords of `set`
+    /// to the domain, e.g. to target the vocabulary of an industry.
+    pub fn keyword_set(&self, set: KeywordSet) -> impl Iterator<Item = Permutation> + '_ {
+        self.keyword_forms(set, KeywordForms::default())
+    }
+
+    /// Permutation method that prepends and appends the generic keywords
+    /// of `locale` to the domain, e.g. `cuenta` for [`Locale::Es`].
+    pub fn keyword_locale(&self, locale: Locale) -> impl Iterator<Item = Permutation> + '_ {
+        self.keyword_list(
+            locale.keywords(),
+            KeywordForms::default(),
+            &ValidationCtx::of(self),
+        )
+    }
+
+    /// Permutation method that adds the keywords of `set` to the domain
+    /// in the given `forms` only, e.g. only as a hyphenated suffix.
+    pub fn keyword_forms(
+        &self,
+        set: KeywordSet,
+        forms: KeywordForms,
+    ) -> impl Iterator<Item = Permutation> + '_ {
+        self.keyword_list(set.keywords(), forms, &ValidationCtx::of(self))
+    }
+
+    fn keyword_list(
+        &self,
+        keywords: &'static [&'static str],
+        forms: KeywordForms,
+        ctx: &ValidationCtx,
+    ) -> impl Iterator<Item = Permutation> + '_ {
+        self.affix_in(keywords, forms, PermutationKind::Keyword, ctx)
+    }
+
+    /// Permutation method that adds each of the runtime-supplied `words`
+    /// to the domain in the given `forms`, e.g. an industry wordlist
+    /// loaded from a file. Words are trimmed and lowercased, and empty
+    /// ones are skipped.
+    pub fn dictionary<S: AsRef<str>>(
+        &self,
+        words: &[S],
+        forms: KeywordForms,
+    ) -> impl Iterator<Item = Permutation> + '_ {
+        let normalized: Vec<String> = words
+            .iter()
+            .map(|word| word.as_ref().trim().to_lowercase())
+            .filter(|word| !word.is_empty())
+            .collect();
+
+        self.affix_in(
+            normalized,
+            forms,
+            PermutationKind::Dictionary,
+            &ValidationCtx::of(self),
+        )
+    }
+
+    /// Add each of `words` to the domain in the given `forms`, labelling
+    /// the permutations with `kind`.
+    fn affix_in<'a, W>(
+        &'a self,
+        words: W,
+        forms: KeywordForms,
+        kind: PermutationKind,
+        ctx: &ValidationCtx,
+    ) -> impl Iterator<Item = Permutation> + 'a
+    where
+        W: IntoIterator + 'a,
+        W::Item: AsRef<str>,
+    {
+        // Labels split between each pair of consecutive tokens.
+        let splits: Vec<(&str, &str)> = if forms.infix {
+            self.tokenize()
+                .windows(2)
+                .map(|pair| (&self.domain[..pair[0].end()], &self.domain[pair[1].start..]))
+                .collect()
+        } else {
+            vec![]
+        };
+
+        let candidates = words.into_iter().flat_map(move |entry| {
+            let word = entry.as_ref();
+            let mut names = vec![];
+
+            if forms.suffix && forms.hyphenated {
+                names.push(format!("{}-{}", self.domain, word));
+            }
+            if forms.suffix && forms.concatenated {
+                names.push(format!("{}{}", self.domain, word));
+            }
+            if forms.prefix && forms.hyphenated {
+                names.push(format!("{}-{}", word, self.domain));
+            }
+            if forms.prefix && forms.concatenated {
+                names.push(format!("{}{}", word, self.domain));
+            }
+            for (left, right) in &splits {
+                if forms.hyphenated {
+                    names.push(format!("{left}-{word}-{right}"));
                 }
+                if forms.concatenated {
+                    names.push(format!("{left}{word}{right}"));
+                }
+            }
 
-                None
-            })
-        })
+            names
+                .into_iter()
+                .map(move |name| format!("{name}.{}", self.tld))
+        });
+
+        ctx.clone().parse_each(kind, candidates)
     }
 
     /// Permutation method that replaces all TLDs as variations of the
-    /// root domain passed.
+    /// root domain passed. The runtime suffix list is used if one is
+    /// installed (see [`crate::psl`]).
     pub fn tld(&self) -> impl Iterator<Item = Permutation> + '_ {
-        TLDS.iter().filter_map(move |tld| {
+        self.tld_set(&TldSet::All)
+    }
+
+    /// Permutation method that replaces the ccTLD with the suffixes of the
+    /// neighboring countries, according to the built-in mapping (see
+    /// [`TldSet::Neighbors`]), e.g. `example.de` -> `example.at`.
+    pub fn tld_neighbors(&self) -> impl Iterator<Item = Permutation> + '_ {
+        self.tld_set(&TldSet::Neighbors(CcTldNeighbors::builtin()))
+    }
+
+    /// Permutation method that replaces the TLD with each TLD in `set`,
+    /// e.g. to only swap in country-code TLDs.
+    pub fn tld_set(&self, set: &TldSet) -> impl Iterator<Item = Permutation> + '_ {
+        self.tld_excluding(set, &TldExclusions::default())
+    }
+
+    /// Permutation method that replaces the TLD with each TLD in `set`,
+    /// skipping the candidates matching `exclusions`, e.g. the base
+    /// domain itself.
+    pub fn tld_excluding(
+        &self,
+        set: &TldSet,
+        exclusions: &TldExclusions,
+    ) -> impl Iterator<Item = Permutation> + '_ {
+        self.tld_in(set, exclusions, &ValidationCtx::of(self))
+    }
+
+    /// TLD permutations of [`Domain::tld_excluding`] grouped into a single
+    /// record listing every TLD variant of the label, see [`TldGroup`].
+    /// Returns `None` if no TLD permutation is generated.
+    ///
+    /// Unlike [`TldGroup::group`], the label is the one of the domain even
+    /// for suffixes the PSL splits differently (e.g. `example.city.kobe.jp`).
+    pub fn tld_grouped(&self, set: &TldSet, exclusions: &TldExclusions) -> Option<TldGroup> {
+        let prefix = format!("{}.", self.domain);
+        let tlds: Vec<String> = self
+            .tld_excluding(set, exclusions)
+            .filter_map(|permutation| {
+                permutation
+                    .domain
+                    .fqdn
+                    .strip_prefix(&prefix)
+                    .map(String::from)
+            })
+            .collect();
+
+        (!tlds.is_empty()).then(|| TldGroup {
+            label: self.domain.clone(),
+            tlds,
+        })
+    }
+
+    fn tld_in(
+        &self,
+        set: &TldSet,
+        exclusions: &TldExclusions,
+        ctx: &ValidationCtx,
+    ) -> impl Iterator<Item = Permutation> + '_ {
+        let selected = set.clone();
+        let excluded = exclusions.clone();
+        let suffixes = ctx.clone();
+
+        let candidates = (0..ctx.tld_count()).filter_map(move |index| {
+            let tld = suffixes.tld_at(index)?;
+
+            if !selected.selects(&self.tld, tld) {
+                return None;
+            }
+
             let fqdn = format!("{}.{}", &self.domain, tld);
 
-            if let Ok(domain) = Domain::new(fqdn.as_str()) {
-                return Some(Permutation {
-                    domain,
-                    kind: PermutationKind::Tld,
-                });
+            if excluded.excludes(self, tld, &fqdn) {
+                return None;
             }
 
-            None
-        })
+            Some(fqdn)
+        });
+
+        ctx.clone().parse_each(PermutationKind::Tld, candidates)
     }
 
     /// Permutation method that maps one or more characters into another
     /// set of one or more characters that are similar, or easy to miss,
     /// such as `d` -> `cl`, `ck` -> `kk`.
     pub fn mapped(&self) -> impl Iterator<Item = Permutation> + '_ {
+        self.mapped_table(&MappedTable::builtin(), MappedMode::Every)
+    }
+
+    /// Permutation method that maps substrings of the domain into the
+    /// alternatives listed in `table`, e.g. to add substitutions observed
+    /// in the wild. The `mode` selects whether every occurrence of a
+    /// substring is substituted at once or one at a time.
+    pub fn mapped_table(
+        &self,
+        table: &MappedTable,
+        mode: MappedMode,
+    ) -> impl Iterator<Item = Permutation> + '_ {
+        self.substitute(
+            table,
+            mode,
+            PermutationKind::Mapped,
+            &ValidationCtx::of(self),
+        )
+        .into_iter()
+    }
+
+    /// Permutation method that substitutes character sequences commonly
+    /// confused by OCR engines (e.g. `rn` <-> `m`, `cl` <-> `d`), one
+    /// occurrence at a time and in both directions.
+    pub fn ocr_confusion(&self) -> impl Iterator<Item = Permutation> + '_ {
+        self.substitute(
+            &ocr_confusions(),
+            MappedMode::Single,
+            PermutationKind::OcrConfusion,
+            &ValidationCtx::of(self),
+        )
+        .into_iter()
+    }
+
+    /// Substitute the occurrences of each key of `table` with its
+    /// alternatives, yielding permutations of the given `kind`.
+    fn substitute(
+        &self,
+        table: &MappedTable,
+        mode: MappedMode,
+        kind: PermutationKind,
+        ctx: &ValidationCtx,
+    ) -> Vec<Permutation> {
         let mut results = vec![];
 
-        for (key, values) in MAPPED_VALUES.entries() {
-            if self.domain.contains(key) {
-                let parts = self.domain.split(key);
+        for (key, values) in &table.values {
+            if key.is_empty() {
+                continue;
+            }
+
+            let occurrences: Vec<usize> = self
+                .domain
+                .match_indices(key.as_str())
+                .map(|(offset, _)| offset)
+                .collect();
+            let every = matches!(mode, MappedMode::Every | MappedMode::Both);
+            let single =
+                mode == MappedMode::Single || (mode == MappedMode::Both && occurrences.len() > 1);
 
-                for mapped_value in *values {
-                    let result = format!(
-                        "{domain}.{tld}",
-                        domain = parts.clone().join(mapped_value),
-                        tld = self.tld
-                    );
+            for mapped_value in values {
+                let mut candidates = vec![];
 
-                    if let Ok(domain) = Domain::new(result.as_str()) {
+                if every && !occurrences.is_empty() {
+                    candidates.push(self.domain.replace(key.as_str(), mapped_value));
+                }
+
+                if single {
+                    candidates.extend(occurrences.iter().map(|offset| {
+                        format!(
+                            "{}{}{}",
+                            &self.domain[..*offset],
+                            mapped_value,
+                            &self.domain[offset + key.len()..]
+                        )
+                    }));
+                }
+
+                for candidate in candidates {
+                    let result = format!("{candidate}.{tld}", tld = self.tld);
+
+                    if let Ok(domain) = ctx.parse(result.as_str()) {
                         results.push(Permutation {
                             domain,
-                            kind: PermutationKind::Mapped,
+                            kind,
+                            substitutions: vec![],
                         });
                     }
                 }
             }
         }
 
-        results.into_iter()
+        results
+    }
+
+    /// Permutation method that transliterates the domain across scripts,
+    /// e.g. `пример.com` -> `primer.com` or `example.com` -> `ексампле.com`,
+    /// for brands whose users type transliterated names.
+    ///
+    /// Cyrillic and Greek domains are romanized, once per romanization
+    /// (e.g. `х` -> `kh`, `h` or `x`), whereas Latin domains are spelled in
+    /// Cyrillic and Greek, followed by the typos of each spelling on the
+    /// keyboard of its script (e.g. `шоп24.com` -> `щоп24.com`).
+    pub fn transliteration(&self) -> impl Iterator<Item = Permutation> + '_ {
+        self.transliteration_in(&ValidationCtx::of(self))
+    }
+
+    fn transliteration_in(&self, ctx: &ValidationCtx) -> impl Iterator<Item = Permutation> + '_ {
+        ctx.clone().parse_each(
+            PermutationKind::Transliteration,
+            self.transliterations()
+                .into_iter()
+                .chain(self.transliteration_typos())
+                .map(move |name| format!("{name}.{}", self.tld)),
+        )
+    }
+
+    /// Transliterations of the domain (without the TLD), deduplicated.
+    fn transliterations(&self) -> Vec<String> {
+        let name = self.domain.to_lowercase();
+        let mut names = vec![];
+
+        if let Some(table) = name.chars().find_map(|c| match Script::of(c) {
+            Script::Cyrillic => Some(&CYRILLIC_TO_LATIN),
+            Script::Greek => Some(&GREEK_TO_LATIN),
+            _ => None,
+        }) {
+            // Every character uses its n-th romanization, or its last one
+            // if it has fewer.
+            let systems = name
+                .chars()
+                .filter_map(|c| table.get(&c).map(|values| values.len()))
+                .max()
+                .unwrap_or_default();
+
+            for system in 0..systems {
+                names.push(
+                    name.chars()
+                        .map(|c| match table.get(&c) {
+                            Some(values) => values
+                                .get(system)
+                                .or_else(|| values.last())
+                                .map_or_else(String::new, ToString::to_string),
+                            None => c.to_string(),
+                        })
+                        .collect::<String>(),
+                );
+            }
+        } else {
+            names.extend(
+                [&LATIN_TO_CYRILLIC, &LATIN_TO_GREEK]
+                    .into_iter()
+                    .filter_map(|table| spell(&name, table)),
+            );
+        }
+
+        names.retain(|candidate| *candidate != name);
+        names.dedup();
+        names
+    }
+
+    /// Keyboard typos of the transliterations of the domain (without the
+    /// TLD) into a non-Latin script, substituting a single character with
+    /// a key adjacent to it in the layout of that script, deduplicated.
+    fn transliteration_typos(&self) -> Vec<String> {
+        let names = self.transliterations();
+        let mut seen: HashSet<String> = names.iter().cloned().collect();
+        let mut typos = vec![];
+
+        for name in &names {
+            let chars: Vec<char> = name.chars().collect();
+
+            for layout in script_layouts(name) {
+                for (i, c) in chars.iter().enumerate() {
+                    for key in layout.get(c).into_iter().flat_map(|keys| keys.chars()) {
+                        let mut substituted = chars.clone();
+                        substituted[i] = key;
+                        let typo: String = substituted.into_iter().collect();
+
+                        if seen.insert(typo.clone()) {
+                            typos.push(typo);
+                        }
+                    }
+                }
+            }
+        }
+
+        typos
+    }
+}
+
+/// Wrap `permutations` (or any other iterator) into an iterator keeping
+/// track of how many items it yielded against `total_estimate`. The
+/// [`Progress`](crate::progress::Progress) handle it exposes reports the
+/// percentage, rate and ETA, and can be polled from other threads or
+/// tasks, e.g. while the permutations are enriched.
+///
+/// ```
+/// use twistrs::permutate::{self, Domain};
+///
+/// let domain = Domain::new("example.com").unwrap();
+/// let permutations = permutate::with_progress(domain.addition(), 26);
+/// let progress = permutations.progress();
+///
+/// assert_eq!(permutations.count(), 26);
+/// assert_eq!(progress.percent(), 100);
+/// ```
+pub fn with_progress<I>(permutations: I, total_estimate: usize) -> ProgressIter<I::IntoIter>
+where
+    I: IntoIterator,
+{
+    ProgressIter::new(permutations.into_iter(), total_estimate)
+}
+
+/// Whether `candidate` is a homoglyph of `base`, i.e. whether they only
+/// differ by characters the built-in homoglyph table confuses, directly
+/// or through another confusion (e.g. `c` to `e` to `é`) as done by the
+/// homoglyph permutation. Unlike the homoglyph permutation, nothing is
+/// generated, making the check cheap enough to triage inbound domains
+/// one by one.
+///
+/// Candidates may be given in Unicode or punycode form, and identical
+/// domains are not considered homoglyphs of one another.
+///
+/// ```
+/// use twistrs::permutate::{self, Domain};
+///
+/// let base = Domain::new("apple.com").unwrap();
+///
+/// assert!(permutate::is_homoglyph_of(&base, &Domain::new("àpple.com").unwrap()));
+/// assert!(permutate::is_homoglyph_of(&base, &Domain::new("xn--pple-zna.com").unwrap()));
+/// assert!(!permutate::is_homoglyph_of(&base, &Domain::new("apples.com").unwrap()));
+/// ```
+pub fn is_homoglyph_of(base: &Domain, candidate: &Domain) -> bool {
+    !confusable_chars(base, candidate).is_empty()
+}
+
+/// Characters of `candidate` confused with those of `base`, empty unless
+/// `candidate` is a homoglyph of `base` (see [`is_homoglyph_of`]).
+pub fn confusable_chars(base: &Domain, candidate: &Domain) -> Vec<Confusable> {
+    confusables(base, candidate, |c| HOMOGLYPHS.get(&c).copied())
+}
+
+/// Compare the skeletons of `base` and `candidate`, that is their Unicode
+/// forms with every character folded onto the one of `base` it is
+/// confused with according to `glyphs`, in at most two steps.
+fn confusables<'a, F>(base: &Domain, candidate: &Domain, glyphs: F) -> Vec<Confusable>
+where
+    F: Fn(char) -> Option<&'a str>,
+{
+    let unicode = |domain: &Domain| idna::domain_to_unicode(&domain.fqdn).0.to_lowercase();
+    let (base_fqdn, candidate_fqdn) = (unicode(base), unicode(candidate));
+
+    if base_fqdn.chars().count() != candidate_fqdn.chars().count() {
+        return vec![];
+    }
+
+    let mut confused = vec![];
+
+    for (position, (expected, glyph)) in base_fqdn.chars().zip(candidate_fqdn.chars()).enumerate() {
+        if expected == glyph {
+            continue;
+        }
+
+        let confused_with = |c| glyphs(c).is_some_and(|confusions| confusions.contains(glyph));
+        let confusions = glyphs(expected).unwrap_or_default();
+
+        if !confused_with(expected) && !confusions.chars().any(confused_with) {
+            return vec![];
+        }
+
+        confused.push(Confusable {
+            position,
+            base: expected,
+            glyph,
+        });
     }
+
+    confused
+}
+
+/// The OCR confusions as a table, keyed in both directions.
+fn ocr_confusions() -> MappedTable {
+    let mut table = MappedTable::empty();
+
+    for (left, right) in OCR_CONFUSIONS {
+        table.insert(left, &[right]);
+        table.insert(right, &[left]);
+    }
+
+    table
+}
+
+/// Keyboard layouts of the non-Latin scripts `text` is written in.
+fn script_layouts(text: &str) -> Vec<&'static phf::Map<char, &'static str>> {
+    [
+        (Script::Cyrillic, &RUSSIAN_KEYBOARD_LAYOUT),
+        (Script::Greek, &GREEK_KEYBOARD_LAYOUT),
+        (Script::Arabic, &ARABIC_KEYBOARD_LAYOUT),
+    ]
+    .into_iter()
+    .filter(|(script, _)| text.chars().any(|c| Script::of(c) == *script))
+    .map(|(_, layout)| layout)
+    .collect()
+}
+
+/// Spell the Latin `name` using `table`, matching the longest keys first.
+/// Returns `None` if a letter cannot be spelled, since the result would mix
+/// scripts.
+fn spell(name: &str, table: &phf::Map<&'static str, &'static str>) -> Option<String> {
+    let longest = table.keys().map(|key| key.len()).max().unwrap_or_default();
+    let mut spelled = String::new();
+    let mut rest = name;
+
+    while let Some(c) = rest.chars().next() {
+        let matched = (1..=longest.min(rest.len()))
+            .rev()
+            .filter_map(|len| rest.get(..len))
+            .find_map(|prefix| table.get(prefix).map(|value| (prefix.len(), *value)));
+
+        match matched {
+            Some((len, value)) => {
+                spelled.push_str(value);
+                rest = &rest[len..];
+            }
+            None if c.is_ascii_digit() || c == '-' || c == '.' => {
+                spelled.push(c);
+                rest = &rest[c.len_utf8()..];
+            }
+            None => return None,
+        }
+    }
+
+    Some(spelled)
 }
 
 #[cfg(test)]
@@ -642,6 +4141,48 @@ mod tests {
         assert!(!permutations.is_empty());
     }
 
+    #[test]
+    fn test_permutation_engine_kinds_and_progress() {
+        use std::sync::atomic::{AtomicUsize, Ordering};
+        use std::sync::Arc;
+
+        let d = Domain::new("www.example.com").unwrap();
+        let reported = Arc::new(AtomicUsize::new(0));
+        let reported_clone = Arc::clone(&reported);
+
+        let mut engine = PermutationEngine::new();
+        engine.kinds = vec![PermutationKind::Addition, PermutationKind::Omission];
+        engine.on_progress(move |done, total_estimate, phase| {
+            assert_eq!(phase, Phase::Generation);
+            assert!(total_estimate > 0);
+            reported_clone.store(done, Ordering::SeqCst);
+        });
+
+        let permutations: Vec<_> = engine.generate(&d).unwrap().collect();
+
+        assert!(permutations.iter().all(|p| engine.kinds.contains(&p.kind)));
+        assert_eq!(reported.load(Ordering::SeqCst), permutations.len());
+        assert_eq!(engine.estimate(&d), ASCII_LOWER.len() + d.fqdn.len());
+    }
+
+    #[test]
+    fn test_permutation_kind_from_str() {
+        assert_eq!(
+            "vowel_swap".parse::<PermutationKind>().unwrap(),
+            PermutationKind::VowelSwap
+        );
+        assert_eq!(
+            "TLD".parse::<PermutationKind>().unwrap(),
+            PermutationKind::Tld
+        );
+        assert!(matches!(
+            "nope".parse::<PermutationKind>(),
+            Err(Error::PermutationError(
+                PermutationError::UnknownKind { .. }
+            ))
+        ));
+    }
+
     #[test]
     fn test_addition_mode() {
         let d = Domain::new("www.example.com").unwrap();
@@ -666,6 +4207,194 @@ mod tests {
         assert!(!permutations.is_empty());
     }
 
+    #[test]
+    fn test_is_homoglyph_of() {
+        let base = Domain::new("paypal.com").unwrap();
+        let homoglyph = |fqdn: &str| is_homoglyph_of(&base, &Domain::new(fqdn).unwrap());
+
+        assert!(homoglyph("pàypal.com"));
+        assert!(homoglyph("paypa1.com"));
+        assert!(homoglyph("PAYPA1.com"));
+        assert!(!homoglyph("paypal.com"));
+        assert!(!homoglyph("paypals.com"));
+        assert!(!homoglyph("paypax.com"));
+
+        let confused = confusable_chars(&base, &Domain::new("pàypa1.com").unwrap());
+        assert_eq!(
+            confused,
+            vec![
+                Confusable {
+                    position: 1,
+                    base: 'a',
+                    glyph: 'à',
+                },
+                Confusable {
+                    position: 5,
+                    base: 'l',
+                    glyph: '1',
+                },
+            ]
+        );
+
+        // Every homoglyph permutation passes the check.
+        let d = Domain::new("abc.com").unwrap();
+        assert!(d
+            .homoglyph()
+            .unwrap()
+            .filter(|p| p.domain != d)
+            .all(|p| is_homoglyph_of(&d, &p.domain)));
+
+        let mut cyrillic = HomoglyphTable::empty();
+        cyrillic.insert('a', "а");
+        let candidate = Domain::new("pаypal.com").unwrap();
+        assert_eq!(cyrillic.confusable_chars(&base, &candidate).len(), 1);
+        assert!(!is_homoglyph_of(&base, &candidate));
+    }
+
+    #[test]
+    fn test_homoglyph_table_mode() {
+        let d = Domain::new("example.com").unwrap();
+
+        // Restrict the permutation to Cyrillic lookalikes only.
+        let mut cyrillic = HomoglyphTable::empty();
+        cyrillic.insert('a', "а");
+        cyrillic.insert('e', "е");
+
+        let permutations: HashSet<String> = d
+            .homoglyph_table(&cyrillic)
+            .unwrap()
+            .map(|p| p.domain.fqdn)
+            .collect();
+
+        assert!(permutations.contains("exаmple.com"));
+        assert!(permutations.contains("еxample.com"));
+        assert!(permutations
+            .iter()
+            .all(|fqdn| fqdn.chars().all(|c| c.is_ascii() || "ае".contains(c))));
+
+        let mut merged = HomoglyphTable::builtin();
+        merged.merge(&cyrillic);
+        assert!(merged.get('a').unwrap().starts_with('à'));
+        assert!(merged.get('a').unwrap().ends_with('а'));
+
+        let mut engine = PermutationEngine::new();
+        engine.kinds = vec![PermutationKind::Homoglyph];
+        engine.homoglyphs = cyrillic;
+        assert_eq!(engine.generate(&d).unwrap().count(), permutations.len());
+    }
+
+    #[test]
+    fn test_homoglyph_options() {
+        let d = Domain::new("apple.com").unwrap();
+        let mut table = HomoglyphTable::builtin();
+        table.merge(&HomoglyphTable::cross_script());
+
+        let fqdns = |options: &HomoglyphOptions| -> HashSet<String> {
+            d.homoglyph_with(&table, options)
+                .unwrap()
+                .map(|p| p.domain.fqdn)
+                .collect()
+        };
+        let label_scripts = |fqdn: &str| -> HashSet<Script> {
+            fqdn.split('.')
+                .next()
+                .unwrap()
+                .chars()
+                .map(Script::of)
+                .collect()
+        };
+
+        let mixed = fqdns(&HomoglyphOptions {
+            single_script: false,
+            ..HomoglyphOptions::script(Script::Cyrillic)
+        });
+        assert!(mixed.contains("аpple.com"));
+        assert!(mixed.iter().all(|fqdn| fqdn
+            .chars()
+            .all(|c| c.is_ascii() || Script::of(c) == Script::Cyrillic)));
+
+        // Only the root label spelled entirely in Cyrillic is registrable.
+        let cyrillic = fqdns(&HomoglyphOptions::script(Script::Cyrillic));
+        assert_eq!(cyrillic, HashSet::from(["арр\u{04CF}е.com".to_string()]));
+        assert!(fqdns(&HomoglyphOptions::script(Script::Greek)).is_empty());
+
+        let diacritics = fqdns(&HomoglyphOptions::latin_diacritics());
+        assert!(diacritics.contains("àpple.com"));
+        assert!(!diacritics.contains("app1e.com"));
+        assert!(diacritics
+            .iter()
+            .all(|fqdn| label_scripts(fqdn) == HashSet::from([Script::Latin])));
+
+        let mut engine = PermutationEngine::new();
+        engine.kinds = vec![PermutationKind::Homoglyph];
+        engine.homoglyph_options = HomoglyphOptions::latin_diacritics();
+        assert_eq!(engine.generate(&d).unwrap().count(), diacritics.len());
+        assert!(engine.estimate(&d) < PermutationEngine::new().estimate(&d));
+    }
+
+    #[test]
+    fn test_homoglyph_substitutions() {
+        let d = Domain::new("apple.com").unwrap();
+        let mut cyrillic = HomoglyphTable::empty();
+        cyrillic.insert('a', "а");
+        cyrillic.insert('e', "е");
+
+        let permutation = d
+            .homoglyph_table(&cyrillic)
+            .unwrap()
+            .find(|p| p.domain.fqdn == "аpplе.com")
+            .unwrap();
+
+        let substitutions = |p: &Permutation| {
+            p.substitutions
+                .iter()
+                .map(|s| (s.position, s.original, s.replacement))
+                .collect::<Vec<_>>()
+        };
+        assert_eq!(
+            substitutions(&permutation),
+            vec![(0, 'a', 'а'), (4, 'e', 'е')]
+        );
+        assert!(permutation
+            .substitutions
+            .iter()
+            .all(|s| s.script == Script::Cyrillic));
+
+        assert_eq!(Script::of('ạ'), Script::Latin);
+        assert_eq!(Script::of('1'), Script::Common);
+
+        // Swapped characters are not substituted ones.
+        let transposed = d
+            .transposition()
+            .find(|p| p.domain.fqdn == "aplpe.com")
+            .unwrap();
+        assert!(transposed.substitutions.is_empty());
+        assert!(d.omission().all(|p| p.substitutions.is_empty()));
+
+        // IDN homographs are yielded in punycode, and their substitutions
+        // are positioned within the Unicode form.
+        let www = Domain::new("www.apple.com").unwrap();
+        let homograph = www
+            .idn_homograph()
+            .find(|p| p.domain.fqdn == "www.xn--pple-43d.com")
+            .unwrap();
+        assert_eq!(substitutions(&homograph), vec![(4, 'a', 'а')]);
+        assert_eq!(homograph.substitutions[0].script, Script::Cyrillic);
+
+        let whole = www
+            .idn_homograph()
+            .find(|p| p.substitutions.len() == 5)
+            .unwrap();
+        assert_eq!(
+            whole
+                .substitutions
+                .iter()
+                .map(|s| s.position)
+                .collect::<Vec<_>>(),
+            vec![4, 5, 6, 7, 8]
+        );
+    }
+
     #[test]
     fn test_hyphenation_mode() {
         let d = Domain::new("www.example.com").unwrap();
@@ -682,6 +4411,18 @@ mod tests {
         assert!(!permutations.is_empty());
     }
 
+    #[test]
+    fn test_fat_finger_mode() {
+        let d = Domain::new("google.com").unwrap();
+        let fqdns: Vec<String> = d.fat_finger().map(|p| p.domain.fqdn).collect();
+
+        assert!(fqdns.contains(&"googvle.com".to_string()));
+        assert!(fqdns.contains(&"gtoogle.com".to_string()));
+        assert!(!fqdns.contains(&"ygoogle.com".to_string()));
+        assert!(fqdns.len() <= d.estimate(PermutationKind::FatFinger));
+        assert!(d.fat_finger().all(|p| p.kind == PermutationKind::FatFinger));
+    }
+
     #[test]
     fn test_omission_mode() {
         let d = Domain::new("www.example.com").unwrap();
@@ -698,6 +4439,33 @@ mod tests {
         assert!(!permutations.is_empty());
     }
 
+    #[test]
+    fn test_charset_mode() {
+        let charset = Charset {
+            digits: true,
+            ..Charset::default()
+        };
+
+        let google = Domain::new("google.com").unwrap();
+        let additions: Vec<_> = google
+            .addition_charset(charset)
+            .map(|p| p.domain.fqdn)
+            .collect();
+        assert!(additions.contains(&"google1.com".to_string()));
+        assert_eq!(additions.len(), ASCII_LOWER.len() + 10);
+
+        let no4 = Domain::new("no4.com").unwrap();
+        assert!(!no4.repetition().any(|p| p.domain.fqdn == "no44.com"));
+        assert!(no4
+            .repetition_charset(charset)
+            .any(|p| p.domain.fqdn == "no44.com"));
+
+        let mut engine = PermutationEngine::new();
+        engine.kinds = vec![PermutationKind::Addition, PermutationKind::Repetition];
+        engine.charset = charset;
+        assert_eq!(engine.estimate(&no4), 36 + 6);
+    }
+
     #[test]
     fn test_replacement_mode() {
         let d = Domain::new("www.example.com").unwrap();
@@ -730,6 +4498,463 @@ mod tests {
         assert!(!permutations.is_empty());
     }
 
+    #[cfg(feature = "arbitrary")]
+    #[test]
+    fn test_arbitrary_domains() {
+        use arbitrary::{Arbitrary, Unstructured};
+
+        // Deterministic inputs, the fuzz targets in `fuzz/` explore further.
+        let mut generated = 0;
+        for seed in 0..256_u32 {
+            let bytes = Sha256::digest(seed.to_le_bytes());
+
+            let Ok(domain) = Domain::arbitrary(&mut Unstructured::new(&bytes)) else {
+                continue;
+            };
+
+            for kind in PermutationKind::ALL {
+                // Homoglyphs are generated eagerly, in quadratic time.
+                if kind == PermutationKind::Homoglyph && domain.fqdn.len() > 12 {
+                    continue;
+                }
+
+                if let Ok(permutations) = domain.permutations(kind) {
+                    permutations.take(200).for_each(drop);
+                }
+            }
+
+            generated += 1;
+        }
+
+        assert!(generated > 0);
+    }
+
+    #[test]
+    fn test_token_reorder_mode() {
+        let d = Domain::new("bankofamerica.com").unwrap();
+        let permutations: Vec<String> = d.token_reorder().map(|p| p.domain.fqdn).collect();
+
+        assert!(permutations.contains(&"americabank.com".to_string()));
+        assert!(permutations.contains(&"ofamericabank.com".to_string()));
+        assert!(!permutations.contains(&"bankofamerica.com".to_string()));
+        assert_eq!(permutations.iter().unique().count(), permutations.len());
+        assert_eq!(
+            d.estimate(PermutationKind::TokenReorder),
+            permutations.len()
+        );
+
+        assert_eq!(
+            Domain::new("google.com").unwrap().token_reorder().count(),
+            0
+        );
+    }
+
+    #[test]
+    #[cfg(feature = "phonetic")]
+    fn test_homophone_mode() {
+        let d = Domain::new("www.forsale.com").unwrap();
+        let permutations: Vec<String> = d.homophone().map(|p| p.domain.fqdn).collect();
+
+        assert!(permutations.contains(&"www.foursale.com".to_string()));
+        assert!(permutations.contains(&"www.forsail.com".to_string()));
+        assert_eq!(permutations.iter().unique().count(), permutations.len());
+        assert_eq!(d.estimate(PermutationKind::Homophone), permutations.len());
+
+        let hyphenated: Vec<String> = Domain::new("right-to-buy.com")
+            .unwrap()
+            .homophone()
+            .map(|p| p.domain.fqdn)
+            .collect();
+        assert!(hyphenated.contains(&"write-to-buy.com".to_string()));
+        assert!(hyphenated.contains(&"right-two-buy.com".to_string()));
+        assert!(hyphenated.contains(&"right-to-by.com".to_string()));
+
+        assert_eq!(Domain::new("google.com").unwrap().homophone().count(), 0);
+    }
+
+    /// Generate the permutations of `kind` for `fqdn`, asserting that they
+    /// are all of that kind and that the estimate is `estimate`.
+    fn generated(kind: PermutationKind, fqdn: &str, estimate: usize) -> Vec<String> {
+        let d = Domain::new(fqdn).unwrap();
+        let permutations: Vec<Permutation> = d.permutations(kind).unwrap().collect();
+
+        assert!(
+            permutations.iter().all(|p| p.kind == kind),
+            "{kind:?} of {fqdn}"
+        );
+        assert_eq!(d.estimate(kind), estimate, "{kind:?} of {fqdn}");
+        assert!(permutations.len() <= estimate, "{kind:?} of {fqdn}");
+
+        permutations.into_iter().map(|p| p.domain.fqdn).collect()
+    }
+
+    #[test]
+    fn test_generator_modes() {
+        use PermutationKind::*;
+
+        // Kind, domain, estimate and the permutations expected, in order.
+        // Dot omission and swap estimates also count the dots of the TLD,
+        // whose candidates do not parse.
+        let cases: &[(PermutationKind, &str, usize, &[&str])] = &[
+            (
+                NumberStep,
+                "office365.com",
+                2,
+                &["office364.com", "office366.com"],
+            ),
+            (
+                NumberStep,
+                "web2.shop10.com",
+                4,
+                &[
+                    "web1.shop10.com",
+                    "web3.shop10.com",
+                    "web2.shop9.com",
+                    "web2.shop11.com",
+                ],
+            ),
+            (
+                NumberStep,
+                "agent007.com",
+                2,
+                &["agent006.com", "agent008.com"],
+            ),
+            (NumberStep, "0day.com", 1, &["1day.com"]),
+            (NumberStep, "example.com", 0, &[]),
+            (Spelling, "4chan.com", 1, &["fourchan.com"]),
+            (Spelling, "onepassword.com", 1, &["1password.com"]),
+            (
+                Spelling,
+                "7-eleven.com",
+                2,
+                &["seven-eleven.com", "7-11.com"],
+            ),
+            (Spelling, "office365.com", 0, &[]),
+            (Spelling, "google.com", 0, &[]),
+            (HyphenRemoval, "my-brand.com", 1, &["mybrand.com"]),
+            (
+                HyphenRemoval,
+                "www.my-brand-name.co.uk",
+                3,
+                &[
+                    "www.mybrand-name.co.uk",
+                    "www.my-brandname.co.uk",
+                    "www.mybrandname.co.uk",
+                ],
+            ),
+            // Either hyphen of the `xn--` prefix yields the same candidate.
+            (
+                HyphenRemoval,
+                "xn--mnchen-3ya.de",
+                3,
+                &["xn-mnchen-3ya.de", "xn--mnchen3ya.de", "xnmnchen3ya.de"],
+            ),
+            (HyphenRemoval, "mybrand.com", 0, &[]),
+            (
+                SeparatorVariation,
+                "bankofamerica.com",
+                6,
+                &[
+                    "bank-ofamerica.com",
+                    "bankof-america.com",
+                    "bank-of-america.com",
+                    "bank.ofamerica.com",
+                    "bankof.america.com",
+                    "bank.of.america.com",
+                ],
+            ),
+            // Existing hyphens are only removed by hyphen removal.
+            (SeparatorVariation, "bank-of-america.com", 0, &[]),
+            (DotOmission, "www.example.com", 2, &["wwwexample.com"]),
+            (
+                DotOmission,
+                "mail.example.co.uk",
+                3,
+                &["mailexample.co.uk", "mail.exampleco.uk"],
+            ),
+            (DotOmission, "example.com", 1, &[]),
+            (DotHyphenSwap, "shop.example.com", 2, &["shop-example.com"]),
+            (
+                DotHyphenSwap,
+                "my-shop.example.com",
+                3,
+                &["my.shop.example.com", "my-shop-example.com"],
+            ),
+            (DotHyphenSwap, "my-shop.com", 2, &["my.shop.com"]),
+            (DotHyphenSwap, "example.com", 1, &[]),
+            (
+                WrongLayout,
+                "amazon.com",
+                3,
+                &["amayon.com", "qmqwon.com", "qmqyon.com"],
+            ),
+            (
+                WrongLayout,
+                "www.lazy.com",
+                4,
+                &[
+                    "www.layz.com",
+                    "www.lqwy.com",
+                    "www.lqyw.com",
+                    "www.lqwz.com",
+                ],
+            ),
+            // Labels typed alike on every layout have no permutations.
+            (WrongLayout, "dnc.com", 0, &[]),
+            (
+                VowelRemoval,
+                "facebook.com",
+                4,
+                &["fcebook.com", "facbook.com", "facebok.com", "fcbk.com"],
+            ),
+            (VowelRemoval, "www.shop.co.uk", 1, &["www.shp.co.uk"]),
+            (VowelRemoval, "io.com", 2, &["o.com", "i.com"]),
+            (VowelRemoval, "xyz.com", 0, &[]),
+            (Misspelling, "address.com", 2, &["adress.com", "addres.com"]),
+            (
+                Misspelling,
+                "www.receivepay.com",
+                1,
+                &["www.recievepay.com"],
+            ),
+            (
+                Misspelling,
+                "successful.com",
+                3,
+                &["sucessful.com", "succesful.com", "successfull.com"],
+            ),
+            (
+                Misspelling,
+                "address-until.com",
+                3,
+                &["adress-until.com", "addres-until.com", "address-untill.com"],
+            ),
+            (Misspelling, "google.com", 0, &[]),
+            (Misspelling, "Address.com", 2, &["adress.com", "addres.com"]),
+            (
+                Misspelling,
+                "www.ReceivePay.com",
+                1,
+                &["www.recievePay.com"],
+            ),
+        ];
+
+        for (kind, fqdn, estimate, expected) in cases {
+            assert_eq!(
+                generated(*kind, fqdn, *estimate),
+                *expected,
+                "{kind:?} of {fqdn}"
+            );
+        }
+    }
+
+    #[test]
+    fn test_tld_append_mode() {
+        let appended = generated(
+            PermutationKind::TldAppend,
+            "example.com",
+            2 * POPULAR_TLDS.len(),
+        );
+        assert_eq!(appended[..2], ["examplecom.com", "examplecom.net"]);
+        assert!(appended.contains(&"example-com.net".to_string()));
+        assert_eq!(appended.len(), 2 * POPULAR_TLDS.len());
+
+        let nested = generated(
+            PermutationKind::TldAppend,
+            "www.example.co.uk",
+            2 * (POPULAR_TLDS.len() + 1),
+        );
+        assert_eq!(nested[0], "www.examplecouk.co.uk");
+        assert!(nested.contains(&"www.example-co-uk.com".to_string()));
+
+        assert_eq!(
+            generated(
+                PermutationKind::TldAppend,
+                "example.de",
+                2 * (POPULAR_TLDS.len() + 1)
+            )
+            .len(),
+            2 * (POPULAR_TLDS.len() + 1)
+        );
+    }
+
+    #[test]
+    fn test_idn_homograph_mode() {
+        let d = Domain::new("apple.com").unwrap();
+        let permutations: Vec<Permutation> = d.idn_homograph().collect();
+        let fqdns: Vec<&str> = permutations
+            .iter()
+            .map(|p| p.domain.fqdn.as_str())
+            .collect();
+
+        assert!(fqdns.contains(&"xn--pple-43d.com"));
+        assert!(permutations.iter().all(|p| p.domain.fqdn.is_ascii()
+            && p.domain.domain.starts_with("xn--")
+            && p.domain.tld == "com"));
+
+        let unicode: HashSet<String> = fqdns
+            .iter()
+            .map(|fqdn| idna::domain_to_unicode(fqdn).0)
+            .collect();
+        assert!(unicode.contains("аpple.com"));
+        assert!(unicode.contains("аррӏе.com"));
+        assert!(unicode.contains("αpple.com"));
+        assert!(unicode.contains("appӏe.com"));
+        assert_eq!(unicode.len(), fqdns.len());
+
+        assert!(permutations.len() <= d.estimate(PermutationKind::IdnHomograph));
+
+        // Punycode root labels are substituted in their Unicode form.
+        let idn = Domain::new("xn--mnchen-3ya.de").unwrap();
+        let decoded: Vec<String> = idn
+            .idn_homograph()
+            .map(|p| idna::domain_to_unicode(&p.domain.fqdn).0)
+            .collect();
+        assert!(decoded.contains(&"münсhen.de".to_string()));
+        assert!(decoded
+            .iter()
+            .all(|fqdn| fqdn.contains('ü') && !fqdn.starts_with("xn") && fqdn.ends_with(".de")));
+
+        // Labels too short for the reserved positions are substituted too.
+        let short: Vec<String> = Domain::new("go.com")
+            .unwrap()
+            .idn_homograph()
+            .map(|p| idna::domain_to_unicode(&p.domain.fqdn).0)
+            .collect();
+        assert!(short.contains(&"gο.com".to_string()));
+
+        assert!(Domain::new("123.com")
+            .unwrap()
+            .idn_homograph()
+            .next()
+            .is_none());
+    }
+
+    #[test]
+    fn test_token_permutations() {
+        let d = Domain::new("www.unitedhealthgroup.com").unwrap();
+
+        let hyphenated: Vec<String> = d.token_hyphenation().map(|p| p.domain.fqdn).collect();
+        assert_eq!(
+            hyphenated,
+            vec![
+                "www.united-healthgroup.com",
+                "www.unitedhealth-group.com",
+                "www.united-health-group.com",
+            ]
+        );
+
+        let tokens = d.tokenize();
+        let omissions: Vec<String> = d
+            .token_permutations(&tokens[1], PermutationKind::Omission)
+            .unwrap()
+            .map(|p| p.domain.fqdn)
+            .collect();
+        assert!(omissions.contains(&"www.unitedhealhgroup.com".to_string()));
+        assert!(omissions
+            .iter()
+            .all(|fqdn| fqdn.starts_with("www.united") && fqdn.ends_with("group.com")));
+
+        let idn = Domain::new("xn--mnchen-3ya.de").unwrap();
+        assert!(idn.tokenize().is_empty());
+        assert_eq!(idn.separator_variation().count(), 0);
+        assert_eq!(idn.estimate(PermutationKind::SeparatorVariation), 0);
+
+        let foreign = Domain::new("example.com").unwrap().tokenize();
+        assert!(d
+            .token_permutations(&foreign[0], PermutationKind::Omission)
+            .is_err());
+    }
+
+    #[test]
+    fn test_engine_config() {
+        use crate::filter::{AbusedTlds, FilterDescriptor};
+
+        let domain = Domain::new("example.com").unwrap();
+
+        let mut engine = PermutationEngine::new();
+        engine.kinds = vec![PermutationKind::Tld, PermutationKind::Addition];
+        engine.set_filter(AbusedTlds);
+
+        let config = engine.config();
+        assert_eq!(config.filter, Some(FilterDescriptor::AbusedTlds));
+
+        let json = serde_json::to_string(&engine).unwrap();
+        let restored: PermutationEngine = serde_json::from_str(&json).unwrap();
+        assert_eq!(restored.config(), config);
+        assert_eq!(restored.fingerprint(), engine.fingerprint());
+        assert_eq!(
+            restored.generate(&domain).unwrap().collect::<Vec<_>>(),
+            engine.generate(&domain).unwrap().collect::<Vec<_>>()
+        );
+
+        // Any change to the configuration changes the fingerprint.
+        assert_ne!(PermutationEngine::new().fingerprint(), engine.fingerprint());
+        assert_eq!(
+            PermutationEngine::new().fingerprint(),
+            EngineConfig::default().fingerprint()
+        );
+
+        // Closures are described by name, but cannot be restored.
+        engine.set_filter(|permutation: &Permutation| permutation.domain.tld == "net");
+        assert!(matches!(
+            engine.config().filter,
+            Some(FilterDescriptor::Custom(_))
+        ));
+        let custom = serde_json::to_string(&engine).unwrap();
+        assert!(serde_json::from_str::<PermutationEngine>(&custom).is_err());
+    }
+
+    #[test]
+    fn test_permutation_set() {
+        let d = Domain::new("example.com").unwrap();
+
+        let additions: PermutationSet = d.addition().collect();
+        let insertions: PermutationSet = d.insertion().collect();
+        assert_eq!(additions.len(), 26);
+        assert!(additions.contains("EXAMPLEA.com."));
+
+        let union = additions.union(&insertions);
+        let intersection = additions.intersection(&insertions);
+        let difference = additions.difference(&insertions);
+        let symmetric = additions.symmetric_difference(&insertions);
+
+        assert_eq!(
+            union.len(),
+            additions.len() + insertions.len() - intersection.len()
+        );
+        assert_eq!(difference.len(), additions.len() - intersection.len());
+        assert_eq!(symmetric.len(), union.len() - intersection.len());
+        assert!(intersection.keys().all(|key| insertions.contains(key)));
+
+        // Keeps the permutation of `self` for FQDNs in both sets.
+        let shared = intersection.keys().next().unwrap();
+        assert_eq!(union.get(shared).unwrap().kind, PermutationKind::Addition);
+
+        let by_id = PermutationSet::by_id(&d, d.addition().chain(d.insertion()));
+        assert_eq!(by_id.len(), additions.len() + insertions.len());
+
+        // The Unicode and punycode forms of an FQDN collide.
+        let mut idn = PermutationSet::new();
+        assert!(idn.insert(Permutation {
+            domain: Domain::new("münchen.de").unwrap(),
+            kind: PermutationKind::Homoglyph,
+            substitutions: vec![],
+        }));
+        assert!(!idn.insert(Permutation {
+            domain: Domain::new("xn--mnchen-3ya.de").unwrap(),
+            kind: PermutationKind::IdnHomograph,
+            substitutions: vec![],
+        }));
+        assert_eq!(idn.len(), 1);
+        assert!(idn.contains("xn--mnchen-3ya.de") && idn.contains("MÜNCHEN.de."));
+
+        let json = serde_json::to_string(&intersection).unwrap();
+        let decoded: PermutationSet = serde_json::from_str(&json).unwrap();
+        assert_eq!(decoded, intersection);
+        assert_eq!(decoded.into_iter().count(), intersection.len());
+    }
+
     #[test]
     fn test_keyword_mode() {
         let d = Domain::new("www.example.com").unwrap();
@@ -738,6 +4963,221 @@ mod tests {
         assert!(!permutations.is_empty());
     }
 
+    #[test]
+    fn test_keyword_set_mode() {
+        let d = Domain::new("example.com").unwrap();
+
+        let finance: Vec<_> = d.keyword_set(KeywordSet::Finance).collect();
+        assert!(finance.iter().any(|p| p.domain.fqdn == "example-bank.com"));
+
+        let mut engine = PermutationEngine::new();
+        engine.kinds = vec![PermutationKind::Keyword];
+        engine.keywords = vec![KeywordSet::Generic, KeywordSet::Crypto];
+
+        // Keywords shared by both sets are only added once.
+        let permutations: Vec<_> = engine.generate(&d).unwrap().collect();
+        assert!(
+            permutations.len() < d.keyword().count() + d.keyword_set(KeywordSet::Crypto).count()
+        );
+        assert_eq!(permutations.iter().unique().count(), permutations.len());
+        assert!(engine.estimate(&d) >= permutations.len());
+    }
+
+    #[test]
+    fn test_keyword_locale_mode() {
+        let d = Domain::new("example.com").unwrap();
+
+        let spanish: Vec<_> = d.keyword_locale(Locale::Es).collect();
+        assert!(spanish
+            .iter()
+            .any(|p| p.domain.fqdn == "example-cuenta.com"));
+        assert_eq!(d.keyword_locale(Locale::En).count(), d.keyword().count());
+
+        let mut engine = PermutationEngine::new();
+        engine.kinds = vec![PermutationKind::Keyword];
+        engine.keywords = vec![KeywordSet::Generic, KeywordSet::Finance];
+        engine.locales = vec![Locale::Pt, Locale::De];
+
+        let permutations: Vec<_> = engine.generate(&d).unwrap().collect();
+        assert!(
+            permutations.len()
+                <= d.keyword_locale(Locale::Pt).count()
+                    + d.keyword_locale(Locale::De).count()
+                    + d.keyword_set(KeywordSet::Finance).count()
+        );
+        assert_eq!(engine.estimate(&d), permutations.len());
+        assert!(!permutations
+            .iter()
+            .any(|p| p.domain.fqdn == "example-login.com"));
+
+        // Keywords shared across locales are only added once.
+        engine.keywords = vec![KeywordSet::Generic];
+        engine.locales = vec![Locale::En, Locale::De];
+        let merged: Vec<String> = engine
+            .generate(&d)
+            .unwrap()
+            .map(|p| p.domain.fqdn)
+            .collect();
+        assert_eq!(
+            merged
+                .iter()
+                .filter(|fqdn| *fqdn == "example-bank.com")
+                .count(),
+            1
+        );
+        assert_eq!(merged.iter().unique().count(), merged.len());
+        assert!(engine.estimate(&d) >= merged.len());
+    }
+
+    #[test]
+    fn test_keyword_forms_mode() {
+        let d = Domain::new("example.com").unwrap();
+
+        let hyphenated_suffix = KeywordForms {
+            prefix: false,
+            concatenated: false,
+            ..KeywordForms::default()
+        };
+        assert_eq!(hyphenated_suffix.count(), 1);
+
+        let permutations: Vec<_> = d
+            .keyword_forms(KeywordSet::Finance, hyphenated_suffix)
+            .collect();
+        assert!(permutations
+            .iter()
+            .any(|p| p.domain.fqdn == "example-bank.com"));
+        assert!(permutations
+            .iter()
+            .all(|p| p.domain.fqdn.starts_with("example-")));
+
+        let mut engine = PermutationEngine::new();
+        engine.kinds = vec![PermutationKind::Keyword];
+        engine.keyword_forms = hyphenated_suffix;
+        assert_eq!(engine.estimate(&d), KEYWORDS.len());
+        assert_eq!(
+            engine.generate(&d).unwrap().count(),
+            d.keyword().count() / 4
+        );
+    }
+
+    #[test]
+    fn test_dictionary_mode() {
+        let d = Domain::new("unitedhealth.com").unwrap();
+        let words = ["Ledger", " wallet ", ""];
+
+        let fqdns: Vec<String> = d
+            .dictionary(&words, KeywordForms::default())
+            .map(|p| p.domain.fqdn)
+            .collect();
+        assert_eq!(fqdns.len(), 2 * KeywordForms::default().count());
+        assert!(fqdns.contains(&"unitedhealth-ledger.com".to_string()));
+        assert!(fqdns.contains(&"walletunitedhealth.com".to_string()));
+
+        let infix = KeywordForms {
+            prefix: false,
+            suffix: false,
+            infix: true,
+            ..KeywordForms::default()
+        };
+        assert_eq!(infix.count_in(&d), 2);
+        let infixed: Vec<String> = d.dictionary(&words, infix).map(|p| p.domain.fqdn).collect();
+        assert_eq!(
+            infixed,
+            vec![
+                "united-ledger-health.com",
+                "unitedledgerhealth.com",
+                "united-wallet-health.com",
+                "unitedwallethealth.com",
+            ]
+        );
+        assert!(d
+            .dictionary(&words, infix)
+            .all(|p| p.kind == PermutationKind::Dictionary));
+
+        let mut engine = PermutationEngine::new();
+        engine.kinds = vec![PermutationKind::Dictionary];
+        assert_eq!(engine.generate(&d).unwrap().count(), 0);
+
+        engine.dictionary = vec!["ledger".to_string(), "wallet".to_string()];
+        engine.keyword_forms = infix;
+        assert_eq!(engine.estimate(&d), 4);
+        assert_eq!(engine.generate(&d).unwrap().count(), 4);
+        assert_eq!(
+            PermutationEngine::from_config(engine.config())
+                .unwrap()
+                .dictionary,
+            engine.dictionary
+        );
+    }
+
+    #[test]
+    fn test_idna_mode() {
+        let permutation = |fqdn: &str| Permutation {
+            domain: Domain::from_parts(
+                fqdn.to_string(),
+                "com".to_string(),
+                fqdn.trim_end_matches(".com").to_string(),
+            ),
+            kind: PermutationKind::Homoglyph,
+            substitutions: vec![],
+        };
+
+        // Full-width characters are mapped by IDNA, so are not
+        // registrable as-is, unlike uppercase ones.
+        assert!(IdnaMode::Validate
+            .apply(permutation("exａmple.com"))
+            .is_none());
+        assert!(IdnaMode::Validate
+            .apply(permutation("EXAMPLE.com"))
+            .is_some());
+        assert!(IdnaMode::Off.apply(permutation("exａmple.com")).is_some());
+
+        let ascii = IdnaMode::Ascii.apply(permutation("exámple.com")).unwrap();
+        assert!(ascii.domain.fqdn.starts_with("xn--"));
+        assert!(ascii.domain.domain.starts_with("xn--"));
+        assert_eq!(ascii.domain.tld, "com");
+
+        let d = Domain::new("example.com").unwrap();
+        let mut engine = PermutationEngine::new();
+        engine.kinds = vec![PermutationKind::Homoglyph];
+        engine.idna = IdnaMode::Ascii;
+
+        assert!(engine
+            .generate(&d)
+            .unwrap()
+            .all(|p| p.domain.fqdn.is_ascii()));
+
+        // Neither punycode nor uppercase bases lose their permutations,
+        // nor do IDN homographs, already in punycode.
+        engine.idna = IdnaMode::Validate;
+        for (fqdn, kind) in [
+            ("xn--mnchen-3ya.de", PermutationKind::Addition),
+            ("münchen.de", PermutationKind::Addition),
+            ("EXAMPLE.com", PermutationKind::Addition),
+            ("apple.com", PermutationKind::IdnHomograph),
+        ] {
+            let base = Domain::new(fqdn).unwrap();
+            engine.kinds = vec![kind];
+
+            let unvalidated = base.permutations(kind).unwrap().count();
+            assert!(unvalidated > 0);
+            assert_eq!(
+                engine.generate(&base).unwrap().count(),
+                unvalidated,
+                "{fqdn}"
+            );
+        }
+
+        let ace = IdnaMode::Ascii
+            .apply(Permutation {
+                domain: Domain::new("xn--mnchen-3ya.de").unwrap(),
+                kind: PermutationKind::Addition,
+                substitutions: vec![],
+            })
+            .unwrap();
+        assert_eq!(ace.domain.fqdn, "xn--mnchen-3ya.de");
+    }
+
     #[test]
     fn test_tld_mode() {
         let d = Domain::new("www.example.com").unwrap();
@@ -746,6 +5186,89 @@ mod tests {
         assert!(!permutations.is_empty());
     }
 
+    #[test]
+    fn test_tld_excluding_mode() {
+        let d = Domain::new("example.com").unwrap();
+        let exclusions = TldExclusions {
+            original: true,
+            owned: BTreeSet::from([String::from("EXAMPLE.de")]),
+        };
+
+        let all: HashSet<String> = d.tld().map(|p| p.domain.fqdn).collect();
+        let excluding: HashSet<String> = d
+            .tld_excluding(&TldSet::All, &exclusions)
+            .map(|p| p.domain.fqdn)
+            .collect();
+
+        assert!(all.contains("example.com") && all.contains("example.de"));
+        assert!(!excluding.contains("example.com"));
+        assert!(!excluding.contains("example.de"));
+        assert_eq!(excluding.len(), all.len() - 2);
+    }
+
+    #[test]
+    fn test_tld_grouped_mode() {
+        let d = Domain::new("example.com").unwrap();
+        let group = d
+            .tld_grouped(&TldSet::All, &TldExclusions::default())
+            .unwrap();
+
+        let all: HashSet<Permutation> = d.tld().collect();
+        assert_eq!(group.label, "example");
+        assert_eq!(group.tlds.len(), all.len());
+        assert!(group.keys().any(|key| key == "example.de"));
+        assert_eq!(group.permutations().collect::<HashSet<_>>(), all);
+
+        // Labels are grouped case-insensitively, other kinds are skipped.
+        let permutation = |fqdn: &str, kind| Permutation {
+            domain: Domain::new(fqdn).unwrap(),
+            kind,
+            substitutions: vec![],
+        };
+        let groups = TldGroup::group([
+            permutation("example.net", PermutationKind::Tld),
+            permutation("exmaple.com", PermutationKind::Transposition),
+            permutation("EXAMPLE.org", PermutationKind::Tld),
+            permutation("example.net", PermutationKind::Tld),
+            permutation("acme.de", PermutationKind::Tld),
+        ]);
+        assert_eq!(
+            groups,
+            vec![
+                TldGroup {
+                    label: "example".to_string(),
+                    tlds: vec!["net".to_string(), "org".to_string()],
+                },
+                TldGroup {
+                    label: "acme".to_string(),
+                    tlds: vec!["de".to_string()],
+                },
+            ]
+        );
+    }
+
+    #[test]
+    fn test_tld_neighbors_mode() {
+        let d = Domain::new("example.de").unwrap();
+        let fqdns: Vec<String> = d.tld_neighbors().map(|p| p.domain.fqdn).collect();
+
+        assert!(fqdns.contains(&String::from("example.at")));
+        assert!(fqdns.contains(&String::from("example.ch")));
+        assert!(!fqdns.contains(&String::from("example.com")));
+
+        let mut neighbors = CcTldNeighbors::empty();
+        neighbors.insert("au", &["co.nz"]);
+        let au = Domain::new("example.com.au").unwrap();
+        let custom: Vec<String> = au
+            .tld_set(&TldSet::Neighbors(neighbors))
+            .map(|p| p.domain.fqdn)
+            .collect();
+        assert_eq!(custom, vec!["example.co.nz"]);
+
+        let com = Domain::new("example.com").unwrap();
+        assert_eq!(com.tld_neighbors().count(), 0);
+    }
+
     #[test]
     fn test_mapping_mode() {
         let d = Domain::new("www.exoock96z.com").unwrap();
@@ -779,6 +5302,7 @@ mod tests {
                 Some(Permutation {
                     domain,
                     kind: PermutationKind::Addition,
+                    substitutions: vec![],
                 })
             } else {
                 None
@@ -792,6 +5316,282 @@ mod tests {
         assert_eq!(filtered_domains.len(), 5);
     }
 
+    #[test]
+    fn test_domain_runtime_suffix_list() {
+        let list = SuffixList::parse(
+            "// ===BEGIN ICANN DOMAINS===\ncom\nuk\ngov.uk\n// ===END ICANN DOMAINS===\n",
+        )
+        .unwrap();
+
+        let domain =
+            Domain::parse("www.example.gov.uk", Some(&list), &ParseOptions::default()).unwrap();
+        assert_eq!(domain.tld, "gov.uk");
+        assert_eq!(domain.domain, "example");
+
+        // Suffixes missing from the runtime list are rejected, even if
+        // the embedded list knows them.
+        assert!(Domain::parse("example.org", Some(&list), &ParseOptions::default()).is_err());
+        assert!(Domain::parse("gov.uk", Some(&list), &ParseOptions::default()).is_err());
+    }
+
+    #[test]
+    fn test_canonical_form() {
+        let unicode = Domain::new("Bücher.de").unwrap();
+        let ascii = Domain::new("xn--bcher-kva.de").unwrap();
+
+        assert_eq!(unicode.fqdn, "Bücher.de");
+        assert_eq!(unicode.canonical(), "xn--bcher-kva.de");
+        assert_eq!(unicode, ascii);
+        assert_eq!(
+            Domain::new("EXAMPLE.com").unwrap(),
+            Domain::new("example.com").unwrap()
+        );
+        assert_eq!(canonicalize("Example.COM."), "example.com");
+        assert_ne!(
+            Domain::new("example.com").unwrap(),
+            Domain::new("example.net").unwrap()
+        );
+
+        let unique: HashSet<Domain> = [unicode, ascii].into_iter().collect();
+        assert_eq!(unique.len(), 1);
+
+        // The canonical form is computed again on deserialization.
+        let json = r#"{"fqdn":"EXAMPLE.com","tld":"com","domain":"EXAMPLE","canonical":"x"}"#;
+        let deserialized: Domain = serde_json::from_str(json).unwrap();
+        assert_eq!(deserialized.canonical(), "example.com");
+    }
+
+    #[test]
+    fn test_domain_ref() {
+        for fqdn in [
+            "example.com",
+            "www.example.co.uk",
+            "example.github.io",
+            "bücher.de",
+        ] {
+            let borrowed = DomainRef::new(fqdn).unwrap();
+            let owned = Domain::new(fqdn).unwrap();
+
+            assert_eq!(borrowed.to_domain(), owned);
+            assert_eq!(owned.as_domain_ref(), borrowed);
+        }
+
+        let options = ParseOptions {
+            private_suffixes: true,
+        };
+        let private = DomainRef::new_with("example.github.io", &options).unwrap();
+        assert_eq!((private.domain, private.tld), ("example", "github.io"));
+
+        assert!(DomainRef::new("localhost").is_err());
+        assert!(DomainRef::new("127.0.0.1").is_err());
+        assert!(ValidationCtx::new()
+            .parse_ref("example.invalidtld")
+            .is_err());
+    }
+
+    #[test]
+    fn test_private_suffixes() {
+        let private = ParseOptions {
+            private_suffixes: true,
+        };
+
+        let icann = Domain::new("example.github.io").unwrap();
+        assert_eq!(
+            (icann.domain.as_str(), icann.tld.as_str()),
+            ("github", "io")
+        );
+        assert_eq!(icann.section(), SuffixSection::Icann);
+
+        let d = Domain::new_with("example.github.io", &private).unwrap();
+        assert_eq!(
+            (d.domain.as_str(), d.tld.as_str()),
+            ("example", "github.io")
+        );
+        assert_eq!(d.section(), SuffixSection::Private);
+
+        // Permutations keep the private suffix as the TLD.
+        assert!(d
+            .addition()
+            .all(|p| p.domain.tld == "github.io" && p.domain.domain.len() == 8));
+        assert!(d.tld().any(|p| p.domain.fqdn == "example.com"));
+
+        let list = SuffixList::parse(
+            "// ===BEGIN ICANN DOMAINS===\ncom\nio\n// ===END ICANN DOMAINS===\n\
+             // ===BEGIN PRIVATE DOMAINS===\ngithub.io\ngitlab.io\n// ===END PRIVATE DOMAINS===\n",
+        )
+        .unwrap();
+        let parsed = Domain::parse("example.github.io", Some(&list), &private).unwrap();
+        assert_eq!(parsed.tld, "github.io");
+
+        let mut ctx = ValidationCtx::new();
+        ctx.set_suffix_list(list);
+        let icann_only: Vec<String> = parsed
+            .tld_in(&TldSet::All, &TldExclusions::default(), &ctx)
+            .map(|p| p.domain.fqdn)
+            .collect();
+        assert_eq!(icann_only, vec!["example.com", "example.io"]);
+
+        ctx.private_suffixes = true;
+        let swapped: Vec<String> = parsed
+            .tld_in(&TldSet::All, &TldExclusions::default(), &ctx)
+            .map(|p| p.domain.fqdn)
+            .collect();
+        assert_eq!(
+            swapped,
+            vec![
+                "example.com",
+                "example.io",
+                "example.github.io",
+                "example.gitlab.io"
+            ]
+        );
+    }
+
+    #[test]
+    fn test_all_interleaved() {
+        let d = Domain::new("example.com").unwrap();
+
+        let first: Vec<PermutationKind> = d
+            .all_interleaved(None)
+            .unwrap()
+            .take(3)
+            .map(|p| p.kind)
+            .collect();
+        assert_eq!(
+            first,
+            vec![
+                PermutationKind::Addition,
+                PermutationKind::Bitsquatting,
+                PermutationKind::Hyphenation
+            ]
+        );
+
+        let all: HashSet<Permutation> = d.all().unwrap().collect();
+        let interleaved: HashSet<Permutation> = d.all_interleaved(None).unwrap().collect();
+        assert_eq!(interleaved, all);
+
+        let capped: Vec<Permutation> = d.all_interleaved(Some(2)).unwrap().collect();
+        for kind in PermutationKind::ALL {
+            assert!(capped.iter().filter(|p| p.kind == kind).count() <= 2);
+        }
+        assert!(capped.iter().any(|p| p.kind == PermutationKind::Homoglyph));
+    }
+
+    #[test]
+    fn test_validation_ctx() {
+        let mut ctx = ValidationCtx::new();
+        ctx.set_suffix_list(
+            SuffixList::parse(
+                "// ===BEGIN ICANN DOMAINS===\ncom\nuk\ngov.uk\n// ===END ICANN DOMAINS===\n",
+            )
+            .unwrap(),
+        );
+
+        assert_eq!(ctx.parse("example.gov.uk").unwrap().tld, "gov.uk");
+
+        // Only the suffixes of the context are swapped in.
+        let d = Domain::new("example.com").unwrap();
+        let tlds: Vec<String> = d
+            .permutations_in(PermutationKind::Tld, &ctx)
+            .unwrap()
+            .map(|p| p.domain.fqdn)
+            .collect();
+        assert_eq!(tlds, vec!["example.com", "example.gov.uk", "example.uk"]);
+
+        let mut filtered = ValidationCtx::new();
+        filtered.idna = IdnaMode::Ascii;
+        filtered.set_filter(|p: &Permutation| p.kind == PermutationKind::Homoglyph);
+
+        let permutations: Vec<Permutation> = d.all_in(&filtered).unwrap().collect();
+        assert!(!permutations.is_empty());
+        assert!(permutations
+            .iter()
+            .all(|p| p.kind == PermutationKind::Homoglyph && p.domain.fqdn.is_ascii()));
+
+        let mut engine = PermutationEngine::new();
+        engine.kinds = vec![PermutationKind::Homoglyph, PermutationKind::Addition];
+        assert_eq!(
+            engine.generate_in(&d, &filtered).unwrap().count(),
+            permutations.len()
+        );
+        assert_eq!(
+            engine
+                .generate_in(&d, &engine.validation_ctx())
+                .unwrap()
+                .count(),
+            engine.generate(&d).unwrap().count()
+        );
+    }
+
+    #[test]
+    fn test_domain_error_variants() {
+        let error = |fqdn: &str| match Domain::new(fqdn) {
+            Err(Error::PermutationError(e)) => e,
+            other => panic!("expected a permutation error, found {other:?}"),
+        };
+
+        assert!(matches!(
+            error("127.0.0.1"),
+            PermutationError::IpAddressInput { .. }
+        ));
+        assert!(matches!(
+            error("[::1]"),
+            PermutationError::IpAddressInput { .. }
+        ));
+        assert!(matches!(
+            error("localhost"),
+            PermutationError::MissingTld { .. }
+        ));
+        assert!(matches!(
+            error("example.notatld"),
+            PermutationError::UnsupportedSuffix { suffix, .. } if suffix == "notatld"
+        ));
+        assert!(matches!(
+            error("not a domain"),
+            PermutationError::InvalidDomain { .. }
+        ));
+    }
+
+    #[test]
+    fn test_permutation_id_is_stable() {
+        let origin = Domain::new("example.com").unwrap();
+        let permutation = Permutation {
+            domain: Domain::new("examp1e.com").unwrap(),
+            kind: PermutationKind::Homoglyph,
+            substitutions: vec![],
+        };
+
+        // Pinned, since identifiers must not change across versions.
+        let id = permutation.id(&origin);
+        assert_eq!(id, "2e85168daac6a430cf9719f8e30f45d4");
+        assert_eq!(id, permutation.id(&Domain::new("EXAMPLE.com").unwrap()));
+
+        let other_kind = Permutation {
+            kind: PermutationKind::Mapped,
+            ..permutation.clone()
+        };
+        assert_ne!(id, other_kind.id(&origin));
+        assert_ne!(id, permutation.id(&Domain::new("example.org").unwrap()));
+
+        // The Unicode and punycode forms of a domain share identifiers.
+        let unicode = Permutation {
+            domain: Domain::new("münchen.de").unwrap(),
+            kind: PermutationKind::Homoglyph,
+            substitutions: vec![],
+        };
+        let ascii = Permutation {
+            domain: Domain::new("xn--mnchen-3ya.de").unwrap(),
+            kind: PermutationKind::Homoglyph,
+            substitutions: vec![],
+        };
+        let base = Domain::new("Munchen.de").unwrap();
+        assert_eq!(unicode.id(&base), ascii.id(&base));
+        assert_eq!(
+            ascii.id(&Domain::new("xn--mnchen-3ya.de").unwrap()),
+            ascii.id(&Domain::new("münchen.de").unwrap())
+        );
+    }
+
     #[test]
     fn test_domains_empty_permutations_regression() {
         let domains: Vec<Domain> = vec!["ox.ac.uk", "oxford.ac.uk", "cool.co.nz"]
@@ -836,6 +5636,138 @@ mod tests {
         assert_eq!(results.len(), 3);
     }
 
+    #[test]
+    fn test_mapped_table_mode() {
+        let d = Domain::new("vvallet.com").unwrap();
+
+        let mut table = MappedTable::empty();
+        table.insert("vv", &["w"]);
+        table.insert("q", &["g"]);
+
+        let permutations: Vec<String> = d
+            .mapped_table(&table, MappedMode::Every)
+            .map(|p| p.domain.fqdn)
+            .collect();
+        assert_eq!(permutations, vec!["wallet.com"]);
+
+        let mut merged = MappedTable::builtin();
+        merged.merge(&table);
+        assert_eq!(merged.get("q").unwrap(), ["9", "g"]);
+        assert_eq!(d.estimate(PermutationKind::Mapped), d.mapped().count());
+
+        let mut engine = PermutationEngine::new();
+        engine.kinds = vec![PermutationKind::Mapped];
+        engine.mapped = merged;
+        assert!(engine
+            .generate(&d)
+            .unwrap()
+            .any(|p| p.domain.fqdn == "wallet.com"));
+    }
+
+    #[test]
+    fn test_mapped_single_occurrence_mode() {
+        let d = Domain::new("wowow.com").unwrap();
+
+        let mut table = MappedTable::empty();
+        table.insert("w", &["vv"]);
+
+        let single: Vec<String> = d
+            .mapped_table(&table, MappedMode::Single)
+            .map(|p| p.domain.fqdn)
+            .collect();
+        assert_eq!(single, vec!["vvowow.com", "wovvow.com", "wowovv.com"]);
+
+        let both: Vec<String> = d
+            .mapped_table(&table, MappedMode::Both)
+            .map(|p| p.domain.fqdn)
+            .collect();
+        assert_eq!(both.len(), 4);
+        assert!(both.contains(&"vvovvovv.com".to_string()));
+
+        // A single occurrence yields the same candidate either way.
+        let once = Domain::new("town.com").unwrap();
+        assert_eq!(once.mapped_table(&table, MappedMode::Both).count(), 1);
+
+        let mut engine = PermutationEngine::new();
+        engine.kinds = vec![PermutationKind::Mapped];
+        engine.mapped = table;
+        engine.mapped_mode = MappedMode::Both;
+        assert_eq!(engine.estimate(&d), 4);
+        assert_eq!(engine.generate(&d).unwrap().count(), 4);
+    }
+
+    #[test]
+    fn test_transliteration_mode() {
+        let cyrillic = Domain::new("яндекс.com").unwrap();
+        let romanized: Vec<String> = cyrillic.transliteration().map(|p| p.domain.fqdn).collect();
+        assert_eq!(romanized, vec!["yandeks.com", "iandeks.com"]);
+        assert_eq!(
+            cyrillic.estimate(PermutationKind::Transliteration),
+            romanized.len()
+        );
+
+        let greek = Domain::new("αθηνα.gr").unwrap();
+        assert!(greek
+            .transliteration()
+            .any(|p| p.domain.fqdn == "athina.gr"));
+
+        let latin = Domain::new("shop24.com").unwrap();
+        let spelled: Vec<String> = latin.transliteration().map(|p| p.domain.fqdn).collect();
+        assert_eq!(spelled[..2], ["шоп24.com", "σχοπ24.com"]);
+
+        // Transliterations into another script are followed by their
+        // typos on the keyboard of that script.
+        assert!(spelled.contains(&"щоп24.com".to_string()));
+        assert!(spelled.contains(&"σψοπ24.com".to_string()));
+        assert_eq!(
+            latin.estimate(PermutationKind::Transliteration),
+            spelled.len()
+        );
+    }
+
+    #[test]
+    fn test_script_keyboard_layouts() {
+        let cyrillic = Domain::new("яндекс.com").unwrap();
+        let typos: Vec<String> = cyrillic.replacement().map(|p| p.domain.fqdn).collect();
+        assert!(typos.iter().any(|fqdn| fqdn.contains('ч')));
+        assert!(cyrillic
+            .fat_finger()
+            .any(|p| p.domain.fqdn == "яндлекс.com"));
+        assert!(cyrillic.estimate(PermutationKind::Replacement) >= typos.len());
+
+        let greek = Domain::new("αθηνα.gr").unwrap();
+        assert!(greek.insertion().any(|p| p.domain.fqdn.contains('β')));
+
+        let arabic = Domain::new("مثال.com").unwrap();
+        assert!(arabic.replacement().next().is_some());
+
+        // Latin labels are left to the Latin layouts.
+        let latin = Domain::new("example.com").unwrap();
+        assert!(latin.replacement().all(|p| p.domain.fqdn.is_ascii()));
+    }
+
+    #[test]
+    fn test_ocr_confusion_mode() {
+        let d = Domain::new("modern.com").unwrap();
+        let permutations: Vec<Permutation> = d.ocr_confusion().collect();
+
+        let fqdns: HashSet<&str> = permutations
+            .iter()
+            .map(|p| p.domain.fqdn.as_str())
+            .collect();
+        assert!(fqdns.contains("rnodern.com"));
+        assert!(fqdns.contains("modem.com"));
+        assert!(fqdns.contains("m0dern.com"));
+        assert!(fqdns.contains("moclern.com"));
+        assert!(permutations
+            .iter()
+            .all(|p| p.kind == PermutationKind::OcrConfusion));
+        assert_eq!(
+            d.estimate(PermutationKind::OcrConfusion),
+            permutations.len()
+        );
+    }
+
     #[test]
     fn test_mapped_generates_expected_permutation() {
         let domain = Domain::new("trm.com").unwrap();
@@ -843,7 +5775,6 @@ mod tests {
 
         let results: Vec<Permutation> = domain
             .mapped()
-            .into_iter()
             .filter(|p| p.domain.fqdn == expected.fqdn)
             .collect();
 
@@ -0,0 +1,1612 @@
+//! The pipeline module glues the permutation and enrichment modules
+//! together. A [`Pipeline`] enriches a set of permutations concurrently,
+//! whereas a [`ScanJob`] drives a full scan of a single domain in
+//! batches and can be checkpointed and resumed at any point.
+//!
+//! Example:
+//!
+//! ```no_run
+//! use twistrs::permutate::{Domain, PermutationEngine};
+//! use twistrs::pipeline::{ScanJob, ScanState};
+//!
+//! #[tokio::main]
+//! async fn main() {
+//!     let mut job = ScanJob::new(Domain::new("example.com").unwrap());
+//!
+//!     // Enrich a first batch and persist the state somewhere safe.
+//!     job.step(500).await.unwrap();
+//!     let state: ScanState = job.checkpoint();
+//!
+//!     // ...later on, potentially in a different process, with the same
+//!     // engine configuration.
+//!     let mut job = ScanJob::resume(state, PermutationEngine::new()).unwrap();
+//!     job.run().await.unwrap();
+//!
+//!     println!("{:?}", job.results());
+//! }
+//! ```
+use std::collections::BinaryHeap;
+use std::fmt;
+use std::sync::atomic::Ordering;
+use std::sync::Arc;
+use std::time::Duration;
+
+use futures::future;
+use futures::stream::{self, Stream, StreamExt};
+use serde::{Deserialize, Serialize};
+
+use crate::breaker::CircuitBreaker;
+use crate::constants::DEFAULT_CONCURRENCY;
+use crate::enrich::{
+    DomainMetadata, EnrichmentError, EnrichmentKind, EnrichmentKinds, WhoIsOptions,
+};
+use crate::error::Error;
+use crate::filter::ResultFilter;
+use crate::permutate::{Domain, Permutation, PermutationEngine};
+use crate::progress::{Callback, Phase};
+use crate::resolver::{Resolver, ReverseIp, SystemResolver};
+use crate::scoring::{self, CompositeScorer, Scored, Scorer};
+use crate::summary::ScanSummary;
+use crate::throttle::{AdaptiveConcurrency, DestinationLimiter};
+use crate::watchdog::{heartbeats, Activity, Heartbeat, Watchdog};
+
+/// Default number of permutations enriched per `ScanJob` step.
+const DEFAULT_BATCH_SIZE: usize = 1000;
+
+#[derive(thiserror::Error, Debug)]
+pub enum ScanError {
+    #[error(
+        "scan was generated with a different engine configuration (scan: {scan}, engine: {engine})"
+    )]
+    FingerprintMismatch { scan: String, engine: String },
+}
+
+/// A permutation along with the metadata gathered while enriching it.
+#[derive(Debug, Clone, Serialize, Deserialize, Hash, Eq, PartialEq)]
+pub struct ScanResult {
+    pub permutation: Permutation,
+    pub metadata: DomainMetadata,
+
+    /// Fingerprint of the engine configuration the permutation was
+    /// generated with, see [`Pipeline::set_fingerprint`].
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub fingerprint: Option<String>,
+}
+
+/// Observer of the events happening within a `Pipeline`, useful for
+/// cross-cutting concerns such as audit logging and live dashboards.
+///
+/// All methods default to no-ops, so implementors only need to override
+/// the events they are interested in.
+pub trait Observer: Send + Sync {
+    /// Invoked when a permutation enters the pipeline, right before it
+    /// is enriched.
+    fn on_permutation(&self, _permutation: &Permutation) {}
+
+    /// Invoked when a permutation has been enriched, regardless of whether
+    /// it resolved or not.
+    fn on_enriched(&self, _result: &ScanResult) {}
+
+    /// Invoked when enriching a resolving permutation fails. Note that
+    /// permutations that do not resolve are not considered errors.
+    fn on_error(&self, _permutation: &Permutation, _error: &Error) {}
+
+    /// Invoked when enriching a permutation stalled and was cancelled,
+    /// with the number of the attempt that stalled, see [`Watchdog`].
+    fn on_stall(&self, _permutation: &Permutation, _attempt: usize) {}
+
+    /// Invoked periodically while the pipeline runs, see [`Watchdog`].
+    fn on_heartbeat(&self, _heartbeat: &Heartbeat) {}
+}
+
+/// Concurrent enrichment of permutations.
+///
+/// Each permutation is resolved and, if it resolves, its HTTP banner is
+/// fetched. Permutations that do not resolve are still yielded with an
+/// empty `DomainMetadata` so that callers can tell them apart.
+///
+/// Lookups are performed live by default, see [`Pipeline::set_resolver`]
+/// to answer them from fixtures instead.
+#[derive(Clone)]
+pub struct Pipeline {
+    /// Maximum number of permutations enriched concurrently.
+    pub concurrency: usize,
+
+    progress: Option<Callback>,
+    observers: Vec<Arc<dyn Observer>>,
+    resolver: Arc<dyn Resolver>,
+    enrichments: EnrichmentKinds,
+    fingerprint: Option<String>,
+    reverse_ip: Option<Arc<dyn ReverseIp>>,
+    destinations: Option<DestinationLimiter>,
+    breaker: Option<(CircuitBreaker, Duration)>,
+    nameserver_base: Option<DomainMetadata>,
+    adaptive: Option<AdaptiveConcurrency>,
+    watchdog: Option<Watchdog>,
+    result_filter: Option<Arc<dyn ResultFilter>>,
+}
+
+impl fmt::Debug for Pipeline {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("Pipeline")
+            .field("concurrency", &self.concurrency)
+            .field("progress", &self.progress)
+            .field("observers", &self.observers.len())
+            .field("enrichments", &self.enrichments)
+            .field("fingerprint", &self.fingerprint)
+            .field("destinations", &self.destinations)
+            .field("breaker", &self.breaker)
+            .field("nameserver_base", &self.nameserver_base)
+            .field("adaptive", &self.adaptive)
+            .field("watchdog", &self.watchdog)
+            .field("result_filter", &self.result_filter.is_some())
+            .finish_non_exhaustive()
+    }
+}
+
+impl Default for Pipeline {
+    fn default() -> Pipeline {
+        Pipeline {
+            concurrency: DEFAULT_CONCURRENCY,
+            progress: None,
+            observers: vec![],
+            resolver: Arc::new(SystemResolver::default()),
+            enrichments: EnrichmentKinds::DNS | EnrichmentKinds::HTTP_BANNER,
+            fingerprint: None,
+            reverse_ip: None,
+            destinations: None,
+            breaker: None,
+            nameserver_base: None,
+            adaptive: None,
+            watchdog: None,
+            result_filter: None,
+        }
+    }
+}
+
+impl Pipeline {
+    /// Create a new pipeline with the default concurrency.
+    pub fn new() -> Pipeline {
+        Pipeline::default()
+    }
+
+    /// Register a hook that is invoked every time a permutation has been
+    /// enriched. The total estimate is derived from the size hint of the
+    /// permutations passed to [`Pipeline::run`].
+    pub fn on_progress<F>(&mut self, f: F)
+    where
+        F: Fn(usize, usize, Phase) + Send + Sync + 'static,
+    {
+        self.progress = Some(Callback::new(f));
+    }
+
+    /// Register an observer that is notified of every permutation going
+    /// through the pipeline. Observers are invoked in registration order.
+    pub fn observe<O>(&mut self, observer: O)
+    where
+        O: Observer + 'static,
+    {
+        self.observers.push(Arc::new(observer));
+    }
+
+    /// Replace the resolver performing the DNS and HTTP banner lookups,
+    /// e.g. with a [`MockResolver`](crate::resolver::MockResolver) for
+    /// deterministic tests or air-gapped use, or with a
+    /// [`SystemResolver`] over a configured
+    /// [`EnrichmentContext`](crate::enrich::EnrichmentContext).
+    pub fn set_resolver<R>(&mut self, resolver: R)
+    where
+        R: Resolver + 'static,
+    {
+        self.resolver = Arc::new(resolver);
+    }
+
+    /// Select the enrichments run on resolving permutations, the DNS and
+    /// HTTP banner lookups by default. Permutations are always resolved
+    /// first, as those that do not resolve are not enriched any further.
+    ///
+    /// Kinds other than the DNS and HTTP banner lookups are run through
+    /// [`Resolver::enrichments`], and kinds requiring a feature that is
+    /// not toggled fail every resolving permutation, see
+    /// [`EnrichmentKinds::supported`].
+    pub fn set_enrichments(&mut self, kinds: EnrichmentKinds) {
+        self.enrichments = kinds;
+    }
+
+    /// Enrichments run on resolving permutations.
+    pub fn enrichments(&self) -> EnrichmentKinds {
+        self.enrichments
+    }
+
+    /// Stamp results with `fingerprint`, that of the engine configuration
+    /// the permutations were generated with (see
+    /// [`PermutationEngine::fingerprint`]), so that results of different
+    /// configurations can be told apart.
+    pub fn set_fingerprint(&mut self, fingerprint: String) {
+        self.fingerprint = Some(fingerprint);
+    }
+
+    /// Fingerprint results are stamped with, if any.
+    pub fn fingerprint(&self) -> Option<&str> {
+        self.fingerprint.as_deref()
+    }
+
+    /// Attach the domains co-hosted on the IPs of resolving permutations,
+    /// as listed by `provider`, see [`DomainMetadata::reverse_ip_lookup`].
+    pub fn set_reverse_ip<R>(&mut self, provider: R)
+    where
+        R: ReverseIp + 'static,
+    {
+        self.reverse_ip = Some(Arc::new(provider));
+    }
+
+    /// Cap the number of in-flight HTTP requests per resolved IP, and of
+    /// `WhoIs` lookups per server (see [`WhoIsOptions::destination_for`]),
+    /// to `max_in_flight`, on top of the overall concurrency, so that hosts
+    /// serving many permutations are not hammered. A limit of `0` removes
+    /// the cap.
+    pub fn set_destination_limit(&mut self, max_in_flight: usize) {
+        self.destinations = (max_in_flight > 0).then(|| DestinationLimiter::new(max_in_flight));
+    }
+
+    /// Look the nameservers of resolving permutations up and compare them
+    /// against those of `base`, see [`DomainMetadata::compare_nameservers`],
+    /// so that likely defensive registrations are de-prioritized by the
+    /// scorer. A `base` without nameservers turns the lookups off.
+    pub fn set_nameserver_base(&mut self, base: DomainMetadata) {
+        self.nameserver_base = base.nameservers().is_some().then_some(base);
+    }
+
+    /// Short-circuit the HTTP banner lookups towards resolved IPs that keep
+    /// timing out, see [`CircuitBreaker`]. Each lookup times out after
+    /// `timeout`, and lookups towards an open circuit fail straight away.
+    pub fn set_circuit_breaker(&mut self, breaker: CircuitBreaker, timeout: Duration) {
+        self.breaker = Some((breaker, timeout));
+    }
+
+    /// Breaker short-circuiting the HTTP banner lookups, if any.
+    pub fn circuit_breaker(&self) -> Option<&CircuitBreaker> {
+        self.breaker.as_ref().map(|(breaker, _)| breaker)
+    }
+
+    /// Adjust the number of permutations enriched concurrently to the
+    /// failures observed, between `min` and the concurrency of the
+    /// pipeline, see [`AdaptiveConcurrency`]. Stalls, timeouts and lookups
+    /// skipped by the circuit breaker count as failures, whereas other
+    /// enrichment errors and permutations that do not resolve do not.
+    ///
+    /// The maximum of the controller is derived from
+    /// [`Pipeline::concurrency`] every time the pipeline runs, so the
+    /// concurrency can be changed before or after this call.
+    ///
+    /// The controller is shared with clones of the pipeline and across
+    /// runs, and can be inspected through [`Pipeline::adaptive_concurrency`].
+    pub fn set_adaptive_concurrency(&mut self, min: usize) {
+        self.adaptive = Some(AdaptiveConcurrency::new(min, self.concurrency));
+    }
+
+    /// Controller adjusting the concurrency of the pipeline, if any.
+    pub fn adaptive_concurrency(&self) -> Option<&AdaptiveConcurrency> {
+        self.adaptive.as_ref()
+    }
+
+    /// Cancel and restart enrichment tasks that stall, and notify
+    /// observers of heartbeats while the pipeline runs.
+    pub fn set_watchdog(&mut self, watchdog: Watchdog) {
+        self.watchdog = Some(watchdog);
+    }
+
+    /// Only yield the enriched permutations that `filter` matches, e.g.
+    /// those that resolve and have MX records, see [`ResultFilter`].
+    /// Observers and progress hooks are still notified of every
+    /// permutation enriched.
+    ///
+    /// Only [`Pipeline::run`] and [`Pipeline::run_prioritized`] apply the
+    /// filter, as filters match permutations, which [`Pipeline::run_fqdns`]
+    /// does not have.
+    pub fn set_result_filter<F>(&mut self, filter: F)
+    where
+        F: ResultFilter + 'static,
+    {
+        self.result_filter = Some(Arc::new(filter));
+    }
+
+    /// Enrich `permutations`, yielding results in completion order.
+    pub fn run<'a, I>(&'a self, permutations: I) -> impl Stream<Item = ScanResult> + 'a
+    where
+        I: IntoIterator<Item = Permutation> + 'a,
+    {
+        self.sync_adaptive_concurrency();
+
+        let candidates = permutations.into_iter();
+        let (lower, upper) = candidates.size_hint();
+        let total_estimate = upper.unwrap_or(lower);
+        let activity = Arc::new(Activity::default());
+        let tasks = Arc::clone(&activity);
+
+        let results = stream::iter(candidates)
+            .map(move |permutation| {
+                let task = Arc::clone(&tasks);
+
+                async move {
+                    for observer in &self.observers {
+                        observer.on_permutation(&permutation);
+                    }
+
+                    let fqdn = &permutation.domain.fqdn;
+                    let (metadata, failure) = self
+                        .enrich_watched(fqdn, &task, |attempt| {
+                            for observer in &self.observers {
+                                observer.on_stall(&permutation, attempt);
+                            }
+                        })
+                        .await
+                        .unwrap_or_else(|error| (DomainMetadata::new(fqdn.clone()), Some(error)));
+
+                    if let Some(error) = failure {
+                        for observer in &self.observers {
+                            observer.on_error(&permutation, &error);
+                        }
+                    }
+
+                    let result = ScanResult {
+                        permutation,
+                        metadata,
+                        fingerprint: self.fingerprint.clone(),
+                    };
+
+                    for observer in &self.observers {
+                        observer.on_enriched(&result);
+                    }
+
+                    result
+                }
+            })
+            .buffer_unordered(self.concurrency.max(1))
+            .enumerate()
+            .map(move |(i, result)| {
+                if let Some(progress) = &self.progress {
+                    progress.call(i + 1, total_estimate, Phase::Enrichment);
+                }
+
+                result
+            })
+            .filter(move |result| {
+                future::ready(match &self.result_filter {
+                    Some(filter) => filter.matches(&result.permutation, &result.metadata),
+                    None => true,
+                })
+            });
+
+        heartbeats(results, self.watchdog, activity, move |heartbeat| {
+            for observer in &self.observers {
+                observer.on_heartbeat(heartbeat);
+            }
+        })
+    }
+
+    /// Enrich the FQDNs of `fqdns` as they come in, e.g. candidates from
+    /// a feed rather than from the permutation engine, yielding results
+    /// in completion order. Errors of the input stream are passed
+    /// through as-is.
+    ///
+    /// Since no permutations are involved, observers are only notified
+    /// of heartbeats, progress is reported without a total estimate, and
+    /// every result is yielded regardless of the result filter, see
+    /// [`Pipeline::set_result_filter`]. FQDNs that stall past the restarts
+    /// of the [`Watchdog`] yield an error.
+    pub fn run_fqdns<'a, S>(
+        &'a self,
+        fqdns: S,
+    ) -> impl Stream<Item = Result<DomainMetadata, Error>> + 'a
+    where
+        S: Stream<Item = Result<String, Error>> + 'a,
+    {
+        self.sync_adaptive_concurrency();
+
+        let activity = Arc::new(Activity::default());
+        let tasks = Arc::clone(&activity);
+
+        let results = fqdns
+            .map(move |fqdn| {
+                let task = Arc::clone(&tasks);
+
+                async move { Ok(self.enrich_watched(&fqdn?, &task, |_| {}).await?.0) }
+            })
+            .buffer_unordered(self.concurrency.max(1))
+            .enumerate()
+            .map(move |(i, result)| {
+                if let Some(progress) = &self.progress {
+                    progress.call(i + 1, 0, Phase::Enrichment);
+                }
+
+                result
+            });
+
+        heartbeats(results, self.watchdog, activity, move |heartbeat| {
+            for observer in &self.observers {
+                observer.on_heartbeat(heartbeat);
+            }
+        })
+    }
+
+    /// Cap the adaptive concurrency, if any, to the current concurrency.
+    fn sync_adaptive_concurrency(&self) {
+        if let Some(controller) = &self.adaptive {
+            controller.set_max(self.concurrency.max(1));
+        }
+    }
+
+    /// Enrich `fqdn` under the watchdog, if any, invoking `on_stall` for
+    /// every stalled attempt. Fails if the task stalls past the restarts.
+    ///
+    /// With adaptive concurrency, waits for a slot first and records
+    /// whether the task failed with a sign of congestion.
+    async fn enrich_watched<S>(
+        &self,
+        fqdn: &str,
+        activity: &Activity,
+        on_stall: S,
+    ) -> Result<(DomainMetadata, Option<Error>), Error>
+    where
+        S: FnMut(usize),
+    {
+        let Some(controller) = &self.adaptive else {
+            return self.supervise(fqdn, activity, on_stall).await;
+        };
+
+        let permit = controller.acquire().await;
+        let enriched = self.supervise(fqdn, activity, on_stall).await;
+        permit.record(match &enriched {
+            Ok((_, Some(e))) | Err(e) => congested(e),
+            Ok((_, None)) => false,
+        });
+
+        enriched
+    }
+
+    async fn supervise<S>(
+        &self,
+        fqdn: &str,
+        activity: &Activity,
+        mut on_stall: S,
+    ) -> Result<(DomainMetadata, Option<Error>), Error>
+    where
+        S: FnMut(usize),
+    {
+        let Some(watchdog) = self.watchdog else {
+            return Ok(enrich(self, fqdn).await);
+        };
+
+        activity.in_flight.fetch_add(1, Ordering::Relaxed);
+        let enriched = watchdog
+            .supervise(
+                fqdn,
+                || enrich(self, fqdn),
+                |attempt| {
+                    activity.stalls.fetch_add(1, Ordering::Relaxed);
+                    on_stall(attempt);
+                },
+            )
+            .await;
+        activity.in_flight.fetch_sub(1, Ordering::Relaxed);
+
+        enriched
+    }
+
+    /// Enrich `permutations` in descending order of risk as scored by
+    /// `scorer`, so that the most dangerous candidates are enriched first
+    /// should the stream be dropped early.
+    ///
+    /// Note that all permutations are scored upfront, and that with a
+    /// concurrency greater than one results are still yielded in
+    /// completion order.
+    pub fn run_prioritized<'a, I>(
+        &'a self,
+        permutations: I,
+        scorer: &dyn Scorer,
+    ) -> impl Stream<Item = ScanResult> + 'a
+    where
+        I: IntoIterator<Item = Permutation>,
+    {
+        let mut queue: BinaryHeap<Scored> = permutations
+            .into_iter()
+            .map(|permutation| Scored {
+                score: scorer.score(&permutation, None),
+                permutation,
+            })
+            .collect();
+        let total = queue.len();
+
+        self.run(ExactSize {
+            inner: std::iter::from_fn(move || queue.pop().map(|scored| scored.permutation)),
+            remaining: total,
+        })
+    }
+}
+
+/// Iterator adapter carrying an exact size hint, used to keep progress
+/// estimates accurate for iterators that cannot report it themselves.
+struct ExactSize<I> {
+    inner: I,
+    remaining: usize,
+}
+
+impl<I: Iterator> Iterator for ExactSize<I> {
+    type Item = I::Item;
+
+    fn next(&mut self) -> Option<I::Item> {
+        let item = self.inner.next()?;
+        self.remaining = self.remaining.saturating_sub(1);
+        Some(item)
+    }
+
+    fn size_hint(&self) -> (usize, Option<usize>) {
+        (self.remaining, Some(self.remaining))
+    }
+}
+
+/// Internal helper that resolves the FQDN and, if it resolves, grabs
+/// its HTTP banner, nameservers and neighbors. Resolution failures are
+/// folded into an empty `DomainMetadata`, whereas failures past
+/// resolution are returned.
+async fn enrich(pipeline: &Pipeline, fqdn: &str) -> (DomainMetadata, Option<Error>) {
+    let resolver = &*pipeline.resolver;
+
+    match resolver.dns_resolvable(fqdn).await {
+        Ok(mut resolved) => {
+            let first_ip = resolved
+                .dns
+                .ips()
+                .and_then(|ips| ips.first().map(ToString::to_string));
+
+            let mut error = None;
+
+            if pipeline.enrichments.contains(EnrichmentKinds::HTTP_BANNER) {
+                // Held until the banner has been fetched.
+                let _permit = match (&pipeline.destinations, &first_ip) {
+                    (Some(limiter), Some(ip)) => limiter.acquire(ip).await,
+                    _ => None,
+                };
+
+                let fetched = match &pipeline.breaker {
+                    Some((breaker, timeout)) => {
+                        let destination = first_ip.as_deref().unwrap_or(fqdn);
+                        let lookup = resolver.http_banner(fqdn);
+
+                        breaker
+                            .guard(EnrichmentKind::HttpBanner, destination, *timeout, lookup)
+                            .await
+                    }
+                    None => resolver.http_banner(fqdn).await,
+                };
+
+                match fetched {
+                    Ok(banner) => resolved.http_banner = banner.http_banner,
+                    Err(e) => error = Some(e),
+                }
+            }
+
+            let mut others =
+                pipeline.enrichments - (EnrichmentKinds::DNS | EnrichmentKinds::HTTP_BANNER);
+
+            // WhoIs lookups are capped per server rather than per IP, so
+            // they are run on their own while holding a slot of theirs.
+            if let Some(limiter) = &pipeline.destinations {
+                if others.contains(EnrichmentKinds::WHOIS) {
+                    others.remove(EnrichmentKinds::WHOIS);
+
+                    let destination = WhoIsOptions::default().destination_for(fqdn);
+                    let _permit = limiter.acquire(&destination).await;
+                    let enriched = resolver.enrichments(fqdn, EnrichmentKinds::WHOIS).await;
+                    merge_enriched(&mut resolved, &mut error, enriched);
+                }
+            }
+
+            if !others.is_empty() {
+                let enriched = resolver.enrichments(fqdn, others).await;
+                merge_enriched(&mut resolved, &mut error, enriched);
+            }
+
+            if let Some(base) = &pipeline.nameserver_base {
+                match resolver.nameservers(fqdn).await {
+                    Ok(found) => {
+                        resolved.dns.ns = found.dns.ns;
+                        resolved.compare_nameservers(base);
+                    }
+                    Err(e) => error = error.or(Some(e)),
+                }
+            }
+
+            if let Some(provider) = &pipeline.reverse_ip {
+                match resolved.reverse_ip_lookup(&**provider).await {
+                    Ok(neighbors) => resolved.neighbors = neighbors.neighbors,
+                    Err(e) => error = error.or(Some(e)),
+                }
+            }
+
+            (resolved, error)
+        }
+        Err(_) => (DomainMetadata::new(fqdn.to_string()), None),
+    }
+}
+
+/// Whether `error` hints at congestion, i.e. a stall, a timeout or a lookup
+/// skipped by the circuit breaker, as opposed to e.g. a missing header.
+fn congested(error: &Error) -> bool {
+    match error {
+        Error::WatchdogError(_)
+        | Error::BreakerError(_)
+        | Error::EnrichmentError(EnrichmentError::HttpProbeTimeout { .. }) => true,
+        Error::EnrichmentError(EnrichmentError::PartialFailure { errors, .. }) => {
+            errors.iter().any(|(_, e)| congested(e))
+        }
+        _ => false,
+    }
+}
+
+/// Merge the outcome of the `enriched` lookups into `resolved`, keeping
+/// the results of the enrichments that succeeded and the first error.
+fn merge_enriched(
+    resolved: &mut DomainMetadata,
+    error: &mut Option<Error>,
+    enriched: Result<DomainMetadata, Error>,
+) {
+    match enriched {
+        Ok(metadata) => *resolved = std::mem::take(resolved).merge(metadata),
+        Err(e) => {
+            if let Error::EnrichmentError(EnrichmentError::PartialFailure { metadata, .. }) = &e {
+                *resolved = std::mem::take(resolved).merge((**metadata).clone());
+            }
+
+            *error = error.take().or(Some(e));
+        }
+    }
+}
+
+/// Serializable snapshot of a `ScanJob`, produced by
+/// [`ScanJob::checkpoint`] and consumed by [`ScanJob::resume`].
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct ScanState {
+    /// Domain being scanned.
+    pub domain: Domain,
+
+    /// Permutations generated for the scan, in enrichment order. This
+    /// is `None` until the generation phase has completed.
+    pub permutations: Option<Vec<Permutation>>,
+
+    /// Fingerprint of the engine configuration the permutations were
+    /// generated with, see [`PermutationEngine::fingerprint`].
+    #[serde(default)]
+    pub fingerprint: Option<String>,
+
+    /// Index of the next permutation to enrich.
+    pub cursor: usize,
+
+    /// Nameservers of the domain, looked up by the first step and compared
+    /// against those of each resolving permutation, see
+    /// [`Pipeline::set_nameserver_base`].
+    #[serde(default)]
+    pub base: Option<DomainMetadata>,
+
+    /// Results of the permutations enriched so far that resolved.
+    pub results: Vec<ScanResult>,
+}
+
+/// Resumable scan of a single domain.
+///
+/// The scan generates all permutations of the domain once and then
+/// enriches them in batches. Between any two batches the job can be
+/// checkpointed, so that an interrupted scan does not restart from zero.
+#[derive(Clone)]
+pub struct ScanJob {
+    /// Engine used to generate the permutations of the domain.
+    pub engine: PermutationEngine,
+
+    /// Pipeline used to enrich permutations.
+    pub pipeline: Pipeline,
+
+    /// Number of permutations enriched per step.
+    pub batch_size: usize,
+
+    scorer: Arc<dyn Scorer>,
+    state: ScanState,
+    progress: Option<Callback>,
+}
+
+impl fmt::Debug for ScanJob {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("ScanJob")
+            .field("engine", &self.engine)
+            .field("pipeline", &self.pipeline)
+            .field("batch_size", &self.batch_size)
+            .field("state", &self.state)
+            .field("progress", &self.progress)
+            .finish_non_exhaustive()
+    }
+}
+
+impl ScanJob {
+    /// Create a new scan of `domain`.
+    pub fn new(domain: Domain) -> ScanJob {
+        ScanJob::with_state(
+            ScanState {
+                domain,
+                permutations: None,
+                fingerprint: None,
+                cursor: 0,
+                base: None,
+                results: vec![],
+            },
+            PermutationEngine::new(),
+        )
+    }
+
+    /// Recreate a scan from a previous checkpoint, using `engine` to
+    /// generate the permutations should the checkpoint predate them.
+    ///
+    /// Fails with `FingerprintMismatch` if the permutations of the
+    /// checkpoint were generated with a different engine configuration
+    /// (including that of a different crate version), as the cursor
+    /// would not point to the same permutation anymore.
+    pub fn resume(state: ScanState, engine: PermutationEngine) -> Result<ScanJob, Error> {
+        let job = ScanJob::with_state(state, engine);
+        job.check_fingerprint()?;
+
+        Ok(job)
+    }
+
+    fn with_state(state: ScanState, engine: PermutationEngine) -> ScanJob {
+        ScanJob {
+            engine,
+            pipeline: Pipeline::new(),
+            batch_size: DEFAULT_BATCH_SIZE,
+            scorer: Arc::new(CompositeScorer::new(state.domain.clone())),
+            state,
+            progress: None,
+        }
+    }
+
+    /// Make sure the permutations of the scan, if any, were generated
+    /// with the configuration of the engine.
+    fn check_fingerprint(&self) -> Result<(), Error> {
+        let Some(scan) = &self.state.fingerprint else {
+            return Ok(());
+        };
+
+        let engine = self.engine.fingerprint();
+        if *scan != engine {
+            return Err(ScanError::FingerprintMismatch {
+                scan: scan.clone(),
+                engine,
+            }
+            .into());
+        }
+
+        Ok(())
+    }
+
+    /// Replace the scorer ordering permutations riskiest first, so that a
+    /// scan that is interrupted early has covered the candidates that
+    /// matter the most, and ranking the results of the summary. Defaults
+    /// to a [`CompositeScorer`] of the domain.
+    pub fn set_scorer<S>(&mut self, scorer: S)
+    where
+        S: Scorer + 'static,
+    {
+        self.scorer = Arc::new(scorer);
+    }
+
+    /// Register a hook reporting progress of both the generation and the
+    /// enrichment phase. Enrichment progress covers the scan as a whole,
+    /// including permutations enriched before the scan was resumed.
+    pub fn on_progress<F>(&mut self, f: F)
+    where
+        F: Fn(usize, usize, Phase) + Send + Sync + 'static,
+    {
+        let callback = Callback::new(f);
+        let generation = callback.clone();
+
+        self.engine.on_progress(move |done, total_estimate, phase| {
+            generation.call(done, total_estimate, phase)
+        });
+        self.progress = Some(callback);
+    }
+
+    /// Snapshot the current state of the scan.
+    pub fn checkpoint(&self) -> ScanState {
+        self.state.clone()
+    }
+
+    /// Results gathered so far.
+    pub fn results(&self) -> &[ScanResult] {
+        &self.state.results
+    }
+
+    /// Results gathered so far, riskiest first now that they are enriched,
+    /// see [`scoring::rank_results`].
+    pub fn ranked_results(&self) -> Vec<ScanResult> {
+        scoring::rank_results(self.scorer.as_ref(), self.state.results.iter().cloned())
+    }
+
+    /// Summary of the results gathered so far, counting every generated
+    /// permutation.
+    pub fn summary(&self) -> ScanSummary {
+        let mut summary = ScanSummary::with_scorer(
+            self.state.domain.clone(),
+            self.scorer.as_ref(),
+            &self.state.results,
+        );
+
+        if let Some(permutations) = &self.state.permutations {
+            summary.set_generated(permutations);
+        }
+
+        summary
+    }
+
+    /// Whether every generated permutation has been enriched.
+    pub fn is_complete(&self) -> bool {
+        self.state
+            .permutations
+            .as_ref()
+            .is_some_and(|permutations| self.state.cursor >= permutations.len())
+    }
+
+    /// Generate the permutations of the domain, if not done already.
+    ///
+    /// Fails with `FingerprintMismatch` if the engine was replaced by one
+    /// with a different configuration since, see [`ScanJob::resume`].
+    pub fn generate(&mut self) -> Result<&[Permutation], Error> {
+        self.check_fingerprint()?;
+
+        if self.state.permutations.is_none() {
+            let mut permutations = scoring::rank(
+                self.scorer.as_ref(),
+                self.engine.generate(&self.state.domain)?,
+            );
+            permutations.dedup();
+
+            self.state.permutations = Some(permutations);
+            self.state.fingerprint = Some(self.engine.fingerprint());
+        }
+
+        Ok(self.state.permutations.as_deref().unwrap_or_default())
+    }
+
+    /// Enrich up to `batch_size` permutations starting at the cursor,
+    /// returning the number of permutations processed.
+    pub async fn step(&mut self, batch_size: usize) -> Result<usize, Error> {
+        let start = self.state.cursor;
+        let permutations = self.generate()?;
+        let total = permutations.len();
+        let batch: Vec<Permutation> = permutations
+            .iter()
+            .skip(start)
+            .take(batch_size)
+            .cloned()
+            .collect();
+        let processed = batch.len();
+
+        if self.state.base.is_none() {
+            let fqdn = &self.state.domain.fqdn;
+
+            // Scans go on without comparing nameservers if the lookup fails.
+            let base = self
+                .pipeline
+                .resolver
+                .nameservers(fqdn)
+                .await
+                .unwrap_or_else(|_| DomainMetadata::new(fqdn.clone()));
+
+            self.state.base = Some(base);
+        }
+
+        if let Some(base) = &self.state.base {
+            self.pipeline.set_nameserver_base(base.clone());
+        }
+
+        if let Some(fingerprint) = &self.state.fingerprint {
+            self.pipeline.set_fingerprint(fingerprint.clone());
+        }
+
+        let callback = self.progress.clone();
+        let results: Vec<ScanResult> = self
+            .pipeline
+            .run(batch)
+            .enumerate()
+            .map(|(i, result)| {
+                if let Some(progress) = &callback {
+                    progress.call(start + i + 1, total, Phase::Enrichment);
+                }
+
+                result
+            })
+            .collect()
+            .await;
+
+        self.state.results.extend(
+            results
+                .into_iter()
+                .filter(|result| result.metadata.dns.ips().is_some()),
+        );
+        self.state.cursor = start + processed;
+
+        Ok(processed)
+    }
+
+    /// Run the scan to completion.
+    pub async fn run(&mut self) -> Result<(), Error> {
+        while !self.is_complete() {
+            self.step(self.batch_size.max(1)).await?;
+        }
+
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    use std::collections::BTreeMap;
+    use std::sync::atomic::AtomicUsize;
+
+    use futures::future::{self, BoxFuture, FutureExt};
+
+    use crate::breaker::{BreakerError, BreakerState};
+    use crate::filter::Resolvable;
+    use crate::permutate::PermutationKind;
+    use crate::resolver::MockResolver;
+
+    #[derive(Default)]
+    struct CountingObserver {
+        permutations: AtomicUsize,
+        enriched: AtomicUsize,
+    }
+
+    impl Observer for Arc<CountingObserver> {
+        fn on_permutation(&self, _permutation: &Permutation) {
+            self.permutations.fetch_add(1, Ordering::SeqCst);
+        }
+
+        fn on_enriched(&self, result: &ScanResult) {
+            assert!(result.metadata.dns.ips().is_none());
+            self.enriched.fetch_add(1, Ordering::SeqCst);
+        }
+    }
+
+    /// Permutations under the reserved `.invalid` TLD, guaranteed to never
+    /// resolve (RFC 6761).
+    fn unresolvable(labels: &[&str]) -> Vec<Permutation> {
+        labels
+            .iter()
+            .map(|label| Permutation {
+                domain: Domain::from_parts(
+                    format!("{label}.invalid"),
+                    String::from("invalid"),
+                    label.to_string(),
+                ),
+                kind: PermutationKind::Addition,
+                substitutions: vec![],
+            })
+            .collect()
+    }
+
+    #[tokio::test]
+    async fn test_pipeline_observers() {
+        let observer = Arc::new(CountingObserver::default());
+        let mut pipeline = Pipeline::new();
+        pipeline.observe(Arc::clone(&observer));
+
+        let results: Vec<ScanResult> = pipeline
+            .run(unresolvable(&["twistrs", "twistrs-observer"]))
+            .collect()
+            .await;
+
+        assert_eq!(results.len(), 2);
+        assert_eq!(observer.permutations.load(Ordering::SeqCst), 2);
+        assert_eq!(observer.enriched.load(Ordering::SeqCst), 2);
+    }
+
+    #[tokio::test]
+    async fn test_pipeline_mock_resolver() {
+        let mut resolver = MockResolver::from_hosts("127.0.0.1 twistrs.invalid").unwrap();
+        resolver.insert_banner("twistrs.invalid", "nginx");
+
+        let mut pipeline = Pipeline::new();
+        pipeline.set_resolver(resolver);
+
+        let results: BTreeMap<String, DomainMetadata> = pipeline
+            .run(unresolvable(&["twistrs", "twistrs-mock"]))
+            .map(|result| (result.permutation.domain.fqdn, result.metadata))
+            .collect()
+            .await;
+
+        let resolved = &results["twistrs.invalid"];
+        assert_eq!(resolved.dns.ips(), Some(vec!["127.0.0.1".parse().unwrap()]));
+        assert_eq!(resolved.http_banner.as_deref(), Some("nginx"));
+        assert_eq!(resolved.neighbors, None);
+        assert_eq!(
+            results["twistrs-mock.invalid"],
+            DomainMetadata::new(String::from("twistrs-mock.invalid"))
+        );
+    }
+
+    #[tokio::test]
+    async fn test_pipeline_result_filter() {
+        let mut resolver =
+            MockResolver::from_hosts("127.0.0.1 twistrs.invalid twistrs-parked.invalid").unwrap();
+        resolver.insert_banner("twistrs.invalid", "nginx");
+
+        let mut pipeline = Pipeline::new();
+        pipeline.set_resolver(resolver);
+        pipeline.set_result_filter(|_: &Permutation, metadata: &DomainMetadata| {
+            metadata.http_banner.is_some()
+        });
+
+        let results: Vec<ScanResult> = pipeline
+            .run(unresolvable(&[
+                "twistrs",
+                "twistrs-parked",
+                "twistrs-unresolvable",
+            ]))
+            .collect()
+            .await;
+
+        assert_eq!(results.len(), 1);
+        assert_eq!(results[0].permutation.domain.fqdn, "twistrs.invalid");
+
+        pipeline.set_result_filter(Resolvable);
+        let resolvable = pipeline
+            .run(unresolvable(&[
+                "twistrs",
+                "twistrs-parked",
+                "twistrs-unresolvable",
+            ]))
+            .count()
+            .await;
+        assert_eq!(resolvable, 2);
+
+        // Without permutations to match, FQDNs are never filtered.
+        let fqdns = ["twistrs.invalid", "twistrs-unresolvable.invalid"];
+        let enriched = pipeline
+            .run_fqdns(stream::iter(fqdns.map(|fqdn| Ok(fqdn.to_string()))))
+            .count()
+            .await;
+        assert_eq!(enriched, 2);
+    }
+
+    #[tokio::test]
+    async fn test_pipeline_reverse_ip() {
+        let resolver =
+            MockResolver::from_hosts("127.0.0.1 twistrs.invalid parked.invalid").unwrap();
+
+        let mut pipeline = Pipeline::new();
+        pipeline.set_resolver(resolver.clone());
+        pipeline.set_reverse_ip(resolver);
+
+        let results: Vec<ScanResult> = pipeline.run(unresolvable(&["twistrs"])).collect().await;
+        let neighbors = results[0].metadata.neighbors.as_ref().unwrap();
+
+        assert_eq!(
+            neighbors[&"127.0.0.1".parse().unwrap()],
+            vec![String::from("parked.invalid")]
+        );
+    }
+
+    #[tokio::test]
+    async fn test_pipeline_destination_limit() {
+        let hosts: String = (0..10)
+            .map(|i| format!("127.0.0.1 twistrs{i}.invalid\n"))
+            .collect();
+
+        let resolver = Arc::new(CountingBanners {
+            inner: MockResolver::from_hosts(&hosts).unwrap(),
+            ..CountingBanners::default()
+        });
+
+        let mut pipeline = Pipeline::new();
+        pipeline.set_resolver(Arc::clone(&resolver));
+        pipeline.set_destination_limit(1);
+
+        let owned: Vec<String> = (0..10).map(|i| format!("twistrs{i}")).collect();
+        let labels: Vec<&str> = owned.iter().map(String::as_str).collect();
+
+        let results: Vec<ScanResult> = pipeline.run(unresolvable(&labels)).collect().await;
+        assert_eq!(results.len(), 10);
+        assert!(results
+            .iter()
+            .all(|result| result.metadata.dns.ips().is_some()));
+        assert_eq!(resolver.peak.load(Ordering::SeqCst), 1);
+
+        let limiter = pipeline.destinations.as_ref().unwrap();
+        assert_eq!(limiter.max_in_flight, 1);
+        assert_eq!(limiter.in_flight("127.0.0.1"), 0);
+        assert_eq!(limiter.destinations(), 0);
+
+        // Without the cap, lookups towards the same IP overlap.
+        pipeline.set_destination_limit(0);
+        assert!(pipeline.destinations.is_none());
+
+        resolver.peak.store(0, Ordering::SeqCst);
+        let _: Vec<ScanResult> = pipeline.run(unresolvable(&labels)).collect().await;
+        assert!(resolver.peak.load(Ordering::SeqCst) > 1);
+
+        // WhoIs lookups are capped per server, and only hold a slot of
+        // theirs, whereas no banner lookup takes a slot of the IP.
+        pipeline.set_destination_limit(1);
+        pipeline.set_enrichments(EnrichmentKinds::DNS | EnrichmentKinds::WHOIS);
+        resolver.peak.store(0, Ordering::SeqCst);
+
+        let _: Vec<ScanResult> = pipeline.run(unresolvable(&labels)).collect().await;
+        assert_eq!(resolver.peak.load(Ordering::SeqCst), 0);
+        assert_eq!(resolver.whois_peak.load(Ordering::SeqCst), 1);
+        assert_eq!(resolver.whois_lookups.load(Ordering::SeqCst), 10);
+    }
+
+    /// Resolver answering from `inner`, keeping track of the peak number
+    /// of HTTP banner and WHOIS lookups in flight.
+    #[derive(Default)]
+    struct CountingBanners {
+        inner: MockResolver,
+        in_flight: AtomicUsize,
+        peak: AtomicUsize,
+        whois_in_flight: AtomicUsize,
+        whois_peak: AtomicUsize,
+        whois_lookups: AtomicUsize,
+    }
+
+    impl Resolver for Arc<CountingBanners> {
+        fn dns_resolvable<'a>(
+            &'a self,
+            fqdn: &'a str,
+        ) -> BoxFuture<'a, Result<DomainMetadata, Error>> {
+            self.inner.dns_resolvable(fqdn)
+        }
+
+        fn http_banner<'a>(
+            &'a self,
+            fqdn: &'a str,
+        ) -> BoxFuture<'a, Result<DomainMetadata, Error>> {
+            async move {
+                let current = self.in_flight.fetch_add(1, Ordering::SeqCst) + 1;
+                self.peak.fetch_max(current, Ordering::SeqCst);
+                tokio::time::sleep(Duration::from_millis(5)).await;
+                self.in_flight.fetch_sub(1, Ordering::SeqCst);
+
+                self.inner.http_banner(fqdn).await
+            }
+            .boxed()
+        }
+
+        fn enrichments<'a>(
+            &'a self,
+            fqdn: &'a str,
+            kinds: EnrichmentKinds,
+        ) -> BoxFuture<'a, Result<DomainMetadata, Error>> {
+            async move {
+                if kinds.contains(EnrichmentKinds::WHOIS) {
+                    self.whois_lookups.fetch_add(1, Ordering::SeqCst);
+                    let current = self.whois_in_flight.fetch_add(1, Ordering::SeqCst) + 1;
+                    self.whois_peak.fetch_max(current, Ordering::SeqCst);
+                    tokio::time::sleep(Duration::from_millis(5)).await;
+                    self.whois_in_flight.fetch_sub(1, Ordering::SeqCst);
+                }
+
+                Ok(DomainMetadata::new(fqdn.to_string()))
+            }
+            .boxed()
+        }
+    }
+
+    /// Resolver stalling on the first `stalls` lookups, answering from
+    /// `inner` afterwards.
+    struct StallingResolver {
+        inner: MockResolver,
+        stalls: usize,
+        lookups: AtomicUsize,
+    }
+
+    impl Resolver for StallingResolver {
+        fn dns_resolvable<'a>(
+            &'a self,
+            fqdn: &'a str,
+        ) -> BoxFuture<'a, Result<DomainMetadata, Error>> {
+            if self.lookups.fetch_add(1, Ordering::SeqCst) < self.stalls {
+                return future::pending().boxed();
+            }
+
+            self.inner.dns_resolvable(fqdn)
+        }
+
+        fn http_banner<'a>(
+            &'a self,
+            fqdn: &'a str,
+        ) -> BoxFuture<'a, Result<DomainMetadata, Error>> {
+            self.inner.http_banner(fqdn)
+        }
+    }
+
+    /// Resolver answering from `inner`, with HTTP banner lookups that
+    /// never complete.
+    struct StallingBanners {
+        inner: MockResolver,
+    }
+
+    impl Resolver for StallingBanners {
+        fn dns_resolvable<'a>(
+            &'a self,
+            fqdn: &'a str,
+        ) -> BoxFuture<'a, Result<DomainMetadata, Error>> {
+            self.inner.dns_resolvable(fqdn)
+        }
+
+        fn http_banner<'a>(
+            &'a self,
+            _fqdn: &'a str,
+        ) -> BoxFuture<'a, Result<DomainMetadata, Error>> {
+            future::pending().boxed()
+        }
+    }
+
+    #[derive(Default)]
+    struct BreakerObserver {
+        timeouts: AtomicUsize,
+        short_circuits: AtomicUsize,
+    }
+
+    impl Observer for Arc<BreakerObserver> {
+        fn on_error(&self, _permutation: &Permutation, error: &Error) {
+            match error {
+                Error::BreakerError(BreakerError::Timeout { .. }) => {
+                    self.timeouts.fetch_add(1, Ordering::SeqCst);
+                }
+                Error::BreakerError(BreakerError::Open { .. }) => {
+                    self.short_circuits.fetch_add(1, Ordering::SeqCst);
+                }
+                _ => {}
+            }
+        }
+    }
+
+    #[tokio::test]
+    async fn test_pipeline_circuit_breaker() {
+        let observer = Arc::new(BreakerObserver::default());
+        let mut pipeline = Pipeline::new();
+        pipeline.concurrency = 1;
+        pipeline.observe(Arc::clone(&observer));
+        pipeline.set_resolver(StallingBanners {
+            inner: MockResolver::from_hosts(
+                "127.0.0.1 twistrs0.invalid twistrs1.invalid twistrs2.invalid",
+            )
+            .unwrap(),
+        });
+        pipeline.set_circuit_breaker(
+            CircuitBreaker::new(1, Duration::from_secs(60)),
+            Duration::from_millis(20),
+        );
+
+        let results: Vec<ScanResult> = pipeline
+            .run(unresolvable(&["twistrs0", "twistrs1", "twistrs2"]))
+            .collect()
+            .await;
+        assert_eq!(results.len(), 3);
+
+        // The first banner lookup times out, opening the circuit towards
+        // the shared IP for the other two.
+        assert_eq!(observer.timeouts.load(Ordering::SeqCst), 1);
+        assert_eq!(observer.short_circuits.load(Ordering::SeqCst), 2);
+        assert_eq!(
+            pipeline
+                .circuit_breaker()
+                .unwrap()
+                .state(EnrichmentKind::HttpBanner, "127.0.0.1"),
+            BreakerState::Open
+        );
+    }
+
+    /// Resolver marking every domain mail capable, while failing the
+    /// WHOIS lookups.
+    struct MailResolver {
+        inner: MockResolver,
+    }
+
+    impl Resolver for MailResolver {
+        fn dns_resolvable<'a>(
+            &'a self,
+            fqdn: &'a str,
+        ) -> BoxFuture<'a, Result<DomainMetadata, Error>> {
+            self.inner.dns_resolvable(fqdn)
+        }
+
+        fn http_banner<'a>(
+            &'a self,
+            fqdn: &'a str,
+        ) -> BoxFuture<'a, Result<DomainMetadata, Error>> {
+            self.inner.http_banner(fqdn)
+        }
+
+        fn enrichments<'a>(
+            &'a self,
+            fqdn: &'a str,
+            kinds: EnrichmentKinds,
+        ) -> BoxFuture<'a, Result<DomainMetadata, Error>> {
+            let mut metadata = DomainMetadata::new(fqdn.to_string());
+            metadata.mail_capable = Some(kinds.contains(EnrichmentKinds::MAIL_CAPABLE));
+
+            let enriched = if kinds.contains(EnrichmentKinds::WHOIS) {
+                Err(EnrichmentError::PartialFailure {
+                    metadata: Box::new(metadata),
+                    errors: vec![(
+                        EnrichmentKind::WhoIs,
+                        EnrichmentError::UnsupportedKind {
+                            kind: EnrichmentKind::WhoIs,
+                        }
+                        .into(),
+                    )],
+                }
+                .into())
+            } else {
+                Ok(metadata)
+            };
+
+            future::ready(enriched).boxed()
+        }
+    }
+
+    #[derive(Default)]
+    struct PartialObserver {
+        failures: AtomicUsize,
+    }
+
+    impl Observer for Arc<PartialObserver> {
+        fn on_error(&self, _permutation: &Permutation, error: &Error) {
+            if matches!(
+                error,
+                Error::EnrichmentError(EnrichmentError::PartialFailure { .. })
+            ) {
+                self.failures.fetch_add(1, Ordering::SeqCst);
+            }
+        }
+    }
+
+    #[tokio::test]
+    async fn test_pipeline_enrichments() {
+        let mut resolver = MockResolver::from_hosts("127.0.0.1 twistrs.invalid").unwrap();
+        resolver.insert_banner("twistrs.invalid", "nginx");
+
+        let mut pipeline = Pipeline::new();
+        pipeline.set_resolver(MailResolver { inner: resolver });
+
+        let defaults = &pipeline
+            .run(unresolvable(&["twistrs"]))
+            .collect::<Vec<_>>()
+            .await[0];
+        assert_eq!(defaults.metadata.http_banner.as_deref(), Some("nginx"));
+        assert_eq!(defaults.metadata.mail_capable, None);
+
+        pipeline.set_enrichments(
+            EnrichmentKinds::DNS | EnrichmentKinds::MAIL_CAPABLE | EnrichmentKinds::WHOIS,
+        );
+        let observer = Arc::new(PartialObserver::default());
+        pipeline.observe(Arc::clone(&observer));
+
+        let selected = &pipeline
+            .run(unresolvable(&["twistrs"]))
+            .collect::<Vec<_>>()
+            .await[0];
+        assert!(selected.metadata.dns.resolves());
+        assert_eq!(selected.metadata.http_banner, None);
+        // The results of the enrichments that succeeded are kept.
+        assert_eq!(selected.metadata.mail_capable, Some(true));
+        assert_eq!(observer.failures.load(Ordering::SeqCst), 1);
+    }
+
+    #[derive(Default)]
+    struct WatchdogObserver {
+        stalls: AtomicUsize,
+        heartbeats: AtomicUsize,
+        errors: AtomicUsize,
+    }
+
+    impl Observer for Arc<WatchdogObserver> {
+        fn on_error(&self, _permutation: &Permutation, error: &Error) {
+            if matches!(error, Error::WatchdogError(_)) {
+                self.errors.fetch_add(1, Ordering::SeqCst);
+            }
+        }
+
+        fn on_stall(&self, _permutation: &Permutation, _attempt: usize) {
+            self.stalls.fetch_add(1, Ordering::SeqCst);
+        }
+
+        fn on_heartbeat(&self, _heartbeat: &Heartbeat) {
+            self.heartbeats.fetch_add(1, Ordering::SeqCst);
+        }
+    }
+
+    fn watchdog() -> Watchdog {
+        Watchdog {
+            stall_timeout: Duration::from_millis(50),
+            max_restarts: 1,
+            heartbeat_interval: Duration::from_millis(10),
+        }
+    }
+
+    #[tokio::test]
+    async fn test_pipeline_adaptive_concurrency() {
+        let mut pipeline = Pipeline::new();
+        pipeline.concurrency = 8;
+        pipeline.set_resolver(StallingResolver {
+            inner: MockResolver::default(),
+            stalls: usize::MAX,
+            lookups: AtomicUsize::new(0),
+        });
+        pipeline.set_watchdog(Watchdog {
+            max_restarts: 0,
+            ..watchdog()
+        });
+        pipeline.set_adaptive_concurrency(2);
+
+        let controller = pipeline.adaptive_concurrency().unwrap().clone();
+        assert_eq!(controller.limit(), 8);
+
+        let permutations = unresolvable(&["twistrs0", "twistrs1", "twistrs2", "twistrs3"]);
+        let stalled: Vec<ScanResult> = pipeline.run(permutations.clone()).collect().await;
+        assert_eq!(stalled.len(), 4);
+        assert_eq!(controller.limit(), 4);
+        assert_eq!(controller.in_flight(), 0);
+
+        // Permutations that do not resolve are not failures.
+        pipeline.set_resolver(MockResolver::default());
+        let _: Vec<ScanResult> = pipeline.run(permutations.clone()).collect().await;
+        assert_eq!(controller.limit(), 5);
+
+        // Neither are lookups failing for reasons other than congestion.
+        let labels = ["twistrs0", "twistrs1", "twistrs2", "twistrs3", "twistrs4"];
+        pipeline.set_resolver(MailResolver {
+            inner: MockResolver::from_hosts(&format!(
+                "127.0.0.1 {}",
+                labels.map(|label| format!("{label}.invalid")).join(" ")
+            ))
+            .unwrap(),
+        });
+        pipeline.set_enrichments(EnrichmentKinds::DNS | EnrichmentKinds::WHOIS);
+        let _: Vec<ScanResult> = pipeline.run(unresolvable(&labels)).collect().await;
+        assert_eq!(controller.limit(), 6);
+
+        // The maximum follows the concurrency set after the controller.
+        pipeline.concurrency = 3;
+        let _: Vec<ScanResult> = pipeline.run(permutations).collect().await;
+        assert_eq!(controller.max(), 3);
+        assert!(controller.limit() <= 3);
+    }
+
+    #[tokio::test]
+    async fn test_pipeline_watchdog_restart() {
+        let observer = Arc::new(WatchdogObserver::default());
+        let mut pipeline = Pipeline::new();
+        pipeline.set_resolver(StallingResolver {
+            inner: MockResolver::from_hosts("127.0.0.1 twistrs.invalid").unwrap(),
+            stalls: 1,
+            lookups: AtomicUsize::new(0),
+        });
+        pipeline.set_watchdog(watchdog());
+        pipeline.observe(Arc::clone(&observer));
+
+        let results: Vec<ScanResult> = pipeline.run(unresolvable(&["twistrs"])).collect().await;
+        assert!(results[0].metadata.dns.ips().is_some());
+        assert_eq!(observer.stalls.load(Ordering::SeqCst), 1);
+        assert_eq!(observer.errors.load(Ordering::SeqCst), 0);
+        assert!(observer.heartbeats.load(Ordering::SeqCst) > 0);
+    }
+
+    #[tokio::test]
+    async fn test_pipeline_watchdog_give_up() {
+        let observer = Arc::new(WatchdogObserver::default());
+        let mut pipeline = Pipeline::new();
+        pipeline.set_resolver(StallingResolver {
+            inner: MockResolver::default(),
+            stalls: usize::MAX,
+            lookups: AtomicUsize::new(0),
+        });
+        pipeline.set_watchdog(watchdog());
+        pipeline.observe(Arc::clone(&observer));
+
+        let results: Vec<ScanResult> = pipeline.run(unresolvable(&["twistrs"])).collect().await;
+        assert_eq!(results[0].metadata.dns.ips(), None);
+        assert_eq!(observer.stalls.load(Ordering::SeqCst), 2);
+        assert_eq!(observer.errors.load(Ordering::SeqCst), 1);
+
+        let stalled: Vec<Result<DomainMetadata, Error>> = pipeline
+            .run_fqdns(stream::iter(vec![Ok(String::from("twistrs.invalid"))]))
+            .collect()
+            .await;
+        assert!(matches!(stalled[..], [Err(Error::WatchdogError(_))]));
+    }
+
+    #[test]
+    fn test_scan_job_checkpoint_and_resume() {
+        let mut job = ScanJob::new(Domain::new("example.com").unwrap());
+        let generated = job.generate().unwrap().to_vec();
+        assert!(!generated.is_empty());
+        assert!(!job.is_complete());
+        assert_eq!(job.checkpoint().fingerprint, Some(job.engine.fingerprint()));
+
+        let mut partial = job.checkpoint();
+        partial.cursor = 10;
+
+        let resumed = ScanJob::resume(partial, PermutationEngine::new()).unwrap();
+        assert_eq!(resumed.checkpoint().permutations, Some(generated.clone()));
+        assert_eq!(resumed.checkpoint().cursor, 10);
+
+        let mut finished = resumed.checkpoint();
+        finished.cursor = generated.len();
+        assert!(ScanJob::resume(finished, PermutationEngine::new())
+            .unwrap()
+            .is_complete());
+    }
+
+    #[test]
+    fn test_scan_job_rejects_other_engines() {
+        let mut job = ScanJob::new(Domain::new("example.com").unwrap());
+        job.generate().unwrap();
+
+        let mut engine = PermutationEngine::new();
+        engine.kinds = vec![PermutationKind::Tld];
+
+        assert!(matches!(
+            ScanJob::resume(job.checkpoint(), engine.clone()),
+            Err(Error::ScanError(ScanError::FingerprintMismatch { .. }))
+        ));
+
+        // Nor can the engine be swapped once resumed.
+        job.engine = engine;
+        assert!(job.generate().is_err());
+
+        // Checkpoints without permutations can be resumed with any engine.
+        let fresh = ScanJob::new(Domain::new("example.com").unwrap());
+        assert!(ScanJob::resume(fresh.checkpoint(), PermutationEngine::new()).is_ok());
+    }
+
+    #[tokio::test]
+    async fn test_scan_job_compares_nameservers() {
+        let mut resolver = MockResolver::from_hosts("127.0.0.1 examplea.com exampleb.com").unwrap();
+        resolver.insert_nameserver("example.com", "ns1.example.net");
+        resolver.insert_nameserver("examplea.com", "NS1.example.net.");
+        resolver.insert_nameserver("exampleb.com", "ns1.parking.invalid");
+
+        let mut job = ScanJob::new(Domain::new("example.com").unwrap());
+        job.engine.kinds = vec![PermutationKind::Addition];
+        job.pipeline.set_resolver(resolver);
+        job.run().await.unwrap();
+
+        let shared = |fqdn: &str| {
+            job.results()
+                .iter()
+                .find(|result| result.permutation.domain.fqdn == fqdn)
+                .and_then(|result| result.metadata.shared_nameservers)
+        };
+        assert_eq!(shared("examplea.com"), Some(true));
+        assert_eq!(shared("exampleb.com"), Some(false));
+        assert!(job
+            .checkpoint()
+            .base
+            .is_some_and(|base| base.nameservers().is_some()));
+        assert!(job
+            .results()
+            .iter()
+            .all(|result| result.fingerprint == Some(job.engine.fingerprint())));
+
+        // Both score the same before enrichment, but the likely defensive
+        // registration is ranked last once enriched.
+        let ranked = job.ranked_results();
+        assert_eq!(ranked.len(), 2);
+        assert_eq!(ranked[1].permutation.domain.fqdn, "examplea.com");
+        assert_eq!(
+            job.summary().top_risks[0].permutation.domain.fqdn,
+            "exampleb.com"
+        );
+    }
+}
@@ -0,0 +1,227 @@
+//! The progress module exposes the types used to report progress of
+//! long-running permutation and enrichment work back to clients, for
+//! instance to render progress bars in CLIs and web UIs.
+//!
+//! Example:
+//!
+//! ```
+//! use twistrs::permutate::{Domain, PermutationEngine};
+//!
+//! let domain = Domain::new("example.com").unwrap();
+//! let mut engine = PermutationEngine::new();
+//!
+//! engine.on_progress(|done, total_estimate, phase| {
+//!     eprintln!("{:?}: {}/{}", phase, done, total_estimate);
+//! });
+//!
+//! let permutations: Vec<_> = engine.generate(&domain).unwrap().collect();
+//! ```
+//!
+//! Any iterator can also be wrapped into a [`ProgressIter`], see
+//! [`with_progress`](crate::permutate::with_progress), exposing a
+//! [`Progress`] handle that can be polled from other threads or tasks.
+use std::fmt;
+use std::sync::atomic::{AtomicBool, AtomicUsize, Ordering};
+use std::sync::Arc;
+use std::time::{Duration, Instant};
+
+use serde::{Deserialize, Serialize};
+
+/// Phase of a scan that progress is being reported for.
+#[derive(Clone, Copy, Debug, Serialize, Deserialize, Hash, Eq, PartialEq)]
+pub enum Phase {
+    /// Permutations are being generated.
+    Generation,
+
+    /// Permutations are being enriched.
+    Enrichment,
+}
+
+/// Shareable progress hook, invoked with the number of items processed
+/// so far, the estimated total and the phase they belong to.
+///
+/// Note that the total is an estimate and `done` may exceed it.
+#[derive(Clone)]
+pub struct Callback(Arc<dyn Fn(usize, usize, Phase) + Send + Sync>);
+
+impl Callback {
+    /// Wrap a closure into a `Callback`.
+    pub fn new<F>(f: F) -> Callback
+    where
+        F: Fn(usize, usize, Phase) + Send + Sync + 'static,
+    {
+        Callback(Arc::new(f))
+    }
+
+    /// Report progress through the wrapped closure.
+    pub fn call(&self, done: usize, total_estimate: usize, phase: Phase) {
+        (self.0)(done, total_estimate, phase)
+    }
+}
+
+impl fmt::Debug for Callback {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str("Callback")
+    }
+}
+
+/// Shared handle over the progress of a [`ProgressIter`], cheap to clone
+/// and safe to poll from other threads or tasks while the iterator is
+/// being consumed.
+#[derive(Clone, Debug)]
+pub struct Progress(Arc<ProgressState>);
+
+#[derive(Debug)]
+struct ProgressState {
+    done: AtomicUsize,
+    finished: AtomicBool,
+    total_estimate: usize,
+    started: Instant,
+}
+
+impl Progress {
+    /// Create a handle expecting around `total_estimate` items, starting
+    /// the clock.
+    pub fn new(total_estimate: usize) -> Progress {
+        Progress(Arc::new(ProgressState {
+            done: AtomicUsize::new(0),
+            finished: AtomicBool::new(false),
+            total_estimate,
+            started: Instant::now(),
+        }))
+    }
+
+    /// Number of items yielded so far.
+    pub fn done(&self) -> usize {
+        self.0.done.load(Ordering::Relaxed)
+    }
+
+    pub fn total_estimate(&self) -> usize {
+        self.0.total_estimate
+    }
+
+    /// Whether the iterator has been exhausted.
+    pub fn is_finished(&self) -> bool {
+        self.0.finished.load(Ordering::Relaxed)
+    }
+
+    /// Time elapsed since the handle was created.
+    pub fn elapsed(&self) -> Duration {
+        self.0.started.elapsed()
+    }
+
+    /// Percentage of the estimate yielded so far, capped at 99 until the
+    /// iterator has been exhausted since the total is only an estimate.
+    pub fn percent(&self) -> usize {
+        if self.is_finished() {
+            return 100;
+        }
+
+        self.done()
+            .saturating_mul(100)
+            .checked_div(self.total_estimate())
+            .unwrap_or_default()
+            .min(99)
+    }
+
+    /// Items yielded per second, on average.
+    pub fn rate(&self) -> usize {
+        let per_second = (self.done() as u128)
+            .saturating_mul(1_000_000)
+            .checked_div(self.elapsed().as_micros())
+            .unwrap_or_default();
+
+        usize::try_from(per_second).unwrap_or(usize::MAX)
+    }
+
+    /// Estimated time remaining at the average rate so far, `None` until
+    /// the first item has been yielded.
+    pub fn eta(&self) -> Option<Duration> {
+        if self.is_finished() {
+            return Some(Duration::ZERO);
+        }
+
+        let remaining = self.total_estimate().saturating_sub(self.done()) as u128;
+        let nanos = self
+            .elapsed()
+            .as_nanos()
+            .saturating_mul(remaining)
+            .checked_div(self.done() as u128)?;
+
+        Some(Duration::from_nanos(
+            u64::try_from(nanos).unwrap_or(u64::MAX),
+        ))
+    }
+}
+
+/// Iterator wrapper keeping track of the items yielded by the wrapped
+/// iterator, see [`with_progress`](crate::permutate::with_progress).
+// Reads better than `progress::Iter` once imported.
+#[allow(clippy::module_name_repetitions)]
+#[derive(Debug)]
+pub struct ProgressIter<I> {
+    iter: I,
+    progress: Progress,
+}
+
+impl<I: Iterator> ProgressIter<I> {
+    /// Wrap `iter`, expected to yield around `total_estimate` items.
+    pub fn new(iter: I, total_estimate: usize) -> ProgressIter<I> {
+        ProgressIter {
+            iter,
+            progress: Progress::new(total_estimate),
+        }
+    }
+
+    /// Handle over the progress of the iterator.
+    pub fn progress(&self) -> Progress {
+        self.progress.clone()
+    }
+}
+
+impl<I: Iterator> Iterator for ProgressIter<I> {
+    type Item = I::Item;
+
+    fn next(&mut self) -> Option<I::Item> {
+        let item = self.iter.next();
+
+        if item.is_some() {
+            self.progress.0.done.fetch_add(1, Ordering::Relaxed);
+        } else {
+            self.progress.0.finished.store(true, Ordering::Relaxed);
+        }
+
+        item
+    }
+
+    fn size_hint(&self) -> (usize, Option<usize>) {
+        self.iter.size_hint()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_progress_iter() {
+        let mut iter = ProgressIter::new(0..10, 20);
+        let progress = iter.progress();
+
+        assert_eq!(progress.eta(), None);
+        assert_eq!(iter.by_ref().take(5).count(), 5);
+        assert_eq!(progress.done(), 5);
+        assert_eq!(progress.percent(), 25);
+        assert!(progress.eta().is_some());
+
+        assert_eq!(iter.count(), 5);
+        assert!(progress.is_finished());
+        assert_eq!(progress.percent(), 100);
+        assert_eq!(progress.eta(), Some(Duration::ZERO));
+
+        // Estimates that fall short do not report completion early.
+        let mut short = ProgressIter::new(0..10, 5);
+        short.by_ref().take(8).for_each(drop);
+        assert_eq!(short.progress().percent(), 99);
+    }
+}
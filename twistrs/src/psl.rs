@@ -0,0 +1,412 @@
+//! The psl module exposes a runtime replacement for the public suffix
+//! list that is embedded into the crate at compile time.
+//!
+//! The embedded list goes stale as new suffixes are delegated, leading
+//! to valid domains being rejected and to multi-label suffixes (e.g.
+//! `gov.uk`) being parsed incorrectly. Clients can fetch the latest
+//! list, validate it and install it, after which both [`Domain::new`]
+//! and the TLD permutations use it instead of the embedded list.
+//!
+//! Example:
+//!
+//! ```
+//! use twistrs::psl::{self, SuffixList};
+//!
+//! let list = SuffixList::parse("// ===BEGIN ICANN DOMAINS===\ncom\nuk\ngov.uk\n").unwrap();
+//! assert_eq!(list.suffix_of("www.example.gov.uk"), Some("gov.uk"));
+//!
+//! // Hot-swap the list into the parser, and later revert to the
+//! // embedded one.
+//! psl::install(list);
+//! psl::uninstall();
+//! ```
+//!
+//! ### Features
+//!
+//! Fetching the list over HTTPS (see [`fetch`] and [`refresh`]) requires
+//! the `psl_refresh` feature toggled. Parsing and installing a list is
+//! always available, e.g. for lists distributed out-of-band.
+//!
+//! [`Domain::new`]: crate::permutate::Domain::new
+use std::collections::HashSet;
+use std::sync::{Arc, RwLock};
+
+use serde::{Deserialize, Serialize};
+
+use crate::error::Error;
+
+/// Location of the canonical public suffix list.
+pub const DEFAULT_URL: &str = "https://publicsuffix.org/list/public_suffix_list.dat";
+
+/// Minimum number of rules a list must contain to be considered valid,
+/// guarding against truncated downloads. The full ICANN section contains
+/// close to 7,000 rules.
+const MIN_RULES: usize = 1000;
+
+/// Suffixes that any complete list must contain.
+const REQUIRED_SUFFIXES: [&str; 3] = ["com", "net", "org"];
+
+const ICANN_BEGIN: &str = "===BEGIN ICANN DOMAINS===";
+const ICANN_END: &str = "===END ICANN DOMAINS===";
+const PRIVATE_BEGIN: &str = "===BEGIN PRIVATE DOMAINS===";
+const PRIVATE_END: &str = "===END PRIVATE DOMAINS===";
+
+lazy_static! {
+    static ref ACTIVE: RwLock<Option<Arc<SuffixList>>> = RwLock::new(None);
+}
+
+#[derive(thiserror::Error, Debug)]
+pub enum SuffixListError {
+    #[error("error fetching public suffix list (url: {url}, error: {error})")]
+    FetchError { url: String, error: anyhow::Error },
+
+    #[error("invalid public suffix list (reason: {reason})")]
+    InvalidList { reason: String },
+}
+
+/// Section of the public suffix list a suffix comes from.
+#[derive(Clone, Copy, Debug, Serialize, Deserialize, Hash, Eq, PartialEq, Ord, PartialOrd)]
+pub enum SuffixSection {
+    /// Suffixes delegated by ICANN, e.g. `com` or `co.uk`.
+    Icann,
+
+    /// Suffixes submitted by the operators of shared platforms, e.g.
+    /// `github.io`, under which anyone can register a name.
+    Private,
+}
+
+/// Rules of a single section of the list.
+#[derive(Debug, Clone, Default)]
+struct Rules {
+    rules: HashSet<String>,
+    wildcards: HashSet<String>,
+    exceptions: HashSet<String>,
+}
+
+impl Rules {
+    fn insert(&mut self, rule: String) {
+        if let Some(exception) = rule.strip_prefix('!') {
+            self.exceptions.insert(exception.to_string());
+        } else if let Some(parent) = rule.strip_prefix("*.") {
+            self.wildcards.insert(parent.to_string());
+        } else {
+            self.rules.insert(rule);
+        }
+    }
+
+    /// Every suffix that can be registered under, sorted.
+    fn suffixes(&self) -> Vec<String> {
+        let mut suffixes: Vec<String> = self
+            .rules
+            .iter()
+            .chain(self.wildcards.iter())
+            .cloned()
+            .collect::<HashSet<String>>()
+            .into_iter()
+            .collect();
+
+        suffixes.sort();
+        suffixes
+    }
+
+    fn suffix_of<'a>(&self, fqdn: &'a str) -> Option<&'a str> {
+        let name = fqdn.trim_end_matches('.');
+        let starts =
+            std::iter::once(0).chain(name.match_indices('.').map(|(offset, _)| offset + 1));
+
+        // Candidates are visited longest first, so the first match is the
+        // prevailing rule.
+        for start in starts {
+            let candidate = name.get(start..)?;
+            let lowercase = candidate.to_lowercase();
+            let parent = candidate
+                .find('.')
+                .and_then(|offset| candidate.get(offset + 1..));
+
+            if self.exceptions.contains(&lowercase) {
+                return parent;
+            }
+
+            if self.rules.contains(&lowercase)
+                || parent.is_some_and(|suffix| self.wildcards.contains(&suffix.to_lowercase()))
+            {
+                return Some(candidate);
+            }
+        }
+
+        None
+    }
+}
+
+/// Parsed ICANN and private sections of the public suffix list.
+#[derive(Debug, Clone, Default)]
+pub struct SuffixList {
+    icann: Rules,
+    private: Rules,
+
+    /// Every ICANN suffix that can be registered under, sorted.
+    suffixes: Vec<String>,
+
+    /// Every private suffix that can be registered under, sorted.
+    private_suffixes: Vec<String>,
+}
+
+impl SuffixList {
+    /// Parse the ICANN and private sections of a list in the public suffix
+    /// list format. Private suffixes are kept apart, and only used when
+    /// asked for (see [`SuffixList::private_suffix_of`]), matching the
+    /// embedded list.
+    pub fn parse(text: &str) -> Result<SuffixList, Error> {
+        let mut list = SuffixList::default();
+        let mut section = None;
+
+        for line in text.lines().map(str::trim) {
+            if line.contains(ICANN_BEGIN) {
+                section = Some(SuffixSection::Icann);
+            } else if line.contains(PRIVATE_BEGIN) {
+                section = Some(SuffixSection::Private);
+            } else if line.contains(ICANN_END) || line.contains(PRIVATE_END) {
+                section = None;
+            } else if !line.is_empty() && !line.starts_with("//") {
+                // Rules are only ever made up of the first token on a line.
+                let rule = line
+                    .split_whitespace()
+                    .next()
+                    .unwrap_or_default()
+                    .to_lowercase();
+
+                match section {
+                    Some(SuffixSection::Icann) => list.icann.insert(rule),
+                    Some(SuffixSection::Private) => list.private.insert(rule),
+                    None => {}
+                }
+            }
+        }
+
+        if list.icann.rules.is_empty() {
+            return Err(SuffixListError::InvalidList {
+                reason: "no ICANN rules found".to_string(),
+            }
+            .into());
+        }
+
+        list.suffixes = list.icann.suffixes();
+        list.private_suffixes = list.private.suffixes();
+
+        Ok(list)
+    }
+
+    /// Sanity check the list before it is installed, rejecting lists that
+    /// are truncated or otherwise incomplete.
+    pub fn validate(&self) -> Result<(), Error> {
+        if self.icann.rules.len() < MIN_RULES {
+            return Err(SuffixListError::InvalidList {
+                reason: format!(
+                    "expected at least {MIN_RULES} rules, found {}",
+                    self.icann.rules.len()
+                ),
+            }
+            .into());
+        }
+
+        if let Some(missing) = REQUIRED_SUFFIXES
+            .iter()
+            .find(|suffix| !self.icann.rules.contains(**suffix))
+        {
+            return Err(SuffixListError::InvalidList {
+                reason: format!("missing required suffix {missing}"),
+            }
+            .into());
+        }
+
+        Ok(())
+    }
+
+    /// Find the public suffix of `fqdn` (e.g. `gov.uk` for
+    /// `www.example.gov.uk`), returning `None` if the name is not under
+    /// any ICANN suffix in the list.
+    pub fn suffix_of<'a>(&self, fqdn: &'a str) -> Option<&'a str> {
+        self.icann.suffix_of(fqdn)
+    }
+
+    /// Find the private suffix of `fqdn` (e.g. `github.io` for
+    /// `example.github.io`), returning `None` if the name is not under any
+    /// private suffix in the list.
+    pub fn private_suffix_of<'a>(&self, fqdn: &'a str) -> Option<&'a str> {
+        self.private.suffix_of(fqdn)
+    }
+
+    /// Section `suffix` comes from, `None` if it is not a suffix of the
+    /// list. Suffixes listed in both sections are ICANN ones.
+    pub fn section_of(&self, suffix: &str) -> Option<SuffixSection> {
+        let trimmed = suffix.trim_end_matches('.');
+        let name = format!("twistrs.{trimmed}");
+        let matches = |found: Option<&str>| {
+            found.is_some_and(|matched| matched.eq_ignore_ascii_case(trimmed))
+        };
+
+        if matches(self.icann.suffix_of(&name)) {
+            Some(SuffixSection::Icann)
+        } else if matches(self.private.suffix_of(&name)) {
+            Some(SuffixSection::Private)
+        } else {
+            None
+        }
+    }
+
+    /// Every ICANN suffix that can be registered under, sorted.
+    pub fn suffixes(&self) -> &[String] {
+        &self.suffixes
+    }
+
+    /// Every private suffix that can be registered under, sorted.
+    pub fn private_suffixes(&self) -> &[String] {
+        &self.private_suffixes
+    }
+}
+
+/// Install `list`, replacing the embedded list (or a previously installed
+/// one) for every subsequent parse.
+pub fn install(list: SuffixList) -> Arc<SuffixList> {
+    let installed = Arc::new(list);
+
+    // The lock is never held across a panic, recover the guard regardless.
+    *ACTIVE
+        .write()
+        .unwrap_or_else(std::sync::PoisonError::into_inner) = Some(Arc::clone(&installed));
+
+    installed
+}
+
+/// Revert to the list embedded at compile time.
+pub fn uninstall() {
+    *ACTIVE
+        .write()
+        .unwrap_or_else(std::sync::PoisonError::into_inner) = None;
+}
+
+/// The installed list, if any.
+pub fn active() -> Option<Arc<SuffixList>> {
+    ACTIVE
+        .read()
+        .unwrap_or_else(std::sync::PoisonError::into_inner)
+        .clone()
+}
+
+/// Download and validate the list at `url`, without installing it.
+///
+/// ### Features
+///
+/// This function requires the `psl_refresh` feature toggled.
+#[cfg(feature = "psl_refresh")]
+pub async fn fetch(url: &str) -> Result<SuffixList, Error> {
+    let fetch_error = |error: anyhow::Error| SuffixListError::FetchError {
+        url: url.to_string(),
+        error,
+    };
+
+    let uri: hyper::Uri = url
+        .parse()
+        .map_err(|e| fetch_error(anyhow::Error::msg(e)))?;
+
+    let connector = hyper_rustls::HttpsConnectorBuilder::new()
+        .with_webpki_roots()
+        .https_only()
+        .enable_http1()
+        .build();
+    let client: hyper::Client<_, hyper::Body> = hyper::Client::builder().build(connector);
+
+    let response = client
+        .get(uri)
+        .await
+        .map_err(|e| fetch_error(anyhow::Error::msg(e)))?;
+
+    if !response.status().is_success() {
+        return Err(fetch_error(anyhow::anyhow!("unexpected status {}", response.status())).into());
+    }
+
+    let body = hyper::body::to_bytes(response.into_body())
+        .await
+        .map_err(|e| fetch_error(anyhow::Error::msg(e)))?;
+    let text = std::str::from_utf8(&body).map_err(|e| fetch_error(anyhow::Error::msg(e)))?;
+
+    let list = SuffixList::parse(text)?;
+    list.validate()?;
+
+    Ok(list)
+}
+
+/// Download the latest list from [`DEFAULT_URL`] and install it. The
+/// currently active list is kept if the download fails validation.
+///
+/// ### Features
+///
+/// This function requires the `psl_refresh` feature toggled.
+#[cfg(feature = "psl_refresh")]
+pub async fn refresh() -> Result<Arc<SuffixList>, Error> {
+    Ok(install(fetch(DEFAULT_URL).await?))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const LIST: &str = "\
+// ===BEGIN ICANN DOMAINS===
+com
+uk
+gov.uk
+co.uk
+// Wildcard with an exception.
+*.kawasaki.jp
+!city.kawasaki.jp
+// ===END ICANN DOMAINS===
+// ===BEGIN PRIVATE DOMAINS===
+github.io
+// ===END PRIVATE DOMAINS===
+";
+
+    #[test]
+    fn test_suffix_of() {
+        let list = SuffixList::parse(LIST).unwrap();
+
+        assert_eq!(list.suffix_of("www.example.gov.uk"), Some("gov.uk"));
+        assert_eq!(list.suffix_of("example.COM"), Some("COM"));
+        assert_eq!(
+            list.suffix_of("example.foo.kawasaki.jp"),
+            Some("foo.kawasaki.jp")
+        );
+        assert_eq!(list.suffix_of("city.kawasaki.jp"), Some("kawasaki.jp"));
+        assert_eq!(list.suffix_of("example.github.io"), None);
+        assert_eq!(list.suffix_of("example.invalid"), None);
+
+        assert!(list.suffixes().contains(&"kawasaki.jp".to_string()));
+        assert!(!list.suffixes().contains(&"github.io".to_string()));
+    }
+
+    #[test]
+    fn test_private_suffixes() {
+        let list = SuffixList::parse(LIST).unwrap();
+
+        assert_eq!(
+            list.private_suffix_of("example.GitHub.io"),
+            Some("GitHub.io")
+        );
+        assert_eq!(list.private_suffix_of("example.com"), None);
+        assert_eq!(list.private_suffixes(), &["github.io".to_string()]);
+
+        assert_eq!(list.section_of("co.uk"), Some(SuffixSection::Icann));
+        assert_eq!(list.section_of("github.io"), Some(SuffixSection::Private));
+        assert_eq!(
+            list.section_of("foo.kawasaki.jp"),
+            Some(SuffixSection::Icann)
+        );
+        assert_eq!(list.section_of("example.com"), None);
+    }
+
+    #[test]
+    fn test_validate_rejects_incomplete_lists() {
+        assert!(SuffixList::parse("com\nnet\n").is_err());
+        assert!(SuffixList::parse(LIST).unwrap().validate().is_err());
+    }
+}
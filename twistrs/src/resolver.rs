@@ -0,0 +1,410 @@
+//! The resolver module exposes the [`Resolver`] trait, the source of the
+//! DNS and HTTP banner lookups performed by a [`Pipeline`], along with
+//! two implementations.
+//!
+//! * [`SystemResolver`]—the default, performs live lookups through an
+//!   [`EnrichmentContext`].
+//! * [`MockResolver`]—reads from user-supplied fixtures, useful for
+//!   deterministic tests and for running without network access.
+//!
+//! Fixtures can be loaded from a hosts-style file, or recorded from live
+//! results and (de)serialized to JSON.
+//!
+//! The [`ReverseIp`] trait is the hook for passive DNS or reverse-IP data
+//! sources, listing the domains co-hosted with each candidate, see
+//! [`Pipeline::set_reverse_ip`].
+//!
+//! Example:
+//!
+//! ```
+//! use futures::StreamExt;
+//! use twistrs::permutate::Domain;
+//! use twistrs::pipeline::Pipeline;
+//! use twistrs::resolver::MockResolver;
+//!
+//! #[tokio::main]
+//! async fn main() {
+//!     let mut resolver = MockResolver::from_hosts("127.0.0.1 examplea.com").unwrap();
+//!     resolver.insert_banner("examplea.com", "nginx");
+//!
+//!     let mut pipeline = Pipeline::new();
+//!     pipeline.set_resolver(resolver);
+//!
+//!     let domain = Domain::new("example.com").unwrap();
+//!     let results: Vec<_> = pipeline.run(domain.addition()).collect().await;
+//! }
+//! ```
+//!
+//! [`Pipeline`]: crate::pipeline::Pipeline
+//! [`EnrichmentContext`]: crate::enrich::EnrichmentContext
+//! [`Pipeline::set_reverse_ip`]: crate::pipeline::Pipeline::set_reverse_ip
+
+// The names of the resolvers read better with the suffix.
+#![allow(clippy::module_name_repetitions)]
+
+use std::collections::BTreeMap;
+use std::net::IpAddr;
+use std::sync::Arc;
+
+use futures::future::{ready, BoxFuture, FutureExt};
+use serde::{Deserialize, Serialize};
+
+use crate::enrich::{
+    DnsRecord, DnsRecordType, DnsRecords, DomainMetadata, EnrichmentContext, EnrichmentError,
+    EnrichmentKinds,
+};
+use crate::error::Error;
+
+#[derive(thiserror::Error, Debug)]
+pub enum ResolverError {
+    #[error("error parsing hosts fixture (line: {line}, error: {error})")]
+    HostsError { line: usize, error: anyhow::Error },
+}
+
+/// Source of the DNS and HTTP banner lookups of a pipeline.
+///
+/// Both methods mirror their [`DomainMetadata`] counterparts, returning
+/// a `DomainMetadata` holding only the fields they look up.
+pub trait Resolver: Send + Sync {
+    /// Resolve the IPs of `fqdn`, see [`DomainMetadata::dns_resolvable`].
+    fn dns_resolvable<'a>(&'a self, fqdn: &'a str) -> BoxFuture<'a, Result<DomainMetadata, Error>>;
+
+    /// Fetch the HTTP server banner of `fqdn`, see
+    /// [`DomainMetadata::http_banner`].
+    fn http_banner<'a>(&'a self, fqdn: &'a str) -> BoxFuture<'a, Result<DomainMetadata, Error>>;
+
+    /// Look the nameservers of `fqdn` up, see
+    /// [`DomainMetadata::nameservers`]. Defaults to leaving them unset,
+    /// for resolvers without NS data.
+    fn nameservers<'a>(&'a self, fqdn: &'a str) -> BoxFuture<'a, Result<DomainMetadata, Error>> {
+        ready(Ok(DomainMetadata::new(fqdn.to_string()))).boxed()
+    }
+
+    /// Run the other enrichments of `kinds` on `fqdn`, see
+    /// [`DomainMetadata::run`] and [`Pipeline::set_enrichments`].
+    /// Defaults to leaving their fields unset, for resolvers without
+    /// such data.
+    ///
+    /// [`Pipeline::set_enrichments`]: crate::pipeline::Pipeline::set_enrichments
+    fn enrichments<'a>(
+        &'a self,
+        fqdn: &'a str,
+        _kinds: EnrichmentKinds,
+    ) -> BoxFuture<'a, Result<DomainMetadata, Error>> {
+        ready(Ok(DomainMetadata::new(fqdn.to_string()))).boxed()
+    }
+}
+
+/// Source of the domains co-hosted on an IP, e.g. backed by passive DNS
+/// or a reverse-IP service. No implementation performs live lookups, as
+/// there is no standard protocol for them.
+pub trait ReverseIp: Send + Sync {
+    /// Domains known to be hosted on `ip`.
+    fn neighbors(&self, ip: IpAddr) -> BoxFuture<'_, Result<Vec<String>, Error>>;
+}
+
+/// Resolver performing live lookups through the clients of its
+/// [`EnrichmentContext`].
+#[derive(Clone, Debug, Default)]
+pub struct SystemResolver {
+    context: Arc<EnrichmentContext>,
+}
+
+impl SystemResolver {
+    pub fn new(context: EnrichmentContext) -> SystemResolver {
+        SystemResolver {
+            context: Arc::new(context),
+        }
+    }
+}
+
+impl Resolver for SystemResolver {
+    fn dns_resolvable<'a>(&'a self, fqdn: &'a str) -> BoxFuture<'a, Result<DomainMetadata, Error>> {
+        async move {
+            DomainMetadata::new(fqdn.to_string())
+                .dns_resolvable_with_context(&self.context)
+                .await
+        }
+        .boxed()
+    }
+
+    fn http_banner<'a>(&'a self, fqdn: &'a str) -> BoxFuture<'a, Result<DomainMetadata, Error>> {
+        async move {
+            DomainMetadata::new(fqdn.to_string())
+                .http_banner_with_context(&self.context)
+                .await
+        }
+        .boxed()
+    }
+
+    fn enrichments<'a>(
+        &'a self,
+        fqdn: &'a str,
+        kinds: EnrichmentKinds,
+    ) -> BoxFuture<'a, Result<DomainMetadata, Error>> {
+        async move {
+            DomainMetadata::new(fqdn.to_string())
+                .run_with_context(kinds, &self.context)
+                .await
+        }
+        .boxed()
+    }
+
+    // Without the `dns` feature toggled, nameservers are left unset.
+    #[cfg(feature = "dns")]
+    fn nameservers<'a>(&'a self, fqdn: &'a str) -> BoxFuture<'a, Result<DomainMetadata, Error>> {
+        async move {
+            DomainMetadata::new(fqdn.to_string())
+                .nameserver_lookup_with_context(&self.context)
+                .await
+        }
+        .boxed()
+    }
+}
+
+/// Resolver answering from fixtures, without any network access.
+///
+/// Domains without a host entry do not resolve, and domains without a
+/// banner entry fail the banner lookup, as they would live. Lookups are
+/// case-insensitive.
+#[derive(Clone, Debug, Default, Serialize, Deserialize, Eq, PartialEq)]
+#[serde(default, deny_unknown_fields)]
+pub struct MockResolver {
+    /// IPs each domain resolves to.
+    pub hosts: BTreeMap<String, Vec<IpAddr>>,
+
+    /// HTTP server banner each domain serves.
+    pub banners: BTreeMap<String, String>,
+
+    /// Nameservers of each domain. Domains without an entry leave their
+    /// nameservers unset.
+    pub nameservers: BTreeMap<String, Vec<String>>,
+}
+
+impl MockResolver {
+    /// Create a resolver without any fixtures, where no domain resolves.
+    pub fn new() -> MockResolver {
+        MockResolver::default()
+    }
+
+    /// Parse a hosts-style fixture, where each line holds an IP followed
+    /// by the domains resolving to it. Blank lines and `#` comments are
+    /// skipped.
+    pub fn from_hosts(hosts: &str) -> Result<MockResolver, Error> {
+        let mut resolver = MockResolver::new();
+
+        for (i, line) in hosts.lines().enumerate() {
+            let mut fields = line
+                .split('#')
+                .next()
+                .unwrap_or_default()
+                .split_whitespace();
+
+            let Some(address) = fields.next() else {
+                continue;
+            };
+
+            let ip: IpAddr = address.parse().map_err(|e| ResolverError::HostsError {
+                line: i + 1,
+                error: anyhow::Error::msg(e),
+            })?;
+
+            let mut names = fields.peekable();
+            if names.peek().is_none() {
+                return Err(ResolverError::HostsError {
+                    line: i + 1,
+                    error: anyhow::Error::msg("missing domain name"),
+                }
+                .into());
+            }
+
+            for name in names {
+                resolver.insert_host(name, ip);
+            }
+        }
+
+        Ok(resolver)
+    }
+
+    /// Add `ip` to the IPs `fqdn` resolves to.
+    pub fn insert_host(&mut self, fqdn: &str, ip: IpAddr) {
+        let ips = self.hosts.entry(normalize(fqdn)).or_default();
+
+        if !ips.contains(&ip) {
+            ips.push(ip);
+        }
+    }
+
+    /// Set the HTTP server banner served by `fqdn`.
+    pub fn insert_banner(&mut self, fqdn: &str, banner: &str) {
+        self.banners.insert(normalize(fqdn), banner.to_string());
+    }
+
+    /// Add `nameserver` to the nameservers of `fqdn`.
+    pub fn insert_nameserver(&mut self, fqdn: &str, nameserver: &str) {
+        let nameservers = self.nameservers.entry(normalize(fqdn)).or_default();
+        let name = normalize(nameserver);
+
+        if !nameservers.contains(&name) {
+            nameservers.push(name);
+        }
+    }
+
+    /// Record the IPs, banner and nameservers of live `metadata` as
+    /// fixtures, so that a live run can be replayed later on.
+    pub fn record(&mut self, metadata: &DomainMetadata) {
+        for ip in metadata.dns.ips().into_iter().flatten() {
+            self.insert_host(&metadata.fqdn, ip);
+        }
+
+        if let Some(banner) = &metadata.http_banner {
+            self.insert_banner(&metadata.fqdn, banner);
+        }
+
+        for nameserver in metadata.nameservers().into_iter().flatten() {
+            self.insert_nameserver(&metadata.fqdn, &nameserver);
+        }
+    }
+}
+
+impl Resolver for MockResolver {
+    fn dns_resolvable<'a>(&'a self, fqdn: &'a str) -> BoxFuture<'a, Result<DomainMetadata, Error>> {
+        let result = match self.hosts.get(&normalize(fqdn)) {
+            Some(ips) => Ok(DomainMetadata {
+                dns: DnsRecords::from_ips(ips.iter().copied()),
+                ..DomainMetadata::new(fqdn.to_string())
+            }),
+            None => Err(EnrichmentError::DnsResolutionError {
+                domain: fqdn.to_string(),
+            }
+            .into()),
+        };
+
+        ready(result).boxed()
+    }
+
+    fn http_banner<'a>(&'a self, fqdn: &'a str) -> BoxFuture<'a, Result<DomainMetadata, Error>> {
+        let result = match self.banners.get(&normalize(fqdn)) {
+            Some(banner) => Ok(DomainMetadata {
+                http_banner: Some(banner.clone()),
+                ..DomainMetadata::new(fqdn.to_string())
+            }),
+            None => Err(EnrichmentError::HttpBannerError {
+                domain: fqdn.to_string(),
+                error: anyhow::Error::msg("no banner fixture for domain"),
+            }
+            .into()),
+        };
+
+        ready(result).boxed()
+    }
+
+    fn nameservers<'a>(&'a self, fqdn: &'a str) -> BoxFuture<'a, Result<DomainMetadata, Error>> {
+        let ns = self.nameservers.get(&normalize(fqdn)).map(|nameservers| {
+            nameservers
+                .iter()
+                .map(|nameserver| DnsRecord::new(DnsRecordType::Ns, nameserver.as_str()))
+                .collect()
+        });
+
+        ready(Ok(DomainMetadata {
+            dns: DnsRecords {
+                ns,
+                ..DnsRecords::default()
+            },
+            ..DomainMetadata::new(fqdn.to_string())
+        }))
+        .boxed()
+    }
+}
+
+/// Neighbors are the domains whose host fixtures include the IP.
+impl ReverseIp for MockResolver {
+    fn neighbors(&self, ip: IpAddr) -> BoxFuture<'_, Result<Vec<String>, Error>> {
+        let neighbors = self
+            .hosts
+            .iter()
+            .filter(|(_, ips)| ips.contains(&ip))
+            .map(|(fqdn, _)| fqdn.clone())
+            .collect();
+
+        ready(Ok(neighbors)).boxed()
+    }
+}
+
+fn normalize(fqdn: &str) -> String {
+    fqdn.trim_end_matches('.').to_lowercase()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[tokio::test]
+    async fn test_mock_resolver_from_hosts() {
+        let resolver = MockResolver::from_hosts(
+            "# fixtures\n127.0.0.1 examplea.com EXAMPLEB.com\n\n::1 examplea.com # v6\n",
+        )
+        .unwrap();
+
+        let resolved = resolver.dns_resolvable("ExampleA.com.").await.unwrap();
+        assert_eq!(
+            resolved.dns.ips().unwrap(),
+            vec![
+                "127.0.0.1".parse::<IpAddr>().unwrap(),
+                "::1".parse().unwrap()
+            ]
+        );
+        assert!(resolver.dns_resolvable("exampleb.com").await.is_ok());
+        assert!(resolver.dns_resolvable("examplec.com").await.is_err());
+        assert!(resolver.http_banner("examplea.com").await.is_err());
+
+        assert!(MockResolver::from_hosts("not-an-ip examplea.com").is_err());
+        assert!(MockResolver::from_hosts("127.0.0.1").is_err());
+    }
+
+    #[tokio::test]
+    async fn test_mock_resolver_neighbors() {
+        let resolver =
+            MockResolver::from_hosts("127.0.0.1 examplea.com exampleb.com\n127.0.0.2 examplec.com")
+                .unwrap();
+
+        let mut metadata = resolver.dns_resolvable("examplea.com").await.unwrap();
+        metadata = metadata.reverse_ip_lookup(&resolver).await.unwrap();
+
+        let neighbors = metadata.neighbors.unwrap();
+        assert_eq!(
+            neighbors[&"127.0.0.1".parse::<IpAddr>().unwrap()],
+            vec![String::from("exampleb.com")]
+        );
+    }
+
+    #[tokio::test]
+    async fn test_mock_resolver_record_round_trip() {
+        let mut metadata = DomainMetadata::new(String::from("examplea.com"));
+        metadata.dns = DnsRecords::from_ips(["127.0.0.1".parse().unwrap()]);
+        metadata.http_banner = Some(String::from("nginx"));
+        metadata.dns.ns = Some(vec![DnsRecord::new(DnsRecordType::Ns, "NS1.example.net.")]);
+
+        let mut resolver = MockResolver::new();
+        resolver.record(&metadata);
+
+        let json = serde_json::to_string(&resolver).unwrap();
+        let replayed: MockResolver = serde_json::from_str(&json).unwrap();
+        assert_eq!(replayed, resolver);
+
+        let banner = replayed.http_banner("examplea.com").await.unwrap();
+        assert_eq!(banner.http_banner.as_deref(), Some("nginx"));
+
+        let nameservers = replayed.nameservers("examplea.com").await.unwrap();
+        assert_eq!(nameservers.nameservers(), metadata.nameservers());
+        assert_eq!(
+            replayed
+                .nameservers("exampleb.com")
+                .await
+                .unwrap()
+                .nameservers(),
+            None
+        );
+    }
+}
@@ -0,0 +1,660 @@
+//! The scoring module ranks permutations by how dangerous they are to
+//! the domain they were generated from. Scores are used by the
+//! [`Pipeline`](crate::pipeline::Pipeline) to spend network budget on the
+//! riskiest candidates first.
+//!
+//! Edits near the start of the domain weigh more than those near its end,
+//! since they are more noticeable, along a configurable [`PositionCurve`].
+//!
+//! Example:
+//!
+//! ```
+//! use twistrs::permutate::{Domain, Permutation, PermutationKind};
+//! use twistrs::scoring::CompositeScorer;
+//!
+//! let base = Domain::new("example.com").unwrap();
+//! let scorer = CompositeScorer::new(base);
+//!
+//! let close = Permutation {
+//!     domain: Domain::new("examp1e.com").unwrap(),
+//!     kind: PermutationKind::Homoglyph,
+//!     substitutions: vec![],
+//! };
+//! let far = Permutation {
+//!     domain: Domain::new("example-login.com").unwrap(),
+//!     kind: PermutationKind::Keyword,
+//!     substitutions: vec![],
+//! };
+//!
+//! assert!(scorer.score(&close) > scorer.score(&far));
+//! ```
+//!
+//! Once enriched, results can be re-scored with
+//! [`CompositeScorer::score_result`] and re-ranked with
+//! [`CompositeScorer::rank_results`], which de-prioritize candidates that
+//! are most likely defensive registrations of the base domain.
+//!
+//! With the `enrich` feature toggled, other models (e.g. a trained
+//! classifier) can be plugged into the prioritization and ranking paths
+//! of the pipeline by implementing [`Scorer`].
+
+// Scores are inherently fractional, unlike the rest of the crate.
+#![allow(clippy::float_arithmetic)]
+
+use std::cmp::Ordering;
+use std::collections::{BTreeMap, BinaryHeap};
+
+use serde::{Deserialize, Serialize};
+
+#[cfg(feature = "enrich")]
+use crate::enrich::DomainMetadata;
+use crate::permutate::{Domain, Permutation, PermutationKind};
+#[cfg(feature = "enrich")]
+use crate::pipeline::ScanResult;
+
+/// Weight given to permutation kinds without an explicit weight.
+const DEFAULT_KIND_WEIGHT: f64 = 0.5;
+
+/// Factor applied to the score of likely defensive registrations.
+const DEFAULT_DEFENSIVE_WEIGHT: f64 = 0.1;
+
+/// Weight of edits at the end of the domain under the default
+/// [`PositionCurve`], relative to those at its start.
+const DEFAULT_END_WEIGHT: f64 = 0.5;
+
+/// Model scoring how dangerous a permutation is to the domain it was
+/// generated from, between `0.0` (harmless) and `1.0` (most dangerous),
+/// along with its enriched metadata once available.
+///
+/// [`CompositeScorer`] is the default implementation, see
+/// [`ScanJob::set_scorer`](crate::pipeline::ScanJob::set_scorer) and
+/// [`Pipeline::run_prioritized`](crate::pipeline::Pipeline::run_prioritized).
+///
+/// ### Features
+///
+/// This trait requires the `enrich` feature toggled.
+#[cfg(feature = "enrich")]
+pub trait Scorer: Send + Sync {
+    /// Score `permutation`, along with its `metadata` if it was enriched.
+    fn score(&self, permutation: &Permutation, metadata: Option<&DomainMetadata>) -> f64;
+}
+
+/// Default scorer combining how visually close a permutation is to the
+/// base domain with how likely its kind is to be used maliciously.
+///
+/// Scores range from `0.0` (harmless) to `1.0` (most dangerous).
+#[derive(Clone, Debug, Serialize, Deserialize)]
+pub struct CompositeScorer {
+    /// Domain that permutations are compared against.
+    pub base: Domain,
+
+    /// Relative risk of each permutation kind, between `0.0` and `1.0`.
+    /// Kinds that are not present are weighted `0.5`.
+    pub kind_weights: BTreeMap<PermutationKind, f64>,
+
+    /// Factor applied to the score of enriched results that share a
+    /// nameserver with the base domain, as they are almost certainly
+    /// defensive registrations.
+    #[serde(default = "default_defensive_weight")]
+    pub defensive_weight: f64,
+
+    /// How much edits weigh depending on where they occur in the root
+    /// label, see [`positional_similarity`].
+    #[serde(default)]
+    pub position_curve: PositionCurve,
+}
+
+/// Weight of an edit depending on its position in the root label, as
+/// edits near the start of a label are more noticeable than those near
+/// its end (e.g. `xample.com` stands out more than `exampl.com`).
+///
+/// Weights are relative to the first character, which always weighs
+/// `1.0`.
+#[derive(Clone, Copy, Debug, Serialize, Deserialize, PartialEq)]
+pub enum PositionCurve {
+    /// Every position weighs the same, i.e. plain [`similarity`].
+    Flat,
+
+    /// Weights decrease linearly down to `end` at the last character.
+    Linear { end: f64 },
+
+    /// Each position weighs `decay` times the previous one.
+    Exponential { decay: f64 },
+}
+
+impl Default for PositionCurve {
+    fn default() -> PositionCurve {
+        PositionCurve::Linear {
+            end: DEFAULT_END_WEIGHT,
+        }
+    }
+}
+
+impl PositionCurve {
+    /// Weight of each of `len` positions, never negative.
+    pub fn weights(&self, len: usize) -> Vec<f64> {
+        match *self {
+            PositionCurve::Flat => vec![1.0; len],
+            PositionCurve::Linear { end } => (0..len)
+                .map(|i| 1.0 - (1.0 - end) * ratio(i, len.saturating_sub(1).max(1)))
+                .map(|weight| weight.max(0.0))
+                .collect(),
+            PositionCurve::Exponential { decay } => {
+                std::iter::successors(Some(1.0_f64), |weight| Some(weight * decay.max(0.0)))
+                    .take(len)
+                    .collect()
+            }
+        }
+    }
+}
+
+impl CompositeScorer {
+    /// Create a scorer for permutations of `base` using the default
+    /// kind weights.
+    pub fn new(base: Domain) -> CompositeScorer {
+        let kind_weights = PermutationKind::ALL
+            .iter()
+            .map(|kind| (*kind, default_kind_weight(*kind)))
+            .collect();
+
+        CompositeScorer {
+            base,
+            kind_weights,
+            defensive_weight: DEFAULT_DEFENSIVE_WEIGHT,
+            position_curve: PositionCurve::default(),
+        }
+    }
+
+    /// Score `permutation` against the base domain, weighing the edits
+    /// of its root label by position (see
+    /// [`CompositeScorer::position_curve`]) and comparing its TLD as a
+    /// whole. Both are weighed by their length.
+    pub fn score(&self, permutation: &Permutation) -> f64 {
+        let weight = self
+            .kind_weights
+            .get(&permutation.kind)
+            .copied()
+            .unwrap_or(DEFAULT_KIND_WEIGHT);
+
+        let (base, candidate) = (&self.base, &permutation.domain);
+        let label = positional_similarity(&base.domain, &candidate.domain, self.position_curve);
+        let tld = similarity(&base.tld, &candidate.tld);
+
+        let longest = |left: &str, right: &str| left.chars().count().max(right.chars().count());
+        let label_len = longest(&base.domain, &candidate.domain);
+        let total = label_len + longest(&base.tld, &candidate.tld);
+        let closeness = if total == 0 {
+            1.0
+        } else {
+            label * ratio(label_len, total) + tld * ratio(total - label_len, total)
+        };
+
+        (weight * closeness).clamp(0.0, 1.0)
+    }
+
+    /// Score an enriched `result`, de-prioritizing likely defensive
+    /// registrations, see
+    /// [`DomainMetadata::compare_nameservers`](crate::enrich::DomainMetadata::compare_nameservers).
+    ///
+    /// ### Features
+    ///
+    /// This function requires the `enrich` feature toggled.
+    #[cfg(feature = "enrich")]
+    pub fn score_result(&self, result: &ScanResult) -> f64 {
+        Scorer::score(self, &result.permutation, Some(&result.metadata))
+    }
+
+    /// Sort `permutations` riskiest first. Ties are broken on the
+    /// permutation itself, so that the order is stable regardless of how
+    /// the permutations were generated.
+    pub fn rank<I>(&self, permutations: I) -> Vec<Permutation>
+    where
+        I: IntoIterator<Item = Permutation>,
+    {
+        rank_by(permutations, |permutation| self.score(permutation))
+    }
+
+    /// Sort enriched `results` riskiest first as scored by
+    /// [`CompositeScorer::score_result`]. Ties are broken on the
+    /// permutation, as in [`CompositeScorer::rank`].
+    ///
+    /// ### Features
+    ///
+    /// This function requires the `enrich` feature toggled.
+    #[cfg(feature = "enrich")]
+    pub fn rank_results<I>(&self, results: I) -> Vec<ScanResult>
+    where
+        I: IntoIterator<Item = ScanResult>,
+    {
+        rank_results_by(results, |result| self.score_result(result))
+    }
+}
+
+#[cfg(feature = "enrich")]
+impl Scorer for CompositeScorer {
+    fn score(&self, permutation: &Permutation, metadata: Option<&DomainMetadata>) -> f64 {
+        let score = CompositeScorer::score(self, permutation);
+
+        if metadata.is_some_and(|enriched| enriched.shared_nameservers == Some(true)) {
+            (score * self.defensive_weight).clamp(0.0, 1.0)
+        } else {
+            score
+        }
+    }
+}
+
+/// Sort `permutations` riskiest first as scored by `scorer`, before they
+/// are enriched, see [`CompositeScorer::rank`].
+///
+/// ### Features
+///
+/// This function requires the `enrich` feature toggled.
+#[cfg(feature = "enrich")]
+pub fn rank<I>(scorer: &dyn Scorer, permutations: I) -> Vec<Permutation>
+where
+    I: IntoIterator<Item = Permutation>,
+{
+    rank_by(permutations, |permutation| scorer.score(permutation, None))
+}
+
+/// Sort enriched `results` riskiest first as scored by `scorer`, see
+/// [`CompositeScorer::rank_results`].
+///
+/// ### Features
+///
+/// This function requires the `enrich` feature toggled.
+#[cfg(feature = "enrich")]
+pub fn rank_results<I>(scorer: &dyn Scorer, results: I) -> Vec<ScanResult>
+where
+    I: IntoIterator<Item = ScanResult>,
+{
+    rank_results_by(results, |result| {
+        scorer.score(&result.permutation, Some(&result.metadata))
+    })
+}
+
+/// Sort enriched `results` riskiest first according to `score`.
+#[cfg(feature = "enrich")]
+fn rank_results_by<I, F>(results: I, score: F) -> Vec<ScanResult>
+where
+    I: IntoIterator<Item = ScanResult>,
+    F: Fn(&ScanResult) -> f64,
+{
+    let mut scored: Vec<(f64, ScanResult)> = results
+        .into_iter()
+        .map(|result| (score(&result), result))
+        .collect();
+
+    scored.sort_by(|(left_score, left), (right_score, right)| {
+        right_score
+            .total_cmp(left_score)
+            .then_with(|| left.permutation.cmp(&right.permutation))
+    });
+
+    scored.into_iter().map(|(_, result)| result).collect()
+}
+
+/// Sort `permutations` riskiest first according to `score`.
+fn rank_by<I, F>(permutations: I, score: F) -> Vec<Permutation>
+where
+    I: IntoIterator<Item = Permutation>,
+    F: Fn(&Permutation) -> f64,
+{
+    let mut queue: BinaryHeap<Scored> = permutations
+        .into_iter()
+        .map(|permutation| Scored {
+            score: score(&permutation),
+            permutation,
+        })
+        .collect();
+
+    std::iter::from_fn(|| queue.pop().map(|scored| scored.permutation)).collect()
+}
+
+fn default_defensive_weight() -> f64 {
+    DEFAULT_DEFENSIVE_WEIGHT
+}
+
+/// Default risk weights, favouring kinds that are hard to tell apart from
+/// the original at a glance.
+fn default_kind_weight(kind: PermutationKind) -> f64 {
+    match kind {
+        PermutationKind::Homoglyph | PermutationKind::IdnHomograph => 1.0,
+        PermutationKind::Transposition | PermutationKind::Tld | PermutationKind::OcrConfusion => {
+            0.9
+        }
+        PermutationKind::Omission
+        | PermutationKind::DotOmission
+        | PermutationKind::DotHyphenSwap
+        | PermutationKind::TldAppend
+        | PermutationKind::Repetition
+        | PermutationKind::Insertion
+        | PermutationKind::FatFinger
+        | PermutationKind::Hyphenation
+        | PermutationKind::HyphenRemoval
+        | PermutationKind::SeparatorVariation
+        | PermutationKind::Homophone
+        | PermutationKind::Misspelling
+        | PermutationKind::Mapped => 0.8,
+        PermutationKind::Replacement
+        | PermutationKind::VowelSwap
+        | PermutationKind::Keyword
+        | PermutationKind::Dictionary
+        | PermutationKind::NumberStep
+        | PermutationKind::Spelling
+        | PermutationKind::WrongLayout
+        | PermutationKind::Transliteration => 0.7,
+        PermutationKind::Addition
+        | PermutationKind::Bitsquatting
+        | PermutationKind::DoubleVowelInsertion
+        | PermutationKind::VowelRemoval => 0.6,
+        PermutationKind::Subdomain | PermutationKind::TokenReorder => DEFAULT_KIND_WEIGHT,
+    }
+}
+
+/// Normalised Levenshtein similarity between two strings, where `1.0`
+/// means identical and `0.0` means nothing in common.
+pub fn similarity(left: &str, right: &str) -> f64 {
+    let a: Vec<char> = left.chars().collect();
+    let b: Vec<char> = right.chars().collect();
+    let longest = a.len().max(b.len());
+
+    if longest == 0 {
+        return 1.0;
+    }
+
+    let mut row: Vec<usize> = (0..=b.len()).collect();
+
+    for (i, ca) in a.iter().enumerate() {
+        let mut diagonal = row[0];
+        row[0] = i + 1;
+
+        for (j, cb) in b.iter().enumerate() {
+            let substitution = diagonal + usize::from(ca != cb);
+            diagonal = row[j + 1];
+            row[j + 1] = substitution.min(row[j] + 1).min(diagonal + 1);
+        }
+    }
+
+    1.0 - ratio(row[b.len()], longest)
+}
+
+/// Levenshtein similarity between two strings where each edit costs the
+/// weight of its position under `curve`, normalised so that `1.0` means
+/// identical and `0.0` means nothing in common. Positions are counted
+/// from the start of the strings, which [`CompositeScorer::score`] passes
+/// the root labels as. A [`PositionCurve::Flat`] curve yields plain
+/// [`similarity`].
+pub fn positional_similarity(left: &str, right: &str, curve: PositionCurve) -> f64 {
+    let a: Vec<char> = left.chars().collect();
+    let b: Vec<char> = right.chars().collect();
+    let weights = curve.weights(a.len().max(b.len()));
+    let total: f64 = weights.iter().sum();
+
+    if total <= 0.0 {
+        return 1.0;
+    }
+
+    let mut row: Vec<f64> = std::iter::once(0.0)
+        .chain(weights.iter().take(b.len()).scan(0.0, |cost, weight| {
+            *cost += weight;
+            Some(*cost)
+        }))
+        .collect();
+
+    for (i, ca) in a.iter().enumerate() {
+        let mut diagonal = row[0];
+        row[0] += weights[i];
+
+        for (j, cb) in b.iter().enumerate() {
+            let edit = if ca == cb {
+                0.0
+            } else {
+                weights[i].max(weights[j])
+            };
+            let substitution = diagonal + edit;
+            diagonal = row[j + 1];
+            row[j + 1] = substitution
+                .min(row[j] + weights[j])
+                .min(diagonal + weights[i]);
+        }
+    }
+
+    (1.0 - row[b.len()] / total).clamp(0.0, 1.0)
+}
+
+/// Lossless-enough `numerator / denominator` for domain-sized inputs.
+pub(crate) fn ratio(numerator: usize, denominator: usize) -> f64 {
+    let to_f64 = |n: usize| f64::from(u32::try_from(n).unwrap_or(u32::MAX));
+    to_f64(numerator) / to_f64(denominator)
+}
+
+/// Permutation paired with its score, ordered by score so that it can be
+/// held in a max-heap.
+#[derive(Debug, Clone)]
+pub(crate) struct Scored {
+    pub score: f64,
+    pub permutation: Permutation,
+}
+
+impl PartialEq for Scored {
+    fn eq(&self, other: &Self) -> bool {
+        self.cmp(other) == Ordering::Equal
+    }
+}
+
+impl Eq for Scored {}
+
+impl PartialOrd for Scored {
+    fn partial_cmp(&self, other: &Self) -> Option<Ordering> {
+        Some(self.cmp(other))
+    }
+}
+
+impl Ord for Scored {
+    fn cmp(&self, other: &Self) -> Ordering {
+        // Ties are broken on the permutation itself (reversed, so that the
+        // heap pops them in ascending order) to keep the order stable.
+        self.score
+            .total_cmp(&other.score)
+            .then_with(|| other.permutation.cmp(&self.permutation))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn permutation(fqdn: &str, kind: PermutationKind) -> Permutation {
+        Permutation {
+            domain: Domain::new(fqdn).unwrap(),
+            kind,
+            substitutions: vec![],
+        }
+    }
+
+    #[test]
+    fn test_similarity() {
+        assert!((similarity("example.com", "example.com") - 1.0).abs() < f64::EPSILON);
+        assert!((similarity("", "") - 1.0).abs() < f64::EPSILON);
+        assert!(similarity("abc", "xyz").abs() < f64::EPSILON);
+        assert!(
+            similarity("example.com", "examplee.com") > similarity("example.com", "eksample.com")
+        );
+    }
+
+    #[test]
+    fn test_positional_similarity() {
+        for (left, right) in [
+            ("example.com", "examplee.com"),
+            ("example.com", "eksample.com"),
+            ("abc", "xyz"),
+            ("", "abc"),
+        ] {
+            assert!(
+                (positional_similarity(left, right, PositionCurve::Flat) - similarity(left, right))
+                    .abs()
+                    < 1e-9
+            );
+        }
+
+        let curve = PositionCurve::default();
+        assert!(
+            positional_similarity("example.com", "exampl.com", curve)
+                > positional_similarity("example.com", "xample.com", curve)
+        );
+        assert!(positional_similarity("abc", "xyz", curve).abs() < f64::EPSILON);
+
+        let steep = PositionCurve::Exponential { decay: 0.5 };
+        assert_eq!(steep.weights(3), vec![1.0, 0.5, 0.25]);
+        assert_eq!(
+            PositionCurve::Linear { end: 0.5 }.weights(3),
+            vec![1.0, 0.75, 0.5]
+        );
+        assert!(
+            positional_similarity("brandname.com", "brandnane.com", steep)
+                > positional_similarity("brandname.com", "brandnane.com", curve)
+        );
+    }
+
+    #[test]
+    fn test_scored_heap_pops_highest_first() {
+        let scorer = CompositeScorer::new(Domain::new("example.com").unwrap());
+        let mut heap: BinaryHeap<Scored> = [
+            permutation("example-shop.com", PermutationKind::Keyword),
+            permutation("examp1e.com", PermutationKind::Homoglyph),
+            permutation("exampl.com", PermutationKind::Omission),
+        ]
+        .into_iter()
+        .map(|permutation| Scored {
+            score: scorer.score(&permutation),
+            permutation,
+        })
+        .collect();
+
+        let order: Vec<String> = std::iter::from_fn(|| heap.pop())
+            .map(|scored| scored.permutation.domain.fqdn)
+            .collect();
+
+        assert_eq!(order, vec!["examp1e.com", "exampl.com", "example-shop.com"]);
+    }
+
+    #[test]
+    fn test_score_weighs_label_positions() {
+        let kind = PermutationKind::Omission;
+        let scorer = CompositeScorer::new(Domain::new("example.com").unwrap());
+
+        assert!(
+            scorer.score(&permutation("exampl.com", kind))
+                > scorer.score(&permutation("xample.com", kind))
+        );
+
+        // Positions are those within the root label, regardless of the
+        // subdomains in front of it.
+        let nested = CompositeScorer::new(Domain::new("www.example.com").unwrap());
+        assert!(
+            (scorer.score(&permutation("exampl.com", kind))
+                - nested.score(&permutation("www.exampl.com", kind)))
+            .abs()
+                < 1e-9
+        );
+
+        // The TLD is compared as a whole, on top of the label.
+        assert!(
+            (scorer.score(&permutation("example.net", kind)) - 0.7 * default_kind_weight(kind))
+                .abs()
+                < 1e-9
+        );
+    }
+
+    #[test]
+    fn test_rank() {
+        let scorer = CompositeScorer::new(Domain::new("example.com").unwrap());
+        let ranked = scorer.rank([
+            permutation("example-shop.com", PermutationKind::Keyword),
+            permutation("examp1e.com", PermutationKind::Homoglyph),
+        ]);
+
+        assert_eq!(ranked[0].domain.fqdn, "examp1e.com");
+    }
+
+    #[test]
+    #[cfg(feature = "enrich")]
+    fn test_score_result_deprioritizes_defensive_registrations() {
+        use crate::enrich::DomainMetadata;
+
+        let scorer = CompositeScorer::new(Domain::new("example.com").unwrap());
+        let mut result = ScanResult {
+            permutation: permutation("examp1e.com", PermutationKind::Homoglyph),
+            metadata: DomainMetadata::new(String::from("examp1e.com")),
+            fingerprint: None,
+        };
+
+        let undecided = scorer.score_result(&result);
+        assert!((undecided - scorer.score(&result.permutation)).abs() < f64::EPSILON);
+
+        result.metadata.shared_nameservers = Some(true);
+        assert!(scorer.score_result(&result) < undecided * 0.5);
+
+        let attack = ScanResult {
+            permutation: permutation("examp1e.net", PermutationKind::Tld),
+            metadata: DomainMetadata::new(String::from("examp1e.net")),
+            fingerprint: None,
+        };
+        let ranked = scorer.rank_results([result, attack]);
+        assert_eq!(ranked[0].permutation.domain.fqdn, "examp1e.net");
+    }
+
+    #[test]
+    #[cfg(feature = "enrich")]
+    fn test_custom_scorer() {
+        use crate::enrich::DnsRecords;
+        use crate::summary::ScanSummary;
+
+        /// Scores omissions as the riskiest, unless others serve content.
+        struct Omissions;
+
+        impl Scorer for Omissions {
+            fn score(&self, permutation: &Permutation, metadata: Option<&DomainMetadata>) -> f64 {
+                if metadata.is_some_and(|enriched| enriched.http_banner.is_some()) {
+                    1.0
+                } else if permutation.kind == PermutationKind::Omission {
+                    0.9
+                } else {
+                    0.1
+                }
+            }
+        }
+
+        let ranked = rank(
+            &Omissions,
+            [
+                permutation("examp1e.com", PermutationKind::Homoglyph),
+                permutation("exampl.com", PermutationKind::Omission),
+            ],
+        );
+        assert_eq!(ranked[0].domain.fqdn, "exampl.com");
+
+        let results: Vec<ScanResult> = ranked
+            .into_iter()
+            .map(|permutation| {
+                let mut metadata = DomainMetadata::new(permutation.domain.fqdn.clone());
+                metadata.dns = DnsRecords::from_ips(["127.0.0.1".parse().unwrap()]);
+                if permutation.kind == PermutationKind::Homoglyph {
+                    metadata.http_banner = Some(String::from("nginx"));
+                }
+
+                ScanResult {
+                    permutation,
+                    metadata,
+                    fingerprint: None,
+                }
+            })
+            .collect();
+
+        let summary =
+            ScanSummary::with_scorer(Domain::new("example.com").unwrap(), &Omissions, &results);
+        assert_eq!(summary.top_risks[0].permutation.domain.fqdn, "examp1e.com");
+        assert!((summary.top_risks[0].score - 1.0).abs() < f64::EPSILON);
+    }
+}
@@ -0,0 +1,261 @@
+//! gRPC service exposing permutation and enrichment over a versioned
+//! protobuf definition (see `proto/twistrs/v1/twistrs.proto`).
+//!
+//! The generated client and server stubs are exported under [`proto`],
+//! meaning that Rust clients can talk to the service without compiling
+//! the definition themselves.
+//!
+//! Example:
+//!
+//! ```no_run
+//! use twistrs::server::grpc::{self, Config};
+//!
+//! #[tokio::main]
+//! async fn main() {
+//!     grpc::serve(Config::default()).await.unwrap();
+//! }
+//! ```
+//!
+//! ### Features
+//!
+//! This module requires the `grpc` feature toggled.
+use std::net::{Ipv4Addr, SocketAddr};
+use std::pin::Pin;
+
+use futures::stream::{Stream, StreamExt};
+use serde::{Deserialize, Serialize};
+use tokio::sync::mpsc;
+use tokio_stream::wrappers::ReceiverStream;
+use tonic::{Request, Response, Status};
+
+use crate::config::{EnrichmentConfig, ScoringConfig};
+use crate::error::Error;
+use crate::permutate::Permutation;
+use crate::pipeline::ScanResult;
+use crate::server::{enrich, permutations, scan_enrichment, ServeError};
+
+use self::proto::twistrs_server::{Twistrs, TwistrsServer};
+
+/// Code generated from the protobuf definition.
+#[allow(
+    missing_copy_implementations,
+    unused_qualifications,
+    clippy::all,
+    clippy::pedantic,
+    clippy::shadow_reuse,
+    clippy::shadow_same,
+    clippy::shadow_unrelated
+)]
+pub mod proto {
+    tonic::include_proto!("twistrs.v1");
+}
+
+/// Default port, matching the one used by the gRPC example.
+const DEFAULT_PORT: u16 = 50051;
+
+/// Number of messages buffered per stream before applying backpressure.
+const STREAM_BUFFER: usize = 64;
+
+/// Configuration of the gRPC service.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct Config {
+    /// Address the service listens on.
+    pub addr: SocketAddr,
+
+    /// How permutations are enriched.
+    pub enrichment: EnrichmentConfig,
+
+    /// How permutations are scored when enriched riskiest first.
+    pub scoring: ScoringConfig,
+}
+
+impl Default for Config {
+    fn default() -> Config {
+        Config {
+            addr: SocketAddr::from((Ipv4Addr::UNSPECIFIED, DEFAULT_PORT)),
+            enrichment: EnrichmentConfig::default(),
+            scoring: ScoringConfig::default(),
+        }
+    }
+}
+
+/// Implementation of the `twistrs.v1.Twistrs` service.
+#[derive(Debug, Clone)]
+pub struct Service {
+    config: Config,
+}
+
+impl Service {
+    /// Create a new service using `config`.
+    pub fn new(config: Config) -> Service {
+        Service { config }
+    }
+}
+
+/// Serve the gRPC service on `config.addr` until the process is stopped.
+pub async fn serve(config: Config) -> Result<(), Error> {
+    let addr = config.addr;
+
+    tonic::transport::Server::builder()
+        .add_service(TwistrsServer::new(Service::new(config)))
+        .serve(addr)
+        .await
+        .map_err(|e| ServeError::TransportError {
+            error: anyhow::Error::msg(e),
+        })?;
+
+    Ok(())
+}
+
+type ResponseStream<T> = Pin<Box<dyn Stream<Item = Result<T, Status>> + Send>>;
+
+#[tonic::async_trait]
+impl Twistrs for Service {
+    type PermutateStream = ResponseStream<proto::Permutation>;
+    type ScanStream = ResponseStream<proto::ScanResult>;
+
+    async fn permutate(
+        &self,
+        request: Request<proto::PermutateRequest>,
+    ) -> Result<Response<Self::PermutateStream>, Status> {
+        let message = request.into_inner();
+        let (_, permutations, _) = permutations(&message.fqdn, &message.kinds)
+            .map_err(|e| Status::invalid_argument(e.to_string()))?;
+
+        Ok(Response::new(Box::pin(futures::stream::iter(
+            permutations
+                .into_iter()
+                .map(proto::Permutation::from)
+                .map(Ok),
+        ))))
+    }
+
+    async fn scan(
+        &self,
+        request: Request<proto::ScanRequest>,
+    ) -> Result<Response<Self::ScanStream>, Status> {
+        let message = request.into_inner();
+        let (domain, permutations, fingerprint) = permutations(&message.fqdn, &message.kinds)
+            .map_err(|e| Status::invalid_argument(e.to_string()))?;
+
+        let enrichment = scan_enrichment(&self.config.enrichment, &message.enrichments);
+        let mut pipeline = enrichment
+            .pipeline()
+            .map_err(|e| Status::invalid_argument(e.to_string()))?;
+        pipeline.set_fingerprint(fingerprint);
+        let resolving_only = message.resolving_only || enrichment.resolving_only;
+        let scorer = self.config.scoring.scorer(domain);
+
+        let (tx, rx) = mpsc::channel(STREAM_BUFFER);
+
+        tokio::spawn(async move {
+            let mut results = enrich(&pipeline, permutations, &enrichment, &scorer);
+
+            while let Some(result) = results.next().await {
+                if resolving_only && result.metadata.dns.ips().is_none() {
+                    continue;
+                }
+
+                if tx.send(Ok(result.into())).await.is_err() {
+                    // Client has gone away, stop enriching on its behalf.
+                    break;
+                }
+            }
+        });
+
+        Ok(Response::new(Box::pin(ReceiverStream::new(rx))))
+    }
+}
+
+impl From<Permutation> for proto::Permutation {
+    fn from(permutation: Permutation) -> proto::Permutation {
+        proto::Permutation {
+            fqdn: permutation.domain.fqdn,
+            kind: format!("{:?}", permutation.kind),
+        }
+    }
+}
+
+impl From<ScanResult> for proto::ScanResult {
+    fn from(result: ScanResult) -> proto::ScanResult {
+        proto::ScanResult {
+            permutation: Some(result.permutation.into()),
+            ips: result
+                .metadata
+                .dns
+                .ips()
+                .unwrap_or_default()
+                .iter()
+                .map(ToString::to_string)
+                .collect(),
+            http_banner: result.metadata.http_banner.unwrap_or_default(),
+            fingerprint: result.fingerprint.unwrap_or_default(),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[tokio::test]
+    async fn test_grpc_permutate() {
+        let service = Service::new(Config::default());
+
+        let response = service
+            .permutate(Request::new(proto::PermutateRequest {
+                fqdn: String::from("example.com"),
+                kinds: vec![String::from("tld")],
+            }))
+            .await
+            .unwrap();
+
+        let permutations: Vec<proto::Permutation> =
+            response.into_inner().map(Result::unwrap).collect().await;
+
+        assert!(!permutations.is_empty());
+        assert!(permutations.iter().all(|p| p.kind == "Tld"));
+    }
+
+    #[tokio::test]
+    async fn test_grpc_rejects_invalid_input() {
+        let service = Service::new(Config::default());
+
+        let unknown_kind = service
+            .permutate(Request::new(proto::PermutateRequest {
+                fqdn: String::from("example.com"),
+                kinds: vec![String::from("nonexistent")],
+            }))
+            .await;
+        assert_eq!(
+            unknown_kind.err().map(|status| status.code()),
+            Some(tonic::Code::InvalidArgument)
+        );
+
+        let invalid_domain = service
+            .scan(Request::new(proto::ScanRequest {
+                fqdn: String::from("not a domain"),
+                kinds: vec![],
+                resolving_only: true,
+                enrichments: vec![],
+            }))
+            .await;
+        assert_eq!(
+            invalid_domain.err().map(|status| status.code()),
+            Some(tonic::Code::InvalidArgument)
+        );
+
+        let unknown_enrichment = service
+            .scan(Request::new(proto::ScanRequest {
+                fqdn: String::from("example.com"),
+                kinds: vec![String::from("tld")],
+                resolving_only: true,
+                enrichments: vec![String::from("nonexistent")],
+            }))
+            .await;
+        assert_eq!(
+            unknown_enrichment.err().map(|status| status.code()),
+            Some(tonic::Code::InvalidArgument)
+        );
+    }
+}
@@ -0,0 +1,716 @@
+//! REST/JSON service running scans in the background, for clients that
+//! would rather not speak gRPC.
+//!
+//! | Method | Path                    | Description                                  |
+//! |--------|-------------------------|----------------------------------------------|
+//! | `POST` | `/scan`                 | Start a scan, returns its identifier.        |
+//! | `GET`  | `/scan/{id}/results`    | Page through results (`offset` or `after`).  |
+//! | `GET`  | `/scan/{id}/events`     | Stream results as server-sent events.        |
+//!
+//! Scans are kept in memory until `scan_ttl_secs` after they complete,
+//! whereas the metadata of their results is kept in a [`DomainStore`]
+//! under keys scoped to the scan, in memory unless another store is
+//! given to [`router_with_store`].
+//! Results can be paged through by `offset`, or by `after`, the stable
+//! permutation ID (see [`Permutation::id`]) of the last result received.
+//!
+//! Example:
+//!
+//! ```no_run
+//! use twistrs::server::http::{self, Config};
+//!
+//! #[tokio::main]
+//! async fn main() {
+//!     http::serve(Config::default()).await.unwrap();
+//! }
+//! ```
+//!
+//! ### Features
+//!
+//! This module requires the `http` feature toggled.
+use std::collections::HashMap;
+use std::convert::Infallible;
+use std::fmt;
+use std::net::{Ipv4Addr, SocketAddr};
+use std::sync::{Arc, Mutex, MutexGuard, PoisonError};
+use std::time::{Duration, Instant};
+
+use axum::extract::{Path, Query, State};
+use axum::http::StatusCode;
+use axum::response::sse::{Event, KeepAlive, Sse};
+use axum::response::{IntoResponse, Response};
+use axum::routing::{get, post};
+use axum::{Json, Router};
+use futures::future;
+use futures::stream::{self, Stream, StreamExt};
+use serde::{Deserialize, Serialize};
+use tokio::sync::broadcast;
+use uuid::Uuid;
+
+use crate::config::{EnrichmentConfig, ScoringConfig};
+use crate::enrich::DomainMetadata;
+use crate::error::Error;
+use crate::permutate::{Domain, Permutation};
+use crate::pipeline::ScanResult;
+use crate::server::{enrich, permutations, scan_enrichment, ServeError};
+use crate::storage::{DomainStore, MemoryStore};
+
+/// Default port the service listens on.
+const DEFAULT_PORT: u16 = 8080;
+
+/// Default and maximum number of results returned per page.
+const DEFAULT_PAGE_SIZE: usize = 100;
+const MAX_PAGE_SIZE: usize = 1000;
+
+/// Number of live results buffered per event stream subscriber.
+const EVENT_BUFFER: usize = 1024;
+
+/// Default number of seconds completed scans are kept for.
+const DEFAULT_SCAN_TTL_SECS: u64 = 60 * 60 * 24;
+
+/// Configuration of the REST service.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(default)]
+pub struct Config {
+    /// Address the service listens on.
+    pub addr: SocketAddr,
+
+    /// How permutations are enriched.
+    pub enrichment: EnrichmentConfig,
+
+    /// How permutations are scored when enriched riskiest first.
+    pub scoring: ScoringConfig,
+
+    /// Seconds completed scans are kept for, after which they can no
+    /// longer be paged through. Scans that are still running never
+    /// expire.
+    pub scan_ttl_secs: u64,
+}
+
+impl Default for Config {
+    fn default() -> Config {
+        Config {
+            addr: SocketAddr::from((Ipv4Addr::UNSPECIFIED, DEFAULT_PORT)),
+            enrichment: EnrichmentConfig::default(),
+            scoring: ScoringConfig::default(),
+            scan_ttl_secs: DEFAULT_SCAN_TTL_SECS,
+        }
+    }
+}
+
+/// Body of `POST /scan`.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct ScanRequest {
+    pub fqdn: String,
+
+    /// Names of the permutation kinds to generate (e.g. `"Homoglyph"`),
+    /// all kinds are generated if left empty.
+    #[serde(default)]
+    pub kinds: Vec<String>,
+
+    /// Only keep results of permutations that resolve.
+    #[serde(default)]
+    pub resolving_only: bool,
+
+    /// Names of the enrichments run on resolving permutations (e.g.
+    /// `"mail_capable"`), those of the service config are run if left
+    /// empty.
+    #[serde(default)]
+    pub enrichments: Vec<String>,
+}
+
+/// Response of `POST /scan`.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct ScanCreated {
+    pub id: Uuid,
+    pub total: usize,
+
+    /// Fingerprint of the engine configuration the permutations were
+    /// generated with, also set on each result.
+    pub fingerprint: String,
+}
+
+/// Query parameters of `GET /scan/{id}/results`.
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+pub struct PageQuery {
+    #[serde(default)]
+    pub offset: usize,
+    pub limit: Option<usize>,
+
+    /// Permutation ID of the result to start after, taking precedence
+    /// over `offset`.
+    pub after: Option<String>,
+}
+
+/// Response of `GET /scan/{id}/results`.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct Page {
+    pub results: Vec<ScanResult>,
+
+    /// Offset to request the next page from, `None` once the scan has
+    /// completed and every result has been returned.
+    pub next_offset: Option<usize>,
+
+    /// Permutation ID to request the next page after, set alongside
+    /// `next_offset` once at least one result has been returned.
+    pub next_after: Option<String>,
+
+    /// Whether the scan has completed.
+    pub complete: bool,
+
+    /// Error that stopped the scan early, if any.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub error: Option<String>,
+}
+
+/// Progress of a scan, shared between the request handlers and the
+/// background task running the scan. The metadata of its results is
+/// kept in the store, see [`result_key`].
+#[derive(Debug)]
+struct Scan {
+    id: Uuid,
+    domain: Domain,
+    fingerprint: String,
+
+    /// Permutations enriched so far, in completion order.
+    permutations: Vec<Permutation>,
+
+    /// Position of each result by permutation ID.
+    positions: HashMap<String, usize>,
+    complete: bool,
+
+    /// When the scan completed, from which point on it expires.
+    completed_at: Option<Instant>,
+    error: Option<String>,
+    events: broadcast::Sender<ScanResult>,
+}
+
+impl Scan {
+    fn new(id: Uuid, domain: Domain, fingerprint: String) -> Scan {
+        Scan {
+            id,
+            domain,
+            fingerprint,
+            permutations: vec![],
+            positions: HashMap::new(),
+            complete: false,
+            completed_at: None,
+            error: None,
+            events: broadcast::channel(EVENT_BUFFER).0,
+        }
+    }
+
+    fn push(&mut self, permutation: Permutation) {
+        self.positions
+            .insert(permutation.id(&self.domain), self.permutations.len());
+        self.permutations.push(permutation);
+    }
+
+    fn finish(&mut self, error: Option<String>) {
+        self.complete = true;
+        self.completed_at = Some(Instant::now());
+        self.error = error;
+        // Closing the channel ends every event stream.
+        self.events = broadcast::channel(1).0;
+    }
+
+    fn is_expired(&self, ttl: Duration) -> bool {
+        self.completed_at.is_some_and(|at| at.elapsed() >= ttl)
+    }
+
+    /// Up to `take` results starting at `skip`, whose metadata is yet to
+    /// be fetched from the store.
+    fn snapshot(&self, skip: usize, take: usize) -> Snapshot {
+        let end = skip.saturating_add(take).min(self.permutations.len());
+
+        Snapshot {
+            id: self.id,
+            domain: self.domain.clone(),
+            fingerprint: self.fingerprint.clone(),
+            permutations: self
+                .permutations
+                .get(skip..end)
+                .unwrap_or_default()
+                .to_vec(),
+        }
+    }
+}
+
+/// Key the metadata of a result is stored under. Keys are scoped to the
+/// scan, so that scans generating the same FQDN do not overwrite each
+/// other's results.
+fn result_key(scan: Uuid, permutation: &Permutation, domain: &Domain) -> String {
+    format!("{scan}/{}", permutation.id(domain))
+}
+
+/// Results of a scan taken while the scans are locked, so that their
+/// metadata can be fetched from the store once they are unlocked.
+struct Snapshot {
+    id: Uuid,
+    domain: Domain,
+    fingerprint: String,
+    permutations: Vec<Permutation>,
+}
+
+impl Snapshot {
+    /// The results along with their metadata fetched from `store`.
+    fn fetch(self, store: &dyn DomainStore) -> Result<Vec<ScanResult>, Error> {
+        self.permutations
+            .into_iter()
+            .map(|permutation| {
+                let fqdn = permutation.domain.fqdn.clone();
+                let metadata = store
+                    .get(&result_key(self.id, &permutation, &self.domain))?
+                    .map_or_else(
+                        || DomainMetadata::new(fqdn.clone()),
+                        |stored| DomainMetadata {
+                            fqdn: fqdn.clone(),
+                            ..stored
+                        },
+                    );
+
+                Ok(ScanResult {
+                    permutation,
+                    metadata,
+                    fingerprint: Some(self.fingerprint.clone()),
+                })
+            })
+            .collect()
+    }
+}
+
+type SharedStore = Arc<Mutex<Box<dyn DomainStore + Send>>>;
+
+/// State shared by every handler.
+#[derive(Clone)]
+struct Scans {
+    config: Config,
+    scans: Arc<Mutex<HashMap<Uuid, Scan>>>,
+    store: SharedStore,
+}
+
+impl fmt::Debug for Scans {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("Scans")
+            .field("config", &self.config)
+            .field("scans", &self.scans)
+            .finish_non_exhaustive()
+    }
+}
+
+impl Scans {
+    fn new<S>(config: Config, store: S) -> Scans
+    where
+        S: DomainStore + Send + 'static,
+    {
+        Scans {
+            config,
+            scans: Arc::default(),
+            store: Arc::new(Mutex::new(Box::new(store))),
+        }
+    }
+
+    /// Lock the scans, dropping the expired ones.
+    fn scans(&self) -> MutexGuard<'_, HashMap<Uuid, Scan>> {
+        let ttl = Duration::from_secs(self.config.scan_ttl_secs);
+        let mut scans = self.scans.lock().unwrap_or_else(PoisonError::into_inner);
+        scans.retain(|_, scan| !scan.is_expired(ttl));
+
+        scans
+    }
+
+    fn insert(&self, id: Uuid, scan: Scan) {
+        self.scans().insert(id, scan);
+    }
+
+    fn with<T>(&self, id: Uuid, f: impl FnOnce(&mut Scan) -> T) -> Option<T> {
+        self.scans().get_mut(&id).map(f)
+    }
+
+    /// Run `f` over the store off the async workers, since backends such
+    /// as the sled and sqlite ones block on disk I/O.
+    async fn with_store<T, F>(&self, f: F) -> Result<T, Error>
+    where
+        T: Send + 'static,
+        F: FnOnce(&mut dyn DomainStore) -> Result<T, Error> + Send + 'static,
+    {
+        let store = Arc::clone(&self.store);
+
+        tokio::task::spawn_blocking(move || {
+            f(&mut **store.lock().unwrap_or_else(PoisonError::into_inner))
+        })
+        .await
+        .map_err(|e| ServeError::TransportError {
+            error: anyhow::Error::msg(e),
+        })?
+    }
+}
+
+/// Build the service's router, e.g. to nest it within an existing
+/// application. The metadata of results is kept in memory.
+pub fn router(config: Config) -> Router {
+    router_with_store(config, MemoryStore::new())
+}
+
+/// Build the service's router, keeping the metadata of results in
+/// `store`, e.g. a [`SledStore`](crate::storage::SledStore) so that it
+/// outlives the scans.
+pub fn router_with_store<S>(config: Config, store: S) -> Router
+where
+    S: DomainStore + Send + 'static,
+{
+    Router::new()
+        .route("/scan", post(create_scan))
+        .route("/scan/:id/results", get(scan_results))
+        .route("/scan/:id/events", get(scan_events))
+        .with_state(Scans::new(config, store))
+}
+
+/// Serve the REST service on `config.addr` until the process is stopped.
+pub async fn serve(config: Config) -> Result<(), Error> {
+    serve_with_store(config, MemoryStore::new()).await
+}
+
+/// Serve the REST service on `config.addr` until the process is stopped,
+/// keeping the metadata of results in `store`, see [`router_with_store`].
+pub async fn serve_with_store<S>(config: Config, store: S) -> Result<(), Error>
+where
+    S: DomainStore + Send + 'static,
+{
+    let addr = config.addr;
+
+    axum::Server::try_bind(&addr)
+        .map_err(|e| ServeError::TransportError {
+            error: anyhow::Error::msg(e),
+        })?
+        .serve(router_with_store(config, store).into_make_service())
+        .await
+        .map_err(|e| ServeError::TransportError {
+            error: anyhow::Error::msg(e),
+        })?;
+
+    Ok(())
+}
+
+async fn create_scan(
+    State(state): State<Scans>,
+    Json(request): Json<ScanRequest>,
+) -> Result<(StatusCode, Json<ScanCreated>), HandlerError> {
+    let (domain, permutations, fingerprint) = permutations(&request.fqdn, &request.kinds)
+        .map_err(|e| HandlerError::BadRequest(e.to_string()))?;
+    let total = permutations.len();
+    let id = Uuid::new_v4();
+
+    let enrichment = scan_enrichment(&state.config.enrichment, &request.enrichments);
+    let mut pipeline = enrichment
+        .pipeline()
+        .map_err(|e| HandlerError::BadRequest(e.to_string()))?;
+    pipeline.set_fingerprint(fingerprint.clone());
+    let scorer = state.config.scoring.scorer(domain.clone());
+    let resolving_only = request.resolving_only || enrichment.resolving_only;
+
+    state.insert(id, Scan::new(id, domain.clone(), fingerprint.clone()));
+
+    tokio::spawn(async move {
+        let mut results = enrich(&pipeline, permutations, &enrichment, &scorer);
+        let mut error = None;
+
+        while let Some(result) = results.next().await {
+            if resolving_only && result.metadata.dns.ips().is_none() {
+                continue;
+            }
+
+            // Stored before being pushed, so that every result listed by
+            // the scan can be fetched.
+            let metadata = DomainMetadata {
+                fqdn: result_key(id, &result.permutation, &domain),
+                ..result.metadata.clone()
+            };
+
+            if let Err(e) = state.with_store(move |store| store.put(metadata)).await {
+                error = Some(e.to_string());
+                break;
+            }
+
+            state.with(id, |scan| {
+                scan.push(result.permutation.clone());
+                // Nobody may be subscribed to the events, which is fine.
+                let _ = scan.events.send(result);
+            });
+        }
+
+        state.with(id, |scan| scan.finish(error));
+    });
+
+    Ok((
+        StatusCode::ACCEPTED,
+        Json(ScanCreated {
+            id,
+            total,
+            fingerprint,
+        }),
+    ))
+}
+
+async fn scan_results(
+    State(state): State<Scans>,
+    Path(id): Path<Uuid>,
+    Query(query): Query<PageQuery>,
+) -> Result<Json<Page>, HandlerError> {
+    let limit = query
+        .limit
+        .unwrap_or(DEFAULT_PAGE_SIZE)
+        .clamp(1, MAX_PAGE_SIZE);
+
+    let (start, snapshot, total, complete, error) = state
+        .with(id, |scan| {
+            let start = match &query.after {
+                Some(after) => scan
+                    .positions
+                    .get(after)
+                    .map(|position| position + 1)
+                    .ok_or_else(|| HandlerError::BadRequest(format!("unknown result: {after}")))?,
+                None => query.offset,
+            };
+
+            Ok::<_, HandlerError>((
+                start,
+                scan.snapshot(start, limit),
+                scan.permutations.len(),
+                scan.complete,
+                scan.error.clone(),
+            ))
+        })
+        .ok_or(HandlerError::NotFound)??;
+
+    let domain = snapshot.domain.clone();
+    let results = state
+        .with_store(move |store| snapshot.fetch(store))
+        .await
+        .map_err(|e| HandlerError::Internal(e.to_string()))?;
+    let end = start.saturating_add(results.len());
+    let more = end < total || !complete;
+
+    Ok(Json(Page {
+        next_offset: more.then_some(end),
+        next_after: more
+            .then(|| match results.last() {
+                Some(last) => Some(last.permutation.id(&domain)),
+                None => query.after.clone(),
+            })
+            .flatten(),
+        complete,
+        error,
+        results,
+    }))
+}
+
+async fn scan_events(
+    State(state): State<Scans>,
+    Path(id): Path<Uuid>,
+) -> Result<Sse<impl Stream<Item = Result<Event, Infallible>>>, HandlerError> {
+    // Snapshotting and subscribing under the same lock guarantees that no
+    // result is either missed or sent twice. The metadata of the snapshot
+    // is in the store already, and is fetched once the scans are unlocked.
+    let (snapshot, subscription) = state
+        .with(id, |scan| {
+            (
+                scan.snapshot(0, usize::MAX),
+                (!scan.complete).then(|| scan.events.subscribe()),
+            )
+        })
+        .ok_or(HandlerError::NotFound)?;
+
+    let backlog = state
+        .with_store(move |store| snapshot.fetch(store))
+        .await
+        .map_err(|e| HandlerError::Internal(e.to_string()))?;
+
+    let live = stream::unfold(subscription, |pending| async move {
+        let mut receiver = pending?;
+
+        loop {
+            match receiver.recv().await {
+                Ok(result) => return Some((result, Some(receiver))),
+                Err(broadcast::error::RecvError::Closed) => return None,
+                // Slow subscribers skip results rather than stall the scan.
+                Err(broadcast::error::RecvError::Lagged(_)) => {}
+            }
+        }
+    });
+
+    // Results that fail to serialize are dropped rather than sent as
+    // empty events.
+    let events = stream::iter(backlog)
+        .chain(live)
+        .filter_map(|result| future::ready(Event::default().json_data(result).ok().map(Ok)));
+
+    Ok(Sse::new(events).keep_alive(KeepAlive::default()))
+}
+
+/// Errors returned to clients as `{"error": "..."}` bodies.
+#[derive(Debug)]
+enum HandlerError {
+    BadRequest(String),
+    NotFound,
+    Internal(String),
+}
+
+impl IntoResponse for HandlerError {
+    fn into_response(self) -> Response {
+        let (status, message) = match self {
+            HandlerError::BadRequest(message) => (StatusCode::BAD_REQUEST, message),
+            HandlerError::NotFound => (StatusCode::NOT_FOUND, String::from("scan not found")),
+            HandlerError::Internal(message) => (StatusCode::INTERNAL_SERVER_ERROR, message),
+        };
+
+        (status, Json(HashMap::from([("error", message)]))).into_response()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    use axum::body::Body;
+    use axum::http::Request;
+    use tower::ServiceExt;
+
+    use crate::enrich::DomainMetadata;
+    use crate::permutate::PermutationKind;
+
+    fn push(state: &Scans, scan: &mut Scan, fqdn: &str, banner: &str) {
+        let permutation = Permutation {
+            domain: Domain::new(fqdn).unwrap(),
+            kind: PermutationKind::Tld,
+            substitutions: vec![],
+        };
+        let mut metadata = DomainMetadata::new(result_key(scan.id, &permutation, &scan.domain));
+        metadata.http_banner = Some(banner.to_string());
+        state.store.lock().unwrap().put(metadata).unwrap();
+
+        scan.push(permutation);
+    }
+
+    async fn get_page(state: &Scans, uri: &str) -> (StatusCode, Option<Page>) {
+        let response = Router::new()
+            .route("/scan/:id/results", get(scan_results))
+            .with_state(state.clone())
+            .oneshot(Request::get(uri).body(Body::empty()).unwrap())
+            .await
+            .unwrap();
+        let status = response.status();
+        let body = hyper::body::to_bytes(response.into_body()).await.unwrap();
+
+        (status, serde_json::from_slice(&body).ok())
+    }
+
+    #[tokio::test]
+    async fn test_http_rejects_invalid_scan() {
+        for body in [
+            r#"{"fqdn": "not a domain"}"#,
+            r#"{"fqdn": "example.com", "kinds": ["tld"], "enrichments": ["nonexistent"]}"#,
+        ] {
+            let response = router(Config::default())
+                .oneshot(
+                    Request::post("/scan")
+                        .header("content-type", "application/json")
+                        .body(Body::from(body))
+                        .unwrap(),
+                )
+                .await
+                .unwrap();
+
+            assert_eq!(response.status(), StatusCode::BAD_REQUEST);
+        }
+    }
+
+    #[tokio::test]
+    async fn test_http_results_pagination() {
+        let state = Scans::new(Config::default(), MemoryStore::new());
+        let id = Uuid::new_v4();
+
+        let mut scan = Scan::new(id, Domain::new("example.com").unwrap(), String::new());
+        for fqdn in ["a.com", "b.com", "c.com"] {
+            push(&state, &mut scan, fqdn, "nginx");
+        }
+        scan.finish(None);
+        state.insert(id, scan);
+
+        // Another scan generating the same FQDN keeps its own metadata.
+        let other = Uuid::new_v4();
+        let mut other_scan = Scan::new(other, Domain::new("example.org").unwrap(), String::new());
+        push(&state, &mut other_scan, "a.com", "apache");
+        state.insert(other, other_scan);
+
+        let (_, first_page) = get_page(&state, &format!("/scan/{id}/results?limit=2")).await;
+        let first = first_page.unwrap();
+        assert_eq!(first.results.len(), 2);
+        assert_eq!(first.next_offset, Some(2));
+        // The metadata of results is read back from the store.
+        assert_eq!(first.results[0].metadata.fqdn, "a.com");
+        assert_eq!(
+            first.results[0].metadata.http_banner.as_deref(),
+            Some("nginx")
+        );
+
+        let (_, other_page) = get_page(&state, &format!("/scan/{other}/results")).await;
+        assert_eq!(
+            other_page.unwrap().results[0]
+                .metadata
+                .http_banner
+                .as_deref(),
+            Some("apache")
+        );
+
+        let (_, last_page) = get_page(&state, &format!("/scan/{id}/results?offset=2")).await;
+        let last = last_page.unwrap();
+        assert_eq!(last.results.len(), 1);
+        assert_eq!(last.next_offset, None);
+        assert!(last.complete);
+
+        let after = first.next_after.unwrap();
+        let (_, keyset_page) = get_page(&state, &format!("/scan/{id}/results?after={after}")).await;
+        let keyset = keyset_page.unwrap();
+        assert_eq!(keyset.results[0].permutation.domain.fqdn, "c.com");
+        assert_eq!(keyset.next_after, None);
+
+        let (unknown_status, _) =
+            get_page(&state, &format!("/scan/{id}/results?after=unknown")).await;
+        assert_eq!(unknown_status, StatusCode::BAD_REQUEST);
+
+        let (status, _) = get_page(&state, &format!("/scan/{}/results", Uuid::new_v4())).await;
+        assert_eq!(status, StatusCode::NOT_FOUND);
+    }
+
+    #[tokio::test]
+    async fn test_http_scans_expire() {
+        let config = Config {
+            scan_ttl_secs: 0,
+            ..Config::default()
+        };
+        let state = Scans::new(config, MemoryStore::new());
+        let (running, completed) = (Uuid::new_v4(), Uuid::new_v4());
+
+        state.insert(
+            running,
+            Scan::new(running, Domain::new("example.com").unwrap(), String::new()),
+        );
+
+        let domain = Domain::new("example.com").unwrap();
+        let mut scan = Scan::new(completed, domain.clone(), String::new());
+        push(&state, &mut scan, "a.com", "nginx");
+        let key = result_key(completed, &scan.permutations[0], &domain);
+        scan.finish(None);
+        state.insert(completed, scan);
+
+        let (running_status, _) = get_page(&state, &format!("/scan/{running}/results")).await;
+        assert_eq!(running_status, StatusCode::OK);
+
+        let (completed_status, _) = get_page(&state, &format!("/scan/{completed}/results")).await;
+        assert_eq!(completed_status, StatusCode::NOT_FOUND);
+
+        // The metadata outlives the scan in the store.
+        assert!(state.store.lock().unwrap().get(&key).unwrap().is_some());
+    }
+}
@@ -0,0 +1,81 @@
+//! The server module exposes ready-made services that let twistrs be
+//! run as a standalone service (e.g. as a sidecar) rather than being
+//! embedded as a library.
+//!
+//! * [`grpc`]—requires the `grpc` feature.
+//! * [`http`]—REST/JSON API, requires the `http` feature.
+//!
+//! ### Features
+//!
+//! This module requires the `grpc` or `http` feature toggled. Each
+//! server is gated behind its own feature, so that clients only pull in
+//! the dependencies of the transports they use.
+
+use futures::stream::{BoxStream, StreamExt};
+
+use crate::config::EnrichmentConfig;
+use crate::error::Error;
+use crate::permutate::{Domain, Permutation, PermutationEngine};
+use crate::pipeline::{Pipeline, ScanResult};
+use crate::scoring::CompositeScorer;
+
+#[cfg(feature = "grpc")]
+pub mod grpc;
+
+#[cfg(feature = "http")]
+pub mod http;
+
+#[derive(thiserror::Error, Debug)]
+pub enum ServeError {
+    #[error("error serving requests (error: {error})")]
+    TransportError { error: anyhow::Error },
+}
+
+/// Generate the permutations of `fqdn` of the requested `kinds` (every
+/// kind if empty), along with the fingerprint of the engine configuration
+/// used.
+pub(crate) fn permutations(
+    fqdn: &str,
+    kinds: &[String],
+) -> Result<(Domain, Vec<Permutation>, String), Error> {
+    let domain = Domain::new(fqdn)?;
+    let mut engine = PermutationEngine::new();
+
+    if !kinds.is_empty() {
+        engine.kinds = kinds
+            .iter()
+            .map(|name| name.parse())
+            .collect::<Result<_, _>>()?;
+    }
+
+    let permutations = engine.generate(&domain)?.collect();
+
+    Ok((domain, permutations, engine.fingerprint()))
+}
+
+/// The enrichment config of a scan, where the enrichments requested by
+/// the client (if any) take precedence over those of the service.
+pub(crate) fn scan_enrichment(config: &EnrichmentConfig, requested: &[String]) -> EnrichmentConfig {
+    let mut enrichment = config.clone();
+
+    if !requested.is_empty() {
+        enrichment.enrichments = requested.to_vec();
+    }
+
+    enrichment
+}
+
+/// Enrich `permutations` through `pipeline`, riskiest first if the
+/// enrichment config asks for it.
+pub(crate) fn enrich<'a>(
+    pipeline: &'a Pipeline,
+    permutations: Vec<Permutation>,
+    enrichment: &EnrichmentConfig,
+    scorer: &CompositeScorer,
+) -> BoxStream<'a, ScanResult> {
+    if enrichment.prioritize {
+        pipeline.run_prioritized(permutations, scorer).boxed()
+    } else {
+        pipeline.run(permutations).boxed()
+    }
+}
@@ -0,0 +1,182 @@
+//! The spill module exposes [`ResultBuffer`], a buffer that keeps
+//! results in memory up to a threshold and spills the remainder to a
+//! temporary file, so that scans of millions of candidates can be
+//! gathered on modest hardware without running out of memory.
+//!
+//! Items are read back in the order they were pushed, and the temporary
+//! file is removed once the buffer is dropped.
+//!
+//! Example:
+//!
+//! ```
+//! use twistrs::permutate::Domain;
+//! use twistrs::spill::ResultBuffer;
+//!
+//! let domain = Domain::new("example.com").unwrap();
+//! let mut buffer = ResultBuffer::new(1_000);
+//!
+//! for permutation in domain.all().unwrap() {
+//!     buffer.push(permutation).unwrap();
+//! }
+//!
+//! for permutation in buffer.drain().unwrap() {
+//!     let permutation = permutation.unwrap();
+//! }
+//! ```
+//!
+//! ### Features
+//!
+//! This module requires the `spill` feature toggled.
+
+use std::fs::File;
+use std::io::{BufRead, BufReader, BufWriter, Seek, SeekFrom, Write};
+
+use serde::{de::DeserializeOwned, Serialize};
+
+use crate::error::Error;
+
+#[derive(thiserror::Error, Debug)]
+pub enum BufferError {
+    #[error("error accessing spill file (error: {error})")]
+    IoError { error: anyhow::Error },
+
+    #[error("error (de)serializing spilled item (error: {error})")]
+    SerializationError { error: anyhow::Error },
+}
+
+/// Append-only buffer spilling to a temporary file past `threshold`
+/// items held in memory.
+#[derive(Debug)]
+pub struct ResultBuffer<T> {
+    /// Maximum number of items held in memory.
+    pub threshold: usize,
+
+    memory: Vec<T>,
+    file: Option<BufWriter<File>>,
+    spilled: usize,
+}
+
+impl<T> ResultBuffer<T>
+where
+    T: Serialize + DeserializeOwned,
+{
+    /// Create an empty buffer holding up to `threshold` items in memory.
+    pub fn new(threshold: usize) -> ResultBuffer<T> {
+        ResultBuffer {
+            threshold,
+            memory: vec![],
+            file: None,
+            spilled: 0,
+        }
+    }
+
+    /// Append `item` to the buffer.
+    pub fn push(&mut self, item: T) -> Result<(), Error> {
+        // Once spilled, every item goes to disk so that the order is kept.
+        if self.file.is_none() && self.memory.len() < self.threshold {
+            self.memory.push(item);
+            return Ok(());
+        }
+
+        let file = match &mut self.file {
+            Some(file) => file,
+            None => self
+                .file
+                .insert(BufWriter::new(tempfile::tempfile().map_err(|e| {
+                    BufferError::IoError {
+                        error: anyhow::Error::msg(e),
+                    }
+                })?)),
+        };
+
+        serde_json::to_writer(&mut *file, &item).map_err(|e| BufferError::SerializationError {
+            error: anyhow::Error::msg(e),
+        })?;
+        file.write_all(b"\n").map_err(|e| BufferError::IoError {
+            error: anyhow::Error::msg(e),
+        })?;
+        self.spilled += 1;
+
+        Ok(())
+    }
+
+    /// Total number of items in the buffer.
+    pub fn len(&self) -> usize {
+        self.memory.len() + self.spilled
+    }
+
+    /// Whether the buffer holds no items.
+    pub fn is_empty(&self) -> bool {
+        self.len() == 0
+    }
+
+    /// Whether any item has been spilled to disk.
+    pub fn is_spilled(&self) -> bool {
+        self.spilled > 0
+    }
+
+    /// Consume the buffer, yielding every item in the order they were
+    /// pushed. Items read back from disk may fail to deserialize.
+    pub fn drain(self) -> Result<impl Iterator<Item = Result<T, Error>>, Error> {
+        let lines = match self.file {
+            Some(writer) => {
+                let mut file = writer.into_inner().map_err(|e| BufferError::IoError {
+                    error: anyhow::Error::msg(e.to_string()),
+                })?;
+                file.seek(SeekFrom::Start(0))
+                    .map_err(|e| BufferError::IoError {
+                        error: anyhow::Error::msg(e),
+                    })?;
+
+                Some(BufReader::new(file).lines())
+            }
+            None => None,
+        };
+
+        let spilled = lines.into_iter().flatten().map(|read| {
+            let line = read.map_err(|e| BufferError::IoError {
+                error: anyhow::Error::msg(e),
+            })?;
+
+            Ok(
+                serde_json::from_str(&line).map_err(|e| BufferError::SerializationError {
+                    error: anyhow::Error::msg(e),
+                })?,
+            )
+        });
+
+        Ok(self.memory.into_iter().map(Ok).chain(spilled))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::permutate::{Domain, Permutation};
+
+    #[test]
+    fn test_buffer_spills_in_order() {
+        let domain = Domain::new("example.com").unwrap();
+        let permutations: Vec<Permutation> = domain.addition().collect();
+
+        let mut buffer = ResultBuffer::new(10);
+        for permutation in permutations.clone() {
+            buffer.push(permutation).unwrap();
+        }
+
+        assert!(buffer.is_spilled());
+        assert_eq!(buffer.len(), permutations.len());
+
+        let drained: Vec<Permutation> = buffer.drain().unwrap().map(Result::unwrap).collect();
+        assert_eq!(drained, permutations);
+    }
+
+    #[test]
+    fn test_buffer_below_threshold_stays_in_memory() {
+        let mut buffer = ResultBuffer::new(10);
+        buffer.push(String::from("example.com")).unwrap();
+
+        assert!(!buffer.is_spilled());
+        assert_eq!(buffer.drain().unwrap().count(), 1);
+    }
+}
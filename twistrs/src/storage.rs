@@ -0,0 +1,810 @@
+//! The storage module exposes the [`DomainStore`] trait, used to
+//! persist enrichment results between runs, along with a number of
+//! embedded backends.
+//!
+//! * [`MemoryStore`]—always available, lives as long as the process.
+//! * [`SledStore`]—requires the `sled_store` feature.
+//! * [`SqliteStore`]—requires the `sqlite_store` feature.
+//!
+//! Records are keyed by FQDN, meaning that a store holds at most one
+//! [`DomainMetadata`] per domain. Large stores can be paged through by
+//! offset ([`DomainStore::page`]) or, more efficiently, by FQDN
+//! ([`DomainStore::page_after`]). Stores know nothing of permutations,
+//! so paging by permutation ID is left to the scan results, see the
+//! `after` cursor of the `http` server.
+//!
+//! Any store can back an [`EnrichmentCache`], expiring records after a
+//! TTL so that repeated runs only re-enrich the expired ones.
+//!
+//! Example:
+//!
+//! ```
+//! use twistrs::enrich::DomainMetadata;
+//! use twistrs::storage::{Comparison, DomainStore, MemoryStore};
+//!
+//! let mut store = MemoryStore::new();
+//! let metadata = DomainMetadata::new(String::from("example.com"));
+//!
+//! assert!(matches!(store.compare(&metadata).unwrap(), Comparison::New));
+//!
+//! store.put(metadata.clone()).unwrap();
+//! assert!(matches!(store.compare(&metadata).unwrap(), Comparison::Unchanged));
+//! ```
+use std::collections::{BTreeMap, HashSet};
+use std::ops::Bound;
+use std::sync::{Arc, Mutex, PoisonError};
+use std::time::{Duration, SystemTime, UNIX_EPOCH};
+
+#[cfg(any(feature = "sled_store", feature = "sqlite_store"))]
+use std::path::Path;
+
+use futures::stream::StreamExt;
+
+use crate::enrich::DomainMetadata;
+use crate::error::Error;
+use crate::permutate::Permutation;
+use crate::pipeline::{Observer, Pipeline, ScanResult};
+
+/// Default TTL of the records that did not resolve, short so that
+/// domains registered in the meantime are not missed for long.
+const DEFAULT_NEGATIVE_TTL: Duration = Duration::from_secs(60 * 60);
+
+#[derive(thiserror::Error, Debug)]
+pub enum StoreError {
+    #[error("error accessing domain store backend (error: {error})")]
+    BackendError { error: anyhow::Error },
+
+    #[error("error (de)serializing stored record (fqdn: {fqdn}, error: {error})")]
+    SerializationError { fqdn: String, error: anyhow::Error },
+}
+
+/// Outcome of comparing a fresh `DomainMetadata` against the record
+/// stored under the same FQDN.
+#[allow(clippy::large_enum_variant)]
+#[derive(Debug, Clone)]
+pub enum Comparison {
+    /// No record is stored for the FQDN.
+    New,
+
+    /// The stored record resolves to the same IPs and serves the same
+    /// content as the fresh one.
+    Unchanged,
+
+    /// The stored record differs, the previously stored record is
+    /// attached.
+    Changed(DomainMetadata),
+}
+
+/// Chunk of records returned by [`DomainStore::page_after`].
+#[derive(Debug, Clone, Default)]
+pub struct StorePage {
+    pub records: Vec<DomainMetadata>,
+
+    /// FQDN to request the next page after, `None` once every record has
+    /// been returned.
+    pub next: Option<String>,
+}
+
+impl StorePage {
+    fn new(records: Vec<DomainMetadata>, limit: usize) -> StorePage {
+        let next = (records.len() == limit)
+            .then(|| records.last().map(|record| record.fqdn.clone()))
+            .flatten();
+
+        StorePage { records, next }
+    }
+}
+
+/// Persistent key-value store of `DomainMetadata`, keyed by FQDN.
+pub trait DomainStore {
+    /// Fetch the record stored for `fqdn`, if any.
+    fn get(&self, fqdn: &str) -> Result<Option<DomainMetadata>, Error>;
+
+    /// Store `metadata` under its FQDN, returning the record it replaced.
+    fn put(&mut self, metadata: DomainMetadata) -> Result<Option<DomainMetadata>, Error>;
+
+    /// Iterate over all stored records, ordered by FQDN. Records that
+    /// cannot be read (e.g. corrupted ones) yield an error rather than
+    /// being skipped.
+    fn iter(&self) -> Result<Box<dyn Iterator<Item = Result<DomainMetadata, Error>> + '_>, Error>;
+
+    /// Fetch up to `limit` records ordered by FQDN, skipping the first
+    /// `offset` ones. Fails if any of them cannot be read.
+    fn page(&self, offset: usize, limit: usize) -> Result<Vec<DomainMetadata>, Error> {
+        self.iter()?.skip(offset).take(limit).collect()
+    }
+
+    /// Fetch up to `limit` records ordered by FQDN, starting right after
+    /// the `after` FQDN or from the first record if `None`. Unlike
+    /// [`DomainStore::page`], pages stay consistent while records are
+    /// being added and do not slow down the further they are. Fails if
+    /// any record up to the end of the page cannot be read.
+    ///
+    /// The cursor is the FQDN rather than a permutation ID (see
+    /// [`Permutation::id`](crate::permutate::Permutation::id)), as records
+    /// are keyed and ordered by FQDN and the ID of a domain depends on the
+    /// origin it was generated from, which the store does not record.
+    fn page_after(&self, after: Option<&str>, limit: usize) -> Result<StorePage, Error> {
+        let records = self
+            .iter()?
+            .filter(|entry| match entry {
+                Ok(record) => match after {
+                    Some(fqdn) => record.fqdn.as_str() > fqdn,
+                    None => true,
+                },
+                Err(_) => true,
+            })
+            .take(limit)
+            .collect::<Result<_, _>>()?;
+
+        Ok(StorePage::new(records, limit))
+    }
+
+    /// Compare `current` against the record stored under the same FQDN
+    /// without modifying the store.
+    fn compare(&self, current: &DomainMetadata) -> Result<Comparison, Error> {
+        Ok(match self.get(&current.fqdn)? {
+            None => Comparison::New,
+            Some(previous) if is_equivalent(&previous, current) => Comparison::Unchanged,
+            Some(previous) => Comparison::Changed(previous),
+        })
+    }
+}
+
+/// Two records are considered equivalent when they resolve to the same
+/// set of IPs and expose the same content.
+fn is_equivalent(previous: &DomainMetadata, current: &DomainMetadata) -> bool {
+    let sorted_ips = |metadata: &DomainMetadata| {
+        metadata.dns.ips().map(|mut ips| {
+            ips.sort();
+            ips
+        })
+    };
+
+    sorted_ips(previous) == sorted_ips(current)
+        && previous.http_banner == current.http_banner
+        && previous.who_is_lookup == current.who_is_lookup
+}
+
+/// In-memory `DomainStore`, primarily useful for short-lived processes
+/// and tests.
+#[derive(Debug, Clone, Default)]
+pub struct MemoryStore {
+    records: BTreeMap<String, DomainMetadata>,
+}
+
+impl MemoryStore {
+    /// Create a new, empty store.
+    pub fn new() -> MemoryStore {
+        MemoryStore::default()
+    }
+}
+
+impl DomainStore for MemoryStore {
+    fn get(&self, fqdn: &str) -> Result<Option<DomainMetadata>, Error> {
+        Ok(self.records.get(fqdn).cloned())
+    }
+
+    fn put(&mut self, metadata: DomainMetadata) -> Result<Option<DomainMetadata>, Error> {
+        Ok(self.records.insert(metadata.fqdn.clone(), metadata))
+    }
+
+    fn iter(&self) -> Result<Box<dyn Iterator<Item = Result<DomainMetadata, Error>> + '_>, Error> {
+        Ok(Box::new(self.records.values().cloned().map(Ok)))
+    }
+
+    fn page_after(&self, after: Option<&str>, limit: usize) -> Result<StorePage, Error> {
+        let start = after.map_or(Bound::Unbounded, Bound::Excluded);
+        let records = self
+            .records
+            .range::<str, _>((start, Bound::Unbounded))
+            .take(limit)
+            .map(|(_, record)| record.clone())
+            .collect();
+
+        Ok(StorePage::new(records, limit))
+    }
+}
+
+/// Cache of enrichment results backed by a `DomainStore`, e.g. a
+/// [`SledStore`] persisting the cache between daily monitoring runs.
+///
+/// Records that resolved are cached for `ttl`, and records that did not
+/// for `negative_ttl`, unless stored through
+/// [`EnrichmentCache::put_with_ttl`]. The expiry of each record is kept
+/// in its `cached_until` field. Enrichments that failed (e.g. timed out)
+/// are never cached, so that they are retried by the next run.
+///
+/// ```
+/// use std::time::Duration;
+///
+/// use twistrs::enrich::DomainMetadata;
+/// use twistrs::storage::{EnrichmentCache, MemoryStore};
+///
+/// let mut cache = EnrichmentCache::new(MemoryStore::new(), Duration::from_secs(60 * 60 * 24));
+/// cache.put(DomainMetadata::new(String::from("example.com"))).unwrap();
+///
+/// assert!(cache.get("example.com").unwrap().is_some());
+/// ```
+#[derive(Debug, Clone)]
+pub struct EnrichmentCache<S: DomainStore = MemoryStore> {
+    store: S,
+    pub ttl: Duration,
+    pub negative_ttl: Duration,
+}
+
+impl<S: DomainStore> EnrichmentCache<S> {
+    /// Create a cache over `store`, caching records that resolved for
+    /// `ttl` and the others for an hour, or `ttl` if shorter.
+    pub fn new(store: S, ttl: Duration) -> EnrichmentCache<S> {
+        EnrichmentCache {
+            store,
+            ttl,
+            negative_ttl: ttl.min(DEFAULT_NEGATIVE_TTL),
+        }
+    }
+
+    /// Store backing the cache, including the expired records.
+    pub fn store(&self) -> &S {
+        &self.store
+    }
+
+    /// Fetch the record cached for `fqdn`, unless it expired.
+    pub fn get(&self, fqdn: &str) -> Result<Option<DomainMetadata>, Error> {
+        let now = unix_now();
+
+        Ok(self
+            .store
+            .get(fqdn)?
+            .filter(|metadata| metadata.cached_until.is_some_and(|until| until > now)))
+    }
+
+    /// Cache `metadata` for `ttl` or `negative_ttl`, depending on whether
+    /// it resolved.
+    pub fn put(&mut self, metadata: DomainMetadata) -> Result<(), Error> {
+        let ttl = if metadata.dns.ips().is_some() {
+            self.ttl
+        } else {
+            self.negative_ttl
+        };
+
+        self.put_with_ttl(metadata, ttl)
+    }
+
+    /// Cache `metadata` for `ttl`, e.g. the TTL of its DNS records (see
+    /// [`DomainMetadata::min_ttl`]).
+    pub fn put_with_ttl(
+        &mut self,
+        mut metadata: DomainMetadata,
+        ttl: Duration,
+    ) -> Result<(), Error> {
+        metadata.cached_until = Some(unix_now().saturating_add(ttl.as_secs()));
+        self.store.put(metadata).map(|_| ())
+    }
+
+    /// Enrich `permutations` through `pipeline`, answering the ones with
+    /// a fresh record from the cache and caching the others, unless their
+    /// enrichment failed. Cached results come first, followed by the
+    /// enriched ones in completion order.
+    pub async fn run<I>(
+        &mut self,
+        pipeline: &Pipeline,
+        permutations: I,
+    ) -> Result<Vec<ScanResult>, Error>
+    where
+        I: IntoIterator<Item = Permutation>,
+    {
+        let mut results = vec![];
+        let mut expired = vec![];
+
+        for permutation in permutations {
+            match self.get(&permutation.domain.fqdn)? {
+                Some(metadata) => results.push(ScanResult {
+                    permutation,
+                    metadata,
+                    fingerprint: pipeline.fingerprint().map(str::to_string),
+                }),
+                None => expired.push(permutation),
+            }
+        }
+
+        let failures = Arc::new(Failures::default());
+        let mut observed = pipeline.clone();
+        observed.observe(Arc::clone(&failures));
+
+        let enriched: Vec<ScanResult> = observed.run(expired).collect().await;
+        let failed = failures
+            .fqdns
+            .lock()
+            .unwrap_or_else(PoisonError::into_inner);
+
+        for result in &enriched {
+            if !failed.contains(&result.permutation.domain.fqdn) {
+                self.put(result.metadata.clone())?;
+            }
+        }
+
+        results.extend(enriched);
+        Ok(results)
+    }
+}
+
+/// Observer recording the FQDNs whose enrichment failed, so that
+/// [`EnrichmentCache::run`] leaves them uncached.
+#[derive(Default)]
+struct Failures {
+    fqdns: Mutex<HashSet<String>>,
+}
+
+impl Observer for Arc<Failures> {
+    fn on_error(&self, permutation: &Permutation, _error: &Error) {
+        self.fqdns
+            .lock()
+            .unwrap_or_else(PoisonError::into_inner)
+            .insert(permutation.domain.fqdn.clone());
+    }
+}
+
+/// Seconds elapsed since the Unix epoch.
+pub(crate) fn unix_now() -> u64 {
+    SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .map_or(0, |elapsed| elapsed.as_secs())
+}
+
+#[cfg(any(feature = "sled_store", feature = "sqlite_store"))]
+fn serialize(metadata: &DomainMetadata) -> Result<Vec<u8>, Error> {
+    Ok(
+        serde_json::to_vec(metadata).map_err(|e| StoreError::SerializationError {
+            fqdn: metadata.fqdn.clone(),
+            error: anyhow::Error::msg(e),
+        })?,
+    )
+}
+
+#[cfg(any(feature = "sled_store", feature = "sqlite_store"))]
+fn deserialize(fqdn: &str, bytes: &[u8]) -> Result<DomainMetadata, Error> {
+    Ok(
+        serde_json::from_slice(bytes).map_err(|e| StoreError::SerializationError {
+            fqdn: fqdn.to_string(),
+            error: anyhow::Error::msg(e),
+        })?,
+    )
+}
+
+/// `DomainStore` backed by an embedded [sled](https://docs.rs/sled) database.
+///
+/// ### Features
+///
+/// This struct requires the `sled_store` feature toggled.
+#[cfg(feature = "sled_store")]
+pub struct SledStore {
+    db: sled::Db,
+}
+
+#[cfg(feature = "sled_store")]
+impl SledStore {
+    /// Open (or create) a sled database at `path`.
+    pub fn open<P: AsRef<Path>>(path: P) -> Result<SledStore, Error> {
+        let db = sled::open(path).map_err(|e| StoreError::BackendError {
+            error: anyhow::Error::msg(e),
+        })?;
+
+        Ok(SledStore { db })
+    }
+}
+
+#[cfg(feature = "sled_store")]
+impl DomainStore for SledStore {
+    fn get(&self, fqdn: &str) -> Result<Option<DomainMetadata>, Error> {
+        self.db
+            .get(fqdn)
+            .map_err(|e| StoreError::BackendError {
+                error: anyhow::Error::msg(e),
+            })?
+            .map(|bytes| deserialize(fqdn, &bytes))
+            .transpose()
+    }
+
+    fn put(&mut self, metadata: DomainMetadata) -> Result<Option<DomainMetadata>, Error> {
+        let bytes = serialize(&metadata)?;
+
+        self.db
+            .insert(metadata.fqdn.as_str(), bytes)
+            .map_err(|e| StoreError::BackendError {
+                error: anyhow::Error::msg(e),
+            })?
+            .map(|previous| deserialize(&metadata.fqdn, &previous))
+            .transpose()
+    }
+
+    fn iter(&self) -> Result<Box<dyn Iterator<Item = Result<DomainMetadata, Error>> + '_>, Error> {
+        Ok(Box::new(self.db.iter().map(sled_record)))
+    }
+
+    fn page_after(&self, after: Option<&str>, limit: usize) -> Result<StorePage, Error> {
+        let start = after.map_or(Bound::Unbounded, |fqdn| Bound::Excluded(fqdn.as_bytes()));
+        let records = self
+            .db
+            .range::<&[u8], _>((start, Bound::Unbounded))
+            .map(sled_record)
+            .take(limit)
+            .collect::<Result<_, _>>()?;
+
+        Ok(StorePage::new(records, limit))
+    }
+}
+
+/// Deserialize an entry read from sled.
+#[cfg(feature = "sled_store")]
+fn sled_record(entry: sled::Result<(sled::IVec, sled::IVec)>) -> Result<DomainMetadata, Error> {
+    let (key, value) = entry.map_err(|e| StoreError::BackendError {
+        error: anyhow::Error::msg(e),
+    })?;
+
+    deserialize(&String::from_utf8_lossy(&key), &value)
+}
+
+/// `DomainStore` backed by an embedded `SQLite` database.
+///
+/// ### Features
+///
+/// This struct requires the `sqlite_store` feature toggled.
+#[cfg(feature = "sqlite_store")]
+pub struct SqliteStore {
+    connection: rusqlite::Connection,
+}
+
+#[cfg(feature = "sqlite_store")]
+impl SqliteStore {
+    /// Open (or create) a `SQLite` database at `path`.
+    pub fn open<P: AsRef<Path>>(path: P) -> Result<SqliteStore, Error> {
+        SqliteStore::from_connection(rusqlite::Connection::open(path).map_err(|e| {
+            StoreError::BackendError {
+                error: anyhow::Error::msg(e),
+            }
+        })?)
+    }
+
+    /// Create a `SQLite` database that only lives in memory.
+    pub fn open_in_memory() -> Result<SqliteStore, Error> {
+        SqliteStore::from_connection(rusqlite::Connection::open_in_memory().map_err(|e| {
+            StoreError::BackendError {
+                error: anyhow::Error::msg(e),
+            }
+        })?)
+    }
+
+    fn from_connection(connection: rusqlite::Connection) -> Result<SqliteStore, Error> {
+        connection
+            .execute(
+                "CREATE TABLE IF NOT EXISTS domain_metadata (
+                    fqdn TEXT PRIMARY KEY NOT NULL,
+                    metadata BLOB NOT NULL
+                )",
+                [],
+            )
+            .map_err(|e| StoreError::BackendError {
+                error: anyhow::Error::msg(e),
+            })?;
+
+        Ok(SqliteStore { connection })
+    }
+
+    /// Run `sql`, selecting the FQDN and metadata columns, where each
+    /// record that cannot be read yields an error.
+    fn query<P: rusqlite::Params>(
+        &self,
+        sql: &str,
+        params: P,
+    ) -> Result<Vec<Result<DomainMetadata, Error>>, Error> {
+        let error = |e: rusqlite::Error| StoreError::BackendError {
+            error: anyhow::Error::msg(e),
+        };
+
+        let mut statement = self.connection.prepare(sql).map_err(error)?;
+        let records = statement
+            .query_map(params, |row| {
+                Ok((row.get::<_, String>(0)?, row.get::<_, Vec<u8>>(1)?))
+            })
+            .map_err(error)?
+            .map(|row| {
+                let (fqdn, bytes) = row.map_err(error)?;
+                deserialize(&fqdn, &bytes)
+            })
+            .collect();
+
+        Ok(records)
+    }
+}
+
+#[cfg(feature = "sqlite_store")]
+impl DomainStore for SqliteStore {
+    fn get(&self, fqdn: &str) -> Result<Option<DomainMetadata>, Error> {
+        use rusqlite::OptionalExtension;
+
+        self.connection
+            .query_row(
+                "SELECT metadata FROM domain_metadata WHERE fqdn = ?1",
+                [fqdn],
+                |row| row.get::<_, Vec<u8>>(0),
+            )
+            .optional()
+            .map_err(|e| StoreError::BackendError {
+                error: anyhow::Error::msg(e),
+            })?
+            .map(|bytes| deserialize(fqdn, &bytes))
+            .transpose()
+    }
+
+    fn put(&mut self, metadata: DomainMetadata) -> Result<Option<DomainMetadata>, Error> {
+        let previous = self.get(&metadata.fqdn)?;
+
+        self.connection
+            .execute(
+                "INSERT OR REPLACE INTO domain_metadata (fqdn, metadata) VALUES (?1, ?2)",
+                rusqlite::params![metadata.fqdn, serialize(&metadata)?],
+            )
+            .map_err(|e| StoreError::BackendError {
+                error: anyhow::Error::msg(e),
+            })?;
+
+        Ok(previous)
+    }
+
+    fn iter(&self) -> Result<Box<dyn Iterator<Item = Result<DomainMetadata, Error>> + '_>, Error> {
+        let records = self.query(
+            "SELECT fqdn, metadata FROM domain_metadata ORDER BY fqdn",
+            [],
+        )?;
+
+        Ok(Box::new(records.into_iter()))
+    }
+
+    fn page(&self, offset: usize, limit: usize) -> Result<Vec<DomainMetadata>, Error> {
+        self.query(
+            "SELECT fqdn, metadata FROM domain_metadata ORDER BY fqdn LIMIT ?1 OFFSET ?2",
+            rusqlite::params![sql_count(limit), sql_count(offset)],
+        )?
+        .into_iter()
+        .collect()
+    }
+
+    fn page_after(&self, after: Option<&str>, limit: usize) -> Result<StorePage, Error> {
+        // Every FQDN sorts after the empty string.
+        let records = self
+            .query(
+                "SELECT fqdn, metadata FROM domain_metadata WHERE fqdn > ?1 ORDER BY fqdn LIMIT ?2",
+                rusqlite::params![after.unwrap_or_default(), sql_count(limit)],
+            )?
+            .into_iter()
+            .collect::<Result<_, _>>()?;
+
+        Ok(StorePage::new(records, limit))
+    }
+}
+
+/// `SQLite` integers are signed 64-bit, larger counts are clamped.
+#[cfg(feature = "sqlite_store")]
+fn sql_count(count: usize) -> i64 {
+    i64::try_from(count).unwrap_or(i64::MAX)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    use crate::enrich::DnsRecords;
+
+    fn metadata(fqdn: &str, banner: Option<&str>) -> DomainMetadata {
+        let mut metadata = DomainMetadata::new(fqdn.to_string());
+        metadata.dns = DnsRecords::from_ips(["127.0.0.1".parse().unwrap()]);
+        metadata.http_banner = banner.map(String::from);
+        metadata
+    }
+
+    fn exercise_store<S: DomainStore>(store: &mut S) {
+        let original = metadata("example.com", Some("nginx"));
+        let changed = metadata("example.com", Some("apache"));
+
+        assert!(matches!(store.compare(&original).unwrap(), Comparison::New));
+        assert!(store.put(original.clone()).unwrap().is_none());
+        assert!(matches!(
+            store.compare(&original).unwrap(),
+            Comparison::Unchanged
+        ));
+        assert!(matches!(
+            store.compare(&changed).unwrap(),
+            Comparison::Changed(_)
+        ));
+
+        store.put(metadata("another.com", None)).unwrap();
+
+        let fqdns: Vec<String> = store.iter().unwrap().map(|m| m.unwrap().fqdn).collect();
+        assert_eq!(fqdns, vec!["another.com", "example.com"]);
+
+        store.put(metadata("third.com", None)).unwrap();
+        let paged: Vec<String> = store
+            .page(1, 5)
+            .unwrap()
+            .into_iter()
+            .map(|m| m.fqdn)
+            .collect();
+        assert_eq!(paged, vec!["example.com", "third.com"]);
+
+        let first = store.page_after(None, 2).unwrap();
+        assert_eq!(first.records.len(), 2);
+        assert_eq!(first.next.as_deref(), Some("example.com"));
+
+        let last = store.page_after(first.next.as_deref(), 2).unwrap();
+        assert_eq!(last.records[0].fqdn, "third.com");
+        assert_eq!(last.next, None);
+        assert_eq!(
+            store.get("example.com").unwrap().unwrap().http_banner,
+            Some(String::from("nginx"))
+        );
+    }
+
+    #[tokio::test]
+    async fn test_enrichment_cache() {
+        use crate::permutate::Domain;
+        use crate::resolver::MockResolver;
+
+        let mut cache = EnrichmentCache::new(MemoryStore::new(), Duration::from_secs(60));
+        cache.negative_ttl = Duration::ZERO;
+
+        let mut resolver = MockResolver::from_hosts("127.0.0.1 examplea.com").unwrap();
+        resolver.insert_banner("examplea.com", "nginx");
+        let mut pipeline = Pipeline::new();
+        pipeline.set_resolver(resolver);
+
+        let domain = Domain::new("example.com").unwrap();
+        let results = cache.run(&pipeline, domain.addition()).await.unwrap();
+        assert_eq!(results.len(), 26);
+
+        assert!(cache.get("examplea.com").unwrap().is_some());
+        assert!(cache.get("exampleb.com").unwrap().is_none());
+        assert!(cache.store().get("exampleb.com").unwrap().is_some());
+
+        // Fresh records are answered from the cache, without resolving.
+        pipeline.set_resolver(MockResolver::default());
+        let cached = cache.run(&pipeline, domain.addition()).await.unwrap();
+        assert_eq!(cached[0].metadata.fqdn, "examplea.com");
+        assert!(cached[0].metadata.dns.ips().is_some());
+
+        cache
+            .put_with_ttl(metadata("examplea.com", None), Duration::ZERO)
+            .unwrap();
+        assert!(cache.get("examplea.com").unwrap().is_none());
+    }
+
+    #[tokio::test]
+    async fn test_enrichment_cache_retries_failures() {
+        use crate::permutate::Domain;
+        use crate::resolver::MockResolver;
+
+        let ttl = Duration::from_secs(60 * 60 * 24);
+        let mut cache = EnrichmentCache::new(MemoryStore::new(), ttl);
+        assert_eq!(cache.negative_ttl, DEFAULT_NEGATIVE_TTL);
+        assert_eq!(
+            EnrichmentCache::new(MemoryStore::new(), Duration::from_secs(60)).negative_ttl,
+            Duration::from_secs(60)
+        );
+
+        // Resolves, but the banner lookup fails for want of a fixture.
+        let mut pipeline = Pipeline::new();
+        pipeline.set_resolver(MockResolver::from_hosts("127.0.0.1 examplea.com").unwrap());
+
+        let domain = Domain::new("example.com").unwrap();
+        cache.run(&pipeline, domain.addition()).await.unwrap();
+        assert!(cache.get("examplea.com").unwrap().is_none());
+        assert!(cache.get("exampleb.com").unwrap().is_some());
+
+        // The failed lookup is retried by the next run, and cached once it
+        // succeeds.
+        let mut resolver = MockResolver::from_hosts("127.0.0.1 examplea.com").unwrap();
+        resolver.insert_banner("examplea.com", "nginx");
+        pipeline.set_resolver(resolver);
+
+        let results = cache.run(&pipeline, domain.addition()).await.unwrap();
+        let retried = results
+            .iter()
+            .find(|result| result.metadata.fqdn == "examplea.com")
+            .unwrap();
+        assert_eq!(retried.metadata.http_banner.as_deref(), Some("nginx"));
+        assert!(cache.get("examplea.com").unwrap().is_some());
+    }
+
+    #[test]
+    fn test_memory_store() {
+        exercise_store(&mut MemoryStore::new());
+    }
+
+    /// Reading `corrupted.com`, whose record cannot be deserialized,
+    /// fails rather than skipping it.
+    #[cfg(any(feature = "sled_store", feature = "sqlite_store"))]
+    fn exercise_corrupted_store<S: DomainStore>(store: &mut S) {
+        store.put(metadata("another.com", None)).unwrap();
+
+        assert!(store.get("corrupted.com").is_err());
+        assert!(store.iter().unwrap().any(|record| record.is_err()));
+        assert!(store.page(0, 10).is_err());
+        assert!(store.page_after(None, 10).is_err());
+
+        // Pages that stop short of the corrupted record are fine.
+        assert_eq!(store.page_after(None, 1).unwrap().records.len(), 1);
+    }
+
+    #[test]
+    #[cfg(feature = "sled_store")]
+    fn test_sled_store() {
+        let db = sled::Config::new().temporary(true).open().unwrap();
+        exercise_store(&mut SledStore { db });
+    }
+
+    #[test]
+    #[cfg(feature = "sled_store")]
+    fn test_sled_store_corrupted_records() {
+        let db = sled::Config::new().temporary(true).open().unwrap();
+        db.insert("corrupted.com", b"not json".to_vec()).unwrap();
+
+        exercise_corrupted_store(&mut SledStore { db });
+    }
+
+    #[test]
+    #[cfg(feature = "sqlite_store")]
+    fn test_sqlite_store() {
+        exercise_store(&mut SqliteStore::open_in_memory().unwrap());
+    }
+
+    #[test]
+    #[cfg(feature = "sqlite_store")]
+    fn test_sqlite_store_corrupted_records() {
+        let mut store = SqliteStore::open_in_memory().unwrap();
+        store
+            .connection
+            .execute(
+                "INSERT INTO domain_metadata (fqdn, metadata) VALUES (?1, ?2)",
+                rusqlite::params!["corrupted.com", b"not json".to_vec()],
+            )
+            .unwrap();
+
+        exercise_corrupted_store(&mut store);
+    }
+
+    #[test]
+    #[cfg(feature = "sqlite_store")]
+    fn test_sqlite_store_legacy_records() {
+        let store = SqliteStore::open_in_memory().unwrap();
+        store
+            .connection
+            .execute(
+                "INSERT INTO domain_metadata (fqdn, metadata) VALUES (?1, ?2)",
+                rusqlite::params![
+                    "example.com",
+                    br#"{"fqdn":"example.com","ips":["127.0.0.1","::1"],"http_banner":"nginx"}"#
+                        .to_vec()
+                ],
+            )
+            .unwrap();
+
+        let legacy = store.get("example.com").unwrap().unwrap();
+        assert_eq!(
+            legacy.dns.ips(),
+            Some(vec!["127.0.0.1".parse().unwrap(), "::1".parse().unwrap()])
+        );
+        assert!(matches!(
+            store
+                .compare(&metadata("example.com", Some("nginx")))
+                .unwrap(),
+            Comparison::Changed(_)
+        ));
+
+        let mut resolving = metadata("example.com", Some("nginx"));
+        resolving.dns = legacy.dns.clone();
+        assert!(matches!(
+            store.compare(&resolving).unwrap(),
+            Comparison::Unchanged
+        ));
+    }
+}
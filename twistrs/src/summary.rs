@@ -0,0 +1,247 @@
+//! The summary module exposes [`ScanSummary`], the aggregates of a scan
+//! (e.g. how many permutations were generated and resolved per kind, and
+//! the riskiest resolving ones), so that every frontend reports the same
+//! figures instead of recomputing them.
+//!
+//! Example:
+//!
+//! ```no_run
+//! use futures::stream::StreamExt;
+//! use twistrs::permutate::Domain;
+//! use twistrs::pipeline::{Pipeline, ScanResult};
+//! use twistrs::scoring::CompositeScorer;
+//! use twistrs::summary::ScanSummary;
+//!
+//! #[tokio::main]
+//! async fn main() {
+//!     let domain = Domain::new("example.com").unwrap();
+//!     let results: Vec<ScanResult> = Pipeline::new().run(domain.addition()).collect().await;
+//!
+//!     let summary = ScanSummary::new(&CompositeScorer::new(domain), &results);
+//!     println!("{} of {} resolve", summary.resolvable, summary.generated);
+//! }
+//! ```
+//!
+//! ### Features
+//!
+//! This module requires the `enrich` feature toggled.
+
+use std::collections::BTreeMap;
+
+use serde::{Deserialize, Serialize};
+
+use crate::monitor::Event;
+use crate::permutate::{Domain, Permutation, PermutationKind};
+use crate::pipeline::ScanResult;
+use crate::scoring::{CompositeScorer, Scorer};
+
+/// Number of resolving permutations listed in [`ScanSummary::top_risks`].
+const TOP_RISKS: usize = 10;
+
+/// Counts of a scan for a single permutation kind.
+#[derive(Clone, Copy, Debug, Default, Serialize, Deserialize, Hash, Eq, PartialEq)]
+pub struct KindCounts {
+    /// Number of permutations generated.
+    pub generated: usize,
+
+    /// Number of permutations that resolve.
+    pub resolvable: usize,
+
+    /// Number of permutations reported as newly registered, see
+    /// [`ScanSummary::record_event`].
+    pub newly_registered: usize,
+}
+
+/// Resolving permutation along with its risk score.
+#[derive(Clone, Debug, Serialize, Deserialize, PartialEq)]
+pub struct RiskEntry {
+    pub permutation: Permutation,
+
+    /// Score given by the [`Scorer`] of the scan, by default
+    /// [`CompositeScorer::score_result`].
+    pub score: f64,
+}
+
+/// Aggregates of a scan of a single domain.
+// Reads better than `summary::Scan` once imported.
+#[allow(clippy::module_name_repetitions)]
+#[derive(Clone, Debug, Serialize, Deserialize, PartialEq)]
+pub struct ScanSummary {
+    /// Domain the permutations were generated from.
+    pub domain: Domain,
+
+    /// Number of permutations generated.
+    pub generated: usize,
+
+    /// Number of permutations that resolve.
+    pub resolvable: usize,
+
+    /// Number of permutations reported as newly registered.
+    pub newly_registered: usize,
+
+    /// Counts per permutation kind.
+    pub per_kind: BTreeMap<PermutationKind, KindCounts>,
+
+    /// Riskiest resolving permutations, riskiest first.
+    pub top_risks: Vec<RiskEntry>,
+}
+
+impl ScanSummary {
+    /// Summarize `results`, each of which counts as generated, scoring
+    /// resolving ones with `scorer`.
+    pub fn new<'a, I>(scorer: &CompositeScorer, results: I) -> ScanSummary
+    where
+        I: IntoIterator<Item = &'a ScanResult>,
+    {
+        ScanSummary::summarize(scorer.base.clone(), results, |result| {
+            scorer.score_result(result)
+        })
+    }
+
+    /// Summarize `results` of a scan of `domain` as [`ScanSummary::new`]
+    /// does, scoring resolving ones with any `scorer`.
+    pub fn with_scorer<'a, I>(domain: Domain, scorer: &dyn Scorer, results: I) -> ScanSummary
+    where
+        I: IntoIterator<Item = &'a ScanResult>,
+    {
+        ScanSummary::summarize(domain, results, |result| {
+            scorer.score(&result.permutation, Some(&result.metadata))
+        })
+    }
+
+    fn summarize<'a, I, F>(domain: Domain, results: I, score: F) -> ScanSummary
+    where
+        I: IntoIterator<Item = &'a ScanResult>,
+        F: Fn(&ScanResult) -> f64,
+    {
+        let mut summary = ScanSummary {
+            domain,
+            generated: 0,
+            resolvable: 0,
+            newly_registered: 0,
+            per_kind: BTreeMap::new(),
+            top_risks: vec![],
+        };
+
+        for result in results {
+            let counts = summary.per_kind.entry(result.permutation.kind).or_default();
+            counts.generated += 1;
+            summary.generated += 1;
+
+            if result.metadata.dns.resolves() {
+                counts.resolvable += 1;
+                summary.resolvable += 1;
+
+                summary.top_risks.push(RiskEntry {
+                    permutation: result.permutation.clone(),
+                    score: score(result),
+                });
+            }
+        }
+
+        // Ties are broken on the permutation, so that the summary does not
+        // depend on the order results completed in.
+        summary.top_risks.sort_by(|left, right| {
+            right
+                .score
+                .total_cmp(&left.score)
+                .then_with(|| left.permutation.cmp(&right.permutation))
+        });
+        summary.top_risks.truncate(TOP_RISKS);
+
+        summary
+    }
+
+    /// Count `permutations` as the ones generated, for scans whose results
+    /// only hold resolving permutations (e.g. a
+    /// [`ScanJob`](crate::pipeline::ScanJob)).
+    pub fn set_generated<'a, I>(&mut self, permutations: I)
+    where
+        I: IntoIterator<Item = &'a Permutation>,
+    {
+        self.generated = 0;
+        for counts in self.per_kind.values_mut() {
+            counts.generated = 0;
+        }
+
+        for permutation in permutations {
+            self.per_kind.entry(permutation.kind).or_default().generated += 1;
+            self.generated += 1;
+        }
+    }
+
+    /// Count the permutation of a monitoring `event` as newly registered,
+    /// if it is one.
+    pub fn record_event(&mut self, event: &Event) {
+        if let Event::NewlyRegistered { permutation, .. } = event {
+            self.per_kind
+                .entry(permutation.kind)
+                .or_default()
+                .newly_registered += 1;
+            self.newly_registered += 1;
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    use crate::enrich::{DnsRecords, DomainMetadata};
+
+    fn result(fqdn: &str, kind: PermutationKind, resolving: bool) -> ScanResult {
+        let mut metadata = DomainMetadata::new(fqdn.to_string());
+        if resolving {
+            metadata.dns = DnsRecords::from_ips(["127.0.0.1".parse().unwrap()]);
+        }
+
+        ScanResult {
+            permutation: Permutation {
+                domain: Domain::new(fqdn).unwrap(),
+                kind,
+                substitutions: vec![],
+            },
+            metadata,
+            fingerprint: None,
+        }
+    }
+
+    #[test]
+    fn test_scan_summary() {
+        let scorer = CompositeScorer::new(Domain::new("example.com").unwrap());
+        let results = vec![
+            result("examplea.com", PermutationKind::Addition, false),
+            result("exampleb.com", PermutationKind::Addition, true),
+            result("examp1e.com", PermutationKind::Homoglyph, true),
+        ];
+
+        let mut summary = ScanSummary::new(&scorer, &results);
+        assert_eq!(summary.generated, 3);
+        assert_eq!(summary.resolvable, 2);
+        assert_eq!(
+            summary.per_kind[&PermutationKind::Addition],
+            KindCounts {
+                generated: 2,
+                resolvable: 1,
+                newly_registered: 0,
+            }
+        );
+        assert_eq!(summary.top_risks.len(), 2);
+        assert!(summary.top_risks[0].score >= summary.top_risks[1].score);
+
+        summary.record_event(&Event::NewlyRegistered {
+            permutation: results[1].permutation.clone(),
+            metadata: results[1].metadata.clone(),
+        });
+        assert_eq!(summary.newly_registered, 1);
+        assert_eq!(
+            summary.per_kind[&PermutationKind::Addition].newly_registered,
+            1
+        );
+
+        let generated: Vec<Permutation> = Domain::new("example.com").unwrap().addition().collect();
+        summary.set_generated(&generated);
+        assert_eq!(summary.generated, generated.len());
+        assert_eq!(summary.per_kind[&PermutationKind::Homoglyph].generated, 0);
+    }
+}
@@ -0,0 +1,402 @@
+//! The throttle module exposes [`DestinationLimiter`], capping the number
+//! of in-flight requests per destination (e.g. per resolved IP or per
+//! WHOIS server) independently of the overall concurrency.
+//!
+//! Thousands of parked permutations are commonly served by a handful of
+//! hosts, so without a per-destination cap a scan can inadvertently
+//! hammer a single host.
+//!
+//! It also exposes [`AdaptiveConcurrency`], adjusting the overall number
+//! of in-flight tasks to the failures observed (AIMD): the limit grows
+//! additively while tasks succeed, and is cut multiplicatively once they
+//! start failing, e.g. because resolvers time out.
+//!
+//! Example:
+//!
+//! ```
+//! use twistrs::throttle::DestinationLimiter;
+//!
+//! #[tokio::main]
+//! async fn main() {
+//!     let limiter = DestinationLimiter::new(2);
+//!
+//!     let permit = limiter.acquire("whois.verisign-grs.com").await;
+//!     // ...perform the lookup while holding the permit.
+//!     drop(permit);
+//! }
+//! ```
+//!
+//! ### Features
+//!
+//! This module requires the `enrich` feature toggled.
+use std::collections::HashMap;
+use std::sync::{Arc, Mutex, PoisonError};
+
+use tokio::sync::{Notify, OwnedSemaphorePermit, Semaphore};
+
+/// Default step by which the limit of an [`AdaptiveConcurrency`] grows.
+const DEFAULT_INCREASE: usize = 1;
+
+/// Default percentage of its limit an [`AdaptiveConcurrency`] keeps after
+/// a failure.
+const DEFAULT_BACKOFF_PERCENT: usize = 50;
+
+/// Per-destination cap on in-flight requests.
+///
+/// Clones share the same limits, so a single limiter can be handed to
+/// every task talking to the same destinations. Destinations are only
+/// tracked while requests towards them are in flight or waiting, so that
+/// scans spanning many hosts do not accumulate idle state.
+#[derive(Clone, Debug)]
+pub struct DestinationLimiter {
+    /// Maximum number of in-flight requests per destination, `0` meaning
+    /// unlimited.
+    pub max_in_flight: usize,
+
+    destinations: Arc<Mutex<HashMap<String, Arc<Semaphore>>>>,
+}
+
+impl DestinationLimiter {
+    /// Create a limiter allowing up to `max_in_flight` requests per
+    /// destination.
+    pub fn new(max_in_flight: usize) -> DestinationLimiter {
+        DestinationLimiter {
+            max_in_flight,
+            destinations: Arc::default(),
+        }
+    }
+
+    /// Wait for a slot towards `destination`, which is released once the
+    /// returned permit is dropped. Returns `None` straight away if the
+    /// limiter is unlimited.
+    pub async fn acquire(&self, destination: &str) -> Option<DestinationPermit> {
+        if self.max_in_flight == 0 {
+            return None;
+        }
+
+        let key = destination.to_lowercase();
+        let semaphore = Arc::clone(
+            self.lock()
+                .entry(key.clone())
+                .or_insert_with(|| Arc::new(Semaphore::new(self.max_in_flight))),
+        );
+
+        // Semaphores are never closed, so acquiring only fails if the
+        // limiter is misused through `tokio` internals.
+        let permit = semaphore.acquire_owned().await.ok()?;
+
+        Some(DestinationPermit {
+            permit: Some(permit),
+            destination: key,
+            limiter: self.clone(),
+        })
+    }
+
+    /// Number of requests currently in flight towards `destination`.
+    pub fn in_flight(&self, destination: &str) -> usize {
+        self.lock()
+            .get(&destination.to_lowercase())
+            .map_or(0, |semaphore| {
+                self.max_in_flight
+                    .saturating_sub(semaphore.available_permits())
+            })
+    }
+
+    /// Number of destinations currently tracked, i.e. with requests in
+    /// flight or waiting.
+    pub fn destinations(&self) -> usize {
+        self.lock().len()
+    }
+
+    fn lock(&self) -> std::sync::MutexGuard<'_, HashMap<String, Arc<Semaphore>>> {
+        self.destinations
+            .lock()
+            .unwrap_or_else(PoisonError::into_inner)
+    }
+}
+
+/// Slot of a [`DestinationLimiter`], released when dropped. The last slot
+/// released towards a destination stops it from being tracked.
+#[derive(Debug)]
+pub struct DestinationPermit {
+    permit: Option<OwnedSemaphorePermit>,
+    destination: String,
+    limiter: DestinationLimiter,
+}
+
+impl Drop for DestinationPermit {
+    fn drop(&mut self) {
+        // Released under the lock, so that a task about to wait on the
+        // same semaphore has either cloned it already or creates a new one.
+        let mut destinations = self.limiter.lock();
+        drop(self.permit.take());
+
+        let idle = destinations
+            .get(&self.destination)
+            .is_some_and(|semaphore| Arc::strong_count(semaphore) == 1);
+
+        if idle {
+            destinations.remove(&self.destination);
+        }
+    }
+}
+
+/// Controller adjusting the number of in-flight tasks between `min` and
+/// `max` with additive increase and multiplicative decrease (AIMD).
+///
+/// The limit starts at `max`. Every time a full window of tasks (i.e. as
+/// many as the current limit) succeeds, it grows by `increase`. When a
+/// task fails, it is cut down to `backoff_percent` of itself, unless the
+/// task was already in flight at the last cut, so that a burst of
+/// concurrent failures only counts once.
+///
+/// Clones share the same limit, so a single controller can be handed to
+/// every task.
+#[derive(Clone, Debug)]
+pub struct AdaptiveConcurrency {
+    /// Least number of in-flight tasks, however many fail.
+    pub min: usize,
+
+    /// Step by which the limit grows after a window of successes.
+    pub increase: usize,
+
+    /// Percentage of the limit kept after a failure.
+    pub backoff_percent: usize,
+
+    state: Arc<Mutex<AdaptiveState>>,
+    released: Arc<Notify>,
+}
+
+#[derive(Debug)]
+struct AdaptiveState {
+    max: usize,
+    limit: usize,
+    in_flight: usize,
+    successes: usize,
+
+    /// Tasks completed since the last backoff, and how many were in
+    /// flight at the time, whose failures are part of the same burst.
+    since_backoff: usize,
+    burst: usize,
+}
+
+impl AdaptiveConcurrency {
+    /// Create a controller keeping between `min` and `max` tasks in
+    /// flight, both at least one.
+    pub fn new(min: usize, max: usize) -> AdaptiveConcurrency {
+        let floor = min.max(1);
+        let ceiling = max.max(floor);
+
+        AdaptiveConcurrency {
+            min: floor,
+            increase: DEFAULT_INCREASE,
+            backoff_percent: DEFAULT_BACKOFF_PERCENT,
+            state: Arc::new(Mutex::new(AdaptiveState {
+                max: ceiling,
+                limit: ceiling,
+                in_flight: 0,
+                successes: 0,
+                since_backoff: 0,
+                burst: 0,
+            })),
+            released: Arc::default(),
+        }
+    }
+
+    /// Wait until fewer tasks than the current limit are in flight. The
+    /// slot is released once the returned permit is dropped, and the
+    /// outcome of the task should be recorded through
+    /// [`AdaptivePermit::record`].
+    pub async fn acquire(&self) -> AdaptivePermit {
+        loop {
+            // Registered before checking, so that a release happening in
+            // between is not missed.
+            let released = self.released.notified();
+
+            {
+                let mut state = self.lock();
+
+                if state.in_flight < state.limit {
+                    state.in_flight += 1;
+
+                    return AdaptivePermit {
+                        controller: self.clone(),
+                    };
+                }
+            }
+
+            released.await;
+        }
+    }
+
+    /// Current maximum number of in-flight tasks.
+    pub fn limit(&self) -> usize {
+        self.lock().limit
+    }
+
+    /// Number of tasks currently in flight.
+    pub fn in_flight(&self) -> usize {
+        self.lock().in_flight
+    }
+
+    /// Greatest number of in-flight tasks, however many succeed.
+    pub fn max(&self) -> usize {
+        self.lock().max
+    }
+
+    /// Change the greatest number of in-flight tasks to `max`, at least
+    /// `min`, for every clone of the controller. A limit at the previous
+    /// maximum follows the new one, and is otherwise kept below it.
+    pub fn set_max(&self, max: usize) {
+        let mut state = self.lock();
+        let ceiling = max.max(self.min);
+
+        state.limit = if state.limit >= state.max {
+            ceiling
+        } else {
+            state.limit.min(ceiling)
+        };
+        state.max = ceiling;
+        drop(state);
+
+        self.released.notify_waiters();
+    }
+
+    fn record(&self, failed: bool) {
+        let mut state = self.lock();
+        state.since_backoff = state.since_backoff.saturating_add(1);
+
+        if failed {
+            state.successes = 0;
+
+            if state.since_backoff >= state.burst {
+                state.limit = (state.limit * self.backoff_percent / 100).clamp(self.min, state.max);
+                state.since_backoff = 0;
+                state.burst = state.in_flight;
+            }
+        } else {
+            state.successes += 1;
+
+            if state.successes >= state.limit {
+                state.limit = state
+                    .limit
+                    .saturating_add(self.increase)
+                    .clamp(self.min, state.max);
+                state.successes = 0;
+                self.released.notify_waiters();
+            }
+        }
+    }
+
+    fn lock(&self) -> std::sync::MutexGuard<'_, AdaptiveState> {
+        self.state.lock().unwrap_or_else(PoisonError::into_inner)
+    }
+}
+
+/// Slot of an [`AdaptiveConcurrency`], released when dropped.
+#[derive(Debug)]
+pub struct AdaptivePermit {
+    controller: AdaptiveConcurrency,
+}
+
+impl AdaptivePermit {
+    /// Record whether the task holding the permit failed, adjusting the
+    /// limit accordingly, and release the slot.
+    pub fn record(self, failed: bool) {
+        self.controller.record(failed);
+    }
+}
+
+impl Drop for AdaptivePermit {
+    fn drop(&mut self) {
+        let mut state = self.controller.lock();
+        state.in_flight = state.in_flight.saturating_sub(1);
+        drop(state);
+
+        self.controller.released.notify_waiters();
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[tokio::test]
+    async fn test_destination_limiter() {
+        let limiter = DestinationLimiter::new(2);
+
+        let first = limiter.acquire("203.0.113.1").await;
+        let second = limiter.acquire("203.0.113.1").await;
+        assert!(first.is_some() && second.is_some());
+        assert_eq!(limiter.in_flight("203.0.113.1"), 2);
+
+        // Other destinations are unaffected by a saturated one.
+        assert!(limiter.acquire("203.0.113.2").await.is_some());
+
+        let shared = limiter.clone();
+        let waiting = tokio::spawn(async move { shared.acquire("203.0.113.1").await.is_some() });
+        tokio::task::yield_now().await;
+        assert!(!waiting.is_finished());
+
+        drop(first);
+        assert!(waiting.await.unwrap());
+        assert!(DestinationLimiter::new(0).acquire("any").await.is_none());
+
+        // Idle destinations are no longer tracked.
+        assert_eq!(limiter.destinations(), 1);
+        drop(second);
+        assert_eq!(limiter.destinations(), 0);
+        assert_eq!(limiter.in_flight("203.0.113.1"), 0);
+    }
+
+    #[tokio::test]
+    async fn test_adaptive_concurrency() {
+        let controller = AdaptiveConcurrency::new(2, 8);
+        assert_eq!(controller.limit(), 8);
+
+        // A burst of concurrent failures only backs off once.
+        let permits: Vec<AdaptivePermit> =
+            futures::future::join_all((0..8).map(|_| controller.acquire())).await;
+        assert_eq!(controller.in_flight(), 8);
+
+        for permit in permits {
+            permit.record(true);
+        }
+        assert_eq!(controller.limit(), 4);
+        assert_eq!(controller.in_flight(), 0);
+
+        // Saturated at the new limit.
+        let held: Vec<AdaptivePermit> =
+            futures::future::join_all((0..4).map(|_| controller.acquire())).await;
+        let shared = controller.clone();
+        let waiting = tokio::spawn(async move { shared.acquire().await.record(false) });
+        tokio::task::yield_now().await;
+        assert!(!waiting.is_finished());
+
+        // A window of successes grows the limit, letting the waiting task in.
+        for permit in held {
+            permit.record(false);
+        }
+        waiting.await.unwrap();
+        assert_eq!(controller.limit(), 5);
+
+        // Repeated failures never go below the minimum.
+        for _ in 0..10 {
+            for _ in 0..=controller.limit() {
+                controller.acquire().await.record(true);
+            }
+        }
+        assert_eq!(controller.limit(), 2);
+        assert_eq!(AdaptiveConcurrency::new(0, 0).limit(), 1);
+
+        // A limit below the maximum stays put, one at it follows.
+        controller.set_max(16);
+        assert_eq!((controller.max(), controller.limit()), (16, 2));
+
+        let fresh = AdaptiveConcurrency::new(2, 8);
+        fresh.clone().set_max(16);
+        assert_eq!(fresh.limit(), 16);
+        fresh.set_max(0);
+        assert_eq!((fresh.max(), fresh.limit()), (2, 2));
+    }
+}
@@ -0,0 +1,313 @@
+//! The tld module exposes category metadata about top-level domains,
+//! such as whether a TLD is a country-code or a new generic TLD and how
+//! commonly it is abused, allowing scans to be scoped accordingly.
+//!
+//! The metadata is compiled into the library from
+//! `data/tld-categories.txt`. TLDs that are not listed there are
+//! categorized by their shape. Similarly, the commonly abused TLDs are
+//! compiled from `data/abused-tlds.txt`, and the neighbors of each
+//! ccTLD (see [`CcTldNeighbors`]) from `data/cctld-neighbors.txt`.
+//!
+//! Example:
+//!
+//! ```
+//! use twistrs::permutate::Domain;
+//! use twistrs::tld::{tld_info, TldCategory, TldSet};
+//!
+//! let info = tld_info("co.uk").unwrap();
+//! assert_eq!(info.category, TldCategory::CountryCode);
+//!
+//! let domain = Domain::new("example.com").unwrap();
+//! let permutations: Vec<_> = domain
+//!     .tld_set(&TldSet::Categories(vec![TldCategory::Generic]))
+//!     .collect();
+//! ```
+
+// The names of the TLD types read better with the prefix, even within
+// this module.
+#![allow(clippy::module_name_repetitions)]
+
+use std::collections::BTreeMap;
+
+use serde::{Deserialize, Serialize};
+
+use crate::psl;
+use crate::tlds::TLDS;
+
+include!(concat!(env!("OUT_DIR"), "/tld_categories.rs"));
+include!(concat!(env!("OUT_DIR"), "/abused_tlds.rs"));
+include!(concat!(env!("OUT_DIR"), "/cctld_neighbors.rs"));
+
+/// Category of a top-level domain.
+#[derive(Clone, Copy, Debug, Serialize, Deserialize, Hash, Eq, PartialEq, Ord, PartialOrd)]
+#[cfg_attr(feature = "arbitrary", derive(arbitrary::Arbitrary))]
+pub enum TldCategory {
+    /// Legacy generic TLDs (e.g. `.com`, `.net`).
+    Generic,
+
+    /// Country-code TLDs (e.g. `.uk`, `.рф`).
+    CountryCode,
+
+    /// Generic TLDs delegated since 2012 (e.g. `.xyz`, `.shop`).
+    NewGeneric,
+
+    /// Sponsored TLDs, restricted to a community (e.g. `.edu`, `.gov`).
+    Sponsored,
+
+    /// Infrastructure TLDs (i.e. `.arpa`).
+    Infrastructure,
+}
+
+/// How commonly a TLD is abused for phishing and similar attacks.
+#[derive(Clone, Copy, Debug, Serialize, Deserialize, Hash, Eq, PartialEq, Ord, PartialOrd)]
+pub enum RiskTier {
+    Low,
+    Medium,
+    High,
+}
+
+/// Metadata about a public suffix, describing the TLD it falls under.
+#[derive(Clone, Debug, Serialize, Deserialize, Hash, Eq, PartialEq)]
+pub struct TldInfo {
+    /// The public suffix (e.g. `co.uk`).
+    pub suffix: String,
+
+    /// The top-level domain of the suffix (e.g. `uk`).
+    pub tld: String,
+
+    pub category: TldCategory,
+    pub risk: RiskTier,
+}
+
+/// Lookup the metadata of the public suffix `name` (e.g. `com` or `co.uk`),
+/// in its Unicode or punycode form (e.g. `рф` or `xn--p1ai`), returning
+/// `None` if the suffix is unknown.
+pub fn tld_info(name: &str) -> Option<TldInfo> {
+    let suffix = name.trim_start_matches('.').to_lowercase();
+
+    // Suffix lists hold IDN suffixes in their Unicode form.
+    let unicode = if suffix.contains("xn--") {
+        idna::domain_to_unicode(&suffix).0
+    } else {
+        suffix.clone()
+    };
+
+    let known = match psl::active() {
+        Some(list) => list.suffixes().binary_search(&unicode).is_ok(),
+        None => TLDS.binary_search(&unicode.as_str()).is_ok(),
+    };
+
+    if !known {
+        return None;
+    }
+
+    let tld = suffix.rsplit('.').next().unwrap_or_default().to_string();
+
+    let (category, risk) = TLD_CATEGORIES.get(tld.as_str()).copied().unwrap_or(
+        if tld.len() == 2 && tld.chars().all(|c| c.is_ascii_alphabetic()) {
+            (TldCategory::CountryCode, RiskTier::Low)
+        } else {
+            (TldCategory::NewGeneric, RiskTier::Medium)
+        },
+    );
+
+    Some(TldInfo {
+        suffix,
+        tld,
+        category,
+        risk,
+    })
+}
+
+/// Whether the public suffix `name` falls under a commonly abused TLD,
+/// regardless of whether the suffix itself is known.
+pub fn is_abused(name: &str) -> bool {
+    let suffix = name.trim_start_matches('.').to_lowercase();
+    let tld = suffix.rsplit('.').next().unwrap_or_default();
+
+    ABUSED_TLDS.contains(tld)
+}
+
+/// Country-code TLDs mapped to the suffixes of geographically or
+/// linguistically adjacent countries (e.g. `de` to `at` and `ch`), used by
+/// [`TldSet::Neighbors`]. Defaults to the built-in mapping.
+#[derive(Clone, Debug, Serialize, Deserialize, Hash, Eq, PartialEq)]
+#[serde(transparent)]
+pub struct CcTldNeighbors {
+    pub neighbors: BTreeMap<String, Vec<String>>,
+}
+
+impl Default for CcTldNeighbors {
+    fn default() -> CcTldNeighbors {
+        CcTldNeighbors::builtin()
+    }
+}
+
+impl CcTldNeighbors {
+    /// The mapping shipped with the library.
+    pub fn builtin() -> CcTldNeighbors {
+        CcTldNeighbors {
+            neighbors: CCTLD_NEIGHBORS
+                .entries()
+                .map(|(cctld, neighbors)| {
+                    let suffixes = neighbors.iter().map(|neighbor| (*neighbor).to_string());
+                    ((*cctld).to_string(), suffixes.collect())
+                })
+                .collect(),
+        }
+    }
+
+    /// A mapping without any entries, e.g. to replace the built-in one.
+    pub fn empty() -> CcTldNeighbors {
+        CcTldNeighbors {
+            neighbors: BTreeMap::new(),
+        }
+    }
+
+    /// Add `neighbors` as neighbors of `cctld`, keeping the existing ones.
+    pub fn insert(&mut self, cctld: &str, neighbors: &[&str]) {
+        let entry = self.neighbors.entry(cctld.to_lowercase()).or_default();
+
+        for neighbor in neighbors {
+            let suffix = neighbor.trim_start_matches('.').to_lowercase();
+
+            if !entry.contains(&suffix) {
+                entry.push(suffix);
+            }
+        }
+    }
+
+    /// The neighbors of the ccTLD that the public suffix `suffix` falls
+    /// under (e.g. `uk` for `co.uk`), if any.
+    pub fn get(&self, suffix: &str) -> &[String] {
+        let tld = suffix.trim_start_matches('.').rsplit('.').next();
+
+        tld.and_then(|cctld| self.neighbors.get(&cctld.to_lowercase()))
+            .map_or(&[], Vec::as_slice)
+    }
+}
+
+/// Set of public suffixes that the TLD permutation swaps in.
+#[derive(Clone, Debug, Default, Serialize, Deserialize, Hash, Eq, PartialEq)]
+pub enum TldSet {
+    /// Every known public suffix.
+    #[default]
+    All,
+
+    /// Suffixes under a TLD of one of the given categories.
+    Categories(Vec<TldCategory>),
+
+    /// Suffixes under a commonly abused TLD (see [`is_abused`]).
+    Abused,
+
+    /// Suffixes neighboring the ccTLD of the base domain, a far smaller
+    /// sweep targeting the same regional audience. Nothing is swapped in
+    /// for domains under a TLD without neighbors (e.g. `.com`).
+    Neighbors(CcTldNeighbors),
+}
+
+impl TldSet {
+    /// Whether the public suffix `suffix` belongs to the set, regardless
+    /// of the base domain, i.e. whether it neighbors any ccTLD for
+    /// [`TldSet::Neighbors`].
+    pub fn contains(&self, suffix: &str) -> bool {
+        match self {
+            TldSet::All => true,
+            TldSet::Abused => is_abused(suffix),
+            TldSet::Categories(categories) => {
+                tld_info(suffix).is_some_and(|info| categories.contains(&info.category))
+            }
+            TldSet::Neighbors(table) => table
+                .neighbors
+                .values()
+                .any(|neighbors| neighbors.iter().any(|neighbor| neighbor == suffix)),
+        }
+    }
+
+    /// Whether the public suffix `suffix` is swapped in for a base domain
+    /// under the public suffix `base`.
+    pub fn selects(&self, base: &str, suffix: &str) -> bool {
+        match self {
+            TldSet::Neighbors(table) => table.get(base).iter().any(|neighbor| neighbor == suffix),
+            _ => self.contains(suffix),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_tld_info() {
+        let com = tld_info("com").unwrap();
+        assert_eq!(com.category, TldCategory::Generic);
+        assert_eq!(com.risk, RiskTier::Low);
+
+        let co_uk = tld_info(".co.uk").unwrap();
+        assert_eq!(co_uk.tld, "uk");
+        assert_eq!(co_uk.category, TldCategory::CountryCode);
+
+        assert_eq!(tld_info("tk").unwrap().risk, RiskTier::High);
+        assert_eq!(tld_info("рф").unwrap().category, TldCategory::CountryCode);
+        assert_eq!(
+            tld_info("xn--p1ai").unwrap(),
+            tld_info("рф")
+                .map(|info| TldInfo {
+                    suffix: String::from("xn--p1ai"),
+                    tld: String::from("xn--p1ai"),
+                    ..info
+                })
+                .unwrap()
+        );
+        assert_eq!(tld_info("ελ").unwrap().category, TldCategory::CountryCode);
+        assert_eq!(
+            tld_info("xn--wgbh1c").unwrap().category,
+            TldCategory::CountryCode
+        );
+        assert_eq!(
+            tld_info("онлайн").unwrap().category,
+            TldCategory::NewGeneric
+        );
+        assert_eq!(
+            tld_info("technology").unwrap().category,
+            TldCategory::NewGeneric
+        );
+        assert_eq!(tld_info("gov").unwrap().category, TldCategory::Sponsored);
+
+        assert!(tld_info("notatld").is_none());
+    }
+
+    #[test]
+    fn test_cctld_neighbors() {
+        let neighbors = CcTldNeighbors::builtin();
+
+        assert!(neighbors.get("de").contains(&String::from("at")));
+        assert_eq!(neighbors.get("co.uk"), ["ie"]);
+        assert!(neighbors.get("com").is_empty());
+
+        let set = TldSet::Neighbors(neighbors);
+        assert!(set.selects("de", "ch"));
+        assert!(!set.selects("de", "com"));
+        assert!(!set.selects("com", "ch"));
+        assert!(set.contains("ch"));
+
+        let mut custom = CcTldNeighbors::empty();
+        custom.insert("UK", &[".co.ie", "ie", "ie"]);
+        assert_eq!(custom.get("co.uk"), ["co.ie", "ie"]);
+    }
+
+    #[test]
+    fn test_tld_set_contains() {
+        let set = TldSet::Categories(vec![TldCategory::Sponsored, TldCategory::Generic]);
+
+        assert!(set.contains("edu"));
+        assert!(set.contains("org"));
+        assert!(!set.contains("de"));
+        assert!(TldSet::All.contains("de"));
+
+        assert!(TldSet::Abused.contains("xyz"));
+        assert!(TldSet::Abused.contains("TK"));
+        assert!(!TldSet::Abused.contains("com"));
+    }
+}
@@ -0,0 +1,284 @@
+//! The tls module exposes the client settings of the HTTPS enrichment
+//! path, see [`DomainMetadata::https_banner`].
+//!
+//! Phishing hosts frequently present broken, self-signed or mismatched
+//! certificates, so on top of a custom root store and control over the
+//! server name sent through SNI, [`TlsOptions`] can accept invalid
+//! certificates while recording why they failed validation.
+//!
+//! Example:
+//!
+//! ```no_run
+//! use twistrs::enrich::DomainMetadata;
+//! use twistrs::tls::{Sni, TlsOptions};
+//!
+//! #[tokio::main]
+//! async fn main() {
+//!     let options = TlsOptions {
+//!         accept_invalid: true,
+//!         sni: Sni::Name(String::from("example.com")),
+//!         ..TlsOptions::default()
+//!     };
+//!
+//!     let domain_metadata = DomainMetadata::new(String::from("examplle.com"));
+//!     let tls = domain_metadata.https_banner(&options).await.unwrap().tls;
+//!
+//!     println!("{:?}", tls.and_then(|tls| tls.validation_error));
+//! }
+//! ```
+//!
+//! ### Features
+//!
+//! This module requires the `tls` feature toggled.
+//!
+//! [`DomainMetadata::https_banner`]: crate::enrich::DomainMetadata::https_banner
+
+// The names of the TLS types read better with the prefix.
+#![allow(clippy::module_name_repetitions)]
+
+use std::sync::{Arc, Mutex, PoisonError};
+use std::time::{Duration, SystemTime};
+
+use rustls::client::{ServerCertVerified, ServerCertVerifier, WebPkiVerifier};
+use rustls::{Certificate, ClientConfig, OwnedTrustAnchor, RootCertStore, ServerName};
+use serde::{Deserialize, Serialize};
+
+use crate::error::Error;
+
+#[derive(thiserror::Error, Debug)]
+pub enum TlsError {
+    #[error("invalid root certificate (error: {error})")]
+    RootError { error: anyhow::Error },
+
+    #[error("invalid server name (name: {name})")]
+    ServerNameError { name: String },
+}
+
+/// Server name sent through SNI.
+#[derive(Clone, Debug, Default, Serialize, Deserialize, Hash, Eq, PartialEq)]
+pub enum Sni {
+    /// The domain being enriched.
+    #[default]
+    Domain,
+
+    /// A fixed name, e.g. the domain being impersonated.
+    Name(String),
+
+    /// No SNI extension at all.
+    Disabled,
+}
+
+/// TLS client settings of the HTTPS enrichment path.
+#[derive(Clone, Debug, Serialize, Deserialize, Hash, Eq, PartialEq)]
+#[serde(default, deny_unknown_fields)]
+pub struct TlsOptions {
+    /// DER-encoded root certificates trusted on top of the builtin roots.
+    pub roots: Vec<Vec<u8>>,
+
+    /// Whether to trust the builtin Mozilla roots.
+    pub builtin_roots: bool,
+
+    /// Whether to complete the handshake with certificates that fail
+    /// validation, recording the validation error instead.
+    pub accept_invalid: bool,
+
+    pub sni: Sni,
+
+    /// Time allowed for the whole exchange, from connecting up to the
+    /// response headers, so that peers stalling the handshake or the
+    /// request do not hang the lookup.
+    pub timeout: Duration,
+}
+
+impl Default for TlsOptions {
+    fn default() -> TlsOptions {
+        TlsOptions {
+            roots: vec![],
+            builtin_roots: true,
+            accept_invalid: false,
+            sni: Sni::default(),
+            timeout: Duration::from_secs(10),
+        }
+    }
+}
+
+impl TlsOptions {
+    /// Server name `fqdn` is connected to as, which certificates are
+    /// validated against.
+    pub(crate) fn server_name(&self, fqdn: &str) -> Result<ServerName, Error> {
+        let name = match &self.sni {
+            Sni::Name(name) => name.as_str(),
+            Sni::Domain | Sni::Disabled => fqdn,
+        };
+
+        Ok(
+            ServerName::try_from(name).map_err(|_| TlsError::ServerNameError {
+                name: name.to_string(),
+            })?,
+        )
+    }
+
+    /// Client configuration along with the slot the validation error of
+    /// the handshake is recorded into.
+    pub(crate) fn client_config(&self) -> Result<(ClientConfig, ValidationSlot), Error> {
+        let mut roots = RootCertStore::empty();
+
+        if self.builtin_roots {
+            roots.add_trust_anchors(webpki_roots::TLS_SERVER_ROOTS.iter().map(|anchor| {
+                OwnedTrustAnchor::from_subject_spki_name_constraints(
+                    anchor.subject,
+                    anchor.spki,
+                    anchor.name_constraints,
+                )
+            }));
+        }
+
+        for root in &self.roots {
+            roots
+                .add(&Certificate(root.clone()))
+                .map_err(|e| TlsError::RootError {
+                    error: anyhow::Error::msg(e),
+                })?;
+        }
+
+        let slot = ValidationSlot::default();
+        let verifier = RecordingVerifier {
+            inner: WebPkiVerifier::new(roots, None),
+            accept_invalid: self.accept_invalid,
+            slot: slot.clone(),
+        };
+
+        let mut config = ClientConfig::builder()
+            .with_safe_defaults()
+            .with_custom_certificate_verifier(Arc::new(verifier))
+            .with_no_client_auth();
+        config.enable_sni = self.sni != Sni::Disabled;
+
+        Ok((config, slot))
+    }
+}
+
+/// DNS names in the subject alternative names of `certificate`, lowercase
+/// and deduplicated. Certificates that cannot be parsed have none.
+pub(crate) fn subject_alt_names(certificate: &Certificate) -> Vec<String> {
+    let Ok(parsed) = webpki::EndEntityCert::try_from(certificate.0.as_slice()) else {
+        return vec![];
+    };
+
+    let mut names: Vec<String> = parsed
+        .dns_names()
+        .map(|names| {
+            names
+                .map(|name| <&str>::from(name).to_lowercase())
+                .collect()
+        })
+        .unwrap_or_default();
+
+    names.sort();
+    names.dedup();
+    names
+}
+
+/// Validation error recorded during a handshake, if any.
+#[derive(Clone, Debug, Default)]
+pub(crate) struct ValidationSlot(Arc<Mutex<Option<String>>>);
+
+impl ValidationSlot {
+    pub(crate) fn take(&self) -> Option<String> {
+        self.0.lock().unwrap_or_else(PoisonError::into_inner).take()
+    }
+}
+
+/// Certificate verifier deferring to webpki, optionally accepting invalid
+/// certificates while recording why they failed validation.
+struct RecordingVerifier {
+    inner: WebPkiVerifier,
+    accept_invalid: bool,
+    slot: ValidationSlot,
+}
+
+impl ServerCertVerifier for RecordingVerifier {
+    fn verify_server_cert(
+        &self,
+        end_entity: &Certificate,
+        intermediates: &[Certificate],
+        server_name: &ServerName,
+        scts: &mut dyn Iterator<Item = &[u8]>,
+        ocsp_response: &[u8],
+        now: SystemTime,
+    ) -> Result<ServerCertVerified, rustls::Error> {
+        match self.inner.verify_server_cert(
+            end_entity,
+            intermediates,
+            server_name,
+            scts,
+            ocsp_response,
+            now,
+        ) {
+            Ok(verified) => Ok(verified),
+            Err(e) if self.accept_invalid => {
+                *self.slot.0.lock().unwrap_or_else(PoisonError::into_inner) = Some(e.to_string());
+                Ok(ServerCertVerified::assertion())
+            }
+            Err(e) => Err(e),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn verify(accept_invalid: bool) -> (Result<ServerCertVerified, rustls::Error>, Option<String>) {
+        let slot = ValidationSlot::default();
+        let verifier = RecordingVerifier {
+            inner: WebPkiVerifier::new(RootCertStore::empty(), None),
+            accept_invalid,
+            slot: slot.clone(),
+        };
+
+        let result = verifier.verify_server_cert(
+            &Certificate(b"not a certificate".to_vec()),
+            &[],
+            &ServerName::try_from("example.com").unwrap(),
+            &mut std::iter::empty(),
+            &[],
+            SystemTime::now(),
+        );
+
+        (result, slot.take())
+    }
+
+    #[test]
+    fn test_recording_verifier() {
+        let (accepted, recorded) = verify(true);
+        assert!(accepted.is_ok());
+        assert!(recorded.is_some());
+
+        let (rejected, unrecorded) = verify(false);
+        assert!(rejected.is_err());
+        assert!(unrecorded.is_none());
+    }
+
+    #[test]
+    fn test_tls_options() {
+        let named = TlsOptions {
+            sni: Sni::Name(String::from("example.com")),
+            ..TlsOptions::default()
+        };
+
+        assert_eq!(
+            named.server_name("examplle.com").unwrap(),
+            ServerName::try_from("example.com").unwrap()
+        );
+        assert!(named.client_config().unwrap().0.enable_sni);
+
+        let invalid = TlsOptions {
+            sni: Sni::Disabled,
+            roots: vec![b"not a certificate".to_vec()],
+            ..TlsOptions::default()
+        };
+        assert!(invalid.client_config().is_err());
+        assert!(TlsOptions::default().server_name("not a name").is_err());
+    }
+}
@@ -0,0 +1,234 @@
+//! The token module exposes the segmentation of domain labels into
+//! tokens, e.g. `unitedhealthgroup` into `united`, `health` and `group`.
+//!
+//! Compound brands are commonly squatted token by token (e.g.
+//! `united-healthgroup`), which character-level permutations miss. See
+//! [`Domain::tokenize`] and the token-aware permutations built on top of
+//! it.
+//!
+//! Example:
+//!
+//! ```
+//! use twistrs::permutate::Domain;
+//!
+//! let domain = Domain::new("unitedhealthgroup.com").unwrap();
+//! let tokens: Vec<String> = domain.tokenize().into_iter().map(|token| token.text).collect();
+//!
+//! assert_eq!(tokens, vec!["united", "health", "group"]);
+//! ```
+//!
+//! [`Domain::tokenize`]: crate::permutate::Domain::tokenize
+
+// `TokenKind` reads better than `token::Kind`.
+#![allow(clippy::module_name_repetitions)]
+
+use serde::{Deserialize, Serialize};
+
+use crate::permutate::KeywordSet;
+
+// Word list used for segmentation, generated during compile time.
+include!(concat!(env!("OUT_DIR"), "/words.rs"));
+
+/// Shortest word used to segment labels that cannot be fully covered by
+/// words, so that brands (e.g. `google`) are not split on short words
+/// they happen to contain (e.g. `go`).
+const MIN_PARTIAL_WORD: usize = 3;
+
+/// Kind of a token.
+#[derive(Clone, Copy, Debug, Serialize, Deserialize, Hash, Eq, PartialEq, Ord, PartialOrd)]
+pub enum TokenKind {
+    /// A dictionary word or keyword.
+    Word,
+
+    /// A run of digits.
+    Number,
+
+    /// A run of letters that is not a known word, e.g. a brand name.
+    Unknown,
+}
+
+/// Token of a domain label.
+#[derive(Clone, Debug, Serialize, Deserialize, Hash, Eq, PartialEq, Ord, PartialOrd)]
+pub struct Token {
+    pub text: String,
+    pub kind: TokenKind,
+
+    /// Byte offset of the token within the label.
+    pub start: usize,
+}
+
+impl Token {
+    /// Byte offset right after the token within the label.
+    pub fn end(&self) -> usize {
+        self.start + self.text.len()
+    }
+}
+
+/// Whether `word` is a dictionary word or a keyword, case-insensitively.
+pub fn is_word(word: &str) -> bool {
+    let lowercase = word.to_lowercase();
+
+    WORDS.contains(lowercase.as_str())
+        || KeywordSet::ALL
+            .iter()
+            .any(|set| set.keywords().contains(&lowercase.as_str()))
+}
+
+/// Split `label` into tokens. Hyphens and dots separate tokens without
+/// being part of any, digits form tokens of their own and runs of
+/// letters are segmented into words.
+///
+/// Runs that can be entirely covered by words are split into as few words
+/// as possible. Otherwise, words of at least three letters are split out
+/// and the remainder is kept as unknown tokens.
+pub fn tokenize(label: &str) -> Vec<Token> {
+    let mut tokens = vec![];
+    let mut chars = label.char_indices().peekable();
+
+    while let Some((start, c)) = chars.next() {
+        if c == '-' || c == '.' {
+            continue;
+        }
+
+        let digits = c.is_ascii_digit();
+        let mut end = start + c.len_utf8();
+
+        while let Some(&(offset, next)) = chars.peek() {
+            if next == '-' || next == '.' || next.is_ascii_digit() != digits {
+                break;
+            }
+
+            end = offset + next.len_utf8();
+            chars.next();
+        }
+
+        if digits {
+            tokens.push(Token {
+                text: label[start..end].to_string(),
+                kind: TokenKind::Number,
+                start,
+            });
+        } else {
+            tokens.extend(segment(&label[start..end], start));
+        }
+    }
+
+    tokens
+}
+
+/// Segment a run of letters starting at byte offset `offset` of a label.
+fn segment(run: &str, offset: usize) -> Vec<Token> {
+    let full = split(run, 1).filter(|(unknown, _)| *unknown == 0);
+    let (_, bounds) = full
+        .unwrap_or_else(|| split(run, MIN_PARTIAL_WORD).unwrap_or((run.chars().count(), vec![])));
+
+    let mut tokens: Vec<Token> = vec![];
+
+    for (start, end, word) in bounds {
+        // Merge consecutive unknown characters into a single token.
+        if let Some(last) = tokens.last_mut() {
+            if !word && last.kind == TokenKind::Unknown {
+                last.text.push_str(&run[start..end]);
+                continue;
+            }
+        }
+
+        tokens.push(Token {
+            text: run[start..end].to_string(),
+            kind: if word {
+                TokenKind::Word
+            } else {
+                TokenKind::Unknown
+            },
+            start: offset + start,
+        });
+    }
+
+    tokens
+}
+
+/// Split `run` into words of at least `min_word` characters and single
+/// unknown characters, minimizing the unknown characters first and the
+/// number of tokens second. Returns the number of unknown characters
+/// along with the byte bounds of each token and whether it is a word.
+#[allow(clippy::type_complexity)]
+fn split(run: &str, min_word: usize) -> Option<(usize, Vec<(usize, usize, bool)>)> {
+    let offsets: Vec<usize> = run
+        .char_indices()
+        .map(|(offset, _)| offset)
+        .chain([run.len()])
+        .collect();
+    let n = offsets.len() - 1;
+
+    // Best (unknown characters, tokens, previous position, word) ending
+    // at each character position.
+    let mut best: Vec<Option<(usize, usize, usize, bool)>> = vec![None; n + 1];
+    best[0] = Some((0, 0, 0, false));
+
+    for i in 1..=n {
+        let mut candidates = vec![];
+
+        if let Some((unknown, count, _, _)) = best[i - 1] {
+            candidates.push((unknown + 1, count + 1, i - 1, false));
+        }
+
+        for j in 0..i.saturating_sub(min_word - 1) {
+            if let Some((unknown, count, _, _)) = best[j] {
+                if is_word(&run[offsets[j]..offsets[i]]) {
+                    candidates.push((unknown, count + 1, j, true));
+                }
+            }
+        }
+
+        best[i] = candidates
+            .into_iter()
+            .min_by_key(|(unknown, count, _, _)| (*unknown, *count));
+    }
+
+    let (unknown, _, _, _) = best[n]?;
+    let mut bounds = vec![];
+    let mut i = n;
+
+    while i > 0 {
+        let (_, _, previous, word) = best[i]?;
+        bounds.push((offsets[previous], offsets[i], word));
+        i = previous;
+    }
+
+    bounds.reverse();
+    Some((unknown, bounds))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn texts(label: &str) -> Vec<String> {
+        tokenize(label)
+            .into_iter()
+            .map(|token| token.text)
+            .collect()
+    }
+
+    #[test]
+    fn test_tokenize() {
+        assert_eq!(
+            texts("unitedhealthgroup"),
+            vec!["united", "health", "group"]
+        );
+        assert_eq!(texts("paypal"), vec!["pay", "pal"]);
+        assert_eq!(texts("google"), vec!["google"]);
+        assert_eq!(texts("acmebank"), vec!["acme", "bank"]);
+        assert_eq!(texts("bank-of-acme24"), vec!["bank", "of", "acme", "24"]);
+
+        let tokens = tokenize("my-acmebank");
+        assert_eq!(
+            tokens.iter().map(|token| token.kind).collect::<Vec<_>>(),
+            vec![TokenKind::Word, TokenKind::Unknown, TokenKind::Word]
+        );
+        assert_eq!((tokens[1].start, tokens[1].end()), (3, 7));
+
+        assert!(tokenize("").is_empty());
+        assert_eq!(texts("bücher"), vec!["bücher"]);
+    }
+}
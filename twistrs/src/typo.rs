@@ -0,0 +1,273 @@
+//! The typo module exposes a probabilistic typo model, which attaches a
+//! likelihood to each candidate it generates, so that clients can keep
+//! only the most probable typos of a domain (e.g. the top 500) rather
+//! than every permutation the engine generates.
+//!
+//! The model only covers single typos, whose number grows linearly with
+//! the length of the domain. Every candidate is enumerated to normalise
+//! the likelihoods, [`TypoModel::top`] only saves sorting all of them.
+//!
+//! The likelihood of a typo is the product of the weight of its kind
+//! (e.g. replacing a character is more common than transposing two) and
+//! the weight of the character mistyped, spread evenly across the keys
+//! adjacent to it on a QWERTY keyboard. Likelihoods are normalised so that
+//! they sum up to `1.0` across all candidates of a domain.
+//!
+//! Example:
+//!
+//! ```
+//! use twistrs::permutate::Domain;
+//! use twistrs::typo::TypoModel;
+//!
+//! let domain = Domain::new("example.com").unwrap();
+//! let model = TypoModel::default();
+//!
+//! for typo in model.top(&domain, 10) {
+//!     println!("{} ({:.4})", typo.permutation.domain.fqdn, typo.likelihood);
+//! }
+//! ```
+
+// Likelihoods are inherently fractional, unlike the rest of the crate, and
+// the model reads better with the prefix.
+#![allow(clippy::float_arithmetic, clippy::module_name_repetitions)]
+
+use std::cmp::Ordering;
+use std::collections::BTreeMap;
+
+use serde::{Deserialize, Serialize};
+
+use crate::constants::QWERTY_KEYBOARD_LAYOUT;
+use crate::permutate::{Domain, Permutation, PermutationKind};
+use crate::scoring::ratio;
+
+/// Weight given to characters without an explicit weight.
+const DEFAULT_CHAR_WEIGHT: f64 = 1.0;
+
+/// Typo model, weighting candidates by how likely they are to be typed by
+/// mistake.
+#[derive(Clone, Debug, Serialize, Deserialize, PartialEq)]
+#[serde(default, deny_unknown_fields)]
+pub struct TypoModel {
+    /// Relative likelihood of each kind of typo. Only the replacement,
+    /// insertion, omission, transposition and repetition kinds are
+    /// supported, and kinds that are not listed are not generated.
+    pub kind_weights: BTreeMap<PermutationKind, f64>,
+
+    /// Relative likelihood of each character being mistyped. Characters
+    /// that are not listed are weighted `1.0`.
+    pub char_weights: BTreeMap<char, f64>,
+}
+
+impl Default for TypoModel {
+    fn default() -> TypoModel {
+        // Roughly in line with the distribution of typing errors observed
+        // in studies of mistyped domain names.
+        TypoModel {
+            kind_weights: BTreeMap::from([
+                (PermutationKind::Replacement, 0.35),
+                (PermutationKind::Omission, 0.25),
+                (PermutationKind::Insertion, 0.15),
+                (PermutationKind::Transposition, 0.15),
+                (PermutationKind::Repetition, 0.10),
+            ]),
+            char_weights: BTreeMap::new(),
+        }
+    }
+}
+
+/// Permutation paired with the likelihood of it being typed by mistake.
+#[derive(Clone, Debug, Serialize, Deserialize, PartialEq)]
+pub struct Typo {
+    pub permutation: Permutation,
+
+    /// Likelihood of the typo, between `0.0` and `1.0`.
+    pub likelihood: f64,
+}
+
+impl TypoModel {
+    /// Create a model with the default weights.
+    pub fn new() -> TypoModel {
+        TypoModel::default()
+    }
+
+    /// Generate every typo of `domain`, most probable first.
+    pub fn generate(&self, domain: &Domain) -> Vec<Typo> {
+        let mut typos = self.candidates(domain);
+        typos.sort_by(by_likelihood);
+        typos
+    }
+
+    /// Generate the `n` most probable typos of `domain`, most probable
+    /// first. Every typo is still enumerated, see the module docs, however
+    /// only the `n` kept are sorted.
+    pub fn top(&self, domain: &Domain, n: usize) -> Vec<Typo> {
+        let mut typos = self.candidates(domain);
+
+        if n < typos.len() {
+            typos.select_nth_unstable_by(n, by_likelihood);
+            typos.truncate(n);
+        }
+
+        typos.sort_by(by_likelihood);
+        typos
+    }
+
+    /// Every typo of `domain` along with its normalised likelihood, in no
+    /// particular order.
+    fn candidates(&self, domain: &Domain) -> Vec<Typo> {
+        let chars: Vec<char> = domain.domain.chars().collect();
+        let mut candidates: Vec<(String, PermutationKind, f64)> = vec![];
+
+        for (i, c) in chars.iter().enumerate() {
+            let weight = self.char_weight(*c);
+            let neighbours: Vec<char> = QWERTY_KEYBOARD_LAYOUT
+                .get(c)
+                .map(|keys| keys.chars().collect())
+                .unwrap_or_default();
+            let (before, after) = (&chars[..i], &chars[i + 1..]);
+
+            for neighbour in &neighbours {
+                let spread = weight * ratio(1, neighbours.len());
+
+                candidates.push((
+                    join(&[before, &[*neighbour], after]),
+                    PermutationKind::Replacement,
+                    self.kind_weight(PermutationKind::Replacement) * spread,
+                ));
+
+                // The adjacent key is equally likely to be hit before or
+                // after the intended one.
+                for inserted in [
+                    join(&[before, &[*neighbour, *c], after]),
+                    join(&[before, &[*c, *neighbour], after]),
+                ] {
+                    candidates.push((
+                        inserted,
+                        PermutationKind::Insertion,
+                        self.kind_weight(PermutationKind::Insertion) * spread / 2.0,
+                    ));
+                }
+            }
+
+            candidates.push((
+                join(&[before, after]),
+                PermutationKind::Omission,
+                self.kind_weight(PermutationKind::Omission) * weight,
+            ));
+            candidates.push((
+                join(&[before, &[*c, *c], after]),
+                PermutationKind::Repetition,
+                self.kind_weight(PermutationKind::Repetition) * weight,
+            ));
+
+            if let Some(next) = after.first().filter(|next| *next != c) {
+                candidates.push((
+                    join(&[before, &[*next, *c], &after[1..]]),
+                    PermutationKind::Transposition,
+                    self.kind_weight(PermutationKind::Transposition)
+                        * (weight + self.char_weight(*next))
+                        / 2.0,
+                ));
+            }
+        }
+
+        // The same candidate can be reached through several typos (e.g.
+        // omitting either `l` of `hello`), in which case they add up.
+        let mut merged: BTreeMap<String, (PermutationKind, f64)> = BTreeMap::new();
+        for (name, kind, likelihood) in candidates {
+            if likelihood > 0.0 && name != domain.domain {
+                let entry = merged.entry(name).or_insert((kind, 0.0));
+                entry.1 += likelihood;
+            }
+        }
+
+        let mut typos: Vec<Typo> = merged
+            .into_iter()
+            .filter_map(|(name, (kind, likelihood))| {
+                let fqdn = format!("{name}.{}", domain.tld);
+
+                Domain::new(&fqdn).ok().map(|permutation| Typo {
+                    permutation: Permutation {
+                        domain: permutation,
+                        kind,
+                        substitutions: vec![],
+                    },
+                    likelihood,
+                })
+            })
+            .collect();
+
+        let total: f64 = typos.iter().map(|typo| typo.likelihood).sum();
+        for typo in &mut typos {
+            typo.likelihood /= total;
+        }
+
+        typos
+    }
+
+    fn kind_weight(&self, kind: PermutationKind) -> f64 {
+        self.kind_weights.get(&kind).copied().unwrap_or_default()
+    }
+
+    fn char_weight(&self, c: char) -> f64 {
+        self.char_weights
+            .get(&c)
+            .copied()
+            .unwrap_or(DEFAULT_CHAR_WEIGHT)
+    }
+}
+
+/// Most probable first, ties broken by permutation so that the order is
+/// deterministic.
+fn by_likelihood(a: &Typo, b: &Typo) -> Ordering {
+    b.likelihood
+        .total_cmp(&a.likelihood)
+        .then_with(|| a.permutation.cmp(&b.permutation))
+}
+
+fn join(parts: &[&[char]]) -> String {
+    parts.iter().flat_map(|part| part.iter()).collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_typo_likelihoods() {
+        let domain = Domain::new("example.com").unwrap();
+        let typos = TypoModel::new().generate(&domain);
+
+        let total: f64 = typos.iter().map(|typo| typo.likelihood).sum();
+        assert!((total - 1.0).abs() < 1e-9);
+        assert!(typos
+            .windows(2)
+            .all(|pair| pair[0].likelihood >= pair[1].likelihood));
+        assert!(typos
+            .iter()
+            .any(|typo| typo.permutation.domain.fqdn == "exmaple.com"));
+        assert!(typos
+            .iter()
+            .all(|typo| typo.permutation.domain.fqdn != "example.com"));
+
+        for n in [0, 1, 5, typos.len(), typos.len() + 1] {
+            let top = TypoModel::new().top(&domain, n);
+            assert_eq!(top, typos[..n.min(typos.len())]);
+        }
+    }
+
+    #[test]
+    fn test_typo_weights_are_overridable() {
+        let domain = Domain::new("example.com").unwrap();
+
+        let mut model = TypoModel::new();
+        model.kind_weights = BTreeMap::from([(PermutationKind::Omission, 1.0)]);
+        model.char_weights.insert('x', 10.0);
+
+        let typos = model.generate(&domain);
+        assert!(typos
+            .iter()
+            .all(|typo| typo.permutation.kind == PermutationKind::Omission));
+        assert_eq!(typos[0].permutation.domain.fqdn, "eample.com");
+    }
+}
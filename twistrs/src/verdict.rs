@@ -0,0 +1,339 @@
+//! The verdict module exposes [`assess`], a single call deciding whether a
+//! candidate domain (e.g. the sender of an inbound email, or the host of
+//! a reported URL) squats a base domain.
+//!
+//! The resulting [`Verdict`] wraps the permutation kinds the candidate
+//! matches, its similarity scores and the signals backing them into a
+//! single confidence, so that triage services do not need to stitch the
+//! permutation, scoring and enrichment modules together themselves.
+//!
+//! Example:
+//!
+//! ```
+//! use twistrs::permutate::{Domain, PermutationKind};
+//! use twistrs::verdict::assess;
+//!
+//! let base = Domain::new("example.com").unwrap();
+//!
+//! let verdict = assess(&base, &Domain::new("exmaple.com").unwrap());
+//! assert!(verdict.kinds.contains(&PermutationKind::Transposition));
+//! assert!(verdict.confidence > 0.5);
+//!
+//! let verdict = assess(&base, &Domain::new("unrelated.org").unwrap());
+//! assert!(verdict.kinds.is_empty());
+//! assert_eq!(verdict.confidence, 0.0);
+//! ```
+//!
+//! With the `enrich` feature toggled, [`assess_enriched`] additionally
+//! enriches the candidate through a [`Pipeline`], weighing in whether it
+//! resolves and serves content.
+//!
+//! [`Pipeline`]: crate::pipeline::Pipeline
+
+// Scores are inherently fractional, unlike the rest of the crate.
+#![allow(clippy::float_arithmetic)]
+
+#[cfg(feature = "enrich")]
+use futures::stream::{self, StreamExt};
+use serde::{Deserialize, Serialize};
+
+#[cfg(feature = "enrich")]
+use crate::enrich::DomainMetadata;
+#[cfg(feature = "enrich")]
+use crate::error::Error;
+use crate::permutate::{confusable_chars, Confusable, Domain, Permutation, PermutationKind};
+#[cfg(feature = "enrich")]
+use crate::pipeline::Pipeline;
+use crate::scoring::{similarity, CompositeScorer};
+
+/// Confidence given to candidates embedding the base label (e.g.
+/// `example-login.com`) that no permutation kind generates.
+const EMBEDDED_LABEL_CONFIDENCE: f64 = 0.5;
+
+/// Confidence added to candidates that resolve.
+#[cfg(feature = "enrich")]
+const RESOLVING_BOOST: f64 = 0.1;
+
+/// Confidence added to candidates serving HTTP content.
+#[cfg(feature = "enrich")]
+const SERVING_BOOST: f64 = 0.05;
+
+/// Factor applied to the confidence of candidates that do not resolve.
+#[cfg(feature = "enrich")]
+const UNRESOLVED_FACTOR: f64 = 0.5;
+
+/// Evidence backing a verdict.
+#[derive(Clone, Debug, Serialize, Deserialize, PartialEq)]
+pub enum Signal {
+    /// Characters of the candidate confused with those of the base domain.
+    Homoglyph(Vec<Confusable>),
+
+    /// The candidate registers the base label under another suffix.
+    SameLabel,
+
+    /// The candidate embeds the base label, e.g. `example-login.com`.
+    EmbeddedLabel,
+
+    /// The candidate resolves, along with the number of IPs it resolves to.
+    Resolves(usize),
+
+    /// The candidate does not resolve.
+    Unresolved,
+
+    /// The candidate serves HTTP content, along with its server banner.
+    ServesHttp(String),
+
+    /// The candidate shares a nameserver with the base domain, and is
+    /// almost certainly a defensive registration.
+    SharedNameservers,
+}
+
+/// Similarity scores of a candidate against the base domain.
+#[derive(Clone, Copy, Debug, Default, Serialize, Deserialize, PartialEq)]
+pub struct Scores {
+    /// Normalised Levenshtein similarity of the FQDNs, see
+    /// [`similarity`].
+    pub similarity: f64,
+
+    /// Highest [`CompositeScorer`] score across the matched kinds, `0.0`
+    /// if none matched.
+    pub risk: f64,
+}
+
+/// Decision on whether a candidate domain squats a base domain.
+#[derive(Clone, Debug, Serialize, Deserialize, PartialEq)]
+pub struct Verdict {
+    /// Domain the candidate was assessed against.
+    pub base: Domain,
+
+    /// Domain that was assessed.
+    pub candidate: Domain,
+
+    /// Permutation kinds of the base domain generating the candidate.
+    pub kinds: Vec<PermutationKind>,
+
+    pub scores: Scores,
+
+    pub signals: Vec<Signal>,
+
+    /// Confidence that the candidate squats the base domain, between
+    /// `0.0` (unrelated) and `1.0` (certain).
+    pub confidence: f64,
+}
+
+impl Verdict {
+    /// Whether the candidate matches a permutation kind or embeds the
+    /// base label.
+    pub fn is_squat(&self) -> bool {
+        self.confidence > 0.0
+    }
+
+    /// Weigh the enriched `metadata` of the candidate into the verdict.
+    /// Candidates that do not resolve lose half of their confidence,
+    /// likely defensive registrations most of it.
+    ///
+    /// ### Features
+    ///
+    /// This function requires the `enrich` feature toggled.
+    #[cfg(feature = "enrich")]
+    pub fn weigh(&mut self, metadata: &DomainMetadata) {
+        if !self.is_squat() {
+            return;
+        }
+
+        match metadata.dns.ips().as_deref() {
+            Some(ips) if !ips.is_empty() => {
+                self.signals.push(Signal::Resolves(ips.len()));
+                self.confidence += RESOLVING_BOOST;
+            }
+            _ => {
+                self.signals.push(Signal::Unresolved);
+                self.confidence *= UNRESOLVED_FACTOR;
+            }
+        }
+
+        if let Some(banner) = &metadata.http_banner {
+            self.signals.push(Signal::ServesHttp(banner.clone()));
+            self.confidence += SERVING_BOOST;
+        }
+
+        if metadata.shared_nameservers == Some(true) {
+            self.signals.push(Signal::SharedNameservers);
+            self.confidence *= CompositeScorer::new(self.base.clone()).defensive_weight;
+        }
+
+        self.confidence = self.confidence.clamp(0.0, 1.0);
+    }
+}
+
+/// Assess whether `candidate` squats `base`, without any network lookups.
+///
+/// The candidate is classified by the permutation kinds of `base` that
+/// generate it, and its confidence is the highest
+/// [`CompositeScorer::score`] across them. Candidates that no kind
+/// generates but that embed the base label are given a flat confidence,
+/// and the base domain itself is not a squat of itself.
+pub fn assess(base: &Domain, candidate: &Domain) -> Verdict {
+    let mut verdict = Verdict {
+        base: base.clone(),
+        candidate: candidate.clone(),
+        kinds: vec![],
+        scores: Scores {
+            similarity: similarity(&base.fqdn, &candidate.fqdn),
+            risk: 0.0,
+        },
+        signals: vec![],
+        confidence: 0.0,
+    };
+
+    if candidate == base {
+        return verdict;
+    }
+
+    let confusables = confusable_chars(base, candidate);
+    verdict.kinds = classify(base, candidate, !confusables.is_empty());
+
+    if !confusables.is_empty() {
+        verdict.signals.push(Signal::Homoglyph(confusables));
+    }
+
+    let label = base.domain.to_lowercase();
+    if candidate.domain.to_lowercase() == label {
+        verdict.signals.push(Signal::SameLabel);
+    } else if candidate.domain.to_lowercase().contains(&label) {
+        verdict.signals.push(Signal::EmbeddedLabel);
+    }
+
+    let scorer = CompositeScorer::new(base.clone());
+    verdict.scores.risk = verdict
+        .kinds
+        .iter()
+        .map(|kind| {
+            scorer.score(&Permutation {
+                domain: candidate.clone(),
+                kind: *kind,
+                substitutions: vec![],
+            })
+        })
+        .fold(0.0, f64::max);
+
+    verdict.confidence = if verdict.signals.contains(&Signal::EmbeddedLabel) {
+        verdict.scores.risk.max(EMBEDDED_LABEL_CONFIDENCE)
+    } else {
+        verdict.scores.risk
+    };
+
+    verdict
+}
+
+/// Assess whether `candidate` squats `base`, see [`assess`], and weigh in
+/// the metadata `pipeline` enriches the candidate with, see
+/// [`Verdict::weigh`]. Candidates that are not squats are not enriched.
+///
+/// ### Features
+///
+/// This function requires the `enrich` feature toggled.
+#[cfg(feature = "enrich")]
+pub async fn assess_enriched(
+    base: &Domain,
+    candidate: &Domain,
+    pipeline: &Pipeline,
+) -> Result<Verdict, Error> {
+    let mut verdict = assess(base, candidate);
+    if !verdict.is_squat() {
+        return Ok(verdict);
+    }
+
+    let fqdns = stream::iter([Ok(candidate.fqdn.clone())]);
+    let enriched = Box::pin(pipeline.run_fqdns(fqdns)).next().await;
+
+    if let Some(metadata) = enriched {
+        verdict.weigh(&metadata?);
+    }
+
+    Ok(verdict)
+}
+
+/// Permutation kinds of `base` generating `candidate`, given whether it
+/// is a homoglyph of `base`.
+fn classify(base: &Domain, candidate: &Domain, homoglyph: bool) -> Vec<PermutationKind> {
+    PermutationKind::ALL
+        .iter()
+        .copied()
+        .filter(|kind| match kind {
+            // Confusables are compared directly rather than through the
+            // (large) set of homoglyph permutations.
+            PermutationKind::Homoglyph => homoglyph,
+            _ => base.permutations(*kind).is_ok_and(|mut permutations| {
+                permutations.any(|permutation| permutation.domain == *candidate)
+            }),
+        })
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn verdict(candidate: &str) -> Verdict {
+        assess(
+            &Domain::new("example.com").unwrap(),
+            &Domain::new(candidate).unwrap(),
+        )
+    }
+
+    #[test]
+    fn test_assess() {
+        let typo = verdict("exmaple.com");
+        assert_eq!(typo.kinds, vec![PermutationKind::Transposition]);
+        assert!(typo.scores.similarity > 0.8);
+        assert!(typo.is_squat());
+
+        let tld = verdict("example.net");
+        assert!(tld.kinds.contains(&PermutationKind::Tld));
+        assert!(tld.signals.contains(&Signal::SameLabel));
+
+        let homoglyph = verdict("examp1e.com");
+        assert!(homoglyph.kinds.contains(&PermutationKind::Homoglyph));
+        assert!(matches!(homoglyph.signals[0], Signal::Homoglyph(_)));
+        assert!(homoglyph.confidence >= typo.confidence);
+
+        let embedded = verdict("example-secure-login.com");
+        assert!(embedded.kinds.is_empty());
+        assert_eq!(embedded.signals, vec![Signal::EmbeddedLabel]);
+        assert!((embedded.confidence - EMBEDDED_LABEL_CONFIDENCE).abs() < f64::EPSILON);
+
+        assert!(!verdict("example.com").is_squat());
+        assert!(!verdict("EXAMPLE.com").is_squat());
+        assert!(!verdict("unrelated.org").is_squat());
+    }
+
+    #[cfg(feature = "enrich")]
+    #[tokio::test]
+    async fn test_assess_enriched() {
+        use crate::resolver::MockResolver;
+
+        let base = Domain::new("example.com").unwrap();
+        let mut resolver = MockResolver::new();
+        resolver.insert_host("exmaple.com", "127.0.0.1".parse().unwrap());
+        resolver.insert_banner("exmaple.com", "nginx");
+
+        let mut pipeline = Pipeline::new();
+        pipeline.set_resolver(resolver);
+
+        let resolving = Domain::new("exmaple.com").unwrap();
+        let enriched = assess_enriched(&base, &resolving, &pipeline).await.unwrap();
+        assert!(enriched.confidence > assess(&base, &resolving).confidence);
+        assert!(enriched.signals.contains(&Signal::Resolves(1)));
+        assert!(enriched
+            .signals
+            .contains(&Signal::ServesHttp("nginx".to_string())));
+
+        let unresolved = Domain::new("exampel.com").unwrap();
+        let discounted = assess_enriched(&base, &unresolved, &pipeline)
+            .await
+            .unwrap();
+        assert!(discounted.confidence < assess(&base, &unresolved).confidence);
+        assert!(discounted.signals.contains(&Signal::Unresolved));
+    }
+}
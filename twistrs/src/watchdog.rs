@@ -0,0 +1,197 @@
+//! The watchdog module exposes [`Watchdog`], guarding long-running scans
+//! against enrichment tasks that stall, e.g. on a wedged socket.
+//!
+//! Once attached to a [`Pipeline`], the watchdog cancels and restarts
+//! tasks that make no progress within `stall_timeout`, and the pipeline
+//! reports a [`Heartbeat`] to its observers every `heartbeat_interval`,
+//! so that unattended deployments can tell a slow scan from a hung one.
+//!
+//! Example:
+//!
+//! ```
+//! use std::time::Duration;
+//!
+//! use twistrs::pipeline::Pipeline;
+//! use twistrs::watchdog::Watchdog;
+//!
+//! let mut pipeline = Pipeline::new();
+//! pipeline.set_watchdog(Watchdog {
+//!     stall_timeout: Duration::from_secs(20),
+//!     max_restarts: 1,
+//!     ..Watchdog::default()
+//! });
+//! ```
+//!
+//! ### Features
+//!
+//! This module requires the `enrich` feature toggled.
+//!
+//! [`Pipeline`]: crate::pipeline::Pipeline
+
+use std::future::Future;
+use std::sync::atomic::{AtomicUsize, Ordering};
+use std::sync::Arc;
+use std::time::Duration;
+
+use futures::stream::{self, Stream, StreamExt};
+use serde::{Deserialize, Serialize};
+use tokio::time::Instant;
+
+use crate::error::Error;
+
+// `watchdog::Error` would read as the crate `Error` once imported.
+#[allow(clippy::module_name_repetitions)]
+#[derive(thiserror::Error, Debug)]
+pub enum WatchdogError {
+    #[error("enrichment stalled (fqdn: {fqdn}, attempts: {attempts})")]
+    Stalled { fqdn: String, attempts: usize },
+}
+
+/// Stall detection and heartbeat settings of a pipeline.
+#[derive(Clone, Copy, Debug, Serialize, Deserialize, Eq, PartialEq)]
+pub struct Watchdog {
+    /// How long an enrichment task may run before being considered
+    /// stalled and cancelled.
+    pub stall_timeout: Duration,
+
+    /// How many times a stalled task is restarted before giving up on it.
+    pub max_restarts: usize,
+
+    /// Interval between two heartbeats.
+    pub heartbeat_interval: Duration,
+}
+
+impl Default for Watchdog {
+    fn default() -> Watchdog {
+        Watchdog {
+            stall_timeout: Duration::from_secs(60),
+            max_restarts: 2,
+            heartbeat_interval: Duration::from_secs(30),
+        }
+    }
+}
+
+/// Liveness report of a running pipeline.
+#[derive(Clone, Copy, Debug, Default, Serialize, Deserialize, Eq, PartialEq)]
+pub struct Heartbeat {
+    /// Number of results yielded so far.
+    pub completed: usize,
+
+    /// Number of enrichment tasks currently running.
+    pub in_flight: usize,
+
+    /// Number of stalled tasks restarted or given up on so far.
+    pub stalls: usize,
+
+    /// Time since the pipeline started.
+    pub elapsed: Duration,
+}
+
+/// Counters shared between the enrichment tasks of a pipeline run.
+#[derive(Debug, Default)]
+pub(crate) struct Activity {
+    pub(crate) in_flight: AtomicUsize,
+    pub(crate) stalls: AtomicUsize,
+}
+
+impl Watchdog {
+    /// Create a watchdog cancelling tasks stalled for `stall_timeout`,
+    /// with the default restarts and heartbeat interval.
+    pub fn new(stall_timeout: Duration) -> Watchdog {
+        Watchdog {
+            stall_timeout,
+            ..Watchdog::default()
+        }
+    }
+
+    /// Run the future built by `task`, cancelling and rebuilding it every
+    /// time it stalls, up to `max_restarts` times. `on_stall` is invoked
+    /// with the attempt number of every stalled run.
+    pub(crate) async fn supervise<T, F, Fut, S>(
+        &self,
+        fqdn: &str,
+        mut task: F,
+        mut on_stall: S,
+    ) -> Result<T, Error>
+    where
+        F: FnMut() -> Fut,
+        Fut: Future<Output = T>,
+        S: FnMut(usize),
+    {
+        let attempts = self.max_restarts + 1;
+
+        for attempt in 1..=attempts {
+            if let Ok(output) = tokio::time::timeout(self.stall_timeout, task()).await {
+                return Ok(output);
+            }
+
+            on_stall(attempt);
+        }
+
+        Err(WatchdogError::Stalled {
+            fqdn: fqdn.to_string(),
+            attempts,
+        }
+        .into())
+    }
+}
+
+/// Pass `stream` through, invoking `on_beat` every `heartbeat_interval` of
+/// `watchdog` for as long as the stream is being polled. Without a
+/// watchdog the stream is passed through as-is.
+pub(crate) fn heartbeats<'a, S, B>(
+    stream: S,
+    watchdog: Option<Watchdog>,
+    activity: Arc<Activity>,
+    on_beat: B,
+) -> impl Stream<Item = S::Item> + 'a
+where
+    S: Stream + 'a,
+    B: Fn(&Heartbeat) + 'a,
+{
+    let state = Arc::new((activity, on_beat));
+    let interval = watchdog.map(|settings| settings.heartbeat_interval);
+    let start = Instant::now();
+
+    stream::unfold(
+        (Box::pin(stream), start, 0),
+        move |(mut items, mut last, completed)| {
+            let shared = Arc::clone(&state);
+
+            async move {
+                let Some(period) = interval else {
+                    let item = items.next().await?;
+                    return Some((item, (items, last, completed + 1)));
+                };
+
+                let (counters, report) = &*shared;
+                let beat = || {
+                    report(&Heartbeat {
+                        completed,
+                        in_flight: counters.in_flight.load(Ordering::Relaxed),
+                        stalls: counters.stalls.load(Ordering::Relaxed),
+                        elapsed: start.elapsed(),
+                    });
+                };
+
+                loop {
+                    let deadline = last + period;
+
+                    if let Ok(next) = tokio::time::timeout_at(deadline, items.next()).await {
+                        // Items flowing faster than the interval must not
+                        // hold heartbeats back.
+                        if Instant::now() >= deadline {
+                            beat();
+                            last = Instant::now();
+                        }
+
+                        return next.map(|item| (item, (items, last, completed + 1)));
+                    }
+
+                    beat();
+                    last = Instant::now();
+                }
+            }
+        },
+    )
+}
@@ -0,0 +1,783 @@
+//! The whois module exposes [`BulkWhoIs`], a driver enriching thousands
+//! of domains (e.g. the registered permutations of a scan) with `WhoIs`
+//! and RDAP lookups without getting banned by the servers queried.
+//!
+//! Domains under a TLD listed in [`BulkWhoIs::rdap_servers`] are looked
+//! up over RDAP, e.g. through the servers of the IANA bootstrap registry
+//! (see [`fetch_rdap_servers`]), and the others over `WhoIs`.
+//!
+//! Domains are queued per destination, i.e. per `WhoIs` server (see
+//! [`WhoIsOptions::destination_for`]) or RDAP server host. Destinations
+//! are queried concurrently, but each one sequentially with at least
+//! `politeness` between two of its queries. Negative responses, i.e. of
+//! domains that are not registered, are cached for `negative_ttl` in a
+//! [`DomainStore`] so that subsequent runs do not query them again. The
+//! cache lives in memory by default, or outlives the process when backed
+//! by a persistent store, see [`BulkWhoIs::with_store`].
+//!
+//! Example:
+//!
+//! ```no_run
+//! use std::time::Duration;
+//!
+//! use futures::StreamExt;
+//! use twistrs::whois::{self, BulkWhoIs};
+//!
+//! #[tokio::main]
+//! async fn main() {
+//!     let mut bulk = BulkWhoIs::new(Duration::from_secs(2), Duration::from_secs(24 * 60 * 60));
+//!     bulk.rdap_servers = whois::fetch_rdap_servers(whois::RDAP_BOOTSTRAP_URL)
+//!         .await
+//!         .unwrap();
+//!
+//!     let fqdns = vec!["examp1e.com".to_string(), "example.net".to_string()];
+//!
+//!     let mut results = Box::pin(bulk.run(fqdns));
+//!     while let Some(result) = results.next().await {
+//!         println!("{:?}", result);
+//!     }
+//! }
+//! ```
+//!
+//! ### Features
+//!
+//! This module requires the `whois` feature toggled.
+
+use std::collections::{BTreeMap, HashSet};
+use std::fmt;
+use std::sync::{Arc, Mutex, MutexGuard, PoisonError};
+use std::time::Duration;
+
+use futures::stream::{self, Stream};
+use hyper::body::HttpBody;
+use hyper::client::{Client, HttpConnector};
+use hyper::header::ACCEPT;
+use hyper::{Body, Request, StatusCode, Uri};
+use hyper_rustls::HttpsConnector;
+use serde_json::Value;
+use tokio::time::Instant;
+
+use crate::enrich::{
+    DomainMetadata, EnrichmentContext, EnrichmentError, EnrichmentKind, WhoIsMetadata, WhoIsOptions,
+};
+use crate::error::Error;
+use crate::storage::{unix_now, DomainStore, MemoryStore};
+
+/// URL of the IANA bootstrap registry of the RDAP servers of each TLD.
+pub const RDAP_BOOTSTRAP_URL: &str = "https://data.iana.org/rdap/dns.json";
+
+/// Timeout of a single RDAP lookup, as for `WhoIs` ones.
+const RDAP_TIMEOUT: Duration = Duration::from_secs(5);
+
+/// Largest RDAP response read, well above that of a domain object.
+const RDAP_BODY_LIMIT: usize = 256 * 1024;
+
+/// Phrases of `WhoIs` responses stating that the domain is not
+/// registered, lowercase.
+const NEGATIVE_PHRASES: [&str; 11] = [
+    "no match for",
+    "not found",
+    "no data found",
+    "no entries found",
+    "no object found",
+    "no matching record",
+    "object does not exist",
+    "domain not found",
+    "is available for registration",
+    "status: free",
+    "status: available",
+];
+
+#[derive(thiserror::Error, Debug)]
+pub enum WhoIsError {
+    #[error("error performing rdap lookup (domain: {domain}, error: {error})")]
+    RdapError {
+        domain: String,
+        error: anyhow::Error,
+    },
+
+    #[error("error fetching rdap bootstrap registry (url: {url}, error: {error})")]
+    FetchError { url: String, error: anyhow::Error },
+
+    #[error("error parsing rdap bootstrap registry (error: {error})")]
+    BootstrapError { error: anyhow::Error },
+}
+
+/// Driver of bulk `WhoIs` and RDAP lookups, see the [module](self)
+/// documentation.
+///
+/// Clones share the same negative cache.
+pub struct BulkWhoIs<S: DomainStore = MemoryStore> {
+    /// Options of each `WhoIs` lookup, also selecting the server of each
+    /// domain.
+    pub options: WhoIsOptions,
+
+    /// Base URLs of the RDAP servers per TLD (e.g.
+    /// `https://rdap.verisign.com/com/v1/`), keyed without the leading
+    /// dot. The longest matching suffix wins. Domains without a server are
+    /// looked up over `WhoIs`, which is the case of every domain by
+    /// default.
+    pub rdap_servers: BTreeMap<String, String>,
+
+    /// Least delay between two queries to the same destination.
+    pub politeness: Duration,
+
+    /// How long negative responses are cached.
+    pub negative_ttl: Duration,
+
+    context: EnrichmentContext,
+    rdap: Client<HttpsConnector<HttpConnector>>,
+    negatives: Arc<Mutex<S>>,
+}
+
+impl<S: DomainStore> Clone for BulkWhoIs<S> {
+    fn clone(&self) -> BulkWhoIs<S> {
+        BulkWhoIs {
+            options: self.options.clone(),
+            rdap_servers: self.rdap_servers.clone(),
+            politeness: self.politeness,
+            negative_ttl: self.negative_ttl,
+            context: self.context.clone(),
+            rdap: self.rdap.clone(),
+            negatives: Arc::clone(&self.negatives),
+        }
+    }
+}
+
+impl<S: DomainStore> fmt::Debug for BulkWhoIs<S> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("BulkWhoIs")
+            .field("options", &self.options)
+            .field("rdap_servers", &self.rdap_servers)
+            .field("politeness", &self.politeness)
+            .field("negative_ttl", &self.negative_ttl)
+            .finish_non_exhaustive()
+    }
+}
+
+impl Default for BulkWhoIs {
+    fn default() -> BulkWhoIs {
+        BulkWhoIs::new(Duration::from_secs(2), Duration::from_secs(24 * 60 * 60))
+    }
+}
+
+impl BulkWhoIs {
+    /// Create a driver waiting `politeness` between two queries to the
+    /// same destination, and caching negative responses in memory for
+    /// `negative_ttl`.
+    pub fn new(politeness: Duration, negative_ttl: Duration) -> BulkWhoIs {
+        BulkWhoIs::with_store(MemoryStore::new(), politeness, negative_ttl)
+    }
+}
+
+impl<S: DomainStore> BulkWhoIs<S> {
+    /// Create a driver as [`BulkWhoIs::new`] does, caching negative
+    /// responses in `store` instead, e.g. a `SledStore` so that they are
+    /// kept between runs. The store should be dedicated to the driver, as
+    /// it holds a record per domain.
+    pub fn with_store(store: S, politeness: Duration, negative_ttl: Duration) -> BulkWhoIs<S> {
+        let connector = hyper_rustls::HttpsConnectorBuilder::new()
+            .with_webpki_roots()
+            .https_only()
+            .enable_http1()
+            .build();
+
+        BulkWhoIs {
+            options: WhoIsOptions::default(),
+            rdap_servers: BTreeMap::new(),
+            politeness,
+            negative_ttl,
+            context: EnrichmentContext::default(),
+            rdap: Client::builder().build(connector),
+            negatives: Arc::new(Mutex::new(store)),
+        }
+    }
+
+    /// Look domains up through the `WhoIs` client and breaker of
+    /// `context`, see [`EnrichmentContext`]. RDAP lookups go through its
+    /// breaker as well.
+    pub fn set_context(&mut self, context: EnrichmentContext) {
+        self.context = context;
+    }
+
+    /// Store holding the negative responses, including the expired ones.
+    pub fn store(&self) -> MutexGuard<'_, S> {
+        self.negatives
+            .lock()
+            .unwrap_or_else(PoisonError::into_inner)
+    }
+
+    /// RDAP server `fqdn` is looked up through, if any.
+    pub fn rdap_server_for(&self, fqdn: &str) -> Option<&str> {
+        let name = fqdn.trim_end_matches('.').to_lowercase();
+
+        self.rdap_servers
+            .iter()
+            .filter(|(tld, _)| {
+                let suffix = tld.trim_start_matches('.').to_lowercase();
+                name.strip_suffix(&suffix)
+                    .is_some_and(|rest| rest.is_empty() || rest.ends_with('.'))
+            })
+            .max_by_key(|(tld, _)| tld.trim_start_matches('.').len())
+            .map(|(_, server)| server.as_str())
+    }
+
+    /// Destination the lookup of `fqdn` is sent to: the host of its RDAP
+    /// server if any, or else its `WhoIs` destination, see
+    /// [`WhoIsOptions::destination_for`].
+    pub fn destination_for(&self, fqdn: &str) -> String {
+        self.rdap_server_for(fqdn)
+            .and_then(|server| server.parse::<Uri>().ok())
+            .and_then(|uri| uri.host().map(str::to_lowercase))
+            .unwrap_or_else(|| self.options.destination_for(fqdn))
+    }
+
+    /// Distinct `fqdns` queued per destination, in the order they are
+    /// queried.
+    pub fn queues<I, T>(&self, fqdns: I) -> BTreeMap<String, Vec<String>>
+    where
+        I: IntoIterator<Item = T>,
+        T: AsRef<str>,
+    {
+        let mut seen = HashSet::new();
+        let mut queues: BTreeMap<String, Vec<String>> = BTreeMap::new();
+
+        for fqdn in fqdns {
+            let name = fqdn.as_ref().trim_end_matches('.').to_lowercase();
+
+            if seen.insert(name.clone()) {
+                queues
+                    .entry(self.destination_for(&name))
+                    .or_default()
+                    .push(name);
+            }
+        }
+
+        queues
+    }
+
+    /// Cached negative response of `fqdn`, unless it expired.
+    pub fn cached(&self, fqdn: &str) -> Result<Option<WhoIsMetadata>, Error> {
+        cached_in(&*self.store(), fqdn)
+    }
+}
+
+impl<S: DomainStore + Send + 'static> BulkWhoIs<S> {
+    /// Look each of `fqdns` up, yielding their metadata as the lookups
+    /// complete. Domains with a cached negative response are yielded
+    /// straight away, without querying their server.
+    pub fn run<I, T>(&self, fqdns: I) -> impl Stream<Item = Result<DomainMetadata, Error>> + '_
+    where
+        I: IntoIterator<Item = T>,
+        T: AsRef<str>,
+    {
+        stream::select_all(
+            self.queues(fqdns).into_values().map(|queue| {
+                Box::pin(stream::unfold(
+                    (queue.into_iter(), None),
+                    move |(mut pending, last_query): (
+                        std::vec::IntoIter<String>,
+                        Option<Instant>,
+                    )| async move {
+                        let fqdn = pending.next()?;
+
+                        match self.cached_off_workers(&fqdn).await {
+                            Ok(Some(who_is)) => {
+                                let mut metadata = DomainMetadata::new(fqdn);
+                                metadata.who_is = Some(who_is);
+
+                                return Some((Ok(metadata), (pending, last_query)));
+                            }
+                            Ok(None) => {}
+                            Err(e) => return Some((Err(e), (pending, last_query))),
+                        }
+
+                        if let Some(last) = last_query {
+                            tokio::time::sleep_until(last + self.politeness).await;
+                        }
+
+                        let queried_at = Instant::now();
+                        let result = self.lookup(fqdn).await;
+
+                        Some((result, (pending, Some(queried_at))))
+                    },
+                ))
+            }),
+        )
+    }
+
+    /// Look `fqdn` up, caching the response if it is negative.
+    async fn lookup(&self, fqdn: String) -> Result<DomainMetadata, Error> {
+        let (metadata, negative) = if let Some(server) = self.rdap_server_for(&fqdn) {
+            self.rdap_lookup(&fqdn, server).await?
+        } else {
+            let metadata = DomainMetadata::new(fqdn.clone())
+                .whois_lookup_with_context(&self.options, &self.context)
+                .await?;
+            let negative = metadata
+                .who_is
+                .as_ref()
+                .is_some_and(|who_is| is_negative(&who_is.raw));
+
+            (metadata, negative)
+        };
+
+        if let Some(who_is) = metadata.who_is.as_ref().filter(|_| negative) {
+            self.remember(fqdn, who_is.clone()).await?;
+        }
+
+        Ok(metadata)
+    }
+
+    /// Cached negative response of `fqdn` as [`BulkWhoIs::cached`] reads
+    /// it, off the async workers.
+    async fn cached_off_workers(&self, fqdn: &str) -> Result<Option<WhoIsMetadata>, Error> {
+        let name = fqdn.to_string();
+
+        self.with_negatives(fqdn, move |store| cached_in(store, &name))
+            .await
+    }
+
+    /// Cache the negative response `who_is` of `fqdn` for `negative_ttl`.
+    async fn remember(&self, fqdn: String, who_is: WhoIsMetadata) -> Result<(), Error> {
+        let mut metadata = DomainMetadata::new(fqdn.clone());
+        metadata.who_is = Some(who_is);
+        metadata.cached_until = Some(unix_now().saturating_add(self.negative_ttl.as_secs()));
+
+        self.with_negatives(&fqdn, move |store| store.put(metadata).map(|_| ()))
+            .await
+    }
+
+    /// Run `f` on the negative cache within the blocking pool, as
+    /// persistent stores perform disk I/O under its lock.
+    async fn with_negatives<T, F>(&self, fqdn: &str, f: F) -> Result<T, Error>
+    where
+        T: Send + 'static,
+        F: FnOnce(&mut S) -> Result<T, Error> + Send + 'static,
+    {
+        let negatives = Arc::clone(&self.negatives);
+
+        tokio::task::spawn_blocking(move || {
+            f(&mut negatives.lock().unwrap_or_else(PoisonError::into_inner))
+        })
+        .await
+        .map_err(|e| EnrichmentError::WhoIsTaskError {
+            domain: fqdn.to_string(),
+            error: anyhow::Error::msg(e),
+        })?
+    }
+
+    /// Look `fqdn` up through the RDAP server at `server`, returning its
+    /// metadata along with whether it is not registered, i.e. whether the
+    /// server answered `404 Not Found`.
+    async fn rdap_lookup(&self, fqdn: &str, server: &str) -> Result<(DomainMetadata, bool), Error> {
+        let error = |e: anyhow::Error| WhoIsError::RdapError {
+            domain: fqdn.to_string(),
+            error: e,
+        };
+
+        let uri: Uri = format!("{}/domain/{fqdn}", server.trim_end_matches('/'))
+            .parse()
+            .map_err(|e| error(anyhow::Error::msg(e)))?;
+        let request = Request::get(uri)
+            .header(ACCEPT, "application/rdap+json")
+            .body(Body::empty())
+            .map_err(|e| error(anyhow::Error::msg(e)))?;
+
+        let exchange = async {
+            let response = self
+                .rdap
+                .request(request)
+                .await
+                .map_err(|e| error(anyhow::Error::msg(e)))?;
+            let status = response.status();
+            let body = read_capped(response.into_body(), RDAP_BODY_LIMIT)
+                .await
+                .map_err(error)?;
+
+            Ok::<_, Error>((status, String::from_utf8_lossy(&body).into_owned()))
+        };
+
+        // Lookups through the same server share its circuit.
+        let destination = self.destination_for(fqdn);
+        let (status, raw) = match &self.context.breaker {
+            Some(breaker) => {
+                breaker
+                    .guard(EnrichmentKind::WhoIs, &destination, RDAP_TIMEOUT, exchange)
+                    .await?
+            }
+            None => tokio::time::timeout(RDAP_TIMEOUT, exchange)
+                .await
+                .map_err(|_| error(anyhow::anyhow!("timed out after {RDAP_TIMEOUT:?}")))??,
+        };
+
+        let negative = status == StatusCode::NOT_FOUND;
+        if !negative && !status.is_success() {
+            return Err(error(anyhow::anyhow!("unexpected status {status}")).into());
+        }
+
+        let fields = if negative {
+            BTreeMap::new()
+        } else {
+            rdap_fields(&raw).map_err(error)?
+        };
+
+        let mut metadata = DomainMetadata::new(fqdn.to_string());
+        metadata.who_is = Some(WhoIsMetadata {
+            server: Some(server.to_string()),
+            raw,
+            fields,
+        });
+
+        Ok((metadata, negative))
+    }
+}
+
+/// Cached negative response of `fqdn` in `store`, unless it expired.
+fn cached_in<S: DomainStore>(store: &S, fqdn: &str) -> Result<Option<WhoIsMetadata>, Error> {
+    let name = fqdn.trim_end_matches('.').to_lowercase();
+    let now = unix_now();
+
+    Ok(store
+        .get(&name)?
+        .filter(|metadata| metadata.cached_until.is_some_and(|until| until > now))
+        .and_then(|metadata| metadata.who_is))
+}
+
+/// Bytes of `body`, failing once more than `limit` have been read so that
+/// a misbehaving server cannot exhaust the memory.
+async fn read_capped(mut body: Body, limit: usize) -> Result<Vec<u8>, anyhow::Error> {
+    let mut bytes = vec![];
+
+    while let Some(chunk) = body.data().await {
+        bytes.extend_from_slice(&chunk?);
+
+        if bytes.len() > limit {
+            anyhow::bail!("response exceeds {limit} bytes");
+        }
+    }
+
+    Ok(bytes)
+}
+
+/// `WhoIs` fields (e.g. `Registrar` or `Creation Date`) of the RDAP
+/// domain object `raw`, so that responses of both protocols are read
+/// alike.
+fn rdap_fields(raw: &str) -> Result<BTreeMap<String, Vec<String>>, anyhow::Error> {
+    let domain: Value = serde_json::from_str(raw)?;
+    let mut fields: BTreeMap<String, Vec<String>> = BTreeMap::new();
+    let mut add = |key: &str, found: Option<&str>| {
+        if let Some(value) = found {
+            fields
+                .entry(key.to_string())
+                .or_default()
+                .push(value.to_string());
+        }
+    };
+
+    add("Domain Name", domain["ldhName"].as_str());
+
+    for entity in domain["entities"].as_array().into_iter().flatten() {
+        let is_registrar = entity["roles"]
+            .as_array()
+            .into_iter()
+            .flatten()
+            .any(|role| role == "registrar");
+
+        if is_registrar {
+            // jCard properties are `[name, parameters, type, value]`.
+            let name = entity["vcardArray"][1]
+                .as_array()
+                .into_iter()
+                .flatten()
+                .find(|property| property[0] == "fn");
+
+            add("Registrar", name.and_then(|property| property[3].as_str()));
+        }
+    }
+
+    for event in domain["events"].as_array().into_iter().flatten() {
+        let key = match event["eventAction"].as_str() {
+            Some("registration") => "Creation Date",
+            Some("expiration") => "Registry Expiry Date",
+            Some("last changed") => "Updated Date",
+            _ => continue,
+        };
+
+        add(key, event["eventDate"].as_str());
+    }
+
+    for status in domain["status"].as_array().into_iter().flatten() {
+        add("Domain Status", status.as_str());
+    }
+
+    for nameserver in domain["nameservers"].as_array().into_iter().flatten() {
+        add("Name Server", nameserver["ldhName"].as_str());
+    }
+
+    Ok(fields)
+}
+
+/// RDAP servers of each TLD listed in the bootstrap registry `raw` (see
+/// RFC 9224), preferring HTTPS ones, as expected by
+/// [`BulkWhoIs::rdap_servers`].
+pub fn parse_rdap_bootstrap(raw: &str) -> Result<BTreeMap<String, String>, Error> {
+    let error = |e: anyhow::Error| WhoIsError::BootstrapError { error: e };
+    let registry: Value = serde_json::from_str(raw).map_err(|e| error(anyhow::Error::msg(e)))?;
+    let services = registry["services"]
+        .as_array()
+        .ok_or_else(|| error(anyhow::anyhow!("missing services")))?;
+
+    let mut servers = BTreeMap::new();
+
+    for service in services {
+        let urls: Vec<&str> = service[1]
+            .as_array()
+            .into_iter()
+            .flatten()
+            .filter_map(Value::as_str)
+            .collect();
+        let Some(url) = urls
+            .iter()
+            .find(|url| url.starts_with("https://"))
+            .or_else(|| urls.first())
+        else {
+            continue;
+        };
+
+        for tld in service[0]
+            .as_array()
+            .into_iter()
+            .flatten()
+            .filter_map(Value::as_str)
+        {
+            servers.insert(tld.to_lowercase(), (*url).to_string());
+        }
+    }
+
+    Ok(servers)
+}
+
+/// Download the bootstrap registry at `url` (e.g.
+/// [`RDAP_BOOTSTRAP_URL`]), see [`parse_rdap_bootstrap`].
+pub async fn fetch_rdap_servers(url: &str) -> Result<BTreeMap<String, String>, Error> {
+    let fetch_error = |error: anyhow::Error| WhoIsError::FetchError {
+        url: url.to_string(),
+        error,
+    };
+
+    let uri: Uri = url
+        .parse()
+        .map_err(|e| fetch_error(anyhow::Error::msg(e)))?;
+
+    let connector = hyper_rustls::HttpsConnectorBuilder::new()
+        .with_webpki_roots()
+        .https_only()
+        .enable_http1()
+        .build();
+    let client: Client<_, Body> = Client::builder().build(connector);
+
+    let response = client
+        .get(uri)
+        .await
+        .map_err(|e| fetch_error(anyhow::Error::msg(e)))?;
+
+    if !response.status().is_success() {
+        return Err(fetch_error(anyhow::anyhow!("unexpected status {}", response.status())).into());
+    }
+
+    let body = hyper::body::to_bytes(response.into_body())
+        .await
+        .map_err(|e| fetch_error(anyhow::Error::msg(e)))?;
+
+    parse_rdap_bootstrap(&String::from_utf8_lossy(&body))
+}
+
+/// Whether the `WhoIs` response `raw` states that the domain is not
+/// registered.
+pub fn is_negative(raw: &str) -> bool {
+    let lowercase = raw.to_lowercase();
+
+    NEGATIVE_PHRASES
+        .iter()
+        .any(|phrase| lowercase.contains(phrase))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_queues() {
+        let mut bulk = BulkWhoIs::default();
+        bulk.options
+            .tld_servers
+            .insert(String::from("co.uk"), String::from("whois.co.uk"));
+
+        let queues = bulk.queues([
+            "example.com",
+            "example.co.uk",
+            "EXAMPLE.com.",
+            "examp1e.com",
+            "example.net",
+        ]);
+
+        // .com and .net share the same server, and thus queue.
+        assert_eq!(
+            queues,
+            BTreeMap::from([
+                (
+                    String::from("whois.co.uk"),
+                    vec![String::from("example.co.uk")]
+                ),
+                (
+                    String::from("whois.verisign-grs.com"),
+                    vec![
+                        String::from("example.com"),
+                        String::from("examp1e.com"),
+                        String::from("example.net")
+                    ]
+                ),
+            ])
+        );
+
+        // TLDs without a known server fall back to queueing by TLD.
+        assert_eq!(bulk.options.destination_for("example.invalid"), "invalid");
+
+        // Domains with an RDAP server are queued by its host instead.
+        bulk.rdap_servers.insert(
+            String::from("net"),
+            String::from("https://rdap.verisign.com/net/v1/"),
+        );
+        assert_eq!(
+            bulk.rdap_server_for("EXAMPLE.net."),
+            Some("https://rdap.verisign.com/net/v1/")
+        );
+        assert_eq!(
+            bulk.queues(["example.com", "example.net"]),
+            BTreeMap::from([
+                (
+                    String::from("rdap.verisign.com"),
+                    vec![String::from("example.net")]
+                ),
+                (
+                    String::from("whois.verisign-grs.com"),
+                    vec![String::from("example.com")]
+                ),
+            ])
+        );
+    }
+
+    #[tokio::test]
+    async fn test_negative_cache() {
+        let bulk = BulkWhoIs::new(Duration::from_secs(2), Duration::from_secs(60));
+        let negative = WhoIsMetadata {
+            raw: String::from("No match for \"EXAMP1E.COM\"."),
+            ..WhoIsMetadata::default()
+        };
+
+        assert!(is_negative(&negative.raw));
+        assert!(!is_negative("   Domain Name: EXAMPLE.COM\r\n"));
+
+        bulk.remember(String::from("examp1e.com"), negative.clone())
+            .await
+            .unwrap();
+        assert_eq!(bulk.cached("EXAMP1E.com").unwrap(), Some(negative.clone()));
+
+        // Negatives are kept in the store, so that a driver over the same
+        // store (e.g. of the next run) does not query them again.
+        let store = bulk.store().clone();
+        let next = BulkWhoIs::with_store(store, Duration::from_secs(2), Duration::from_secs(60));
+        assert_eq!(next.cached("examp1e.com").unwrap(), Some(negative.clone()));
+
+        let expired = BulkWhoIs::new(Duration::from_secs(2), Duration::ZERO);
+        expired
+            .remember(String::from("examp1e.com"), negative)
+            .await
+            .unwrap();
+        assert_eq!(expired.cached("examp1e.com").unwrap(), None);
+        assert!(expired.store().get("examp1e.com").unwrap().is_some());
+        assert_eq!(
+            expired.cached_off_workers("examp1e.com").await.unwrap(),
+            None
+        );
+    }
+
+    #[tokio::test]
+    async fn test_read_capped() {
+        let body = read_capped(Body::from("{}"), 2).await.unwrap();
+        assert_eq!(body, b"{}");
+
+        assert!(read_capped(Body::from("{ }"), 2).await.is_err());
+    }
+
+    #[test]
+    fn test_rdap_fields() {
+        let raw = r#"{
+            "objectClassName": "domain",
+            "ldhName": "EXAMPLE.COM",
+            "entities": [{
+                "roles": ["registrar"],
+                "vcardArray": ["vcard", [
+                    ["version", {}, "text", "4.0"],
+                    ["fn", {}, "text", "RESERVED-Internet Assigned Numbers Authority"]
+                ]]
+            }],
+            "events": [
+                {"eventAction": "registration", "eventDate": "1995-08-14T04:00:00Z"},
+                {"eventAction": "expiration", "eventDate": "2025-08-13T04:00:00Z"},
+                {"eventAction": "last update of RDAP database", "eventDate": "2024-01-01T00:00:00Z"}
+            ],
+            "status": ["client delete prohibited", "client transfer prohibited"],
+            "nameservers": [{"ldhName": "A.IANA-SERVERS.NET"}, {"ldhName": "B.IANA-SERVERS.NET"}]
+        }"#;
+
+        let fields = rdap_fields(raw).unwrap();
+        assert_eq!(fields["Domain Name"], ["EXAMPLE.COM"]);
+        assert_eq!(
+            fields["Registrar"],
+            ["RESERVED-Internet Assigned Numbers Authority"]
+        );
+        assert_eq!(fields["Creation Date"], ["1995-08-14T04:00:00Z"]);
+        assert_eq!(fields["Registry Expiry Date"], ["2025-08-13T04:00:00Z"]);
+        assert!(!fields.contains_key("Updated Date"));
+        assert_eq!(fields["Domain Status"].len(), 2);
+        assert_eq!(
+            fields["Name Server"],
+            ["A.IANA-SERVERS.NET", "B.IANA-SERVERS.NET"]
+        );
+
+        assert!(rdap_fields("not json").is_err());
+    }
+
+    #[test]
+    fn test_parse_rdap_bootstrap() {
+        let raw = r#"{
+            "version": "1.0",
+            "services": [
+                [["com", "net"], ["http://rdap.example/", "https://rdap.verisign.com/com/v1/"]],
+                [["ORG"], ["https://rdap.publicinterestregistry.org/rdap/"]],
+                [["empty"], []]
+            ]
+        }"#;
+
+        assert_eq!(
+            parse_rdap_bootstrap(raw).unwrap(),
+            BTreeMap::from([
+                (
+                    String::from("com"),
+                    String::from("https://rdap.verisign.com/com/v1/")
+                ),
+                (
+                    String::from("net"),
+                    String::from("https://rdap.verisign.com/com/v1/")
+                ),
+                (
+                    String::from("org"),
+                    String::from("https://rdap.publicinterestregistry.org/rdap/")
+                ),
+            ])
+        );
+        assert!(parse_rdap_bootstrap("{}").is_err());
+    }
+}
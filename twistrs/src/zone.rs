@@ -0,0 +1,220 @@
+//! The zone module exposes offline registration checks, intersecting
+//! permutations with a TLD zone file or a list of newly registered
+//! domains (NRD) instead of resolving them one by one.
+//!
+//! Permutations are indexed by FQDN once, after which the zone is
+//! streamed line by line, so that zones with hundreds of millions of
+//! names can be matched without holding them in memory.
+//!
+//! Example:
+//!
+//! ```
+//! use twistrs::permutate::Domain;
+//! use twistrs::zone::{self, PermutationIndex};
+//!
+//! let domain = Domain::new("example.com").unwrap();
+//! let index = PermutationIndex::new(domain.addition());
+//!
+//! let zone_file = "$ORIGIN com.\nexamplea NS ns1.registrar.net.\nexamplez.com. NS ns2.registrar.net.\n";
+//! let registered = zone::registered(zone_file.as_bytes(), &index).unwrap();
+//!
+//! assert_eq!(registered.len(), 2);
+//! ```
+
+use std::collections::{HashMap, HashSet};
+use std::fs::File;
+use std::io::{BufRead, BufReader};
+use std::path::Path;
+
+use crate::error::Error;
+use crate::permutate::{canonicalize, Permutation};
+
+// Named like the other error types, e.g. `EnrichmentError`.
+#[allow(clippy::module_name_repetitions)]
+#[derive(thiserror::Error, Debug)]
+pub enum ZoneError {
+    #[error("error opening zone file (path: {path}, error: {error})")]
+    OpenError { path: String, error: anyhow::Error },
+
+    #[error("error reading zone file (line: {line}, error: {error})")]
+    ReadError { line: usize, error: anyhow::Error },
+}
+
+/// Permutations indexed by FQDN, to be matched against registered names.
+#[derive(Debug, Clone, Default)]
+pub struct PermutationIndex {
+    permutations: HashMap<String, Vec<Permutation>>,
+}
+
+impl PermutationIndex {
+    /// Index `permutations` by their canonical FQDN, so that they match
+    /// owners regardless of case and of whether IDNs are in their Unicode
+    /// or punycode form, see [`canonicalize`].
+    pub fn new<I>(permutations: I) -> PermutationIndex
+    where
+        I: IntoIterator<Item = Permutation>,
+    {
+        let mut index = PermutationIndex::default();
+
+        for permutation in permutations {
+            index
+                .permutations
+                .entry(canonicalize(&permutation.domain.fqdn))
+                .or_default()
+                .push(permutation);
+        }
+
+        index
+    }
+
+    /// Number of distinct FQDNs indexed.
+    pub fn len(&self) -> usize {
+        self.permutations.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.permutations.is_empty()
+    }
+
+    /// Permutations generating `fqdn`, if any.
+    pub fn get(&self, fqdn: &str) -> Option<&[Permutation]> {
+        self.permutations
+            .get(&canonicalize(fqdn))
+            .map(Vec::as_slice)
+    }
+}
+
+/// Permutations of `index` registered in the zone read from `reader`, in
+/// the order they first appear in the zone.
+///
+/// Both zone files in master file format and plain lists holding one
+/// domain per line (e.g. NRD feeds) are supported. Only owner names are
+/// considered, relative ones being completed with the last `$ORIGIN`
+/// directive, and comments (`;` or `#`) are skipped.
+pub fn registered<R>(reader: R, index: &PermutationIndex) -> Result<Vec<Permutation>, Error>
+where
+    R: BufRead,
+{
+    let mut origin: Option<String> = None;
+    let mut seen: HashSet<String> = HashSet::new();
+    let mut registered = vec![];
+
+    for (i, read) in reader.lines().enumerate() {
+        let line = read.map_err(|e| ZoneError::ReadError {
+            line: i + 1,
+            error: anyhow::Error::msg(e),
+        })?;
+
+        // Lines starting with whitespace continue the previous owner.
+        if line.starts_with(char::is_whitespace) || line.starts_with('#') {
+            continue;
+        }
+
+        let Some(owner) = line
+            .split(';')
+            .next()
+            .and_then(|record| record.split_whitespace().next())
+        else {
+            continue;
+        };
+
+        if owner.eq_ignore_ascii_case("$ORIGIN") {
+            origin = line.split_whitespace().nth(1).map(canonicalize);
+            continue;
+        }
+
+        if owner.starts_with('$') || owner == "@" {
+            continue;
+        }
+
+        let fqdn = match &origin {
+            Some(suffix) if !owner.ends_with('.') => format!("{}.{suffix}", canonicalize(owner)),
+            _ => canonicalize(owner),
+        };
+
+        if let Some(permutations) = index.permutations.get(&fqdn) {
+            if seen.insert(fqdn) {
+                registered.extend(permutations.iter().cloned());
+            }
+        }
+    }
+
+    Ok(registered)
+}
+
+/// Permutations of `index` registered in the zone file at `path`, see
+/// [`registered`].
+pub fn registered_in_file<P>(path: P, index: &PermutationIndex) -> Result<Vec<Permutation>, Error>
+where
+    P: AsRef<Path>,
+{
+    let file = File::open(path.as_ref()).map_err(|e| ZoneError::OpenError {
+        path: path.as_ref().display().to_string(),
+        error: anyhow::Error::msg(e),
+    })?;
+
+    registered(BufReader::new(file), index)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    use crate::permutate::{Domain, PermutationKind};
+
+    #[test]
+    fn test_registered() {
+        let domain = Domain::new("example.com").unwrap();
+        let index = PermutationIndex::new(domain.addition());
+        assert!(index.get("EXAMPLEA.com.").is_some());
+
+        let zone_file = "\
+            ; comment\n\
+            $ORIGIN COM.\n\
+            $TTL 86400\n\
+            @ SOA a.gtld-servers.net. nstld.verisign-grs.com. 1 1800 900 604800 86400\n\
+            EXAMPLEB NS NS1.REGISTRAR.NET.\n\
+            EXAMPLEB NS NS2.REGISTRAR.NET.\n\
+            \tNS NS3.REGISTRAR.NET.\n\
+            UNRELATED NS NS1.REGISTRAR.NET.\n\
+            examplea.com. 3600 IN NS ns1.registrar.net. ; absolute\n";
+
+        let fqdns: Vec<String> = registered(zone_file.as_bytes(), &index)
+            .unwrap()
+            .into_iter()
+            .map(|permutation| permutation.domain.fqdn)
+            .collect();
+        assert_eq!(fqdns, vec!["exampleb.com", "examplea.com"]);
+
+        // Plain lists of domains, e.g. NRD feeds.
+        let nrd = "# newly registered\nexamplec.com\nexample.net\n";
+        assert_eq!(registered(nrd.as_bytes(), &index).unwrap().len(), 1);
+
+        assert!(registered_in_file("/nonexistent/zone", &index).is_err());
+    }
+
+    #[test]
+    fn test_registered_idn() {
+        let homograph = Permutation {
+            domain: Domain::new("exämple.com").unwrap(),
+            kind: PermutationKind::IdnHomograph,
+            substitutions: vec![],
+        };
+        let index = PermutationIndex::new([homograph.clone()]);
+        assert!(index.get("EXÄMPLE.com").is_some());
+        assert!(index.get("xn--exmple-cua.com.").is_some());
+
+        // Zones hold the punycode form of IDNs.
+        let zone_file = "$ORIGIN com.
+XN--EXMPLE-CUA NS ns1.registrar.net.
+";
+        assert_eq!(
+            registered(zone_file.as_bytes(), &index).unwrap(),
+            vec![homograph.clone()]
+        );
+        assert_eq!(
+            registered("xn--exmple-cua.com.\n".as_bytes(), &index).unwrap(),
+            vec![homograph]
+        );
+    }
+}